@@ -37,8 +37,10 @@ static CLI_VERSION: Lazy<String> = Lazy::new(|| {
 });
 
 fn main() -> IoResult<()> {
-  log::init();
   let cli_opt = CliOpt::parse();
+  if let Err(e) = log::init(&cli_opt) {
+    eprintln!("Failed to initialize logging: {e}");
+  }
   trace!("cli_opt: {:?}", cli_opt);
 
   // Print version and exit
@@ -65,12 +67,17 @@ fn main() -> IoResult<()> {
     // Initialize user config.
     event_loop.init_config()?;
 
-    // Initialize terminal.
+    // Initialize command-line histories.
+    event_loop.init_history()?;
+
+    // Initialize terminal, and the system clipboard (OSC 52 needs the terminal).
     event_loop.init_tui()?;
+    event_loop.init_clipboard()?;
 
     // Initialize buffers and windows.
     event_loop.init_buffers()?;
     event_loop.init_windows()?;
+    event_loop.init_autosave()?;
 
     // Finish initialize terminal.
     event_loop.init_tui_done()?;
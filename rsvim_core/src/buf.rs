@@ -2,30 +2,62 @@
 
 use crate::defaults::grapheme::AsciiControlCodeFormatter;
 // use crate::evloop::msg::WorkerToMasterMessage;
-use crate::res::IoResult;
+use crate::buf::truncated::TruncatedLines;
+use crate::buf::windex::BufWindexes;
+use crate::envar;
+use crate::res::{BufferErr, BufferResult, IoErrKind, IoResult};
+use crate::{rlock, wlock};
 
 // Re-export
-pub use crate::buf::opt::{BufferLocalOptions, FileEncoding};
+pub use crate::buf::changelist::ChangeList;
+pub use crate::buf::dir::{DirEntry, DirEntryKind, DirListing};
+pub use crate::buf::highlight::{
+  ColorScheme, ColorSchemes, HighlightSpan, HighlightStyle, Highlights, SyntaxToken,
+};
+pub use crate::buf::journal::JournalSnapshot;
+pub use crate::buf::opt::{AmbiguousWidth, BufType, BufferLocalOptions, FileEncoding};
+pub use crate::buf::sign::{Sign, Signs};
+pub use crate::buf::windex::BufWindex;
 
 use ahash::AHashMap as HashMap;
 use ascii::AsciiChar;
 use compact_str::CompactString;
-use parking_lot::RwLock;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use parking_lot::{Mutex, RwLock};
 use path_absolutize::Absolutize;
+use regex::Regex;
 use ropey::iter::Lines;
 use ropey::{Rope, RopeBuilder, RopeSlice};
 use std::collections::BTreeMap;
 use std::convert::From;
 use std::fs::Metadata;
-use std::io::Read;
+use std::io::{BufRead, Read, Write};
+use std::ops::Range;
 use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicI32, Ordering};
 use std::sync::{Arc, Weak};
 use std::time::Instant;
 use tracing::trace;
+use unicode_segmentation::UnicodeSegmentation;
 use unicode_width::UnicodeWidthChar;
 
+pub mod changelist;
+pub mod dir;
+pub mod highlight;
+pub mod journal;
 pub mod opt;
+pub mod sign;
+pub mod truncated;
+pub mod windex;
+pub mod word;
+
+/// The magic header bytes of gzip-compressed file.
+const GZIP_MAGIC_HEADER: [u8; 2] = [0x1f, 0x8b];
+
+/// The chunk size when reading file.
+const READ_CHUNK_SIZE: usize = 64 * 1024;
 
 /// Buffer ID.
 pub type BufferId = i32;
@@ -48,6 +80,16 @@ pub fn next_buffer_id() -> BufferId {
 //  CHANGED, // Buffer content has been modified.
 //}
 
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+/// The kind of the buffer.
+pub enum BufferKind {
+  /// The normal buffer, i.e. a file or an unnamed buffer.
+  #[default]
+  File,
+  /// The directory buffer, it lists the directory entries and cannot be modified.
+  Directory,
+}
+
 #[derive(Debug)]
 /// The Vim buffer, it is the in-memory texts mapping to the filesystem.
 ///
@@ -65,8 +107,30 @@ pub struct Buffer {
   options: BufferLocalOptions,
   filename: Option<PathBuf>,
   absolute_filename: Option<PathBuf>,
+  // The display name of the buffer without file name, i.e. the scratch buffer.
+  name: Option<String>,
   metadata: Option<Metadata>,
   last_sync_time: Option<Instant>,
+  compressed: bool,
+  listed: bool,
+  large: bool,
+  highlights: Highlights,
+  // The signs of the lines, rendered in the 'signcolumn'.
+  signs: Signs,
+  changed_tick: usize,
+  // The changed tick when the buffer is loaded or last written.
+  saved_tick: usize,
+  line_changes: Vec<LineChange>,
+  change_list: ChangeList,
+  kind: BufferKind,
+  dir_listing: Option<DirListing>,
+  external_change: ExternalChange,
+  // The width indexes of the long lines, they're built lazily by the renders.
+  windexes: Mutex<BufWindexes>,
+  // The truncated long lines, they're materialized lazily by the word-break renders.
+  truncated_lines: Mutex<TruncatedLines>,
+  // The journal found when the file is opened, it's newer than the file.
+  recovery_journal: Option<PathBuf>,
   // worker_send_to_master: Sender<WorkerToMasterMessage>,
 }
 
@@ -90,8 +154,24 @@ impl Buffer {
       options,
       filename,
       absolute_filename,
+      name: None,
       metadata,
       last_sync_time,
+      compressed: false,
+      listed: true,
+      large: false,
+      highlights: Highlights::new(),
+      signs: Signs::new(),
+      changed_tick: 0,
+      saved_tick: 0,
+      line_changes: vec![],
+      change_list: ChangeList::new(),
+      kind: BufferKind::File,
+      dir_listing: None,
+      external_change: ExternalChange::Unchanged,
+      windexes: Mutex::new(BufWindexes::new()),
+      truncated_lines: Mutex::new(TruncatedLines::default()),
+      recovery_journal: None,
     }
   }
 
@@ -104,8 +184,24 @@ impl Buffer {
       options,
       filename: None,
       absolute_filename: None,
+      name: None,
       metadata: None,
       last_sync_time: None,
+      compressed: false,
+      listed: true,
+      large: false,
+      highlights: Highlights::new(),
+      signs: Signs::new(),
+      changed_tick: 0,
+      saved_tick: 0,
+      line_changes: vec![],
+      change_list: ChangeList::new(),
+      kind: BufferKind::File,
+      dir_listing: None,
+      external_change: ExternalChange::Unchanged,
+      windexes: Mutex::new(BufWindexes::new()),
+      truncated_lines: Mutex::new(TruncatedLines::default()),
+      recovery_journal: None,
     }
   }
 
@@ -133,6 +229,28 @@ impl Buffer {
     self.absolute_filename = absolute_filename;
   }
 
+  /// The display name of the buffer without file name, i.e. the scratch buffer created by
+  /// [`new_scratch_buffer`](BuffersManager::new_scratch_buffer).
+  pub fn name(&self) -> &Option<String> {
+    &self.name
+  }
+
+  pub fn set_name(&mut self, name: Option<String>) {
+    self.name = name;
+  }
+
+  /// Get the name to display, i.e. the file name, or the display name if the buffer has no file
+  /// name, or `[No Name]`.
+  pub fn display_name(&self) -> String {
+    self
+      .filename
+      .as_ref()
+      .and_then(|filename| filename.file_name())
+      .map(|name| name.to_string_lossy().to_string())
+      .or_else(|| self.name.clone())
+      .unwrap_or_else(|| "[No Name]".to_string())
+  }
+
   pub fn metadata(&self) -> &Option<Metadata> {
     &self.metadata
   }
@@ -149,6 +267,53 @@ impl Buffer {
     self.last_sync_time = last_sync_time;
   }
 
+  /// Whether the file is gzip-compressed on filesystem, i.e. the buffer contents are decompressed
+  /// when reading and should be re-compressed when saving.
+  pub fn compressed(&self) -> bool {
+    self.compressed
+  }
+
+  pub fn set_compressed(&mut self, compressed: bool) {
+    self.compressed = compressed;
+  }
+
+  /// Whether the buffer is listed, i.e. shows in the buffer list and can be navigated by
+  /// `:bnext`/`:bprev`. Special buffers such as help are unlisted.
+  /// See: <https://vimhelp.org/options.txt.html#%27buflisted%27>.
+  pub fn listed(&self) -> bool {
+    self.listed
+  }
+
+  pub fn set_listed(&mut self, listed: bool) {
+    self.listed = listed;
+  }
+
+  /// Whether the buffer is opened from a large file, i.e. the file size exceeds the
+  /// [`large_file_threshold`](crate::envar::global_config::GlobalConfig::large_file_threshold).
+  ///
+  /// The features that cost extra memory or time on every line are disabled for a large buffer,
+  /// i.e. the syntax tokens, the cached width indexes and the recovery journal.
+  pub fn is_large(&self) -> bool {
+    self.large
+  }
+
+  pub fn set_large(&mut self, large: bool) {
+    self.large = large;
+    self.highlights.set_syntax_enabled(!large);
+  }
+
+  /// The kind of the buffer.
+  pub fn kind(&self) -> BufferKind {
+    self.kind
+  }
+
+  /// The recovery journal found when the file is opened, i.e. the editor crashed before the buffer
+  /// was written. The caller should prompt to recover it with
+  /// [`recover_buffer`](BuffersManager::recover_buffer).
+  pub fn recovery_journal(&self) -> &Option<PathBuf> {
+    &self.recovery_journal
+  }
+
   // pub fn status(&self) -> BufferStatus {
   //   BufferStatus::INIT
   // }
@@ -166,6 +331,8 @@ impl Buffer {
   /// [Unicode Standard Annex #11](https://www.unicode.org/reports/tr11/), implemented with
   /// [UnicodeWidthChar], there's another equivalent crate
   /// [icu::properties::EastAsianWidth](https://docs.rs/icu/latest/icu/properties/maps/fn.east_asian_width.html#).
+  ///
+  /// The ambiguous width characters (i.e. `§`) occupy 1 or 2 cells by the 'ambiwidth' option.
   pub fn char_width(&self, c: char) -> usize {
     if c.is_ascii_control() {
       let ac = AsciiChar::from_ascii(c).unwrap();
@@ -178,7 +345,10 @@ impl Buffer {
         }
       }
     } else {
-      UnicodeWidthChar::width_cjk(c).unwrap()
+      match self.ambiguous_width() {
+        AmbiguousWidth::Single => UnicodeWidthChar::width(c).unwrap(),
+        AmbiguousWidth::Double => UnicodeWidthChar::width_cjk(c).unwrap(),
+      }
     }
   }
 
@@ -240,11 +410,180 @@ impl Buffer {
     self.rope.lines()
   }
 
+  /// Same with [`Rope::get_slice`](Rope::get_slice), get the chars in `char_range`.
+  pub fn get_slice(&self, char_range: Range<usize>) -> Option<RopeSlice> {
+    self.rope.get_slice(char_range)
+  }
+
   /// Same with [`Rope::len_lines`](Rope::len_lines).
+  ///
+  /// NOTE: If the buffer ends with a line break, the last line is an empty line after it, i.e.
+  /// `"a\nb\n"` has 3 lines.
   pub fn len_lines(&self) -> usize {
     self.rope.len_lines()
   }
 
+  /// Whether the last line ends with a line break (`\n`, `\r\n` or `\r`). An empty buffer has no
+  /// last line, thus it returns `false`.
+  pub fn last_line_has_eol(&self) -> bool {
+    let len_chars = self.rope.len_chars();
+    len_chars > 0 && matches!(self.rope.char(len_chars - 1), '\n' | '\r')
+  }
+
+  /// Get the index of the last line. Unlike [`len_lines`](Buffer::len_lines), the empty line after
+  /// the trailing line break is not a line, i.e. both `"a\nb\n"` and `"a\nb"` have the last line
+  /// `1`. An empty buffer has the last line `0`.
+  pub fn last_line_idx(&self) -> usize {
+    if self.last_line_has_eol() {
+      self.rope.len_lines() - 2
+    } else {
+      self.rope.len_lines().saturating_sub(1)
+    }
+  }
+
+  /// Get the `[noeol]` indicator for the status line, if the last line doesn't end with a line
+  /// break, same with Vim.
+  pub fn noeol_indicator(&self) -> Option<&'static str> {
+    if self.rope.len_chars() > 0 && !self.last_line_has_eol() {
+      Some("[noeol]")
+    } else {
+      None
+    }
+  }
+
+  /// Get the terminal title of the buffer, i.e. the [`display_name`](Buffer::display_name),
+  /// followed by `+` if the buffer is modified, e.g. `file.txt + — rsvim`.
+  /// See: <https://vimhelp.org/options.txt.html#%27title%27>.
+  pub fn title(&self) -> String {
+    let name = self.display_name();
+    let modified = if self.is_modified() { " +" } else { "" };
+    format!("{name}{modified} — rsvim")
+  }
+
+  /// Get the `[RO]` indicator for the status line, if the buffer is `readonly`, same with Vim.
+  pub fn readonly_indicator(&self) -> Option<&'static str> {
+    if self.options.readonly() {
+      Some("[RO]")
+    } else {
+      None
+    }
+  }
+
+  /// Get the `[large]` indicator for the status line, if the buffer is opened from a large file.
+  pub fn large_indicator(&self) -> Option<&'static str> {
+    if self.large {
+      Some("[large]")
+    } else {
+      None
+    }
+  }
+
+  /// Same with [`Rope::line_to_char`](Rope::line_to_char), get the char index where the line
+  /// starts. The `line_idx` can be one-past-the-end, i.e. [`len_lines`](Buffer::len_lines), it
+  /// returns [`len_chars`](Buffer::len_chars).
+  ///
+  /// # Panics
+  ///
+  /// If `line_idx` is out of bounds, i.e. `line_idx > len_lines()`.
+  pub fn line_to_char(&self, line_idx: usize) -> usize {
+    self.rope.line_to_char(line_idx)
+  }
+
+  /// Same with [`Rope::char_to_line`](Rope::char_to_line), get the line index of the char. The
+  /// `char_idx` can be one-past-the-end, i.e. [`len_chars`](Buffer::len_chars), it returns the
+  /// last line.
+  ///
+  /// # Panics
+  ///
+  /// If `char_idx` is out of bounds, i.e. `char_idx > len_chars()`.
+  pub fn char_to_line(&self, char_idx: usize) -> usize {
+    self.rope.char_to_line(char_idx)
+  }
+
+  /// Convert the char index (based on the whole buffer) to the `(line, col)` position, the `col`
+  /// is the char index inside the line.
+  ///
+  /// - The chars of the line break map to the end of the line, i.e. the `col` is
+  ///   [`line_len_chars`](Buffer::line_len_chars). Thus the `\n` of `\r\n` maps to the same
+  ///   position with `\r`.
+  /// - The one-past-the-end char index, i.e. [`len_chars`](Buffer::len_chars), maps to the end of
+  ///   the last line. If the buffer ends with a line break, it's the start of the empty line after
+  ///   it, same with [`Rope::char_to_line`](Rope::char_to_line).
+  ///
+  /// It returns `None` if `char_idx > len_chars()`.
+  pub fn char_to_line_col(&self, char_idx: usize) -> Option<(usize, usize)> {
+    if char_idx > self.rope.len_chars() {
+      return None;
+    }
+    let line_idx = self.rope.char_to_line(char_idx);
+    let col = char_idx - self.rope.line_to_char(line_idx);
+    Some((line_idx, std::cmp::min(col, self.line_len_chars(line_idx))))
+  }
+
+  /// Convert the `(line, col)` position to the char index (based on the whole buffer), the reverse
+  /// of [`char_to_line_col`](Buffer::char_to_line_col).
+  ///
+  /// - The `col` can be [`line_len_chars`](Buffer::line_len_chars), i.e. the end of the line, it
+  ///   maps to the line break, or the one-past-the-end char index for the last line without line
+  ///   break.
+  /// - The `line` can be the empty line after the trailing line break, i.e. `len_lines() - 1`,
+  ///   only the `col` 0 is valid in it, which is the one-past-the-end char index.
+  ///
+  /// It returns `None` if `line >= len_lines()` or `col > line_len_chars(line)`, see
+  /// [`line_col_to_char_clamped`](Buffer::line_col_to_char_clamped) for the clamped one.
+  pub fn line_col_to_char(&self, line: usize, col: usize) -> Option<usize> {
+    if line >= self.rope.len_lines() || col > self.line_len_chars(line) {
+      return None;
+    }
+    Some(self.rope.line_to_char(line) + col)
+  }
+
+  /// Same with [`line_col_to_char`](Buffer::line_col_to_char), but the out of bounds `line` is
+  /// clamped to the last line (i.e. `len_lines() - 1`), and the out of bounds `col` is clamped to
+  /// the end of the line.
+  pub fn line_col_to_char_clamped(&self, line: usize, col: usize) -> usize {
+    let line = std::cmp::min(line, self.rope.len_lines() - 1);
+    let col = std::cmp::min(col, self.line_len_chars(line));
+    self.rope.line_to_char(line) + col
+  }
+
+  /// Get the chars count of the line, excluding the trailing line break (`\n`, `\r\n` or `\r`).
+  ///
+  /// It returns `0` if `line_idx` is out of bounds.
+  pub fn line_len_chars(&self, line_idx: usize) -> usize {
+    match self.rope.get_line(line_idx) {
+      Some(line) => {
+        let mut len = line.len_chars();
+        while len > 0 && matches!(line.char(len - 1), '\n' | '\r') {
+          len -= 1;
+        }
+        len
+      }
+      None => 0,
+    }
+  }
+
+  /// Get the char index of the first non-blank (i.e. not space or tab) char of the line, or the
+  /// last char if the line is blank, like the `^` motion.
+  ///
+  /// It returns `0` if the line is empty or `line_idx` is out of bounds.
+  pub fn first_non_blank_char(&self, line_idx: usize) -> usize {
+    let line_len = self.line_len_chars(line_idx);
+    match self.rope.get_line(line_idx) {
+      Some(line) => line
+        .chars()
+        .take(line_len)
+        .position(|c| c != ' ' && c != '\t')
+        .unwrap_or(line_len.saturating_sub(1)),
+      None => 0,
+    }
+  }
+
+  /// Same with [`Rope::len_chars`](Rope::len_chars).
+  pub fn len_chars(&self) -> usize {
+    self.rope.len_chars()
+  }
+
   // lines }
 
   /// Alias to method [`Rope::write_to`](Rope::write_to).
@@ -252,184 +591,1232 @@ impl Buffer {
     self.rope.write_to(writer)
   }
 
-  /// Alias to method [`Rope::append`](Rope::append).
-  pub fn append(&mut self, other: Rope) {
-    self.rope.append(other)
+  /// Same with [`write_to`](Buffer::write_to), but compresses the contents with gzip if the buffer
+  /// is [`compressed`](Buffer::compressed).
+  ///
+  /// # Errors
+  ///
+  /// It returns [`BufferErr::NotWritable`] if the buffer is `nofile` or `nowrite` (see
+  /// 'buftype'), [`BufferErr::ReadOnly`] if the buffer is `readonly`.
+  pub fn save_to<T: std::io::Write>(&self, writer: T) -> BufferResult<()> {
+    self.force_save_to(writer, false)
   }
-}
-// Rope }
 
-// Options {
-impl Buffer {
-  pub fn options(&self) -> &BufferLocalOptions {
-    &self.options
+  /// Same with [`save_to`](Buffer::save_to), but the `readonly` buffer is also saved if `force`,
+  /// i.e. `:write!`. The `nofile` and `nowrite` buffers are never saved.
+  pub fn force_save_to<T: std::io::Write>(&self, writer: T, force: bool) -> BufferResult<()> {
+    if !self.buf_type().writable() {
+      trace!("Failed to save {} buffer {:?}", self.buf_type(), self.id);
+      return Err(BufferErr::NotWritable);
+    }
+    if self.options.readonly() && !force {
+      trace!("Failed to save readonly buffer {:?}", self.id);
+      return Err(BufferErr::ReadOnly);
+    }
+    if self.compressed {
+      let mut encoder = GzEncoder::new(writer, Compression::default());
+      self.rope.write_to(&mut encoder)?;
+      encoder.finish()?;
+      Ok(())
+    } else {
+      Ok(self.rope.write_to(writer)?)
+    }
   }
 
-  pub fn set_options(&mut self, options: &BufferLocalOptions) {
-    self.options = options.clone();
+  /// Alias to method [`Rope::append`](Rope::append).
+  ///
+  /// # Errors
+  ///
+  /// It returns [`BufferErr::NotModifiable`] if the buffer is not `modifiable`, and the buffer
+  /// contents are unchanged.
+  pub fn append(&mut self, other: Rope) -> BufferResult<()> {
+    self.modifiable_guard()?;
+    let (line_idx, line_char_idx) = self.char_to_line_col(self.rope.len_chars()).unwrap();
+    let len_lines = self.rope.len_lines();
+    self.rope.append(other);
+    self.changed_tick += 1;
+    self.record_line_change(line_idx, 0, len_lines);
+    self.change_list.push(line_idx, line_char_idx);
+    Ok(())
   }
 
-  pub fn tab_stop(&self) -> u16 {
-    self.options.tab_stop()
+  /// The change counter, it increases on each text change by [`insert`](Buffer::insert),
+  /// [`remove`](Buffer::remove) and [`replace`](Buffer::replace), similar to Vim's
+  /// `b:changedtick`.
+  /// See: <https://vimhelp.org/eval.txt.html#b%3Achangedtick>.
+  pub fn changed_tick(&self) -> usize {
+    self.changed_tick
   }
 
-  pub fn set_tab_stop(&mut self, value: u16) {
-    self.options.set_tab_stop(value);
+  /// Whether the buffer text is changed since it's loaded or last written, i.e. the 'modified'
+  /// option. The directory buffer and the buffer cannot be written (see 'buftype') are never
+  /// modified.
+  /// See: <https://vimhelp.org/options.txt.html#%27modified%27>.
+  pub fn is_modified(&self) -> bool {
+    self.kind == BufferKind::File
+      && self.buf_type().writable()
+      && self.changed_tick != self.saved_tick
   }
-}
-// Options }
 
-impl PartialEq for Buffer {
-  fn eq(&self, other: &Self) -> bool {
-    self.id == other.id
+  /// Mark the buffer as not modified, i.e. it's written to file.
+  pub fn set_unmodified(&mut self) {
+    self.saved_tick = self.changed_tick;
   }
 }
+// Rope }
 
-impl Eq for Buffer {}
-
-#[derive(Debug, Clone)]
-/// The manager for all normal (file) buffers.
-///
-/// NOTE: A buffer has its unique filepath (on filesystem), and there is at most 1 unnamed buffer.
-pub struct BuffersManager {
-  // Buffers collection
-  buffers: BTreeMap<BufferId, BufferArc>,
-
-  // Buffers maps by absolute file path.
-  buffers_by_path: HashMap<Option<PathBuf>, BufferArc>,
-
-  // Local options for buffers.
-  local_options: BufferLocalOptions,
+// Edit {
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+/// The lines changed by an edit, i.e. [`insert`](Buffer::insert), [`remove`](Buffer::remove) or
+/// [`replace`](Buffer::replace). The `removed` lines after `line_idx` are joined into it, then the
+/// `inserted` lines are split from it. The edits inside a line are not recorded.
+pub struct LineChange {
+  pub line_idx: usize,
+  pub removed: usize,
+  pub inserted: usize,
 }
 
-impl BuffersManager {
-  pub fn new() -> Self {
-    BuffersManager {
-      buffers: BTreeMap::new(),
-      buffers_by_path: HashMap::new(),
-      local_options: BufferLocalOptions::default(),
+impl LineChange {
+  /// Get the line index after the change, for the line at `line_idx` before the change. The
+  /// lines removed by the change are moved to the changed line.
+  pub fn adjust(&self, line_idx: usize) -> usize {
+    if line_idx <= self.line_idx {
+      line_idx
+    } else if line_idx <= self.line_idx + self.removed {
+      self.line_idx
+    } else {
+      line_idx - self.removed + self.inserted
     }
   }
+}
 
-  pub fn to_arc(b: BuffersManager) -> BuffersManagerArc {
-    Arc::new(RwLock::new(b))
+impl Buffer {
+  fn modifiable_guard(&self) -> BufferResult<()> {
+    if self.options.modifiable() && self.kind != BufferKind::Directory {
+      Ok(())
+    } else {
+      trace!("Failed to edit unmodifiable buffer {:?}", self.id);
+      Err(BufferErr::NotModifiable)
+    }
   }
 
-  /// Open a file with a newly created buffer.
-  ///
-  /// The file name must be unique and not existed, there are two use cases:
-  /// 1. If the file exists on filesystem, the buffer will read the file contents into buffer.
-  /// 2. If the file doesn't exist, the buffer will be empty but only set the file name.
+  /// Insert `text` at `char_idx`, similar to [`Rope::insert`](Rope::insert).
   ///
-  /// # Returns
+  /// # Errors
   ///
-  /// It returns the buffer ID if the buffer created successfully, also the reading operations must
-  /// be successful if the file exists on filesystem.
-  /// Otherwise it returns the error.
+  /// It returns [`BufferErr::NotModifiable`] if the buffer is not `modifiable`, and the buffer
+  /// contents are unchanged.
   ///
   /// # Panics
   ///
-  /// If the file name already exists.
+  /// If `char_idx` is out of bounds.
+  pub fn insert(&mut self, char_idx: usize, text: &str) -> BufferResult<()> {
+    self.modifiable_guard()?;
+    let (line_idx, line_char_idx) = self.char_to_line_col(char_idx).unwrap();
+    let len_lines = self.rope.len_lines();
+    self.rope.insert(char_idx, text);
+    self.changed_tick += 1;
+    self.record_line_change(line_idx, 0, len_lines);
+    self.change_list.push(line_idx, line_char_idx);
+    Ok(())
+  }
+
+  /// Remove the chars in `char_range`, similar to [`Rope::remove`](Rope::remove).
   ///
-  /// NOTE: This is a primitive API.
-  pub fn new_file_buffer(&mut self, filename: &Path) -> IoResult<BufferId> {
-    let abs_filename = match filename.absolutize() {
-      Ok(abs_filename) => abs_filename.to_path_buf(),
-      Err(e) => {
-        trace!("Failed to absolutize filepath {:?}:{:?}", filename, e);
-        return Err(e);
-      }
-    };
+  /// # Errors
+  ///
+  /// It returns [`BufferErr::NotModifiable`] if the buffer is not `modifiable`, and the buffer
+  /// contents are unchanged.
+  ///
+  /// # Panics
+  ///
+  /// If `char_range` is out of bounds.
+  pub fn remove(&mut self, char_range: Range<usize>) -> BufferResult<()> {
+    self.modifiable_guard()?;
+    let (line_idx, line_char_idx) = self.char_to_line_col(char_range.start).unwrap();
+    let removed = self.rope.char_to_line(char_range.end) - line_idx;
+    let len_lines = self.rope.len_lines();
+    self.rope.remove(char_range);
+    self.changed_tick += 1;
+    self.record_line_change(line_idx, removed, len_lines);
+    self.change_list.push(line_idx, line_char_idx);
+    Ok(())
+  }
 
-    assert!(!self
-      .buffers_by_path
-      .contains_key(&Some(abs_filename.clone())));
+  /// Replace the chars in `char_range` with `text`.
+  ///
+  /// # Errors
+  ///
+  /// It returns [`BufferErr::NotModifiable`] if the buffer is not `modifiable`, and the buffer
+  /// contents are unchanged.
+  ///
+  /// # Panics
+  ///
+  /// If `char_range` is out of bounds.
+  pub fn replace(&mut self, char_range: Range<usize>, text: &str) -> BufferResult<()> {
+    self.modifiable_guard()?;
+    let start_char_idx = char_range.start;
+    let (line_idx, line_char_idx) = self.char_to_line_col(start_char_idx).unwrap();
+    let removed = self.rope.char_to_line(char_range.end) - line_idx;
+    let len_lines = self.rope.len_lines();
+    self.rope.remove(char_range);
+    self.rope.insert(start_char_idx, text);
+    self.changed_tick += 1;
+    self.record_line_change(line_idx, removed, len_lines);
+    self.change_list.push(line_idx, line_char_idx);
+    Ok(())
+  }
 
-    let existed = match std::fs::exists(abs_filename.clone()) {
-      Ok(existed) => existed,
-      Err(e) => {
-        trace!("Failed to detect file {:?}:{:?}", filename, e);
-        return Err(e);
-      }
+  // Record the lines changed by an edit, `len_lines` is the lines count before the edit. The
+  // change list and the width indexes follow the lines changes.
+  fn record_line_change(&mut self, line_idx: usize, removed: usize, len_lines: usize) {
+    let inserted = (self.rope.len_lines() + removed).saturating_sub(len_lines);
+    let line_change = LineChange {
+      line_idx,
+      removed,
+      inserted,
     };
+    self.invalidate_windexes(&line_change);
+    if removed > 0 || inserted > 0 {
+      self.change_list.adjust(&line_change);
+      self.signs.adjust(&line_change);
+      self.line_changes.push(line_change);
+    }
+  }
 
-    let buf = if existed {
-      match self.edit_file(filename, &abs_filename) {
-        Ok(buf) => buf,
-        Err(e) => {
-          return Err(e);
-        }
-      }
+  /// Make sure the `line_idx` is a line, i.e. end the last line with a line break if `line_idx` is
+  /// the empty line after it, e.g. the cursor moves to the next line after inserting a line break
+  /// at the end of the last line. Same with Vim's 'fixendofline'.
+  ///
+  /// # Errors
+  ///
+  /// It returns [`BufferErr::NotModifiable`] if the buffer is not `modifiable`.
+  pub fn ensure_line(&mut self, line_idx: usize) -> BufferResult<()> {
+    if line_idx > self.last_line_idx() && line_idx + 1 == self.rope.len_lines() {
+      self.insert(self.rope.len_chars(), "\n")?;
+    }
+    Ok(())
+  }
+
+  // Make sure the `line_idx` is a line, or one-past-the-last line if `end`.
+  fn line_guard(&self, line_idx: usize, end: bool) -> BufferResult<()> {
+    let len_lines = self.last_line_idx() + 1;
+    if line_idx < len_lines || (end && line_idx == len_lines) {
+      Ok(())
     } else {
-      Buffer::_new(
-        Rope::new(),
-        self.local_options().clone(),
-        Some(filename.to_path_buf()),
-        Some(abs_filename.clone()),
-        None,
-        None,
-      )
-    };
+      Err(BufferErr::InvalidLine(line_idx))
+    }
+  }
 
-    let buf_id = buf.id();
-    let buf = Buffer::to_arc(buf);
-    self.buffers.insert(buf_id, buf.clone());
-    self.buffers_by_path.insert(Some(abs_filename), buf);
-    Ok(buf_id)
+  /// Replace the text of the line, excluding the trailing line break.
+  ///
+  /// # Errors
+  ///
+  /// It returns [`BufferErr::InvalidLine`] if `line_idx` is after the
+  /// [`last_line_idx`](Buffer::last_line_idx), or [`BufferErr::NotModifiable`] if the buffer is not
+  /// `modifiable`, and the buffer contents are unchanged.
+  pub fn set_line(&mut self, line_idx: usize, text: &str) -> BufferResult<()> {
+    self.line_guard(line_idx, false)?;
+    let start_char_idx = self.rope.line_to_char(line_idx);
+    let end_char_idx = start_char_idx + self.line_len_chars(line_idx);
+    self.replace(start_char_idx..end_char_idx, text)
   }
 
-  /// Create new empty buffer without file name.
+  /// Replace the chars from `start_char_idx` to `end_char_idx` (both are char indexes inside the
+  /// line, excluding the trailing line break) in the line with `text`, with a single edit, i.e.
+  /// `r` and replace mode overwrite the chars under the cursor.
   ///
-  /// The file name of this buffer is empty, i.e. the buffer is unnamed.
+  /// Returns the replaced text.
   ///
-  /// # Returns
+  /// # Errors
+  ///
+  /// It returns [`BufferErr::InvalidLine`] if `line_idx` is after the
+  /// [`last_line_idx`](Buffer::last_line_idx), [`BufferErr::InvalidColumn`] if the char range is
+  /// backwards or its end is after the end of line, or [`BufferErr::NotModifiable`] if the buffer
+  /// is not `modifiable`, and the buffer contents are unchanged.
+  pub fn replace_range(
+    &mut self,
+    line_idx: usize,
+    start_char_idx: usize,
+    end_char_idx: usize,
+    text: &str,
+  ) -> BufferResult<CompactString> {
+    self.line_guard(line_idx, false)?;
+    if end_char_idx > self.line_len_chars(line_idx) {
+      return Err(BufferErr::InvalidColumn(end_char_idx));
+    }
+    if start_char_idx > end_char_idx {
+      return Err(BufferErr::InvalidColumn(start_char_idx));
+    }
+    let line_start = self.rope.line_to_char(line_idx);
+    let char_range = line_start + start_char_idx..line_start + end_char_idx;
+    let replaced: CompactString = self.rope.slice(char_range.clone()).chars().collect();
+    self.replace(char_range, text)?;
+    Ok(replaced)
+  }
+
+  /// Insert the lines after the first `after` lines, i.e. `0` inserts at the top, same with Vim's
+  /// `appendbufline()`. The lines are inserted with a single edit.
   ///
-  /// It returns the buffer ID if there is no other unnamed buffers.
+  /// # Errors
   ///
-  /// # Panics
+  /// It returns [`BufferErr::InvalidLine`] if `after` is after the lines count, or
+  /// [`BufferErr::NotModifiable`] if the buffer is not `modifiable`, and the buffer contents are
+  /// unchanged.
+  pub fn append_lines(&mut self, after: usize, lines: &[String]) -> BufferResult<()> {
+    self.line_guard(after, true)?;
+    if lines.is_empty() {
+      return Ok(());
+    }
+    if after <= self.last_line_idx() {
+      let text: String = lines.iter().map(|line| format!("{line}\n")).collect();
+      self.insert(self.rope.line_to_char(after), &text)
+    } else if self.last_line_has_eol() {
+      let text: String = lines.iter().map(|line| format!("{line}\n")).collect();
+      self.insert(self.rope.len_chars(), &text)
+    } else {
+      // The last line doesn't end with a line break, so do the appended lines.
+      let text: String = lines.iter().map(|line| format!("\n{line}")).collect();
+      self.insert(self.rope.len_chars(), &text)
+    }
+  }
+
+  /// Delete the lines in `line_range`, with a single edit.
   ///
-  /// If there is already other unnamed buffers.
+  /// # Errors
   ///
-  /// NOTE: This is a primitive API.
-  pub fn new_empty_buffer(&mut self) -> BufferId {
-    assert!(!self.buffers_by_path.contains_key(&None));
+  /// It returns [`BufferErr::InvalidLine`] if `line_range` is backwards or its end is after the
+  /// lines count, or [`BufferErr::NotModifiable`] if the buffer is not `modifiable`, and the buffer
+  /// contents are unchanged.
+  pub fn delete_lines(&mut self, line_range: Range<usize>) -> BufferResult<()> {
+    self.line_guard(line_range.end, true)?;
+    if line_range.start > line_range.end {
+      return Err(BufferErr::InvalidLine(line_range.start));
+    }
+    if line_range.is_empty() {
+      return Ok(());
+    }
+    let char_range = if line_range.end <= self.last_line_idx() {
+      self.rope.line_to_char(line_range.start)..self.rope.line_to_char(line_range.end)
+    } else if line_range.start > 0 && !self.last_line_has_eol() {
+      // Delete the line break before the deleted last lines, the previous line becomes the last.
+      let prev_line_idx = line_range.start - 1;
+      let start_char_idx =
+        self.rope.line_to_char(prev_line_idx) + self.line_len_chars(prev_line_idx);
+      start_char_idx..self.rope.len_chars()
+    } else {
+      self.rope.line_to_char(line_range.start)..self.rope.len_chars()
+    };
+    self.remove(char_range)
+  }
 
-    let buf = Buffer::_new(
-      Rope::new(),
-      self.local_options().clone(),
-      None,
-      None,
-      None,
-      None,
-    );
-    let buf_id = buf.id();
-    let buf = Buffer::to_arc(buf);
-    self.buffers.insert(buf_id, buf.clone());
-    self.buffers_by_path.insert(None, buf);
-    buf_id
+  /// Take the lines changed by the edits since last taken, in the order of edits. The positions
+  /// saved outside of the buffer (i.e. the jump list) follow the edits with them.
+  pub fn take_line_changes(&mut self) -> Vec<LineChange> {
+    std::mem::take(&mut self.line_changes)
   }
-}
 
-// Primitive APIs {
+  /// The change list, i.e. the positions of the recent edits, see [`ChangeList`].
+  pub fn change_list(&self) -> &ChangeList {
+    &self.change_list
+  }
 
-impl BuffersManager {
-  fn to_rope(&self, buf: &[u8], bufsize: usize) -> Rope {
-    let bufstr = self.to_str(buf, bufsize);
-    let mut block = RopeBuilder::new();
-    block.append(&bufstr.to_owned());
-    block.finish()
+  pub fn change_list_mut(&mut self) -> &mut ChangeList {
+    &mut self.change_list
   }
+}
+// Edit }
 
-  fn to_str(&self, buf: &[u8], bufsize: usize) -> String {
-    let fencoding = self.local_options().file_encoding();
-    match fencoding {
-      FileEncoding::Utf8 => String::from_utf8_lossy(&buf[0..bufsize]).into_owned(),
-    }
+// Directory {
+impl Buffer {
+  /// The directory listing of the directory buffer.
+  pub fn dir_listing(&self) -> Option<&DirListing> {
+    self.dir_listing.as_ref()
   }
 
-  // Implementation for [new_buffer_edit_file](new_buffer_edit_file).
-  fn edit_file(&self, filename: &Path, absolute_filename: &Path) -> IoResult<Buffer> {
-    match std::fs::File::open(filename) {
+  /// Set the directory listing, the buffer becomes a directory buffer and its contents are the
+  /// listed entries, one per line.
+  pub fn set_dir_listing(&mut self, listing: DirListing) {
+    let len_lines = self.rope.len_lines();
+    self.rope = Rope::from_str(&listing.text());
+    self.kind = BufferKind::Directory;
+    self.dir_listing = Some(listing);
+    self.changed_tick += 1;
+    self.record_line_change(0, len_lines - 1, len_lines);
+  }
+
+  /// Re-read the directory of the directory buffer, i.e. after the 'showhidden' option changed.
+  ///
+  /// # Errors
+  ///
+  /// If the directory cannot be read, and the buffer contents are unchanged.
+  pub fn refresh_dir_listing(&mut self) -> IoResult<()> {
+    let path = match &self.dir_listing {
+      Some(listing) => listing.path().to_path_buf(),
+      None => return Ok(()),
+    };
+    let listing = DirListing::read(&path, self.options.show_hidden())?;
+    self.set_dir_listing(listing);
+    Ok(())
+  }
+}
+// Directory }
+
+// External change {
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+/// The change of the file outside the editor, since the buffer is loaded or last written.
+/// See: <https://vimhelp.org/message.txt.html#W11>.
+pub enum ExternalChange {
+  #[default]
+  Unchanged,
+  /// The file's modified time or size changed, i.e. another program wrote it.
+  Modified,
+  /// The file is deleted.
+  Deleted,
+}
+
+impl Buffer {
+  /// The external change detected by the last
+  /// [`check_external_change`](Buffer::check_external_change).
+  pub fn external_change(&self) -> ExternalChange {
+    self.external_change
+  }
+
+  /// Compare the metadata of the file with the one when the buffer is loaded or last written, and
+  /// remember the result. The buffer without file (or the file didn't exist when loaded) and the
+  /// directory buffer are never changed.
+  pub fn check_external_change(&mut self) -> ExternalChange {
+    let (filename, metadata) = match (&self.absolute_filename, &self.metadata) {
+      (Some(filename), Some(metadata)) if self.kind == BufferKind::File => (filename, metadata),
+      _ => return self.external_change,
+    };
+    self.external_change = match std::fs::metadata(filename) {
+      Ok(current) => {
+        if current.len() != metadata.len() || current.modified().ok() != metadata.modified().ok() {
+          ExternalChange::Modified
+        } else {
+          ExternalChange::Unchanged
+        }
+      }
+      Err(e) if e.kind() == IoErrKind::NotFound => ExternalChange::Deleted,
+      Err(e) => {
+        trace!("Failed to fetch metadata from file {:?}:{:?}", filename, e);
+        self.external_change
+      }
+    };
+    self.external_change
+  }
+
+  // The buffer is in sync with the file, i.e. it's written or reloaded.
+  fn mark_synced(&mut self, metadata: Metadata) {
+    self.metadata = Some(metadata);
+    self.last_sync_time = Some(Instant::now());
+    self.external_change = ExternalChange::Unchanged;
+    self.set_unmodified();
+  }
+
+  // Replace the contents with the `recovered` contents from the journal, the buffer is modified
+  // since it's different from the file.
+  fn recover_from(&mut self, recovered: Rope) {
+    let len_lines = self.rope.len_lines();
+    self.rope = recovered;
+    self.changed_tick += 1;
+    self.record_line_change(0, len_lines - 1, len_lines);
+    self.recovery_journal = None;
+  }
+
+  // Replace the contents with the `reloaded` buffer of the same file.
+  fn reload_from(&mut self, reloaded: Buffer) {
+    let len_lines = self.rope.len_lines();
+    self.rope = reloaded.rope;
+    self.compressed = reloaded.compressed;
+    self.set_large(reloaded.large);
+    self.changed_tick += 1;
+    self.record_line_change(0, len_lines - 1, len_lines);
+    if let Some(metadata) = reloaded.metadata {
+      self.mark_synced(metadata);
+    }
+  }
+}
+// External change }
+
+// Format {
+
+/// The text width used by [`Buffer::reflow`] when the 'textwidth' option is `0`.
+/// See: <https://vimhelp.org/change.txt.html#gq>.
+pub const REFLOW_TEXT_WIDTH: u16 = 79;
+
+impl Buffer {
+  /// Reflow the lines in `line_range`, i.e. rewrap each paragraph (separated by blank lines) to
+  /// fit in the 'textwidth' display columns, or [`REFLOW_TEXT_WIDTH`] if it is `0`. Also known as
+  /// the `gq` operator.
+  ///
+  /// The lines are only broken at word boundaries, and between wide chars (e.g. CJK), a word
+  /// wider than the 'textwidth' is put on its own line. The indentation of the first line of each
+  /// paragraph is preserved on all the rewrapped lines.
+  ///
+  /// The lines are replaced with a single edit.
+  ///
+  /// # Returns
+  ///
+  /// It returns the line range of the reflowed lines.
+  ///
+  /// # Errors
+  ///
+  /// It returns [`BufferErr::NotModifiable`] if the buffer is not `modifiable`, and the buffer
+  /// contents are unchanged.
+  pub fn reflow(&mut self, line_range: Range<usize>) -> BufferResult<Range<usize>> {
+    let end_line_idx = std::cmp::min(line_range.end, self.len_lines());
+    if line_range.start >= end_line_idx {
+      return Ok(line_range.start..line_range.start);
+    }
+    let text_width = match self.text_width() {
+      0 => REFLOW_TEXT_WIDTH,
+      w => w,
+    } as usize;
+
+    let start_char_idx = self.line_to_char(line_range.start);
+    let end_char_idx = self.line_to_char(end_line_idx);
+    let text = self.rope.slice(start_char_idx..end_char_idx).to_string();
+
+    let mut reflowed: Vec<String> = vec![];
+    let mut paragraph: Vec<&str> = vec![];
+    for line in text.lines() {
+      if line.trim().is_empty() {
+        self.reflow_paragraph(&paragraph, text_width, &mut reflowed);
+        paragraph.clear();
+        reflowed.push(line.to_string());
+      } else {
+        paragraph.push(line);
+      }
+    }
+    self.reflow_paragraph(&paragraph, text_width, &mut reflowed);
+
+    let mut reflowed_text = reflowed.join("\n");
+    if text.ends_with('\n') {
+      reflowed_text.push('\n');
+    }
+    self.replace(start_char_idx..end_char_idx, &reflowed_text)?;
+    Ok(line_range.start..line_range.start + reflowed.len())
+  }
+
+  // Rewrap the `paragraph` lines into `reflowed`.
+  fn reflow_paragraph(&self, paragraph: &[&str], text_width: usize, reflowed: &mut Vec<String>) {
+    if paragraph.is_empty() {
+      return;
+    }
+    let first_line = paragraph[0];
+    let indent = &first_line[..first_line.len() - first_line.trim_start().len()];
+    let indent_width = self.str_width(indent);
+
+    // The unbreakable units, each unit is `(text, display width, whether there's a space before)`.
+    let mut units: Vec<(String, usize, bool)> = vec![];
+    let mut space_before = false;
+    let mut last_wide = false;
+    for line in paragraph.iter() {
+      for wd in line.trim().split_word_bounds() {
+        if wd.trim().is_empty() {
+          space_before = true;
+          continue;
+        }
+        let wd_width = self.str_width(wd);
+        let wide = wd.chars().all(|c| self.char_width(c) > 1);
+        match units.last_mut() {
+          Some(last) if !space_before && !wide && !last_wide => {
+            last.0.push_str(wd);
+            last.1 += wd_width;
+          }
+          _ => units.push((wd.to_string(), wd_width, space_before)),
+        }
+        space_before = false;
+        last_wide = wide;
+      }
+      // Line break is a space between the words.
+      space_before = true;
+    }
+
+    let mut line = String::from(indent);
+    let mut line_width = indent_width;
+    let mut line_empty = true;
+    for (unit, unit_width, space_before) in units.into_iter() {
+      let sep_width = if space_before && !line_empty { 1 } else { 0 };
+      if !line_empty && line_width + sep_width + unit_width > text_width {
+        reflowed.push(line);
+        line = String::from(indent);
+        line_width = indent_width;
+        line_empty = true;
+      }
+      if space_before && !line_empty {
+        line.push(' ');
+        line_width += 1;
+      }
+      line.push_str(&unit);
+      line_width += unit_width;
+      line_empty = false;
+    }
+    reflowed.push(line);
+  }
+
+  /// Join the `count` lines after `start_line` into it, i.e. Vim's `J` (`count` is `1`) and `gJ`
+  /// if `preserve_whitespace`.
+  ///
+  /// The line breaks are removed, and the leading whitespaces of the joined lines are replaced
+  /// with one space. No space is inserted if the text before ends with a whitespace or is empty,
+  /// or the joined line is empty or starts with `)`. With `preserve_whitespace`, only the line
+  /// breaks are removed.
+  ///
+  /// The lines are replaced with a single edit. The `count` is clamped to the last line.
+  ///
+  /// # Returns
+  ///
+  /// It returns the cursor position `(line_idx, char_idx)` at the last join point, i.e. the
+  /// inserted space, or the first char of the last joined line. If there's no line after
+  /// `start_line`, the buffer is unchanged and it returns the last char of `start_line`.
+  ///
+  /// # Errors
+  ///
+  /// It returns [`BufferErr::NotModifiable`] if the buffer is not `modifiable`, and the buffer
+  /// contents are unchanged.
+  ///
+  /// See: <https://vimhelp.org/change.txt.html#J>.
+  pub fn join_lines(
+    &mut self,
+    start_line: usize,
+    count: usize,
+    preserve_whitespace: bool,
+  ) -> BufferResult<(usize, usize)> {
+    let end_line = std::cmp::min(start_line + count, self.last_line_idx());
+    if start_line >= end_line {
+      let line_len = self.line_len_chars(start_line);
+      return Ok((start_line, line_len.saturating_sub(1)));
+    }
+
+    let line_text = |line_idx: usize| -> String {
+      self
+        .rope
+        .line(line_idx)
+        .chars()
+        .take(self.line_len_chars(line_idx))
+        .collect()
+    };
+    let mut joined = line_text(start_line);
+    let mut joined_len = joined.chars().count();
+    let mut join_char_idx = joined_len;
+    for line_idx in start_line + 1..=end_line {
+      let text = line_text(line_idx);
+      let text = if preserve_whitespace {
+        text.as_str()
+      } else {
+        text.trim_start_matches([' ', '\t'])
+      };
+      join_char_idx = joined_len;
+      let insert_space = !preserve_whitespace
+        && !text.is_empty()
+        && !text.starts_with(')')
+        && !joined.is_empty()
+        && !joined.ends_with([' ', '\t']);
+      if insert_space {
+        joined.push(' ');
+        joined_len += 1;
+      }
+      joined.push_str(text);
+      joined_len += text.chars().count();
+    }
+
+    let start_char_idx = self.rope.line_to_char(start_line);
+    let end_char_idx = self.rope.line_to_char(end_line) + self.line_len_chars(end_line);
+    self.replace(start_char_idx..end_char_idx, &joined)?;
+    let join_char_idx = std::cmp::min(join_char_idx, joined_len.saturating_sub(1));
+    Ok((start_line, join_char_idx))
+  }
+}
+// Format }
+
+// Indent {
+impl Buffer {
+  /// The leading whitespaces (spaces and tabs) of the line, i.e. its indent.
+  pub fn line_indent(&self, line_idx: usize) -> String {
+    match self.get_line(line_idx) {
+      Some(line) => line
+        .chars()
+        .take_while(|c| *c == ' ' || *c == '\t')
+        .collect(),
+      None => String::new(),
+    }
+  }
+
+  /// The display width of the `indent`, a tab stops at the next multiple of 'tabstop'.
+  pub fn indent_width(&self, indent: &str) -> usize {
+    let tab_stop = std::cmp::max(self.tab_stop(), 1) as usize;
+    indent.chars().fold(0, |width, c| match c {
+      '\t' => (width / tab_stop + 1) * tab_stop,
+      _ => width + 1,
+    })
+  }
+
+  /// Make an indent with display `width`, it is all spaces if 'expandtab' is set, otherwise it
+  /// uses as many tabs as possible.
+  pub fn make_indent(&self, width: usize) -> String {
+    let tab_stop = std::cmp::max(self.tab_stop(), 1) as usize;
+    if self.expand_tab() {
+      " ".repeat(width)
+    } else {
+      format!(
+        "{}{}",
+        "\t".repeat(width / tab_stop),
+        " ".repeat(width % tab_stop)
+      )
+    }
+  }
+
+  /// The width of an indent step, i.e. the 'shiftwidth' option, or 'tabstop' if it is `0`.
+  pub fn indent_step(&self) -> usize {
+    match self.shift_width() {
+      0 => self.tab_stop() as usize,
+      shift_width => shift_width as usize,
+    }
+  }
+}
+// Indent }
+
+// Search {
+impl Buffer {
+  /// Search the pattern forward from the next char of `char_idx` (based on the whole buffer), it
+  /// wraps around the end of buffer, i.e. 'wrapscan'. The pattern matches inside a line, the empty
+  /// matches are ignored.
+  ///
+  /// The search checks the `deadline` on each line, so it can be cancelled on large buffer, i.e.
+  /// the incremental search while typing.
+  ///
+  /// # Returns
+  ///
+  /// It returns the char range (based on the whole buffer) of the first match, or `None` if there
+  /// is no match or the deadline is reached.
+  pub fn search(
+    &self,
+    pattern: &Regex,
+    char_idx: usize,
+    deadline: Option<Instant>,
+  ) -> Option<Range<usize>> {
+    let len_lines = self.len_lines();
+    let start_line_idx = self.char_to_line(std::cmp::min(char_idx, self.len_chars()));
+    let start_char_idx = char_idx - self.line_to_char(start_line_idx);
+
+    // The start line is searched twice, first for the chars after `char_idx`, then for the chars
+    // before it after wrapping around.
+    for i in 0..=len_lines {
+      if let Some(deadline) = deadline {
+        if Instant::now() >= deadline {
+          trace!("Search pattern {:?} timeout at line {}", pattern, i);
+          return None;
+        }
+      }
+      let line_idx = (start_line_idx + i) % len_lines;
+      let line = self.rope.line(line_idx);
+      let text = line.to_string();
+      let found = pattern
+        .find_iter(&text)
+        .filter(|m| !m.is_empty())
+        .map(|m| line.byte_to_char(m.start())..line.byte_to_char(m.end()))
+        .find(|r| {
+          if i == 0 {
+            r.start > start_char_idx
+          } else if i == len_lines {
+            r.start <= start_char_idx
+          } else {
+            true
+          }
+        });
+      if let Some(r) = found {
+        let line_start = self.line_to_char(line_idx);
+        return Some(line_start + r.start..line_start + r.end);
+      }
+    }
+    None
+  }
+
+  /// Same with [`search`](Buffer::search), but it searches backward from the previous char of
+  /// `char_idx`, and wraps around the start of buffer, i.e. `?` and `N`.
+  pub fn search_backward(
+    &self,
+    pattern: &Regex,
+    char_idx: usize,
+    deadline: Option<Instant>,
+  ) -> Option<Range<usize>> {
+    let len_lines = self.len_lines();
+    let start_line_idx = self.char_to_line(std::cmp::min(char_idx, self.len_chars()));
+    let start_char_idx = char_idx - self.line_to_char(start_line_idx);
+
+    // The start line is searched twice, first for the chars before `char_idx`, then for the chars
+    // after it after wrapping around.
+    for i in 0..=len_lines {
+      if let Some(deadline) = deadline {
+        if Instant::now() >= deadline {
+          trace!("Search pattern {:?} timeout at line {}", pattern, i);
+          return None;
+        }
+      }
+      let line_idx = (start_line_idx + len_lines - i % len_lines) % len_lines;
+      let line = self.rope.line(line_idx);
+      let text = line.to_string();
+      let found = pattern
+        .find_iter(&text)
+        .filter(|m| !m.is_empty())
+        .map(|m| line.byte_to_char(m.start())..line.byte_to_char(m.end()))
+        .filter(|r| {
+          if i == 0 {
+            r.start < start_char_idx
+          } else if i == len_lines {
+            r.start >= start_char_idx
+          } else {
+            true
+          }
+        })
+        .last();
+      if let Some(r) = found {
+        let line_start = self.line_to_char(line_idx);
+        return Some(line_start + r.start..line_start + r.end);
+      }
+    }
+    None
+  }
+}
+// Search }
+
+// Highlight {
+impl Buffer {
+  /// Get the highlight spans.
+  pub fn highlights(&self) -> &Highlights {
+    &self.highlights
+  }
+
+  /// Get the mutable highlight spans.
+  pub fn highlights_mut(&mut self) -> &mut Highlights {
+    &mut self.highlights
+  }
+}
+// Highlight }
+
+// Sign {
+impl Buffer {
+  /// Get the signs, see [`Signs`].
+  pub fn signs(&self) -> &Signs {
+    &self.signs
+  }
+
+  /// Place the sign on the line, it's rendered in the 'signcolumn'. It replaces the sign of the
+  /// same group on the line, see [`Signs::place`].
+  ///
+  /// # Errors
+  ///
+  /// It returns [`BufferErr::InvalidLine`] if `line_idx` is after the
+  /// [`last_line_idx`](Buffer::last_line_idx).
+  pub fn place_sign(&mut self, line_idx: usize, sign: Sign) -> BufferResult<()> {
+    self.line_guard(line_idx, false)?;
+    self.signs.place(line_idx, sign);
+    Ok(())
+  }
+
+  /// Remove the sign of the group on the line.
+  pub fn unplace_sign(&mut self, line_idx: usize, group: &str) -> Option<Sign> {
+    self.signs.unplace(line_idx, group)
+  }
+}
+// Sign }
+
+// Options {
+impl Buffer {
+  pub fn options(&self) -> &BufferLocalOptions {
+    &self.options
+  }
+
+  pub fn set_options(&mut self, options: &BufferLocalOptions) {
+    self.options = options.clone();
+    self.clear_windexes();
+  }
+
+  pub fn tab_stop(&self) -> u16 {
+    self.options.tab_stop()
+  }
+
+  pub fn set_tab_stop(&mut self, value: u16) {
+    self.options.set_tab_stop(value);
+    self.clear_windexes();
+  }
+
+  pub fn ambiguous_width(&self) -> AmbiguousWidth {
+    self.options.ambiguous_width()
+  }
+
+  pub fn set_ambiguous_width(&mut self, value: AmbiguousWidth) {
+    self.options.set_ambiguous_width(value);
+    self.clear_windexes();
+  }
+
+  pub fn modifiable(&self) -> bool {
+    self.options.modifiable()
+  }
+
+  pub fn set_modifiable(&mut self, value: bool) {
+    self.options.set_modifiable(value);
+  }
+
+  pub fn readonly(&self) -> bool {
+    self.options.readonly()
+  }
+
+  pub fn set_readonly(&mut self, value: bool) {
+    self.options.set_readonly(value);
+  }
+
+  /// The 'buftype' option.
+  pub fn buf_type(&self) -> BufType {
+    self.options.buf_type()
+  }
+
+  pub fn set_buf_type(&mut self, value: BufType) {
+    self.options.set_buf_type(value);
+  }
+
+  pub fn text_width(&self) -> u16 {
+    self.options.text_width()
+  }
+
+  pub fn set_text_width(&mut self, value: u16) {
+    self.options.set_text_width(value);
+  }
+
+  pub fn auto_indent(&self) -> bool {
+    self.options.auto_indent()
+  }
+
+  pub fn set_auto_indent(&mut self, value: bool) {
+    self.options.set_auto_indent(value);
+  }
+
+  pub fn expand_tab(&self) -> bool {
+    self.options.expand_tab()
+  }
+
+  pub fn set_expand_tab(&mut self, value: bool) {
+    self.options.set_expand_tab(value);
+  }
+
+  pub fn shift_width(&self) -> u16 {
+    self.options.shift_width()
+  }
+
+  pub fn set_shift_width(&mut self, value: u16) {
+    self.options.set_shift_width(value);
+  }
+
+  pub fn backup(&self) -> bool {
+    self.options.backup()
+  }
+
+  pub fn set_backup(&mut self, value: bool) {
+    self.options.set_backup(value);
+  }
+
+  pub fn show_hidden(&self) -> bool {
+    self.options.show_hidden()
+  }
+
+  pub fn set_show_hidden(&mut self, value: bool) {
+    self.options.set_show_hidden(value);
+  }
+}
+// Options }
+
+impl PartialEq for Buffer {
+  fn eq(&self, other: &Self) -> bool {
+    self.id == other.id
+  }
+}
+
+impl Eq for Buffer {}
+
+#[derive(Debug, Clone)]
+/// The manager for all normal (file) buffers.
+///
+/// NOTE: A named buffer has its unique filepath (on filesystem), while there can be multiple
+/// unnamed and scratch buffers, they're only identified by their IDs.
+pub struct BuffersManager {
+  // Buffers collection
+  buffers: BTreeMap<BufferId, BufferArc>,
+
+  // Buffers maps by absolute file path, the buffers without file name (i.e. the unnamed and
+  // scratch buffers) are only in the collection keyed by ID.
+  buffers_by_path: HashMap<PathBuf, BufferArc>,
+
+  // Local options for buffers.
+  local_options: BufferLocalOptions,
+
+  // The directory of the recovery journals.
+  journal_dir: PathBuf,
+
+  // The file size threshold of the large buffers.
+  large_file_threshold: u64,
+}
+
+impl BuffersManager {
+  pub fn new() -> Self {
+    BuffersManager {
+      buffers: BTreeMap::new(),
+      buffers_by_path: HashMap::new(),
+      local_options: BufferLocalOptions::default(),
+      journal_dir: rlock!(envar::GLOBAL_CONFIG()).swap_dir().to_path_buf(),
+      large_file_threshold: rlock!(envar::GLOBAL_CONFIG()).large_file_threshold(),
+    }
+  }
+
+  /// The file size threshold of the large buffers, by default is the
+  /// [`large_file_threshold`](crate::envar::global_config::GlobalConfig::large_file_threshold).
+  /// See [`Buffer::is_large`].
+  pub fn large_file_threshold(&self) -> u64 {
+    self.large_file_threshold
+  }
+
+  pub fn set_large_file_threshold(&mut self, value: u64) {
+    self.large_file_threshold = value;
+  }
+
+  pub fn to_arc(b: BuffersManager) -> BuffersManagerArc {
+    Arc::new(RwLock::new(b))
+  }
+
+  /// Open a file with a newly created buffer.
+  ///
+  /// The file name must be unique and not existed, there are two use cases:
+  /// 1. If the file exists on filesystem, the buffer will read the file contents into buffer.
+  /// 2. If the file doesn't exist, the buffer will be empty but only set the file name.
+  ///
+  /// # Returns
+  ///
+  /// It returns the buffer ID if the buffer created successfully, also the reading operations must
+  /// be successful if the file exists on filesystem.
+  /// Otherwise it returns the error.
+  ///
+  /// If there's a journal newer than the file, i.e. the editor crashed before the buffer was
+  /// written, it's set to the buffer's [`recovery_journal`](Buffer::recovery_journal).
+  ///
+  /// # Panics
+  ///
+  /// If the file name already exists.
+  ///
+  /// NOTE: This is a primitive API.
+  pub fn new_file_buffer(&mut self, filename: &Path) -> IoResult<BufferId> {
+    let abs_filename = match filename.absolutize() {
+      Ok(abs_filename) => abs_filename.to_path_buf(),
+      Err(e) => {
+        trace!("Failed to absolutize filepath {:?}:{:?}", filename, e);
+        return Err(e);
+      }
+    };
+
+    assert!(!self.buffers_by_path.contains_key(&abs_filename));
+
+    let existed = match std::fs::exists(abs_filename.clone()) {
+      Ok(existed) => existed,
+      Err(e) => {
+        trace!("Failed to detect file {:?}:{:?}", filename, e);
+        return Err(e);
+      }
+    };
+
+    let mut buf = if existed && abs_filename.is_dir() {
+      self.edit_directory(filename, &abs_filename)?
+    } else if existed {
+      match self.edit_file(filename, &abs_filename, self.large_file_threshold) {
+        Ok(buf) => buf,
+        Err(e) => {
+          return Err(e);
+        }
+      }
+    } else {
+      Buffer::_new(
+        Rope::new(),
+        self.local_options().clone(),
+        Some(filename.to_path_buf()),
+        Some(abs_filename.clone()),
+        None,
+        None,
+      )
+    };
+    if buf.kind() == BufferKind::File {
+      buf.recovery_journal = self.find_recovery_journal(&buf, &abs_filename);
+    }
+
+    let buf_id = buf.id();
+    let buf = Buffer::to_arc(buf);
+    self.buffers.insert(buf_id, buf.clone());
+    self.buffers_by_path.insert(abs_filename, buf);
+    Ok(buf_id)
+  }
+
+  /// Get the buffer of the file, the file name is absolutized to match the buffers, same with
+  /// [`new_file_buffer`](BuffersManager::new_file_buffer).
+  pub fn find_file_buffer(&self, filename: &Path) -> Option<BufferId> {
+    let abs_filename = filename.absolutize().ok()?.to_path_buf();
+    self
+      .buffers_by_path
+      .get(&abs_filename)
+      .map(|buf| rlock!(buf).id())
+  }
+
+  /// Create new empty buffer without file name.
+  ///
+  /// The file name of this buffer is empty, i.e. the buffer is unnamed. There can be multiple
+  /// unnamed buffers.
+  ///
+  /// # Returns
+  ///
+  /// It returns the buffer ID.
+  ///
+  /// NOTE: This is a primitive API.
+  pub fn new_empty_buffer(&mut self) -> BufferId {
+    let buf = Buffer::_new(
+      Rope::new(),
+      self.local_options().clone(),
+      None,
+      None,
+      None,
+      None,
+    );
+    let buf_id = buf.id();
+    self.buffers.insert(buf_id, Buffer::to_arc(buf));
+    buf_id
+  }
+
+  /// Create new scratch buffer for the plugin UIs, i.e. file explorers and pickers.
+  ///
+  /// The buffer is unlisted and `nofile` (see 'buftype'), i.e. it's not related to a file and
+  /// never written, the `name` is only for display.
+  /// See: <https://vimhelp.org/windows.txt.html#scratch-buffer>.
+  ///
+  /// # Returns
+  ///
+  /// It returns the buffer ID.
+  ///
+  /// NOTE: This is a primitive API.
+  pub fn new_scratch_buffer(&mut self, name: &str) -> BufferId {
+    let mut options = self.local_options().clone();
+    options.set_buf_type(BufType::NoFile);
+    let mut buf = Buffer::_new_empty(options);
+    buf.set_name(Some(name.to_string()));
+    buf.set_listed(false);
+    let buf_id = buf.id();
+    self.buffers.insert(buf_id, Buffer::to_arc(buf));
+    buf_id
+  }
+}
+
+// Primitive APIs {
+
+// Read all bytes from the reader into the buffer chunk by chunk, returns the read bytes.
+fn read_chunks<R: Read>(mut reader: R, buf: &mut Vec<u8>) -> std::io::Result<usize> {
+  let mut chunk = vec![0_u8; READ_CHUNK_SIZE];
+  let mut bytes = 0_usize;
+  loop {
+    match reader.read(&mut chunk) {
+      Ok(0) => return Ok(bytes),
+      Ok(n) => {
+        buf.extend_from_slice(&chunk[..n]);
+        bytes += n;
+      }
+      Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+      Err(e) => return Err(e),
+    }
+  }
+}
+
+/// The temporary file when writing a file, i.e. `.{filename}.tmp` in the same directory.
+pub fn temp_filename(filename: &Path) -> PathBuf {
+  let mut name = std::ffi::OsString::from(".");
+  name.push(filename.file_name().unwrap_or_default());
+  name.push(".tmp");
+  filename.with_file_name(name)
+}
+
+/// The backup file when writing a file with 'backup' option, i.e. `{filename}~`.
+/// See: <https://vimhelp.org/options.txt.html#%27backupext%27>.
+pub fn backup_filename(filename: &Path) -> PathBuf {
+  let mut name = filename.as_os_str().to_os_string();
+  name.push("~");
+  PathBuf::from(name)
+}
+
+// Write the payload to a temporary file in the same directory, then rename it to the file. Thus
+// the file is never observed in a truncated state, and it is untouched if any step fails. The
+// permissions of the original file are preserved.
+//
+// If `backup` is `true`, the original file is copied to the backup file before the rename.
+fn write_file(filename: &Path, payload: &[u8], backup: bool) -> IoResult<()> {
+  let tmp_filename = temp_filename(filename);
+  let describe = |action: &str, path: &Path, e: std::io::Error| {
+    std::io::Error::new(e.kind(), format!("Failed to {} {:?}: {}", action, path, e))
+  };
+
+  let result = (|| {
+    let original_metadata = std::fs::metadata(filename).ok();
+
+    let mut fp = std::fs::File::create(&tmp_filename)
+      .map_err(|e| describe("create temporary file", &tmp_filename, e))?;
+    fp.write_all(payload)
+      .map_err(|e| describe("write temporary file", &tmp_filename, e))?;
+    if let Some(metadata) = &original_metadata {
+      fp.set_permissions(metadata.permissions())
+        .map_err(|e| describe("set permissions of", &tmp_filename, e))?;
+    }
+    fp.sync_all()
+      .map_err(|e| describe("sync temporary file", &tmp_filename, e))?;
+    drop(fp);
+
+    if backup && original_metadata.is_some() {
+      let backup_filename = backup_filename(filename);
+      std::fs::copy(filename, &backup_filename)
+        .map_err(|e| describe("write backup file", &backup_filename, e))?;
+    }
+
+    std::fs::rename(&tmp_filename, filename)
+      .map_err(|e| describe("rename temporary file to", filename, e))
+  })();
+
+  if result.is_err() {
+    // The temporary file can be left if it is not renamed.
+    let _ = std::fs::remove_file(&tmp_filename);
+  }
+  result
+}
+
+impl BuffersManager {
+  fn to_rope(&self, buf: &[u8], bufsize: usize) -> Rope {
+    let bufstr = self.to_str(buf, bufsize);
+    let mut block = RopeBuilder::new();
+    block.append(&bufstr.to_owned());
+    block.finish()
+  }
+
+  fn to_str(&self, buf: &[u8], bufsize: usize) -> String {
+    let fencoding = self.local_options().file_encoding();
+    match fencoding {
+      FileEncoding::Utf8 => String::from_utf8_lossy(&buf[0..bufsize]).into_owned(),
+    }
+  }
+
+  // Implementation for [new_buffer_edit_file](new_buffer_edit_file), the buffer is large if the
+  // file size exceeds the `large_file_threshold`.
+  //
+  // NOTE: If the file starts with gzip magic header (`1f 8b`), it is transparently decompressed
+  // into the buffer, no matter what the file extension is. A file that has `.gz` extension but is
+  // not actually gzip-compressed is read as plain text.
+  fn edit_file(
+    &self,
+    filename: &Path,
+    absolute_filename: &Path,
+    large_file_threshold: u64,
+  ) -> IoResult<Buffer> {
+    match std::fs::File::open(filename) {
       Ok(fp) => {
         let metadata = match fp.metadata() {
           Ok(metadata) => metadata,
@@ -438,9 +1825,24 @@ impl BuffersManager {
             return Err(e);
           }
         };
-        let mut buf: Vec<u8> = Vec::new();
         let mut reader = std::io::BufReader::new(fp);
-        let bytes = match reader.read_to_end(&mut buf) {
+        let compressed = match reader.fill_buf() {
+          Ok(header) => header.starts_with(&GZIP_MAGIC_HEADER),
+          Err(e) => {
+            trace!("Failed to read file {:?}:{:?}", filename, e);
+            return Err(e);
+          }
+        };
+        let large = metadata.len() > large_file_threshold;
+        // Pre-allocate with the file size to avoid re-allocations on large file. For gzip file the
+        // decompressed contents are larger, the buffer still grows while reading.
+        let mut buf: Vec<u8> = Vec::with_capacity(metadata.len() as usize);
+        let bytes = if compressed {
+          read_chunks(GzDecoder::new(reader), &mut buf)
+        } else {
+          read_chunks(reader, &mut buf)
+        };
+        let bytes = match bytes {
           Ok(bytes) => bytes,
           Err(e) => {
             trace!("Failed to read file {:?}:{:?}", filename, e);
@@ -448,21 +1850,31 @@ impl BuffersManager {
           }
         };
         trace!(
-          "Read {} bytes (buf: {}) from file {:?}",
+          "Read {} bytes (buf: {}, compressed: {}, large: {}) from file {:?}",
           bytes,
           buf.len(),
+          compressed,
+          large,
           filename
         );
         assert!(bytes == buf.len());
 
-        Ok(Buffer::_new(
+        let readonly = metadata.permissions().readonly();
+        let mut buffer = Buffer::_new(
           self.to_rope(&buf, buf.len()),
           self.local_options().clone(),
           Some(filename.to_path_buf()),
           Some(absolute_filename.to_path_buf()),
           Some(metadata),
           Some(Instant::now()),
-        ))
+        );
+        buffer.set_compressed(compressed);
+        buffer.set_large(large);
+        // Same with Vim, the file without write permission is opened as `readonly`.
+        if readonly {
+          buffer.set_readonly(true);
+        }
+        Ok(buffer)
       }
       Err(e) => {
         trace!("Failed to open file {:?}:{:?}", filename, e);
@@ -470,10 +1882,271 @@ impl BuffersManager {
       }
     }
   }
+
+  // Implementation for [new_buffer_edit_file](new_buffer_edit_file) when the file is a directory,
+  // the buffer lists the directory entries instead.
+  fn edit_directory(&self, filename: &Path, absolute_filename: &Path) -> IoResult<Buffer> {
+    let metadata = match std::fs::metadata(absolute_filename) {
+      Ok(metadata) => metadata,
+      Err(e) => {
+        trace!(
+          "Failed to fetch metadata from directory {:?}:{:?}",
+          filename,
+          e
+        );
+        return Err(e);
+      }
+    };
+    let listing = DirListing::read(absolute_filename, self.local_options().show_hidden())?;
+    let mut buffer = Buffer::_new(
+      Rope::new(),
+      self.local_options().clone(),
+      Some(filename.to_path_buf()),
+      Some(absolute_filename.to_path_buf()),
+      Some(metadata),
+      Some(Instant::now()),
+    );
+    buffer.set_dir_listing(listing);
+    Ok(buffer)
+  }
+}
+
+impl BuffersManager {
+  /// Write the buffer contents to its file, i.e. `:write`.
+  ///
+  /// # Returns
+  ///
+  /// It returns the written bytes if successful.
+  ///
+  /// # Errors
+  ///
+  /// If the buffer doesn't exist or cannot be written (see 'buftype') or has no file name, or the
+  /// buffer is `readonly` or the file is modified outside the editor (unless `force`, i.e.
+  /// `:write!`), or it's a directory buffer, or the file writing operations failed.
+  ///
+  /// NOTE: This is a primitive API, the `BufWritePre`/`BufWritePost` autocmds are fired by
+  /// [`JsRuntime::write_buffer`](crate::js::JsRuntime::write_buffer).
+  pub fn write_buffer(&self, id: &BufferId, force: bool) -> BufferResult<usize> {
+    let buf = match self.buffers.get(id) {
+      Some(buf) => buf,
+      None => return Err(BufferErr::NotExist(*id)),
+    };
+    let mut buf = wlock!(buf);
+    if !buf.buf_type().writable() {
+      return Err(BufferErr::NotWritable);
+    }
+    let filename = match buf.absolute_filename() {
+      Some(filename) => filename.clone(),
+      None => return Err(BufferErr::NoFileName),
+    };
+    if buf.kind() == BufferKind::Directory {
+      return Err(BufferErr::IsDirectory(filename));
+    }
+
+    if !force && buf.check_external_change() == ExternalChange::Modified {
+      return Err(BufferErr::ChangedExternally(filename));
+    }
+
+    // Save into memory first, so the file is untouched if the buffer is readonly.
+    let mut payload: Vec<u8> = Vec::new();
+    buf.force_save_to(&mut payload, force)?;
+    write_file(&filename, &payload, buf.backup())?;
+    trace!("Wrote {} bytes to file {:?}", payload.len(), filename);
+
+    buf.mark_synced(std::fs::metadata(&filename)?);
+    // The file has the latest contents, the journal is useless.
+    if let Err(e) = journal::remove_journal(&self.journal_dir, &filename) {
+      trace!("Failed to remove journal of {:?}:{:?}", filename, e);
+    }
+    buf.recovery_journal = None;
+    Ok(payload.len())
+  }
+
+  /// Reload the buffer from its file, i.e. `:edit!`. The changes in the buffer are discarded, and
+  /// the external change is cleared.
+  ///
+  /// # Errors
+  ///
+  /// If the buffer doesn't exist or has no file name, or the file reading operations failed, and
+  /// the buffer contents are unchanged.
+  pub fn reload_buffer(&self, id: &BufferId) -> BufferResult<()> {
+    let buf = match self.buffers.get(id) {
+      Some(buf) => buf,
+      None => return Err(BufferErr::NotExist(*id)),
+    };
+    let mut buf = wlock!(buf);
+    let filename = match buf.absolute_filename() {
+      Some(filename) => filename.clone(),
+      None => return Err(BufferErr::NoFileName),
+    };
+    if buf.kind() == BufferKind::Directory {
+      buf.refresh_dir_listing()?;
+      return Ok(());
+    }
+
+    let reloaded = self.edit_file(&filename, &filename, self.large_file_threshold)?;
+    trace!("Reloaded buffer {:?} from file {:?}", id, filename);
+    buf.reload_from(reloaded);
+    Ok(())
+  }
+
+  /// Whether any buffer is modified, i.e. the editor cannot quit without `!`.
+  pub fn any_modified(&self) -> bool {
+    self.buffers.values().any(|buf| rlock!(buf).is_modified())
+  }
 }
 
 // Primitive APIs }
 
+// Recovery journal {
+impl BuffersManager {
+  /// The directory of the recovery journals, by default is the
+  /// [`swap_dir`](crate::envar::global_config::GlobalConfig::swap_dir). See [`journal`].
+  pub fn journal_dir(&self) -> &Path {
+    &self.journal_dir
+  }
+
+  pub fn set_journal_dir(&mut self, value: PathBuf) {
+    self.journal_dir = value;
+  }
+
+  // Find the journal newer than the file. The journal that has the same contents with the buffer
+  // is useless, it's removed.
+  fn find_recovery_journal(&self, buf: &Buffer, absolute_filename: &Path) -> Option<PathBuf> {
+    let path = journal::find_newer_journal(&self.journal_dir, absolute_filename)?;
+    match journal::read_journal(&path) {
+      Ok(rope) if rope == buf.rope => {
+        let _ = journal::remove_journal(&self.journal_dir, absolute_filename);
+        None
+      }
+      Ok(_) => Some(path),
+      Err(e) => {
+        trace!("Failed to read journal {:?}:{:?}", path, e);
+        None
+      }
+    }
+  }
+
+  /// Take the snapshots of the modified file buffers to write into their journals, see
+  /// [`journal::write_journal`]. The buffers waiting for recovery are skipped, otherwise their
+  /// journals are overwritten. The large buffers are skipped as well, see [`Buffer::is_large`].
+  ///
+  /// NOTE: Only the read locks of the buffers are held, the ropes are cheap to clone.
+  pub fn journal_snapshots(&self) -> Vec<JournalSnapshot> {
+    self
+      .buffers
+      .values()
+      .filter_map(|buf| {
+        let buf = rlock!(buf);
+        match buf.absolute_filename() {
+          Some(filename)
+            if buf.is_modified() && buf.recovery_journal().is_none() && !buf.is_large() =>
+          {
+            Some(JournalSnapshot {
+              buf_id: buf.id(),
+              changed_tick: buf.changed_tick(),
+              filename: filename.clone(),
+              rope: buf.rope.clone(),
+            })
+          }
+          _ => None,
+        }
+      })
+      .collect()
+  }
+
+  /// Recover the buffer from its journal, i.e. `:recover`. The buffer is modified after recovery,
+  /// it should be written to keep the recovered contents.
+  ///
+  /// # Errors
+  ///
+  /// If the buffer doesn't exist or has no file name, or there's no journal, or the journal
+  /// reading operations failed, and the buffer contents are unchanged.
+  pub fn recover_buffer(&self, id: &BufferId) -> BufferResult<()> {
+    let buf = match self.buffers.get(id) {
+      Some(buf) => buf,
+      None => return Err(BufferErr::NotExist(*id)),
+    };
+    let mut buf = wlock!(buf);
+    let filename = match buf.absolute_filename() {
+      Some(filename) => filename.clone(),
+      None => return Err(BufferErr::NoFileName),
+    };
+    let path = journal::journal_path(&self.journal_dir, &filename);
+    let recovered = match journal::read_journal(&path) {
+      Ok(recovered) => recovered,
+      Err(e) if e.kind() == IoErrKind::NotFound => return Err(BufferErr::NoJournal(filename)),
+      Err(e) => return Err(e.into()),
+    };
+    trace!("Recovered buffer {:?} from journal {:?}", id, path);
+    buf.recover_from(recovered);
+    Ok(())
+  }
+
+  /// Remove the journals of the buffers, i.e. the editor quits normally. The journals of the
+  /// buffers waiting for recovery are kept.
+  pub fn remove_journals(&self) {
+    for buf in self.buffers.values() {
+      let buf = rlock!(buf);
+      if let (Some(filename), None) = (buf.absolute_filename(), buf.recovery_journal()) {
+        if let Err(e) = journal::remove_journal(&self.journal_dir, filename) {
+          trace!("Failed to remove journal of {:?}:{:?}", filename, e);
+        }
+      }
+    }
+  }
+}
+// Recovery journal }
+
+// Listed {
+impl BuffersManager {
+  fn is_listed(buf: &BufferArc) -> bool {
+    rlock!(buf).listed()
+  }
+
+  /// Iterate all listed buffers, ordered by buffer ID.
+  pub fn iter_listed(&self) -> impl Iterator<Item = (&BufferId, &BufferArc)> {
+    self.buffers.iter().filter(|(_, buf)| Self::is_listed(buf))
+  }
+
+  /// Iterate all unlisted buffers, ordered by buffer ID.
+  pub fn iter_unlisted(&self) -> impl Iterator<Item = (&BufferId, &BufferArc)> {
+    self.buffers.iter().filter(|(_, buf)| !Self::is_listed(buf))
+  }
+
+  /// Get the next listed buffer ID after `id` (for `:bnext`), it wraps around to the first listed
+  /// buffer when reaching the end.
+  ///
+  /// # Returns
+  ///
+  /// It returns `None` if there are no listed buffers.
+  pub fn next_listed(&self, id: &BufferId) -> Option<BufferId> {
+    self
+      .buffers
+      .range((std::ops::Bound::Excluded(*id), std::ops::Bound::Unbounded))
+      .chain(self.buffers.iter())
+      .find(|(_, buf)| Self::is_listed(buf))
+      .map(|(buf_id, _)| *buf_id)
+  }
+
+  /// Get the previous listed buffer ID before `id` (for `:bprev`), it wraps around to the last
+  /// listed buffer when reaching the beginning.
+  ///
+  /// # Returns
+  ///
+  /// It returns `None` if there are no listed buffers.
+  pub fn prev_listed(&self, id: &BufferId) -> Option<BufferId> {
+    self
+      .buffers
+      .range(..*id)
+      .rev()
+      .chain(self.buffers.iter().rev())
+      .find(|(_, buf)| Self::is_listed(buf))
+      .map(|(buf_id, _)| *buf_id)
+  }
+}
+// Listed }
+
 // BTreeMap {
 impl BuffersManager {
   pub fn is_empty(&self) -> bool {
@@ -484,8 +2157,23 @@ impl BuffersManager {
     self.buffers.len()
   }
 
+  /// Remove the buffer, its recovery journal is removed if it's not modified, i.e. it's closed
+  /// cleanly.
+  ///
+  /// NOTE: The windows remember the view states of the buffers they showed, call
+  /// [`Tree::forget_buffer`](crate::ui::tree::Tree::forget_buffer) to clean them.
   pub fn remove(&mut self, id: &BufferId) -> Option<BufferArc> {
-    self.buffers.remove(id)
+    let buf = self.buffers.remove(id)?;
+    {
+      let buf = rlock!(buf);
+      if let (Some(filename), false) = (buf.absolute_filename(), buf.is_modified()) {
+        let _ = journal::remove_journal(&self.journal_dir, filename);
+      }
+    }
+    if let Some(filename) = rlock!(buf).absolute_filename() {
+      self.buffers_by_path.remove(filename);
+    }
+    Some(buf)
   }
 
   pub fn get(&self, id: &BufferId) -> Option<&BufferArc> {
@@ -518,20 +2206,60 @@ impl BuffersManager {
 }
 // BTreeMap }
 
-impl Default for BuffersManager {
-  fn default() -> Self {
-    BuffersManager::new()
+impl Default for BuffersManager {
+  fn default() -> Self {
+    BuffersManager::new()
+  }
+}
+
+// Options {
+impl BuffersManager {
+  pub fn local_options(&self) -> &BufferLocalOptions {
+    &self.local_options
+  }
+
+  pub fn set_local_options(&mut self, options: &BufferLocalOptions) {
+    self.local_options = options.clone();
+  }
+
+  pub fn tab_stop(&self) -> u16 {
+    self.local_options.tab_stop()
+  }
+
+  pub fn set_tab_stop(&mut self, value: u16) {
+    self.local_options.set_tab_stop(value);
+  }
+
+  pub fn ambiguous_width(&self) -> AmbiguousWidth {
+    self.local_options.ambiguous_width()
+  }
+
+  pub fn set_ambiguous_width(&mut self, value: AmbiguousWidth) {
+    self.local_options.set_ambiguous_width(value);
+  }
+
+  pub fn modifiable(&self) -> bool {
+    self.local_options.modifiable()
+  }
+
+  pub fn set_modifiable(&mut self, value: bool) {
+    self.local_options.set_modifiable(value);
+  }
+
+  pub fn readonly(&self) -> bool {
+    self.local_options.readonly()
+  }
+
+  pub fn set_readonly(&mut self, value: bool) {
+    self.local_options.set_readonly(value);
   }
-}
 
-// Options {
-impl BuffersManager {
-  pub fn local_options(&self) -> &BufferLocalOptions {
-    &self.local_options
+  pub fn buf_type(&self) -> BufType {
+    self.local_options.buf_type()
   }
 
-  pub fn set_local_options(&mut self, options: &BufferLocalOptions) {
-    self.local_options = options.clone();
+  pub fn set_buf_type(&mut self, value: BufType) {
+    self.local_options.set_buf_type(value);
   }
 }
 // Options }
@@ -545,6 +2273,7 @@ pub type BuffersManagerIter<'a> = std::collections::btree_map::Iter<'a, BufferId
 #[cfg(test)]
 mod tests {
   use super::*;
+  use crate::wlock;
   // use std::fs::File;
   // use tempfile::tempfile;
   // use tokio::sync::mpsc::Receiver;
@@ -588,6 +2317,1246 @@ mod tests {
     assert!(next_buffer_id() > 0);
   }
 
+  #[test]
+  fn edit_directory1() {
+    let tmpdir = assert_fs::TempDir::new().unwrap();
+    std::fs::create_dir(tmpdir.path().join("src")).unwrap();
+    std::fs::write(tmpdir.path().join("README.md"), "readme\n").unwrap();
+    std::fs::write(tmpdir.path().join(".hidden"), "hidden\n").unwrap();
+
+    let mut bufs = BuffersManager::new();
+    let buf_id = bufs.new_file_buffer(tmpdir.path()).unwrap();
+    assert_eq!(bufs.find_file_buffer(tmpdir.path()), Some(buf_id));
+    let buf = bufs.get(&buf_id).unwrap().clone();
+    {
+      let mut buf = buf.write();
+      assert_eq!(buf.kind(), BufferKind::Directory);
+      assert_eq!(buf.rope.to_string(), "../\nsrc/\nREADME.md\n");
+      assert_eq!(
+        buf.dir_listing().unwrap().entry(1).unwrap().path(),
+        tmpdir.path().join("src")
+      );
+      assert!(matches!(buf.insert(0, "x"), Err(BufferErr::NotModifiable)));
+      assert!(matches!(buf.remove(0..1), Err(BufferErr::NotModifiable)));
+
+      buf.set_show_hidden(true);
+      buf.refresh_dir_listing().unwrap();
+      assert_eq!(buf.rope.to_string(), "../\nsrc/\n.hidden\nREADME.md\n");
+    }
+    assert!(matches!(
+      bufs.write_buffer(&buf_id, false),
+      Err(BufferErr::IsDirectory(_))
+    ));
+  }
+
+  #[test]
+  fn edit_file_gzip1() {
+    use std::io::Write;
+
+    let tmpdir = assert_fs::TempDir::new().unwrap();
+    let gz_file = tmpdir.path().join("edit_file_gzip1.log.gz");
+    let payload = "Hello, RSVIM!\nThis is a gzip-compressed file.\n";
+    {
+      let fp = std::fs::File::create(&gz_file).unwrap();
+      let mut encoder = GzEncoder::new(fp, Compression::default());
+      encoder.write_all(payload.as_bytes()).unwrap();
+      encoder.finish().unwrap();
+    }
+    let plain_file = tmpdir.path().join("edit_file_gzip1.txt.gz");
+    std::fs::write(&plain_file, payload).unwrap();
+
+    let mut bufs = BuffersManager::new();
+    let buf_id = bufs.new_file_buffer(&gz_file).unwrap();
+    let buf = bufs.get(&buf_id).unwrap().clone();
+    let buf = buf.read();
+    assert!(buf.compressed());
+    assert_eq!(buf.rope.to_string(), payload);
+    let mut saved: Vec<u8> = Vec::new();
+    buf.save_to(&mut saved).unwrap();
+    assert!(saved.starts_with(&GZIP_MAGIC_HEADER));
+
+    let buf_id = bufs.new_file_buffer(&plain_file).unwrap();
+    let buf = bufs.get(&buf_id).unwrap().clone();
+    let buf = buf.read();
+    assert!(!buf.compressed());
+    assert_eq!(buf.rope.to_string(), payload);
+  }
+
+  #[test]
+  fn edit_file_large1() {
+    let tmpdir = assert_fs::TempDir::new().unwrap();
+    let large_file = tmpdir.path().join("edit_file_large1.txt");
+    let payload = "Hello, RSVIM!\n".repeat(READ_CHUNK_SIZE / 10);
+    std::fs::write(&large_file, &payload).unwrap();
+    let small_file = tmpdir.path().join("edit_file_large1.small.txt");
+    std::fs::write(&small_file, "Hello, RSVIM!\n").unwrap();
+
+    let mut bufs = BuffersManager::new();
+    bufs.set_journal_dir(tmpdir.path().to_path_buf());
+    bufs.set_large_file_threshold(1024);
+    let buf_id = bufs.new_file_buffer(&large_file).unwrap();
+    let small_buf_id = bufs.new_file_buffer(&small_file).unwrap();
+
+    {
+      let buf = bufs.get(&buf_id).unwrap().clone();
+      let mut buf = buf.write();
+      assert!(buf.is_large());
+      assert_eq!(buf.large_indicator(), Some("[large]"));
+      assert_eq!(buf.rope.to_string(), payload);
+      assert_eq!(buf.len_lines(), READ_CHUNK_SIZE / 10 + 1);
+
+      // The syntax tokens are dropped.
+      buf
+        .highlights_mut()
+        .set_syntax(0, vec![SyntaxToken::new(0, 5, "Comment")]);
+      assert!(buf.highlights().syntax(0).is_none());
+      buf.insert(0, "x").unwrap();
+    }
+    {
+      let buf = bufs.get(&small_buf_id).unwrap().clone();
+      let mut buf = buf.write();
+      assert!(!buf.is_large());
+      assert_eq!(buf.large_indicator(), None);
+      assert_eq!(buf.rope.to_string(), "Hello, RSVIM!\n");
+      buf.insert(0, "x").unwrap();
+    }
+
+    // The large buffer has no recovery journal.
+    let snapshots = bufs.journal_snapshots();
+    assert_eq!(snapshots.len(), 1);
+    assert_eq!(snapshots[0].buf_id, small_buf_id);
+  }
+
+  #[cfg(unix)]
+  #[test]
+  fn edit_file_readonly1() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let tmpdir = assert_fs::TempDir::new().unwrap();
+    let filename = tmpdir.path().join("edit_file_readonly1.txt");
+    std::fs::write(&filename, "Hello, RSVIM!\n").unwrap();
+    std::fs::set_permissions(&filename, std::fs::Permissions::from_mode(0o444)).unwrap();
+    let writable_filename = tmpdir.path().join("edit_file_readonly1.writable.txt");
+    std::fs::write(&writable_filename, "Hello, RSVIM!\n").unwrap();
+
+    let mut bufs = BuffersManager::new();
+    let buf_id = bufs.new_file_buffer(&filename).unwrap();
+    let writable_buf_id = bufs.new_file_buffer(&writable_filename).unwrap();
+
+    let buf = bufs.get(&buf_id).unwrap().clone();
+    assert!(buf.read().readonly());
+    assert_eq!(buf.read().readonly_indicator(), Some("[RO]"));
+    assert!(matches!(
+      bufs.write_buffer(&buf_id, false),
+      Err(BufferErr::ReadOnly)
+    ));
+    // The 'readonly' buffer can still be changed unless it's not 'modifiable', same with Vim.
+    buf.write().set_modifiable(false);
+    assert!(matches!(
+      buf.write().insert(0, "A"),
+      Err(BufferErr::NotModifiable)
+    ));
+    assert_eq!(buf.read().rope.to_string(), "Hello, RSVIM!\n");
+
+    let buf = bufs.get(&writable_buf_id).unwrap().clone();
+    assert!(!buf.read().readonly());
+    assert_eq!(buf.read().readonly_indicator(), None);
+  }
+
+  #[test]
+  fn title1() {
+    let tmpdir = assert_fs::TempDir::new().unwrap();
+    let filename = tmpdir.path().join("title1.txt");
+
+    let mut bufs = BuffersManager::new();
+    let buf_id = bufs.new_file_buffer(&filename).unwrap();
+    let buf = bufs.get(&buf_id).unwrap().clone();
+    assert!(!buf.read().is_modified());
+    assert_eq!(buf.read().title(), "title1.txt — rsvim");
+
+    buf.write().insert(0, "Hello").unwrap();
+    assert!(buf.read().is_modified());
+    assert_eq!(buf.read().title(), "title1.txt + — rsvim");
+
+    assert!(bufs.any_modified());
+
+    // The written buffer is not modified.
+    bufs.write_buffer(&buf_id, false).unwrap();
+    assert!(!buf.read().is_modified());
+    assert!(!bufs.any_modified());
+    assert_eq!(buf.read().title(), "title1.txt — rsvim");
+
+    let buf_id = bufs.new_empty_buffer();
+    let buf = bufs.get(&buf_id).unwrap().clone();
+    assert_eq!(buf.read().title(), "[No Name] — rsvim");
+  }
+
+  #[test]
+  fn write_buffer1() {
+    let tmpdir = assert_fs::TempDir::new().unwrap();
+    let filename = tmpdir.path().join("write_buffer1.txt");
+
+    let mut bufs = BuffersManager::new();
+    let buf_id = bufs.new_file_buffer(&filename).unwrap();
+    bufs
+      .get(&buf_id)
+      .unwrap()
+      .write()
+      .insert(0, "Hello, RSVIM!\n")
+      .unwrap();
+    assert_eq!(bufs.write_buffer(&buf_id, false).unwrap(), 14);
+    assert_eq!(
+      std::fs::read_to_string(&filename).unwrap(),
+      "Hello, RSVIM!\n"
+    );
+    assert!(bufs.get(&buf_id).unwrap().read().metadata().is_some());
+
+    // Readonly buffer is not written.
+    bufs.get(&buf_id).unwrap().write().set_readonly(true);
+    bufs
+      .get(&buf_id)
+      .unwrap()
+      .write()
+      .insert(0, "Changed")
+      .unwrap();
+    assert!(matches!(
+      bufs.write_buffer(&buf_id, false),
+      Err(BufferErr::ReadOnly)
+    ));
+    assert_eq!(
+      std::fs::read_to_string(&filename).unwrap(),
+      "Hello, RSVIM!\n"
+    );
+    // Unless it's forced.
+    assert_eq!(bufs.write_buffer(&buf_id, true).unwrap(), 21);
+    assert_eq!(
+      std::fs::read_to_string(&filename).unwrap(),
+      "ChangedHello, RSVIM!\n"
+    );
+
+    let empty_buf_id = bufs.new_empty_buffer();
+    assert!(matches!(
+      bufs.write_buffer(&empty_buf_id, false),
+      Err(BufferErr::NoFileName)
+    ));
+    assert!(matches!(
+      bufs.write_buffer(&-1, false),
+      Err(BufferErr::NotExist(-1))
+    ));
+  }
+
+  #[test]
+  fn recover_buffer1() {
+    let tmpdir = assert_fs::TempDir::new().unwrap();
+    let dir = tmpdir.path().join("swap");
+    let filename = tmpdir.path().join("recover_buffer1.txt");
+    std::fs::write(&filename, "Hello\n").unwrap();
+
+    // The editor crashed after the buffer is journaled.
+    std::thread::sleep(std::time::Duration::from_millis(20));
+    let snapshot = JournalSnapshot {
+      buf_id: 1,
+      changed_tick: 1,
+      filename: filename.absolutize().unwrap().to_path_buf(),
+      rope: Rope::from_str("Hello, RSVIM!\n"),
+    };
+    journal::write_journal(&dir, &snapshot).unwrap();
+
+    let mut bufs = BuffersManager::new();
+    bufs.set_journal_dir(dir.clone());
+    let buf_id = bufs.new_file_buffer(&filename).unwrap();
+    let journal_path = bufs
+      .get(&buf_id)
+      .unwrap()
+      .read()
+      .recovery_journal()
+      .clone()
+      .unwrap();
+    assert!(bufs.journal_snapshots().is_empty());
+
+    bufs.recover_buffer(&buf_id).unwrap();
+    {
+      let buf = bufs.get(&buf_id).unwrap().read();
+      assert_eq!(buf.rope.to_string(), "Hello, RSVIM!\n");
+      assert!(buf.is_modified());
+      assert!(buf.recovery_journal().is_none());
+    }
+    assert_eq!(bufs.journal_snapshots().len(), 1);
+
+    // The journal is useless after the buffer is written.
+    bufs.write_buffer(&buf_id, false).unwrap();
+    assert!(!journal_path.exists());
+    assert!(matches!(
+      bufs.recover_buffer(&buf_id),
+      Err(BufferErr::NoJournal(_))
+    ));
+  }
+
+  #[test]
+  fn write_buffer2() {
+    let tmpdir = assert_fs::TempDir::new().unwrap();
+    let filename = tmpdir.path().join("write_buffer2.txt");
+    std::fs::write(&filename, "Hello, RSVIM!\n").unwrap();
+    assert_eq!(
+      temp_filename(&filename),
+      tmpdir.path().join(".write_buffer2.txt.tmp")
+    );
+    assert_eq!(
+      backup_filename(&filename),
+      tmpdir.path().join("write_buffer2.txt~")
+    );
+
+    let mut bufs = BuffersManager::new();
+    let buf_id = bufs.new_file_buffer(&filename).unwrap();
+    {
+      let buf = bufs.get(&buf_id).unwrap();
+      let mut buf = buf.write();
+      buf.set_backup(true);
+      buf.insert(0, "Changed\n").unwrap();
+    }
+    assert_eq!(bufs.write_buffer(&buf_id, false).unwrap(), 22);
+    assert_eq!(
+      std::fs::read_to_string(&filename).unwrap(),
+      "Changed\nHello, RSVIM!\n"
+    );
+    // The backup file contains the original contents, and the temporary file is renamed.
+    assert_eq!(
+      std::fs::read_to_string(backup_filename(&filename)).unwrap(),
+      "Hello, RSVIM!\n"
+    );
+    assert!(!temp_filename(&filename).exists());
+  }
+
+  #[test]
+  fn write_buffer3() {
+    let tmpdir = assert_fs::TempDir::new().unwrap();
+    let filename = tmpdir.path().join("write_buffer3.txt");
+    std::fs::write(&filename, "Hello, RSVIM!\n").unwrap();
+
+    let mut bufs = BuffersManager::new();
+    let buf_id = bufs.new_file_buffer(&filename).unwrap();
+    bufs
+      .get(&buf_id)
+      .unwrap()
+      .write()
+      .insert(0, "Changed\n")
+      .unwrap();
+
+    // The temporary file cannot be created since there's a directory with the same name, the
+    // original file is untouched.
+    std::fs::create_dir(temp_filename(&filename)).unwrap();
+    match bufs.write_buffer(&buf_id, false) {
+      Err(BufferErr::IoErr(e)) => {
+        assert!(e.to_string().starts_with("Failed to create temporary file"))
+      }
+      r => panic!("Unexpected write result: {:?}", r),
+    }
+    assert_eq!(
+      std::fs::read_to_string(&filename).unwrap(),
+      "Hello, RSVIM!\n"
+    );
+    assert!(!backup_filename(&filename).exists());
+
+    std::fs::remove_dir(temp_filename(&filename)).unwrap();
+    assert_eq!(bufs.write_buffer(&buf_id, false).unwrap(), 22);
+    assert_eq!(
+      std::fs::read_to_string(&filename).unwrap(),
+      "Changed\nHello, RSVIM!\n"
+    );
+  }
+
+  #[cfg(unix)]
+  #[test]
+  fn write_buffer_permissions1() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let tmpdir = assert_fs::TempDir::new().unwrap();
+    let filename = tmpdir.path().join("write_buffer_permissions1.sh");
+    std::fs::write(&filename, "echo Hello\n").unwrap();
+    std::fs::set_permissions(&filename, std::fs::Permissions::from_mode(0o750)).unwrap();
+
+    let mut bufs = BuffersManager::new();
+    let buf_id = bufs.new_file_buffer(&filename).unwrap();
+    bufs
+      .get(&buf_id)
+      .unwrap()
+      .write()
+      .insert(0, "#!/bin/sh\n")
+      .unwrap();
+    bufs.write_buffer(&buf_id, false).unwrap();
+    let mode = std::fs::metadata(&filename).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o750);
+  }
+
+  #[test]
+  fn external_change1() {
+    let tmpdir = assert_fs::TempDir::new().unwrap();
+    let filename = tmpdir.path().join("external_change1.txt");
+    std::fs::write(&filename, "Hello\n").unwrap();
+
+    let mut bufs = BuffersManager::new();
+    let buf_id = bufs.new_file_buffer(&filename).unwrap();
+    let buf = bufs.get(&buf_id).unwrap().clone();
+    assert_eq!(
+      buf.write().check_external_change(),
+      ExternalChange::Unchanged
+    );
+
+    // Modified by another program.
+    std::fs::write(&filename, "Hello, RSVIM!\n").unwrap();
+    assert_eq!(
+      buf.write().check_external_change(),
+      ExternalChange::Modified
+    );
+    assert_eq!(buf.read().external_change(), ExternalChange::Modified);
+
+    // The write is refused.
+    buf.write().insert(0, "Changed").unwrap();
+    assert!(matches!(
+      bufs.write_buffer(&buf_id, false),
+      Err(BufferErr::ChangedExternally(_))
+    ));
+    assert_eq!(
+      std::fs::read_to_string(&filename).unwrap(),
+      "Hello, RSVIM!\n"
+    );
+    // Unless it's forced.
+    assert_eq!(bufs.write_buffer(&buf_id, true).unwrap(), 13);
+    assert_eq!(
+      std::fs::read_to_string(&filename).unwrap(),
+      "ChangedHello\n"
+    );
+    assert_eq!(buf.read().external_change(), ExternalChange::Unchanged);
+    assert_eq!(
+      buf.write().check_external_change(),
+      ExternalChange::Unchanged
+    );
+
+    // Reload discards the changes.
+    std::fs::write(&filename, "Hello\nRSVIM\n").unwrap();
+    assert_eq!(
+      buf.write().check_external_change(),
+      ExternalChange::Modified
+    );
+    buf.write().insert(0, "Changed").unwrap();
+    bufs.reload_buffer(&buf_id).unwrap();
+    {
+      let buf = buf.read();
+      assert_eq!(
+        buf.lines().map(|l| l.to_string()).collect::<String>(),
+        "Hello\nRSVIM\n"
+      );
+      assert!(!buf.is_modified());
+      assert_eq!(buf.external_change(), ExternalChange::Unchanged);
+    }
+    assert_eq!(
+      buf.write().check_external_change(),
+      ExternalChange::Unchanged
+    );
+
+    // Deleted by another program.
+    std::fs::remove_file(&filename).unwrap();
+    assert_eq!(buf.write().check_external_change(), ExternalChange::Deleted);
+    assert!(bufs.reload_buffer(&buf_id).is_err());
+  }
+
+  #[test]
+  fn listed1() {
+    let mut bufs = BuffersManager::new();
+    let mut ids: Vec<BufferId> = vec![];
+    for _ in 0..5 {
+      let buf = Buffer::_new_empty(BufferLocalOptions::default());
+      let buf_id = buf.id();
+      bufs.buffers.insert(buf_id, Buffer::to_arc(buf));
+      ids.push(buf_id);
+    }
+    // Unnamed buffer is listed by default.
+    let unnamed_id = bufs.new_empty_buffer();
+    ids.push(unnamed_id);
+    assert!(rlock!(bufs.get(&unnamed_id).unwrap()).listed());
+
+    // Mark 1st and 3rd buffers as unlisted.
+    wlock!(bufs.get(&ids[1]).unwrap()).set_listed(false);
+    wlock!(bufs.get(&ids[3]).unwrap()).set_listed(false);
+
+    let listed: Vec<BufferId> = bufs.iter_listed().map(|(id, _)| *id).collect();
+    assert_eq!(listed, vec![ids[0], ids[2], ids[4], ids[5]]);
+    let unlisted: Vec<BufferId> = bufs.iter_unlisted().map(|(id, _)| *id).collect();
+    assert_eq!(unlisted, vec![ids[1], ids[3]]);
+
+    // `:bn` cycles only the listed buffers.
+    let mut current = ids[0];
+    let mut visited = vec![];
+    for _ in 0..5 {
+      current = bufs.next_listed(&current).unwrap();
+      visited.push(current);
+    }
+    assert_eq!(visited, vec![ids[2], ids[4], ids[5], ids[0], ids[2]]);
+
+    // `:bp` cycles only the listed buffers.
+    let mut current = ids[0];
+    let mut visited = vec![];
+    for _ in 0..5 {
+      current = bufs.prev_listed(&current).unwrap();
+      visited.push(current);
+    }
+    assert_eq!(visited, vec![ids[5], ids[4], ids[2], ids[0], ids[5]]);
+
+    // Start from an unlisted buffer.
+    assert_eq!(bufs.next_listed(&ids[1]), Some(ids[2]));
+    assert_eq!(bufs.prev_listed(&ids[1]), Some(ids[0]));
+  }
+
+  #[test]
+  fn new_empty_buffer1() {
+    let tmpdir = assert_fs::TempDir::new().unwrap();
+    let filename = tmpdir.path().join("new_empty_buffer1.txt");
+
+    let mut bufs = BuffersManager::new();
+    let file_id = bufs.new_file_buffer(&filename).unwrap();
+    let ids = (0..3).map(|_| bufs.new_empty_buffer()).collect::<Vec<_>>();
+    assert_eq!(bufs.len(), 4);
+    assert!(ids.iter().all(|id| *id != file_id));
+    assert!(ids[0] != ids[1] && ids[1] != ids[2] && ids[0] != ids[2]);
+    for id in ids.iter() {
+      let buf = bufs.get(id).unwrap();
+      assert!(rlock!(buf).filename().is_none());
+      assert!(rlock!(buf).listed());
+    }
+
+    // The named buffer is still found by its path.
+    assert_eq!(bufs.find_file_buffer(&filename), Some(file_id));
+
+    // Removing an unnamed buffer keeps the others.
+    assert!(bufs.remove(&ids[1]).is_some());
+    assert!(bufs.contains_key(&ids[0]) && bufs.contains_key(&ids[2]));
+    assert_eq!(bufs.find_file_buffer(&filename), Some(file_id));
+  }
+
+  #[test]
+  fn scratch_buffer1() {
+    let mut bufs = BuffersManager::new();
+    let scratch_id1 = bufs.new_scratch_buffer("[Explorer]");
+    let scratch_id2 = bufs.new_scratch_buffer("[Picker]");
+    // Multiple unnamed buffers coexist with the scratch buffers.
+    let unnamed_id1 = bufs.new_empty_buffer();
+    let unnamed_id2 = bufs.new_empty_buffer();
+    assert_eq!(bufs.len(), 4);
+    assert_ne!(scratch_id1, scratch_id2);
+    assert_eq!(
+      bufs.iter_unlisted().map(|(id, _)| *id).collect::<Vec<_>>(),
+      vec![scratch_id1, scratch_id2]
+    );
+    assert_eq!(
+      bufs.iter_listed().map(|(id, _)| *id).collect::<Vec<_>>(),
+      vec![unnamed_id1, unnamed_id2]
+    );
+
+    let scratch1 = bufs.get(&scratch_id1).unwrap().clone();
+    let scratch2 = bufs.get(&scratch_id2).unwrap().clone();
+    assert_eq!(rlock!(scratch1).buf_type(), BufType::NoFile);
+    assert_eq!(rlock!(scratch1).display_name(), "[Explorer]");
+    assert_eq!(rlock!(scratch2).title(), "[Picker] — rsvim");
+    assert!(rlock!(scratch1).filename().is_none());
+
+    // The scratch buffer is never modified or written.
+    wlock!(scratch1).insert(0, "entry\n").unwrap();
+    assert!(!rlock!(scratch1).is_modified());
+    assert!(!bufs.any_modified());
+    assert!(matches!(
+      bufs.write_buffer(&scratch_id1, true),
+      Err(BufferErr::NotWritable)
+    ));
+    let mut payload: Vec<u8> = Vec::new();
+    assert!(matches!(
+      rlock!(scratch1).save_to(&mut payload),
+      Err(BufferErr::NotWritable)
+    ));
+    assert!(payload.is_empty());
+    assert_eq!(rlock!(scratch2).len_chars(), 0);
+
+    assert!(bufs.remove(&scratch_id1).is_some());
+    assert!(bufs.contains_key(&scratch_id2));
+  }
+
+  #[test]
+  fn remove1() {
+    let tmpdir = assert_fs::TempDir::new().unwrap();
+    let filename = tmpdir.path().join("remove1.txt");
+
+    let mut bufs = BuffersManager::new();
+    let buf_id = bufs.new_file_buffer(&filename).unwrap();
+    assert_eq!(bufs.find_file_buffer(&filename), Some(buf_id));
+    assert!(bufs.remove(&buf_id).is_some());
+    assert_eq!(bufs.find_file_buffer(&filename), None);
+
+    // The file can be opened again.
+    let buf_id = bufs.new_file_buffer(&filename).unwrap();
+    assert_eq!(bufs.find_file_buffer(&filename), Some(buf_id));
+  }
+
+  #[test]
+  fn listed2() {
+    let mut bufs = BuffersManager::new();
+    let buf_id = bufs.new_empty_buffer();
+    assert_eq!(bufs.next_listed(&buf_id), Some(buf_id));
+    assert_eq!(bufs.prev_listed(&buf_id), Some(buf_id));
+
+    wlock!(bufs.get(&buf_id).unwrap()).set_listed(false);
+    assert_eq!(bufs.next_listed(&buf_id), None);
+    assert_eq!(bufs.prev_listed(&buf_id), None);
+    assert_eq!(bufs.iter_listed().count(), 0);
+  }
+
+  #[test]
+  fn edit1() {
+    let mut buf = Buffer::_new(
+      Rope::from_str("Hello\n"),
+      BufferLocalOptions::default(),
+      None,
+      None,
+      None,
+      None,
+    );
+    buf.insert(5, ", RSVIM").unwrap();
+    assert_eq!(buf.rope.to_string(), "Hello, RSVIM\n");
+    buf.remove(0..7).unwrap();
+    assert_eq!(buf.rope.to_string(), "RSVIM\n");
+    buf.replace(0..5, "World").unwrap();
+    assert_eq!(buf.rope.to_string(), "World\n");
+    assert_eq!(buf.changed_tick(), 3);
+  }
+
+  fn make_reflow_buffer(text: &str, text_width: u16) -> Buffer {
+    let opts = BufferLocalOptions::builder().text_width(text_width).build();
+    Buffer::_new(Rope::from_str(text), opts, None, None, None, None)
+  }
+
+  fn make_buffer_from_str(text: &str) -> Buffer {
+    Buffer::_new(
+      Rope::from_str(text),
+      BufferLocalOptions::default(),
+      None,
+      None,
+      None,
+      None,
+    )
+  }
+
+  fn buffer_lines(buf: &Buffer) -> Vec<String> {
+    buf
+      .lines()
+      .map(|l| l.to_string().trim_end_matches('\n').to_string())
+      .collect()
+  }
+
+  #[test]
+  fn edit_lines1() {
+    let lines = |lines: &[&str]| lines.iter().map(|l| l.to_string()).collect::<Vec<_>>();
+
+    let mut buf = make_buffer_from_str("a\nb\n");
+    buf.set_line(1, "B").unwrap();
+    assert_eq!(buf.rope.to_string(), "a\nB\n");
+    buf.append_lines(0, &lines(&["x", "y"])).unwrap();
+    assert_eq!(buf.rope.to_string(), "x\ny\na\nB\n");
+    buf.append_lines(4, &lines(&["z"])).unwrap();
+    assert_eq!(buf.rope.to_string(), "x\ny\na\nB\nz\n");
+    buf.delete_lines(1..3).unwrap();
+    assert_eq!(buf.rope.to_string(), "x\nB\nz\n");
+    buf.delete_lines(1..3).unwrap();
+    assert_eq!(buf.rope.to_string(), "x\n");
+
+    // The last line without line break.
+    let mut buf = make_buffer_from_str("a\nb");
+    buf.append_lines(2, &lines(&["c"])).unwrap();
+    assert_eq!(buf.rope.to_string(), "a\nb\nc");
+    buf.delete_lines(1..3).unwrap();
+    assert_eq!(buf.rope.to_string(), "a");
+    buf.delete_lines(0..1).unwrap();
+    assert_eq!(buf.rope.to_string(), "");
+
+    // Empty buffer has an empty line.
+    buf.append_lines(1, &lines(&["a"])).unwrap();
+    assert_eq!(buf.rope.to_string(), "\na");
+
+    // Out of range.
+    assert!(matches!(
+      buf.set_line(2, "x"),
+      Err(BufferErr::InvalidLine(2))
+    ));
+    assert!(matches!(
+      buf.append_lines(3, &lines(&["x"])),
+      Err(BufferErr::InvalidLine(3))
+    ));
+    assert!(matches!(
+      buf.delete_lines(1..3),
+      Err(BufferErr::InvalidLine(3))
+    ));
+    assert!(matches!(
+      buf.delete_lines(2..1),
+      Err(BufferErr::InvalidLine(2))
+    ));
+    assert_eq!(buf.rope.to_string(), "\na");
+  }
+
+  #[test]
+  fn replace_range1() {
+    let mut buf = make_buffer_from_str("a你b\nc\n");
+    let changed_tick = buf.changed_tick();
+
+    // A single edit.
+    assert_eq!(buf.replace_range(0, 1, 2, "x").unwrap(), "你");
+    assert_eq!(buf.rope.to_string(), "axb\nc\n");
+    assert_eq!(buf.changed_tick(), changed_tick + 1);
+    assert_eq!(buf.replace_range(0, 0, 3, "好").unwrap(), "axb");
+    assert_eq!(buf.rope.to_string(), "好\nc\n");
+
+    // Insert at the end of line.
+    assert_eq!(buf.replace_range(1, 1, 1, "d").unwrap(), "");
+    assert_eq!(buf.rope.to_string(), "好\ncd\n");
+
+    // Out of range, the line break can't be replaced.
+    assert!(matches!(
+      buf.replace_range(1, 1, 3, "x"),
+      Err(BufferErr::InvalidColumn(3))
+    ));
+    assert!(matches!(
+      buf.replace_range(1, 2, 1, "x"),
+      Err(BufferErr::InvalidColumn(2))
+    ));
+    assert!(matches!(
+      buf.replace_range(2, 0, 0, "x"),
+      Err(BufferErr::InvalidLine(2))
+    ));
+    assert_eq!(buf.rope.to_string(), "好\ncd\n");
+  }
+
+  #[test]
+  fn line_col1() {
+    let buf = make_buffer_from_str("\t你好\nabc\tdef\r\n\n世界");
+    let len_chars = buf.len_chars();
+    assert_eq!(len_chars, 16);
+
+    // Round trip the random positions, generated by a simple LCG.
+    let mut seed: usize = 2112;
+    for _ in 0..200 {
+      seed = (seed * 1103515245 + 12345) % (1 << 31);
+      let char_idx = seed % (len_chars + 1);
+      let (line, col) = buf.char_to_line_col(char_idx).unwrap();
+      assert!(col <= buf.line_len_chars(line));
+      let expect = if char_idx == 12 { 11 } else { char_idx };
+      assert_eq!(buf.line_col_to_char(line, col), Some(expect));
+      assert_eq!(buf.line_col_to_char_clamped(line, col), expect);
+    }
+
+    // Line break maps to the end of the line, `\r\n` maps to the same position.
+    assert_eq!(buf.char_to_line_col(3), Some((0, 3)));
+    assert_eq!(buf.char_to_line_col(11), Some((1, 7)));
+    assert_eq!(buf.char_to_line_col(12), Some((1, 7)));
+    assert_eq!(buf.char_to_line_col(13), Some((2, 0)));
+
+    // Col equals to the line length is allowed, col beyond it is not.
+    assert_eq!(buf.line_col_to_char(0, 3), Some(3));
+    assert_eq!(buf.line_col_to_char(0, 4), None);
+    assert_eq!(buf.line_col_to_char_clamped(0, 4), 3);
+    assert_eq!(buf.line_col_to_char(4, 0), None);
+    assert_eq!(buf.line_col_to_char_clamped(4, 0), 14);
+
+    // One-past-the-end.
+    assert_eq!(buf.char_to_line_col(len_chars), Some((3, 2)));
+    assert_eq!(buf.line_col_to_char(3, 2), Some(len_chars));
+    assert_eq!(buf.char_to_line_col(len_chars + 1), None);
+
+    // One-past-the-end is the empty line after the trailing line break.
+    let buf = make_buffer_from_str("你好\n");
+    assert_eq!(buf.char_to_line_col(3), Some((1, 0)));
+    assert_eq!(buf.line_col_to_char(1, 0), Some(3));
+    assert_eq!(buf.line_col_to_char(1, 1), None);
+    assert_eq!(buf.line_col_to_char_clamped(5, 5), 3);
+
+    // Empty buffer.
+    let buf = make_buffer_from_str("");
+    assert_eq!(buf.char_to_line_col(0), Some((0, 0)));
+    assert_eq!(buf.line_col_to_char(0, 0), Some(0));
+    assert_eq!(buf.line_col_to_char_clamped(1, 1), 0);
+  }
+
+  #[test]
+  fn reflow1() {
+    let text = "The quick brown fox jumps over the lazy dog, and the lazy dog doesn't care about it at all.\n";
+    let mut buf = make_reflow_buffer(text, 20);
+    let line_range = buf.reflow(0..1).unwrap();
+    let lines = buffer_lines(&buf);
+    assert_eq!(line_range, 0..lines.len() - 1);
+    assert!(line_range.len() > 1);
+    for line in lines.iter() {
+      assert!(buf.str_width(line) <= 20, "line:{:?}", line);
+    }
+    assert_eq!(
+      lines[line_range].join(" "),
+      text.trim_end_matches('\n').to_string()
+    );
+    assert_eq!(buf.rope.to_string().chars().last(), Some('\n'));
+  }
+
+  #[test]
+  fn reflow2() {
+    // Indentation is preserved, and the words are never broken.
+    let text = "    Lorem ipsum dolor sit amet, consectetur adipiscing elit.\n\n  Supercalifragilisticexpialidocious word\n";
+    let mut buf = make_reflow_buffer(text, 20);
+    buf.reflow(0..4).unwrap();
+    assert_eq!(
+      buffer_lines(&buf),
+      vec![
+        "    Lorem ipsum",
+        "    dolor sit amet,",
+        "    consectetur",
+        "    adipiscing elit.",
+        "",
+        "  Supercalifragilisticexpialidocious",
+        "  word",
+        "",
+      ]
+    );
+  }
+
+  #[test]
+  fn reflow3() {
+    // Join short lines, and CJK chars are double width.
+    let text = "hello\nworld\n这是一个非常简单而且非常短的测试例子\n";
+    let mut buf = make_reflow_buffer(text, 20);
+    buf.reflow(0..3).unwrap();
+    assert_eq!(
+      buffer_lines(&buf),
+      vec![
+        "hello world 这是一个",
+        "非常简单而且非常短的",
+        "测试例子",
+        ""
+      ]
+    );
+  }
+
+  #[test]
+  fn reflow4() {
+    let opts = BufferLocalOptions::builder()
+      .text_width(5)
+      .modifiable(false)
+      .build();
+    let mut buf = Buffer::_new(Rope::from_str("abc def\n"), opts, None, None, None, None);
+    assert!(matches!(buf.reflow(0..1), Err(BufferErr::NotModifiable)));
+    assert_eq!(buf.rope.to_string(), "abc def\n");
+  }
+
+  #[test]
+  fn join_lines1() {
+    // The line ends with a space, no space is inserted.
+    let mut buf = make_buffer_from_str("Hello \nworld\n");
+    assert_eq!(buf.join_lines(0, 1, false).unwrap(), (0, 6));
+    assert_eq!(buf.rope.to_string(), "Hello world\n");
+    assert_eq!(buf.changed_tick(), 1);
+    assert_eq!(
+      buf.take_line_changes(),
+      vec![LineChange {
+        line_idx: 0,
+        removed: 1,
+        inserted: 0
+      }]
+    );
+
+    // The leading whitespaces of the indented line are squashed into one space.
+    let mut buf = make_buffer_from_str("fn main() {\n    let a = (1\n  )\n}\n");
+    assert_eq!(buf.join_lines(0, 1, false).unwrap(), (0, 11));
+    assert_eq!(buf.rope.to_string(), "fn main() { let a = (1\n  )\n}\n");
+    // No space before `)`.
+    assert_eq!(buf.join_lines(0, 1, false).unwrap(), (0, 22));
+    assert_eq!(buf.rope.to_string(), "fn main() { let a = (1)\n}\n");
+  }
+
+  #[test]
+  fn join_lines2() {
+    // The count is clamped to the last line, the empty line after the last line break is not
+    // joined.
+    let mut buf = make_buffer_from_str("a\nb\n\n\tc\n");
+    assert_eq!(buf.join_lines(1, 10, false).unwrap(), (1, 1));
+    assert_eq!(buf.rope.to_string(), "a\nb c\n");
+    assert_eq!(buf.changed_tick(), 1);
+
+    // Nothing to join on the last line.
+    assert_eq!(buf.join_lines(1, 1, false).unwrap(), (1, 2));
+    assert_eq!(buf.rope.to_string(), "a\nb c\n");
+    assert_eq!(buf.changed_tick(), 1);
+
+    // The empty line is joined without space.
+    let mut buf = make_buffer_from_str("\nabc\n");
+    assert_eq!(buf.join_lines(0, 1, false).unwrap(), (0, 0));
+    assert_eq!(buf.rope.to_string(), "abc\n");
+  }
+
+  #[test]
+  fn join_lines3() {
+    // The whitespaces are preserved, i.e. `gJ`.
+    let mut buf = make_buffer_from_str("Hello\r\n  world\r\n  again");
+    assert_eq!(buf.join_lines(0, 2, true).unwrap(), (0, 12));
+    assert_eq!(buf.rope.to_string(), "Hello  world  again");
+
+    let opts = BufferLocalOptions::builder().modifiable(false).build();
+    let mut buf = Buffer::_new(Rope::from_str("a\nb\n"), opts, None, None, None, None);
+    assert!(matches!(
+      buf.join_lines(0, 1, false),
+      Err(BufferErr::NotModifiable)
+    ));
+    assert_eq!(buf.rope.to_string(), "a\nb\n");
+  }
+
+  #[test]
+  fn edit2() {
+    let opts = BufferLocalOptions::builder().modifiable(false).build();
+    let mut buf = Buffer::_new(Rope::from_str("Hello\n"), opts, None, None, None, None);
+    assert!(matches!(buf.insert(0, "A"), Err(BufferErr::NotModifiable)));
+    assert!(matches!(buf.remove(0..2), Err(BufferErr::NotModifiable)));
+    assert!(matches!(
+      buf.replace(0..2, "B"),
+      Err(BufferErr::NotModifiable)
+    ));
+    assert!(matches!(
+      buf.append(Rope::from_str("World\n")),
+      Err(BufferErr::NotModifiable)
+    ));
+    assert_eq!(buf.rope.to_string(), "Hello\n");
+    assert_eq!(buf.changed_tick(), 0);
+
+    buf.set_modifiable(true);
+    buf.insert(0, "A").unwrap();
+    assert_eq!(buf.rope.to_string(), "AHello\n");
+  }
+
+  #[test]
+  fn signs1() {
+    let mut buf = Buffer::_new(
+      Rope::from_str("a\nb\nc\nd\n"),
+      BufferLocalOptions::default(),
+      None,
+      None,
+      None,
+      None,
+    );
+    buf.place_sign(1, Sign::new("git", "+", 10)).unwrap();
+    buf.place_sign(3, Sign::new("lsp", "E", 10)).unwrap();
+    let glyph = |buf: &Buffer, line_idx: usize| buf.signs().top(line_idx).map(|s| s.glyph());
+
+    // The lines inserted above the signs move them down.
+    buf.insert(0, "x\ny\n").unwrap();
+    assert_eq!(buf.rope.to_string(), "x\ny\na\nb\nc\nd\n");
+    assert_eq!(glyph(&buf, 1), None);
+    assert_eq!(glyph(&buf, 3), Some("+"));
+    assert_eq!(glyph(&buf, 5), Some("E"));
+
+    // The lines removed above the signs move them up.
+    buf.remove(0..4).unwrap();
+    assert_eq!(buf.rope.to_string(), "a\nb\nc\nd\n");
+    assert_eq!(glyph(&buf, 1), Some("+"));
+    assert_eq!(glyph(&buf, 3), Some("E"));
+
+    // The edits inside a line don't move them.
+    buf.insert(0, "A").unwrap();
+    assert_eq!(glyph(&buf, 1), Some("+"));
+
+    // The signs on the removed lines are dropped.
+    buf.remove(2..4).unwrap();
+    assert_eq!(buf.rope.to_string(), "Aa\nc\nd\n");
+    assert_eq!(glyph(&buf, 1), None);
+    assert_eq!(glyph(&buf, 2), Some("E"));
+    buf.remove(2..6).unwrap();
+    assert_eq!(buf.rope.to_string(), "Aa\n");
+    assert!(buf.signs().is_empty());
+  }
+
+  #[test]
+  fn line_changes1() {
+    let mut buf = Buffer::_new(
+      Rope::from_str("a\nb\nc\nd\n"),
+      BufferLocalOptions::default(),
+      None,
+      None,
+      None,
+      None,
+    );
+    buf.insert(2, "x\ny\n").unwrap();
+    buf.insert(0, "A").unwrap();
+    buf.remove(5..9).unwrap();
+    assert_eq!(buf.rope.to_string(), "Aa\nx\nc\nd\n");
+    buf.replace(0..3, "a").unwrap();
+    assert_eq!(buf.rope.to_string(), "ax\nc\nd\n");
+    let line_change = |line_idx, removed, inserted| LineChange {
+      line_idx,
+      removed,
+      inserted,
+    };
+    assert_eq!(
+      buf.take_line_changes(),
+      vec![
+        line_change(1, 0, 2),
+        line_change(2, 2, 0),
+        line_change(0, 1, 0)
+      ]
+    );
+    assert!(buf.take_line_changes().is_empty());
+
+    // The appended lines are inserted after the last line.
+    let changed_tick = buf.changed_tick();
+    buf.append(Rope::from_str("e\nf\n")).unwrap();
+    assert_eq!(buf.rope.to_string(), "ax\nc\nd\ne\nf\n");
+    assert_eq!(buf.changed_tick(), changed_tick + 1);
+    assert_eq!(buf.take_line_changes(), vec![line_change(3, 0, 2)]);
+
+    let change = line_change(2, 2, 1);
+    assert_eq!(change.adjust(1), 1);
+    assert_eq!(change.adjust(2), 2);
+    assert_eq!(change.adjust(3), 2);
+    assert_eq!(change.adjust(4), 2);
+    assert_eq!(change.adjust(5), 4);
+  }
+
+  #[test]
+  fn last_line1() {
+    let make = |text: &str| {
+      Buffer::_new(
+        Rope::from_str(text),
+        BufferLocalOptions::default(),
+        None,
+        None,
+        None,
+        None,
+      )
+    };
+
+    let buf = make("a\nb\n");
+    assert_eq!(buf.len_lines(), 3);
+    assert!(buf.last_line_has_eol());
+    assert_eq!(buf.last_line_idx(), 1);
+    assert_eq!(buf.noeol_indicator(), None);
+
+    let buf = make("a\nb");
+    assert_eq!(buf.len_lines(), 2);
+    assert!(!buf.last_line_has_eol());
+    assert_eq!(buf.last_line_idx(), 1);
+    assert_eq!(buf.noeol_indicator(), Some("[noeol]"));
+
+    let buf = make("a\r\n\r\n");
+    assert!(buf.last_line_has_eol());
+    assert_eq!(buf.last_line_idx(), 1);
+
+    let buf = make("");
+    assert!(!buf.last_line_has_eol());
+    assert_eq!(buf.last_line_idx(), 0);
+    assert_eq!(buf.noeol_indicator(), None);
+
+    // The empty line after the trailing line break becomes a line.
+    let mut buf = make("a\nb\n");
+    buf.ensure_line(1).unwrap();
+    assert_eq!(buf.rope.to_string(), "a\nb\n");
+    buf.ensure_line(2).unwrap();
+    assert_eq!(buf.rope.to_string(), "a\nb\n\n");
+    assert_eq!(buf.last_line_idx(), 2);
+  }
+
+  #[test]
+  fn save_to1() {
+    let opts = BufferLocalOptions::builder().readonly(true).build();
+    let mut buf = Buffer::_new(Rope::from_str("Hello\n"), opts, None, None, None, None);
+    let mut saved: Vec<u8> = Vec::new();
+    assert!(matches!(buf.save_to(&mut saved), Err(BufferErr::ReadOnly)));
+    assert!(saved.is_empty());
+
+    buf.set_readonly(false);
+    buf.save_to(&mut saved).unwrap();
+    assert_eq!(saved, "Hello\n".as_bytes());
+  }
+
+  #[test]
+  fn line_char1() {
+    // Empty buffer.
+    let buf = Buffer::_new_empty(BufferLocalOptions::default());
+    assert_eq!(buf.len_lines(), 1);
+    assert_eq!(buf.line_len_chars(0), 0);
+    assert_eq!(buf.line_to_char(0), 0);
+    assert_eq!(buf.char_to_line(0), 0);
+
+    // With trailing line break.
+    let buf = Buffer::_new(
+      Rope::from_str("hello\nworld\r\n"),
+      BufferLocalOptions::default(),
+      None,
+      None,
+      None,
+      None,
+    );
+    assert_eq!(buf.len_lines(), 3);
+    assert_eq!(buf.line_len_chars(0), 5);
+    assert_eq!(buf.line_len_chars(1), 5);
+    assert_eq!(buf.line_len_chars(2), 0);
+    assert_eq!(buf.line_len_chars(3), 0);
+    assert_eq!(buf.line_to_char(1), 6);
+    assert_eq!(buf.line_to_char(2), 13);
+    assert_eq!(buf.line_to_char(3), 13);
+    assert_eq!(buf.char_to_line(5), 0);
+    assert_eq!(buf.char_to_line(6), 1);
+    assert_eq!(buf.char_to_line(12), 1);
+    assert_eq!(buf.char_to_line(13), 2);
+  }
+
+  #[test]
+  fn line_char2() {
+    // Without trailing line break.
+    let buf = Buffer::_new(
+      Rope::from_str("hello\nworld"),
+      BufferLocalOptions::default(),
+      None,
+      None,
+      None,
+      None,
+    );
+    assert_eq!(buf.len_lines(), 2);
+    assert_eq!(buf.line_len_chars(1), 5);
+    assert_eq!(buf.line_len_chars(2), 0);
+    assert_eq!(buf.line_to_char(1), 6);
+    assert_eq!(buf.line_to_char(2), 11);
+    assert_eq!(buf.char_to_line(10), 1);
+    assert_eq!(buf.char_to_line(11), 1);
+    for char_idx in 0..buf.len_chars() {
+      let line_idx = buf.char_to_line(char_idx);
+      assert!(buf.line_to_char(line_idx) <= char_idx);
+      assert!(char_idx < buf.line_to_char(line_idx + 1));
+    }
+  }
+
+  #[test]
+  fn search1() {
+    let buf = Buffer::_new(
+      Rope::from_str("Hello, RSVIM!\nThis is a test line.\nAnother line.\n"),
+      BufferLocalOptions::default(),
+      None,
+      None,
+      None,
+      None,
+    );
+    let pattern = Regex::new("line").unwrap();
+    assert_eq!(buf.search(&pattern, 0, None), Some(29..33));
+    assert_eq!(buf.search(&pattern, 29, None), Some(43..47));
+    // Wrap around the end of buffer.
+    assert_eq!(buf.search(&pattern, 43, None), Some(29..33));
+    assert_eq!(buf.search(&pattern, buf.len_chars(), None), Some(29..33));
+
+    // The match at the start position is the last one.
+    let pattern = Regex::new("H").unwrap();
+    assert_eq!(buf.search(&pattern, 0, None), Some(0..1));
+
+    let pattern = Regex::new("l+").unwrap();
+    assert_eq!(buf.search(&pattern, 0, None), Some(2..4));
+    let pattern = Regex::new("x*").unwrap();
+    assert_eq!(buf.search(&pattern, 0, None), None);
+    let pattern = Regex::new("RSVIM").unwrap();
+    assert_eq!(buf.search(&pattern, 0, Some(Instant::now())), None);
+  }
+
+  #[test]
+  fn search_backward1() {
+    let buf = Buffer::_new(
+      Rope::from_str("Hello, RSVIM!\nThis is a test line.\nAnother line.\n"),
+      BufferLocalOptions::default(),
+      None,
+      None,
+      None,
+      None,
+    );
+    let pattern = Regex::new("line").unwrap();
+    assert_eq!(buf.search_backward(&pattern, 43, None), Some(29..33));
+    assert_eq!(buf.search_backward(&pattern, 44, None), Some(43..47));
+    // Wrap around the start of buffer.
+    assert_eq!(buf.search_backward(&pattern, 29, None), Some(43..47));
+    assert_eq!(buf.search_backward(&pattern, 0, None), Some(43..47));
+
+    // The last match of the line before the start position.
+    let pattern = Regex::new("i").unwrap();
+    assert_eq!(buf.search_backward(&pattern, 29, None), Some(19..20));
+
+    // The match at the start position is the last one.
+    let pattern = Regex::new("H").unwrap();
+    assert_eq!(buf.search_backward(&pattern, 0, None), Some(0..1));
+
+    let pattern = Regex::new("x*").unwrap();
+    assert_eq!(buf.search_backward(&pattern, 0, None), None);
+    let pattern = Regex::new("RSVIM").unwrap();
+    assert_eq!(buf.search_backward(&pattern, 0, Some(Instant::now())), None);
+  }
+
+  #[test]
+  fn first_non_blank_char1() {
+    let buf = Buffer::_new(
+      Rope::from_str("  \tfoo\n   \n\nbar"),
+      BufferLocalOptions::default(),
+      None,
+      None,
+      None,
+      None,
+    );
+    assert_eq!(buf.first_non_blank_char(0), 3);
+    assert_eq!(buf.first_non_blank_char(1), 2);
+    assert_eq!(buf.first_non_blank_char(2), 0);
+    assert_eq!(buf.first_non_blank_char(3), 0);
+    assert_eq!(buf.first_non_blank_char(4), 0);
+  }
+
+  #[test]
+  fn indent1() {
+    let opts = BufferLocalOptions::builder().tab_stop(4).build();
+    let mut buf = Buffer::_new(
+      Rope::from_str("\t  foo\nbar\n"),
+      opts,
+      None,
+      None,
+      None,
+      None,
+    );
+    assert_eq!(buf.line_indent(0), "\t  ");
+    assert_eq!(buf.line_indent(1), "");
+    assert_eq!(buf.indent_width("\t  "), 6);
+    assert_eq!(buf.indent_width("  \t"), 4);
+    assert_eq!(buf.make_indent(6), "\t  ");
+    buf.set_expand_tab(true);
+    assert_eq!(buf.make_indent(6), "      ");
+    assert_eq!(buf.indent_step(), 8);
+    buf.set_shift_width(0);
+    assert_eq!(buf.indent_step(), 4);
+  }
+
+  #[test]
+  fn ambiguous_width1() {
+    let mut buf = Buffer::_new(
+      Rope::from_str("§λ─\n"),
+      BufferLocalOptions::default(),
+      None,
+      None,
+      None,
+      None,
+    );
+    assert_eq!(buf.ambiguous_width(), AmbiguousWidth::Single);
+    assert_eq!(buf.char_width('§'), 1);
+    assert_eq!(buf.char_width('λ'), 1);
+    assert_eq!(buf.char_width('─'), 1);
+    assert_eq!(buf.str_width("§λ─"), 3);
+    assert_eq!(buf.char_width('好'), 2);
+
+    buf.set_ambiguous_width(AmbiguousWidth::Double);
+    assert_eq!(buf.char_width('§'), 2);
+    assert_eq!(buf.char_width('λ'), 2);
+    assert_eq!(buf.char_width('─'), 2);
+    assert_eq!(buf.str_width("§λ─"), 6);
+    assert_eq!(buf.char_width('好'), 2);
+    assert_eq!(buf.char_width('A'), 1);
+  }
+
   // #[test]
   // fn buffer_unicode_width1() {
   //   let (sender, _) = make_channel();
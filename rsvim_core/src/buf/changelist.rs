@@ -0,0 +1,170 @@
+//! The change list, i.e. the positions of the recent edits in a buffer, walked by `g;`/`g,`.
+//!
+//! See: <https://vimhelp.org/motion.txt.html#changelist>.
+
+use crate::buf::LineChange;
+
+use std::collections::VecDeque;
+
+/// The max changes in the change list, the oldest change is dropped when it's full, same with Vim.
+pub const CHANGE_LIST_SIZE: usize = 100;
+
+#[derive(Debug, Clone, Default)]
+/// The change list, the positions (i.e. the line index and the char index) of the edits are
+/// ordered from the oldest to the newest.
+///
+/// The consecutive edits on the same line are merged into one change, i.e. typing a word only
+/// records the position of its last char.
+pub struct ChangeList {
+  changes: VecDeque<(usize, usize)>,
+
+  // The index of the current change while walking the list, it equals to the changes count if not
+  // walking.
+  current: usize,
+}
+
+impl ChangeList {
+  pub fn new() -> Self {
+    ChangeList::default()
+  }
+
+  pub fn len(&self) -> usize {
+    self.changes.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.changes.is_empty()
+  }
+
+  /// Get the positions of the changes, from the oldest to the newest.
+  pub fn changes(&self) -> impl Iterator<Item = &(usize, usize)> {
+    self.changes.iter()
+  }
+
+  /// Get the index of the current change while walking the list, or the changes count if not
+  /// walking.
+  pub fn current(&self) -> usize {
+    self.current
+  }
+
+  /// Record the position of an edit as the newest change, it replaces the newest change if they're
+  /// on the same line. The walking restarts from the newest change.
+  pub fn push(&mut self, line_idx: usize, char_idx: usize) {
+    if let Some(last) = self.changes.back_mut() {
+      if last.0 == line_idx {
+        *last = (line_idx, char_idx);
+        self.current = self.changes.len();
+        return;
+      }
+    }
+    self.changes.push_back((line_idx, char_idx));
+    while self.changes.len() > CHANGE_LIST_SIZE {
+      self.changes.pop_front();
+    }
+    self.current = self.changes.len();
+  }
+
+  /// Walk back `count` changes, i.e. `g;`. It returns `None` if there're no older changes.
+  pub fn older(&mut self, count: usize) -> Option<(usize, usize)> {
+    let count = count.max(1);
+    if self.current < count {
+      return None;
+    }
+    self.current -= count;
+    self.changes.get(self.current).copied()
+  }
+
+  /// Walk forward `count` changes, i.e. `g,`. It returns `None` if there're no newer changes.
+  pub fn newer(&mut self, count: usize) -> Option<(usize, usize)> {
+    let count = count.max(1);
+    if self.current + count >= self.changes.len() {
+      return None;
+    }
+    self.current += count;
+    self.changes.get(self.current).copied()
+  }
+
+  /// Follow the lines changed in the buffer, the changes after the changed line are shifted, and
+  /// the changes in the removed lines are moved to the changed line.
+  pub fn adjust(&mut self, line_change: &LineChange) {
+    for change in self.changes.iter_mut() {
+      change.0 = line_change.adjust(change.0);
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn lines(change_list: &ChangeList) -> Vec<usize> {
+    change_list.changes().map(|c| c.0).collect()
+  }
+
+  #[test]
+  fn walk1() {
+    let mut change_list = ChangeList::new();
+    assert_eq!(change_list.older(1), None);
+    assert_eq!(change_list.newer(1), None);
+
+    change_list.push(1, 0);
+    change_list.push(1, 3);
+    change_list.push(5, 2);
+    change_list.push(9, 0);
+    // The edits on the same line are merged.
+    assert_eq!(
+      change_list.changes().copied().collect::<Vec<_>>(),
+      vec![(1, 3), (5, 2), (9, 0)]
+    );
+    assert_eq!(change_list.newer(1), None);
+
+    assert_eq!(change_list.older(1), Some((9, 0)));
+    assert_eq!(change_list.older(1), Some((5, 2)));
+    assert_eq!(change_list.older(1), Some((1, 3)));
+    assert_eq!(change_list.older(1), None);
+    assert_eq!(change_list.current(), 0);
+    assert_eq!(change_list.newer(2), Some((9, 0)));
+    assert_eq!(change_list.newer(1), None);
+    assert_eq!(change_list.older(3), None);
+    assert_eq!(change_list.older(2), Some((1, 3)));
+
+    // A new edit restarts from the newest change.
+    change_list.push(2, 0);
+    assert_eq!(change_list.current(), 4);
+    assert_eq!(change_list.older(1), Some((2, 0)));
+  }
+
+  #[test]
+  fn push1() {
+    let mut change_list = ChangeList::new();
+    for line_idx in 0..CHANGE_LIST_SIZE + 10 {
+      change_list.push(line_idx, 0);
+    }
+    assert_eq!(change_list.len(), CHANGE_LIST_SIZE);
+    assert_eq!(change_list.changes().next(), Some(&(10, 0)));
+  }
+
+  #[test]
+  fn adjust1() {
+    let mut change_list = ChangeList::new();
+    change_list.push(1, 0);
+    change_list.push(3, 1);
+    change_list.push(6, 2);
+
+    // Insert 2 lines after line 1.
+    change_list.adjust(&LineChange {
+      line_idx: 1,
+      removed: 0,
+      inserted: 2,
+    });
+    assert_eq!(lines(&change_list), vec![1, 5, 8]);
+
+    // Remove the lines 5-7, i.e. join them into line 4.
+    change_list.adjust(&LineChange {
+      line_idx: 4,
+      removed: 3,
+      inserted: 0,
+    });
+    assert_eq!(lines(&change_list), vec![1, 4, 5]);
+  }
+}
@@ -0,0 +1,251 @@
+//! The directory listing for the directory buffer, i.e. a minimal netrw-style file explorer.
+//!
+//! See: <https://vimhelp.org/pi_netrw.txt.html>.
+
+use crate::res::IoResult;
+
+use std::path::{Path, PathBuf};
+use tracing::trace;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// The kind of the directory entry.
+pub enum DirEntryKind {
+  File,
+  Directory,
+  /// A symbolic link to the listed directory or its ancestors.
+  SymlinkCycle,
+  /// A symbolic link to nothing.
+  BrokenSymlink,
+  /// The entry cannot be read, with the error message.
+  Unreadable(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// An entry in the directory listing, i.e. a line in the directory buffer.
+pub struct DirEntry {
+  name: String,
+  path: PathBuf,
+  kind: DirEntryKind,
+}
+
+impl DirEntry {
+  pub fn new(name: String, path: PathBuf, kind: DirEntryKind) -> Self {
+    DirEntry { name, path, kind }
+  }
+
+  pub fn name(&self) -> &str {
+    &self.name
+  }
+
+  pub fn path(&self) -> &Path {
+    &self.path
+  }
+
+  pub fn kind(&self) -> &DirEntryKind {
+    &self.kind
+  }
+
+  /// Whether the entry can be opened, i.e. a file or a directory.
+  pub fn openable(&self) -> bool {
+    matches!(self.kind, DirEntryKind::File | DirEntryKind::Directory)
+  }
+
+  /// The line of the entry in the directory buffer, the directories are suffixed with `/`, and the
+  /// entries that cannot be opened are annotated.
+  pub fn line(&self) -> String {
+    match &self.kind {
+      DirEntryKind::File => self.name.clone(),
+      DirEntryKind::Directory => format!("{}/", self.name),
+      DirEntryKind::SymlinkCycle => format!("{}/ [symlink cycle]", self.name),
+      DirEntryKind::BrokenSymlink => format!("{}@ [broken symlink]", self.name),
+      DirEntryKind::Unreadable(e) => format!("{} [unreadable: {}]", self.name, e),
+    }
+  }
+}
+
+#[derive(Debug, Clone)]
+/// The listing of a directory, the first entry is the parent directory `../` (if any), then the
+/// directories and files sorted by name, same with netrw's default 'sort sequence'.
+pub struct DirListing {
+  path: PathBuf,
+  entries: Vec<DirEntry>,
+}
+
+impl DirListing {
+  /// Read the entries of the directory, the hidden entries (i.e. the names start with `.`) are
+  /// skipped unless `show_hidden`.
+  ///
+  /// # Errors
+  ///
+  /// If the directory itself cannot be read. The entries that cannot be read are annotated instead.
+  pub fn read(path: &Path, show_hidden: bool) -> IoResult<Self> {
+    let read_dir = match std::fs::read_dir(path) {
+      Ok(read_dir) => read_dir,
+      Err(e) => {
+        trace!("Failed to read directory {:?}:{:?}", path, e);
+        return Err(e);
+      }
+    };
+    let canonical_path = std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+
+    let mut entries: Vec<DirEntry> = vec![];
+    for entry in read_dir {
+      let entry = match entry {
+        Ok(entry) => entry,
+        Err(e) => {
+          entries.push(DirEntry::new(
+            "?".to_string(),
+            path.to_path_buf(),
+            DirEntryKind::Unreadable(e.to_string()),
+          ));
+          continue;
+        }
+      };
+      let name = entry.file_name().to_string_lossy().to_string();
+      if !show_hidden && name.starts_with('.') {
+        continue;
+      }
+      let entry_path = entry.path();
+      let kind = match entry.file_type() {
+        Ok(file_type) if file_type.is_symlink() => match std::fs::metadata(&entry_path) {
+          Ok(metadata) if metadata.is_dir() => match std::fs::canonicalize(&entry_path) {
+            Ok(target) if canonical_path.starts_with(&target) => DirEntryKind::SymlinkCycle,
+            Ok(_) => DirEntryKind::Directory,
+            Err(e) => DirEntryKind::Unreadable(e.to_string()),
+          },
+          Ok(_) => DirEntryKind::File,
+          Err(e) if e.kind() == std::io::ErrorKind::NotFound => DirEntryKind::BrokenSymlink,
+          Err(e) => DirEntryKind::Unreadable(e.to_string()),
+        },
+        Ok(file_type) if file_type.is_dir() => DirEntryKind::Directory,
+        Ok(_) => DirEntryKind::File,
+        Err(e) => DirEntryKind::Unreadable(e.to_string()),
+      };
+      entries.push(DirEntry::new(name, entry_path, kind));
+    }
+    entries.sort_by(|a, b| {
+      let a_dir = a.kind == DirEntryKind::Directory;
+      let b_dir = b.kind == DirEntryKind::Directory;
+      b_dir.cmp(&a_dir).then_with(|| a.name.cmp(&b.name))
+    });
+
+    if let Some(parent) = path.parent() {
+      entries.insert(
+        0,
+        DirEntry::new(
+          "..".to_string(),
+          parent.to_path_buf(),
+          DirEntryKind::Directory,
+        ),
+      );
+    }
+
+    Ok(DirListing {
+      path: path.to_path_buf(),
+      entries,
+    })
+  }
+
+  /// The listed directory.
+  pub fn path(&self) -> &Path {
+    &self.path
+  }
+
+  pub fn entries(&self) -> &Vec<DirEntry> {
+    &self.entries
+  }
+
+  /// Get the entry at the line of the directory buffer.
+  pub fn entry(&self, line_idx: usize) -> Option<&DirEntry> {
+    self.entries.get(line_idx)
+  }
+
+  /// The text of the directory buffer, one entry per line.
+  pub fn text(&self) -> String {
+    self
+      .entries
+      .iter()
+      .map(|entry| format!("{}\n", entry.line()))
+      .collect()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn make_dir_tree() -> assert_fs::TempDir {
+    let tmpdir = assert_fs::TempDir::new().unwrap();
+    std::fs::create_dir(tmpdir.path().join("src")).unwrap();
+    std::fs::create_dir(tmpdir.path().join(".git")).unwrap();
+    std::fs::write(tmpdir.path().join("README.md"), "readme\n").unwrap();
+    std::fs::write(tmpdir.path().join(".gitignore"), "target\n").unwrap();
+    std::fs::write(tmpdir.path().join("Cargo.toml"), "[package]\n").unwrap();
+    std::fs::write(tmpdir.path().join("src").join("main.rs"), "fn main() {}\n").unwrap();
+    tmpdir
+  }
+
+  fn lines(listing: &DirListing) -> Vec<String> {
+    listing.entries().iter().map(|e| e.line()).collect()
+  }
+
+  #[test]
+  fn read1() {
+    let tmpdir = make_dir_tree();
+
+    let listing = DirListing::read(tmpdir.path(), false).unwrap();
+    assert_eq!(
+      lines(&listing),
+      vec!["../", "src/", "Cargo.toml", "README.md"]
+    );
+    assert_eq!(listing.text(), "../\nsrc/\nCargo.toml\nREADME.md\n");
+    assert_eq!(
+      listing.entry(0).unwrap().path(),
+      tmpdir.path().parent().unwrap()
+    );
+    assert_eq!(listing.entry(1).unwrap().path(), tmpdir.path().join("src"));
+    assert!(listing.entry(4).is_none());
+
+    let listing = DirListing::read(tmpdir.path(), true).unwrap();
+    assert_eq!(
+      lines(&listing),
+      vec![
+        "../",
+        ".git/",
+        "src/",
+        ".gitignore",
+        "Cargo.toml",
+        "README.md"
+      ]
+    );
+
+    assert!(DirListing::read(&tmpdir.path().join("README.md"), false).is_err());
+  }
+
+  #[cfg(unix)]
+  #[test]
+  fn read_symlinks1() {
+    let tmpdir = make_dir_tree();
+    let src = tmpdir.path().join("src");
+    std::os::unix::fs::symlink(tmpdir.path(), src.join("root")).unwrap();
+    std::os::unix::fs::symlink(&src, src.join("self")).unwrap();
+    std::os::unix::fs::symlink(src.join("missing.rs"), src.join("broken.rs")).unwrap();
+    std::os::unix::fs::symlink(src.join("main.rs"), src.join("lib.rs")).unwrap();
+
+    let listing = DirListing::read(&src, false).unwrap();
+    assert_eq!(
+      lines(&listing),
+      vec![
+        "../",
+        "broken.rs@ [broken symlink]",
+        "lib.rs",
+        "main.rs",
+        "root/ [symlink cycle]",
+        "self/ [symlink cycle]",
+      ]
+    );
+    assert!(!listing.entry(1).unwrap().openable());
+    assert!(listing.entry(2).unwrap().openable());
+    assert!(!listing.entry(4).unwrap().openable());
+  }
+}
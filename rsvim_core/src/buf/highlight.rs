@@ -0,0 +1,813 @@
+//! Syntax-agnostic highlight spans of a buffer.
+
+use crate::envar;
+use crate::res::AnyResult;
+use crate::rlock;
+
+use ahash::AHashMap as HashMap;
+use compact_str::CompactString;
+use crossterm::style::{Attribute, Attributes, Color};
+use parking_lot::RwLock;
+use std::collections::BTreeMap;
+use std::ops::Range;
+use std::sync::OnceLock;
+
+/// The style of a highlight, i.e. `(fg, bg, attrs)`. The `None` colors are kept unchanged when
+/// it's applied to a cell.
+pub type HighlightStyle = (Option<Color>, Option<Color>, Attributes);
+
+/// The highlight group name, i.e. `Comment`, `String`.
+/// See: <https://vimhelp.org/syntax.txt.html#highlight-groups>.
+pub type HighlightGroup = CompactString;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+/// The style applied to a char range of a line, i.e. `[start_char, end_char)`.
+///
+/// The `fg`/`bg` are kept unchanged if they're `None`, and the `attrs` are added to the cell's
+/// attributes. So the overlapped spans can be combined, and the later span wins if both set the
+/// same color.
+///
+/// The span of the editor (i.e. the search matches) is in a highlight group, its style is resolved
+/// by the current color scheme when rendering, before the span's own style.
+pub struct HighlightSpan {
+  start_char: usize,
+  end_char: usize,
+  fg: Option<Color>,
+  bg: Option<Color>,
+  attrs: Attributes,
+  group: Option<&'static str>,
+}
+
+impl HighlightSpan {
+  pub fn new(
+    start_char: usize,
+    end_char: usize,
+    fg: Option<Color>,
+    bg: Option<Color>,
+    attrs: Attributes,
+  ) -> Self {
+    HighlightSpan {
+      start_char,
+      end_char,
+      fg,
+      bg,
+      attrs,
+      group: None,
+    }
+  }
+
+  /// Start char index (inclusive), based on the line.
+  pub fn start_char(&self) -> usize {
+    self.start_char
+  }
+
+  /// End char index (exclusive), based on the line.
+  pub fn end_char(&self) -> usize {
+    self.end_char
+  }
+
+  pub fn fg(&self) -> Option<Color> {
+    self.fg
+  }
+
+  pub fn bg(&self) -> Option<Color> {
+    self.bg
+  }
+
+  pub fn attrs(&self) -> Attributes {
+    self.attrs
+  }
+
+  /// The highlight group of the span.
+  pub fn group(&self) -> Option<&'static str> {
+    self.group
+  }
+
+  /// The span of the search matches with 'hlsearch', i.e. Vim's `Search` highlight group.
+  /// See: <https://vimhelp.org/syntax.txt.html#hl-Search>.
+  pub fn search(start_char: usize, end_char: usize) -> Self {
+    HighlightSpan {
+      group: Some("Search"),
+      ..HighlightSpan::new(start_char, end_char, None, None, Attributes::default())
+    }
+  }
+
+  /// The span of the current match while typing the search pattern with 'incsearch', i.e. Vim's
+  /// `IncSearch` highlight group.
+  /// See: <https://vimhelp.org/syntax.txt.html#hl-IncSearch>.
+  pub fn inc_search(start_char: usize, end_char: usize) -> Self {
+    HighlightSpan {
+      group: Some("IncSearch"),
+      ..HighlightSpan::new(start_char, end_char, None, None, Attributes::default())
+    }
+  }
+
+  /// Whether the span contains the char.
+  pub fn contains(&self, char_idx: usize) -> bool {
+    self.start_char <= char_idx && char_idx < self.end_char
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// The syntax token of a line, i.e. the char range `[start_char, end_char)` is in the highlight
+/// group. The tokens are provided by a syntax engine (i.e. a plugin), and rendered with the
+/// group's style in the [`ColorScheme`].
+pub struct SyntaxToken {
+  start_char: usize,
+  end_char: usize,
+  group: HighlightGroup,
+}
+
+impl SyntaxToken {
+  pub fn new(start_char: usize, end_char: usize, group: &str) -> Self {
+    SyntaxToken {
+      start_char,
+      end_char,
+      group: HighlightGroup::from(group),
+    }
+  }
+
+  /// Start char index (inclusive), based on the line.
+  pub fn start_char(&self) -> usize {
+    self.start_char
+  }
+
+  /// End char index (exclusive), based on the line.
+  pub fn end_char(&self) -> usize {
+    self.end_char
+  }
+
+  pub fn group(&self) -> &str {
+    &self.group
+  }
+
+  /// Whether the token contains the char.
+  pub fn contains(&self, char_idx: usize) -> bool {
+    self.start_char <= char_idx && char_idx < self.end_char
+  }
+}
+
+#[derive(Debug, Clone, Default)]
+/// The highlight spans of a buffer, maps from line index to its spans in applying order.
+///
+/// The search matches are kept in a separated layer, they're maintained by the editor and applied
+/// after the other spans. The syntax tokens are another layer, they're applied before the other
+/// spans.
+///
+/// NOTE: The spans are not adjusted when the buffer text changes, the scripts should set them
+/// again.
+pub struct Highlights {
+  lines: BTreeMap<usize, Vec<HighlightSpan>>,
+  search: BTreeMap<usize, Vec<HighlightSpan>>,
+  syntax: BTreeMap<usize, Vec<SyntaxToken>>,
+  syntax_disabled: bool,
+}
+
+impl Highlights {
+  pub fn new() -> Self {
+    Highlights::default()
+  }
+
+  /// Get the spans of the line.
+  pub fn get(&self, line_idx: usize) -> Option<&Vec<HighlightSpan>> {
+    self.lines.get(&line_idx)
+  }
+
+  /// Set the spans of the line, the existing spans of the line are replaced.
+  pub fn set(&mut self, line_idx: usize, spans: Vec<HighlightSpan>) {
+    if spans.is_empty() {
+      self.lines.remove(&line_idx);
+    } else {
+      self.lines.insert(line_idx, spans);
+    }
+  }
+
+  /// Clear the spans of the lines in range.
+  pub fn clear(&mut self, line_range: Range<usize>) {
+    if line_range.is_empty() {
+      return;
+    }
+    let mut rest = self.lines.split_off(&line_range.start);
+    let mut after = rest.split_off(&line_range.end);
+    self.lines.append(&mut after);
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.lines.is_empty() && self.search.is_empty() && self.syntax.is_empty()
+  }
+
+  /// Get the search spans of the line.
+  pub fn search(&self, line_idx: usize) -> Option<&Vec<HighlightSpan>> {
+    self.search.get(&line_idx)
+  }
+
+  /// Set the search spans of the line, the existing search spans of the line are replaced.
+  pub fn set_search(&mut self, line_idx: usize, spans: Vec<HighlightSpan>) {
+    if spans.is_empty() {
+      self.search.remove(&line_idx);
+    } else {
+      self.search.insert(line_idx, spans);
+    }
+  }
+
+  /// Clear all the search spans.
+  pub fn clear_search(&mut self) {
+    self.search.clear();
+  }
+
+  /// Get the syntax tokens of the line.
+  pub fn syntax(&self, line_idx: usize) -> Option<&Vec<SyntaxToken>> {
+    self.syntax.get(&line_idx)
+  }
+
+  /// Whether the syntax tokens are kept, they're disabled for the large buffer, see
+  /// [`Buffer::is_large`](crate::buf::Buffer::is_large).
+  pub fn syntax_enabled(&self) -> bool {
+    !self.syntax_disabled
+  }
+
+  /// Enable or disable the syntax tokens, the existing tokens are dropped if it's disabled.
+  pub fn set_syntax_enabled(&mut self, enabled: bool) {
+    self.syntax_disabled = !enabled;
+    if !enabled {
+      self.syntax.clear();
+    }
+  }
+
+  /// Set the syntax tokens of the line, the existing tokens of the line are replaced. It does
+  /// nothing if the syntax tokens are disabled.
+  pub fn set_syntax(&mut self, line_idx: usize, tokens: Vec<SyntaxToken>) {
+    if self.syntax_disabled {
+      return;
+    }
+    if tokens.is_empty() {
+      self.syntax.remove(&line_idx);
+    } else {
+      self.syntax.insert(line_idx, tokens);
+    }
+  }
+
+  /// Clear the syntax tokens of the lines in range.
+  pub fn clear_syntax(&mut self, line_range: Range<usize>) {
+    if line_range.is_empty() {
+      return;
+    }
+    let mut rest = self.syntax.split_off(&line_range.start);
+    let mut after = rest.split_off(&line_range.end);
+    self.syntax.append(&mut after);
+  }
+
+  /// Get the style (fg, bg, attrs) of the char by applying the matched syntax tokens, then all
+  /// the matched spans in order, then the search spans. The highlight groups are resolved by the
+  /// [global](ColorSchemes::global) color schemes. The unchanged parts are `None`/empty.
+  pub fn style_at(&self, line_idx: usize, char_idx: usize) -> HighlightStyle {
+    let mut style = (None, None, Attributes::default());
+    // The color schemes are only locked if there's any highlight group.
+    let mut color_schemes = None;
+    let mut resolve = |group: &str| {
+      color_schemes
+        .get_or_insert_with(|| rlock!(ColorSchemes::global()))
+        .resolve(group)
+    };
+    let tokens = self.syntax.get(&line_idx).into_iter().flatten();
+    for token in tokens.filter(|token| token.contains(char_idx)) {
+      if let Some(group_style) = resolve(&token.group) {
+        apply_style(&mut style, group_style);
+      }
+    }
+    let spans = self.lines.get(&line_idx).into_iter().flatten();
+    let search_spans = self.search.get(&line_idx).into_iter().flatten();
+    for span in spans
+      .chain(search_spans)
+      .filter(|span| span.contains(char_idx))
+    {
+      if let Some(group_style) = span.group.and_then(&mut resolve) {
+        apply_style(&mut style, group_style);
+      }
+      apply_style(&mut style, (span.fg, span.bg, span.attrs));
+    }
+    style
+  }
+}
+
+/// Apply the upper style to the style, the `None` colors of the upper style are kept unchanged,
+/// and the attributes are added.
+pub fn apply_style(style: &mut HighlightStyle, upper: HighlightStyle) {
+  style.0 = upper.0.or(style.0);
+  style.1 = upper.1.or(style.1);
+  style.2.extend(upper.2);
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+// The highlight group definition, i.e. its style, or a link to another group.
+enum HighlightDef {
+  Style(HighlightStyle),
+  Link(HighlightGroup),
+}
+
+/// The max links followed when resolving a highlight group, thus the cyclic links end.
+const MAX_HIGHLIGHT_LINKS: usize = 100;
+
+#[derive(Debug, Clone)]
+/// The color scheme, i.e. the registry of highlight groups and their styles. A group is either
+/// defined with a style, or linked to another group, same with Vim's `:highlight link`.
+///
+/// The default groups are similar to Vim's default colors with dark background, the specific
+/// groups (i.e. `String`) are linked to the general groups (i.e. `Constant`). The editor's UI
+/// groups (i.e. `Search`, `CursorLine`) are also defined, the widgets are rendered with them.
+/// See: <https://vimhelp.org/syntax.txt.html#group-name>.
+/// See: <https://vimhelp.org/syntax.txt.html#highlight-default>.
+pub struct ColorScheme {
+  groups: HashMap<HighlightGroup, HighlightDef>,
+}
+
+impl Default for ColorScheme {
+  fn default() -> Self {
+    let mut color_scheme = ColorScheme {
+      groups: HashMap::new(),
+    };
+    let none = Attributes::none();
+    let bold = Attributes::from(Attribute::Bold);
+    let underlined = Attributes::from(Attribute::Underlined);
+    let reverse = Attributes::from(Attribute::Reverse);
+    for (group, style) in [
+      // The UI groups.
+      ("Normal", (None, None, none)),
+      ("NonText", (Some(Color::Blue), None, bold)),
+      ("Folded", (Some(Color::DarkBlue), Some(Color::Grey), none)),
+      ("CursorLine", (None, Some(Color::DarkGrey), none)),
+      ("LineNr", (Some(Color::Yellow), None, none)),
+      (
+        "SignColumn",
+        (Some(Color::Cyan), Some(Color::DarkGrey), none),
+      ),
+      ("Search", (Some(Color::Black), Some(Color::Yellow), none)),
+      ("IncSearch", (None, None, reverse)),
+      ("Visual", (None, None, reverse)),
+      ("ErrorMsg", (Some(Color::Red), None, bold)),
+      ("TabLine", (None, Some(Color::DarkGrey), underlined)),
+      ("TabLineSel", (None, None, bold)),
+      ("TabLineFill", (None, None, reverse)),
+      // The syntax groups.
+      ("Comment", (Some(Color::Blue), None, none)),
+      ("Constant", (Some(Color::Red), None, none)),
+      ("Identifier", (Some(Color::Cyan), None, none)),
+      ("Statement", (Some(Color::Yellow), None, none)),
+      ("PreProc", (Some(Color::Magenta), None, none)),
+      ("Type", (Some(Color::Green), None, bold)),
+      ("Special", (Some(Color::DarkYellow), None, none)),
+      ("Underlined", (Some(Color::Blue), None, underlined)),
+      ("Error", (Some(Color::White), Some(Color::Red), none)),
+      ("Todo", (Some(Color::Blue), Some(Color::Yellow), none)),
+    ] {
+      color_scheme.set(group, style);
+    }
+    for (group, target) in [
+      ("EndOfBuffer", "NonText"),
+      ("FoldColumn", "Folded"),
+      ("String", "Constant"),
+      ("Character", "Constant"),
+      ("Number", "Constant"),
+      ("Boolean", "Constant"),
+      ("Float", "Number"),
+      ("Function", "Identifier"),
+      ("Conditional", "Statement"),
+      ("Repeat", "Statement"),
+      ("Label", "Statement"),
+      ("Operator", "Statement"),
+      ("Keyword", "Statement"),
+      ("Exception", "Statement"),
+      ("Include", "PreProc"),
+      ("Define", "PreProc"),
+      ("Macro", "PreProc"),
+      ("StorageClass", "Type"),
+      ("Structure", "Type"),
+      ("Typedef", "Type"),
+      ("SpecialChar", "Special"),
+      ("Delimiter", "Special"),
+    ] {
+      color_scheme.link(group, target);
+    }
+    color_scheme
+  }
+}
+
+impl ColorScheme {
+  /// Define the group with the style, i.e. `:highlight {group} guifg=...`.
+  pub fn set(&mut self, group: &str, style: HighlightStyle) {
+    self
+      .groups
+      .insert(HighlightGroup::from(group), HighlightDef::Style(style));
+  }
+
+  /// Link the group to the target group, i.e. `:highlight link {group} {target}`.
+  pub fn link(&mut self, group: &str, target: &str) {
+    self.groups.insert(
+      HighlightGroup::from(group),
+      HighlightDef::Link(HighlightGroup::from(target)),
+    );
+  }
+
+  /// Remove the group, i.e. `:highlight clear {group}`.
+  pub fn clear(&mut self, group: &str) {
+    self.groups.remove(group);
+  }
+
+  /// Resolve the style of the group by following its links. It returns `None` if the group (or
+  /// the linked group) is not defined, or the links are cyclic.
+  pub fn resolve(&self, group: &str) -> Option<HighlightStyle> {
+    let mut group = group;
+    for _ in 0..MAX_HIGHLIGHT_LINKS {
+      match self.groups.get(group)? {
+        HighlightDef::Style(style) => return Some(*style),
+        HighlightDef::Link(target) => group = target.as_str(),
+      }
+    }
+    None
+  }
+}
+
+/// The name of the default color scheme.
+pub const DEFAULT_COLOR_SCHEME: &str = "default";
+
+static COLOR_SCHEMES: OnceLock<RwLock<ColorSchemes>> = OnceLock::new();
+
+#[derive(Debug, Clone)]
+/// The registry of the color schemes by name, the highlight groups are resolved by the current
+/// color scheme, i.e. switched with Vim's `:colorscheme`.
+/// See: <https://vimhelp.org/syntax.txt.html#%3Acolorscheme>.
+pub struct ColorSchemes {
+  schemes: BTreeMap<CompactString, ColorScheme>,
+  current: CompactString,
+  version: usize,
+}
+
+impl ColorSchemes {
+  /// Make the registry with the [default](DEFAULT_COLOR_SCHEME) color scheme.
+  pub fn new() -> Self {
+    let mut schemes = BTreeMap::new();
+    schemes.insert(
+      CompactString::from(DEFAULT_COLOR_SCHEME),
+      ColorScheme::default(),
+    );
+    ColorSchemes {
+      schemes,
+      current: CompactString::from(DEFAULT_COLOR_SCHEME),
+      version: 0,
+    }
+  }
+
+  /// The global color schemes, they're shared by all the widgets and buffers.
+  pub fn global() -> &'static RwLock<ColorSchemes> {
+    COLOR_SCHEMES.get_or_init(|| RwLock::new(ColorSchemes::new()))
+  }
+
+  /// Register the color scheme, the existing one with the same name is replaced.
+  pub fn register(&mut self, name: &str, color_scheme: ColorScheme) {
+    if name == self.current {
+      self.version += 1;
+    }
+    self.schemes.insert(CompactString::from(name), color_scheme);
+  }
+
+  /// Get the color scheme by name.
+  pub fn get(&self, name: &str) -> Option<&ColorScheme> {
+    self.schemes.get(name)
+  }
+
+  /// Get the names of all the color schemes, in alphabetical order.
+  pub fn names(&self) -> Vec<&str> {
+    self.schemes.keys().map(|name| name.as_str()).collect()
+  }
+
+  /// Get the name of the current color scheme.
+  pub fn current_name(&self) -> &str {
+    &self.current
+  }
+
+  /// Get the current color scheme.
+  pub fn current(&self) -> &ColorScheme {
+    self.schemes.get(&self.current).unwrap()
+  }
+
+  /// Get the mutable current color scheme, i.e. to define the groups with `:highlight`.
+  pub fn current_mut(&mut self) -> &mut ColorScheme {
+    self.version += 1;
+    self.schemes.get_mut(&self.current).unwrap()
+  }
+
+  /// Switch to the color scheme, i.e. `:colorscheme {name}`.
+  ///
+  /// # Errors
+  ///
+  /// If the color scheme doesn't exist.
+  pub fn switch(&mut self, name: &str) -> AnyResult<()> {
+    if !self.schemes.contains_key(name) {
+      anyhow::bail!("E185: Cannot find color scheme '{}'", name);
+    }
+    self.current = CompactString::from(name);
+    self.version += 1;
+    Ok(())
+  }
+
+  /// The version of the color schemes, it's increased when the resolved styles may change, i.e.
+  /// the current color scheme is switched or changed. The whole frame is repainted when it
+  /// changes.
+  pub fn version(&self) -> usize {
+    self.version
+  }
+
+  /// Resolve the style of the group by the current color scheme.
+  ///
+  /// NOTE: The RGB colors are downsampled by the canvas, if the terminal doesn't support
+  /// truecolor.
+  pub fn resolve(&self, group: &str) -> Option<HighlightStyle> {
+    self.current().resolve(group)
+  }
+}
+
+impl Default for ColorSchemes {
+  fn default() -> Self {
+    ColorSchemes::new()
+  }
+}
+
+/// Parse the color name, i.e. `"red"`, `"dark_grey"`, or the RGB hex code, i.e. `"#ff8000"`.
+pub fn parse_color(s: &str) -> AnyResult<Color> {
+  if let Some(hex) = s.strip_prefix('#') {
+    if hex.len() == 6 && hex.chars().all(|c| c.is_ascii_hexdigit()) {
+      let rgb = u32::from_str_radix(hex, 16).unwrap();
+      return Ok(Color::Rgb {
+        r: (rgb >> 16) as u8,
+        g: (rgb >> 8) as u8,
+        b: rgb as u8,
+      });
+    }
+  } else if let Ok(color) = Color::try_from(s) {
+    return Ok(color);
+  }
+  anyhow::bail!("E254: Cannot allocate color {}", s)
+}
+
+/// Parse the attribute name, i.e. `"bold"`, `"italic"`, `"underline"`, `"reverse"`.
+/// See: <https://vimhelp.org/syntax.txt.html#attr-list>.
+pub fn parse_attribute(s: &str) -> AnyResult<Attribute> {
+  match s {
+    "bold" => Ok(Attribute::Bold),
+    "italic" => Ok(Attribute::Italic),
+    "underline" => Ok(Attribute::Underlined),
+    "undercurl" => Ok(Attribute::Undercurled),
+    "strikethrough" => Ok(Attribute::CrossedOut),
+    "reverse" => Ok(Attribute::Reverse),
+    _ => anyhow::bail!("E418: Illegal value: {}", s),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn highlights1() {
+    let mut highlights = Highlights::new();
+    assert!(highlights.is_empty());
+    for line_idx in 0..5 {
+      highlights.set(
+        line_idx,
+        vec![HighlightSpan::new(
+          0,
+          3,
+          Some(Color::Red),
+          None,
+          Attributes::default(),
+        )],
+      );
+    }
+    highlights.clear(1..3);
+    assert!(highlights.get(0).is_some());
+    assert!(highlights.get(1).is_none());
+    assert!(highlights.get(2).is_none());
+    assert!(highlights.get(3).is_some());
+    assert!(highlights.get(4).is_some());
+
+    highlights.set(0, vec![]);
+    assert!(highlights.get(0).is_none());
+    highlights.clear(0..usize::MAX);
+    assert!(highlights.is_empty());
+  }
+
+  #[test]
+  fn style_at1() {
+    let mut highlights = Highlights::new();
+    highlights.set(
+      0,
+      vec![
+        HighlightSpan::new(0, 4, Some(Color::Red), None, Attributes::default()),
+        HighlightSpan::new(
+          2,
+          6,
+          Some(Color::Green),
+          Some(Color::Blue),
+          Attributes::from(Attribute::Bold),
+        ),
+      ],
+    );
+    assert_eq!(
+      highlights.style_at(0, 1),
+      (Some(Color::Red), None, Attributes::default())
+    );
+    // The later span wins.
+    assert_eq!(
+      highlights.style_at(0, 3),
+      (
+        Some(Color::Green),
+        Some(Color::Blue),
+        Attributes::from(Attribute::Bold)
+      )
+    );
+    assert_eq!(
+      highlights.style_at(0, 6),
+      (None, None, Attributes::default())
+    );
+    assert_eq!(
+      highlights.style_at(1, 0),
+      (None, None, Attributes::default())
+    );
+  }
+
+  #[test]
+  fn search1() {
+    let mut highlights = Highlights::new();
+    highlights.set(
+      0,
+      vec![HighlightSpan::new(
+        0,
+        4,
+        Some(Color::Red),
+        None,
+        Attributes::from(Attribute::Bold),
+      )],
+    );
+    highlights.set_search(
+      0,
+      vec![HighlightSpan::search(0, 2), HighlightSpan::inc_search(3, 5)],
+    );
+    // The search spans are applied after the other spans.
+    assert_eq!(
+      highlights.style_at(0, 1),
+      (
+        Some(Color::Black),
+        Some(Color::Yellow),
+        Attributes::from(Attribute::Bold)
+      )
+    );
+    let mut attrs = Attributes::from(Attribute::Bold);
+    attrs.set(Attribute::Reverse);
+    assert_eq!(highlights.style_at(0, 3), (Some(Color::Red), None, attrs));
+    assert_eq!(
+      highlights.style_at(0, 4),
+      (None, None, Attributes::from(Attribute::Reverse))
+    );
+
+    // Clearing the search spans doesn't affect the other spans.
+    highlights.clear_search();
+    assert!(highlights.search(0).is_none());
+    assert!(highlights.get(0).is_some());
+    highlights.clear(0..1);
+    assert!(highlights.is_empty());
+  }
+
+  #[test]
+  fn color_scheme1() {
+    let mut color_scheme = ColorScheme::default();
+    let constant = color_scheme.resolve("Constant").unwrap();
+    assert_eq!(constant.0, Some(Color::Red));
+    // The specific groups are linked to the general groups.
+    assert_eq!(color_scheme.resolve("String"), Some(constant));
+    assert_eq!(color_scheme.resolve("Float"), Some(constant));
+    assert_eq!(
+      color_scheme.resolve("EndOfBuffer"),
+      color_scheme.resolve("NonText")
+    );
+    assert_eq!(color_scheme.resolve("Unknown"), None);
+
+    // Define the group overrides the link.
+    let style = (
+      None,
+      Some(Color::Green),
+      Attributes::from(Attribute::Italic),
+    );
+    color_scheme.set("String", style);
+    assert_eq!(color_scheme.resolve("String"), Some(style));
+    assert_eq!(color_scheme.resolve("Float"), Some(constant));
+    color_scheme.clear("Constant");
+    assert_eq!(color_scheme.resolve("Number"), None);
+
+    // The cyclic links are not resolved.
+    color_scheme.link("A", "B");
+    color_scheme.link("B", "A");
+    assert_eq!(color_scheme.resolve("A"), None);
+  }
+
+  #[test]
+  fn syntax1() {
+    let mut highlights = Highlights::new();
+    highlights.set_syntax(
+      0,
+      vec![
+        SyntaxToken::new(0, 4, "Comment"),
+        SyntaxToken::new(2, 6, "Unknown"),
+      ],
+    );
+    highlights.set(
+      0,
+      vec![HighlightSpan::new(
+        3,
+        5,
+        None,
+        Some(Color::Grey),
+        Attributes::default(),
+      )],
+    );
+    let comment = ColorScheme::default().resolve("Comment").unwrap();
+    assert_eq!(highlights.style_at(0, 0), comment);
+    // The undefined group is not rendered.
+    assert_eq!(
+      highlights.style_at(0, 4),
+      (None, Some(Color::Grey), Attributes::default())
+    );
+    // The spans are applied after the tokens.
+    assert_eq!(
+      highlights.style_at(0, 3),
+      (comment.0, Some(Color::Grey), comment.2)
+    );
+    assert_eq!(highlights.syntax(0).unwrap().len(), 2);
+
+    highlights.clear_syntax(0..1);
+    assert!(highlights.syntax(0).is_none());
+    highlights.clear(0..1);
+    assert!(highlights.is_empty());
+  }
+
+  #[test]
+  fn color_schemes1() {
+    let mut color_schemes = ColorSchemes::new();
+    assert_eq!(color_schemes.current_name(), DEFAULT_COLOR_SCHEME);
+    let search = color_schemes.resolve("Search").unwrap();
+    assert_eq!(
+      search,
+      (Some(Color::Black), Some(Color::Yellow), Attributes::none())
+    );
+
+    // Register a color scheme, and switch to it.
+    let mut color_scheme = ColorScheme::default();
+    let rgb = Color::Rgb {
+      r: 0xff,
+      g: 0x80,
+      b: 0x00,
+    };
+    color_scheme.set(
+      "Search",
+      (Some(Color::Black), Some(rgb), Attributes::none()),
+    );
+    color_schemes.register("sunset", color_scheme);
+    assert_eq!(color_schemes.names(), vec![DEFAULT_COLOR_SCHEME, "sunset"]);
+    assert_eq!(color_schemes.resolve("Search"), Some(search));
+    let version = color_schemes.version();
+    color_schemes.switch("sunset").unwrap();
+    assert!(color_schemes.version() > version);
+    assert_eq!(color_schemes.current_name(), "sunset");
+    assert_eq!(
+      color_schemes.resolve("Search"),
+      Some((Some(Color::Black), Some(rgb), Attributes::none()))
+    );
+    // The other groups are same with the default.
+    assert_eq!(
+      color_schemes.resolve("Comment"),
+      ColorScheme::default().resolve("Comment")
+    );
+
+    let e = color_schemes.switch("unknown").unwrap_err();
+    assert_eq!(e.to_string(), "E185: Cannot find color scheme 'unknown'");
+    assert_eq!(color_schemes.current_name(), "sunset");
+  }
+
+  #[test]
+  fn parse1() {
+    assert_eq!(parse_color("red").unwrap(), Color::Red);
+    assert_eq!(parse_color("dark_grey").unwrap(), Color::DarkGrey);
+    assert_eq!(
+      parse_color("#ff8001").unwrap(),
+      Color::Rgb {
+        r: 255,
+        g: 128,
+        b: 1
+      }
+    );
+    assert!(parse_color("#ff80").is_err());
+    assert!(parse_color("#gggggg").is_err());
+    assert!(parse_color("unknown").is_err());
+    assert_eq!(parse_attribute("bold").unwrap(), Attribute::Bold);
+    assert!(parse_attribute("blink").is_err());
+  }
+}
@@ -0,0 +1,148 @@
+//! Recovery journals.
+//!
+//! The contents of the modified file buffers are periodically saved into the journal files (see
+//! [`EventLoop::init_autosave`](crate::evloop::EventLoop::init_autosave)), thus they can be
+//! recovered by [`BuffersManager::recover_buffer`](crate::buf::BuffersManager::recover_buffer)
+//! after the editor crashes. The journals are in the
+//! [`swap_dir`](crate::envar::global_config::GlobalConfig::swap_dir), each one is named by the
+//! hash of the file's absolute path.
+
+use crate::buf::BufferId;
+use crate::res::IoResult;
+
+use ropey::Rope;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use tracing::trace;
+
+/// The extension of the journal files.
+const JOURNAL_EXTENSION: &str = "journal";
+
+#[derive(Debug, Clone)]
+/// The contents of a modified buffer to save into its journal. The rope is cloned from the buffer,
+/// which is cheap, thus the buffer lock is not held while writing the journal.
+pub struct JournalSnapshot {
+  pub buf_id: BufferId,
+  /// The [`changed_tick`](crate::buf::Buffer::changed_tick) when the snapshot is taken.
+  pub changed_tick: usize,
+  /// The absolute file name of the buffer.
+  pub filename: PathBuf,
+  pub rope: Rope,
+}
+
+// The FNV-1a hash of the path, it's stable across the platforms and the compiler versions, thus
+// the journal can be found after restarting the editor.
+fn path_hash(path: &Path) -> u64 {
+  const OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+  const PRIME: u64 = 0x100000001b3;
+  path
+    .to_string_lossy()
+    .bytes()
+    .fold(OFFSET_BASIS, |hash, byte| {
+      (hash ^ byte as u64).wrapping_mul(PRIME)
+    })
+}
+
+/// Get the journal file path of the file in the journals directory.
+pub fn journal_path(dir: &Path, absolute_filename: &Path) -> PathBuf {
+  dir.join(format!(
+    "{:016x}.{}",
+    path_hash(absolute_filename),
+    JOURNAL_EXTENSION
+  ))
+}
+
+/// Write the snapshot into its journal. It's written to a temporary file then renamed, thus the
+/// previous journal is never truncated if the editor crashes in the middle.
+pub fn write_journal(dir: &Path, snapshot: &JournalSnapshot) -> IoResult<()> {
+  std::fs::create_dir_all(dir)?;
+  let path = journal_path(dir, &snapshot.filename);
+  let tmp_path = path.with_extension(format!("{}.tmp", JOURNAL_EXTENSION));
+  let result = (|| {
+    let mut fp = std::io::BufWriter::new(std::fs::File::create(&tmp_path)?);
+    snapshot.rope.write_to(&mut fp)?;
+    fp.into_inner()?.sync_all()?;
+    std::fs::rename(&tmp_path, &path)
+  })();
+  if result.is_err() {
+    let _ = std::fs::remove_file(&tmp_path);
+  }
+  trace!(
+    "Wrote journal {:?} of file {:?}: {:?}",
+    path,
+    snapshot.filename,
+    result
+  );
+  result
+}
+
+/// Read the contents of the journal.
+pub fn read_journal(path: &Path) -> IoResult<Rope> {
+  let fp = std::fs::File::open(path)?;
+  Rope::from_reader(std::io::BufReader::new(fp))
+}
+
+/// Remove the journal of the file, it's not an error if the journal doesn't exist.
+pub fn remove_journal(dir: &Path, absolute_filename: &Path) -> IoResult<()> {
+  match std::fs::remove_file(journal_path(dir, absolute_filename)) {
+    Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+    _ => Ok(()),
+  }
+}
+
+/// Find the journal of the file that is newer than the file, i.e. the buffer was not written
+/// after its contents are journaled. If the file doesn't exist, the journal is always newer.
+pub fn find_newer_journal(dir: &Path, absolute_filename: &Path) -> Option<PathBuf> {
+  let path = journal_path(dir, absolute_filename);
+  let journal_modified = std::fs::metadata(&path).ok()?.modified().ok()?;
+  let file_modified = std::fs::metadata(absolute_filename)
+    .and_then(|metadata| metadata.modified())
+    .unwrap_or(SystemTime::UNIX_EPOCH);
+  (journal_modified > file_modified).then_some(path)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use std::time::Duration;
+
+  #[test]
+  fn journal1() {
+    let tmpdir = assert_fs::TempDir::new().unwrap();
+    let dir = tmpdir.path().join("swap");
+    let filename = tmpdir.path().join("journal1.txt");
+    std::fs::write(&filename, "hello\n").unwrap();
+
+    // The path is hashed stably.
+    assert_eq!(
+      journal_path(Path::new("/swap"), Path::new("/tmp/a.txt")),
+      Path::new("/swap").join("424f3fb4184d0b01.journal")
+    );
+    assert_ne!(
+      journal_path(&dir, &filename),
+      journal_path(&dir, &tmpdir.path().join("journal2.txt"))
+    );
+    assert!(find_newer_journal(&dir, &filename).is_none());
+
+    std::thread::sleep(Duration::from_millis(20));
+    let snapshot = JournalSnapshot {
+      buf_id: 1,
+      changed_tick: 1,
+      filename: filename.clone(),
+      rope: Rope::from_str("hello\nworld\n"),
+    };
+    write_journal(&dir, &snapshot).unwrap();
+    let path = find_newer_journal(&dir, &filename).unwrap();
+    assert_eq!(read_journal(&path).unwrap().to_string(), "hello\nworld\n");
+
+    // The file is written after the journal.
+    std::thread::sleep(Duration::from_millis(20));
+    std::fs::write(&filename, "hello\nworld\n").unwrap();
+    assert!(find_newer_journal(&dir, &filename).is_none());
+
+    remove_journal(&dir, &filename).unwrap();
+    assert!(!path.exists());
+    remove_journal(&dir, &filename).unwrap();
+  }
+}
@@ -3,8 +3,12 @@
 use crate::defaults;
 
 // Re-export
+pub use ambiguous_width::AmbiguousWidth;
+pub use buf_type::BufType;
 pub use file_encoding::FileEncoding;
 
+pub mod ambiguous_width;
+pub mod buf_type;
 pub mod file_encoding;
 
 #[derive(Debug, Clone)]
@@ -12,6 +16,16 @@ pub mod file_encoding;
 pub struct BufferLocalOptions {
   tab_stop: u16,
   file_encoding: FileEncoding,
+  ambiguous_width: AmbiguousWidth,
+  modifiable: bool,
+  readonly: bool,
+  text_width: u16,
+  auto_indent: bool,
+  expand_tab: bool,
+  shift_width: u16,
+  backup: bool,
+  show_hidden: bool,
+  buf_type: BufType,
 }
 
 impl Default for BufferLocalOptions {
@@ -40,6 +54,86 @@ impl BufferLocalOptions {
   pub fn set_file_encoding(&mut self, value: FileEncoding) {
     self.file_encoding = value;
   }
+
+  pub fn ambiguous_width(&self) -> AmbiguousWidth {
+    self.ambiguous_width
+  }
+
+  pub fn set_ambiguous_width(&mut self, value: AmbiguousWidth) {
+    self.ambiguous_width = value;
+  }
+
+  pub fn modifiable(&self) -> bool {
+    self.modifiable
+  }
+
+  pub fn set_modifiable(&mut self, value: bool) {
+    self.modifiable = value;
+  }
+
+  pub fn readonly(&self) -> bool {
+    self.readonly
+  }
+
+  pub fn set_readonly(&mut self, value: bool) {
+    self.readonly = value;
+  }
+
+  pub fn text_width(&self) -> u16 {
+    self.text_width
+  }
+
+  pub fn set_text_width(&mut self, value: u16) {
+    self.text_width = value;
+  }
+
+  pub fn auto_indent(&self) -> bool {
+    self.auto_indent
+  }
+
+  pub fn set_auto_indent(&mut self, value: bool) {
+    self.auto_indent = value;
+  }
+
+  pub fn expand_tab(&self) -> bool {
+    self.expand_tab
+  }
+
+  pub fn set_expand_tab(&mut self, value: bool) {
+    self.expand_tab = value;
+  }
+
+  pub fn shift_width(&self) -> u16 {
+    self.shift_width
+  }
+
+  pub fn set_shift_width(&mut self, value: u16) {
+    self.shift_width = value;
+  }
+
+  pub fn backup(&self) -> bool {
+    self.backup
+  }
+
+  pub fn set_backup(&mut self, value: bool) {
+    self.backup = value;
+  }
+
+  pub fn show_hidden(&self) -> bool {
+    self.show_hidden
+  }
+
+  pub fn set_show_hidden(&mut self, value: bool) {
+    self.show_hidden = value;
+  }
+
+  pub fn buf_type(&self) -> BufType {
+    self.buf_type
+  }
+
+  pub fn set_buf_type(&mut self, value: BufType) {
+    self.buf_type = value;
+  }
 }
 
 #[derive(Debug, Clone)]
@@ -47,6 +141,16 @@ impl BufferLocalOptions {
 pub struct BufferLocalOptionsBuilder {
   tab_stop: u16,
   file_encoding: FileEncoding,
+  ambiguous_width: AmbiguousWidth,
+  modifiable: bool,
+  readonly: bool,
+  text_width: u16,
+  auto_indent: bool,
+  expand_tab: bool,
+  shift_width: u16,
+  backup: bool,
+  show_hidden: bool,
+  buf_type: BufType,
 }
 
 impl BufferLocalOptionsBuilder {
@@ -60,10 +164,70 @@ impl BufferLocalOptionsBuilder {
     self
   }
 
+  pub fn ambiguous_width(&mut self, value: AmbiguousWidth) -> &mut Self {
+    self.ambiguous_width = value;
+    self
+  }
+
+  pub fn modifiable(&mut self, value: bool) -> &mut Self {
+    self.modifiable = value;
+    self
+  }
+
+  pub fn readonly(&mut self, value: bool) -> &mut Self {
+    self.readonly = value;
+    self
+  }
+
+  pub fn text_width(&mut self, value: u16) -> &mut Self {
+    self.text_width = value;
+    self
+  }
+
+  pub fn auto_indent(&mut self, value: bool) -> &mut Self {
+    self.auto_indent = value;
+    self
+  }
+
+  pub fn expand_tab(&mut self, value: bool) -> &mut Self {
+    self.expand_tab = value;
+    self
+  }
+
+  pub fn shift_width(&mut self, value: u16) -> &mut Self {
+    self.shift_width = value;
+    self
+  }
+
+  pub fn backup(&mut self, value: bool) -> &mut Self {
+    self.backup = value;
+    self
+  }
+
+  pub fn show_hidden(&mut self, value: bool) -> &mut Self {
+    self.show_hidden = value;
+    self
+  }
+
+  pub fn buf_type(&mut self, value: BufType) -> &mut Self {
+    self.buf_type = value;
+    self
+  }
+
   pub fn build(&self) -> BufferLocalOptions {
     BufferLocalOptions {
       tab_stop: self.tab_stop,
       file_encoding: self.file_encoding,
+      ambiguous_width: self.ambiguous_width,
+      modifiable: self.modifiable,
+      readonly: self.readonly,
+      text_width: self.text_width,
+      auto_indent: self.auto_indent,
+      expand_tab: self.expand_tab,
+      shift_width: self.shift_width,
+      backup: self.backup,
+      show_hidden: self.show_hidden,
+      buf_type: self.buf_type,
     }
   }
 }
@@ -73,6 +237,16 @@ impl Default for BufferLocalOptionsBuilder {
     BufferLocalOptionsBuilder {
       tab_stop: defaults::buf::TAB_STOP,
       file_encoding: defaults::buf::FILE_ENCODING,
+      ambiguous_width: defaults::buf::AMBIGUOUS_WIDTH,
+      modifiable: defaults::buf::MODIFIABLE,
+      readonly: defaults::buf::READONLY,
+      text_width: defaults::buf::TEXT_WIDTH,
+      auto_indent: defaults::buf::AUTO_INDENT,
+      expand_tab: defaults::buf::EXPAND_TAB,
+      shift_width: defaults::buf::SHIFT_WIDTH,
+      backup: defaults::buf::BACKUP,
+      show_hidden: defaults::buf::SHOW_HIDDEN,
+      buf_type: defaults::buf::BUF_TYPE,
     }
   }
 }
@@ -86,5 +260,41 @@ mod tests {
     let opt1 = BufferLocalOptions::default();
     let opt2 = BufferLocalOptionsBuilder::default().build();
     assert_eq!(opt1.tab_stop(), opt2.tab_stop());
+    assert_eq!(opt1.modifiable(), opt2.modifiable());
+    assert_eq!(opt1.readonly(), opt2.readonly());
+    assert!(opt1.modifiable());
+    assert!(!opt1.readonly());
+    assert_eq!(opt1.text_width(), 0);
+    assert!(opt1.auto_indent());
+    assert!(!opt1.expand_tab());
+    assert_eq!(opt1.shift_width(), 8);
+    assert!(!opt1.backup());
+    assert_eq!(opt1.ambiguous_width(), AmbiguousWidth::Single);
+    assert!(!opt1.show_hidden());
+    assert_eq!(opt1.buf_type(), BufType::Normal);
+  }
+
+  #[test]
+  fn builder1() {
+    let opt = BufferLocalOptionsBuilder::default()
+      .modifiable(false)
+      .readonly(true)
+      .text_width(20)
+      .expand_tab(true)
+      .shift_width(4)
+      .backup(true)
+      .ambiguous_width(AmbiguousWidth::Double)
+      .show_hidden(true)
+      .buf_type(BufType::NoFile)
+      .build();
+    assert!(!opt.modifiable());
+    assert!(opt.readonly());
+    assert_eq!(opt.text_width(), 20);
+    assert!(opt.expand_tab());
+    assert_eq!(opt.shift_width(), 4);
+    assert!(opt.backup());
+    assert_eq!(opt.ambiguous_width(), AmbiguousWidth::Double);
+    assert!(opt.show_hidden());
+    assert_eq!(opt.buf_type(), BufType::NoFile);
   }
 }
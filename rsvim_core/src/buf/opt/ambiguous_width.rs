@@ -0,0 +1,60 @@
+//! The "ambiguous-width" option for Vim buffer.
+
+use std::fmt::Display;
+use std::string::ToString;
+
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+/// The display width of the East Asian Ambiguous characters, i.e. `§`, Greek letters and box
+/// drawing characters, see: <https://www.unicode.org/reports/tr11/#Ambiguous>.
+pub enum AmbiguousWidth {
+  /// Ambiguous characters occupy 1 cell, as most Western terminals render them.
+  Single,
+  /// Ambiguous characters occupy 2 cells, as CJK terminals render them.
+  Double,
+}
+
+impl Display for AmbiguousWidth {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      AmbiguousWidth::Single => write!(f, "single"),
+      AmbiguousWidth::Double => write!(f, "double"),
+    }
+  }
+}
+
+impl TryFrom<&str> for AmbiguousWidth {
+  type Error = String;
+
+  fn try_from(value: &str) -> Result<Self, Self::Error> {
+    let lower_value = value.to_lowercase();
+    match lower_value.as_str() {
+      "single" => Ok(AmbiguousWidth::Single),
+      "double" => Ok(AmbiguousWidth::Double),
+      _ => Err("Unknown AmbiguousWidth value".to_string()),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn display1() {
+    assert_eq!(format!("{}", AmbiguousWidth::Single), "single");
+    assert_eq!(format!("{}", AmbiguousWidth::Double), "double");
+  }
+
+  #[test]
+  fn try_from1() {
+    assert_eq!(
+      AmbiguousWidth::try_from("Single"),
+      Ok(AmbiguousWidth::Single)
+    );
+    assert_eq!(
+      AmbiguousWidth::try_from("double"),
+      Ok(AmbiguousWidth::Double)
+    );
+    assert!(AmbiguousWidth::try_from("triple").is_err());
+  }
+}
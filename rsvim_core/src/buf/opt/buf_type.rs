@@ -0,0 +1,77 @@
+//! The "buf-type" option for Vim buffer.
+
+use std::fmt::Display;
+use std::string::ToString;
+
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq, Default)]
+/// The type of the buffer, i.e. how it's related to a file.
+/// See: <https://vimhelp.org/options.txt.html#%27buftype%27>.
+pub enum BufType {
+  /// `""`, the normal buffer backed by a file.
+  #[default]
+  Normal,
+  /// `nofile`, the buffer is not related to a file and never written, i.e. the scratch buffer for
+  /// plugin UIs.
+  NoFile,
+  /// `nowrite`, the buffer is not written.
+  NoWrite,
+  /// `help`, the help buffer.
+  Help,
+}
+
+impl BufType {
+  /// Whether the buffer can be written, the `nofile` and `nowrite` buffers cannot.
+  pub fn writable(&self) -> bool {
+    !matches!(self, BufType::NoFile | BufType::NoWrite)
+  }
+}
+
+impl Display for BufType {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      BufType::Normal => write!(f, ""),
+      BufType::NoFile => write!(f, "nofile"),
+      BufType::NoWrite => write!(f, "nowrite"),
+      BufType::Help => write!(f, "help"),
+    }
+  }
+}
+
+impl TryFrom<&str> for BufType {
+  type Error = String;
+
+  fn try_from(value: &str) -> Result<Self, Self::Error> {
+    match value {
+      "" => Ok(BufType::Normal),
+      "nofile" => Ok(BufType::NoFile),
+      "nowrite" => Ok(BufType::NoWrite),
+      "help" => Ok(BufType::Help),
+      _ => Err("Unknown BufType value".to_string()),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn display1() {
+    assert_eq!(format!("{}", BufType::Normal), "");
+    assert_eq!(format!("{}", BufType::NoFile), "nofile");
+    assert_eq!(format!("{}", BufType::NoWrite), "nowrite");
+    assert_eq!(format!("{}", BufType::Help), "help");
+  }
+
+  #[test]
+  fn try_from1() {
+    assert_eq!(BufType::try_from(""), Ok(BufType::Normal));
+    assert_eq!(BufType::try_from("nofile"), Ok(BufType::NoFile));
+    assert_eq!(BufType::try_from("help"), Ok(BufType::Help));
+    assert!(BufType::try_from("quickfix").is_err());
+    assert!(BufType::Normal.writable());
+    assert!(BufType::Help.writable());
+    assert!(!BufType::NoFile.writable());
+    assert!(!BufType::NoWrite.writable());
+  }
+}
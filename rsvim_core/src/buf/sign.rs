@@ -0,0 +1,170 @@
+//! Signs of the buffer lines, i.e. the glyphs placed by the plugins (LSP diagnostics, git changes,
+//! etc) and rendered in the 'signcolumn'.
+//!
+//! See: <https://vimhelp.org/sign.txt.html>.
+
+use crate::buf::LineChange;
+
+use compact_str::CompactString;
+use std::collections::BTreeMap;
+
+/// The default sign priority, same with Vim's `sign_place()`.
+pub const DEFAULT_SIGN_PRIORITY: u32 = 10;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// The sign placed on a line.
+pub struct Sign {
+  group: CompactString,
+  glyph: CompactString,
+  priority: u32,
+  line_highlight: Option<CompactString>,
+}
+
+impl Sign {
+  /// Make a sign in the `group`, i.e. the namespace of the plugin that places it. The `glyph` is
+  /// rendered in the 'signcolumn', only its first 2 cells are displayed.
+  pub fn new(group: &str, glyph: &str, priority: u32) -> Self {
+    Sign {
+      group: CompactString::new(group),
+      glyph: CompactString::new(glyph),
+      priority,
+      line_highlight: None,
+    }
+  }
+
+  /// Tint the whole line with the highlight group, same with the `linehl` of Vim's
+  /// `sign_define()`.
+  pub fn with_line_highlight(self, group: &str) -> Self {
+    Sign {
+      line_highlight: Some(CompactString::new(group)),
+      ..self
+    }
+  }
+
+  pub fn group(&self) -> &str {
+    &self.group
+  }
+
+  pub fn glyph(&self) -> &str {
+    &self.glyph
+  }
+
+  /// The sign with the higher priority is displayed if there're multiple signs on a line.
+  pub fn priority(&self) -> u32 {
+    self.priority
+  }
+
+  pub fn line_highlight(&self) -> Option<&str> {
+    self.line_highlight.as_deref()
+  }
+}
+
+#[derive(Debug, Clone, Default)]
+/// The signs of a buffer, indexed by the line index. A line has at most one sign of each group,
+/// and the signs of a line are sorted by the priority, from high to low.
+///
+/// The signs are moved with the lines by the buffer changes, see [`Signs::adjust`].
+pub struct Signs {
+  lines: BTreeMap<usize, Vec<Sign>>,
+}
+
+impl Signs {
+  pub fn new() -> Self {
+    Signs::default()
+  }
+
+  /// Whether there's no sign, i.e. the "auto" 'signcolumn' is hidden.
+  pub fn is_empty(&self) -> bool {
+    self.lines.is_empty()
+  }
+
+  /// Get the signs of the line, from high priority to low.
+  pub fn get(&self, line_idx: usize) -> Option<&Vec<Sign>> {
+    self.lines.get(&line_idx)
+  }
+
+  /// Get the sign displayed on the line, i.e. the one with the highest priority. For the same
+  /// priority, the last placed one wins.
+  pub fn top(&self, line_idx: usize) -> Option<&Sign> {
+    self.get(line_idx).and_then(|signs| signs.first())
+  }
+
+  /// Get the line highlight group of the line, from the sign with the highest priority that has
+  /// one.
+  pub fn line_highlight(&self, line_idx: usize) -> Option<&str> {
+    self
+      .get(line_idx)
+      .and_then(|signs| signs.iter().find_map(|sign| sign.line_highlight()))
+  }
+
+  /// Place the sign on the line, it replaces the sign of the same group on the line.
+  pub fn place(&mut self, line_idx: usize, sign: Sign) {
+    let signs = self.lines.entry(line_idx).or_default();
+    signs.retain(|s| s.group != sign.group);
+    let pos = signs
+      .iter()
+      .position(|s| s.priority <= sign.priority)
+      .unwrap_or(signs.len());
+    signs.insert(pos, sign);
+  }
+
+  /// Remove the sign of the group on the line.
+  pub fn unplace(&mut self, line_idx: usize, group: &str) -> Option<Sign> {
+    let signs = self.lines.get_mut(&line_idx)?;
+    let pos = signs.iter().position(|s| s.group == group)?;
+    let sign = signs.remove(pos);
+    if signs.is_empty() {
+      self.lines.remove(&line_idx);
+    }
+    Some(sign)
+  }
+
+  /// Move the signs after the changed line by the line change, the signs on the removed lines are
+  /// dropped.
+  pub fn adjust(&mut self, line_change: &LineChange) {
+    if line_change.removed == 0 && line_change.inserted == 0 {
+      return;
+    }
+    let removed_end = line_change.line_idx + line_change.removed;
+    let moved = self.lines.split_off(&(line_change.line_idx + 1));
+    for (line_idx, signs) in moved {
+      if line_idx > removed_end {
+        self.lines.insert(line_change.adjust(line_idx), signs);
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn place1() {
+    let mut signs = Signs::new();
+    assert!(signs.is_empty());
+
+    // The higher priority wins, regardless of the placing order.
+    signs.place(0, Sign::new("git", "+", 5));
+    signs.place(0, Sign::new("lsp", "E", 20).with_line_highlight("ErrorMsg"));
+    signs.place(0, Sign::new("mark", "a", 10));
+    assert_eq!(signs.top(0).unwrap().glyph(), "E");
+    let groups: Vec<&str> = signs.get(0).unwrap().iter().map(|s| s.group()).collect();
+    assert_eq!(groups, vec!["lsp", "mark", "git"]);
+    assert_eq!(signs.line_highlight(0), Some("ErrorMsg"));
+
+    // The same priority, the last placed one wins. The same group is replaced.
+    signs.place(0, Sign::new("git", "~", 20));
+    assert_eq!(signs.top(0).unwrap().glyph(), "~");
+    assert_eq!(signs.get(0).unwrap().len(), 3);
+    assert_eq!(signs.line_highlight(0), Some("ErrorMsg"));
+
+    assert_eq!(signs.unplace(0, "lsp").unwrap().glyph(), "E");
+    assert_eq!(signs.unplace(0, "lsp"), None);
+    assert_eq!(signs.line_highlight(0), None);
+    signs.unplace(0, "git");
+    signs.unplace(0, "mark");
+    assert!(signs.get(0).is_none());
+    assert!(signs.is_empty());
+  }
+}
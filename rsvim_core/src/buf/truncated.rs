@@ -0,0 +1,111 @@
+//! The truncated lines of the buffer, i.e. the beginning of the long lines materialized as
+//! strings for the word-break layout.
+
+use crate::buf::Buffer;
+
+use ropey::RopeSlice;
+#[cfg(test)]
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// The max cached truncated lines of a buffer, the cache is cleared when it's full.
+const TRUNCATED_LINES_CACHE_SIZE: usize = 1024;
+
+#[derive(Debug, Default)]
+/// The cached truncated lines of a buffer, keyed by the line index, the start char index and the
+/// max bytes. They're dropped once the buffer is changed, i.e. the
+/// [`changed_tick`](Buffer::changed_tick) is changed.
+pub(super) struct TruncatedLines {
+  changed_tick: usize,
+  lines: HashMap<(usize, usize, usize), Arc<str>>,
+}
+
+impl Buffer {
+  /// Get the chars of the line from `start_c_idx`, truncated after `max_bytes` bytes. The
+  /// truncated lines are cached by the buffer, thus the following renders reuse them until the
+  /// buffer is changed.
+  ///
+  /// # Panics
+  ///
+  /// If `line_idx` is out of bounds.
+  pub fn truncated_line(&self, line_idx: usize, start_c_idx: usize, max_bytes: usize) -> Arc<str> {
+    let mut truncated_lines = self.truncated_lines.lock();
+    if truncated_lines.changed_tick != self.changed_tick() {
+      truncated_lines.changed_tick = self.changed_tick();
+      truncated_lines.lines.clear();
+    }
+
+    let key = (line_idx, start_c_idx, max_bytes);
+    if let Some(truncated_line) = truncated_lines.lines.get(&key) {
+      return truncated_line.clone();
+    }
+    if truncated_lines.lines.len() >= TRUNCATED_LINES_CACHE_SIZE {
+      truncated_lines.lines.clear();
+    }
+    let line = self.rope.line(line_idx);
+    let truncated_line: Arc<str> = Arc::from(truncate_line(&line, start_c_idx, max_bytes));
+    truncated_lines.lines.insert(key, truncated_line.clone());
+    truncated_line
+  }
+}
+
+fn truncate_line(line: &RopeSlice, start_c_idx: usize, max_bytes: usize) -> String {
+  #[cfg(test)]
+  TRUNCATE_LINE_COUNT.with(|count| count.set(count.get() + 1));
+
+  let mut builder = String::new();
+  builder.reserve(max_bytes);
+  for c in line.chars_at(start_c_idx) {
+    if builder.len() > max_bytes {
+      return builder;
+    }
+    builder.push(c);
+  }
+  builder
+}
+
+#[cfg(test)]
+thread_local! {
+  static TRUNCATE_LINE_COUNT: Cell<usize> = const { Cell::new(0) };
+}
+
+#[cfg(test)]
+/// How many times the truncated lines are materialized in current thread.
+pub fn truncate_line_count() -> usize {
+  TRUNCATE_LINE_COUNT.with(|count| count.get())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use crate::buf::BuffersManager;
+  use crate::{rlock, wlock};
+
+  #[test]
+  fn truncated_line1() {
+    let mut bufs = BuffersManager::new();
+    let buf_id = bufs.new_empty_buffer();
+    let buffer = bufs.get(&buf_id).unwrap().clone();
+    wlock!(buffer).insert(0, "hello world\n").unwrap();
+
+    let first = rlock!(buffer).truncated_line(0, 6, 3);
+    assert_eq!(first.as_ref(), "worl");
+    assert!(Arc::ptr_eq(&first, &rlock!(buffer).truncated_line(0, 6, 3)));
+
+    // The buffer is changed, the line is truncated again.
+    wlock!(buffer).insert(0, "A").unwrap();
+    let second = rlock!(buffer).truncated_line(0, 6, 3);
+    assert_eq!(second.as_ref(), " wor");
+
+    // The removed buffer drops its truncated lines, a new buffer never sees them.
+    assert!(bufs.remove(&buf_id).is_some());
+    let buf_id = bufs.new_empty_buffer();
+    let buffer = bufs.get(&buf_id).unwrap().clone();
+    wlock!(buffer).insert(0, "Ahello world\n").unwrap();
+    let count = truncate_line_count();
+    assert_eq!(rlock!(buffer).truncated_line(0, 6, 3).as_ref(), " wor");
+    assert_eq!(truncate_line_count() - count, 1);
+  }
+}
@@ -0,0 +1,201 @@
+//! The display width index of the buffer lines, i.e. the prefix sums of the chars display width.
+
+use crate::buf::{Buffer, LineChange};
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+/// The chars count between two checkpoints of the width index.
+pub const BUF_WINDEX_STEP: usize = 256;
+
+/// The max cached width indexes of a buffer, the cache is cleared when it's full.
+const BUF_WINDEX_CACHE_SIZE: usize = 128;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// The display width index of a long line, i.e. the start display column of the chars at every
+/// [`BUF_WINDEX_STEP`], thus finding the char at a display column only visits the chars after the
+/// nearest checkpoint, instead of all the chars before it.
+///
+/// The indexes are built lazily and cached by the [`Buffer`], an edit only drops the indexes of
+/// the lines it changes, thus the following renders and scrolls reuse them.
+pub struct BufWindex {
+  dcolumns: Vec<usize>,
+}
+
+impl BufWindex {
+  fn new(buffer: &Buffer, line_idx: usize) -> Self {
+    let line = buffer.rope.line(line_idx);
+    let mut dcolumns = Vec::with_capacity(line.len_chars() / BUF_WINDEX_STEP + 1);
+    let mut dcol = 0_usize;
+    for (i, c) in line.chars().enumerate() {
+      if i % BUF_WINDEX_STEP == 0 {
+        dcolumns.push(dcol);
+      }
+      dcol += buffer.char_width(c);
+    }
+    BufWindex { dcolumns }
+  }
+
+  /// Get the last checkpoint starts at or before the display column, returns its char index and
+  /// start display column.
+  pub fn checkpoint(&self, dcolumn: usize) -> (usize, usize) {
+    let k = self
+      .dcolumns
+      .partition_point(|dcol| *dcol <= dcolumn)
+      .saturating_sub(1);
+    (
+      k * BUF_WINDEX_STEP,
+      self.dcolumns.get(k).copied().unwrap_or(0),
+    )
+  }
+}
+
+impl Buffer {
+  /// Get the cached width index of the line, or `None` if it's not built yet.
+  pub fn windex(&self, line_idx: usize) -> Option<Arc<BufWindex>> {
+    self.windexes.lock().get(&line_idx).cloned()
+  }
+
+  /// Find the first char starts at or after the `dcolumn` in the line, returns its char index and
+  /// start display column. If the line is not that wide, the char index is the chars length of
+  /// the line.
+  ///
+  /// For a long line (more than [`BUF_WINDEX_STEP`] chars), it starts from the nearest checkpoint
+  /// of the cached [`BufWindex`], thus the chars visited are bounded regardless of the line
+  /// length. The large buffer (see [`Buffer::is_large`]) doesn't cache the indexes, it visits the
+  /// chars from the line start until the display column, i.e. bounded by the window width.
+  ///
+  /// # Panics
+  ///
+  /// If `line_idx` is out of bounds.
+  pub fn char_at_dcolumn(&self, line_idx: usize, dcolumn: usize) -> (usize, usize) {
+    if dcolumn == 0 {
+      return (0, 0);
+    }
+
+    let line = self.rope.line(line_idx);
+    let (mut c_idx, mut dcol) = if line.len_chars() > BUF_WINDEX_STEP && !self.is_large() {
+      let mut windexes = self.windexes.lock();
+      if !windexes.contains_key(&line_idx) && windexes.len() >= BUF_WINDEX_CACHE_SIZE {
+        windexes.clear();
+      }
+      windexes
+        .entry(line_idx)
+        .or_insert_with(|| Arc::new(BufWindex::new(self, line_idx)))
+        .checkpoint(dcolumn)
+    } else {
+      (0, 0)
+    };
+
+    for c in line.chars_at(c_idx) {
+      if dcol >= dcolumn {
+        break;
+      }
+      dcol += self.char_width(c);
+      c_idx += 1;
+    }
+    (c_idx, dcol)
+  }
+
+  // Drop the width indexes of the lines changed by the edit, and move the indexes of the lines
+  // after it.
+  pub(super) fn invalidate_windexes(&mut self, line_change: &LineChange) {
+    let windexes = self.windexes.get_mut();
+    let moved = windexes.split_off(&line_change.line_idx);
+    for (line_idx, windex) in moved {
+      if line_idx > line_change.line_idx + line_change.removed {
+        windexes.insert(line_change.adjust(line_idx), windex);
+      }
+    }
+  }
+
+  // Drop all the width indexes, i.e. the options of chars width are changed.
+  pub(super) fn clear_windexes(&mut self) {
+    self.windexes.get_mut().clear();
+  }
+}
+
+/// The cached width indexes of a buffer, keyed by the line index.
+pub(super) type BufWindexes = BTreeMap<usize, Arc<BufWindex>>;
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use crate::envar;
+  use crate::test::buf::make_buffer_from_lines;
+  use crate::{rlock, wlock};
+
+  #[test]
+  fn checkpoint1() {
+    // Each wide char is 2 columns.
+    let line = "好".repeat(BUF_WINDEX_STEP * 2 + 10) + "\n";
+    let buffer = make_buffer_from_lines(vec![line.as_str()]);
+    let buffer = rlock!(buffer);
+    let windex = BufWindex::new(&buffer, 0);
+    assert_eq!(windex.checkpoint(0), (0, 0));
+    assert_eq!(windex.checkpoint(BUF_WINDEX_STEP * 2 - 1), (0, 0));
+    assert_eq!(
+      windex.checkpoint(BUF_WINDEX_STEP * 2),
+      (BUF_WINDEX_STEP, BUF_WINDEX_STEP * 2)
+    );
+    assert_eq!(
+      windex.checkpoint(usize::MAX),
+      (BUF_WINDEX_STEP * 2, BUF_WINDEX_STEP * 4)
+    );
+
+    // The char at the display column, a wide char is not split.
+    assert_eq!(buffer.char_at_dcolumn(0, 0), (0, 0));
+    assert_eq!(buffer.char_at_dcolumn(0, 1001), (501, 1002));
+    assert_eq!(buffer.char_at_dcolumn(0, 1002), (501, 1002));
+    // Short line is not indexed.
+    let buffer = make_buffer_from_lines(vec!["hello\n"]);
+    let buffer = rlock!(buffer);
+    assert_eq!(buffer.char_at_dcolumn(0, 3), (3, 3));
+    assert_eq!(buffer.char_at_dcolumn(0, 5), (5, 5));
+    assert!(buffer.windex(0).is_none());
+  }
+
+  #[test]
+  fn invalidate1() {
+    let line = "x".repeat(BUF_WINDEX_STEP * 2) + "\n";
+    let lines: Vec<&str> = (0..5).map(|_| line.as_str()).collect();
+    let buffer = make_buffer_from_lines(lines);
+    let mut buffer = wlock!(buffer);
+    for line_idx in 0..5 {
+      buffer.char_at_dcolumn(line_idx, 300);
+    }
+    let windexes: Vec<Arc<BufWindex>> = (0..5).map(|l| buffer.windex(l).unwrap()).collect();
+
+    // Edit inside line 1, only its index is dropped.
+    let char_idx = buffer.line_to_char(1);
+    buffer.insert(char_idx, "abc").unwrap();
+    assert!(Arc::ptr_eq(&buffer.windex(0).unwrap(), &windexes[0]));
+    assert!(buffer.windex(1).is_none());
+    assert!(Arc::ptr_eq(&buffer.windex(2).unwrap(), &windexes[2]));
+
+    // Join line 2 and 3, the indexes after them are moved up.
+    let char_idx = buffer.line_to_char(3) - 1;
+    buffer.remove(char_idx..char_idx + 1).unwrap();
+    assert!(buffer.windex(2).is_none());
+    assert!(Arc::ptr_eq(&buffer.windex(3).unwrap(), &windexes[4]));
+    assert!(buffer.windex(4).is_none());
+
+    // Change the tab stop, all the indexes are dropped.
+    buffer.set_tab_stop(4);
+    assert!(buffer.windex(0).is_none());
+    assert!(buffer.windex(3).is_none());
+  }
+
+  #[test]
+  fn large1() {
+    let line = "好".repeat(BUF_WINDEX_STEP * 2) + "\n";
+    let buffer = make_buffer_from_lines(vec![line.as_str()]);
+    let mut buffer = wlock!(buffer);
+    buffer.set_large(true);
+
+    // The large buffer is not indexed, the result is the same.
+    assert_eq!(buffer.char_at_dcolumn(0, 1001), (501, 1002));
+    assert!(buffer.windex(0).is_none());
+  }
+}
@@ -0,0 +1,324 @@
+//! The word primitives of a buffer, i.e. the targets of `w`/`b`/`e` and `W`/`B`/`E` motions.
+//!
+//! See: <https://vimhelp.org/motion.txt.html#word-motions>.
+
+use crate::buf::Buffer;
+
+use std::iter;
+use unicode_segmentation::UnicodeSegmentation;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+/// The char class for word motions.
+///
+/// A word is a sequence of chars in the same class (except blank), and it never crosses the line
+/// break. For `WORD` motions, i.e. `W`/`B`/`E`, all the non-blank chars are in the same class.
+/// See: <https://vimhelp.org/motion.txt.html#word>.
+pub enum CharClass {
+  /// Whitespaces, the line breaks are also blanks between lines.
+  Blank,
+  /// Non-blank chars which are not word chars, i.e. `.`/`(`/`,`.
+  Punctuation,
+  /// Letters, digits and `_`, i.e. the chars in the Unicode words.
+  Word,
+  /// CJK ideographs, i.e. `漢字`. Same with Vim they're word chars in a class of their own, thus
+  /// a run of ideographs is a word, separated from the adjacent letters and digits.
+  Cjk,
+}
+
+/// Get the char class, same with Vim the ASCII chars follows the default 'iskeyword', i.e.
+/// `@,48-57,_`. The CJK ideographs are in the [`Cjk`](CharClass::Cjk) class, the other non-ASCII
+/// chars are word chars if they're in the Unicode words, i.e. letters and digits.
+pub fn char_class(c: char, big_word: bool) -> CharClass {
+  if c.is_whitespace() {
+    CharClass::Blank
+  } else if big_word || c == '_' || c.is_ascii_alphanumeric() {
+    CharClass::Word
+  } else if c.is_ascii() {
+    CharClass::Punctuation
+  } else if is_cjk_ideograph(c) {
+    CharClass::Cjk
+  } else {
+    let mut s = [0_u8; 4];
+    if c.encode_utf8(&mut s).unicode_words().next().is_some() {
+      CharClass::Word
+    } else {
+      CharClass::Punctuation
+    }
+  }
+}
+
+// The CJK unified ideographs (with extension A and the supplementary planes) and the
+// compatibility ideographs.
+fn is_cjk_ideograph(c: char) -> bool {
+  matches!(
+    c,
+    '\u{3400}'..='\u{4DBF}' | '\u{4E00}'..='\u{9FFF}' | '\u{F900}'..='\u{FAFF}'
+      | '\u{20000}'..='\u{3FFFF}'
+  )
+}
+
+// A position in the buffer, i.e. `(line_idx, char_idx, char)`. The char is `None` at the line
+// break, i.e. `char_idx` is the length of the line, thus an empty line has only the line break.
+type Item = (usize, usize, Option<char>);
+
+impl Buffer {
+  /// Get the start of next word after `(line_idx, char_idx)`, i.e. `w`/`W`. It skips the rest of
+  /// current word and the blanks, the motion crosses lines, and an empty line is also a word.
+  ///
+  /// It returns `None` if there's no more words.
+  pub fn next_word_start(
+    &self,
+    line_idx: usize,
+    char_idx: usize,
+    big_word: bool,
+  ) -> Option<(usize, usize)> {
+    let mut items = self.items_forward(line_idx, char_idx);
+    let mut class = match items.next() {
+      Some((_, _, Some(c))) => char_class(c, big_word),
+      _ => CharClass::Blank,
+    };
+    for (l, i, c) in items {
+      match c {
+        Some(c) => {
+          let next_class = char_class(c, big_word);
+          if next_class != CharClass::Blank && next_class != class {
+            return Some((l, i));
+          }
+          class = next_class;
+        }
+        None if i == 0 && l != line_idx => return Some((l, i)),
+        None => class = CharClass::Blank,
+      }
+    }
+    None
+  }
+
+  /// Get the start of the word before `(line_idx, char_idx)`, i.e. `b`/`B`. It moves back at
+  /// least 1 char and skips the blanks, then moves to the start of the word. An empty line is
+  /// also a word.
+  ///
+  /// It returns `None` if there's no word before it.
+  pub fn prev_word_start(
+    &self,
+    line_idx: usize,
+    char_idx: usize,
+    big_word: bool,
+  ) -> Option<(usize, usize)> {
+    let mut items = self.items_backward(line_idx, char_idx).peekable();
+    let (l, i, class) = loop {
+      match items.next()? {
+        (l, 0, None) => return Some((l, 0)),
+        (l, i, Some(c)) if !c.is_whitespace() => break (l, i, char_class(c, big_word)),
+        _ => {}
+      }
+    };
+    let mut start = (l, i);
+    while let Some((l, i, Some(c))) = items.peek() {
+      if char_class(*c, big_word) != class {
+        break;
+      }
+      start = (*l, *i);
+      items.next();
+    }
+    Some(start)
+  }
+
+  /// Get the end of the word after `(line_idx, char_idx)`, i.e. `e`/`E`. It moves forward at
+  /// least 1 char and skips the blanks (including empty lines), then moves to the end of the
+  /// word.
+  ///
+  /// It returns `None` if there's no more words.
+  pub fn next_word_end(
+    &self,
+    line_idx: usize,
+    char_idx: usize,
+    big_word: bool,
+  ) -> Option<(usize, usize)> {
+    let mut items = self.items_forward(line_idx, char_idx).skip(1).peekable();
+    let (l, i, class) = loop {
+      match items.next()? {
+        (l, i, Some(c)) if !c.is_whitespace() => break (l, i, char_class(c, big_word)),
+        _ => {}
+      }
+    };
+    let mut end = (l, i);
+    while let Some((l, i, Some(c))) = items.peek() {
+      if char_class(*c, big_word) != class {
+        break;
+      }
+      end = (*l, *i);
+      items.next();
+    }
+    Some(end)
+  }
+
+  // The positions from `(line_idx, char_idx)` (included) to the end of buffer.
+  fn items_forward(&self, line_idx: usize, char_idx: usize) -> impl Iterator<Item = Item> + '_ {
+    (line_idx..=self.last_line_idx()).flat_map(move |l| {
+      let len = self.line_len_chars(l);
+      let start = if l == line_idx {
+        std::cmp::min(char_idx, len)
+      } else {
+        0
+      };
+      self
+        .rope
+        .line(l)
+        .chars_at(start)
+        .take(len - start)
+        .enumerate()
+        .map(move |(i, c)| (l, start + i, Some(c)))
+        .chain(iter::once((l, len, None)))
+    })
+  }
+
+  // The positions from `(line_idx, char_idx)` (excluded) to the start of buffer, in reverse
+  // order.
+  fn items_backward(&self, line_idx: usize, char_idx: usize) -> impl Iterator<Item = Item> + '_ {
+    let line_idx = std::cmp::min(line_idx, self.last_line_idx());
+    (0..=line_idx).rev().flat_map(move |l| {
+      let len = self.line_len_chars(l);
+      let (end, line_break) = if l == line_idx {
+        (std::cmp::min(char_idx, len), None)
+      } else {
+        (len, Some((l, len, None)))
+      };
+      line_break.into_iter().chain(
+        self
+          .rope
+          .line(l)
+          .chars_at(end)
+          .reversed()
+          .take(end)
+          .enumerate()
+          .map(move |(i, c)| (l, end - 1 - i, Some(c))),
+      )
+    })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use crate::envar;
+  use crate::rlock;
+  use crate::test::buf::make_buffer_from_lines;
+
+  // Apply the word primitive repeatedly from the position until there's no more words, and
+  // collect the landing positions.
+  fn collect_words(
+    lines: Vec<&str>,
+    start: (usize, usize),
+    f: fn(&Buffer, usize, usize, bool) -> Option<(usize, usize)>,
+    big_word: bool,
+  ) -> Vec<(usize, usize)> {
+    let buffer = make_buffer_from_lines(lines);
+    let buffer = rlock!(buffer);
+    let mut result = vec![];
+    let mut pos = start;
+    while let Some(next) = f(&buffer, pos.0, pos.1, big_word) {
+      result.push(next);
+      pos = next;
+    }
+    result
+  }
+
+  #[test]
+  fn char_class1() {
+    assert_eq!(char_class(' ', false), CharClass::Blank);
+    assert_eq!(char_class('\t', true), CharClass::Blank);
+    assert_eq!(char_class('a', false), CharClass::Word);
+    assert_eq!(char_class('_', false), CharClass::Word);
+    assert_eq!(char_class('9', false), CharClass::Word);
+    assert_eq!(char_class('.', false), CharClass::Punctuation);
+    assert_eq!(char_class('.', true), CharClass::Word);
+    assert_eq!(char_class('é', false), CharClass::Word);
+    assert_eq!(char_class('好', false), CharClass::Cjk);
+    assert_eq!(char_class('好', true), CharClass::Word);
+    assert_eq!(char_class('，', false), CharClass::Punctuation);
+  }
+
+  #[test]
+  fn punctuation1() {
+    // 0         1
+    // 0123456789012
+    // foo.bar(baz)
+    let lines = vec!["foo.bar(baz)\n"];
+    assert_eq!(
+      collect_words(lines.clone(), (0, 0), Buffer::next_word_start, false),
+      vec![(0, 3), (0, 4), (0, 7), (0, 8), (0, 11)]
+    );
+    assert_eq!(
+      collect_words(lines.clone(), (0, 11), Buffer::prev_word_start, false),
+      vec![(0, 8), (0, 7), (0, 4), (0, 3), (0, 0)]
+    );
+    assert_eq!(
+      collect_words(lines.clone(), (0, 0), Buffer::next_word_end, false),
+      vec![(0, 2), (0, 3), (0, 6), (0, 7), (0, 10), (0, 11)]
+    );
+    // The WORD is the whole line.
+    assert!(collect_words(lines.clone(), (0, 0), Buffer::next_word_start, true).is_empty());
+    assert_eq!(
+      collect_words(lines, (0, 0), Buffer::next_word_end, true),
+      vec![(0, 11)]
+    );
+  }
+
+  #[test]
+  fn cjk1() {
+    // The ideographs run is a word, separated from the letters, and the fullwidth punctuation is
+    // another word.
+    let lines = vec!["abc漢字，好 x\n"];
+    assert_eq!(
+      collect_words(lines.clone(), (0, 0), Buffer::next_word_start, false),
+      vec![(0, 3), (0, 5), (0, 6), (0, 8)]
+    );
+    assert_eq!(
+      collect_words(lines.clone(), (0, 8), Buffer::prev_word_start, false),
+      vec![(0, 6), (0, 5), (0, 3), (0, 0)]
+    );
+    assert_eq!(
+      collect_words(lines, (0, 0), Buffer::next_word_start, true),
+      vec![(0, 8)]
+    );
+  }
+
+  #[test]
+  fn lines1() {
+    // Move across lines, the empty line is a word for `w`/`b`, but skipped by `e`.
+    let lines = vec!["foo bar\n", "  baz\n", "\n", "qux;\n"];
+    assert_eq!(
+      collect_words(lines.clone(), (0, 0), Buffer::next_word_start, false),
+      vec![(0, 4), (1, 2), (2, 0), (3, 0), (3, 3)]
+    );
+    assert_eq!(
+      collect_words(lines.clone(), (3, 3), Buffer::prev_word_start, false),
+      vec![(3, 0), (2, 0), (1, 2), (0, 4), (0, 0)]
+    );
+    assert_eq!(
+      collect_words(lines, (0, 0), Buffer::next_word_end, false),
+      vec![(0, 2), (0, 6), (1, 4), (3, 2), (3, 3)]
+    );
+  }
+
+  #[test]
+  fn buffer_start_end1() {
+    let buffer = make_buffer_from_lines(vec!["  foo  \n"]);
+    let buffer = rlock!(buffer);
+    // No more words at the buffer start/end.
+    assert_eq!(buffer.prev_word_start(0, 0, false), None);
+    assert_eq!(buffer.prev_word_start(0, 2, false), None);
+    assert_eq!(buffer.next_word_start(0, 2, false), None);
+    assert_eq!(buffer.next_word_end(0, 4, false), None);
+    assert_eq!(buffer.next_word_start(0, 0, false), Some((0, 2)));
+    assert_eq!(buffer.next_word_end(0, 0, false), Some((0, 4)));
+    assert_eq!(buffer.prev_word_start(0, 6, false), Some((0, 2)));
+
+    let buffer = make_buffer_from_lines(vec![]);
+    let buffer = rlock!(buffer);
+    assert_eq!(buffer.next_word_start(0, 0, false), None);
+    assert_eq!(buffer.prev_word_start(0, 0, false), None);
+    assert_eq!(buffer.next_word_end(0, 0, false), None);
+  }
+}
@@ -41,6 +41,19 @@ pub struct CliOpt {
 
   #[arg(short = 'V', long = "version", help = "Print version")]
   version: bool,
+
+  #[arg(
+    value_name = "LEVEL",
+    long = "log-level",
+    help = "Set log level, e.g. \"debug\" or \"rsvim_core=trace\""
+  )]
+  log_level: Option<String>,
+
+  #[arg(value_name = "FILE", long = "log-file", help = "Write logs to <FILE>")]
+  log_file: Option<String>,
+
+  #[arg(long = "profile", help = "Record the render timing, see :profile")]
+  profile: bool,
 }
 
 impl CliOpt {
@@ -54,6 +67,21 @@ impl CliOpt {
     self.version
   }
 
+  /// Log level.
+  pub fn log_level(&self) -> &Option<String> {
+    &self.log_level
+  }
+
+  /// Log file.
+  pub fn log_file(&self) -> &Option<String> {
+    &self.log_file
+  }
+
+  /// Record the render timing.
+  pub fn profile(&self) -> bool {
+    self.profile
+  }
+
   // /// Commands should be execute before loading any config.
   // pub fn cmd_before(&self) -> &Option<Vec<String>> {
   //   &self.cmd_before
@@ -95,20 +123,51 @@ mod tests {
       vec!["rsvim".to_string()],
       vec!["rsvim".to_string(), "--version".to_string()],
       vec!["rsvim".to_string(), "README.md".to_string()],
+      vec![
+        "rsvim".to_string(),
+        "--log-level".to_string(),
+        "debug".to_string(),
+        "--log-file".to_string(),
+        "rsvim.log".to_string(),
+      ],
+      vec!["rsvim".to_string(), "--profile".to_string()],
     ];
 
     let expect = [
       CliOpt {
         file: vec![],
         version: false,
+        log_level: None,
+        log_file: None,
+        profile: false,
       },
       CliOpt {
         file: vec![],
         version: true,
+        log_level: None,
+        log_file: None,
+        profile: false,
       },
       CliOpt {
         file: vec!["README.md".to_string()],
         version: false,
+        log_level: None,
+        log_file: None,
+        profile: false,
+      },
+      CliOpt {
+        file: vec![],
+        version: false,
+        log_level: Some("debug".to_string()),
+        log_file: Some("rsvim.log".to_string()),
+        profile: false,
+      },
+      CliOpt {
+        file: vec![],
+        version: false,
+        log_level: None,
+        log_file: None,
+        profile: true,
       },
     ];
 
@@ -118,6 +177,9 @@ mod tests {
       let actual = CliOpt::parse_from(&input[i]);
       assert_eq!(actual.file, expect[i].file);
       assert_eq!(actual.version(), expect[i].version());
+      assert_eq!(actual.log_level(), expect[i].log_level());
+      assert_eq!(actual.log_file(), expect[i].log_file());
+      assert_eq!(actual.profile(), expect[i].profile());
     }
   }
 }
@@ -1,5 +1,7 @@
 //! Vim buffer's default options.
 
+use crate::buf::opt::ambiguous_width::AmbiguousWidth;
+use crate::buf::opt::buf_type::BufType;
 use crate::buf::opt::file_encoding::FileEncoding;
 
 /// Buffer 'tab-stop' option.
@@ -9,3 +11,44 @@ pub const TAB_STOP: u16 = 8;
 /// Buffer 'file-encoding' option.
 /// See: <https://vimhelp.org/options.txt.html#%27fileencoding%27>.
 pub const FILE_ENCODING: FileEncoding = FileEncoding::Utf8;
+
+/// Buffer 'ambiwidth' option.
+/// See: <https://vimhelp.org/options.txt.html#%27ambiwidth%27>.
+pub const AMBIGUOUS_WIDTH: AmbiguousWidth = AmbiguousWidth::Single;
+
+/// Buffer 'modifiable' option.
+/// See: <https://vimhelp.org/options.txt.html#%27modifiable%27>.
+pub const MODIFIABLE: bool = true;
+
+/// Buffer 'readonly' option.
+/// See: <https://vimhelp.org/options.txt.html#%27readonly%27>.
+pub const READONLY: bool = false;
+
+/// Buffer 'textwidth' option, `0` means not set.
+/// See: <https://vimhelp.org/options.txt.html#%27textwidth%27>.
+pub const TEXT_WIDTH: u16 = 0;
+
+/// Buffer 'autoindent' option.
+/// See: <https://vimhelp.org/options.txt.html#%27autoindent%27>.
+pub const AUTO_INDENT: bool = true;
+
+/// Buffer 'expandtab' option.
+/// See: <https://vimhelp.org/options.txt.html#%27expandtab%27>.
+pub const EXPAND_TAB: bool = false;
+
+/// Buffer 'shiftwidth' option, `0` means use the 'tabstop' value.
+/// See: <https://vimhelp.org/options.txt.html#%27shiftwidth%27>.
+pub const SHIFT_WIDTH: u16 = 8;
+
+/// Buffer 'backup' option.
+/// See: <https://vimhelp.org/options.txt.html#%27backup%27>.
+pub const BACKUP: bool = false;
+
+/// Buffer 'showhidden' option, show the hidden entries (i.e. the names start with `.`) in the
+/// directory buffer. It's not a Vim option, netrw toggles it with `gh`.
+/// See: <https://vimhelp.org/pi_netrw.txt.html#netrw-gh>.
+pub const SHOW_HIDDEN: bool = false;
+
+/// Buffer 'buftype' option.
+/// See: <https://vimhelp.org/options.txt.html#%27buftype%27>.
+pub const BUF_TYPE: BufType = BufType::Normal;
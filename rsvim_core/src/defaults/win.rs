@@ -7,3 +7,116 @@ pub const WRAP: bool = true;
 /// Window 'line-break' option, also known as 'word-wrap', default to `false`.
 /// See: <https://vimhelp.org/options.txt.html#%27linebreak%27>.
 pub const LINE_BREAK: bool = false;
+
+/// Window 'fillchars' option, default to `"eob:~"`.
+/// See: <https://vimhelp.org/options.txt.html#%27fillchars%27>.
+pub const FILL_CHARS: &str = "eob:~";
+
+/// Window 'list' option, default to `false`.
+/// See: <https://vimhelp.org/options.txt.html#%27list%27>.
+pub const LIST: bool = false;
+
+/// Window 'listchars' option, default to `"tab:» ,trail:·"`.
+/// See: <https://vimhelp.org/options.txt.html#%27listchars%27>.
+pub const LIST_CHARS: &str = "tab:» ,trail:·";
+
+/// Window 'showbreak' option, default to `""`.
+/// See: <https://vimhelp.org/options.txt.html#%27showbreak%27>.
+pub const SHOW_BREAK: &str = "";
+
+/// Window 'scroll' option, default to `0`, i.e. half of the window height.
+/// See: <https://vimhelp.org/options.txt.html#%27scroll%27>.
+pub const SCROLL: usize = 0;
+
+/// Window 'scrolloff' option, default to `0`.
+/// See: <https://vimhelp.org/options.txt.html#%27scrolloff%27>.
+pub const SCROLL_OFF: usize = 0;
+
+/// Window 'foldmethod' option, default to `"manual"`.
+/// See: <https://vimhelp.org/options.txt.html#%27foldmethod%27>.
+pub const FOLD_METHOD: &str = "manual";
+
+/// Window 'foldlevel' option, default to `0`.
+/// See: <https://vimhelp.org/options.txt.html#%27foldlevel%27>.
+pub const FOLD_LEVEL: usize = 0;
+
+/// Window 'foldenable' option, default to `true`.
+/// See: <https://vimhelp.org/options.txt.html#%27foldenable%27>.
+pub const FOLD_ENABLE: bool = true;
+
+/// Window 'foldmarker' option, default to `"{{{,}}}"`.
+/// See: <https://vimhelp.org/options.txt.html#%27foldmarker%27>.
+pub const FOLD_MARKER: &str = "{{{,}}}";
+
+/// Window 'cursorline' option, default to `false`.
+/// See: <https://vimhelp.org/options.txt.html#%27cursorline%27>.
+pub const CURSOR_LINE: bool = false;
+
+/// Window 'number' option, default to `false`.
+/// See: <https://vimhelp.org/options.txt.html#%27number%27>.
+pub const NUMBER: bool = false;
+
+/// Window 'foldcolumn' option, default to `0`.
+/// See: <https://vimhelp.org/options.txt.html#%27foldcolumn%27>.
+pub const FOLD_COLUMN: usize = 0;
+
+/// Window 'signcolumn' option, default to `"auto"`.
+/// See: <https://vimhelp.org/options.txt.html#%27signcolumn%27>.
+pub const SIGN_COLUMN: &str = "auto";
+
+/// Window 'winfixheight' option, default to `false`.
+/// See: <https://vimhelp.org/options.txt.html#%27winfixheight%27>.
+pub const WIN_FIX_HEIGHT: bool = false;
+
+/// Window 'winfixwidth' option, default to `false`.
+/// See: <https://vimhelp.org/options.txt.html#%27winfixwidth%27>.
+pub const WIN_FIX_WIDTH: bool = false;
+
+/// Global 'incsearch' option, default to `true`.
+/// See: <https://vimhelp.org/options.txt.html#%27incsearch%27>.
+pub const INC_SEARCH: bool = true;
+
+/// Global 'hlsearch' option, default to `true`.
+/// See: <https://vimhelp.org/options.txt.html#%27hlsearch%27>.
+pub const HL_SEARCH: bool = true;
+
+/// Global 'ignorecase' option, default to `false`.
+/// See: <https://vimhelp.org/options.txt.html#%27ignorecase%27>.
+pub const IGNORE_CASE: bool = false;
+
+/// Global 'smartcase' option, default to `false`.
+/// See: <https://vimhelp.org/options.txt.html#%27smartcase%27>.
+pub const SMART_CASE: bool = false;
+
+/// Global 'title' option, default to `true`.
+/// See: <https://vimhelp.org/options.txt.html#%27title%27>.
+pub const TITLE: bool = true;
+
+/// Global 'termguicolors' option, default to `false`. It's turned on at startup if the terminal
+/// supports truecolor.
+/// See: <https://vimhelp.org/options.txt.html#%27termguicolors%27>.
+pub const TERM_GUI_COLORS: bool = false;
+
+/// Global 'lazyredraw' option, default to `false`.
+/// See: <https://vimhelp.org/options.txt.html#%27lazyredraw%27>.
+pub const LAZY_REDRAW: bool = false;
+
+/// Global 'splitbelow' option, default to `false`.
+/// See: <https://vimhelp.org/options.txt.html#%27splitbelow%27>.
+pub const SPLIT_BELOW: bool = false;
+
+/// Global 'splitright' option, default to `false`.
+/// See: <https://vimhelp.org/options.txt.html#%27splitright%27>.
+pub const SPLIT_RIGHT: bool = false;
+
+/// Global 'winminheight' option, default to `1`.
+/// See: <https://vimhelp.org/options.txt.html#%27winminheight%27>.
+pub const WIN_MIN_HEIGHT: usize = 1;
+
+/// Global 'winminwidth' option, default to `1`.
+/// See: <https://vimhelp.org/options.txt.html#%27winminwidth%27>.
+pub const WIN_MIN_WIDTH: usize = 1;
+
+/// Global 'equalalways' option, default to `true`.
+/// See: <https://vimhelp.org/options.txt.html#%27equalalways%27>.
+pub const EQUAL_ALWAYS: bool = true;
@@ -6,8 +6,11 @@ use std::path::PathBuf;
 use std::sync::OnceLock;
 use std::time::Duration;
 
+use crate::envar::global_config::{GlobalConfig, GlobalConfigArc};
 use crate::envar::path_config::PathConfig;
+use crate::ui::canvas::ColorSupport;
 
+pub mod global_config;
 pub mod path_config;
 
 /// Mutex locking timeout in seconds, by default is [`u64::MAX`].
@@ -60,6 +63,20 @@ pub fn CHANNEL_BUF_SIZE() -> usize {
   })
 }
 
+/// The colors the terminal supports, by default is 16 colors.
+///
+/// NOTE: It is detected by the `COLORTERM` and `TERM` environment variables, see
+/// [`ColorSupport::detect`].
+pub fn COLOR_SUPPORT() -> ColorSupport {
+  static VALUE: OnceLock<ColorSupport> = OnceLock::new();
+
+  *VALUE.get_or_init(|| {
+    let colorterm = std::env::var("COLORTERM").ok();
+    let term = std::env::var("TERM").ok();
+    ColorSupport::detect(colorterm.as_deref(), term.as_deref())
+  })
+}
+
 static PATH_CONFIG_VALUE: OnceLock<PathConfig> = OnceLock::new();
 
 /// User config file path, it is detected with following orders:
@@ -105,6 +122,15 @@ pub fn DATA_DIR_PATH() -> PathBuf {
     .clone()
 }
 
+/// Global configs that can be changed at runtime, see [`GlobalConfig`].
+pub fn GLOBAL_CONFIG() -> GlobalConfigArc {
+  static VALUE: OnceLock<GlobalConfigArc> = OnceLock::new();
+
+  VALUE
+    .get_or_init(|| GlobalConfig::to_arc(GlobalConfig::new()))
+    .clone()
+}
+
 #[cfg(test)]
 mod tests {
   use super::*;
@@ -0,0 +1,193 @@
+//! Global configs.
+
+use crate::envar;
+
+use parking_lot::RwLock;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+#[derive(Debug)]
+/// Editor's global configs, i.e. the tunables referenced around the code base.
+///
+/// Unlike the environment variables in [`envar`](crate::envar), these configs can be changed at
+/// runtime.
+pub struct GlobalConfig {
+  channel_buf_size: usize,
+  js_shutdown_timeout: Duration,
+  swap_dir: PathBuf,
+  autosave_interval: Duration,
+  large_file_threshold: u64,
+  history_size: usize,
+  clipboard_commands: Option<ClipboardCommands>,
+  clipboard_timeout: Duration,
+}
+
+pub type GlobalConfigArc = Arc<RwLock<GlobalConfig>>;
+
+/// Default js runtime tasks shutdown timeout.
+const JS_SHUTDOWN_TIMEOUT_MILLIS: u64 = 3000;
+
+/// Default interval to save the modified buffers into the recovery journals, same with Vim's
+/// 'updatetime'.
+const AUTOSAVE_INTERVAL_MILLIS: u64 = 4000;
+
+/// Default large file threshold, i.e. 100MB.
+const LARGE_FILE_THRESHOLD_BYTES: u64 = 100 * 1024 * 1024;
+
+/// Default max entries of each command-line history.
+const HISTORY_SIZE: usize = 200;
+
+/// Default timeout to write/read the system clipboard.
+const CLIPBOARD_TIMEOUT_MILLIS: u64 = 1000;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// The external commands to write and read the system clipboard, each command is the program
+/// followed by its arguments, e.g. `["wl-copy"]` and `["wl-paste", "--no-newline"]`. See
+/// [`clipboard`](crate::state::clipboard).
+pub struct ClipboardCommands {
+  copy: Vec<String>,
+  paste: Vec<String>,
+}
+
+impl ClipboardCommands {
+  pub fn new(copy: Vec<String>, paste: Vec<String>) -> Self {
+    ClipboardCommands { copy, paste }
+  }
+
+  /// The command to write the clipboard, the text is written to its stdin.
+  pub fn copy(&self) -> &[String] {
+    &self.copy
+  }
+
+  /// The command to read the clipboard, the text is read from its stdout.
+  pub fn paste(&self) -> &[String] {
+    &self.paste
+  }
+}
+
+impl Default for GlobalConfig {
+  fn default() -> Self {
+    GlobalConfig::new()
+  }
+}
+
+impl GlobalConfig {
+  pub fn new() -> Self {
+    GlobalConfig {
+      channel_buf_size: envar::CHANNEL_BUF_SIZE(),
+      js_shutdown_timeout: Duration::from_millis(JS_SHUTDOWN_TIMEOUT_MILLIS),
+      swap_dir: envar::CACHE_DIR_PATH().join("swap"),
+      autosave_interval: Duration::from_millis(AUTOSAVE_INTERVAL_MILLIS),
+      large_file_threshold: LARGE_FILE_THRESHOLD_BYTES,
+      history_size: HISTORY_SIZE,
+      clipboard_commands: None,
+      clipboard_timeout: Duration::from_millis(CLIPBOARD_TIMEOUT_MILLIS),
+    }
+  }
+
+  pub fn to_arc(c: GlobalConfig) -> GlobalConfigArc {
+    Arc::new(RwLock::new(c))
+  }
+
+  /// Buffer size for channels communication.
+  pub fn channel_buf_size(&self) -> usize {
+    self.channel_buf_size
+  }
+
+  pub fn set_channel_buf_size(&mut self, value: usize) {
+    self.channel_buf_size = value;
+  }
+
+  /// Timeout for waiting js runtime tasks when editor shutdown.
+  pub fn js_shutdown_timeout(&self) -> Duration {
+    self.js_shutdown_timeout
+  }
+
+  pub fn set_js_shutdown_timeout(&mut self, value: Duration) {
+    self.js_shutdown_timeout = value;
+  }
+
+  /// Swap files directory, by default is `$XDG_CACHE_HOME/rsvim/swap`.
+  pub fn swap_dir(&self) -> &Path {
+    &self.swap_dir
+  }
+
+  pub fn set_swap_dir(&mut self, value: PathBuf) {
+    self.swap_dir = value;
+  }
+
+  /// Interval to save the modified buffers into the recovery journals in the
+  /// [`swap_dir`](GlobalConfig::swap_dir), zero disables it. See [`journal`](crate::buf::journal).
+  pub fn autosave_interval(&self) -> Duration {
+    self.autosave_interval
+  }
+
+  pub fn set_autosave_interval(&mut self, value: Duration) {
+    self.autosave_interval = value;
+  }
+
+  /// File size (in bytes) threshold, the file larger than it is opened as a large file, see
+  /// [`Buffer::is_large`](crate::buf::Buffer::is_large).
+  pub fn large_file_threshold(&self) -> u64 {
+    self.large_file_threshold
+  }
+
+  pub fn set_large_file_threshold(&mut self, value: u64) {
+    self.large_file_threshold = value;
+  }
+
+  /// Max entries of each command-line history, i.e. the Ex commands and the search patterns.
+  /// See: <https://vimhelp.org/options.txt.html#%27history%27>.
+  pub fn history_size(&self) -> usize {
+    self.history_size
+  }
+
+  pub fn set_history_size(&mut self, value: usize) {
+    self.history_size = value;
+  }
+
+  /// The external commands for the system clipboard, it's detected at startup if not set, see
+  /// [`detect_provider`](crate::state::clipboard::detect_provider).
+  pub fn clipboard_commands(&self) -> Option<&ClipboardCommands> {
+    self.clipboard_commands.as_ref()
+  }
+
+  pub fn set_clipboard_commands(&mut self, value: Option<ClipboardCommands>) {
+    self.clipboard_commands = value;
+  }
+
+  /// Timeout to write/read the system clipboard, the register `+`/`*` falls back to its internal
+  /// copy if it's timeout.
+  pub fn clipboard_timeout(&self) -> Duration {
+    self.clipboard_timeout
+  }
+
+  pub fn set_clipboard_timeout(&mut self, value: Duration) {
+    self.clipboard_timeout = value;
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn default1() {
+    let cfg = GlobalConfig::default();
+    assert_eq!(cfg.channel_buf_size(), envar::CHANNEL_BUF_SIZE());
+    assert!(cfg.js_shutdown_timeout() > Duration::ZERO);
+    assert!(cfg.swap_dir().ends_with("swap"));
+    assert_eq!(
+      cfg.autosave_interval(),
+      Duration::from_millis(AUTOSAVE_INTERVAL_MILLIS)
+    );
+    assert_eq!(cfg.large_file_threshold(), LARGE_FILE_THRESHOLD_BYTES);
+    assert_eq!(cfg.history_size(), HISTORY_SIZE);
+    assert_eq!(cfg.clipboard_commands(), None);
+    assert_eq!(
+      cfg.clipboard_timeout(),
+      Duration::from_millis(CLIPBOARD_TIMEOUT_MILLIS)
+    );
+  }
+}
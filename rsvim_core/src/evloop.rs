@@ -1,33 +1,46 @@
 //! Event loop.
 
-use crate::buf::{BuffersManager, BuffersManagerArc};
-use crate::cart::{IRect, U16Size};
+use crate::buf::{
+  journal, BufType, BufferId, BufferLocalOptions, BuffersManager, BuffersManagerArc, ColorSchemes,
+};
+use crate::cart::U16Size;
 use crate::cli::CliOpt;
 use crate::envar;
 use crate::evloop::msg::WorkerToMasterMessage;
-use crate::js::msg::{self as jsmsg, EventLoopToJsRuntimeMessage, JsRuntimeToEventLoopMessage};
+use crate::evloop::schedule::{ready_items, RenderScheduler};
+use crate::evloop::sink::{ShaderSink, StdoutSink};
+use crate::evloop::tui::TuiGuard;
+use crate::js::msg::{
+  self as jsmsg, EventLoopToJsRuntimeMessage, JsRuntimeToEventLoopMessage, OptionScope, OptionValue,
+};
 use crate::js::{JsRuntime, JsRuntimeOptions, SnapshotData};
-use crate::res::IoResult;
+use crate::res::{AnyResult, IoResult};
+use crate::state::autocmd::{AutocmdContext, AutocmdEvent};
+use crate::state::clipboard::{detect_provider, read_clipboard, write_clipboard, ClipboardPut};
+use crate::state::command::{BuiltinExCommand, ExCommandHandler, ExCommandLine};
+use crate::state::fsm::command_line::{sync_search_highlights, update_focused_window};
+use crate::state::fsm::normal::NormalStateful;
+use crate::state::fsm::visual::current_buffer;
 use crate::state::fsm::StatefulValue;
+use crate::state::history::{CmdHistories, HISTORY_FILE_NAME};
+use crate::state::profile::{FrameTimer, RenderPhase};
+use crate::state::quit::QuitRequest;
 use crate::state::{State, StateArc};
-use crate::ui::canvas::{Canvas, CanvasArc, Shader, ShaderCommand};
-use crate::ui::tree::internal::Inodeable;
+use crate::ui::canvas::{Canvas, CanvasArc, ColorSupport, Shader, ShaderCommand};
 use crate::ui::tree::{Tree, TreeArc, TreeNode};
-use crate::ui::widget::{Cursor, Window};
+use crate::ui::widget::window::{
+  FillChars, FoldMarker, FoldMethod, ListChars, SignColumn, Window, WindowLocalOptions,
+};
 use crate::{rlock, wlock};
 
-use crossterm::event::{
-  DisableFocusChange, DisableMouseCapture, EnableFocusChange, EnableMouseCapture, Event,
-  EventStream,
-};
-use crossterm::{self, execute, queue};
-use futures::StreamExt;
+use crossterm::event::{Event, EventStream};
+use futures::{Stream, StreamExt};
 use parking_lot::RwLock;
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
+use std::str::FromStr;
 use std::time::{Instant, SystemTime, UNIX_EPOCH};
 // use heed::types::U16;
-use std::io::Write;
-use std::io::{BufWriter, Stdout};
 use std::sync::Arc;
 use tokio::sync::mpsc::{channel, Receiver, Sender};
 use tokio_util::sync::CancellationToken;
@@ -35,7 +48,10 @@ use tokio_util::task::TaskTracker;
 use tracing::{error, trace};
 
 pub mod msg;
+pub mod schedule;
+pub mod sink;
 pub mod task;
+pub mod tui;
 
 // #[derive(Debug)]
 /// For slow tasks that are suitable to put in the background, this event loop will spawn them in
@@ -72,8 +88,12 @@ pub struct EventLoop {
   pub tree: TreeArc,
   /// Canvas for UI.
   pub canvas: CanvasArc,
-  /// Stdout writer for UI.
-  pub writer: BufWriter<Stdout>,
+  /// Output of the shader commands, i.e. the terminal.
+  pub sink: Box<dyn ShaderSink>,
+  /// Terminal guard, it restores the terminal when the event loop is dropped.
+  pub(crate) tui_guard: Option<TuiGuard>,
+  /// Render scheduler, the terminal is rendered once after each batch of events.
+  pub(crate) render_scheduler: RenderScheduler,
 
   /// (Global) editing state.
   pub state: StateArc,
@@ -92,49 +112,75 @@ pub struct EventLoop {
   /// Most write file operations are spawned with block tracker to ensure they will be safely
   /// complete to avoid damage user data files. While for most reading operations and pure CPU
   /// calculations, they will be cancelled when editor exit.
-  pub detached_tracker: TaskTracker,
-  pub blocked_tracker: TaskTracker,
+  pub(crate) detached_tracker: TaskTracker,
+  pub(crate) blocked_tracker: TaskTracker,
 
   /// Sender: workers => master.
   ///
   /// NOTE: This sender stores here is mostly just for clone to all the other tasks spawned during
   /// running the editor. The master itself doesn't actually use it.
-  pub worker_send_to_master: Sender<WorkerToMasterMessage>,
+  pub(crate) worker_send_to_master: Sender<WorkerToMasterMessage>,
   /// Receiver: master <= workers.
-  pub master_recv_from_worker: Receiver<WorkerToMasterMessage>,
+  pub(crate) master_recv_from_worker: Receiver<WorkerToMasterMessage>,
 
   /// Js runtime.
   pub js_runtime: JsRuntime,
   /// Receiver: master <= js runtime.
-  pub master_recv_from_js_runtime: Receiver<JsRuntimeToEventLoopMessage>,
+  pub(crate) master_recv_from_js_runtime: Receiver<JsRuntimeToEventLoopMessage>,
   /// Sender: master => js runtime.
-  pub master_send_to_js_runtime: Sender<EventLoopToJsRuntimeMessage>,
+  pub(crate) master_send_to_js_runtime: Sender<EventLoopToJsRuntimeMessage>,
   /// An internal connected sender/receiver pair, it's simply for forward the task results
   /// to the event loop again and bypass the limitation of V8 engine.
-  pub js_runtime_tick_dispatcher: Sender<EventLoopToJsRuntimeMessage>,
-  pub js_runtime_tick_queue: Receiver<EventLoopToJsRuntimeMessage>,
+  pub(crate) js_runtime_tick_dispatcher: Sender<EventLoopToJsRuntimeMessage>,
+  pub(crate) js_runtime_tick_queue: Receiver<EventLoopToJsRuntimeMessage>,
 }
 
 impl EventLoop {
-  /// Make new event loop.
+  /// Make new event loop, it renders to the terminal.
   pub fn new(cli_opt: CliOpt, snapshot: SnapshotData) -> IoResult<Self> {
-    // Canvas
     let (cols, rows) = crossterm::terminal::size()?;
-    let canvas_size = U16Size::new(cols, rows);
+    EventLoop::new_with_sink(
+      cli_opt,
+      snapshot,
+      U16Size::new(cols, rows),
+      Box::new(StdoutSink::new()),
+    )
+  }
+
+  /// Make new event loop with the canvas size, it renders to the `sink`, i.e. the headless
+  /// rendering without a real terminal.
+  pub fn new_with_sink(
+    cli_opt: CliOpt,
+    snapshot: SnapshotData,
+    canvas_size: U16Size,
+    sink: Box<dyn ShaderSink>,
+  ) -> IoResult<Self> {
+    // Canvas
     let canvas = Canvas::new(canvas_size);
     let canvas = Canvas::to_arc(canvas);
 
     // UI Tree
-    let tree = Tree::to_arc(Tree::new(canvas_size));
+    let mut tree = Tree::new(canvas_size);
+    // The 'termguicolors' is turned on if the terminal supports truecolor.
+    tree.set_term_gui_colors(envar::COLOR_SUPPORT() == ColorSupport::TrueColor);
+    let tree = Tree::to_arc(tree);
 
     // Buffers
     let buffers_manager = BuffersManager::to_arc(BuffersManager::new());
 
     // State
-    let state = State::to_arc(State::default());
+    let mut state = State::default();
+    state.render_profile_mut().set_enabled(cli_opt.profile());
+    let state = State::to_arc(state);
+
+    // Global configs
+    let channel_buf_size = envar::GLOBAL_CONFIG()
+      .try_read_for(envar::MUTEX_TIMEOUT())
+      .unwrap()
+      .channel_buf_size();
 
     // Worker => master
-    let (worker_send_to_master, master_recv_from_worker) = channel(envar::CHANNEL_BUF_SIZE());
+    let (worker_send_to_master, master_recv_from_worker) = channel(channel_buf_size);
 
     // Since there are too many limitations that we cannot use tokio APIs along with V8 engine, we
     // have to first send task requests to master, let the master handles these tasks for us in the
@@ -164,13 +210,11 @@ impl EventLoop {
     // limitation of V8 engine work along with tokio runtime.
 
     // Js runtime => master
-    let (js_runtime_send_to_master, master_recv_from_js_runtime) =
-      channel(envar::CHANNEL_BUF_SIZE());
+    let (js_runtime_send_to_master, master_recv_from_js_runtime) = channel(channel_buf_size);
     // Master => js runtime
-    let (master_send_to_js_runtime, js_runtime_recv_from_master) =
-      channel(envar::CHANNEL_BUF_SIZE());
+    let (master_send_to_js_runtime, js_runtime_recv_from_master) = channel(channel_buf_size);
     // Master => master
-    let (js_runtime_tick_dispatcher, js_runtime_tick_queue) = channel(envar::CHANNEL_BUF_SIZE());
+    let (js_runtime_tick_dispatcher, js_runtime_tick_queue) = channel(channel_buf_size);
 
     // Runtime Path
     let runtime_path = envar::CONFIG_DIRS_PATH();
@@ -188,7 +232,7 @@ impl EventLoop {
     // Js Runtime
     let js_runtime = JsRuntime::new(
       JsRuntimeOptions::default(),
-      snapshot,
+      Some(snapshot),
       startup_moment,
       startup_unix_epoch,
       js_runtime_send_to_master,
@@ -209,7 +253,9 @@ impl EventLoop {
       tree,
       state,
       buffers: buffers_manager,
-      writer: BufWriter::new(std::io::stdout()),
+      sink,
+      tui_guard: None,
+      render_scheduler: RenderScheduler::new(),
       cancellation_token: CancellationToken::new(),
       detached_tracker,
       blocked_tracker,
@@ -223,32 +269,46 @@ impl EventLoop {
     })
   }
 
-  /// Initialize user config file.
+  /// Initialize user config file, the error is shown in the message area, e.g. invalid option
+  /// values.
   pub fn init_config(&mut self) -> IoResult<()> {
     if let Some(config_file) = envar::CONFIG_FILE_PATH() {
-      self
+      if let Err(e) = self
         .js_runtime
         .execute_module(config_file.to_str().unwrap(), None)
-        .unwrap();
+      {
+        error!("Failed to load config file {:?}:{:?}", config_file, e);
+        wlock!(self.state).echoerr(&e.to_string());
+      }
     }
     Ok(())
   }
 
-  /// Initialize TUI.
-  pub fn init_tui(&self) -> IoResult<()> {
-    if !crossterm::terminal::is_raw_mode_enabled()? {
-      crossterm::terminal::enable_raw_mode()?;
-    }
+  /// Initialize the command-line histories from the file under the cache directory, it's called
+  /// after the user config, thus the `history_size` config applies. The missing or corrupted file
+  /// is ignored.
+  pub fn init_history(&mut self) -> IoResult<()> {
+    let history_size = rlock!(envar::GLOBAL_CONFIG()).history_size();
+    let history_file = envar::CACHE_DIR_PATH().join(HISTORY_FILE_NAME);
+    *wlock!(self.state).cmd_histories_mut() = CmdHistories::load(&history_file, history_size);
+    Ok(())
+  }
 
-    let mut out = std::io::stdout();
-    execute!(
-      out,
-      crossterm::terminal::EnterAlternateScreen,
-      crossterm::terminal::Clear(crossterm::terminal::ClearType::All),
-      EnableMouseCapture,
-      EnableFocusChange,
-    )?;
+  /// Initialize TUI, i.e. enable the raw mode and enter the alternate screen. The terminal is
+  /// restored on shutdown, or when the event loop is dropped by error or panic.
+  pub fn init_tui(&mut self) -> IoResult<()> {
+    self.tui_guard = Some(TuiGuard::enter()?);
+    Ok(())
+  }
 
+  /// Initialize the system clipboard for the registers `+` and `*`, see
+  /// [`detect_provider`](crate::state::clipboard::detect_provider). The OSC 52 is only used with
+  /// the terminal, i.e. after [`init_tui`](EventLoop::init_tui).
+  pub fn init_clipboard(&mut self) -> IoResult<()> {
+    let commands = rlock!(envar::GLOBAL_CONFIG()).clipboard_commands().cloned();
+    let provider = detect_provider(commands, self.tui_guard.is_some());
+    trace!("Clipboard provider:{:?}", provider);
+    wlock!(self.state).registers_mut().set_clipboard(provider);
     Ok(())
   }
 
@@ -262,6 +322,8 @@ impl EventLoop {
         match maybe_buf_id {
           Ok(buf_id) => {
             trace!("Created file buffer {:?}:{:?}", input_file, buf_id);
+            self.fire_buf_read_post(buf_id);
+            self.prompt_recovery_journal(buf_id);
           }
           Err(e) => {
             error!("Failed to create file buffer {:?}:{:?}", input_file, e);
@@ -276,31 +338,93 @@ impl EventLoop {
     Ok(())
   }
 
+  // Prompt the user if the buffer has a recovery journal, i.e. the editor crashed when the file was
+  // modified.
+  fn prompt_recovery_journal(&mut self, buf_id: BufferId) {
+    let found = rlock!(self.buffers).get(&buf_id).and_then(|buf| {
+      let buf = rlock!(buf);
+      buf
+        .recovery_journal()
+        .as_ref()
+        .map(|_| buf.filename().clone().unwrap_or_default())
+    });
+    if let Some(filename) = found {
+      wlock!(self.state).echo(&format!(
+        "Found a recovery journal for {:?}, use :recover to recover it",
+        filename
+      ));
+    }
+  }
+
   /// Initialize windows.
   pub fn init_windows(&mut self) -> IoResult<()> {
-    // Initialize default window.
-    let canvas_size = rlock!(self.canvas).size();
+    // Initialize default window, cursor and message area.
     let mut tree = self.tree.try_write_for(envar::MUTEX_TIMEOUT()).unwrap();
-    let tree_root_id = tree.root_id();
-    let window_shape = IRect::new(
-      (0, 0),
-      (canvas_size.width() as isize, canvas_size.height() as isize),
-    );
-    let window = {
-      let buffers = rlock!(self.buffers);
-      let (buf_id, buf) = buffers.first_key_value().unwrap();
-      trace!("Bind first buffer to default window {:?}", buf_id);
-      Window::new(window_shape, Arc::downgrade(buf), tree.local_options())
-    };
-    let window_id = window.id();
-    let window_node = TreeNode::Window(window);
-    tree.bounded_insert(&tree_root_id, window_node);
+    let buffers = rlock!(self.buffers);
+    let (buf_id, buf) = buffers.first_key_value().unwrap();
+    trace!("Bind first buffer to default window {:?}", buf_id);
+    tree.init_layout(Arc::downgrade(buf));
 
-    // Initialize cursor.
-    let cursor_shape = IRect::new((0, 0), (1, 1));
-    let cursor = Cursor::new(cursor_shape);
-    let cursor_node = TreeNode::Cursor(cursor);
-    tree.bounded_insert(&window_id, cursor_node);
+    Ok(())
+  }
+
+  /// Initialize the autosave task, it periodically writes the modified buffers into their
+  /// recovery journals, see [`journal`]. It's disabled if the
+  /// [`autosave_interval`](crate::envar::global_config::GlobalConfig::autosave_interval) is zero.
+  ///
+  /// The journals are written with block tracker, thus the journals are never removed on
+  /// shutdown while they're still being written.
+  pub fn init_autosave(&mut self) -> IoResult<()> {
+    let interval = rlock!(envar::GLOBAL_CONFIG()).autosave_interval();
+    if interval.is_zero() {
+      return Ok(());
+    }
+
+    let buffers = self.buffers.clone();
+    let cancellation_token = self.cancellation_token.clone();
+    self.blocked_tracker.spawn(async move {
+      // The `changed_tick` of each buffer when it's journaled last time.
+      let mut journaled_ticks: HashMap<BufferId, usize> = HashMap::new();
+      loop {
+        tokio::select! {
+          _ = cancellation_token.cancelled() => break,
+          _ = tokio::time::sleep(interval) => {}
+        }
+
+        let (dir, snapshots) = {
+          let buffers = rlock!(buffers);
+          (
+            buffers.journal_dir().to_path_buf(),
+            buffers.journal_snapshots(),
+          )
+        };
+        let snapshots: Vec<_> = snapshots
+          .into_iter()
+          .filter(|s| journaled_ticks.get(&s.buf_id) != Some(&s.changed_tick))
+          .collect();
+        if snapshots.is_empty() {
+          continue;
+        }
+
+        let written = tokio::task::spawn_blocking(move || {
+          snapshots
+            .into_iter()
+            .filter_map(|s| match journal::write_journal(&dir, &s) {
+              Ok(()) => Some((s.buf_id, s.changed_tick)),
+              Err(e) => {
+                error!("Failed to write journal of {:?}:{:?}", s.filename, e);
+                None
+              }
+            })
+            .collect::<Vec<_>>()
+        })
+        .await;
+        if let Ok(written) = written {
+          journaled_ticks.extend(written);
+        }
+      }
+      trace!("Autosave task exit");
+    });
 
     Ok(())
   }
@@ -315,22 +439,27 @@ impl EventLoop {
       .frame()
       .cursor();
 
+    let mut shader = Shader::new();
     if cursor.blinking() {
-      queue!(self.writer, crossterm::cursor::EnableBlinking)?;
+      shader.push(ShaderCommand::CursorEnableBlinking(
+        crossterm::cursor::EnableBlinking,
+      ));
     } else {
-      queue!(self.writer, crossterm::cursor::DisableBlinking)?;
+      shader.push(ShaderCommand::CursorDisableBlinking(
+        crossterm::cursor::DisableBlinking,
+      ));
     }
     if cursor.hidden() {
-      queue!(self.writer, crossterm::cursor::Hide)?;
+      shader.push(ShaderCommand::CursorHide(crossterm::cursor::Hide));
     } else {
-      queue!(self.writer, crossterm::cursor::Show)?;
+      shader.push(ShaderCommand::CursorShow(crossterm::cursor::Show));
     }
-
-    queue!(self.writer, cursor.style())?;
-    queue!(
-      self.writer,
-      crossterm::cursor::MoveTo(cursor.pos().x(), cursor.pos().y())
-    )?;
+    shader.push(ShaderCommand::CursorSetCursorStyle(cursor.style()));
+    shader.push(ShaderCommand::CursorMoveTo(crossterm::cursor::MoveTo(
+      cursor.pos().x(),
+      cursor.pos().y(),
+    )));
+    self.queue_shader(shader)?;
 
     self.render()?;
 
@@ -341,17 +470,39 @@ impl EventLoop {
     match event {
       Some(Ok(event)) => {
         trace!("Polled terminal event ok: {:?}", event);
+        self.render_scheduler.mark_dirty();
+        let changed_tick = self.current_buffer_changed_tick();
 
         // Handle by state machine
-        let state_response = self
+        self
           .state
           .try_write_for(envar::MUTEX_TIMEOUT())
           .unwrap()
           .handle(self.tree.clone(), self.buffers.clone(), event);
 
+        // Notify js runtime if the buffer text is changed.
+        if let Some((buf_id, tick)) = self.current_buffer_changed_tick() {
+          if changed_tick != Some((buf_id, tick)) {
+            self.send_to_js_runtime(EventLoopToJsRuntimeMessage::BufferChanged(buf_id));
+          }
+        }
+
+        // Dispatch autocmd events and Ex commands to js runtime.
+        self.dispatch_pending_autocmds();
+        let ex_commands = self
+          .state
+          .try_write_for(envar::MUTEX_TIMEOUT())
+          .unwrap()
+          .take_pending_ex_commands();
+        for command_line in ex_commands {
+          self.dispatch_ex_command(&command_line);
+        }
+        self.dispatch_clipboard_requests();
+
         // Exit loop and quit.
-        if let StatefulValue::QuitState(_) = state_response.next_stateful {
-          self.cancellation_token.cancel();
+        let quit_request = wlock!(self.state).take_quit_request();
+        if let Some(request) = quit_request {
+          self.quit(request);
         }
       }
       Some(Err(e)) => {
@@ -365,12 +516,197 @@ impl EventLoop {
     }
   }
 
+  // Get the buffer ID and its changed tick of current window.
+  fn current_buffer_changed_tick(&self) -> Option<(BufferId, usize)> {
+    current_buffer(&rlock!(self.tree)).map(|buf| {
+      let buf = rlock!(buf);
+      (buf.id(), buf.changed_tick())
+    })
+  }
+
+  // Fire `BufReadPost` if the buffer is read from file.
+  fn fire_buf_read_post(&mut self, buf_id: BufferId) {
+    let file = match rlock!(self.buffers).get(&buf_id) {
+      Some(buf) => {
+        let buf = rlock!(buf);
+        if buf.metadata().is_none() {
+          return;
+        }
+        buf.absolute_filename().clone()
+      }
+      None => return,
+    };
+    let ctx = AutocmdContext::buffer(AutocmdEvent::BufReadPost, buf_id, file);
+    self.js_runtime.fire_autocmd(&ctx);
+  }
+
+  fn dispatch_pending_autocmds(&mut self) {
+    let autocmds = self
+      .state
+      .try_write_for(envar::MUTEX_TIMEOUT())
+      .unwrap()
+      .take_pending_autocmds();
+    for ctx in autocmds {
+      self.js_runtime.fire_autocmd(&ctx);
+    }
+  }
+
+  // Write the registers `+`/`*` to the clipboard, and read the clipboard for the puts. They run in
+  // order in the background, the results are sent back as the worker messages.
+  fn dispatch_clipboard_requests(&mut self) {
+    let (provider, writes, puts) = {
+      let mut state = wlock!(self.state);
+      (
+        state.registers().clipboard().cloned(),
+        state.registers_mut().take_clipboard_writes(),
+        state.take_pending_clipboard_puts(),
+      )
+    };
+    let provider = match provider {
+      Some(provider) if !writes.is_empty() || !puts.is_empty() => provider,
+      _ => return,
+    };
+    let timeout = rlock!(envar::GLOBAL_CONFIG()).clipboard_timeout();
+    let worker_send_to_master = self.worker_send_to_master.clone();
+    self.detached_tracker.spawn(async move {
+      for (name, register) in writes {
+        let text = register.text().to_string();
+        let result = write_clipboard(provider.clone(), name, text, timeout).await;
+        let _ = worker_send_to_master
+          .send(WorkerToMasterMessage::ClipboardWritten(result))
+          .await;
+      }
+      for put in puts {
+        let result = read_clipboard(provider.clone(), put.register_name(), timeout).await;
+        let _ = worker_send_to_master
+          .send(WorkerToMasterMessage::ClipboardRead(put, result))
+          .await;
+      }
+    });
+  }
+
+  // Put the register `+`/`*` after reading the clipboard, its internal copy is put if it fails.
+  // The put is dropped if it's not in normal mode anymore, i.e. the keys typed while waiting.
+  fn put_from_clipboard(&mut self, put: ClipboardPut, text: AnyResult<String>) {
+    let changed_tick = self.current_buffer_changed_tick();
+    {
+      let mut state = wlock!(self.state);
+      match text {
+        Ok(text) => state
+          .registers_mut()
+          .set_clipboard_content(put.register_name(), text),
+        Err(e) => {
+          error!("Failed to read clipboard:{:?}", e);
+          state.echoerr(&e.to_string());
+        }
+      }
+      if !matches!(state.stateful(), StatefulValue::NormalMode(_)) {
+        return;
+      }
+      NormalStateful::put_register(
+        &mut state,
+        &mut wlock!(self.tree),
+        Some(put.register_name()),
+        put.after(),
+      );
+    }
+
+    // Notify js runtime if the buffer text is changed.
+    if let Some((buf_id, tick)) = self.current_buffer_changed_tick() {
+      if changed_tick != Some((buf_id, tick)) {
+        self.send_to_js_runtime(EventLoopToJsRuntimeMessage::BufferChanged(buf_id));
+      }
+    }
+  }
+
+  // Dispatch the Ex command queued by state machine, the error is shown in the message area.
+  fn dispatch_ex_command(&mut self, command_line: &ExCommandLine) {
+    let handler = rlock!(self.state).ex_commands().get(command_line.name());
+    match handler {
+      Some(ExCommandHandler::Builtin(BuiltinExCommand::Write)) => {
+        let buf_id = current_buffer(&rlock!(self.tree)).map(|buf| rlock!(buf).id());
+        let result = match buf_id {
+          Some(buf_id) => self.js_runtime.write_buffer(buf_id, command_line.bang()),
+          None => Err(anyhow::anyhow!("E32: No file name")),
+        };
+        if let Err(e) = result {
+          error!("Failed to write buffer {:?}:{:?}", buf_id, e);
+          wlock!(self.state).echoerr(&e.to_string());
+        }
+      }
+      Some(ExCommandHandler::Builtin(BuiltinExCommand::WriteQuit | BuiltinExCommand::Exit)) => {
+        if let Err(e) = self.write_quit(command_line) {
+          error!("Failed to write and quit {:?}:{:?}", command_line, e);
+          wlock!(self.state).echoerr(&e.to_string());
+        }
+      }
+      Some(ExCommandHandler::User) => {
+        if let Err(e) = self
+          .js_runtime
+          .execute_user_command(command_line.name(), command_line.args())
+        {
+          error!("Failed to execute user command {:?}:{:?}", command_line, e);
+        }
+      }
+      _ => { /* Skip */ }
+    }
+  }
+
+  // Write the current buffer and quit, i.e. `:wq` and `:xit`. It doesn't quit if the write fails or
+  // is vetoed by `BufWritePre`. The `:xit` only writes the modified buffer.
+  fn write_quit(&mut self, command_line: &ExCommandLine) -> AnyResult<()> {
+    let buf = current_buffer(&rlock!(self.tree));
+    let (buf_id, modified) = match buf {
+      Some(buf) => {
+        let buf = rlock!(buf);
+        (buf.id(), buf.is_modified())
+      }
+      None => anyhow::bail!("E32: No file name"),
+    };
+    let exit = rlock!(self.state).ex_commands().get(command_line.name())
+      == Some(ExCommandHandler::Builtin(BuiltinExCommand::Exit));
+    if (!exit || modified) && !self.js_runtime.write_buffer(buf_id, command_line.bang())? {
+      return Ok(());
+    }
+    self.quit(QuitRequest::new(false));
+    Ok(())
+  }
+
+  // Quit the editor, unless there're modified buffers and the request is not forced, then the
+  // error is shown in the message area.
+  fn quit(&mut self, request: QuitRequest) {
+    let result = request.accept(&rlock!(self.buffers));
+    match result {
+      Ok(()) => self.cancellation_token.cancel(),
+      Err(e) => wlock!(self.state).echoerr(&e.to_string()),
+    }
+  }
+
   async fn process_worker_notify(&mut self, msg: Option<WorkerToMasterMessage>) {
     trace!("Received {:?} message from workers", msg);
+    self.render_scheduler.mark_dirty();
+    match msg {
+      Some(WorkerToMasterMessage::ClipboardWritten(result)) => match result {
+        // The OSC 52 is output at once, it doesn't wait for the next render.
+        Ok(Some(command)) => {
+          if let Err(e) = self.sink.queue(command).and_then(|_| self.sink.flush()) {
+            error!("Failed to output clipboard command:{:?}", e);
+          }
+        }
+        Ok(None) => {}
+        Err(e) => {
+          error!("Failed to write clipboard:{:?}", e);
+          wlock!(self.state).echoerr(&e.to_string());
+        }
+      },
+      Some(WorkerToMasterMessage::ClipboardRead(put, text)) => self.put_from_clipboard(put, text),
+      None => {}
+    }
   }
 
   async fn process_js_runtime_request(&mut self, msg: Option<JsRuntimeToEventLoopMessage>) {
     if let Some(msg) = msg {
+      self.render_scheduler.mark_dirty();
       match msg {
         JsRuntimeToEventLoopMessage::TimeoutReq(req) => {
           trace!("process_js_runtime_request timeout_req:{:?}", req.future_id);
@@ -388,6 +724,29 @@ impl EventLoop {
             );
           });
         }
+        JsRuntimeToEventLoopMessage::Echo(message) => {
+          wlock!(self.state).echo(&message);
+        }
+        JsRuntimeToEventLoopMessage::Error(e) => {
+          error!("Js runtime error:{:?}", e);
+          wlock!(self.state).echoerr(&e.message);
+        }
+        JsRuntimeToEventLoopMessage::SetOption { scope, name, value } => {
+          let result = set_option(
+            &mut wlock!(self.tree),
+            &mut wlock!(self.buffers),
+            scope,
+            &name,
+            value,
+          );
+          if let Err(e) = result {
+            error!("Failed to set option {:?}:{:?}", name, e);
+            wlock!(self.state).echoerr(&e.to_string());
+          }
+        }
+        JsRuntimeToEventLoopMessage::Quit(request) => {
+          self.quit(request);
+        }
       }
     }
   }
@@ -395,16 +754,44 @@ impl EventLoop {
   async fn process_js_runtime_response(&mut self, msg: Option<EventLoopToJsRuntimeMessage>) {
     if let Some(msg) = msg {
       trace!("process_js_runtime_response msg:{:?}", msg);
-      let _ = self.master_send_to_js_runtime.send(msg).await;
-      self.js_runtime.tick_event_loop();
+      // The js callbacks run along with the tick, they can change the editor.
+      self.render_scheduler.mark_dirty();
+      self.send_to_js_runtime(msg);
     }
   }
 
+  // Send the message to js runtime and tick it at once, so the messages never pile up in the
+  // channel. The message is dropped if the channel is full or closed.
+  fn send_to_js_runtime(&mut self, msg: EventLoopToJsRuntimeMessage) {
+    if let Err(e) = self.master_send_to_js_runtime.try_send(msg) {
+      error!("Failed to send message to js runtime:{:?}", e);
+    }
+    self.js_runtime.tick_event_loop();
+  }
+
   async fn process_cancellation_notify(&mut self) {
     trace!("Receive cancellation token, exit loop");
+    let history_file = envar::CACHE_DIR_PATH().join(HISTORY_FILE_NAME);
+    if let Err(e) = rlock!(self.state).cmd_histories().save(&history_file) {
+      error!("Failed to save history file {:?}:{:?}", history_file, e);
+    }
     self.detached_tracker.close();
     self.blocked_tracker.close();
-    self.blocked_tracker.wait().await;
+    let shutdown_timeout = envar::GLOBAL_CONFIG()
+      .try_read_for(envar::MUTEX_TIMEOUT())
+      .unwrap()
+      .js_shutdown_timeout();
+    if tokio::time::timeout(shutdown_timeout, self.blocked_tracker.wait())
+      .await
+      .is_err()
+    {
+      error!(
+        "Blocked tasks are still running after {:?}, exit loop",
+        shutdown_timeout
+      );
+    }
+    // The editor quits normally, the journals are useless.
+    rlock!(self.buffers).remove_journals();
   }
 
   /// Running the loop, it repeatedly do following steps:
@@ -412,50 +799,138 @@ impl EventLoop {
   /// 1. Receives several things:
   ///    1. User keyboard/mouse events.
   ///    2. Messages sent from workers.
-  ///    3. Cancellation request (which tells this event loop to quit).
-  /// 2. Use the editing state (FSM) to handle the event.
-  /// 3. Render the terminal.
+  ///    3. Messages sent from js runtime.
+  ///    4. Cancellation request (which tells this event loop to quit).
+  /// 2. Use the editing state (FSM) to handle the event, all the immediately-available terminal
+  ///    events are handled in the same batch, i.e. fast key repeat or paste.
+  /// 3. Render the terminal once if anything is changed, see [`RenderScheduler`].
   pub async fn run(&mut self) -> IoResult<()> {
     let mut reader = EventStream::new();
-    loop {
-      tokio::select! {
-        // Receive keyboard/mouse events
-        event = reader.next() => {
-          self.process_event(event).await;
-        }
-        // Receive notification from workers
-        worker_msg = self.master_recv_from_worker.recv() => {
-          self.process_worker_notify(worker_msg).await;
-        }
-        // Receive notification from js runtime
-        js_req = self.master_recv_from_js_runtime.recv() => {
-            self.process_js_runtime_request(js_req).await;
-        }
-        js_resp = self.js_runtime_tick_queue.recv() => {
-            self.process_js_runtime_response(js_resp).await;
+    while self.run_once(&mut reader).await? {}
+    Ok(())
+  }
+
+  // Run one iteration of the loop with the terminal events `reader`, returns `false` if the loop
+  // is cancelled.
+  pub(crate) async fn run_once<S>(&mut self, reader: &mut S) -> IoResult<bool>
+  where
+    S: Stream<Item = IoResult<Event>> + Unpin,
+  {
+    tokio::select! {
+      // Receive keyboard/mouse events
+      event = reader.next() => {
+        self.process_event(event).await;
+        self.process_typeahead().await?;
+        for event in ready_items(reader) {
+          if self.cancellation_token.is_cancelled() {
+            break;
+          }
+          self.process_event(Some(event)).await;
+          self.process_typeahead().await?;
         }
-        // Receive cancellation notify
-        _ = self.cancellation_token.cancelled() => {
-          self.process_cancellation_notify().await;
-          // let _ = self.master_send_to_js_worker.send(EventLoopToJsRuntimeMessage::Shutdown(jsmsg::Dummy::default())).await;
-          break;
+      }
+      // Receive notification from workers
+      worker_msg = self.master_recv_from_worker.recv() => {
+        self.process_worker_notify(worker_msg).await;
+      }
+      // Receive messages from js runtime, i.e. timer requests, echo, errors, etc.
+      js_req = self.master_recv_from_js_runtime.recv() => {
+          self.process_js_runtime_request(js_req).await;
+      }
+      js_resp = self.js_runtime_tick_queue.recv() => {
+          self.process_js_runtime_response(js_resp).await;
+      }
+      // Receive cancellation notify
+      _ = self.cancellation_token.cancelled() => {
+        self.process_cancellation_notify().await;
+        self.send_to_js_runtime(EventLoopToJsRuntimeMessage::Shutdown);
+        return Ok(false);
+      }
+    }
+
+    // Update terminal
+    self.render_if_needed()?;
+    Ok(true)
+  }
+
+  // Replay the keys queued by the register executions, i.e. `@{reg}`. They're handled one by one
+  // like the typed keys, and the intermediate states are rendered unless 'lazyredraw' is on.
+  async fn process_typeahead(&mut self) -> IoResult<()> {
+    loop {
+      let key = wlock!(self.state).macro_recorder_mut().next_key();
+      match key {
+        Some(key) if !self.cancellation_token.is_cancelled() => {
+          self.render_if_needed()?;
+          self.process_event(Some(Ok(Event::Key(key)))).await;
         }
+        _ => return Ok(()),
       }
+    }
+  }
 
-      // Update terminal
+  // Render the terminal if anything is changed, it's skipped while the render is suppressed if
+  // 'lazyredraw' is on.
+  fn render_if_needed(&mut self) -> IoResult<()> {
+    let lazy_redraw = rlock!(self.tree).lazy_redraw();
+    let render_suppressed = rlock!(self.state).is_render_suppressed();
+    if self
+      .render_scheduler
+      .should_render(lazy_redraw, render_suppressed)
+    {
       self.render()?;
     }
-
     Ok(())
   }
 
   fn render(&mut self) -> IoResult<()> {
+    // Each phase is measured if the profiling is enabled, see `--profile`.
+    let mut timer = FrameTimer::start(rlock!(self.state).render_profile().enabled());
+
     // Draw UI components to the canvas.
-    self
-      .tree
-      .try_write_for(envar::MUTEX_TIMEOUT())
-      .unwrap()
-      .draw(self.canvas.clone());
+    {
+      let state = rlock!(self.state);
+      let mut tree = self.tree.try_write_for(envar::MUTEX_TIMEOUT()).unwrap();
+      // The 'hlsearch' highlights follow the viewport of current window.
+      sync_search_highlights(&state, &tree);
+      tree.set_message(state.message().clone());
+      tree.set_cursor_style(state.mode().cursor_style());
+      tree.sync_tab_line();
+      tree.sync_gutters();
+      // The active tab page is switched, the cells not covered by its windows are cleared.
+      // The regions left by the shrunk or moved windows are blanked, the windows covering them
+      // draw on top of them.
+      let stale_regions = tree.take_stale_regions();
+      if tree.take_redraw() {
+        wlock!(self.canvas).clear();
+      } else {
+        let mut canvas = wlock!(self.canvas);
+        for region in stale_regions {
+          canvas.clear_region(region);
+        }
+      }
+      timer.lap(RenderPhase::Prepare);
+      tree.draw(self.canvas.clone());
+      let color_scheme_version = rlock!(ColorSchemes::global()).version();
+      wlock!(self.canvas).set_color_scheme_version(color_scheme_version);
+      // The 'termguicolors' overrides the detected truecolor, otherwise the RGB colors are
+      // downsampled.
+      let color_support = match envar::COLOR_SUPPORT() {
+        _ if tree.term_gui_colors() => ColorSupport::TrueColor,
+        ColorSupport::TrueColor => ColorSupport::Ansi256,
+        color_support => color_support,
+      };
+      wlock!(self.canvas).set_color_support(color_support);
+      // The terminal title follows the buffer of current window, i.e. its file name and whether
+      // it's modified.
+      if tree.title() {
+        if let Some(buffer) = current_buffer(&tree) {
+          let title = rlock!(buffer).title();
+          wlock!(self.canvas).set_title(&title);
+        }
+      }
+    }
+
+    timer.lap(RenderPhase::Draw);
 
     // Compute the commands that need to output to the terminal device.
     let shader = self
@@ -463,81 +938,404 @@ impl EventLoop {
       .try_write_for(envar::MUTEX_TIMEOUT())
       .unwrap()
       .shade();
+    timer.lap(RenderPhase::Diff);
 
+    // Hide the cursor while redrawing, otherwise it flickers through the printed cells.
+    let cursor_hidden = rlock!(self.canvas).frame().cursor().hidden();
+    self
+      .sink
+      .queue(ShaderCommand::CursorHide(crossterm::cursor::Hide))?;
     self.queue_shader(shader)?;
-    self.writer.flush()?;
+    if !cursor_hidden {
+      self
+        .sink
+        .queue(ShaderCommand::CursorShow(crossterm::cursor::Show))?;
+    }
+    self.sink.flush()?;
+    timer.lap(RenderPhase::Flush);
+
+    if let Some(timing) = timer.finish() {
+      wlock!(self.state).render_profile_mut().record(timing);
+    }
 
     Ok(())
   }
 
   /// Put (render) canvas shader.
   fn queue_shader(&mut self, shader: Shader) -> IoResult<()> {
-    for shader_command in shader.iter() {
-      match shader_command {
-        ShaderCommand::CursorSetCursorStyle(command) => queue!(self.writer, command)?,
-        ShaderCommand::CursorDisableBlinking(command) => queue!(self.writer, command)?,
-        ShaderCommand::CursorEnableBlinking(command) => queue!(self.writer, command)?,
-        ShaderCommand::CursorHide(command) => queue!(self.writer, command)?,
-        ShaderCommand::CursorMoveDown(command) => queue!(self.writer, command)?,
-        ShaderCommand::CursorMoveLeft(command) => queue!(self.writer, command)?,
-        ShaderCommand::CursorMoveRight(command) => queue!(self.writer, command)?,
-        ShaderCommand::CursorMoveTo(command) => queue!(self.writer, command)?,
-        ShaderCommand::CursorMoveToColumn(command) => queue!(self.writer, command)?,
-        ShaderCommand::CursorMoveToNextLine(command) => queue!(self.writer, command)?,
-        ShaderCommand::CursorMoveToPreviousLine(command) => queue!(self.writer, command)?,
-        ShaderCommand::CursorMoveToRow(command) => queue!(self.writer, command)?,
-        ShaderCommand::CursorMoveUp(command) => queue!(self.writer, command)?,
-        ShaderCommand::CursorRestorePosition(command) => queue!(self.writer, command)?,
-        ShaderCommand::CursorSavePosition(command) => queue!(self.writer, command)?,
-        ShaderCommand::CursorShow(command) => queue!(self.writer, command)?,
-        ShaderCommand::EventDisableBracketedPaste(command) => queue!(self.writer, command)?,
-        ShaderCommand::EventDisableFocusChange(command) => queue!(self.writer, command)?,
-        ShaderCommand::EventDisableMouseCapture(command) => queue!(self.writer, command)?,
-        ShaderCommand::EventEnableBracketedPaste(command) => queue!(self.writer, command)?,
-        ShaderCommand::EventEnableFocusChange(command) => queue!(self.writer, command)?,
-        ShaderCommand::EventEnableMouseCapture(command) => queue!(self.writer, command)?,
-        ShaderCommand::EventPopKeyboardEnhancementFlags(command) => queue!(self.writer, command)?,
-        ShaderCommand::EventPushKeyboardEnhancementFlags(command) => queue!(self.writer, command)?,
-        ShaderCommand::StyleResetColor(command) => queue!(self.writer, command)?,
-        ShaderCommand::StyleSetAttribute(command) => queue!(self.writer, command)?,
-        ShaderCommand::StyleSetAttributes(command) => queue!(self.writer, command)?,
-        ShaderCommand::StyleSetBackgroundColor(command) => queue!(self.writer, command)?,
-        ShaderCommand::StyleSetColors(command) => queue!(self.writer, command)?,
-        ShaderCommand::StyleSetForegroundColor(command) => queue!(self.writer, command)?,
-        ShaderCommand::StyleSetStyle(command) => queue!(self.writer, command)?,
-        ShaderCommand::StyleSetUnderlineColor(command) => queue!(self.writer, command)?,
-        ShaderCommand::StylePrintStyledContentString(command) => queue!(self.writer, command)?,
-        ShaderCommand::StylePrintString(command) => queue!(self.writer, command)?,
-        ShaderCommand::TerminalBeginSynchronizedUpdate(command) => queue!(self.writer, command)?,
-        ShaderCommand::TerminalClear(command) => queue!(self.writer, command)?,
-        ShaderCommand::TerminalDisableLineWrap(command) => queue!(self.writer, command)?,
-        ShaderCommand::TerminalEnableLineWrap(command) => queue!(self.writer, command)?,
-        ShaderCommand::TerminalEndSynchronizedUpdate(command) => queue!(self.writer, command)?,
-        ShaderCommand::TerminalEnterAlternateScreen(command) => queue!(self.writer, command)?,
-        ShaderCommand::TerminalLeaveAlternateScreen(command) => queue!(self.writer, command)?,
-        ShaderCommand::TerminalScrollDown(command) => queue!(self.writer, command)?,
-        ShaderCommand::TerminalScrollUp(command) => queue!(self.writer, command)?,
-        ShaderCommand::TerminalSetSize(command) => queue!(self.writer, command)?,
-      }
+    for shader_command in shader {
+      self.sink.queue(shader_command)?;
     }
 
     Ok(())
   }
 
   /// Shutdown TUI.
-  pub fn shutdown_tui(&self) -> IoResult<()> {
-    let mut out = std::io::stdout();
-    execute!(
-      out,
-      DisableMouseCapture,
-      DisableFocusChange,
-      crossterm::terminal::LeaveAlternateScreen,
-    )?;
+  pub fn shutdown_tui(&mut self) -> IoResult<()> {
+    let title_changed = rlock!(self.canvas).title().is_some();
+    match self.tui_guard.take() {
+      Some(mut guard) => {
+        guard.set_title_changed(title_changed);
+        guard.restore()
+      }
+      None => Ok(()),
+    }
+  }
+}
 
-    if crossterm::terminal::is_raw_mode_enabled()? {
-      crossterm::terminal::disable_raw_mode()?;
+/// Set the option by its name (same with the `Rsvim.opt` property) in the scope. Same with Vim's
+/// `:set`, the window (buffer) options are set for current window (buffer), and the windows
+/// (buffers) created later.
+pub fn set_option(
+  tree: &mut Tree,
+  buffers: &mut BuffersManager,
+  scope: OptionScope,
+  name: &str,
+  value: OptionValue,
+) -> AnyResult<()> {
+  match scope {
+    OptionScope::Global => set_global_option(tree, name, value),
+    OptionScope::Window => {
+      let mut options = tree.local_options().clone();
+      set_window_option(&mut options, name, value.clone())?;
+      tree.set_local_options(&options);
+      let window_options = current_window(tree).map(|window| window.options().clone());
+      if let Some(mut options) = window_options {
+        set_window_option(&mut options, name, value)?;
+        sync_current_window(tree, |window| window.set_options(&options));
+      }
+      Ok(())
+    }
+    OptionScope::Buffer => {
+      let mut options = buffers.local_options().clone();
+      set_buffer_option(&mut options, name, value.clone())?;
+      buffers.set_local_options(&options);
+      if let Some(buffer) = current_buffer(tree) {
+        let mut options = rlock!(buffer).options().clone();
+        set_buffer_option(&mut options, name, value)?;
+        wlock!(buffer).set_options(&options);
+        sync_current_window(tree, |_| {});
+      }
+      Ok(())
     }
+  }
+}
 
-    Ok(())
+fn set_global_option(tree: &mut Tree, name: &str, value: OptionValue) -> AnyResult<()> {
+  match (name, value) {
+    ("incSearch", OptionValue::Bool(v)) => tree.set_inc_search(v),
+    ("hlSearch", OptionValue::Bool(v)) => tree.set_hl_search(v),
+    ("ignoreCase", OptionValue::Bool(v)) => tree.set_ignore_case(v),
+    ("smartCase", OptionValue::Bool(v)) => tree.set_smart_case(v),
+    ("title", OptionValue::Bool(v)) => tree.set_title(v),
+    ("termGuiColors", OptionValue::Bool(v)) => tree.set_term_gui_colors(v),
+    ("lazyRedraw", OptionValue::Bool(v)) => tree.set_lazy_redraw(v),
+    ("splitBelow", OptionValue::Bool(v)) => tree.set_split_below(v),
+    ("splitRight", OptionValue::Bool(v)) => tree.set_split_right(v),
+    ("winMinHeight", OptionValue::Number(v)) => tree.set_win_min_height(v),
+    ("winMinWidth", OptionValue::Number(v)) => tree.set_win_min_width(v),
+    ("equalAlways", OptionValue::Bool(v)) => tree.set_equal_always(v),
+    (name, value) => anyhow::bail!("E518: Unknown option: {}={:?}", name, value),
+  }
+  Ok(())
+}
+
+fn set_window_option(
+  options: &mut WindowLocalOptions,
+  name: &str,
+  value: OptionValue,
+) -> AnyResult<()> {
+  match (name, value) {
+    ("wrap", OptionValue::Bool(v)) => options.set_wrap(v),
+    ("lineBreak", OptionValue::Bool(v)) => options.set_line_break(v),
+    ("list", OptionValue::Bool(v)) => options.set_list(v),
+    ("scroll", OptionValue::Number(v)) => options.set_scroll(v),
+    ("scrollOff", OptionValue::Number(v)) => options.set_scroll_off(v),
+    ("fillChars", OptionValue::String(v)) => options.set_fill_chars(FillChars::from_str(&v)?),
+    ("listChars", OptionValue::String(v)) => options.set_list_chars(ListChars::from_str(&v)?),
+    ("showBreak", OptionValue::String(v)) => options.set_show_break(v),
+    ("foldMethod", OptionValue::String(v)) => options.set_fold_method(FoldMethod::from_str(&v)?),
+    ("foldLevel", OptionValue::Number(v)) => options.set_fold_level(v),
+    ("foldEnable", OptionValue::Bool(v)) => options.set_fold_enable(v),
+    ("foldMarker", OptionValue::String(v)) => options.set_fold_marker(FoldMarker::from_str(&v)?),
+    ("cursorLine", OptionValue::Bool(v)) => options.set_cursor_line(v),
+    ("number", OptionValue::Bool(v)) => options.set_number(v),
+    ("foldColumn", OptionValue::Number(v)) => options.set_fold_column(v),
+    ("signColumn", OptionValue::String(v)) => options.set_sign_column(SignColumn::from_str(&v)?),
+    ("winFixHeight", OptionValue::Bool(v)) => options.set_win_fix_height(v),
+    ("winFixWidth", OptionValue::Bool(v)) => options.set_win_fix_width(v),
+    (name, value) => anyhow::bail!("E518: Unknown option: {}={:?}", name, value),
+  }
+  Ok(())
+}
+
+fn set_buffer_option(
+  options: &mut BufferLocalOptions,
+  name: &str,
+  value: OptionValue,
+) -> AnyResult<()> {
+  match (name, value) {
+    ("modifiable", OptionValue::Bool(v)) => options.set_modifiable(v),
+    ("readonly", OptionValue::Bool(v)) => options.set_readonly(v),
+    ("bufType", OptionValue::String(v)) => match BufType::try_from(v.as_str()) {
+      Ok(v) => options.set_buf_type(v),
+      Err(_) => anyhow::bail!("E474: Invalid argument: bufType={}", v),
+    },
+    ("tabStop", OptionValue::Number(v)) => match u16::try_from(v) {
+      Ok(v) if v > 0 => options.set_tab_stop(v),
+      _ => anyhow::bail!("E487: Argument must be positive: tabStop={}", v),
+    },
+    (name, value) => anyhow::bail!("E518: Unknown option: {}={:?}", name, value),
+  }
+  Ok(())
+}
+
+// The focused window.
+fn current_window(tree: &Tree) -> Option<&Window> {
+  match tree.node(&tree.focused_window_id()?) {
+    Some(TreeNode::Window(window)) => Some(window),
+    _ => None,
+  }
+}
+
+// Update current window and sync its viewport from its current top line, i.e. the options of it
+// or its buffer are changed. The cursor stays at its position in the buffer, and the gutters are
+// composed again.
+fn sync_current_window(tree: &mut Tree, f: impl FnOnce(&mut Window)) {
+  update_focused_window(tree, |window| {
+    f(window);
+    let viewport = window.viewport();
+    let mut viewport = wlock!(viewport);
+    let cursor = *viewport.cursor();
+    let start_line_idx = viewport.start_line_idx();
+    viewport.sync_from_top_left(start_line_idx, 0);
+    if let Some(cursor) = viewport.cursor_viewport_at(cursor.line_idx(), cursor.char_idx()) {
+      viewport.set_cursor(cursor);
+    }
+  });
+  tree.sync_gutters();
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use crate::evloop::sink::RecordingSink;
+  use crate::state::fsm::visual::cursor_position;
+  use crate::state::register::{Register, RegisterKind};
+  use crate::test::js::make_snapshot;
+
+  use assert_fs::prelude::*;
+  use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+  fn dump(commands: &[ShaderCommand]) -> Vec<String> {
+    commands
+      .iter()
+      .map(|command| format!("{:?}", command))
+      .collect()
+  }
+
+  #[test]
+  fn render1() {
+    let tmpdir = assert_fs::TempDir::new().unwrap();
+    let file = tmpdir.child("render1.txt");
+    file.write_str("hello\n").unwrap();
+
+    let sink = RecordingSink::new();
+    let output = sink.output();
+    let mut event_loop = EventLoop::new_with_sink(
+      CliOpt::default(),
+      make_snapshot(),
+      U16Size::new(10, 3),
+      Box::new(sink),
+    )
+    .unwrap();
+    wlock!(event_loop.buffers)
+      .new_file_buffer(file.path())
+      .unwrap();
+    event_loop.init_windows().unwrap();
+    event_loop.init_tui_done().unwrap();
+
+    // The initial cursor, then the first frame is drawn with the cursor hidden.
+    let commands = dump(&std::mem::take(&mut *output.write()));
+    assert_eq!(
+      commands[..5],
+      [
+        "ShaderCommand::CursorEnableBlinking(EnableBlinking)",
+        "ShaderCommand::CursorShow(Show)",
+        "ShaderCommand::CursorSetCursorStyle(DefaultUserShape)",
+        "ShaderCommand::CursorMoveTo(MoveTo(0, 0))",
+        "ShaderCommand::CursorHide(Hide)",
+      ]
+    );
+    assert!(commands
+      .iter()
+      .any(|command| command.starts_with("ShaderCommand::StylePrintString(Print(\"hello")));
+    assert_eq!(commands.last().unwrap(), "ShaderCommand::CursorShow(Show)");
+
+    // Nothing changed, only the cursor is restored.
+    event_loop.render().unwrap();
+    assert_eq!(
+      dump(&output.read()),
+      vec![
+        "ShaderCommand::CursorHide(Hide)",
+        "ShaderCommand::CursorMoveTo(MoveTo(0, 0))",
+        "ShaderCommand::CursorShow(Show)",
+      ]
+    );
+  }
+
+  fn block_on<F: std::future::Future>(future: F) -> F::Output {
+    tokio::runtime::Builder::new_current_thread()
+      .enable_all()
+      .build()
+      .unwrap()
+      .block_on(future)
+  }
+
+  // Make the event loop with the file, the renders are counted by the render profile.
+  fn make_event_loop(file: &assert_fs::fixture::ChildPath) -> EventLoop {
+    let mut event_loop = EventLoop::new_with_sink(
+      CliOpt::default(),
+      make_snapshot(),
+      U16Size::new(20, 5),
+      Box::new(RecordingSink::new()),
+    )
+    .unwrap();
+    wlock!(event_loop.buffers)
+      .new_file_buffer(file.path())
+      .unwrap();
+    event_loop.init_windows().unwrap();
+    wlock!(event_loop.state)
+      .render_profile_mut()
+      .set_enabled(true);
+    event_loop
+  }
+
+  // The terminal events of the keys, they're all immediately available.
+  fn key_events(keys: &str) -> impl Stream<Item = IoResult<Event>> + Unpin {
+    let events: Vec<IoResult<Event>> = keys
+      .chars()
+      .map(|c| {
+        let code = match c {
+          '\x1b' => KeyCode::Esc,
+          c => KeyCode::Char(c),
+        };
+        Ok(Event::Key(KeyEvent::new(code, KeyModifiers::NONE)))
+      })
+      .collect();
+    futures::stream::iter(events).chain(futures::stream::pending())
+  }
+
+  fn renders(event_loop: &EventLoop) -> usize {
+    rlock!(event_loop.state).render_profile().frames().len()
+  }
+
+  fn buffer_text(event_loop: &EventLoop) -> String {
+    let buffer = current_buffer(&rlock!(event_loop.tree)).unwrap();
+    let buffer = rlock!(buffer);
+    buffer.lines().map(|line| line.to_string()).collect()
+  }
+
+  #[test]
+  fn render_once1() {
+    let tmpdir = assert_fs::TempDir::new().unwrap();
+    let file = tmpdir.child("render_once1.txt");
+    file.write_str("hello\n").unwrap();
+    let mut event_loop = make_event_loop(&file);
+
+    // The buffered events are handled in one batch, and rendered once.
+    let mut events = key_events("ixyz\x1b");
+    assert!(block_on(event_loop.run_once(&mut events)).unwrap());
+    assert_eq!(buffer_text(&event_loop), "xyzhello\n");
+    assert_eq!(renders(&event_loop), 1);
+    assert!(!event_loop.render_scheduler.is_dirty());
+  }
+
+  #[test]
+  fn lazy_redraw1() {
+    let tmpdir = assert_fs::TempDir::new().unwrap();
+    let file = tmpdir.child("lazy_redraw1.txt");
+    file.write_str("a\nb\nc\nd\ne\nf\ng\n").unwrap();
+    let mut event_loop = make_event_loop(&file);
+    wlock!(event_loop.state).registers_mut().yank(
+      Some('a'),
+      Register::new(RegisterKind::Charwise, "jjj".to_string()),
+    );
+    let cursor_line = |event_loop: &EventLoop| {
+      let tree = rlock!(event_loop.tree);
+      cursor_position(&tree).unwrap().line_idx()
+    };
+
+    // Only the final state is rendered.
+    wlock!(event_loop.tree).set_lazy_redraw(true);
+    let mut events = key_events("@a");
+    assert!(block_on(event_loop.run_once(&mut events)).unwrap());
+    assert_eq!(cursor_line(&event_loop), 3);
+    assert_eq!(renders(&event_loop), 1);
+    assert!(!rlock!(event_loop.state).is_render_suppressed());
+
+    // Each replayed key is rendered, then the final state.
+    wlock!(event_loop.tree).set_lazy_redraw(false);
+    let mut events = key_events("@a");
+    assert!(block_on(event_loop.run_once(&mut events)).unwrap());
+    assert_eq!(cursor_line(&event_loop), 6);
+    assert_eq!(renders(&event_loop), 1 + 4);
+  }
+
+  #[test]
+  fn profile1() {
+    let sink = RecordingSink::new();
+    let mut event_loop = EventLoop::new_with_sink(
+      CliOpt::default(),
+      make_snapshot(),
+      U16Size::new(40, 10),
+      Box::new(sink),
+    )
+    .unwrap();
+    wlock!(event_loop.buffers).new_empty_buffer();
+    event_loop.init_windows().unwrap();
+    event_loop.render().unwrap();
+    // The profiling is disabled by default.
+    assert!(rlock!(event_loop.state)
+      .render_profile()
+      .frames()
+      .is_empty());
+
+    wlock!(event_loop.state)
+      .render_profile_mut()
+      .set_enabled(true);
+    for _ in 0..5 {
+      event_loop.canvas.write().clear();
+      event_loop.render().unwrap();
+    }
+    assert_eq!(rlock!(event_loop.state).render_profile().frames().len(), 5);
+
+    // Type `:profile` in the editor.
+    for c in ":profile\n".chars() {
+      let code = match c {
+        '\n' => KeyCode::Enter,
+        c => KeyCode::Char(c),
+      };
+      let event = Event::Key(KeyEvent::new(code, KeyModifiers::NONE));
+      wlock!(event_loop.state).handle(event_loop.tree.clone(), event_loop.buffers.clone(), event);
+    }
+    let buffer = current_buffer(&rlock!(event_loop.tree)).unwrap();
+    let buffer = rlock!(buffer);
+    assert_eq!(buffer.name().as_deref(), Some("[Profile]"));
+    assert!(!buffer.modifiable());
+    let report: String = buffer.lines().map(|line| line.to_string()).collect();
+    assert!(report.starts_with("Render profile of the last 5 frames\n"));
+    for phase in ["prepare", "draw", "diff", "flush", "total"] {
+      let line = report.lines().find(|line| line.starts_with(phase)).unwrap();
+      let millis: Vec<f64> = line
+        .split_whitespace()
+        .skip(1)
+        .map(|n| n.parse().unwrap())
+        .collect();
+      assert_eq!(millis.len(), 3);
+      // min <= avg <= p99
+      assert!(0.0 <= millis[0] && millis[0] <= millis[1] && millis[1] <= millis[2]);
+    }
   }
 }
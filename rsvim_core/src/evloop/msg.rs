@@ -1,11 +1,20 @@
 //! Messages used inside [`EventLoop`](crate::evloop::EventLoop).
 
+use crate::res::AnyResult;
+use crate::state::clipboard::ClipboardPut;
+use crate::ui::canvas::ShaderCommand;
+
 // Worker to Master message {
 
 #[derive(Debug)]
 /// Message.
 pub enum WorkerToMasterMessage {
   // BufferLoadedBytes(BufferLoadedBytes),
+  /// The register `+`/`*` is written to the clipboard, with the command to output to the
+  /// terminal, i.e. OSC 52.
+  ClipboardWritten(AnyResult<Option<ShaderCommand>>),
+  /// The clipboard is read for the put from the register `+`/`*`.
+  ClipboardRead(ClipboardPut, AnyResult<String>),
 }
 
 // Worker to Master message }
@@ -0,0 +1,68 @@
+//! Render scheduler, it coalesces the redraws of an event batch into one frame.
+
+use futures::{FutureExt, Stream, StreamExt};
+
+#[derive(Debug, Copy, Clone, Default)]
+/// The render scheduler. The event loop marks it dirty while handling the events, and renders
+/// only once at the end of each iteration, thus multiple state changes (e.g. a mapping that moves
+/// the cursor and scrolls) are coalesced into one frame.
+pub struct RenderScheduler {
+  dirty: bool,
+}
+
+impl RenderScheduler {
+  pub fn new() -> Self {
+    RenderScheduler { dirty: false }
+  }
+
+  /// Whether a render is needed.
+  pub fn is_dirty(&self) -> bool {
+    self.dirty
+  }
+
+  /// Mark a render is needed.
+  pub fn mark_dirty(&mut self) {
+    self.dirty = true;
+  }
+
+  /// Take the dirty flag and reset it, returns whether a render is needed.
+  pub fn take(&mut self) -> bool {
+    std::mem::take(&mut self.dirty)
+  }
+
+  /// Whether to render now, the dirty flag is reset if so. If 'lazyredraw' is on, the render is
+  /// skipped while it's suppressed, and the dirty flag is kept for the final render.
+  pub fn should_render(&mut self, lazy_redraw: bool, render_suppressed: bool) -> bool {
+    if lazy_redraw && render_suppressed {
+      false
+    } else {
+      self.take()
+    }
+  }
+}
+
+/// Take all the immediately-available items of the stream without waiting, i.e. the buffered
+/// terminal events on fast key repeat or paste. It stops at the pending or exhausted stream.
+pub fn ready_items<S: Stream + Unpin>(stream: &mut S) -> Vec<S::Item> {
+  let mut items = vec![];
+  while let Some(Some(item)) = stream.next().now_or_never() {
+    items.push(item);
+  }
+  items
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn ready_items1() {
+    let mut stream = futures::stream::iter(vec![1, 2, 3]).chain(futures::stream::pending());
+    assert_eq!(ready_items(&mut stream), vec![1, 2, 3]);
+    assert!(ready_items(&mut stream).is_empty());
+
+    let mut stream = futures::stream::iter(vec![1]);
+    assert_eq!(ready_items(&mut stream), vec![1]);
+    assert!(ready_items(&mut stream).is_empty());
+  }
+}
@@ -0,0 +1,166 @@
+//! The output layer of the rendering, i.e. where the shader commands are written to.
+//!
+//! The event loop only emits [`ShaderCommand`]s to a [`ShaderSink`], thus the rendering can run
+//! without a real terminal, e.g. the [`RecordingSink`] records the commands for testing.
+
+use crate::res::IoResult;
+use crate::ui::canvas::ShaderCommand;
+
+use crossterm::queue;
+use parking_lot::RwLock;
+use std::io::Write;
+use std::io::{BufWriter, Stdout};
+use std::sync::Arc;
+
+/// The output of the shader commands.
+pub trait ShaderSink {
+  /// Queue a shader command, it's not output until [`flush`](ShaderSink::flush).
+  fn queue(&mut self, command: ShaderCommand) -> IoResult<()>;
+
+  /// Output all the queued shader commands.
+  fn flush(&mut self) -> IoResult<()>;
+}
+
+#[derive(Debug)]
+/// The sink writes the commands to the stdout, i.e. the terminal.
+pub struct StdoutSink {
+  writer: BufWriter<Stdout>,
+}
+
+impl StdoutSink {
+  pub fn new() -> Self {
+    StdoutSink {
+      writer: BufWriter::new(std::io::stdout()),
+    }
+  }
+}
+
+impl Default for StdoutSink {
+  fn default() -> Self {
+    StdoutSink::new()
+  }
+}
+
+impl ShaderSink for StdoutSink {
+  fn queue(&mut self, command: ShaderCommand) -> IoResult<()> {
+    match command {
+      ShaderCommand::CursorSetCursorStyle(command) => queue!(self.writer, command),
+      ShaderCommand::CursorDisableBlinking(command) => queue!(self.writer, command),
+      ShaderCommand::CursorEnableBlinking(command) => queue!(self.writer, command),
+      ShaderCommand::CursorHide(command) => queue!(self.writer, command),
+      ShaderCommand::CursorMoveDown(command) => queue!(self.writer, command),
+      ShaderCommand::CursorMoveLeft(command) => queue!(self.writer, command),
+      ShaderCommand::CursorMoveRight(command) => queue!(self.writer, command),
+      ShaderCommand::CursorMoveTo(command) => queue!(self.writer, command),
+      ShaderCommand::CursorMoveToColumn(command) => queue!(self.writer, command),
+      ShaderCommand::CursorMoveToNextLine(command) => queue!(self.writer, command),
+      ShaderCommand::CursorMoveToPreviousLine(command) => queue!(self.writer, command),
+      ShaderCommand::CursorMoveToRow(command) => queue!(self.writer, command),
+      ShaderCommand::CursorMoveUp(command) => queue!(self.writer, command),
+      ShaderCommand::CursorRestorePosition(command) => queue!(self.writer, command),
+      ShaderCommand::CursorSavePosition(command) => queue!(self.writer, command),
+      ShaderCommand::CursorShow(command) => queue!(self.writer, command),
+      ShaderCommand::EventDisableBracketedPaste(command) => queue!(self.writer, command),
+      ShaderCommand::EventDisableFocusChange(command) => queue!(self.writer, command),
+      ShaderCommand::EventDisableMouseCapture(command) => queue!(self.writer, command),
+      ShaderCommand::EventEnableBracketedPaste(command) => queue!(self.writer, command),
+      ShaderCommand::EventEnableFocusChange(command) => queue!(self.writer, command),
+      ShaderCommand::EventEnableMouseCapture(command) => queue!(self.writer, command),
+      ShaderCommand::EventPopKeyboardEnhancementFlags(command) => queue!(self.writer, command),
+      ShaderCommand::EventPushKeyboardEnhancementFlags(command) => queue!(self.writer, command),
+      ShaderCommand::StyleResetColor(command) => queue!(self.writer, command),
+      ShaderCommand::StyleSetAttribute(command) => queue!(self.writer, command),
+      ShaderCommand::StyleSetAttributes(command) => queue!(self.writer, command),
+      ShaderCommand::StyleSetBackgroundColor(command) => queue!(self.writer, command),
+      ShaderCommand::StyleSetColors(command) => queue!(self.writer, command),
+      ShaderCommand::StyleSetForegroundColor(command) => queue!(self.writer, command),
+      ShaderCommand::StyleSetStyle(command) => queue!(self.writer, command),
+      ShaderCommand::StyleSetUnderlineColor(command) => queue!(self.writer, command),
+      ShaderCommand::StylePrintStyledContentString(command) => queue!(self.writer, command),
+      ShaderCommand::StylePrintString(command) => queue!(self.writer, command),
+      ShaderCommand::TerminalBeginSynchronizedUpdate(command) => queue!(self.writer, command),
+      ShaderCommand::TerminalClear(command) => queue!(self.writer, command),
+      ShaderCommand::TerminalDisableLineWrap(command) => queue!(self.writer, command),
+      ShaderCommand::TerminalEnableLineWrap(command) => queue!(self.writer, command),
+      ShaderCommand::TerminalEndSynchronizedUpdate(command) => queue!(self.writer, command),
+      ShaderCommand::TerminalEnterAlternateScreen(command) => queue!(self.writer, command),
+      ShaderCommand::TerminalLeaveAlternateScreen(command) => queue!(self.writer, command),
+      ShaderCommand::TerminalScrollDown(command) => queue!(self.writer, command),
+      ShaderCommand::TerminalScrollUp(command) => queue!(self.writer, command),
+      ShaderCommand::TerminalSetSize(command) => queue!(self.writer, command),
+      ShaderCommand::TerminalSetTitle(command) => queue!(self.writer, command),
+    }
+  }
+
+  fn flush(&mut self) -> IoResult<()> {
+    self.writer.flush()
+  }
+}
+
+/// The shader commands output by a [`RecordingSink`].
+pub type RecordedShaderCommandsArc = Arc<RwLock<Vec<ShaderCommand>>>;
+
+#[derive(Debug, Default)]
+/// The sink records the commands instead of writing them to a terminal, i.e. the headless
+/// rendering. The queued commands are moved to the output on [`flush`](ShaderSink::flush), the
+/// same with a real terminal.
+pub struct RecordingSink {
+  queued: Vec<ShaderCommand>,
+  output: RecordedShaderCommandsArc,
+}
+
+impl RecordingSink {
+  pub fn new() -> Self {
+    RecordingSink::default()
+  }
+
+  /// The flushed commands, it's shared with the sink thus it can be checked after the sink is
+  /// moved into the event loop.
+  pub fn output(&self) -> RecordedShaderCommandsArc {
+    self.output.clone()
+  }
+}
+
+impl ShaderSink for RecordingSink {
+  fn queue(&mut self, command: ShaderCommand) -> IoResult<()> {
+    self.queued.push(command);
+    Ok(())
+  }
+
+  fn flush(&mut self) -> IoResult<()> {
+    self.output.write().append(&mut self.queued);
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn recording1() {
+    let mut sink = RecordingSink::new();
+    let output = sink.output();
+    sink
+      .queue(ShaderCommand::CursorHide(crossterm::cursor::Hide))
+      .unwrap();
+    sink
+      .queue(ShaderCommand::CursorMoveTo(crossterm::cursor::MoveTo(1, 2)))
+      .unwrap();
+    assert!(output.read().is_empty());
+
+    sink.flush().unwrap();
+    let output = output
+      .read()
+      .iter()
+      .map(|command| format!("{:?}", command))
+      .collect::<Vec<_>>();
+    assert_eq!(
+      output,
+      vec![
+        "ShaderCommand::CursorHide(Hide)",
+        "ShaderCommand::CursorMoveTo(MoveTo(1, 2))",
+      ]
+    );
+  }
+}
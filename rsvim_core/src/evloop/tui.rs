@@ -0,0 +1,186 @@
+//! Terminal setup and restore, i.e. the raw mode, the alternate screen and the title stack.
+
+use crate::res::IoResult;
+
+use crossterm::event::{
+  DisableFocusChange, DisableMouseCapture, EnableFocusChange, EnableMouseCapture,
+};
+use crossterm::queue;
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+// Save the terminal title on the xterm title stack, it's restored on exit.
+const PUSH_TITLE: &str = "\x1b[22;0t";
+// Restore the terminal title saved on the xterm title stack.
+const POP_TITLE: &str = "\x1b[23;0t";
+
+// Whether the terminal is set up, thus it's restored only once, either on shutdown, error or
+// panic.
+static ENTERED: AtomicBool = AtomicBool::new(false);
+
+/// Output the sequences that enter the alternate screen and save the terminal title.
+pub fn enter_tui<W: Write>(out: &mut W) -> IoResult<()> {
+  queue!(
+    out,
+    crossterm::terminal::EnterAlternateScreen,
+    crossterm::terminal::Clear(crossterm::terminal::ClearType::All),
+    EnableMouseCapture,
+    EnableFocusChange,
+    crossterm::style::Print(PUSH_TITLE),
+  )?;
+  out.flush()
+}
+
+/// Output the sequences that restore the terminal title, leave the alternate screen and show the
+/// cursor. If the title was changed, it's cleared before restoring the saved title, thus the
+/// terminal without title stack shows a neutral title.
+pub fn leave_tui<W: Write>(out: &mut W, title_changed: bool) -> IoResult<()> {
+  if title_changed {
+    queue!(out, crossterm::terminal::SetTitle(""))?;
+  }
+  queue!(
+    out,
+    crossterm::style::Print(POP_TITLE),
+    DisableMouseCapture,
+    DisableFocusChange,
+    crossterm::terminal::LeaveAlternateScreen,
+    crossterm::cursor::Show,
+  )?;
+  out.flush()
+}
+
+#[derive(Debug)]
+/// The guard of the terminal setup, it enables the raw mode and enters the alternate screen, and
+/// restores them when dropped. Thus an error or panic doesn't leave the shell in raw mode and the
+/// alternate screen.
+pub struct TuiGuard {
+  title_changed: bool,
+}
+
+impl TuiGuard {
+  /// Enable the raw mode and enter the alternate screen.
+  pub fn enter() -> IoResult<Self> {
+    if !crossterm::terminal::is_raw_mode_enabled()? {
+      crossterm::terminal::enable_raw_mode()?;
+    }
+    ENTERED.store(true, Ordering::SeqCst);
+    enter_tui(&mut std::io::stdout())?;
+
+    // Restore the terminal if panics, the hook runs before the guard is dropped by unwinding.
+    install_panic_hook(|| {
+      let _ = restore_tui(false);
+    });
+
+    Ok(TuiGuard {
+      title_changed: false,
+    })
+  }
+
+  /// Set whether the terminal title was changed, see [`leave_tui`].
+  pub fn set_title_changed(&mut self, title_changed: bool) {
+    self.title_changed = title_changed;
+  }
+
+  /// Restore the terminal, it does nothing if the terminal is already restored.
+  pub fn restore(&mut self) -> IoResult<()> {
+    restore_tui(self.title_changed)
+  }
+}
+
+impl Drop for TuiGuard {
+  fn drop(&mut self) {
+    let _ = self.restore();
+  }
+}
+
+// Install the panic hook that runs the cleanup before printing the panic, i.e. it's chained to
+// the previous hook.
+fn install_panic_hook<F>(cleanup: F)
+where
+  F: Fn() + Send + Sync + 'static,
+{
+  let hook = std::panic::take_hook();
+  std::panic::set_hook(Box::new(move |info| {
+    cleanup();
+    hook(info);
+  }));
+}
+
+// Leave the alternate screen and disable the raw mode, only once.
+fn restore_tui(title_changed: bool) -> IoResult<()> {
+  if !ENTERED.swap(false, Ordering::SeqCst) {
+    return Ok(());
+  }
+
+  leave_tui(&mut std::io::stdout(), title_changed)?;
+  if crossterm::terminal::is_raw_mode_enabled()? {
+    crossterm::terminal::disable_raw_mode()?;
+  }
+
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn position(out: &[u8], sequence: &str) -> usize {
+    let out = String::from_utf8_lossy(out);
+    out
+      .find(sequence)
+      .unwrap_or_else(|| panic!("{:?} not found in {:?}", sequence, out))
+  }
+
+  #[test]
+  fn enter_leave1() {
+    let mut out: Vec<u8> = vec![];
+    enter_tui(&mut out).unwrap();
+    let entered = out.len();
+    leave_tui(&mut out, false).unwrap();
+
+    // Enter the alternate screen, then save the title.
+    let enter_screen = position(&out, "\x1b[?1049h");
+    let push_title = position(&out, PUSH_TITLE);
+    assert!(enter_screen < push_title);
+    assert!(push_title < entered);
+
+    // Restore the title, then leave the alternate screen and show the cursor.
+    let pop_title = position(&out, POP_TITLE);
+    let leave_screen = position(&out, "\x1b[?1049l");
+    let show_cursor = position(&out, "\x1b[?25h");
+    assert!(entered <= pop_title);
+    assert!(pop_title < leave_screen);
+    assert!(leave_screen < show_cursor);
+  }
+
+  #[test]
+  fn install_panic_hook1() {
+    static CLEANUP: AtomicBool = AtomicBool::new(false);
+    install_panic_hook(|| CLEANUP.store(true, Ordering::SeqCst));
+
+    let result = std::panic::catch_unwind(|| panic!("install_panic_hook1"));
+    assert!(result.is_err());
+    assert!(CLEANUP.load(Ordering::SeqCst));
+
+    // Uninstall the hook.
+    let _ = std::panic::take_hook();
+  }
+
+  #[test]
+  fn restore_tui1() {
+    // The terminal is not set up, thus it's not restored.
+    assert!(restore_tui(false).is_ok());
+    assert!(!ENTERED.load(Ordering::SeqCst));
+  }
+
+  #[test]
+  fn leave2() {
+    let mut out: Vec<u8> = vec![];
+    leave_tui(&mut out, true).unwrap();
+
+    // The changed title is cleared before restoring the saved title.
+    let clear_title = position(&out, "\x1b]0;\x07");
+    let pop_title = position(&out, POP_TITLE);
+    assert!(clear_title < pop_title);
+  }
+}
@@ -0,0 +1,207 @@
+//! Headless editor, i.e. embed the editor into another program.
+//!
+//! The [`Editor`] wires the buffers, the widget tree, the editing state and an in-memory canvas,
+//! it doesn't touch the terminal or stdout. The keys are sent with [`Editor::send_key`] and the
+//! screen is rendered with [`Editor::render`], which returns the frame instead of printing it.
+//!
+//! NOTE: The javascript runtime is driven by the event loop, thus it is not wired here. The
+//! user-defined commands, `:write` and the autocmds need the event loop, they are queued in the
+//! [`State`] and can be taken by [`State::take_pending_ex_commands`].
+//!
+//! The [`prelude`](crate::prelude) re-exports the types needed by the embedders, see
+//! `tests/headless.rs` for an example that only uses the prelude.
+
+use crate::buf::{BufferId, BuffersManager, BuffersManagerArc};
+use crate::cart::U16Size;
+use crate::envar;
+use crate::res::IoResult;
+use crate::state::fsm::command_line::{switch_buffer, sync_search_highlights};
+use crate::state::{State, StateArc};
+use crate::ui::canvas::{Canvas, CanvasArc, Frame};
+use crate::ui::tree::{Tree, TreeArc};
+use crate::{rlock, wlock};
+
+use crossterm::event::{Event, KeyEvent};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+#[derive(Debug, Clone)]
+/// The builder of [`Editor`].
+pub struct EditorBuilder {
+  size: U16Size,
+  files: Vec<PathBuf>,
+}
+
+impl EditorBuilder {
+  /// The terminal size, by default is 80x24.
+  pub fn size(&mut self, width: u16, height: u16) -> &mut Self {
+    self.size = U16Size::new(width, height);
+    self
+  }
+
+  /// Open the file, the first file is shown in the window. If no file is opened, the window shows
+  /// an empty buffer.
+  pub fn file(&mut self, path: &Path) -> &mut Self {
+    self.files.push(path.to_path_buf());
+    self
+  }
+
+  pub fn build(&self) -> IoResult<Editor> {
+    let mut buffers = BuffersManager::new();
+    let mut buf_ids = vec![];
+    for file in self.files.iter() {
+      let buf_id = match buffers.find_file_buffer(file) {
+        Some(buf_id) => buf_id,
+        None => buffers.new_file_buffer(file)?,
+      };
+      buf_ids.push(buf_id);
+    }
+    if buf_ids.is_empty() {
+      buf_ids.push(buffers.new_empty_buffer());
+    }
+
+    let mut tree = Tree::new(self.size);
+    tree.init_layout(Arc::downgrade(buffers.get(&buf_ids[0]).unwrap()));
+
+    Ok(Editor {
+      canvas: Canvas::to_arc(Canvas::new(self.size)),
+      tree: Tree::to_arc(tree),
+      buffers: BuffersManager::to_arc(buffers),
+      state: State::to_arc(State::default()),
+    })
+  }
+}
+
+impl Default for EditorBuilder {
+  fn default() -> Self {
+    EditorBuilder {
+      size: U16Size::new(80, 24),
+      files: vec![],
+    }
+  }
+}
+
+#[derive(Debug)]
+/// The headless editor, see the [module](crate::headless) document.
+pub struct Editor {
+  canvas: CanvasArc,
+  tree: TreeArc,
+  buffers: BuffersManagerArc,
+  state: StateArc,
+}
+
+impl Editor {
+  pub fn builder() -> EditorBuilder {
+    EditorBuilder::default()
+  }
+
+  /// Open the file in current window, i.e. `:edit`. If the file is already opened, the window
+  /// switches to its buffer.
+  pub fn open(&mut self, path: &Path) -> IoResult<BufferId> {
+    let buf_id = {
+      let mut buffers = wlock!(self.buffers);
+      match buffers.find_file_buffer(path) {
+        Some(buf_id) => buf_id,
+        None => buffers.new_file_buffer(path)?,
+      }
+    };
+    let buffer = rlock!(self.buffers).get(&buf_id).unwrap().clone();
+    switch_buffer(&mut wlock!(self.tree), &buffer);
+    Ok(buf_id)
+  }
+
+  /// Resize the terminal, the whole screen is redrawn on next render.
+  pub fn resize(&mut self, width: u16, height: u16) {
+    let size = U16Size::new(width, height);
+    wlock!(self.tree).resize(size);
+    self.canvas = Canvas::to_arc(Canvas::new(size));
+  }
+
+  /// Handle the key, same with the key typed in the terminal.
+  pub fn send_key(&mut self, key: KeyEvent) {
+    let mut state = wlock!(self.state);
+    state.handle(self.tree.clone(), self.buffers.clone(), Event::Key(key));
+    // The keys queued by the register executions are replayed at once.
+    while let Some(key) = state.macro_recorder_mut().next_key() {
+      state.handle(self.tree.clone(), self.buffers.clone(), Event::Key(key));
+    }
+    // There's no js runtime to dispatch the autocmds.
+    state.take_pending_autocmds();
+  }
+
+  /// Draw the widgets and returns current frame.
+  pub fn render(&mut self) -> Frame {
+    let state = rlock!(self.state);
+    let mut tree = wlock!(self.tree);
+    sync_search_highlights(&state, &tree);
+    tree.set_message(state.message().clone());
+    tree.set_cursor_style(state.mode().cursor_style());
+    tree.sync_tab_line();
+    tree.sync_gutters();
+    if tree.take_redraw() {
+      wlock!(self.canvas).clear();
+    }
+    tree.draw(self.canvas.clone());
+    let frame = rlock!(self.canvas).frame().clone();
+    frame
+  }
+
+  pub fn tree(&self) -> TreeArc {
+    self.tree.clone()
+  }
+
+  pub fn buffers(&self) -> BuffersManagerArc {
+    self.buffers.clone()
+  }
+
+  pub fn state(&self) -> StateArc {
+    self.state.clone()
+  }
+
+  pub fn canvas(&self) -> CanvasArc {
+    self.canvas.clone()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use crate::test::render::frame_snapshot;
+
+  use crossterm::event::{KeyCode, KeyModifiers};
+
+  fn send_keys(editor: &mut Editor, keys: &str) {
+    for c in keys.chars() {
+      editor.send_key(KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE));
+    }
+  }
+
+  #[test]
+  fn editor1() {
+    let tmpdir = assert_fs::TempDir::new().unwrap();
+    let path = tmpdir.path().join("a.txt");
+    std::fs::write(&path, "hello\nworld\n").unwrap();
+
+    let mut editor = Editor::builder().size(10, 4).build().unwrap();
+    assert_eq!(
+      frame_snapshot(&editor.render()),
+      vec!["          ", "~         ", "~         ", "          "]
+    );
+
+    editor.open(&path).unwrap();
+    send_keys(&mut editor, "jA!");
+    editor.send_key(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE));
+    assert_eq!(
+      frame_snapshot(&editor.render()),
+      vec!["hello     ", "world!    ", "~         ", "          "]
+    );
+
+    // The window is stretched to the new size, the message area stays on the last row.
+    editor.resize(8, 3);
+    assert_eq!(
+      frame_snapshot(&editor.render()),
+      vec!["hello   ", "world!  ", "        "]
+    );
+  }
+}
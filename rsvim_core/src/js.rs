@@ -1,7 +1,10 @@
 //! JavaScript runtime.
 
-use crate::buf::BuffersManagerArc;
+use crate::buf::{BufferId, BuffersManagerArc};
 use crate::cli::CliOpt;
+use crate::envar;
+use crate::js::autocmd::EventHooks;
+use crate::js::binding::set_property_to;
 use crate::js::err::JsError;
 use crate::js::exception::ExceptionState;
 use crate::js::hook::module_resolve_cb;
@@ -11,6 +14,8 @@ use crate::js::module::{
 };
 use crate::js::msg::{EventLoopToJsRuntimeMessage, JsRuntimeToEventLoopMessage};
 use crate::res::AnyErr;
+use crate::rlock;
+use crate::state::autocmd::{AutocmdContext, AutocmdEvent};
 use crate::state::StateArc;
 use crate::ui::tree::TreeArc;
 
@@ -27,6 +32,7 @@ use std::time::Instant;
 use tokio::sync::mpsc::{Receiver, Sender};
 use tracing::{error, trace};
 
+pub mod autocmd;
 pub mod binding;
 pub mod constant;
 pub mod err;
@@ -58,6 +64,8 @@ pub struct JsRuntimeOptions {
   // pub expose_gc: bool,
   /// V8 flags.
   pub v8_flags: Vec<String>,
+  /// Disable the startup snapshot, i.e. always evaluate the built-in modules on startup.
+  pub disable_snapshot: bool,
 }
 
 // /// A vector with JS callbacks and parameters.
@@ -332,6 +340,12 @@ pub struct JsRuntimeState {
   pub exceptions: ExceptionState,
   /// Runtime options.
   pub options: JsRuntimeOptions,
+  /// User-defined Ex commands callbacks registered by `Rsvim.cmd.create`, by command name.
+  pub user_commands: HashMap<String, v8::Global<v8::Function>>,
+  /// Autocmd event hooks registered by `Rsvim.autocmd`.
+  pub event_hooks: EventHooks,
+  /// Number of the built-in modules evaluated on startup, it's 0 if started from snapshot.
+  pub builtin_modules_evaluated: usize,
   // /// Tracks wake event for current loop iteration.
   // pub wake_event_queued: bool,
 
@@ -349,6 +363,16 @@ pub struct JsRuntimeState {
   // Data Access for RSVIM }
 }
 
+impl JsRuntimeState {
+  /// Send the message to the event loop without waiting, the message is dropped if the channel is
+  /// full or closed.
+  pub fn send_to_master(&self, msg: JsRuntimeToEventLoopMessage) {
+    if let Err(e) = self.js_runtime_send_to_master.try_send(msg) {
+      error!("Failed to send message to event loop:{:?}", e);
+    }
+  }
+}
+
 /// Snapshot data for startup.
 pub struct SnapshotData {
   pub value: &'static [u8],
@@ -372,10 +396,14 @@ pub struct JsRuntime {
 
 impl JsRuntime {
   /// Creates a new JsRuntime based on provided options.
+  ///
+  /// It starts from the `snapshot`, i.e. the one built and embedded in the binary at build time.
+  /// If there's no snapshot, or the snapshot is disabled, it falls back to evaluate the built-in
+  /// modules on startup.
   #[allow(clippy::too_many_arguments)]
   pub fn new(
     options: JsRuntimeOptions,
-    snapshot: SnapshotData,
+    snapshot: Option<SnapshotData>,
     startup_moment: Instant,
     time_origin: u128,
     js_runtime_send_to_master: Sender<JsRuntimeToEventLoopMessage>,
@@ -399,9 +427,18 @@ impl JsRuntime {
     // Fire up the v8 engine.
     init_v8_platform();
 
+    let snapshot = if options.disable_snapshot {
+      None
+    } else {
+      snapshot
+    };
+
     let mut isolate = {
       let create_params = v8::CreateParams::default();
-      let create_params = create_params.snapshot_blob(snapshot.value);
+      let create_params = match &snapshot {
+        Some(snapshot) => create_params.snapshot_blob(snapshot.value),
+        None => create_params,
+      };
       v8::Isolate::new(create_params)
     };
 
@@ -487,6 +524,9 @@ impl JsRuntime {
       // next_tick_queue: Vec::new(),
       exceptions: ExceptionState::new(),
       options,
+      user_commands: HashMap::new(),
+      event_hooks: EventHooks::new(),
+      builtin_modules_evaluated: 0,
       // wake_event_queued: false,
       js_runtime_send_to_master,
       js_runtime_recv_from_master,
@@ -499,6 +539,16 @@ impl JsRuntime {
 
     isolate.set_slot(state.clone());
 
+    // With snapshot, we no longer need to initialize builtin runtime modules any more.
+    if snapshot.is_none() {
+      let context = state.borrow().context.clone();
+      let scope = &mut v8::HandleScope::with_context(&mut isolate, context);
+      for (filename, source) in BUILTIN_RUNTIME_MODULES.iter() {
+        JsRuntimeForSnapshot::init_builtin_module(scope, filename, source);
+        state.borrow_mut().builtin_modules_evaluated += 1;
+      }
+    }
+
     JsRuntime {
       isolate,
       // event_loop,
@@ -506,9 +556,6 @@ impl JsRuntime {
       // inspector,
     }
 
-    // With snapshot, we no longer need to initialize builtin runtime modules any more.
-    // runtime.init_environment(module_handles);
-
     // // Start inspector agent is requested.
     // if let Some(inspector) = runtime.inspector().as_mut() {
     //   let address = address.unwrap();
@@ -637,6 +684,149 @@ impl JsRuntime {
     Ok(())
   }
 
+  /// Executes the user-defined Ex command registered by `Rsvim.cmd.create`, the `args` (i.e. the
+  /// remainder of the command line) is passed to the callback as a string.
+  ///
+  /// The error thrown by the callback is returned, and also shown in the message area.
+  pub fn execute_user_command(&mut self, name: &str, args: &str) -> Result<(), AnyErr> {
+    let scope = &mut self.handle_scope();
+    let state_rc = JsRuntime::state(scope);
+
+    // Drop borrowed `state_rc` before running the callback, it may access the state as well.
+    let callback = state_rc.borrow().user_commands.get(name).cloned();
+    let callback = match callback {
+      Some(callback) => v8::Local::new(scope, callback),
+      None => anyhow::bail!("E492: Not an editor command: {}", name),
+    };
+    let undefined = v8::undefined(scope).into();
+    let args = v8::String::new(scope, args).unwrap();
+
+    let tc_scope = &mut v8::TryCatch::new(scope);
+    callback.call(tc_scope, undefined, &[args.into()]);
+
+    if tc_scope.has_caught() {
+      let exception = tc_scope.exception().unwrap();
+      let error = JsError::from_v8_exception(tc_scope, exception, None);
+      error!("Failed to execute user command {name:?}: {error:?}");
+      let editing_state = state_rc.borrow().editing_state.clone();
+      editing_state
+        .try_write_for(envar::MUTEX_TIMEOUT())
+        .unwrap()
+        .echoerr(&error.to_string());
+      anyhow::bail!(error);
+    }
+
+    run_next_tick_callbacks(tc_scope);
+    Ok(())
+  }
+
+  /// Fires the autocmd event, i.e. calls the callbacks registered by `Rsvim.autocmd` that match
+  /// the event, in registration order. The callback receives the event context object.
+  ///
+  /// It returns `false` if any callback returns `false` or throws an error, and the remaining
+  /// callbacks are skipped. The error is also shown in the message area. Only `BufWritePre`
+  /// respects the return value, i.e. vetoes the write.
+  pub fn fire_autocmd(&mut self, ctx: &AutocmdContext) -> bool {
+    let scope = &mut self.handle_scope();
+    let state_rc = JsRuntime::state(scope);
+
+    // Drop borrowed `state_rc` before running the callbacks, they may register new hooks.
+    let callbacks = state_rc.borrow().event_hooks.matched(ctx);
+    if callbacks.is_empty() {
+      return true;
+    }
+
+    let ctx_object = v8::Object::new(scope);
+    let event = v8::String::new(scope, &ctx.event().to_string()).unwrap();
+    set_property_to(scope, ctx_object, "event", event.into());
+    if let Some(buf_id) = ctx.buf_id() {
+      let buf_id = v8::Integer::new(scope, buf_id);
+      set_property_to(scope, ctx_object, "bufId", buf_id.into());
+    }
+    if let Some(file) = ctx.file() {
+      let file = v8::String::new(scope, &file.to_string_lossy()).unwrap();
+      set_property_to(scope, ctx_object, "file", file.into());
+    }
+    if let Some(old_mode) = ctx.old_mode() {
+      let old_mode = v8::String::new(scope, &old_mode.to_string()).unwrap();
+      set_property_to(scope, ctx_object, "oldMode", old_mode.into());
+    }
+    if let Some(new_mode) = ctx.new_mode() {
+      let new_mode = v8::String::new(scope, &new_mode.to_string()).unwrap();
+      set_property_to(scope, ctx_object, "newMode", new_mode.into());
+    }
+    let undefined = v8::undefined(scope).into();
+
+    let tc_scope = &mut v8::TryCatch::new(scope);
+    let mut result = true;
+    for callback in callbacks {
+      let callback = v8::Local::new(tc_scope, callback);
+      let value = callback.call(tc_scope, undefined, &[ctx_object.into()]);
+
+      if tc_scope.has_caught() {
+        let exception = tc_scope.exception().unwrap();
+        let error = JsError::from_v8_exception(tc_scope, exception, None);
+        error!("Failed to run {} autocmd: {error:?}", ctx.event());
+        let editing_state = state_rc.borrow().editing_state.clone();
+        editing_state
+          .try_write_for(envar::MUTEX_TIMEOUT())
+          .unwrap()
+          .echoerr(&error.to_string());
+        result = false;
+        break;
+      }
+      if value.is_some_and(|value| value.is_false()) {
+        result = false;
+        break;
+      }
+    }
+
+    run_next_tick_callbacks(tc_scope);
+    result
+  }
+
+  /// Writes the buffer to its file, i.e. `:write`, or `:write!` if `force`. The `BufWritePre`
+  /// autocmds are fired before the write and can veto it, the `BufWritePost` autocmds are fired
+  /// after the write.
+  ///
+  /// It returns `false` if the write is vetoed, or the error if the write failed. After a successful
+  /// write, the "written" message is shown in the message area.
+  pub fn write_buffer(&mut self, buf_id: BufferId, force: bool) -> Result<bool, AnyErr> {
+    let buffers = self.get_state().borrow().buffers.clone();
+    let file = rlock!(buffers)
+      .get(&buf_id)
+      .and_then(|buf| rlock!(buf).absolute_filename().clone());
+
+    let ctx = AutocmdContext::buffer(AutocmdEvent::BufWritePre, buf_id, file.clone());
+    if !self.fire_autocmd(&ctx) {
+      trace!("Write buffer {:?} is vetoed by BufWritePre", buf_id);
+      return Ok(false);
+    }
+
+    let bytes = rlock!(buffers).write_buffer(&buf_id, force)?;
+    let message = {
+      let buffers = rlock!(buffers);
+      let buf = rlock!(buffers.get(&buf_id).unwrap());
+      // The empty line after the last line break is not counted.
+      let lines = match buf.len_lines() {
+        0 => 0,
+        n if buf.get_line(n - 1).unwrap().len_chars() == 0 => n - 1,
+        n => n,
+      };
+      let filename = buf.filename().clone().unwrap_or_default();
+      format!("\"{}\" {}L, {}B written", filename.display(), lines, bytes)
+    };
+    let editing_state = self.get_state().borrow().editing_state.clone();
+    editing_state
+      .try_write_for(envar::MUTEX_TIMEOUT())
+      .unwrap()
+      .echo(&message);
+
+    let ctx = AutocmdContext::buffer(AutocmdEvent::BufWritePost, buf_id, file);
+    self.fire_autocmd(&ctx);
+    Ok(true)
+  }
+
   /// Runs a single tick of the event-loop.
   pub fn tick_event_loop(&mut self) {
     let isolate_has_pending_tasks = self.isolate.has_pending_background_tasks();
@@ -705,9 +895,21 @@ impl JsRuntime {
           EventLoopToJsRuntimeMessage::TimeoutResp(resp) => {
             match state.pending_futures.remove(&resp.future_id) {
               Some(timeout_cb) => futures.push(timeout_cb),
-              None => unreachable!("Failed to get timeout future by ID {:?}", resp.future_id),
+              // The future is dropped on shutdown.
+              None => trace!("Skip dropped timeout future by ID {:?}", resp.future_id),
             }
           }
+          EventLoopToJsRuntimeMessage::TickTimers => {
+            // The expired timers are run in this tick.
+          }
+          EventLoopToJsRuntimeMessage::BufferChanged(buf_id) => {
+            trace!("Buffer changed:{:?}", buf_id);
+          }
+          EventLoopToJsRuntimeMessage::Shutdown => {
+            state.pending_futures.clear();
+            state.timeout_handles.clear();
+            futures.clear();
+          }
         }
       }
 
@@ -717,9 +919,11 @@ impl JsRuntime {
     for mut fut in futures {
       fut.run(scope);
       if let Some(error) = check_exceptions(scope) {
-        // FIXME: Cannot simply report error and exit process, because this is inside the editor.
+        // Show the error in the message area, instead of exiting the editor.
         error!("Js runtime timeout error:{error:?}");
-        eprintln!("Js runtime timeout error:{error:?}");
+        Self::state(scope)
+          .borrow()
+          .send_to_master(JsRuntimeToEventLoopMessage::Error(error));
       }
       run_next_tick_callbacks(scope);
     }
@@ -1044,8 +1248,741 @@ pub fn check_exceptions(scope: &mut v8::HandleScope) -> Option<JsError> {
 mod tests {
   use super::*;
 
+  use crate::buf::{BuffersManager, ColorScheme, ColorSchemes};
+  use crate::cart::{IRect, U16Pos, U16Size};
+  use crate::state::command::ExCommandHandler;
+  use crate::state::message::{Message, MessageKind};
+  use crate::state::profile::{FrameTiming, RenderPhase};
+  use crate::state::State;
+  use crate::test::js::{
+    make_js_runtime, make_js_runtime_with, make_js_runtime_with_channels, make_snapshot,
+  };
+  use crate::test::render::snapshot;
+  use crate::test::tree::insert_window;
+  use crate::ui::canvas::Canvas;
+  use crate::ui::tree::internal::Inodeable;
+  use crate::ui::tree::{Tree, TreeNode, TreeNodeId};
+  use crate::ui::widget::MessageArea;
+  use crate::{rlock, wlock};
+
+  use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+  use crossterm::style::{Attribute, Attributes, Color};
+  use std::time::Duration;
+
   #[test]
   fn next_future_id1() {
     assert!(next_future_id() > 0);
   }
+
+  fn make_runtime() -> (JsRuntime, TreeArc, BuffersManagerArc, StateArc) {
+    let tree = Tree::to_arc(Tree::new(U16Size::new(20, 10)));
+    let buffers = BuffersManager::to_arc(BuffersManager::new());
+    let state = State::to_arc(State::default());
+    let js_runtime = make_js_runtime(tree.clone(), buffers.clone(), state.clone());
+    (js_runtime, tree, buffers, state)
+  }
+
+  fn eval_string(js_runtime: &mut JsRuntime, source: &str) -> String {
+    let value = js_runtime
+      .__execute_script("eval.js", source)
+      .unwrap()
+      .unwrap();
+    let scope = &mut js_runtime.handle_scope();
+    let value = v8::Local::new(scope, value);
+    value.to_rust_string_lossy(scope)
+  }
+
+  #[test]
+  fn snapshot1() {
+    let make = |options: JsRuntimeOptions, snapshot: Option<SnapshotData>| {
+      let tree = Tree::to_arc(Tree::new(U16Size::new(20, 10)));
+      let buffers = BuffersManager::to_arc(BuffersManager::new());
+      let state = State::to_arc(State::default());
+      make_js_runtime_with(options, snapshot, tree, buffers, state)
+    };
+    let test_mode = || JsRuntimeOptions {
+      test_mode: true,
+      ..Default::default()
+    };
+
+    // Boot from the snapshot, the built-in modules are not evaluated again.
+    let mut js_runtime = make(test_mode(), Some(make_snapshot()));
+    assert_eq!(js_runtime.get_state().borrow().builtin_modules_evaluated, 0);
+    assert_eq!(
+      eval_string(&mut js_runtime, "typeof Rsvim.echo"),
+      "function"
+    );
+
+    // Falls back to the cold startup without snapshot, or the snapshot is disabled.
+    for (options, snapshot) in [
+      (test_mode(), None),
+      (
+        JsRuntimeOptions {
+          disable_snapshot: true,
+          ..test_mode()
+        },
+        Some(make_snapshot()),
+      ),
+    ] {
+      let mut js_runtime = make(options, snapshot);
+      assert_eq!(
+        js_runtime.get_state().borrow().builtin_modules_evaluated,
+        BUILTIN_RUNTIME_MODULES.len()
+      );
+      assert_eq!(
+        eval_string(&mut js_runtime, "typeof Rsvim.echo"),
+        "function"
+      );
+    }
+  }
+
+  #[test]
+  fn user_command1() {
+    let (mut js_runtime, tree, buffers, state) = make_runtime();
+    js_runtime
+      .__execute_script(
+        "user_command1.js",
+        r#"
+        globalThis.formatArgs = "not run";
+        Rsvim.cmd.create("Format", (args) => {
+          globalThis.formatArgs = args;
+        });
+        "#,
+      )
+      .unwrap();
+    assert_eq!(
+      rlock!(state).ex_commands().get("Format"),
+      Some(ExCommandHandler::User)
+    );
+
+    // Type `:Format a b` in the editor.
+    for c in ":Format a b\n".chars() {
+      let code = match c {
+        '\n' => KeyCode::Enter,
+        c => KeyCode::Char(c),
+      };
+      let event = Event::Key(KeyEvent::new(code, KeyModifiers::NONE));
+      wlock!(state).handle(tree.clone(), buffers.clone(), event);
+    }
+    let user_commands = wlock!(state).take_pending_ex_commands();
+    assert_eq!(user_commands.len(), 1);
+    for command_line in user_commands {
+      js_runtime
+        .execute_user_command(command_line.name(), command_line.args())
+        .unwrap();
+    }
+    assert_eq!(eval_string(&mut js_runtime, "globalThis.formatArgs"), "a b");
+  }
+
+  #[test]
+  fn user_command2() {
+    let (mut js_runtime, _tree, _buffers, state) = make_runtime();
+
+    // Invalid name.
+    assert!(js_runtime
+      .__execute_script(
+        "user_command2.js",
+        r#"Rsvim.cmd.create("format", () => {});"#
+      )
+      .is_err());
+    assert!(rlock!(state).ex_commands().get("format").is_none());
+
+    // The error thrown by the callback shows in the message area.
+    js_runtime
+      .__execute_script(
+        "user_command2.js",
+        r#"Rsvim.cmd.create("Fail", () => { throw new Error("oops"); });"#,
+      )
+      .unwrap();
+    assert!(js_runtime.execute_user_command("Fail", "").is_err());
+    let message = rlock!(state).message().clone().unwrap();
+    assert_eq!(message.kind(), MessageKind::Error);
+    assert!(message.text().contains("oops"));
+
+    assert!(js_runtime.execute_user_command("Unknown", "").is_err());
+  }
+
+  #[test]
+  fn cmd_exec1() {
+    let (mut js_runtime, tree, buffers, _state) = make_runtime();
+    let buf_id = wlock!(buffers).new_empty_buffer();
+    let buffer = rlock!(buffers).get(&buf_id).unwrap().clone();
+    wlock!(buffer).insert(0, "\tab\n").unwrap();
+    let window_id = insert_window(&mut wlock!(tree), &buffer);
+    let size = U16Size::new(20, 10);
+    assert_eq!(snapshot(&rlock!(tree), size)[0], "        ab          ");
+
+    // The options are set for current window and buffer, and the ones created later.
+    js_runtime
+      .__execute_script("cmd_exec1.js", r#"Rsvim.cmd("set tabstop=2 nowrap");"#)
+      .unwrap();
+    assert_eq!(rlock!(buffer).tab_stop(), 2);
+    assert_eq!(rlock!(buffers).tab_stop(), 2);
+    match rlock!(tree).node(&window_id) {
+      Some(TreeNode::Window(window)) => assert!(!window.wrap()),
+      _ => unreachable!(),
+    }
+    assert!(!rlock!(tree).wrap());
+    assert_eq!(snapshot(&rlock!(tree), size)[0], "  ab                ");
+
+    // The error of the command is thrown as exception.
+    assert!(js_runtime
+      .__execute_script("cmd_exec1.js", r#"Rsvim.cmd("Unknown");"#)
+      .is_err());
+    assert_eq!(
+      eval_string(
+        &mut js_runtime,
+        r#"try { Rsvim.cmd("Unknown a b"); "" } catch (e) { e.message }"#
+      ),
+      "E492: Not an editor command: Unknown"
+    );
+    assert!(js_runtime
+      .__execute_script("cmd_exec1.js", r#"Rsvim.cmd(1);"#)
+      .is_err());
+  }
+
+  #[test]
+  fn echo1() {
+    let tree = Tree::to_arc(Tree::new(U16Size::new(20, 10)));
+    let buffers = BuffersManager::to_arc(BuffersManager::new());
+    let state = State::to_arc(State::default());
+    let (mut js_runtime, mut master_recv_from_js_runtime, _master_send_to_js_runtime) =
+      make_js_runtime_with_channels(tree.clone(), buffers, state.clone());
+
+    // The messages are sent to the event loop, instead of updating the message area directly.
+    js_runtime
+      .__execute_script("echo1.js", r#"Rsvim.echo("Hello, RSVIM!");"#)
+      .unwrap();
+    match master_recv_from_js_runtime.try_recv().unwrap() {
+      JsRuntimeToEventLoopMessage::Echo(message) => assert_eq!(message, "Hello, RSVIM!"),
+      msg => panic!("Unexpected message {:?}", msg),
+    }
+    assert!(rlock!(state).message().is_none());
+    assert!(js_runtime
+      .__execute_script("echo1.js", r#"Rsvim.echo(1);"#)
+      .is_err());
+    assert!(master_recv_from_js_runtime.try_recv().is_err());
+
+    js_runtime
+      .__execute_script("echo1.js", r#"Rsvim.echoerr("Oops!");"#)
+      .unwrap();
+    match master_recv_from_js_runtime.try_recv().unwrap() {
+      JsRuntimeToEventLoopMessage::Error(e) => {
+        assert_eq!(e.message, "Oops!");
+        // Same with the event loop.
+        wlock!(state).echoerr(&e.message);
+      }
+      msg => panic!("Unexpected message {:?}", msg),
+    }
+
+    // The error message renders with the error highlight on the last row.
+    let message_area = MessageArea::new(IRect::new((0, 9), (20, 10)));
+    {
+      let mut tree = wlock!(tree);
+      let root_id = tree.root_id();
+      tree
+        .bounded_insert(&root_id, TreeNode::MessageArea(message_area))
+        .unwrap();
+      tree.set_message(rlock!(state).message().clone());
+    }
+    let canvas = Canvas::to_arc(Canvas::new(U16Size::new(20, 10)));
+    rlock!(tree).draw(canvas.clone());
+    let canvas = rlock!(canvas);
+    assert_eq!(
+      canvas.frame().raw_symbols()[9].join(""),
+      "Oops!               "
+    );
+    let cell = canvas.frame().get_cell(U16Pos::new(0, 9));
+    assert_eq!(cell.fg(), Color::Red);
+    assert!(cell.attrs().has(Attribute::Bold));
+  }
+
+  #[test]
+  fn profile1() {
+    let (mut js_runtime, _tree, _buffers, state) = make_runtime();
+    assert_eq!(
+      eval_string(
+        &mut js_runtime,
+        "JSON.stringify([Rsvim.profile().enabled, Rsvim.profile().frames])"
+      ),
+      "[false,0]"
+    );
+    assert_eq!(
+      eval_string(&mut js_runtime, "Rsvim.profile().phases.draw"),
+      "null"
+    );
+
+    {
+      let mut state = wlock!(state);
+      let profile = state.render_profile_mut();
+      profile.set_enabled(true);
+      let mut timing = FrameTiming::default();
+      timing.set(RenderPhase::Draw, Duration::from_millis(2));
+      profile.record(timing);
+    }
+    assert_eq!(
+      eval_string(
+        &mut js_runtime,
+        "JSON.stringify([Rsvim.profile().enabled, Rsvim.profile().frames])"
+      ),
+      "[true,1]"
+    );
+    assert_eq!(
+      eval_string(
+        &mut js_runtime,
+        "JSON.stringify(Rsvim.profile().phases.draw)"
+      ),
+      r#"{"min":2,"avg":2,"p99":2}"#
+    );
+    assert_eq!(
+      eval_string(&mut js_runtime, "Rsvim.profile().phases.total.avg"),
+      "2"
+    );
+  }
+
+  #[test]
+  fn write_buffer1() {
+    let (mut js_runtime, _tree, buffers, state) = make_runtime();
+    let tmpdir = assert_fs::TempDir::new().unwrap();
+    let filename = tmpdir.path().join("write_buffer1.txt");
+    let buf_id = wlock!(buffers).new_file_buffer(&filename).unwrap();
+    let buf = rlock!(buffers).get(&buf_id).unwrap().clone();
+    wlock!(buf).insert(0, "Hello\nRSVIM!\n").unwrap();
+
+    assert!(js_runtime.write_buffer(buf_id, false).unwrap());
+    assert_eq!(
+      rlock!(state).message().clone(),
+      Some(Message::info(&format!(
+        "\"{}\" 2L, 13B written",
+        filename.display()
+      )))
+    );
+  }
+
+  #[test]
+  fn autocmd1() {
+    let (mut js_runtime, _tree, buffers, state) = make_runtime();
+    js_runtime
+      .__execute_script(
+        "autocmd1.js",
+        r#"
+        globalThis.written = [];
+        Rsvim.autocmd("BufWritePre", "*.lock", (ctx) => {
+          return false;
+        });
+        Rsvim.autocmd("BufWritePost", null, (ctx) => {
+          globalThis.written.push(ctx.event + ":" + ctx.bufId);
+        });
+        "#,
+      )
+      .unwrap();
+
+    let tmpdir = assert_fs::TempDir::new().unwrap();
+    let lock_file = tmpdir.path().join("autocmd1.lock");
+    let text_file = tmpdir.path().join("autocmd1.txt");
+    let lock_buf_id = wlock!(buffers).new_file_buffer(&lock_file).unwrap();
+    let text_buf_id = wlock!(buffers).new_file_buffer(&text_file).unwrap();
+    for buf_id in [lock_buf_id, text_buf_id] {
+      let buf = rlock!(buffers).get(&buf_id).unwrap().clone();
+      wlock!(buf).insert(0, "Hello, RSVIM!\n").unwrap();
+    }
+
+    // The write to `*.lock` file is skipped.
+    assert!(!js_runtime.write_buffer(lock_buf_id, false).unwrap());
+    assert!(!lock_file.exists());
+    assert!(js_runtime.write_buffer(text_buf_id, false).unwrap());
+    assert_eq!(
+      std::fs::read_to_string(&text_file).unwrap(),
+      "Hello, RSVIM!\n"
+    );
+    assert_eq!(
+      eval_string(&mut js_runtime, "globalThis.written.join()"),
+      format!("BufWritePost:{}", text_buf_id)
+    );
+
+    // The error thrown by `BufWritePre` callback also vetoes the write.
+    js_runtime
+      .__execute_script(
+        "autocmd1.js",
+        r#"
+        Rsvim.autocmd("BufWritePre", "*.txt", (ctx) => {
+          throw new Error("oops");
+        });
+        "#,
+      )
+      .unwrap();
+    assert!(!js_runtime.write_buffer(text_buf_id, false).unwrap());
+    let message = rlock!(state).message().clone().unwrap();
+    assert_eq!(message.kind(), MessageKind::Error);
+    assert!(message.text().contains("oops"));
+  }
+
+  #[test]
+  fn autocmd2() {
+    let (mut js_runtime, tree, buffers, state) = make_runtime();
+
+    // Invalid event.
+    assert!(js_runtime
+      .__execute_script(
+        "autocmd2.js",
+        r#"Rsvim.autocmd("BufEnter", null, () => {});"#
+      )
+      .is_err());
+
+    js_runtime
+      .__execute_script(
+        "autocmd2.js",
+        r#"
+        globalThis.modes = [];
+        Rsvim.autocmd("ModeChanged", "*:Command-line", (ctx) => {
+          globalThis.modes.push(ctx.oldMode + ":" + ctx.newMode);
+        });
+        "#,
+      )
+      .unwrap();
+
+    // Type `:` and `Esc` in the editor.
+    for code in [KeyCode::Char(':'), KeyCode::Esc] {
+      let event = Event::Key(KeyEvent::new(code, KeyModifiers::NONE));
+      wlock!(state).handle(tree.clone(), buffers.clone(), event);
+    }
+    let autocmds = wlock!(state).take_pending_autocmds();
+    assert_eq!(autocmds.len(), 2);
+    for ctx in autocmds {
+      assert!(js_runtime.fire_autocmd(&ctx));
+    }
+    assert_eq!(
+      eval_string(&mut js_runtime, "globalThis.modes.join()"),
+      "Normal:Command-line"
+    );
+  }
+
+  #[test]
+  fn highlight1() {
+    let (mut js_runtime, _tree, buffers, _state) = make_runtime();
+    let tmpdir = assert_fs::TempDir::new().unwrap();
+    let filename = tmpdir.path().join("highlight1.txt");
+    let buf_id = wlock!(buffers).new_file_buffer(&filename).unwrap();
+    js_runtime
+      .__execute_script(
+        "highlight1.js",
+        &format!(
+          r##"
+          Rsvim.highlight.set({buf_id}, 0, [
+            {{ startChar: 0, endChar: 3, fg: "red" }},
+            {{ startChar: 2, endChar: 5, bg: "#0000ff", attrs: ["bold"] }},
+          ]);
+          Rsvim.highlight.set({buf_id}, 1, [{{ startChar: 0, endChar: 1, fg: "green" }}]);
+          Rsvim.highlight.clear({buf_id}, 1, 2);
+          "##
+        ),
+      )
+      .unwrap();
+
+    let buf = rlock!(buffers).get(&buf_id).unwrap().clone();
+    let highlights = rlock!(buf).highlights().clone();
+    assert_eq!(highlights.get(0).unwrap().len(), 2);
+    assert!(highlights.get(1).is_none());
+    let (fg, bg, attrs) = highlights.style_at(0, 2);
+    assert_eq!(fg, Some(Color::Red));
+    assert_eq!(bg, Some(Color::Rgb { r: 0, g: 0, b: 255 }));
+    assert!(attrs.has(Attribute::Bold));
+
+    // Invalid color, attribute or buffer.
+    for script in [
+      format!(
+        r#"Rsvim.highlight.set({buf_id}, 0, [{{ startChar: 0, endChar: 1, fg: "unknown" }}]);"#
+      ),
+      format!(
+        r#"Rsvim.highlight.set({buf_id}, 0, [{{ startChar: 0, endChar: 1, attrs: ["blink"] }}]);"#
+      ),
+      format!(r#"Rsvim.highlight.set({}, 0, []);"#, buf_id + 100),
+      format!(r#"Rsvim.highlight.set({buf_id}, 0, "spans");"#),
+    ] {
+      assert!(js_runtime
+        .__execute_script("highlight1.js", &script)
+        .is_err());
+    }
+    // The failed calls don't change the spans.
+    assert_eq!(rlock!(buf).highlights().get(0).unwrap().len(), 2);
+  }
+
+  #[test]
+  fn sign1() {
+    let (mut js_runtime, _tree, buffers, _state) = make_runtime();
+    let buf_id = wlock!(buffers).new_empty_buffer();
+    let buf = rlock!(buffers).get(&buf_id).unwrap().clone();
+    wlock!(buf)
+      .append_lines(0, &["a".to_string(), "b".to_string()])
+      .unwrap();
+    js_runtime
+      .__execute_script(
+        "sign1.js",
+        &format!(
+          r#"
+          Rsvim.sign.place({buf_id}, 1, "git", "+");
+          Rsvim.sign.place({buf_id}, 1, "lsp", "E", 20, "ErrorMsg");
+          Rsvim.sign.place({buf_id}, 0, "lsp", "W", 20);
+          Rsvim.sign.unplace({buf_id}, 0, "lsp");
+          "#
+        ),
+      )
+      .unwrap();
+
+    let signs = rlock!(buf).signs().clone();
+    assert!(signs.get(0).is_none());
+    assert_eq!(signs.get(1).unwrap().len(), 2);
+    let top = signs.top(1).unwrap();
+    assert_eq!(top.glyph(), "E");
+    assert_eq!(top.priority(), 20);
+    assert_eq!(signs.line_highlight(1), Some("ErrorMsg"));
+    assert_eq!(signs.get(1).unwrap()[1].priority(), 10);
+
+    // Invalid argument, buffer or line.
+    for script in [
+      format!(r#"Rsvim.sign.place({buf_id}, 0, "git", 1);"#),
+      format!(r#"Rsvim.sign.place({buf_id}, 0, "git", "+", "high");"#),
+      format!(r#"Rsvim.sign.place({}, 0, "git", "+");"#, buf_id + 100),
+      format!(r#"Rsvim.sign.place({buf_id}, 5, "git", "+");"#),
+    ] {
+      assert!(js_runtime.__execute_script("sign1.js", &script).is_err());
+    }
+    assert!(rlock!(buf).signs().get(0).is_none());
+  }
+
+  #[test]
+  fn buf1() {
+    let (mut js_runtime, tree, buffers, _state) = make_runtime();
+    let buf_id = wlock!(buffers).new_empty_buffer();
+    let buf = rlock!(buffers).get(&buf_id).unwrap().clone();
+    let window_id = insert_window(&mut wlock!(tree), &buf);
+
+    // Append two lines, the window follows the buffer.
+    js_runtime
+      .__execute_script(
+        "buf1.js",
+        &format!(r#"Rsvim.buf.appendLines({buf_id}, 0, ["Hello", "RSVIM!"]);"#),
+      )
+      .unwrap();
+    assert_eq!(rlock!(buf).len_lines(), 3);
+    assert_eq!(rlock!(buf).last_line_idx(), 1);
+    assert_eq!(rlock!(buf).get_line(0).unwrap().to_string(), "Hello\n");
+    assert_eq!(rlock!(buf).get_line(1).unwrap().to_string(), "RSVIM!\n");
+    assert!(rlock!(buf).is_modified());
+    assert_eq!(
+      eval_string(
+        &mut js_runtime,
+        &format!(
+          r#"const viewport = Rsvim.win.getViewport({window_id});
+          `${{viewport.startLine}}-${{viewport.endLine}}`"#
+        )
+      ),
+      "0-2"
+    );
+
+    // Set and delete the lines.
+    js_runtime
+      .__execute_script(
+        "buf1.js",
+        &format!(
+          r#"
+          Rsvim.buf.setLine({buf_id}, 1, "World");
+          Rsvim.buf.appendLines({buf_id}, 2, ["!"]);
+          Rsvim.buf.deleteLines({buf_id}, 0, 1);
+          "#
+        ),
+      )
+      .unwrap();
+    assert_eq!(
+      rlock!(buf)
+        .lines()
+        .map(|l| l.to_string())
+        .collect::<String>(),
+      "World\n!\n"
+    );
+
+    // The out-of-range indexes, or the invalid buffer.
+    for (script, message) in [
+      (
+        format!(r#"Rsvim.buf.setLine({buf_id}, 2, "x")"#),
+        "E966: Invalid line number: 2".to_string(),
+      ),
+      (
+        format!(r#"Rsvim.buf.appendLines({buf_id}, 3, ["x"])"#),
+        "E966: Invalid line number: 3".to_string(),
+      ),
+      (
+        format!(r#"Rsvim.buf.deleteLines({buf_id}, 1, 3)"#),
+        "E966: Invalid line number: 3".to_string(),
+      ),
+      (
+        format!(r#"Rsvim.buf.setLine({}, 0, "x")"#, buf_id + 100),
+        format!("E86: Buffer {} does not exist", buf_id + 100),
+      ),
+    ] {
+      assert_eq!(
+        eval_string(
+          &mut js_runtime,
+          &format!(r#"try {{ {script}; "" }} catch (e) {{ e.message }}"#)
+        ),
+        message
+      );
+    }
+    assert_eq!(
+      rlock!(buf)
+        .lines()
+        .map(|l| l.to_string())
+        .collect::<String>(),
+      "World\n!\n"
+    );
+  }
+
+  #[test]
+  fn colorscheme1() {
+    let (mut js_runtime, _tree, _buffers, _state) = make_runtime();
+
+    // Register a color scheme, the other tests are not affected since it's not switched to.
+    let source = r##"
+      Rsvim.colorscheme.register("colorscheme1", {
+        Search: { fg: "black", bg: "#ff8000", attrs: ["bold"] },
+        IncSearch: "Search",
+      });
+      Rsvim.colorscheme("default");
+      [Rsvim.colorscheme(), Rsvim.colorscheme.list().includes("colorscheme1")].join(",")
+      "##;
+    assert_eq!(eval_string(&mut js_runtime, source), "default,true");
+    let search = (
+      Some(Color::Black),
+      Some(Color::Rgb {
+        r: 0xff,
+        g: 0x80,
+        b: 0x00,
+      }),
+      Attributes::from(Attribute::Bold),
+    );
+    {
+      let color_schemes = rlock!(ColorSchemes::global());
+      let color_scheme = color_schemes.get("colorscheme1").unwrap();
+      assert_eq!(color_scheme.resolve("Search"), Some(search));
+      assert_eq!(color_scheme.resolve("IncSearch"), Some(search));
+      assert_eq!(
+        color_scheme.resolve("Comment"),
+        ColorScheme::default().resolve("Comment")
+      );
+    }
+
+    let source = r#"
+      const errors = [];
+      try { Rsvim.colorscheme("unknown"); } catch (e) { errors.push(e.message); }
+      try {
+        Rsvim.colorscheme.register("bad", { Search: 1 });
+      } catch (e) { errors.push(e.message); }
+      try {
+        Rsvim.colorscheme.register("bad", { Search: { fg: "nocolor" } });
+      } catch (e) { errors.push(e.message); }
+      errors.join(";")
+      "#;
+    assert_eq!(
+      eval_string(&mut js_runtime, source),
+      "E185: Cannot find color scheme 'unknown';\
+       E475: Invalid argument: highlight group must be object or string;\
+       E254: Cannot allocate color nocolor"
+    );
+    assert!(rlock!(ColorSchemes::global()).get("bad").is_none());
+  }
+
+  #[test]
+  fn win1() {
+    let (mut js_runtime, tree, buffers, _state) = make_runtime();
+    let buf_id1 = wlock!(buffers).new_empty_buffer();
+    let buf_id2 = wlock!(buffers).new_empty_buffer();
+    let buf1 = rlock!(buffers).get(&buf_id1).unwrap().clone();
+    let text: String = (0..20).map(|i| format!("line {}\n", i)).collect();
+    wlock!(buf1).insert(0, &text).unwrap();
+    let window_id = insert_window(&mut wlock!(tree), &buf1);
+
+    // Split the window, then query the viewports of both windows.
+    let source = format!(
+      r#"
+      const win = Rsvim.win;
+      const oldId = win.current();
+      const newId = win.split(oldId, "horizontal");
+      const shapes = win.list().map((w) => `${{w.id}}:${{w.shape.y}}+${{w.shape.height}}`);
+      const viewports = [oldId, newId].map((id) => {{
+        const viewport = win.getViewport(id);
+        return `${{viewport.startLine}}-${{viewport.endLine}}`;
+      }});
+      win.setBuffer(oldId, {});
+      [win.current() === newId, newId, shapes.join(","), viewports.join(",")].join(";")
+      "#,
+      buf_id2
+    );
+    let result = eval_string(&mut js_runtime, &source);
+    let parts: Vec<&str> = result.split(';').collect();
+    let new_window_id: TreeNodeId = parts[1].parse().unwrap();
+    assert_eq!(parts[0], "true");
+    assert_eq!(parts[2], format!("{}:5+5,{}:0+5", window_id, new_window_id));
+    assert_eq!(parts[3], "0-5,0-5");
+    {
+      let tree = rlock!(tree);
+      assert_eq!(tree.window_ids().len(), 2);
+      match tree.node(&window_id).unwrap() {
+        TreeNode::Window(window) => {
+          assert_eq!(rlock!(window.buffer().upgrade().unwrap()).id(), buf_id2)
+        }
+        _ => unreachable!(),
+      }
+    }
+
+    // Close the new window, the old window takes the whole space again.
+    js_runtime
+      .__execute_script("win1.js", &format!("Rsvim.win.close({});", new_window_id))
+      .unwrap();
+    {
+      let tree = rlock!(tree);
+      assert_eq!(
+        tree.window_ids().iter().copied().collect::<Vec<_>>(),
+        vec![window_id]
+      );
+      assert_eq!(tree.current_window_id(), Some(window_id));
+      assert_eq!(
+        *tree.node(&window_id).unwrap().shape(),
+        IRect::new((0, 0), (20, 10))
+      );
+    }
+
+    // The invalid window IDs, and the last window cannot be closed.
+    assert_eq!(
+      eval_string(
+        &mut js_runtime,
+        r#"try { Rsvim.win.getViewport(-1); "" } catch (e) { e.message }"#
+      ),
+      "E957: Invalid window number: -1"
+    );
+    assert_eq!(
+      eval_string(
+        &mut js_runtime,
+        &format!(
+          r#"try {{ Rsvim.win.close({}); "" }} catch (e) {{ e.message }}"#,
+          window_id
+        )
+      ),
+      "E444: Cannot close last window"
+    );
+    assert!(js_runtime
+      .__execute_script("win1.js", r#"Rsvim.win.split(-1, "vertical");"#)
+      .is_err());
+    assert!(js_runtime
+      .__execute_script(
+        "win1.js",
+        &format!(r#"Rsvim.win.split({}, "diagonal");"#, window_id)
+      )
+      .is_err());
+    assert!(js_runtime
+      .__execute_script(
+        "win1.js",
+        &format!("Rsvim.win.setBuffer({}, -1);", window_id)
+      )
+      .is_err());
+  }
 }
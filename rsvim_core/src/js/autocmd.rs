@@ -0,0 +1,52 @@
+//! Autocmd event hooks registered by `Rsvim.autocmd`.
+
+use crate::state::autocmd::{AutocmdContext, AutocmdEvent};
+
+use ahash::AHashMap as HashMap;
+
+/// The JS callback registered for an event, with an optional file pattern.
+pub struct EventHook {
+  pub pattern: Option<String>,
+  pub callback: v8::Global<v8::Function>,
+}
+
+#[derive(Default)]
+/// The event hooks registry, maps from event kind to its hooks in registration order.
+pub struct EventHooks {
+  hooks: HashMap<AutocmdEvent, Vec<EventHook>>,
+}
+
+impl EventHooks {
+  pub fn new() -> Self {
+    EventHooks::default()
+  }
+
+  /// Register a callback for the event, the hook without pattern matches every event.
+  pub fn add(
+    &mut self,
+    event: AutocmdEvent,
+    pattern: Option<String>,
+    callback: v8::Global<v8::Function>,
+  ) {
+    self
+      .hooks
+      .entry(event)
+      .or_default()
+      .push(EventHook { pattern, callback });
+  }
+
+  /// Get the callbacks matched with the fired event, in registration order.
+  pub fn matched(&self, ctx: &AutocmdContext) -> Vec<v8::Global<v8::Function>> {
+    match self.hooks.get(&ctx.event()) {
+      Some(hooks) => hooks
+        .iter()
+        .filter(|hook| match &hook.pattern {
+          Some(pattern) => ctx.matches(pattern),
+          None => true,
+        })
+        .map(|hook| hook.callback.clone())
+        .collect(),
+      None => vec![],
+    }
+  }
+}
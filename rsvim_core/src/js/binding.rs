@@ -93,6 +93,181 @@ pub fn create_new_context<'s>(scope: &mut v8::HandleScope<'s, ()>) -> v8::Local<
       "opt_set_line_break",
       global_rsvim::opt::set_line_break,
     );
+    set_function_to(
+      scope,
+      vim,
+      "opt_get_modifiable",
+      global_rsvim::opt::get_modifiable,
+    );
+    set_function_to(
+      scope,
+      vim,
+      "opt_set_modifiable",
+      global_rsvim::opt::set_modifiable,
+    );
+    set_function_to(
+      scope,
+      vim,
+      "opt_get_readonly",
+      global_rsvim::opt::get_readonly,
+    );
+    set_function_to(
+      scope,
+      vim,
+      "opt_set_readonly",
+      global_rsvim::opt::set_readonly,
+    );
+    set_function_to(
+      scope,
+      vim,
+      "opt_get_fill_chars",
+      global_rsvim::opt::get_fill_chars,
+    );
+    set_function_to(
+      scope,
+      vim,
+      "opt_set_fill_chars",
+      global_rsvim::opt::set_fill_chars,
+    );
+    set_function_to(scope, vim, "opt_get_list", global_rsvim::opt::get_list);
+    set_function_to(scope, vim, "opt_set_list", global_rsvim::opt::set_list);
+    set_function_to(
+      scope,
+      vim,
+      "opt_get_list_chars",
+      global_rsvim::opt::get_list_chars,
+    );
+    set_function_to(
+      scope,
+      vim,
+      "opt_set_list_chars",
+      global_rsvim::opt::set_list_chars,
+    );
+    set_function_to(
+      scope,
+      vim,
+      "opt_get_show_break",
+      global_rsvim::opt::get_show_break,
+    );
+    set_function_to(
+      scope,
+      vim,
+      "opt_set_show_break",
+      global_rsvim::opt::set_show_break,
+    );
+    set_function_to(scope, vim, "opt_get_scroll", global_rsvim::opt::get_scroll);
+    set_function_to(scope, vim, "opt_set_scroll", global_rsvim::opt::set_scroll);
+    set_function_to(
+      scope,
+      vim,
+      "opt_get_scroll_off",
+      global_rsvim::opt::get_scroll_off,
+    );
+    set_function_to(
+      scope,
+      vim,
+      "opt_set_scroll_off",
+      global_rsvim::opt::set_scroll_off,
+    );
+  }
+
+  // `Rsvim.cmd`
+  {
+    set_function_to(scope, vim, "cmd_create", global_rsvim::cmd::create);
+    set_function_to(scope, vim, "cmd_exec", global_rsvim::cmd::exec);
+  }
+
+  // `Rsvim.echo`/`Rsvim.echoerr`
+  {
+    set_function_to(scope, vim, "echo", global_rsvim::echo::echo);
+    set_function_to(scope, vim, "echoerr", global_rsvim::echo::echoerr);
+  }
+
+  // `Rsvim.profile`
+  {
+    set_function_to(scope, vim, "profile", global_rsvim::profile::profile);
+  }
+
+  // `Rsvim.autocmd`
+  {
+    set_function_to(scope, vim, "autocmd_create", global_rsvim::autocmd::create);
+  }
+
+  // `Rsvim.highlight`
+  {
+    set_function_to(scope, vim, "highlight_set", global_rsvim::highlight::set);
+    set_function_to(
+      scope,
+      vim,
+      "highlight_clear",
+      global_rsvim::highlight::clear,
+    );
+  }
+
+  // `Rsvim.sign`
+  {
+    set_function_to(scope, vim, "sign_place", global_rsvim::sign::place);
+    set_function_to(scope, vim, "sign_unplace", global_rsvim::sign::unplace);
+  }
+
+  // `Rsvim.buf`
+  {
+    set_function_to(scope, vim, "buf_set_line", global_rsvim::buf::set_line);
+    set_function_to(
+      scope,
+      vim,
+      "buf_append_lines",
+      global_rsvim::buf::append_lines,
+    );
+    set_function_to(
+      scope,
+      vim,
+      "buf_delete_lines",
+      global_rsvim::buf::delete_lines,
+    );
+  }
+
+  // `Rsvim.win`
+  {
+    set_function_to(scope, vim, "win_list", global_rsvim::win::list);
+    set_function_to(scope, vim, "win_current", global_rsvim::win::current);
+    set_function_to(scope, vim, "win_set_buffer", global_rsvim::win::set_buffer);
+    set_function_to(scope, vim, "win_split", global_rsvim::win::split);
+    set_function_to(scope, vim, "win_close", global_rsvim::win::close);
+    set_function_to(
+      scope,
+      vim,
+      "win_get_viewport",
+      global_rsvim::win::get_viewport,
+    );
+  }
+
+  // `Rsvim.colorscheme`
+  {
+    set_function_to(
+      scope,
+      vim,
+      "colorscheme_set",
+      global_rsvim::colorscheme::set,
+    );
+    set_function_to(
+      scope,
+      vim,
+      "colorscheme_current",
+      global_rsvim::colorscheme::current,
+    );
+    set_function_to(
+      scope,
+      vim,
+      "colorscheme_list",
+      global_rsvim::colorscheme::list,
+    );
+    set_function_to(
+      scope,
+      vim,
+      "colorscheme_register",
+      global_rsvim::colorscheme::register,
+    );
   }
 
   // Expose low-level functions to JavaScript.
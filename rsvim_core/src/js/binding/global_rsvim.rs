@@ -1,3 +1,12 @@
 //! APIs for `Rsvim` namespace.
 
+pub mod autocmd;
+pub mod buf;
+pub mod cmd;
+pub mod colorscheme;
+pub mod echo;
+pub mod highlight;
 pub mod opt;
+pub mod profile;
+pub mod sign;
+pub mod win;
@@ -0,0 +1,40 @@
+//! APIs for `Rsvim.autocmd`.
+
+use crate::js::binding::throw_exception;
+use crate::js::JsRuntime;
+use crate::state::autocmd::AutocmdEvent;
+
+use tracing::trace;
+
+/// Register a callback for the autocmd event, with an optional file pattern.
+/// See: <https://vimhelp.org/autocmd.txt.html#%3Aautocmd>
+pub fn create(
+  scope: &mut v8::HandleScope,
+  args: v8::FunctionCallbackArguments,
+  _: v8::ReturnValue,
+) {
+  assert!(args.length() == 3);
+  let event = args.get(0).to_rust_string_lossy(scope);
+  let pattern = if args.get(1).is_null_or_undefined() {
+    None
+  } else {
+    Some(args.get(1).to_rust_string_lossy(scope))
+  };
+  let callback = v8::Local::<v8::Function>::try_from(args.get(2)).unwrap();
+  trace!("create: {:?}, pattern: {:?}", event, pattern);
+
+  match event.parse::<AutocmdEvent>() {
+    Ok(event) => {
+      let callback = v8::Global::new(scope, callback);
+      let state_rc = JsRuntime::state(scope);
+      state_rc
+        .borrow_mut()
+        .event_hooks
+        .add(event, pattern, callback);
+    }
+    Err(_) => throw_exception(
+      scope,
+      &anyhow::anyhow!("E216: No such group or event: {}", event),
+    ),
+  }
+}
@@ -0,0 +1,156 @@
+//! APIs for `Rsvim.buf` namespace.
+
+use crate::buf::{Buffer, BufferArc, BufferId};
+use crate::envar;
+use crate::js::binding::throw_exception;
+use crate::js::JsRuntime;
+use crate::res::AnyResult;
+use crate::state::fsm::visual::{current_buffer, cursor_position, sync_cursor};
+use crate::state::motion::clamp_position;
+use crate::ui::tree::{Tree, TreeNode};
+
+use std::sync::Arc;
+use tracing::trace;
+
+/// Edit the buffer with `f`, then the windows of the buffer are re-synced.
+fn edit_buffer(
+  scope: &mut v8::HandleScope,
+  buf_id: BufferId,
+  f: impl FnOnce(&mut Buffer) -> AnyResult<()>,
+) -> AnyResult<()> {
+  let state_rc = JsRuntime::state(scope);
+  let (tree, buffers) = {
+    let state = state_rc.borrow();
+    (state.tree.clone(), state.buffers.clone())
+  };
+  let buffer = match buffers
+    .try_read_for(envar::MUTEX_TIMEOUT())
+    .unwrap()
+    .get(&buf_id)
+  {
+    Some(buffer) => buffer.clone(),
+    None => anyhow::bail!("E86: Buffer {} does not exist", buf_id),
+  };
+  f(&mut buffer.try_write_for(envar::MUTEX_TIMEOUT()).unwrap())?;
+  sync_windows(
+    &mut tree.try_write_for(envar::MUTEX_TIMEOUT()).unwrap(),
+    &buffer,
+  );
+  Ok(())
+}
+
+/// Re-sync the viewports of the windows that display the buffer, and the cursor of current window
+/// is moved into the buffer if it's after the last line.
+fn sync_windows(tree: &mut Tree, buffer: &BufferArc) {
+  let last_line_idx = buffer
+    .try_read_for(envar::MUTEX_TIMEOUT())
+    .unwrap()
+    .last_line_idx();
+  for window_id in tree.window_ids().iter() {
+    if let Some(TreeNode::Window(window)) = tree.node(window_id) {
+      if !window
+        .buffer()
+        .upgrade()
+        .is_some_and(|b| Arc::ptr_eq(&b, buffer))
+      {
+        continue;
+      }
+      let viewport = window.viewport();
+      let mut viewport = viewport.try_write_for(envar::MUTEX_TIMEOUT()).unwrap();
+      let start_line_idx = std::cmp::min(viewport.start_line_idx(), last_line_idx);
+      viewport.sync_from_top_left(start_line_idx, 0);
+    }
+  }
+  if current_buffer(tree).is_some_and(|b| Arc::ptr_eq(&b, buffer)) {
+    if let Some(cursor) = cursor_position(tree) {
+      let next_cursor = clamp_position(
+        &buffer.try_read_for(envar::MUTEX_TIMEOUT()).unwrap(),
+        cursor,
+      );
+      sync_cursor(tree, cursor, next_cursor);
+    }
+  }
+}
+
+/// Replace the text of a buffer line, excluding the trailing line break.
+/// See: <https://vimhelp.org/builtin.txt.html#setbufline%28%29>
+pub fn set_line(
+  scope: &mut v8::HandleScope,
+  args: v8::FunctionCallbackArguments,
+  _: v8::ReturnValue,
+) {
+  assert!(args.length() == 3);
+  let buf_id = args.get(0).int32_value(scope).unwrap_or(0) as BufferId;
+  let line_idx = args.get(1).uint32_value(scope).unwrap_or(0) as usize;
+  let text = args.get(2).to_rust_string_lossy(scope);
+  trace!(
+    "set_line: {:?}, line: {:?}, text: {:?}",
+    buf_id,
+    line_idx,
+    text
+  );
+
+  let result = edit_buffer(scope, buf_id, |buffer| {
+    Ok(buffer.set_line(line_idx, &text)?)
+  });
+  if let Err(e) = result {
+    throw_exception(scope, &e);
+  }
+}
+
+/// Insert the lines after the first `after` lines of a buffer, i.e. `0` inserts at the top.
+/// See: <https://vimhelp.org/builtin.txt.html#appendbufline%28%29>
+pub fn append_lines(
+  scope: &mut v8::HandleScope,
+  args: v8::FunctionCallbackArguments,
+  _: v8::ReturnValue,
+) {
+  assert!(args.length() == 3);
+  let buf_id = args.get(0).int32_value(scope).unwrap_or(0) as BufferId;
+  let after = args.get(1).uint32_value(scope).unwrap_or(0) as usize;
+  let lines = v8::Local::<v8::Array>::try_from(args.get(2)).unwrap();
+  let lines: Vec<String> = (0..lines.length())
+    .map(|i| {
+      let line = lines.get_index(scope, i).unwrap();
+      line.to_rust_string_lossy(scope)
+    })
+    .collect();
+  trace!(
+    "append_lines: {:?}, after: {:?}, lines: {:?}",
+    buf_id,
+    after,
+    lines
+  );
+
+  let result = edit_buffer(scope, buf_id, |buffer| {
+    Ok(buffer.append_lines(after, &lines)?)
+  });
+  if let Err(e) = result {
+    throw_exception(scope, &e);
+  }
+}
+
+/// Delete the buffer lines in range `[startLine, endLine)`.
+/// See: <https://vimhelp.org/builtin.txt.html#deletebufline%28%29>
+pub fn delete_lines(
+  scope: &mut v8::HandleScope,
+  args: v8::FunctionCallbackArguments,
+  _: v8::ReturnValue,
+) {
+  assert!(args.length() == 3);
+  let buf_id = args.get(0).int32_value(scope).unwrap_or(0) as BufferId;
+  let start_line = args.get(1).uint32_value(scope).unwrap_or(0) as usize;
+  let end_line = args.get(2).uint32_value(scope).unwrap_or(0) as usize;
+  trace!(
+    "delete_lines: {:?}, lines: {:?}",
+    buf_id,
+    start_line..end_line
+  );
+
+  let result = edit_buffer(scope, buf_id, |buffer| {
+    Ok(buffer.delete_lines(start_line..end_line)?)
+  });
+  if let Err(e) = result {
+    throw_exception(scope, &e);
+  }
+}
@@ -0,0 +1,78 @@
+//! APIs for `Rsvim.cmd` namespace.
+
+use crate::envar;
+use crate::js::binding::throw_exception;
+use crate::js::msg::JsRuntimeToEventLoopMessage;
+use crate::js::JsRuntime;
+use crate::state::fsm::CommandLineStateful;
+
+use tracing::trace;
+
+/// Create a user-defined Ex command.
+/// See: <https://vimhelp.org/map.txt.html#user-commands>
+pub fn create(
+  scope: &mut v8::HandleScope,
+  args: v8::FunctionCallbackArguments,
+  _: v8::ReturnValue,
+) {
+  assert!(args.length() == 3);
+  let name = args.get(0).to_rust_string_lossy(scope);
+  let callback = v8::Local::<v8::Function>::try_from(args.get(1)).unwrap();
+  let force = args.get(2).to_boolean(scope).boolean_value(scope);
+  trace!("create: {:?}, force: {:?}", name, force);
+
+  let state_rc = JsRuntime::state(scope);
+  let result = state_rc
+    .borrow()
+    .editing_state
+    .try_write_for(envar::MUTEX_TIMEOUT())
+    .unwrap()
+    .ex_commands_mut()
+    .create_user_command(&name, force);
+  match result {
+    Ok(()) => {
+      let callback = v8::Global::new(scope, callback);
+      state_rc.borrow_mut().user_commands.insert(name, callback);
+    }
+    Err(e) => throw_exception(scope, &e),
+  }
+}
+
+/// Execute the Ex command line, same with typing `:{command}` in the command-line mode.
+/// See: <https://vimhelp.org/various.txt.html#%3Aexecute>
+pub fn exec(scope: &mut v8::HandleScope, args: v8::FunctionCallbackArguments, _: v8::ReturnValue) {
+  assert!(args.length() == 1);
+  let command = args.get(0).to_rust_string_lossy(scope);
+  trace!("exec: {:?}", command);
+
+  let state_rc = JsRuntime::state(scope);
+  let (tree, buffers, editing_state) = {
+    let state = state_rc.borrow();
+    (
+      state.tree.clone(),
+      state.buffers.clone(),
+      state.editing_state.clone(),
+    )
+  };
+  let (result, quit_request) = {
+    let mut editing_state = editing_state.try_write_for(envar::MUTEX_TIMEOUT()).unwrap();
+    let result = CommandLineStateful::default().execute_ex_command(
+      &mut editing_state,
+      &tree,
+      &buffers,
+      &command,
+    );
+    // There's no key to confirm the matches, the `c` flag of `:substitute` is dropped.
+    editing_state.take_substitute_confirm();
+    (result, editing_state.take_quit_request())
+  };
+  // The quit request is decided by the event loop.
+  if let Some(request) = quit_request {
+    state_rc
+      .borrow()
+      .send_to_master(JsRuntimeToEventLoopMessage::Quit(request));
+  }
+  if let Err(e) = result {
+    throw_exception(scope, &e);
+  }
+}
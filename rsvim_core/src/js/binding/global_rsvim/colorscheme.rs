@@ -0,0 +1,99 @@
+//! APIs for `Rsvim.colorscheme` namespace.
+
+use crate::buf::{ColorScheme, ColorSchemes};
+use crate::envar;
+use crate::js::binding::global_rsvim::highlight::to_highlight_style;
+use crate::js::binding::throw_exception;
+use crate::res::AnyResult;
+use crate::{rlock, wlock};
+
+use tracing::trace;
+
+/// Convert the JS groups object `{[group]: {fg?, bg?, attrs?} | string}` to the color scheme, the
+/// string value is the linked group. The omitted groups are same with the default color scheme.
+fn to_color_scheme(
+  scope: &mut v8::HandleScope,
+  value: v8::Local<v8::Value>,
+) -> AnyResult<ColorScheme> {
+  let object = match v8::Local::<v8::Object>::try_from(value) {
+    Ok(object) => object,
+    Err(_) => anyhow::bail!("E475: Invalid argument: color scheme groups must be object"),
+  };
+  let mut color_scheme = ColorScheme::default();
+  let groups = object
+    .get_own_property_names(scope, Default::default())
+    .unwrap();
+  for i in 0..groups.length() {
+    let group = groups.get_index(scope, i).unwrap();
+    let value = object.get(scope, group).unwrap();
+    let group = group.to_rust_string_lossy(scope);
+    if value.is_string() {
+      color_scheme.link(&group, &value.to_rust_string_lossy(scope));
+    } else {
+      let style = match v8::Local::<v8::Object>::try_from(value) {
+        Ok(style) => to_highlight_style(scope, style)?,
+        Err(_) => anyhow::bail!("E475: Invalid argument: highlight group must be object or string"),
+      };
+      color_scheme.set(&group, style);
+    }
+  }
+  Ok(color_scheme)
+}
+
+/// Switch to the color scheme, i.e. `:colorscheme {name}`.
+pub fn set(scope: &mut v8::HandleScope, args: v8::FunctionCallbackArguments, _: v8::ReturnValue) {
+  assert!(args.length() == 1);
+  let name = args.get(0).to_rust_string_lossy(scope);
+  trace!("set: {:?}", name);
+
+  if let Err(e) = wlock!(ColorSchemes::global()).switch(&name) {
+    throw_exception(scope, &e);
+  }
+}
+
+/// Get the name of the current color scheme.
+pub fn current(
+  scope: &mut v8::HandleScope,
+  _args: v8::FunctionCallbackArguments,
+  mut rv: v8::ReturnValue,
+) {
+  let name = rlock!(ColorSchemes::global()).current_name().to_string();
+  trace!("current: {:?}", name);
+  rv.set(v8::String::new(scope, &name).unwrap().into());
+}
+
+/// Get the names of all the color schemes.
+pub fn list(
+  scope: &mut v8::HandleScope,
+  _args: v8::FunctionCallbackArguments,
+  mut rv: v8::ReturnValue,
+) {
+  let names: Vec<String> = rlock!(ColorSchemes::global())
+    .names()
+    .into_iter()
+    .map(|name| name.to_string())
+    .collect();
+  trace!("list: {:?}", names);
+  let array = v8::Array::new(scope, names.len() as i32);
+  for (i, name) in names.iter().enumerate() {
+    let value = v8::String::new(scope, name).unwrap();
+    array.set_index(scope, i as u32, value.into());
+  }
+  rv.set(array.into());
+}
+
+/// Register the color scheme, the existing one with the same name is replaced.
+pub fn register(
+  scope: &mut v8::HandleScope,
+  args: v8::FunctionCallbackArguments,
+  _: v8::ReturnValue,
+) {
+  assert!(args.length() == 2);
+  let name = args.get(0).to_rust_string_lossy(scope);
+  trace!("register: {:?}", name);
+
+  match to_color_scheme(scope, args.get(1)) {
+    Ok(color_scheme) => wlock!(ColorSchemes::global()).register(&name, color_scheme),
+    Err(e) => throw_exception(scope, &e),
+  }
+}
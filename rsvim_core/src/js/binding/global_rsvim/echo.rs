@@ -0,0 +1,41 @@
+//! APIs for `Rsvim.echo` and `Rsvim.echoerr`.
+
+use crate::js::err::JsError;
+use crate::js::msg::JsRuntimeToEventLoopMessage;
+use crate::js::JsRuntime;
+
+use tracing::trace;
+
+/// Show a normal message in the message area, it's sent to the event loop.
+/// See: <https://vimhelp.org/eval.txt.html#%3Aecho>
+pub fn echo(scope: &mut v8::HandleScope, args: v8::FunctionCallbackArguments, _: v8::ReturnValue) {
+  assert!(args.length() == 1);
+  let message = args.get(0).to_rust_string_lossy(scope);
+  trace!("echo: {:?}", message);
+
+  let state_rc = JsRuntime::state(scope);
+  state_rc
+    .borrow()
+    .send_to_master(JsRuntimeToEventLoopMessage::Echo(message));
+}
+
+/// Show an error message in the message area, it's sent to the event loop.
+/// See: <https://vimhelp.org/eval.txt.html#%3Aechoerr>
+pub fn echoerr(
+  scope: &mut v8::HandleScope,
+  args: v8::FunctionCallbackArguments,
+  _: v8::ReturnValue,
+) {
+  assert!(args.length() == 1);
+  let message = args.get(0).to_rust_string_lossy(scope);
+  trace!("echoerr: {:?}", message);
+
+  let state_rc = JsRuntime::state(scope);
+  let error = JsError {
+    message,
+    ..Default::default()
+  };
+  state_rc
+    .borrow()
+    .send_to_master(JsRuntimeToEventLoopMessage::Error(error));
+}
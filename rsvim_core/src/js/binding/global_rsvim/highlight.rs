@@ -0,0 +1,135 @@
+//! APIs for `Rsvim.highlight` namespace.
+
+use crate::buf::highlight::{parse_attribute, parse_color};
+use crate::buf::{BufferId, HighlightSpan, HighlightStyle};
+use crate::envar;
+use crate::js::binding::throw_exception;
+use crate::js::JsRuntime;
+use crate::res::AnyResult;
+
+use crossterm::style::{Attributes, Color};
+use tracing::trace;
+
+/// Get the property of the object, it returns `None` if the property is `null` or `undefined`.
+pub fn get_property<'s>(
+  scope: &mut v8::HandleScope<'s>,
+  object: v8::Local<v8::Object>,
+  name: &str,
+) -> Option<v8::Local<'s, v8::Value>> {
+  let key = v8::String::new(scope, name).unwrap();
+  object
+    .get(scope, key.into())
+    .filter(|value| !value.is_null_or_undefined())
+}
+
+/// Convert the JS span object `{startChar, endChar, fg?, bg?, attrs?}` to [`HighlightSpan`].
+fn to_highlight_span(
+  scope: &mut v8::HandleScope,
+  value: v8::Local<v8::Value>,
+) -> AnyResult<HighlightSpan> {
+  let object = match v8::Local::<v8::Object>::try_from(value) {
+    Ok(object) => object,
+    Err(_) => anyhow::bail!("E475: Invalid argument: highlight span must be object"),
+  };
+  let start_char = get_property(scope, object, "startChar")
+    .and_then(|value| value.uint32_value(scope))
+    .unwrap_or(0) as usize;
+  let end_char = get_property(scope, object, "endChar")
+    .and_then(|value| value.uint32_value(scope))
+    .unwrap_or(0) as usize;
+  let (fg, bg, attrs) = to_highlight_style(scope, object)?;
+  Ok(HighlightSpan::new(start_char, end_char, fg, bg, attrs))
+}
+
+/// Convert the `{fg?, bg?, attrs?}` properties of the JS object to [`HighlightStyle`].
+pub fn to_highlight_style(
+  scope: &mut v8::HandleScope,
+  object: v8::Local<v8::Object>,
+) -> AnyResult<HighlightStyle> {
+  let mut color = |name: &str| -> AnyResult<Option<Color>> {
+    match get_property(scope, object, name) {
+      Some(value) => Ok(Some(parse_color(&value.to_rust_string_lossy(scope))?)),
+      None => Ok(None),
+    }
+  };
+  let fg = color("fg")?;
+  let bg = color("bg")?;
+  let mut attrs = Attributes::default();
+  if let Some(value) = get_property(scope, object, "attrs") {
+    let array = match v8::Local::<v8::Array>::try_from(value) {
+      Ok(array) => array,
+      Err(_) => anyhow::bail!("E475: Invalid argument: highlight attrs must be array"),
+    };
+    for i in 0..array.length() {
+      let attr = array.get_index(scope, i).unwrap();
+      attrs.set(parse_attribute(&attr.to_rust_string_lossy(scope))?);
+    }
+  }
+  Ok((fg, bg, attrs))
+}
+
+/// Set the highlight spans of a buffer line, the existing spans of the line are replaced.
+pub fn set(scope: &mut v8::HandleScope, args: v8::FunctionCallbackArguments, _: v8::ReturnValue) {
+  assert!(args.length() == 3);
+  let buf_id = args.get(0).int32_value(scope).unwrap_or(0) as BufferId;
+  let line_idx = args.get(1).uint32_value(scope).unwrap_or(0) as usize;
+  let spans = v8::Local::<v8::Array>::try_from(args.get(2)).unwrap();
+  let spans = (0..spans.length())
+    .map(|i| {
+      let span = spans.get_index(scope, i).unwrap();
+      to_highlight_span(scope, span)
+    })
+    .collect::<AnyResult<Vec<_>>>();
+  trace!(
+    "set: {:?}, line: {:?}, spans: {:?}",
+    buf_id,
+    line_idx,
+    spans
+  );
+
+  let spans = match spans {
+    Ok(spans) => spans,
+    Err(e) => {
+      throw_exception(scope, &e);
+      return;
+    }
+  };
+  let state_rc = JsRuntime::state(scope);
+  let buffers = state_rc.borrow().buffers.clone();
+  let buffers = buffers.try_read_for(envar::MUTEX_TIMEOUT()).unwrap();
+  match buffers.get(&buf_id) {
+    Some(buf) => buf
+      .try_write_for(envar::MUTEX_TIMEOUT())
+      .unwrap()
+      .highlights_mut()
+      .set(line_idx, spans),
+    None => throw_exception(
+      scope,
+      &anyhow::anyhow!("E86: Buffer {} does not exist", buf_id),
+    ),
+  }
+}
+
+/// Clear the highlight spans of the buffer lines in range `[startLine, endLine)`.
+pub fn clear(scope: &mut v8::HandleScope, args: v8::FunctionCallbackArguments, _: v8::ReturnValue) {
+  assert!(args.length() == 3);
+  let buf_id = args.get(0).int32_value(scope).unwrap_or(0) as BufferId;
+  let start_line = args.get(1).uint32_value(scope).unwrap_or(0) as usize;
+  let end_line = args.get(2).uint32_value(scope).unwrap_or(0) as usize;
+  trace!("clear: {:?}, lines: {:?}", buf_id, start_line..end_line);
+
+  let state_rc = JsRuntime::state(scope);
+  let buffers = state_rc.borrow().buffers.clone();
+  let buffers = buffers.try_read_for(envar::MUTEX_TIMEOUT()).unwrap();
+  match buffers.get(&buf_id) {
+    Some(buf) => buf
+      .try_write_for(envar::MUTEX_TIMEOUT())
+      .unwrap()
+      .highlights_mut()
+      .clear(start_line..end_line),
+    None => throw_exception(
+      scope,
+      &anyhow::anyhow!("E86: Buffer {} does not exist", buf_id),
+    ),
+  }
+}
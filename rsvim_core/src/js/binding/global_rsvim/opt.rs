@@ -1,8 +1,11 @@
 //! APIs for `Rsvim.opt` namespace.
 
 use crate::envar;
+use crate::js::binding::throw_exception;
 use crate::js::JsRuntime;
+use crate::ui::widget::window::{FillChars, ListChars};
 
+use std::str::FromStr;
 use tracing::trace;
 
 /// Get the _wrap_ option.
@@ -78,3 +81,305 @@ pub fn set_line_break(
     .unwrap()
     .set_line_break(value);
 }
+
+/// Get the _modifiable_ option.
+/// See: <https://vimhelp.org/options.txt.html#%27modifiable%27>
+pub fn get_modifiable(
+  scope: &mut v8::HandleScope,
+  _args: v8::FunctionCallbackArguments,
+  mut rv: v8::ReturnValue,
+) {
+  let state_rc = JsRuntime::state(scope);
+  let value = state_rc
+    .borrow()
+    .buffers
+    .try_read_for(envar::MUTEX_TIMEOUT())
+    .unwrap()
+    .modifiable();
+  trace!("get_modifiable: {:?}", value);
+  rv.set_bool(value);
+}
+
+/// Set the _modifiable_ option.
+pub fn set_modifiable(
+  scope: &mut v8::HandleScope,
+  args: v8::FunctionCallbackArguments,
+  _: v8::ReturnValue,
+) {
+  assert!(args.length() == 1);
+  let value = args.get(0).to_boolean(scope).boolean_value(scope);
+  let state_rc = JsRuntime::state(scope);
+  trace!("set_modifiable: {:?}", value);
+  state_rc
+    .borrow_mut()
+    .buffers
+    .try_write_for(envar::MUTEX_TIMEOUT())
+    .unwrap()
+    .set_modifiable(value);
+}
+
+/// Get the _readonly_ option.
+/// See: <https://vimhelp.org/options.txt.html#%27readonly%27>
+pub fn get_readonly(
+  scope: &mut v8::HandleScope,
+  _args: v8::FunctionCallbackArguments,
+  mut rv: v8::ReturnValue,
+) {
+  let state_rc = JsRuntime::state(scope);
+  let value = state_rc
+    .borrow()
+    .buffers
+    .try_read_for(envar::MUTEX_TIMEOUT())
+    .unwrap()
+    .readonly();
+  trace!("get_readonly: {:?}", value);
+  rv.set_bool(value);
+}
+
+/// Set the _readonly_ option.
+pub fn set_readonly(
+  scope: &mut v8::HandleScope,
+  args: v8::FunctionCallbackArguments,
+  _: v8::ReturnValue,
+) {
+  assert!(args.length() == 1);
+  let value = args.get(0).to_boolean(scope).boolean_value(scope);
+  let state_rc = JsRuntime::state(scope);
+  trace!("set_readonly: {:?}", value);
+  state_rc
+    .borrow_mut()
+    .buffers
+    .try_write_for(envar::MUTEX_TIMEOUT())
+    .unwrap()
+    .set_readonly(value);
+}
+
+/// Get the _fillchars_ option.
+/// See: <https://vimhelp.org/options.txt.html#%27fillchars%27>
+pub fn get_fill_chars(
+  scope: &mut v8::HandleScope,
+  _args: v8::FunctionCallbackArguments,
+  mut rv: v8::ReturnValue,
+) {
+  let state_rc = JsRuntime::state(scope);
+  let value = state_rc
+    .borrow()
+    .tree
+    .try_read_for(envar::MUTEX_TIMEOUT())
+    .unwrap()
+    .fill_chars();
+  trace!("get_fill_chars: {:?}", value);
+  let value = v8::String::new(scope, &value.to_string()).unwrap();
+  rv.set(value.into());
+}
+
+/// Set the _fillchars_ option.
+pub fn set_fill_chars(
+  scope: &mut v8::HandleScope,
+  args: v8::FunctionCallbackArguments,
+  _: v8::ReturnValue,
+) {
+  assert!(args.length() == 1);
+  let value = args.get(0).to_rust_string_lossy(scope);
+  trace!("set_fill_chars: {:?}", value);
+  match FillChars::from_str(&value) {
+    Ok(value) => {
+      let state_rc = JsRuntime::state(scope);
+      state_rc
+        .borrow_mut()
+        .tree
+        .try_write_for(envar::MUTEX_TIMEOUT())
+        .unwrap()
+        .set_fill_chars(value);
+    }
+    Err(e) => throw_exception(scope, &e),
+  }
+}
+
+/// Get the _list_ option.
+/// See: <https://vimhelp.org/options.txt.html#%27list%27>
+pub fn get_list(
+  scope: &mut v8::HandleScope,
+  _args: v8::FunctionCallbackArguments,
+  mut rv: v8::ReturnValue,
+) {
+  let state_rc = JsRuntime::state(scope);
+  let value = state_rc
+    .borrow()
+    .tree
+    .try_read_for(envar::MUTEX_TIMEOUT())
+    .unwrap()
+    .list();
+  trace!("get_list: {:?}", value);
+  rv.set_bool(value);
+}
+
+/// Set the _list_ option.
+pub fn set_list(
+  scope: &mut v8::HandleScope,
+  args: v8::FunctionCallbackArguments,
+  _: v8::ReturnValue,
+) {
+  assert!(args.length() == 1);
+  let value = args.get(0).to_boolean(scope).boolean_value(scope);
+  let state_rc = JsRuntime::state(scope);
+  trace!("set_list: {:?}", value);
+  state_rc
+    .borrow_mut()
+    .tree
+    .try_write_for(envar::MUTEX_TIMEOUT())
+    .unwrap()
+    .set_list(value);
+}
+
+/// Get the _listchars_ option.
+/// See: <https://vimhelp.org/options.txt.html#%27listchars%27>
+pub fn get_list_chars(
+  scope: &mut v8::HandleScope,
+  _args: v8::FunctionCallbackArguments,
+  mut rv: v8::ReturnValue,
+) {
+  let state_rc = JsRuntime::state(scope);
+  let value = state_rc
+    .borrow()
+    .tree
+    .try_read_for(envar::MUTEX_TIMEOUT())
+    .unwrap()
+    .list_chars();
+  trace!("get_list_chars: {:?}", value);
+  let value = v8::String::new(scope, &value.to_string()).unwrap();
+  rv.set(value.into());
+}
+
+/// Set the _listchars_ option.
+pub fn set_list_chars(
+  scope: &mut v8::HandleScope,
+  args: v8::FunctionCallbackArguments,
+  _: v8::ReturnValue,
+) {
+  assert!(args.length() == 1);
+  let value = args.get(0).to_rust_string_lossy(scope);
+  trace!("set_list_chars: {:?}", value);
+  match ListChars::from_str(&value) {
+    Ok(value) => {
+      let state_rc = JsRuntime::state(scope);
+      state_rc
+        .borrow_mut()
+        .tree
+        .try_write_for(envar::MUTEX_TIMEOUT())
+        .unwrap()
+        .set_list_chars(value);
+    }
+    Err(e) => throw_exception(scope, &e),
+  }
+}
+
+/// Get the _showbreak_ option.
+/// See: <https://vimhelp.org/options.txt.html#%27showbreak%27>
+pub fn get_show_break(
+  scope: &mut v8::HandleScope,
+  _args: v8::FunctionCallbackArguments,
+  mut rv: v8::ReturnValue,
+) {
+  let state_rc = JsRuntime::state(scope);
+  let value = state_rc
+    .borrow()
+    .tree
+    .try_read_for(envar::MUTEX_TIMEOUT())
+    .unwrap()
+    .show_break()
+    .to_string();
+  trace!("get_show_break: {:?}", value);
+  let value = v8::String::new(scope, &value).unwrap();
+  rv.set(value.into());
+}
+
+/// Set the _showbreak_ option.
+pub fn set_show_break(
+  scope: &mut v8::HandleScope,
+  args: v8::FunctionCallbackArguments,
+  _: v8::ReturnValue,
+) {
+  assert!(args.length() == 1);
+  let value = args.get(0).to_rust_string_lossy(scope);
+  trace!("set_show_break: {:?}", value);
+  let state_rc = JsRuntime::state(scope);
+  state_rc
+    .borrow_mut()
+    .tree
+    .try_write_for(envar::MUTEX_TIMEOUT())
+    .unwrap()
+    .set_show_break(&value);
+}
+
+/// Get the _scroll_ option.
+/// See: <https://vimhelp.org/options.txt.html#%27scroll%27>
+pub fn get_scroll(
+  scope: &mut v8::HandleScope,
+  _args: v8::FunctionCallbackArguments,
+  mut rv: v8::ReturnValue,
+) {
+  let state_rc = JsRuntime::state(scope);
+  let value = state_rc
+    .borrow()
+    .tree
+    .try_read_for(envar::MUTEX_TIMEOUT())
+    .unwrap()
+    .scroll();
+  trace!("get_scroll: {:?}", value);
+  rv.set_uint32(value as u32);
+}
+
+/// Set the _scroll_ option.
+pub fn set_scroll(
+  scope: &mut v8::HandleScope,
+  args: v8::FunctionCallbackArguments,
+  _: v8::ReturnValue,
+) {
+  assert!(args.length() == 1);
+  let value = args.get(0).uint32_value(scope).unwrap_or(0) as usize;
+  let state_rc = JsRuntime::state(scope);
+  trace!("set_scroll: {:?}", value);
+  state_rc
+    .borrow_mut()
+    .tree
+    .try_write_for(envar::MUTEX_TIMEOUT())
+    .unwrap()
+    .set_scroll(value);
+}
+
+/// Get the _scrolloff_ option.
+/// See: <https://vimhelp.org/options.txt.html#%27scrolloff%27>
+pub fn get_scroll_off(
+  scope: &mut v8::HandleScope,
+  _args: v8::FunctionCallbackArguments,
+  mut rv: v8::ReturnValue,
+) {
+  let state_rc = JsRuntime::state(scope);
+  let value = state_rc
+    .borrow()
+    .tree
+    .try_read_for(envar::MUTEX_TIMEOUT())
+    .unwrap()
+    .scroll_off();
+  trace!("get_scroll_off: {:?}", value);
+  rv.set_uint32(value as u32);
+}
+
+/// Set the _scrolloff_ option.
+pub fn set_scroll_off(
+  scope: &mut v8::HandleScope,
+  args: v8::FunctionCallbackArguments,
+  _: v8::ReturnValue,
+) {
+  assert!(args.length() == 1);
+  let value = args.get(0).uint32_value(scope).unwrap_or(0) as usize;
+  let state_rc = JsRuntime::state(scope);
+  trace!("set_scroll_off: {:?}", value);
+  state_rc
+    .borrow_mut()
+    .tree
+    .try_write_for(envar::MUTEX_TIMEOUT())
+    .unwrap()
+    .set_scroll_off(value);
+}
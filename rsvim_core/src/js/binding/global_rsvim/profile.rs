@@ -0,0 +1,74 @@
+//! APIs for `Rsvim.profile`.
+
+use crate::envar;
+use crate::js::JsRuntime;
+use crate::state::profile::{PhaseStats, RenderPhase};
+
+use tracing::trace;
+
+/// Set the property of the object.
+fn set_property(
+  scope: &mut v8::HandleScope,
+  object: v8::Local<v8::Object>,
+  name: &str,
+  value: v8::Local<v8::Value>,
+) {
+  let key = v8::String::new(scope, name).unwrap();
+  object.set(scope, key.into(), value);
+}
+
+/// Make the JS `{min, avg, p99}` object in milliseconds, or `null` if there's no recorded frame.
+fn to_stats<'s>(
+  scope: &mut v8::HandleScope<'s>,
+  stats: Option<PhaseStats>,
+) -> v8::Local<'s, v8::Value> {
+  let stats = match stats {
+    Some(stats) => stats,
+    None => return v8::null(scope).into(),
+  };
+  let object = v8::Object::new(scope);
+  for (name, value) in [("min", stats.min), ("avg", stats.avg), ("p99", stats.p99)] {
+    let value = v8::Number::new(scope, value.as_secs_f64() * 1000.0);
+    set_property(scope, object, name, value.into());
+  }
+  object.into()
+}
+
+/// Get the render profile, i.e. `{enabled, frames, phases: {[phase]: {min, avg, p99} | null}}`,
+/// the durations are in milliseconds. The `total` phase is the whole frame.
+pub fn profile(
+  scope: &mut v8::HandleScope,
+  _args: v8::FunctionCallbackArguments,
+  mut rv: v8::ReturnValue,
+) {
+  let state_rc = JsRuntime::state(scope);
+  let (enabled, frames, stats) = {
+    let state = state_rc.borrow();
+    let editing_state = state
+      .editing_state
+      .try_read_for(envar::MUTEX_TIMEOUT())
+      .unwrap();
+    let profile = editing_state.render_profile();
+    let mut stats: Vec<(&str, Option<PhaseStats>)> = RenderPhase::ALL
+      .iter()
+      .map(|phase| (phase.name(), profile.stats(*phase)))
+      .collect();
+    stats.push(("total", profile.total_stats()));
+    (profile.enabled(), profile.frames().len(), stats)
+  };
+  trace!("profile: {:?} frames", frames);
+
+  let phases = v8::Object::new(scope);
+  for (name, stats) in stats {
+    let value = to_stats(scope, stats);
+    set_property(scope, phases, name, value);
+  }
+
+  let object = v8::Object::new(scope);
+  let value = v8::Boolean::new(scope, enabled);
+  set_property(scope, object, "enabled", value.into());
+  let value = v8::Number::new(scope, frames as f64);
+  set_property(scope, object, "frames", value.into());
+  set_property(scope, object, "phases", phases.into());
+  rv.set(object.into());
+}
@@ -0,0 +1,90 @@
+//! APIs for `Rsvim.sign` namespace.
+
+use crate::buf::sign::DEFAULT_SIGN_PRIORITY;
+use crate::buf::{BufferId, Sign};
+use crate::envar;
+use crate::js::binding::throw_exception;
+use crate::js::JsRuntime;
+
+use tracing::trace;
+
+/// Place a sign on a buffer line, it replaces the sign of the same group on the line.
+/// See: <https://vimhelp.org/builtin.txt.html#sign_place%28%29>
+pub fn place(scope: &mut v8::HandleScope, args: v8::FunctionCallbackArguments, _: v8::ReturnValue) {
+  assert!(args.length() == 6);
+  let buf_id = args.get(0).int32_value(scope).unwrap_or(0) as BufferId;
+  let line_idx = args.get(1).uint32_value(scope).unwrap_or(0) as usize;
+  let group = args.get(2).to_rust_string_lossy(scope);
+  let glyph = args.get(3).to_rust_string_lossy(scope);
+  let priority = match args.get(4) {
+    value if value.is_null_or_undefined() => DEFAULT_SIGN_PRIORITY,
+    value => value.uint32_value(scope).unwrap_or(DEFAULT_SIGN_PRIORITY),
+  };
+  let line_highlight = match args.get(5) {
+    value if value.is_null_or_undefined() => None,
+    value => Some(value.to_rust_string_lossy(scope)),
+  };
+  trace!(
+    "place: {:?}, line: {:?}, group: {:?}, glyph: {:?}, priority: {:?}, line_highlight: {:?}",
+    buf_id,
+    line_idx,
+    group,
+    glyph,
+    priority,
+    line_highlight
+  );
+
+  let mut sign = Sign::new(&group, &glyph, priority);
+  if let Some(line_highlight) = line_highlight {
+    sign = sign.with_line_highlight(&line_highlight);
+  }
+  let state_rc = JsRuntime::state(scope);
+  let buffers = state_rc.borrow().buffers.clone();
+  let buffers = buffers.try_read_for(envar::MUTEX_TIMEOUT()).unwrap();
+  let result = match buffers.get(&buf_id) {
+    Some(buf) => buf
+      .try_write_for(envar::MUTEX_TIMEOUT())
+      .unwrap()
+      .place_sign(line_idx, sign)
+      .map_err(anyhow::Error::from),
+    None => Err(anyhow::anyhow!("E86: Buffer {} does not exist", buf_id)),
+  };
+  if let Err(e) = result {
+    throw_exception(scope, &e);
+  }
+}
+
+/// Remove the sign of the group on a buffer line.
+/// See: <https://vimhelp.org/builtin.txt.html#sign_unplace%28%29>
+pub fn unplace(
+  scope: &mut v8::HandleScope,
+  args: v8::FunctionCallbackArguments,
+  _: v8::ReturnValue,
+) {
+  assert!(args.length() == 3);
+  let buf_id = args.get(0).int32_value(scope).unwrap_or(0) as BufferId;
+  let line_idx = args.get(1).uint32_value(scope).unwrap_or(0) as usize;
+  let group = args.get(2).to_rust_string_lossy(scope);
+  trace!(
+    "unplace: {:?}, line: {:?}, group: {:?}",
+    buf_id,
+    line_idx,
+    group
+  );
+
+  let state_rc = JsRuntime::state(scope);
+  let buffers = state_rc.borrow().buffers.clone();
+  let buffers = buffers.try_read_for(envar::MUTEX_TIMEOUT()).unwrap();
+  match buffers.get(&buf_id) {
+    Some(buf) => {
+      buf
+        .try_write_for(envar::MUTEX_TIMEOUT())
+        .unwrap()
+        .unplace_sign(line_idx, &group);
+    }
+    None => throw_exception(
+      scope,
+      &anyhow::anyhow!("E86: Buffer {} does not exist", buf_id),
+    ),
+  }
+}
@@ -0,0 +1,246 @@
+//! APIs for `Rsvim.win` namespace.
+
+use crate::buf::BufferId;
+use crate::envar;
+use crate::js::binding::throw_exception;
+use crate::js::JsRuntime;
+use crate::state::fsm::command_line::switch_buffer;
+use crate::ui::tree::internal::Inodeable;
+use crate::ui::tree::{SplitDirection, TreeNode, TreeNodeId};
+
+use std::sync::Arc;
+use tracing::trace;
+
+/// Set the property of the object.
+fn set_property(
+  scope: &mut v8::HandleScope,
+  object: v8::Local<v8::Object>,
+  name: &str,
+  value: v8::Local<v8::Value>,
+) {
+  let key = v8::String::new(scope, name).unwrap();
+  object.set(scope, key.into(), value);
+}
+
+/// Make the JS number from an integer.
+fn number<'s>(scope: &mut v8::HandleScope<'s>, value: impl Into<f64>) -> v8::Local<'s, v8::Value> {
+  v8::Number::new(scope, value.into()).into()
+}
+
+/// The error for the window ID that doesn't exist.
+fn invalid_window(win_id: TreeNodeId) -> anyhow::Error {
+  anyhow::anyhow!("E957: Invalid window number: {}", win_id)
+}
+
+/// Get all the windows, i.e. the array of `{id, shape: {x, y, width, height}}`, the shape is the
+/// actual shape on the terminal.
+pub fn list(
+  scope: &mut v8::HandleScope,
+  _args: v8::FunctionCallbackArguments,
+  mut rv: v8::ReturnValue,
+) {
+  let state_rc = JsRuntime::state(scope);
+  let tree = state_rc.borrow().tree.clone();
+  let tree = tree.try_read_for(envar::MUTEX_TIMEOUT()).unwrap();
+  let windows: Vec<(TreeNodeId, _)> = tree
+    .window_ids()
+    .iter()
+    .map(|id| (*id, *tree.node(id).unwrap().actual_shape()))
+    .collect();
+  trace!("list: {:?}", windows);
+
+  let array = v8::Array::new(scope, windows.len() as i32);
+  for (i, (id, actual_shape)) in windows.into_iter().enumerate() {
+    let shape = v8::Object::new(scope);
+    let value = number(scope, actual_shape.min().x);
+    set_property(scope, shape, "x", value);
+    let value = number(scope, actual_shape.min().y);
+    set_property(scope, shape, "y", value);
+    let value = number(scope, actual_shape.width());
+    set_property(scope, shape, "width", value);
+    let value = number(scope, actual_shape.height());
+    set_property(scope, shape, "height", value);
+
+    let window = v8::Object::new(scope);
+    let value = number(scope, id);
+    set_property(scope, window, "id", value);
+    set_property(scope, window, "shape", shape.into());
+    array.set_index(scope, i as u32, window.into());
+  }
+  rv.set(array.into());
+}
+
+/// Get the current window ID, i.e. the focused window. It returns `null` if there's no window.
+pub fn current(
+  scope: &mut v8::HandleScope,
+  _args: v8::FunctionCallbackArguments,
+  mut rv: v8::ReturnValue,
+) {
+  let state_rc = JsRuntime::state(scope);
+  let value = state_rc
+    .borrow()
+    .tree
+    .try_read_for(envar::MUTEX_TIMEOUT())
+    .unwrap()
+    .current_window_id();
+  trace!("current: {:?}", value);
+  match value {
+    Some(id) => rv.set_int32(id),
+    None => rv.set_null(),
+  }
+}
+
+/// Set the buffer of the window, i.e. the window switches to another buffer.
+/// See: <https://neovim.io/doc/user/api.html#nvim_win_set_buf()>
+pub fn set_buffer(
+  scope: &mut v8::HandleScope,
+  args: v8::FunctionCallbackArguments,
+  _: v8::ReturnValue,
+) {
+  assert!(args.length() == 2);
+  let win_id = args.get(0).int32_value(scope).unwrap_or(0) as TreeNodeId;
+  let buf_id = args.get(1).int32_value(scope).unwrap_or(0) as BufferId;
+  trace!("set_buffer: {:?}, buffer: {:?}", win_id, buf_id);
+
+  let state_rc = JsRuntime::state(scope);
+  let (tree, buffers) = {
+    let state = state_rc.borrow();
+    (state.tree.clone(), state.buffers.clone())
+  };
+  let buffer = match buffers
+    .try_read_for(envar::MUTEX_TIMEOUT())
+    .unwrap()
+    .get(&buf_id)
+  {
+    Some(buffer) => buffer.clone(),
+    None => {
+      throw_exception(
+        scope,
+        &anyhow::anyhow!("E86: Buffer {} does not exist", buf_id),
+      );
+      return;
+    }
+  };
+  let mut tree = tree.try_write_for(envar::MUTEX_TIMEOUT()).unwrap();
+  // The cursor widget moves along with the restored cursor in the focused window.
+  let found = if tree.focused_window_id() == Some(win_id) {
+    switch_buffer(&mut tree, &buffer);
+    true
+  } else {
+    match tree.node_mut(&win_id) {
+      Some(TreeNode::Window(window)) => {
+        window.set_buffer(Arc::downgrade(&buffer));
+        true
+      }
+      _ => false,
+    }
+  };
+  drop(tree);
+  if !found {
+    throw_exception(scope, &invalid_window(win_id));
+  }
+}
+
+/// Split the window into two windows, the direction is `"horizontal"` (i.e. `:split`) or
+/// `"vertical"` (i.e. `:vsplit`). It returns the new window ID.
+/// See: <https://vimhelp.org/windows.txt.html#%3Asplit>
+pub fn split(
+  scope: &mut v8::HandleScope,
+  args: v8::FunctionCallbackArguments,
+  mut rv: v8::ReturnValue,
+) {
+  assert!(args.length() == 2);
+  let win_id = args.get(0).int32_value(scope).unwrap_or(0) as TreeNodeId;
+  let direction = args.get(1).to_rust_string_lossy(scope);
+  trace!("split: {:?}, direction: {:?}", win_id, direction);
+
+  let direction = match direction.as_str() {
+    "horizontal" => SplitDirection::Horizontal,
+    "vertical" => SplitDirection::Vertical,
+    _ => {
+      throw_exception(
+        scope,
+        &anyhow::anyhow!("E475: Invalid argument: {}", direction),
+      );
+      return;
+    }
+  };
+  let state_rc = JsRuntime::state(scope);
+  let tree = state_rc.borrow().tree.clone();
+  let result = {
+    let mut tree = tree.try_write_for(envar::MUTEX_TIMEOUT()).unwrap();
+    if tree.window_ids().contains(&win_id) {
+      tree
+        .split_window(win_id, direction)
+        .ok_or_else(|| anyhow::anyhow!("E36: Not enough room"))
+    } else {
+      Err(invalid_window(win_id))
+    }
+  };
+  match result {
+    Ok(id) => rv.set_int32(id),
+    Err(e) => throw_exception(scope, &e),
+  }
+}
+
+/// Close the window, the last window cannot be closed.
+/// See: <https://vimhelp.org/windows.txt.html#%3Aclose>
+pub fn close(scope: &mut v8::HandleScope, args: v8::FunctionCallbackArguments, _: v8::ReturnValue) {
+  assert!(args.length() == 1);
+  let win_id = args.get(0).int32_value(scope).unwrap_or(0) as TreeNodeId;
+  trace!("close: {:?}", win_id);
+
+  let state_rc = JsRuntime::state(scope);
+  let tree = state_rc.borrow().tree.clone();
+  let result = {
+    let mut tree = tree.try_write_for(envar::MUTEX_TIMEOUT()).unwrap();
+    if !tree.window_ids().contains(&win_id) {
+      Err(invalid_window(win_id))
+    } else if !tree.close_window(win_id) {
+      Err(anyhow::anyhow!("E444: Cannot close last window"))
+    } else {
+      Ok(())
+    }
+  };
+  if let Err(e) = result {
+    throw_exception(scope, &e);
+  }
+}
+
+/// Get the viewport of the window, i.e. `{startLine, endLine}`, the buffer lines in range
+/// `[startLine, endLine)` are displayed in the window.
+pub fn get_viewport(
+  scope: &mut v8::HandleScope,
+  args: v8::FunctionCallbackArguments,
+  mut rv: v8::ReturnValue,
+) {
+  assert!(args.length() == 1);
+  let win_id = args.get(0).int32_value(scope).unwrap_or(0) as TreeNodeId;
+  trace!("get_viewport: {:?}", win_id);
+
+  let state_rc = JsRuntime::state(scope);
+  let tree = state_rc.borrow().tree.clone();
+  let lines = match tree
+    .try_read_for(envar::MUTEX_TIMEOUT())
+    .unwrap()
+    .node(&win_id)
+  {
+    Some(TreeNode::Window(window)) => {
+      let viewport = window.viewport();
+      let viewport = viewport.try_read_for(envar::MUTEX_TIMEOUT()).unwrap();
+      Some((viewport.start_line_idx(), viewport.end_line_idx()))
+    }
+    _ => None,
+  };
+  match lines {
+    Some((start_line, end_line)) => {
+      let object = v8::Object::new(scope);
+      let value = number(scope, start_line as f64);
+      set_property(scope, object, "startLine", value);
+      let value = number(scope, end_line as f64);
+      set_property(scope, object, "endLine", value);
+      rv.set(object.into());
+    }
+    None => throw_exception(scope, &invalid_window(win_id)),
+  }
+}
@@ -1,9 +1,22 @@
 //! Messages synced between [`EventLoop`](crate::evloop::EventLoop) and
 //! [`JsRuntime`](crate::js::JsRuntime).
+//!
+//! Both directions use the bounded channels with size
+//! [`CHANNEL_BUF_SIZE`](crate::envar::CHANNEL_BUF_SIZE), and they never wait for the capacity on
+//! the main thread, since the event loop and the js runtime run on the same thread:
+//!
+//! 1. Js runtime => event loop: The bindings use `try_send`, the message is dropped (with an error
+//!    log) if the channel is full. Except the timer requests, they're sent from a blocking thread
+//!    and wait for the capacity, because the timer callbacks must not be lost.
+//! 2. Event loop => js runtime: The event loop uses `try_send` and then ticks the js runtime at
+//!    once, so the messages never pile up in the channel.
 
 use std::time::Duration;
 
+use crate::buf::BufferId;
+use crate::js::err::JsError;
 use crate::js::JsFutureId;
+use crate::state::quit::QuitRequest;
 
 // The message JsRuntime send to EventLoop {
 
@@ -12,6 +25,20 @@ use crate::js::JsFutureId;
 /// [`JsRuntime`](crate::js::JsRuntime).
 pub enum JsRuntimeToEventLoopMessage {
   TimeoutReq(TimeoutReq),
+  /// Show a normal message in the message area, i.e. `Rsvim.echo`.
+  Echo(String),
+  /// Show an error message in the message area, i.e. `Rsvim.echoerr` and the uncaught exceptions
+  /// thrown by the timer callbacks.
+  Error(JsError),
+  /// Set the option by its name (same with the `Rsvim.opt` property), in the scope.
+  SetOption {
+    scope: OptionScope,
+    name: String,
+    value: OptionValue,
+  },
+  /// Quit the editor, i.e. `Rsvim.cmd("quit")`. It's rejected if there're modified buffers, unless
+  /// it's forced.
+  Quit(QuitRequest),
 }
 
 // The message JsRuntime send to EventLoop }
@@ -22,12 +49,36 @@ pub enum JsRuntimeToEventLoopMessage {
 /// Message between [`EventLoop`](crate::evloop::EventLoop) and
 /// [`JsRuntime`](crate::js::JsRuntime).
 pub enum EventLoopToJsRuntimeMessage {
-  /// Event loop notify Js runtime to shutdown this thread.
   TimeoutResp(TimeoutResp),
+  /// Event loop notify js runtime to run a tick, i.e. the microtasks and the expired timers.
+  TickTimers,
+  /// Event loop notify js runtime the buffer text is changed by the editor.
+  BufferChanged(BufferId),
+  /// Event loop notify js runtime to shutdown, i.e. drop all the pending futures.
+  Shutdown,
 }
 
 // The message JsRuntime receive from EventLoop }
 
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+/// The option scopes.
+pub enum OptionScope {
+  /// The global options, i.e. 'incsearch' and 'hlsearch'.
+  Global,
+  /// The window-local options for current window and new windows, i.e. 'wrap'.
+  Window,
+  /// The buffer-local options for current buffer and new buffers, i.e. 'modifiable'.
+  Buffer,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// The option values.
+pub enum OptionValue {
+  Bool(bool),
+  Number(usize),
+  String(String),
+}
+
 #[derive(Debug, Default)]
 pub struct TimeoutResp {
   pub future_id: JsFutureId,
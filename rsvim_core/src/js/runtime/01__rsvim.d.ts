@@ -1,9 +1,118 @@
 export declare class Rsvim {
     readonly opt: RsvimOpt;
+    readonly cmd: RsvimCmd;
+    readonly buf: RsvimBuf;
+    readonly highlight: RsvimHighlight;
+    readonly sign: RsvimSign;
+    readonly win: RsvimWin;
+    readonly colorscheme: RsvimColorScheme;
+    echo(message: string): void;
+    echoerr(message: string): void;
+    autocmd(event: RsvimAutocmdEvent, pattern: string | null | undefined, callback: (ctx: RsvimAutocmdContext) => boolean | void): void;
+    profile(): RsvimProfile;
+}
+export type RsvimAutocmdEvent = "BufReadPost" | "BufWritePre" | "BufWritePost" | "ModeChanged";
+export interface RsvimAutocmdContext {
+    event: RsvimAutocmdEvent;
+    bufId?: number;
+    file?: string;
+    oldMode?: string;
+    newMode?: string;
+}
+export interface RsvimProfilePhase {
+    min: number;
+    avg: number;
+    p99: number;
+}
+export interface RsvimProfile {
+    enabled: boolean;
+    frames: number;
+    phases: {
+        prepare: RsvimProfilePhase | null;
+        draw: RsvimProfilePhase | null;
+        diff: RsvimProfilePhase | null;
+        flush: RsvimProfilePhase | null;
+        total: RsvimProfilePhase | null;
+    };
 }
 export declare class RsvimOpt {
     get wrap(): boolean;
     set wrap(value: boolean);
     get lineBreak(): boolean;
     set lineBreak(value: boolean);
+    get modifiable(): boolean;
+    set modifiable(value: boolean);
+    get readonly(): boolean;
+    set readonly(value: boolean);
+    get fillChars(): string;
+    set fillChars(value: string);
+    get list(): boolean;
+    set list(value: boolean);
+    get listChars(): string;
+    set listChars(value: string);
+    get showBreak(): string;
+    set showBreak(value: string);
+    get scroll(): number;
+    set scroll(value: number);
+    get scrollOff(): number;
+    set scrollOff(value: number);
+}
+export interface RsvimCmdOptions {
+    force?: boolean;
+}
+export interface RsvimCmd {
+    (command: string): void;
+    create(name: string, callback: (args: string) => void, opts?: RsvimCmdOptions): void;
+}
+export interface RsvimHighlightSpan {
+    startChar: number;
+    endChar: number;
+    fg?: string;
+    bg?: string;
+    attrs?: string[];
+}
+export declare class RsvimHighlight {
+    set(bufId: number, line: number, spans: RsvimHighlightSpan[]): void;
+    clear(bufId: number, startLine: number, endLine: number): void;
+}
+export declare class RsvimSign {
+    place(bufId: number, line: number, group: string, glyph: string, priority?: number, lineHl?: string): void;
+    unplace(bufId: number, line: number, group: string): void;
+}
+export declare class RsvimBuf {
+    setLine(bufId: number, line: number, text: string): void;
+    appendLines(bufId: number, after: number, lines: string[]): void;
+    deleteLines(bufId: number, startLine: number, endLine: number): void;
+}
+export interface RsvimColorSchemeGroup {
+    fg?: string;
+    bg?: string;
+    attrs?: string[];
+}
+export interface RsvimColorScheme {
+    (name?: string): string;
+    register(name: string, groups: Record<string, RsvimColorSchemeGroup | string>): void;
+    list(): string[];
+}
+export interface RsvimWinInfo {
+    id: number;
+    shape: {
+        x: number;
+        y: number;
+        width: number;
+        height: number;
+    };
+}
+export interface RsvimWinViewport {
+    startLine: number;
+    endLine: number;
+}
+export type RsvimWinSplitDirection = "horizontal" | "vertical";
+export declare class RsvimWin {
+    list(): RsvimWinInfo[];
+    current(): number | null;
+    setBuffer(winId: number, bufId: number): void;
+    split(winId: number, direction: RsvimWinSplitDirection): number;
+    close(winId: number): void;
+    getViewport(winId: number): RsvimWinViewport;
 }
@@ -1,7 +1,42 @@
 var Rsvim = (function () {
     function Rsvim() {
         this.opt = new RsvimOpt();
+        this.cmd = createRsvimCmd();
+        this.buf = new RsvimBuf();
+        this.highlight = new RsvimHighlight();
+        this.sign = new RsvimSign();
+        this.win = new RsvimWin();
+        this.colorscheme = createRsvimColorScheme();
     }
+    Rsvim.prototype.echo = function (message) {
+        if (typeof message !== "string") {
+            throw new Error("\"Rsvim.echo\" message must be string type, but found ".concat(message, " (").concat(typeof message, ")"));
+        }
+        __InternalRsvimGlobalObject.echo(message);
+    };
+    Rsvim.prototype.echoerr = function (message) {
+        if (typeof message !== "string") {
+            throw new Error("\"Rsvim.echoerr\" message must be string type, but found ".concat(message, " (").concat(typeof message, ")"));
+        }
+        __InternalRsvimGlobalObject.echoerr(message);
+    };
+    Rsvim.prototype.autocmd = function (event, pattern, callback) {
+        if (typeof event !== "string") {
+            throw new Error("\"Rsvim.autocmd\" event must be string type, but found ".concat(event, " (").concat(typeof event, ")"));
+        }
+        if (pattern !== null &&
+            pattern !== undefined &&
+            typeof pattern !== "string") {
+            throw new Error("\"Rsvim.autocmd\" pattern must be string type, but found ".concat(pattern, " (").concat(typeof pattern, ")"));
+        }
+        if (typeof callback !== "function") {
+            throw new Error("\"Rsvim.autocmd\" callback must be function type, but found ".concat(callback, " (").concat(typeof callback, ")"));
+        }
+        __InternalRsvimGlobalObject.autocmd_create(event, pattern, callback);
+    };
+    Rsvim.prototype.profile = function () {
+        return __InternalRsvimGlobalObject.profile();
+    };
     return Rsvim;
 }());
 export { Rsvim };
@@ -34,9 +69,312 @@ var RsvimOpt = (function () {
         enumerable: false,
         configurable: true
     });
+    Object.defineProperty(RsvimOpt.prototype, "modifiable", {
+        get: function () {
+            return __InternalRsvimGlobalObject.opt_get_modifiable();
+        },
+        set: function (value) {
+            if (typeof value !== "boolean") {
+                throw new Error("\"Rsvim.opt.modifiable\" value must be boolean type, but found ".concat(value, " (").concat(typeof value, ")"));
+            }
+            __InternalRsvimGlobalObject.opt_set_modifiable(value);
+        },
+        enumerable: false,
+        configurable: true
+    });
+    Object.defineProperty(RsvimOpt.prototype, "readonly", {
+        get: function () {
+            return __InternalRsvimGlobalObject.opt_get_readonly();
+        },
+        set: function (value) {
+            if (typeof value !== "boolean") {
+                throw new Error("\"Rsvim.opt.readonly\" value must be boolean type, but found ".concat(value, " (").concat(typeof value, ")"));
+            }
+            __InternalRsvimGlobalObject.opt_set_readonly(value);
+        },
+        enumerable: false,
+        configurable: true
+    });
+    Object.defineProperty(RsvimOpt.prototype, "fillChars", {
+        get: function () {
+            return __InternalRsvimGlobalObject.opt_get_fill_chars();
+        },
+        set: function (value) {
+            if (typeof value !== "string") {
+                throw new Error("\"Rsvim.opt.fillChars\" value must be string type, but found ".concat(value, " (").concat(typeof value, ")"));
+            }
+            __InternalRsvimGlobalObject.opt_set_fill_chars(value);
+        },
+        enumerable: false,
+        configurable: true
+    });
+    Object.defineProperty(RsvimOpt.prototype, "list", {
+        get: function () {
+            return __InternalRsvimGlobalObject.opt_get_list();
+        },
+        set: function (value) {
+            if (typeof value !== "boolean") {
+                throw new Error("\"Rsvim.opt.list\" value must be boolean type, but found ".concat(value, " (").concat(typeof value, ")"));
+            }
+            __InternalRsvimGlobalObject.opt_set_list(value);
+        },
+        enumerable: false,
+        configurable: true
+    });
+    Object.defineProperty(RsvimOpt.prototype, "listChars", {
+        get: function () {
+            return __InternalRsvimGlobalObject.opt_get_list_chars();
+        },
+        set: function (value) {
+            if (typeof value !== "string") {
+                throw new Error("\"Rsvim.opt.listChars\" value must be string type, but found ".concat(value, " (").concat(typeof value, ")"));
+            }
+            __InternalRsvimGlobalObject.opt_set_list_chars(value);
+        },
+        enumerable: false,
+        configurable: true
+    });
+    Object.defineProperty(RsvimOpt.prototype, "showBreak", {
+        get: function () {
+            return __InternalRsvimGlobalObject.opt_get_show_break();
+        },
+        set: function (value) {
+            if (typeof value !== "string") {
+                throw new Error("\"Rsvim.opt.showBreak\" value must be string type, but found ".concat(value, " (").concat(typeof value, ")"));
+            }
+            __InternalRsvimGlobalObject.opt_set_show_break(value);
+        },
+        enumerable: false,
+        configurable: true
+    });
+    Object.defineProperty(RsvimOpt.prototype, "scroll", {
+        get: function () {
+            return __InternalRsvimGlobalObject.opt_get_scroll();
+        },
+        set: function (value) {
+            if (typeof value !== "number" || !Number.isInteger(value) || value < 0) {
+                throw new Error("\"Rsvim.opt.scroll\" value must be non-negative integer type, but found ".concat(value, " (").concat(typeof value, ")"));
+            }
+            __InternalRsvimGlobalObject.opt_set_scroll(value);
+        },
+        enumerable: false,
+        configurable: true
+    });
+    Object.defineProperty(RsvimOpt.prototype, "scrollOff", {
+        get: function () {
+            return __InternalRsvimGlobalObject.opt_get_scroll_off();
+        },
+        set: function (value) {
+            if (typeof value !== "number" || !Number.isInteger(value) || value < 0) {
+                throw new Error("\"Rsvim.opt.scrollOff\" value must be non-negative integer type, but found ".concat(value, " (").concat(typeof value, ")"));
+            }
+            __InternalRsvimGlobalObject.opt_set_scroll_off(value);
+        },
+        enumerable: false,
+        configurable: true
+    });
     return RsvimOpt;
 }());
 export { RsvimOpt };
+function createRsvimCmd() {
+    var cmd = function (command) {
+        if (typeof command !== "string") {
+            throw new Error("\"Rsvim.cmd\" command must be string type, but found ".concat(command, " (").concat(typeof command, ")"));
+        }
+        __InternalRsvimGlobalObject.cmd_exec(command);
+    };
+    cmd.create = function (name, callback, opts) {
+        if (typeof name !== "string") {
+            throw new Error("\"Rsvim.cmd.create\" name must be string type, but found ".concat(name, " (").concat(typeof name, ")"));
+        }
+        if (typeof callback !== "function") {
+            throw new Error("\"Rsvim.cmd.create\" callback must be function type, but found ".concat(callback, " (").concat(typeof callback, ")"));
+        }
+        var force = opts === undefined || opts.force === undefined ? true : opts.force;
+        if (typeof force !== "boolean") {
+            throw new Error("\"Rsvim.cmd.create\" opts.force must be boolean type, but found ".concat(force, " (").concat(typeof force, ")"));
+        }
+        __InternalRsvimGlobalObject.cmd_create(name, callback, force);
+    };
+    return cmd;
+}
+var RsvimHighlight = (function () {
+    function RsvimHighlight() {
+    }
+    RsvimHighlight.prototype.set = function (bufId, line, spans) {
+        if (typeof bufId !== "number") {
+            throw new Error("\"Rsvim.highlight.set\" bufId must be number type, but found ".concat(bufId, " (").concat(typeof bufId, ")"));
+        }
+        if (typeof line !== "number") {
+            throw new Error("\"Rsvim.highlight.set\" line must be number type, but found ".concat(line, " (").concat(typeof line, ")"));
+        }
+        if (!Array.isArray(spans)) {
+            throw new Error("\"Rsvim.highlight.set\" spans must be array type, but found ".concat(spans, " (").concat(typeof spans, ")"));
+        }
+        __InternalRsvimGlobalObject.highlight_set(bufId, line, spans);
+    };
+    RsvimHighlight.prototype.clear = function (bufId, startLine, endLine) {
+        if (typeof bufId !== "number") {
+            throw new Error("\"Rsvim.highlight.clear\" bufId must be number type, but found ".concat(bufId, " (").concat(typeof bufId, ")"));
+        }
+        if (typeof startLine !== "number") {
+            throw new Error("\"Rsvim.highlight.clear\" startLine must be number type, but found ".concat(startLine, " (").concat(typeof startLine, ")"));
+        }
+        if (typeof endLine !== "number") {
+            throw new Error("\"Rsvim.highlight.clear\" endLine must be number type, but found ".concat(endLine, " (").concat(typeof endLine, ")"));
+        }
+        __InternalRsvimGlobalObject.highlight_clear(bufId, startLine, endLine);
+    };
+    return RsvimHighlight;
+}());
+export { RsvimHighlight };
+var RsvimSign = (function () {
+    function RsvimSign() {
+    }
+    RsvimSign.prototype.place = function (bufId, line, group, glyph, priority, lineHl) {
+        if (typeof bufId !== "number") {
+            throw new Error("\"Rsvim.sign.place\" bufId must be number type, but found ".concat(bufId, " (").concat(typeof bufId, ")"));
+        }
+        if (typeof line !== "number") {
+            throw new Error("\"Rsvim.sign.place\" line must be number type, but found ".concat(line, " (").concat(typeof line, ")"));
+        }
+        if (typeof group !== "string") {
+            throw new Error("\"Rsvim.sign.place\" group must be string type, but found ".concat(group, " (").concat(typeof group, ")"));
+        }
+        if (typeof glyph !== "string") {
+            throw new Error("\"Rsvim.sign.place\" glyph must be string type, but found ".concat(glyph, " (").concat(typeof glyph, ")"));
+        }
+        if (priority !== undefined && priority !== null && typeof priority !== "number") {
+            throw new Error("\"Rsvim.sign.place\" priority must be number type, but found ".concat(priority, " (").concat(typeof priority, ")"));
+        }
+        if (lineHl !== undefined && lineHl !== null && typeof lineHl !== "string") {
+            throw new Error("\"Rsvim.sign.place\" lineHl must be string type, but found ".concat(lineHl, " (").concat(typeof lineHl, ")"));
+        }
+        __InternalRsvimGlobalObject.sign_place(bufId, line, group, glyph, priority, lineHl);
+    };
+    RsvimSign.prototype.unplace = function (bufId, line, group) {
+        if (typeof bufId !== "number") {
+            throw new Error("\"Rsvim.sign.unplace\" bufId must be number type, but found ".concat(bufId, " (").concat(typeof bufId, ")"));
+        }
+        if (typeof line !== "number") {
+            throw new Error("\"Rsvim.sign.unplace\" line must be number type, but found ".concat(line, " (").concat(typeof line, ")"));
+        }
+        if (typeof group !== "string") {
+            throw new Error("\"Rsvim.sign.unplace\" group must be string type, but found ".concat(group, " (").concat(typeof group, ")"));
+        }
+        __InternalRsvimGlobalObject.sign_unplace(bufId, line, group);
+    };
+    return RsvimSign;
+}());
+export { RsvimSign };
+var RsvimBuf = (function () {
+    function RsvimBuf() {
+    }
+    RsvimBuf.prototype.setLine = function (bufId, line, text) {
+        if (typeof bufId !== "number") {
+            throw new Error("\"Rsvim.buf.setLine\" bufId must be number type, but found ".concat(bufId, " (").concat(typeof bufId, ")"));
+        }
+        if (typeof line !== "number") {
+            throw new Error("\"Rsvim.buf.setLine\" line must be number type, but found ".concat(line, " (").concat(typeof line, ")"));
+        }
+        if (typeof text !== "string") {
+            throw new Error("\"Rsvim.buf.setLine\" text must be string type, but found ".concat(text, " (").concat(typeof text, ")"));
+        }
+        __InternalRsvimGlobalObject.buf_set_line(bufId, line, text);
+    };
+    RsvimBuf.prototype.appendLines = function (bufId, after, lines) {
+        if (typeof bufId !== "number") {
+            throw new Error("\"Rsvim.buf.appendLines\" bufId must be number type, but found ".concat(bufId, " (").concat(typeof bufId, ")"));
+        }
+        if (typeof after !== "number") {
+            throw new Error("\"Rsvim.buf.appendLines\" after must be number type, but found ".concat(after, " (").concat(typeof after, ")"));
+        }
+        if (!Array.isArray(lines)) {
+            throw new Error("\"Rsvim.buf.appendLines\" lines must be array type, but found ".concat(lines, " (").concat(typeof lines, ")"));
+        }
+        __InternalRsvimGlobalObject.buf_append_lines(bufId, after, lines);
+    };
+    RsvimBuf.prototype.deleteLines = function (bufId, startLine, endLine) {
+        if (typeof bufId !== "number") {
+            throw new Error("\"Rsvim.buf.deleteLines\" bufId must be number type, but found ".concat(bufId, " (").concat(typeof bufId, ")"));
+        }
+        if (typeof startLine !== "number") {
+            throw new Error("\"Rsvim.buf.deleteLines\" startLine must be number type, but found ".concat(startLine, " (").concat(typeof startLine, ")"));
+        }
+        if (typeof endLine !== "number") {
+            throw new Error("\"Rsvim.buf.deleteLines\" endLine must be number type, but found ".concat(endLine, " (").concat(typeof endLine, ")"));
+        }
+        __InternalRsvimGlobalObject.buf_delete_lines(bufId, startLine, endLine);
+    };
+    return RsvimBuf;
+}());
+export { RsvimBuf };
+function createRsvimColorScheme() {
+    var colorscheme = function (name) {
+        if (name !== undefined) {
+            if (typeof name !== "string") {
+                throw new Error("\"Rsvim.colorscheme\" name must be string type, but found ".concat(name, " (").concat(typeof name, ")"));
+            }
+            __InternalRsvimGlobalObject.colorscheme_set(name);
+        }
+        return __InternalRsvimGlobalObject.colorscheme_current();
+    };
+    colorscheme.register = function (name, groups) {
+        if (typeof name !== "string") {
+            throw new Error("\"Rsvim.colorscheme.register\" name must be string type, but found ".concat(name, " (").concat(typeof name, ")"));
+        }
+        if (typeof groups !== "object" || groups === null) {
+            throw new Error("\"Rsvim.colorscheme.register\" groups must be object type, but found ".concat(groups, " (").concat(typeof groups, ")"));
+        }
+        __InternalRsvimGlobalObject.colorscheme_register(name, groups);
+    };
+    colorscheme.list = function () {
+        return __InternalRsvimGlobalObject.colorscheme_list();
+    };
+    return colorscheme;
+}
+var RsvimWin = (function () {
+    function RsvimWin() {
+    }
+    RsvimWin.prototype.list = function () {
+        return __InternalRsvimGlobalObject.win_list();
+    };
+    RsvimWin.prototype.current = function () {
+        return __InternalRsvimGlobalObject.win_current();
+    };
+    RsvimWin.prototype.setBuffer = function (winId, bufId) {
+        if (typeof winId !== "number") {
+            throw new Error("\"Rsvim.win.setBuffer\" winId must be number type, but found ".concat(winId, " (").concat(typeof winId, ")"));
+        }
+        if (typeof bufId !== "number") {
+            throw new Error("\"Rsvim.win.setBuffer\" bufId must be number type, but found ".concat(bufId, " (").concat(typeof bufId, ")"));
+        }
+        __InternalRsvimGlobalObject.win_set_buffer(winId, bufId);
+    };
+    RsvimWin.prototype.split = function (winId, direction) {
+        if (typeof winId !== "number") {
+            throw new Error("\"Rsvim.win.split\" winId must be number type, but found ".concat(winId, " (").concat(typeof winId, ")"));
+        }
+        if (direction !== "horizontal" && direction !== "vertical") {
+            throw new Error("\"Rsvim.win.split\" direction must be \"horizontal\" or \"vertical\", but found ".concat(direction, " (").concat(typeof direction, ")"));
+        }
+        return __InternalRsvimGlobalObject.win_split(winId, direction);
+    };
+    RsvimWin.prototype.close = function (winId) {
+        if (typeof winId !== "number") {
+            throw new Error("\"Rsvim.win.close\" winId must be number type, but found ".concat(winId, " (").concat(typeof winId, ")"));
+        }
+        __InternalRsvimGlobalObject.win_close(winId);
+    };
+    RsvimWin.prototype.getViewport = function (winId) {
+        if (typeof winId !== "number") {
+            throw new Error("\"Rsvim.win.getViewport\" winId must be number type, but found ".concat(winId, " (").concat(typeof winId, ")"));
+        }
+        return __InternalRsvimGlobalObject.win_get_viewport(winId);
+    };
+    return RsvimWin;
+}());
+export { RsvimWin };
 (function (globalThis) {
     globalThis.Rsvim = new Rsvim();
 })(globalThis);
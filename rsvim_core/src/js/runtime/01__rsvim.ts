@@ -21,6 +21,15 @@
  * The `Rsvim` global object, it contains multiple sub fields:
  *
  * - `Rsvim.opt`: Global editor options.
+ * - `Rsvim.cmd`: Ex commands.
+ * - `Rsvim.buf`: Edit buffer lines.
+ * - `Rsvim.highlight`: Highlight spans of buffer lines.
+ * - `Rsvim.sign`: Place signs on buffer lines.
+ * - `Rsvim.win`: Query and manipulate windows.
+ * - `Rsvim.colorscheme`: Register and switch color schemes.
+ * - `Rsvim.autocmd`: Register callbacks for editor events.
+ * - `Rsvim.echo`/`Rsvim.echoerr`: Show messages in the message area.
+ * - `Rsvim.profile`: Get the render profile.
  *
  *
  * @example
@@ -34,6 +43,205 @@
  */
 export class Rsvim {
   readonly opt: RsvimOpt = new RsvimOpt();
+  readonly cmd: RsvimCmd = createRsvimCmd();
+  readonly buf: RsvimBuf = new RsvimBuf();
+  readonly highlight: RsvimHighlight = new RsvimHighlight();
+  readonly sign: RsvimSign = new RsvimSign();
+  readonly win: RsvimWin = new RsvimWin();
+  readonly colorscheme: RsvimColorScheme = createRsvimColorScheme();
+
+  /**
+   * Show a message in the message area, i.e. the last row of the terminal, like Vim's `:echo`.
+   *
+   * The message is cleared on the next key, and truncated if it is longer than the terminal width.
+   *
+   * @see [Vim: eval.txt - :echo](https://vimhelp.org/eval.txt.html#%3Aecho)
+   *
+   * @example
+   * ```javascript
+   * Rsvim.echo("Hello, RSVIM!");
+   * ```
+   *
+   * @param {string} message - The message.
+   * @throws {@link !Error} if message is not a string value.
+   */
+  echo(message: string): void {
+    if (typeof message !== "string") {
+      throw new Error(
+        `"Rsvim.echo" message must be string type, but found ${message} (${typeof message})`,
+      );
+    }
+    // @ts-ignore Ignore warning
+    __InternalRsvimGlobalObject.echo(message);
+  }
+
+  /**
+   * Show an error message in the message area, like Vim's `:echoerr`. Same with
+   * {@link Rsvim.echo}, but the message is rendered with the error highlight.
+   *
+   * @see [Vim: eval.txt - :echoerr](https://vimhelp.org/eval.txt.html#%3Aechoerr)
+   *
+   * @example
+   * ```javascript
+   * Rsvim.echoerr("Something went wrong!");
+   * ```
+   *
+   * @param {string} message - The error message.
+   * @throws {@link !Error} if message is not a string value.
+   */
+  echoerr(message: string): void {
+    if (typeof message !== "string") {
+      throw new Error(
+        `"Rsvim.echoerr" message must be string type, but found ${message} (${typeof message})`,
+      );
+    }
+    // @ts-ignore Ignore warning
+    __InternalRsvimGlobalObject.echoerr(message);
+  }
+
+  /**
+   * Register a callback for the editor event, like Vim's `:autocmd`.
+   *
+   * The pattern is a simple glob, i.e. `*` matches any chars and `?` matches any single char. The
+   * buffer events (`BufReadPost`, `BufWritePre`, `BufWritePost`) match the absolute file name, the
+   * `ModeChanged` event matches `{oldMode}:{newMode}`, for example `Normal:Insert`. The callback
+   * without pattern (`null` or `undefined`) runs for every event.
+   *
+   * The `BufWritePre` callback can veto the write by returning `false` or throwing an error.
+   *
+   * @see [Vim: autocmd.txt](https://vimhelp.org/autocmd.txt.html)
+   *
+   * @example
+   * ```javascript
+   * // Never write the lock files.
+   * Rsvim.autocmd("BufWritePre", "*.lock", (ctx) => {
+   *   return false;
+   * });
+   * ```
+   *
+   * @param {RsvimAutocmdEvent} event - The event name.
+   * @param {string | null | undefined} pattern - The pattern to match.
+   * @param {(ctx: RsvimAutocmdContext) => boolean | void} callback - The callback runs when the event is fired.
+   * @throws {@link !Error} if event is not a string value, or pattern is not a string value, or
+   * callback is not a function value, or the event name is invalid.
+   */
+  autocmd(
+    event: RsvimAutocmdEvent,
+    pattern: string | null | undefined,
+    callback: (ctx: RsvimAutocmdContext) => boolean | void,
+  ): void {
+    if (typeof event !== "string") {
+      throw new Error(
+        `"Rsvim.autocmd" event must be string type, but found ${event} (${typeof event})`,
+      );
+    }
+    if (
+      pattern !== null &&
+      pattern !== undefined &&
+      typeof pattern !== "string"
+    ) {
+      throw new Error(
+        `"Rsvim.autocmd" pattern must be string type, but found ${pattern} (${typeof pattern})`,
+      );
+    }
+    if (typeof callback !== "function") {
+      throw new Error(
+        `"Rsvim.autocmd" callback must be function type, but found ${callback} (${typeof callback})`,
+      );
+    }
+    // @ts-ignore Ignore warning
+    __InternalRsvimGlobalObject.autocmd_create(event, pattern, callback);
+  }
+
+  /**
+   * Get the render profile, i.e. the time spent in each phase of rendering the last frames. It is
+   * only recorded when the editor is started with the `--profile` option, same with `:profile`.
+   *
+   * @example
+   * ```javascript
+   * const profile = Rsvim.profile();
+   * if (profile.enabled && profile.phases.total) {
+   *   Rsvim.echo(`avg frame: ${profile.phases.total.avg}ms`);
+   * }
+   * ```
+   *
+   * @returns {RsvimProfile} The render profile.
+   */
+  profile(): RsvimProfile {
+    // @ts-ignore Ignore warning
+    return __InternalRsvimGlobalObject.profile();
+  }
+}
+
+/**
+ * The editor events for `Rsvim.autocmd`.
+ *
+ * - `BufReadPost`: After reading a file into a new buffer.
+ * - `BufWritePre`: Before writing the buffer to a file.
+ * - `BufWritePost`: After writing the buffer to a file.
+ * - `ModeChanged`: After the editing mode changed.
+ *
+ * @category Editor APIs
+ */
+export type RsvimAutocmdEvent =
+  | "BufReadPost"
+  | "BufWritePre"
+  | "BufWritePost"
+  | "ModeChanged";
+
+/**
+ * The context of the fired event, it is passed to the `Rsvim.autocmd` callbacks.
+ *
+ * @category Editor APIs
+ */
+export interface RsvimAutocmdContext {
+  /** The event name. */
+  event: RsvimAutocmdEvent;
+  /** The buffer ID, only for buffer events. */
+  bufId?: number;
+  /** The absolute file name of the buffer, only for buffer events. */
+  file?: string;
+  /** The old editing mode, only for `ModeChanged`. */
+  oldMode?: string;
+  /** The new editing mode, only for `ModeChanged`. */
+  newMode?: string;
+}
+
+/**
+ * The statistics of a render phase over the recorded frames, in milliseconds.
+ *
+ * @category Editor APIs
+ */
+export interface RsvimProfilePhase {
+  /** The minimum time. */
+  min: number;
+  /** The average time. */
+  avg: number;
+  /** The 99th percentile time. */
+  p99: number;
+}
+
+/**
+ * The render profile returned by `Rsvim.profile`. A phase is `null` if there's no recorded frame.
+ *
+ * @category Editor APIs
+ */
+export interface RsvimProfile {
+  /** Whether the profiling is enabled, i.e. the `--profile` option. */
+  enabled: boolean;
+  /** The number of the recorded frames. */
+  frames: number;
+  /**
+   * The phases of rendering a frame, the `total` is the whole frame. The `prepare` phase collects
+   * the states shown on the widgets, i.e. the search highlights and the message.
+   */
+  phases: {
+    prepare: RsvimProfilePhase | null;
+    draw: RsvimProfilePhase | null;
+    diff: RsvimProfilePhase | null;
+    flush: RsvimProfilePhase | null;
+    total: RsvimProfilePhase | null;
+  };
 }
 
 /**
@@ -147,6 +355,1085 @@ export class RsvimOpt {
     // @ts-ignore Ignore warning
     __InternalRsvimGlobalObject.opt_set_line_break(value);
   }
+
+  /**
+   * Get the _modifiable_ option.
+   *
+   * Local to Buffer.
+   *
+   * When `false` (off), the buffer contents cannot be changed, any edit on the buffer is refused
+   * with error `E21`.
+   *
+   * @see [Vim: options.txt - 'modifiable'](https://vimhelp.org/options.txt.html#%27modifiable%27)
+   *
+   * @example
+   * ```javascript
+   * // Get the 'modifiable' option.
+   * const value = Rsvim.opt.modifiable;
+   * // Set the 'modifiable' option.
+   * Rsvim.opt.modifiable = false;
+   * ```
+   *
+   * @returns {boolean}
+   * @defaultValue `true`
+   */
+  get modifiable(): boolean {
+    // @ts-ignore Ignore warning
+    return __InternalRsvimGlobalObject.opt_get_modifiable();
+  }
+
+  /**
+   * Set the _modifiable_ option.
+   *
+   * @param {boolean} value - The _modifiable_ option.
+   * @throws {@link !Error} if value is not a boolean value.
+   */
+  set modifiable(value: boolean) {
+    if (typeof value !== "boolean") {
+      throw new Error(
+        `"Rsvim.opt.modifiable" value must be boolean type, but found ${value} (${typeof value})`,
+      );
+    }
+    // @ts-ignore Ignore warning
+    __InternalRsvimGlobalObject.opt_set_modifiable(value);
+  }
+
+  /**
+   * Get the _readonly_ option.
+   *
+   * Local to Buffer.
+   *
+   * When `true` (on), saving the buffer is refused with error `E45`, the buffer contents can
+   * still be changed unless {@link modifiable} is `false`.
+   *
+   * @see [Vim: options.txt - 'readonly'](https://vimhelp.org/options.txt.html#%27readonly%27)
+   *
+   * @example
+   * ```javascript
+   * // Get the 'readonly' option.
+   * const value = Rsvim.opt.readonly;
+   * // Set the 'readonly' option.
+   * Rsvim.opt.readonly = true;
+   * ```
+   *
+   * @returns {boolean}
+   * @defaultValue `false`
+   */
+  get readonly(): boolean {
+    // @ts-ignore Ignore warning
+    return __InternalRsvimGlobalObject.opt_get_readonly();
+  }
+
+  /**
+   * Set the _readonly_ option.
+   *
+   * @param {boolean} value - The _readonly_ option.
+   * @throws {@link !Error} if value is not a boolean value.
+   */
+  set readonly(value: boolean) {
+    if (typeof value !== "boolean") {
+      throw new Error(
+        `"Rsvim.opt.readonly" value must be boolean type, but found ${value} (${typeof value})`,
+      );
+    }
+    // @ts-ignore Ignore warning
+    __InternalRsvimGlobalObject.opt_set_readonly(value);
+  }
+
+  /**
+   * Get the _fillchars_ option.
+   *
+   * Local to Window.
+   *
+   * Characters to fill the special items of a window, it is a comma separated list of
+   * `{item}:{char}`. For now only the `eob` item is supported, i.e. the empty lines below the end
+   * of a buffer.
+   *
+   * @see [Vim: options.txt - 'fillchars'](https://vimhelp.org/options.txt.html#%27fillchars%27)
+   *
+   * @example
+   * ```javascript
+   * // Get the 'fillChars' option.
+   * const value = Rsvim.opt.fillChars;
+   * // Set the 'fillChars' option.
+   * Rsvim.opt.fillChars = "eob: ";
+   * ```
+   *
+   * @returns {string}
+   * @defaultValue `"eob:~"`
+   */
+  get fillChars(): string {
+    // @ts-ignore Ignore warning
+    return __InternalRsvimGlobalObject.opt_get_fill_chars();
+  }
+
+  /**
+   * Set the _fillchars_ option.
+   *
+   * @param {string} value - The _fillchars_ option.
+   * @throws {@link !Error} if value is not a string value, or it is invalid.
+   */
+  set fillChars(value: string) {
+    if (typeof value !== "string") {
+      throw new Error(
+        `"Rsvim.opt.fillChars" value must be string type, but found ${value} (${typeof value})`,
+      );
+    }
+    // @ts-ignore Ignore warning
+    __InternalRsvimGlobalObject.opt_set_fill_chars(value);
+  }
+
+  /**
+   * Get the _list_ option.
+   *
+   * Local to Window.
+   *
+   * Show the tabs, trailing spaces and line ends with the {@link listChars}.
+   *
+   * @see [Vim: options.txt - 'list'](https://vimhelp.org/options.txt.html#%27list%27)
+   *
+   * @example
+   * ```javascript
+   * // Get the 'list' option.
+   * const value = Rsvim.opt.list;
+   * // Set the 'list' option.
+   * Rsvim.opt.list = true;
+   * ```
+   *
+   * @returns {boolean}
+   * @defaultValue `false`
+   */
+  get list(): boolean {
+    // @ts-ignore Ignore warning
+    return __InternalRsvimGlobalObject.opt_get_list();
+  }
+
+  /**
+   * Set the _list_ option.
+   *
+   * @param {boolean} value - The _list_ option.
+   * @throws {@link !Error} if value is not a boolean value.
+   */
+  set list(value: boolean) {
+    if (typeof value !== "boolean") {
+      throw new Error(
+        `"Rsvim.opt.list" value must be boolean type, but found ${value} (${typeof value})`,
+      );
+    }
+    // @ts-ignore Ignore warning
+    __InternalRsvimGlobalObject.opt_set_list(value);
+  }
+
+  /**
+   * Get the _listchars_ option.
+   *
+   * Local to Window.
+   *
+   * Characters to show in {@link list} mode, it is a comma separated list of `{item}:{chars}`.
+   * For now these items are supported:
+   *
+   * - `tab:{lead}{fill}`: The tab is shown as the `lead` char, followed by the `fill` char for the
+   *   rest of the tab width.
+   * - `trail:{char}`: The trailing spaces at the end of a line.
+   * - `eol:{char}`: The char shown after the end of each line.
+   *
+   * The omitted items are not shown. All chars must be single-width.
+   *
+   * @see [Vim: options.txt - 'listchars'](https://vimhelp.org/options.txt.html#%27listchars%27)
+   *
+   * @example
+   * ```javascript
+   * // Get the 'listChars' option.
+   * const value = Rsvim.opt.listChars;
+   * // Set the 'listChars' option.
+   * Rsvim.opt.listChars = "tab:>-,trail:-,eol:$";
+   * ```
+   *
+   * @returns {string}
+   * @defaultValue `"tab:» ,trail:·"`
+   */
+  get listChars(): string {
+    // @ts-ignore Ignore warning
+    return __InternalRsvimGlobalObject.opt_get_list_chars();
+  }
+
+  /**
+   * Set the _listchars_ option.
+   *
+   * @param {string} value - The _listchars_ option.
+   * @throws {@link !Error} if value is not a string value, or it is invalid.
+   */
+  set listChars(value: string) {
+    if (typeof value !== "string") {
+      throw new Error(
+        `"Rsvim.opt.listChars" value must be string type, but found ${value} (${typeof value})`,
+      );
+    }
+    // @ts-ignore Ignore warning
+    __InternalRsvimGlobalObject.opt_set_list_chars(value);
+  }
+
+  /**
+   * Get the _showbreak_ option, the string put at the start of the wrapped rows.
+   *
+   * @see [Vim: options.txt - 'showbreak'](https://vimhelp.org/options.txt.html#%27showbreak%27)
+   *
+   * @example
+   * ```javascript
+   * // Get the 'showBreak' option.
+   * const value = Rsvim.opt.showBreak;
+   * // Set the 'showBreak' option.
+   * Rsvim.opt.showBreak = "> ";
+   * ```
+   *
+   * @returns {string}
+   * @defaultValue `""`
+   */
+  get showBreak(): string {
+    // @ts-ignore Ignore warning
+    return __InternalRsvimGlobalObject.opt_get_show_break();
+  }
+
+  /**
+   * Set the _showbreak_ option.
+   *
+   * @param {string} value - The _showbreak_ option.
+   * @throws {@link !Error} if value is not a string value.
+   */
+  set showBreak(value: string) {
+    if (typeof value !== "string") {
+      throw new Error(
+        `"Rsvim.opt.showBreak" value must be string type, but found ${value} (${typeof value})`,
+      );
+    }
+    // @ts-ignore Ignore warning
+    __InternalRsvimGlobalObject.opt_set_show_break(value);
+  }
+
+  /**
+   * Get the _scroll_ option.
+   *
+   * Local to Window.
+   *
+   * Number of rows to scroll with `Ctrl-D` and `Ctrl-U` commands. When `0`, it uses half of the
+   * window height.
+   *
+   * @see [Vim: options.txt - 'scroll'](https://vimhelp.org/options.txt.html#%27scroll%27)
+   *
+   * @example
+   * ```javascript
+   * // Get the 'scroll' option.
+   * const value = Rsvim.opt.scroll;
+   * // Set the 'scroll' option.
+   * Rsvim.opt.scroll = 10;
+   * ```
+   *
+   * @returns {number}
+   * @defaultValue `0`
+   */
+  get scroll(): number {
+    // @ts-ignore Ignore warning
+    return __InternalRsvimGlobalObject.opt_get_scroll();
+  }
+
+  /**
+   * Set the _scroll_ option.
+   *
+   * @param {number} value - The _scroll_ option.
+   * @throws {@link !Error} if value is not a non-negative integer value.
+   */
+  set scroll(value: number) {
+    if (typeof value !== "number" || !Number.isInteger(value) || value < 0) {
+      throw new Error(
+        `"Rsvim.opt.scroll" value must be non-negative integer type, but found ${value} (${typeof value})`,
+      );
+    }
+    // @ts-ignore Ignore warning
+    __InternalRsvimGlobalObject.opt_set_scroll(value);
+  }
+
+  /**
+   * Get the _scrolloff_ option.
+   *
+   * Local to Window.
+   *
+   * Minimal number of lines to keep above and below the cursor when scrolling, except at the
+   * beginning and end of the buffer.
+   *
+   * @see [Vim: options.txt - 'scrolloff'](https://vimhelp.org/options.txt.html#%27scrolloff%27)
+   *
+   * @example
+   * ```javascript
+   * // Get the 'scrollOff' option.
+   * const value = Rsvim.opt.scrollOff;
+   * // Set the 'scrollOff' option.
+   * Rsvim.opt.scrollOff = 5;
+   * ```
+   *
+   * @returns {number}
+   * @defaultValue `0`
+   */
+  get scrollOff(): number {
+    // @ts-ignore Ignore warning
+    return __InternalRsvimGlobalObject.opt_get_scroll_off();
+  }
+
+  /**
+   * Set the _scrolloff_ option.
+   *
+   * @param {number} value - The _scrolloff_ option.
+   * @throws {@link !Error} if value is not a non-negative integer value.
+   */
+  set scrollOff(value: number) {
+    if (typeof value !== "number" || !Number.isInteger(value) || value < 0) {
+      throw new Error(
+        `"Rsvim.opt.scrollOff" value must be non-negative integer type, but found ${value} (${typeof value})`,
+      );
+    }
+    // @ts-ignore Ignore warning
+    __InternalRsvimGlobalObject.opt_set_scroll_off(value);
+  }
+}
+
+/**
+ * The options of user-defined Ex command.
+ *
+ * @category Editor APIs
+ */
+export interface RsvimCmdOptions {
+  /**
+   * Whether to replace the existing command with the same name.
+   *
+   * @defaultValue `true`
+   */
+  force?: boolean;
+}
+
+/**
+ * The `Rsvim.cmd` for Ex commands, it is also a function to execute the Ex command.
+ *
+ * @example
+ * ```javascript
+ * // Create a variable alias to 'Rsvim.cmd'.
+ * const cmd = Rsvim.cmd;
+ * ```
+ *
+ * @category Editor APIs
+ */
+export interface RsvimCmd {
+  /**
+   * Execute the Ex command, same with typing `:{command}` in the command-line mode. The leading
+   * `:` is optional.
+   *
+   * The `:write` and user-defined commands are queued, and executed by the editor later. The
+   * confirmation (`c` flag) of `:substitute` is not supported.
+   *
+   * @see [Vim: various.txt - :execute](https://vimhelp.org/various.txt.html#%3Aexecute)
+   *
+   * @example
+   * ```javascript
+   * Rsvim.cmd("set tabstop=2");
+   * Rsvim.cmd("%s/foo/bar/g");
+   * ```
+   *
+   * @param {string} command - The Ex command line.
+   * @throws {@link !Error} if command is not a string value, or the command is unknown, or it
+   * fails. The error message is the same with the one shown in the message area.
+   */
+  (command: string): void;
+
+  /**
+   * Create a user-defined Ex command, it can be executed by typing `:{name} {args}` in the
+   * command-line mode.
+   *
+   * The name must start with an uppercase letter and only contains alphanumeric chars, like Vim's
+   * user commands. The remainder of the command line is passed to the callback as a string. The
+   * error thrown by the callback is shown in the message area.
+   *
+   * @see [Vim: map.txt - user-commands](https://vimhelp.org/map.txt.html#user-commands)
+   *
+   * @example
+   * ```javascript
+   * // Type `:Format` in the editor to run it.
+   * Rsvim.cmd.create("Format", (args) => {
+   *   // ...
+   * });
+   * ```
+   *
+   * @param {string} name - The command name.
+   * @param {(args: string) => void} callback - The callback runs when executing the command.
+   * @param {RsvimCmdOptions} [opts] - The command options.
+   * @throws {@link !Error} if name is not a string value or callback is not a function value, or
+   * the name is invalid, or the command already exists and `opts.force` is `false`.
+   */
+  create(
+    name: string,
+    callback: (args: string) => void,
+    opts?: RsvimCmdOptions,
+  ): void;
+}
+
+function createRsvimCmd(): RsvimCmd {
+  const cmd = function (command: string): void {
+    if (typeof command !== "string") {
+      throw new Error(
+        `"Rsvim.cmd" command must be string type, but found ${command} (${typeof command})`,
+      );
+    }
+    // @ts-ignore Ignore warning
+    __InternalRsvimGlobalObject.cmd_exec(command);
+  } as RsvimCmd;
+
+  cmd.create = function (
+    name: string,
+    callback: (args: string) => void,
+    opts?: RsvimCmdOptions,
+  ): void {
+    if (typeof name !== "string") {
+      throw new Error(
+        `"Rsvim.cmd.create" name must be string type, but found ${name} (${typeof name})`,
+      );
+    }
+    if (typeof callback !== "function") {
+      throw new Error(
+        `"Rsvim.cmd.create" callback must be function type, but found ${callback} (${typeof callback})`,
+      );
+    }
+    const force =
+      opts === undefined || opts.force === undefined ? true : opts.force;
+    if (typeof force !== "boolean") {
+      throw new Error(
+        `"Rsvim.cmd.create" opts.force must be boolean type, but found ${force} (${typeof force})`,
+      );
+    }
+    // @ts-ignore Ignore warning
+    __InternalRsvimGlobalObject.cmd_create(name, callback, force);
+  };
+
+  return cmd;
+}
+
+/**
+ * The highlight span of a buffer line, i.e. the style applied to the char range
+ * `[startChar, endChar)` of the line.
+ *
+ * The colors are the names (`"red"`, `"dark_grey"`, etc) or the RGB hex codes (`"#ff8000"`). The
+ * omitted colors are kept unchanged, so the overlapped spans can be combined.
+ *
+ * @category Editor APIs
+ */
+export interface RsvimHighlightSpan {
+  /** The start char index (inclusive) of the line. */
+  startChar: number;
+  /** The end char index (exclusive) of the line. */
+  endChar: number;
+  /** The foreground color. */
+  fg?: string;
+  /** The background color. */
+  bg?: string;
+  /** The attributes, i.e. `"bold"`, `"italic"`, `"underline"`, `"undercurl"`, `"strikethrough"`, `"reverse"`. */
+  attrs?: string[];
+}
+
+/**
+ * The `Rsvim.highlight` object for highlight spans of buffer lines.
+ *
+ * @example
+ * ```javascript
+ * // Create a variable alias to 'Rsvim.highlight'.
+ * const highlight = Rsvim.highlight;
+ * ```
+ *
+ * @category Editor APIs
+ * @hideconstructor
+ */
+export class RsvimHighlight {
+  /**
+   * Set the highlight spans of a buffer line, the existing spans of the line are replaced. The
+   * spans are applied in order, the later span wins if they set the same color.
+   *
+   * NOTE: The spans are not adjusted when the buffer text changes.
+   *
+   * @example
+   * ```javascript
+   * // Highlight the first 5 chars of the first line.
+   * Rsvim.highlight.set(bufId, 0, [{ startChar: 0, endChar: 5, fg: "red", attrs: ["bold"] }]);
+   * ```
+   *
+   * @param {number} bufId - The buffer ID.
+   * @param {number} line - The line index (0-based).
+   * @param {RsvimHighlightSpan[]} spans - The highlight spans.
+   * @throws {@link !Error} if bufId or line is not a number value, or spans is not an array, or
+   * the buffer doesn't exist, or any span is invalid.
+   */
+  set(bufId: number, line: number, spans: RsvimHighlightSpan[]): void {
+    if (typeof bufId !== "number") {
+      throw new Error(
+        `"Rsvim.highlight.set" bufId must be number type, but found ${bufId} (${typeof bufId})`,
+      );
+    }
+    if (typeof line !== "number") {
+      throw new Error(
+        `"Rsvim.highlight.set" line must be number type, but found ${line} (${typeof line})`,
+      );
+    }
+    if (!Array.isArray(spans)) {
+      throw new Error(
+        `"Rsvim.highlight.set" spans must be array type, but found ${spans} (${typeof spans})`,
+      );
+    }
+    // @ts-ignore Ignore warning
+    __InternalRsvimGlobalObject.highlight_set(bufId, line, spans);
+  }
+
+  /**
+   * Clear the highlight spans of the buffer lines in range `[startLine, endLine)`.
+   *
+   * @example
+   * ```javascript
+   * // Clear all the highlight spans.
+   * Rsvim.highlight.clear(bufId, 0, Number.MAX_SAFE_INTEGER);
+   * ```
+   *
+   * @param {number} bufId - The buffer ID.
+   * @param {number} startLine - The start line index (inclusive).
+   * @param {number} endLine - The end line index (exclusive).
+   * @throws {@link !Error} if any argument is not a number value, or the buffer doesn't exist.
+   */
+  clear(bufId: number, startLine: number, endLine: number): void {
+    if (typeof bufId !== "number") {
+      throw new Error(
+        `"Rsvim.highlight.clear" bufId must be number type, but found ${bufId} (${typeof bufId})`,
+      );
+    }
+    if (typeof startLine !== "number") {
+      throw new Error(
+        `"Rsvim.highlight.clear" startLine must be number type, but found ${startLine} (${typeof startLine})`,
+      );
+    }
+    if (typeof endLine !== "number") {
+      throw new Error(
+        `"Rsvim.highlight.clear" endLine must be number type, but found ${endLine} (${typeof endLine})`,
+      );
+    }
+    // @ts-ignore Ignore warning
+    __InternalRsvimGlobalObject.highlight_clear(bufId, startLine, endLine);
+  }
+}
+
+/**
+ * The `Rsvim.sign` object for the signs of buffer lines, i.e. the LSP diagnostics or the git
+ * changes. The signs are rendered in the 'signcolumn', a line has at most one sign of each group
+ * and the sign with the highest priority is displayed.
+ *
+ * @see [Vim: sign.txt](https://vimhelp.org/sign.txt.html)
+ *
+ * @example
+ * ```javascript
+ * // Create a variable alias to 'Rsvim.sign'.
+ * const sign = Rsvim.sign;
+ * ```
+ *
+ * @category Editor APIs
+ * @hideconstructor
+ */
+export class RsvimSign {
+  /**
+   * Place a sign on a buffer line, it replaces the sign of the same group on the line. For the
+   * same priority, the last placed sign is displayed.
+   *
+   * NOTE: The signs move with their lines when the lines above them are inserted or deleted, and
+   * the signs on the deleted lines are removed.
+   *
+   * @see [Vim: builtin.txt - sign_place()](https://vimhelp.org/builtin.txt.html#sign_place%28%29)
+   *
+   * @example
+   * ```javascript
+   * // Place an error sign on the first line, and tint the line.
+   * Rsvim.sign.place(bufId, 0, "lsp", "E", 20, "ErrorMsg");
+   * ```
+   *
+   * @param {number} bufId - The buffer ID.
+   * @param {number} line - The line index (0-based).
+   * @param {string} group - The sign group, i.e. the name of the plugin.
+   * @param {string} glyph - The sign text, only the first 2 cells are displayed.
+   * @param {number} priority - (Optional) The sign priority, by default is 10.
+   * @param {string} lineHl - (Optional) The highlight group to tint the whole line.
+   * @throws {@link !Error} if any argument is in wrong type, or the buffer or the line doesn't
+   * exist.
+   */
+  place(
+    bufId: number,
+    line: number,
+    group: string,
+    glyph: string,
+    priority?: number,
+    lineHl?: string,
+  ): void {
+    if (typeof bufId !== "number") {
+      throw new Error(
+        `"Rsvim.sign.place" bufId must be number type, but found ${bufId} (${typeof bufId})`,
+      );
+    }
+    if (typeof line !== "number") {
+      throw new Error(
+        `"Rsvim.sign.place" line must be number type, but found ${line} (${typeof line})`,
+      );
+    }
+    if (typeof group !== "string") {
+      throw new Error(
+        `"Rsvim.sign.place" group must be string type, but found ${group} (${typeof group})`,
+      );
+    }
+    if (typeof glyph !== "string") {
+      throw new Error(
+        `"Rsvim.sign.place" glyph must be string type, but found ${glyph} (${typeof glyph})`,
+      );
+    }
+    if (priority !== undefined && priority !== null && typeof priority !== "number") {
+      throw new Error(
+        `"Rsvim.sign.place" priority must be number type, but found ${priority} (${typeof priority})`,
+      );
+    }
+    if (lineHl !== undefined && lineHl !== null && typeof lineHl !== "string") {
+      throw new Error(
+        `"Rsvim.sign.place" lineHl must be string type, but found ${lineHl} (${typeof lineHl})`,
+      );
+    }
+    // @ts-ignore Ignore warning
+    __InternalRsvimGlobalObject.sign_place(bufId, line, group, glyph, priority, lineHl);
+  }
+
+  /**
+   * Remove the sign of the group on a buffer line.
+   *
+   * @see [Vim: builtin.txt - sign_unplace()](https://vimhelp.org/builtin.txt.html#sign_unplace%28%29)
+   *
+   * @example
+   * ```javascript
+   * Rsvim.sign.unplace(bufId, 0, "lsp");
+   * ```
+   *
+   * @param {number} bufId - The buffer ID.
+   * @param {number} line - The line index (0-based).
+   * @param {string} group - The sign group.
+   * @throws {@link !Error} if any argument is in wrong type, or the buffer doesn't exist.
+   */
+  unplace(bufId: number, line: number, group: string): void {
+    if (typeof bufId !== "number") {
+      throw new Error(
+        `"Rsvim.sign.unplace" bufId must be number type, but found ${bufId} (${typeof bufId})`,
+      );
+    }
+    if (typeof line !== "number") {
+      throw new Error(
+        `"Rsvim.sign.unplace" line must be number type, but found ${line} (${typeof line})`,
+      );
+    }
+    if (typeof group !== "string") {
+      throw new Error(
+        `"Rsvim.sign.unplace" group must be string type, but found ${group} (${typeof group})`,
+      );
+    }
+    // @ts-ignore Ignore warning
+    __InternalRsvimGlobalObject.sign_unplace(bufId, line, group);
+  }
+}
+
+/**
+ * The `Rsvim.buf` object for editing buffers, i.e. the plugins that generate contents.
+ *
+ * The line indexes are 0-based, and the empty line after the trailing line break is not a line.
+ * The windows that display the buffer are re-rendered after the edit.
+ *
+ * @example
+ * ```javascript
+ * // Create a variable alias to 'Rsvim.buf'.
+ * const buf = Rsvim.buf;
+ * ```
+ *
+ * @category Editor APIs
+ * @hideconstructor
+ */
+export class RsvimBuf {
+  /**
+   * Replace the text of a buffer line, excluding the trailing line break.
+   *
+   * @see [Vim: builtin.txt - setbufline()](https://vimhelp.org/builtin.txt.html#setbufline%28%29)
+   *
+   * @param {number} bufId - The buffer ID.
+   * @param {number} line - The line index (0-based).
+   * @param {string} text - The line text.
+   * @throws {@link !Error} if bufId or line is not a number value, or text is not a string value,
+   * or the buffer doesn't exist, or the line is out of range, or the buffer is not modifiable.
+   */
+  setLine(bufId: number, line: number, text: string): void {
+    if (typeof bufId !== "number") {
+      throw new Error(
+        `"Rsvim.buf.setLine" bufId must be number type, but found ${bufId} (${typeof bufId})`,
+      );
+    }
+    if (typeof line !== "number") {
+      throw new Error(
+        `"Rsvim.buf.setLine" line must be number type, but found ${line} (${typeof line})`,
+      );
+    }
+    if (typeof text !== "string") {
+      throw new Error(
+        `"Rsvim.buf.setLine" text must be string type, but found ${text} (${typeof text})`,
+      );
+    }
+    // @ts-ignore Ignore warning
+    __InternalRsvimGlobalObject.buf_set_line(bufId, line, text);
+  }
+
+  /**
+   * Insert the lines after the first `after` lines of a buffer, i.e. `0` inserts at the top.
+   *
+   * @see [Vim: builtin.txt - appendbufline()](https://vimhelp.org/builtin.txt.html#appendbufline%28%29)
+   *
+   * @example
+   * ```javascript
+   * // Append two lines at the top of the buffer.
+   * Rsvim.buf.appendLines(bufId, 0, ["Hello", "RSVIM!"]);
+   * ```
+   *
+   * @param {number} bufId - The buffer ID.
+   * @param {number} after - The lines count before the inserted lines.
+   * @param {string[]} lines - The lines, without the line breaks.
+   * @throws {@link !Error} if bufId or after is not a number value, or lines is not an array, or
+   * the buffer doesn't exist, or after is out of range, or the buffer is not modifiable.
+   */
+  appendLines(bufId: number, after: number, lines: string[]): void {
+    if (typeof bufId !== "number") {
+      throw new Error(
+        `"Rsvim.buf.appendLines" bufId must be number type, but found ${bufId} (${typeof bufId})`,
+      );
+    }
+    if (typeof after !== "number") {
+      throw new Error(
+        `"Rsvim.buf.appendLines" after must be number type, but found ${after} (${typeof after})`,
+      );
+    }
+    if (!Array.isArray(lines)) {
+      throw new Error(
+        `"Rsvim.buf.appendLines" lines must be array type, but found ${lines} (${typeof lines})`,
+      );
+    }
+    // @ts-ignore Ignore warning
+    __InternalRsvimGlobalObject.buf_append_lines(bufId, after, lines);
+  }
+
+  /**
+   * Delete the buffer lines in range `[startLine, endLine)`.
+   *
+   * @see [Vim: builtin.txt - deletebufline()](https://vimhelp.org/builtin.txt.html#deletebufline%28%29)
+   *
+   * @param {number} bufId - The buffer ID.
+   * @param {number} startLine - The start line index (inclusive).
+   * @param {number} endLine - The end line index (exclusive).
+   * @throws {@link !Error} if any argument is not a number value, or the buffer doesn't exist, or
+   * the range is out of range, or the buffer is not modifiable.
+   */
+  deleteLines(bufId: number, startLine: number, endLine: number): void {
+    if (typeof bufId !== "number") {
+      throw new Error(
+        `"Rsvim.buf.deleteLines" bufId must be number type, but found ${bufId} (${typeof bufId})`,
+      );
+    }
+    if (typeof startLine !== "number") {
+      throw new Error(
+        `"Rsvim.buf.deleteLines" startLine must be number type, but found ${startLine} (${typeof startLine})`,
+      );
+    }
+    if (typeof endLine !== "number") {
+      throw new Error(
+        `"Rsvim.buf.deleteLines" endLine must be number type, but found ${endLine} (${typeof endLine})`,
+      );
+    }
+    // @ts-ignore Ignore warning
+    __InternalRsvimGlobalObject.buf_delete_lines(bufId, startLine, endLine);
+  }
+}
+
+/**
+ * The style of a highlight group in the color scheme, same with the style of
+ * {@link RsvimHighlightSpan}. The colors are the names (`"red"`, `"dark_grey"`, etc) or the RGB hex
+ * codes (`"#ff8000"`).
+ *
+ * @category Editor APIs
+ */
+export interface RsvimColorSchemeGroup {
+  /** The foreground color. */
+  fg?: string;
+  /** The background color. */
+  bg?: string;
+  /** The attributes, i.e. `"bold"`, `"italic"`, `"underline"`, `"undercurl"`, `"strikethrough"`, `"reverse"`. */
+  attrs?: string[];
+}
+
+/**
+ * The `Rsvim.colorscheme` for color schemes, it is also a function to switch the color scheme.
+ *
+ * A color scheme maps the highlight groups (`Normal`, `Comment`, `Search`, `CursorLine`, etc) to
+ * their styles, the editor and the syntax tokens are rendered with the current color scheme.
+ *
+ * @example
+ * ```javascript
+ * // Create a variable alias to 'Rsvim.colorscheme'.
+ * const colorscheme = Rsvim.colorscheme;
+ * ```
+ *
+ * @category Editor APIs
+ */
+export interface RsvimColorScheme {
+  /**
+   * Switch to the color scheme, same with `:colorscheme {name}`. Without the name, it only returns
+   * the name of the current color scheme.
+   *
+   * @see [Vim: syntax.txt - :colorscheme](https://vimhelp.org/syntax.txt.html#%3Acolorscheme)
+   *
+   * @example
+   * ```javascript
+   * Rsvim.colorscheme("sunset");
+   * const name = Rsvim.colorscheme(); // "sunset"
+   * ```
+   *
+   * @param {string} [name] - The color scheme name.
+   * @returns {string} The name of the current color scheme.
+   * @throws {@link !Error} if name is not a string value, or the color scheme doesn't exist.
+   */
+  (name?: string): string;
+
+  /**
+   * Register a color scheme, the existing one with the same name is replaced. The group is either
+   * a style, or the name of another group it links to. The omitted groups are the same with the
+   * `"default"` color scheme.
+   *
+   * @example
+   * ```javascript
+   * Rsvim.colorscheme.register("sunset", {
+   *   Normal: { fg: "#e0e0e0", bg: "#202020" },
+   *   Search: { fg: "black", bg: "#ff8000" },
+   *   IncSearch: "Search",
+   * });
+   * ```
+   *
+   * @param {string} name - The color scheme name.
+   * @param {Record<string, RsvimColorSchemeGroup | string>} groups - The highlight groups.
+   * @throws {@link !Error} if name is not a string value or groups is not an object, or any group
+   * is invalid.
+   */
+  register(
+    name: string,
+    groups: Record<string, RsvimColorSchemeGroup | string>,
+  ): void;
+
+  /**
+   * Get the names of all the color schemes, in alphabetical order.
+   *
+   * @returns {string[]} The color scheme names.
+   */
+  list(): string[];
+}
+
+function createRsvimColorScheme(): RsvimColorScheme {
+  const colorscheme = function (name?: string): string {
+    if (name !== undefined) {
+      if (typeof name !== "string") {
+        throw new Error(
+          `"Rsvim.colorscheme" name must be string type, but found ${name} (${typeof name})`,
+        );
+      }
+      // @ts-ignore Ignore warning
+      __InternalRsvimGlobalObject.colorscheme_set(name);
+    }
+    // @ts-ignore Ignore warning
+    return __InternalRsvimGlobalObject.colorscheme_current();
+  } as RsvimColorScheme;
+
+  colorscheme.register = function (
+    name: string,
+    groups: Record<string, RsvimColorSchemeGroup | string>,
+  ): void {
+    if (typeof name !== "string") {
+      throw new Error(
+        `"Rsvim.colorscheme.register" name must be string type, but found ${name} (${typeof name})`,
+      );
+    }
+    if (typeof groups !== "object" || groups === null) {
+      throw new Error(
+        `"Rsvim.colorscheme.register" groups must be object type, but found ${groups} (${typeof groups})`,
+      );
+    }
+    // @ts-ignore Ignore warning
+    __InternalRsvimGlobalObject.colorscheme_register(name, groups);
+  };
+
+  colorscheme.list = function (): string[] {
+    // @ts-ignore Ignore warning
+    return __InternalRsvimGlobalObject.colorscheme_list();
+  };
+
+  return colorscheme;
+}
+
+/**
+ * The window and its shape on the terminal, i.e. the top-left position and the size.
+ *
+ * @category Editor APIs
+ */
+export interface RsvimWinInfo {
+  /** The window ID. */
+  id: number;
+  /** The shape of the window. */
+  shape: { x: number; y: number; width: number; height: number };
+}
+
+/**
+ * The viewport of a window, i.e. the buffer lines in range `[startLine, endLine)` are displayed
+ * in the window.
+ *
+ * @category Editor APIs
+ */
+export interface RsvimWinViewport {
+  /** The start line index (inclusive). */
+  startLine: number;
+  /** The end line index (exclusive). */
+  endLine: number;
+}
+
+/**
+ * The direction to split a window, `"horizontal"` is the top and bottom windows (i.e. `:split`),
+ * `"vertical"` is the left and right windows (i.e. `:vsplit`).
+ *
+ * @category Editor APIs
+ */
+export type RsvimWinSplitDirection = "horizontal" | "vertical";
+
+/**
+ * The `Rsvim.win` object for querying and manipulating windows.
+ *
+ * @example
+ * ```javascript
+ * // Create a variable alias to 'Rsvim.win'.
+ * const win = Rsvim.win;
+ * ```
+ *
+ * @category Editor APIs
+ * @hideconstructor
+ */
+export class RsvimWin {
+  /**
+   * Get all the windows and their shapes.
+   *
+   * @returns {RsvimWinInfo[]} The windows.
+   */
+  list(): RsvimWinInfo[] {
+    // @ts-ignore Ignore warning
+    return __InternalRsvimGlobalObject.win_list();
+  }
+
+  /**
+   * Get the current window ID, i.e. the window has the cursor.
+   *
+   * @returns {number | null} The window ID, or `null` if there's no window.
+   */
+  current(): number | null {
+    // @ts-ignore Ignore warning
+    return __InternalRsvimGlobalObject.win_current();
+  }
+
+  /**
+   * Set the buffer of a window, the window displays the buffer from its first line.
+   *
+   * @param {number} winId - The window ID.
+   * @param {number} bufId - The buffer ID.
+   * @throws {@link !Error} if winId or bufId is not a number value, or the window or the buffer
+   * doesn't exist.
+   */
+  setBuffer(winId: number, bufId: number): void {
+    if (typeof winId !== "number") {
+      throw new Error(
+        `"Rsvim.win.setBuffer" winId must be number type, but found ${winId} (${typeof winId})`,
+      );
+    }
+    if (typeof bufId !== "number") {
+      throw new Error(
+        `"Rsvim.win.setBuffer" bufId must be number type, but found ${bufId} (${typeof bufId})`,
+      );
+    }
+    // @ts-ignore Ignore warning
+    __InternalRsvimGlobalObject.win_set_buffer(winId, bufId);
+  }
+
+  /**
+   * Split a window into two windows, same with Vim the new window is on the top/left half. If the
+   * split window is the current window, the new window becomes the current window.
+   *
+   * @see [Vim: windows.txt - :split](https://vimhelp.org/windows.txt.html#%3Asplit)
+   *
+   * @example
+   * ```javascript
+   * const winId = Rsvim.win.split(Rsvim.win.current(), "vertical");
+   * ```
+   *
+   * @param {number} winId - The window ID.
+   * @param {RsvimWinSplitDirection} direction - The split direction.
+   * @returns {number} The new window ID.
+   * @throws {@link !Error} if winId is not a number value, or direction is invalid, or the window
+   * doesn't exist, or it's too small to split.
+   */
+  split(winId: number, direction: RsvimWinSplitDirection): number {
+    if (typeof winId !== "number") {
+      throw new Error(
+        `"Rsvim.win.split" winId must be number type, but found ${winId} (${typeof winId})`,
+      );
+    }
+    if (direction !== "horizontal" && direction !== "vertical") {
+      throw new Error(
+        `"Rsvim.win.split" direction must be "horizontal" or "vertical", but found ${direction} (${typeof direction})`,
+      );
+    }
+    // @ts-ignore Ignore warning
+    return __InternalRsvimGlobalObject.win_split(winId, direction);
+  }
+
+  /**
+   * Close a window, its space is given to the adjacent window.
+   *
+   * @see [Vim: windows.txt - :close](https://vimhelp.org/windows.txt.html#%3Aclose)
+   *
+   * @param {number} winId - The window ID.
+   * @throws {@link !Error} if winId is not a number value, or the window doesn't exist, or it's
+   * the last window.
+   */
+  close(winId: number): void {
+    if (typeof winId !== "number") {
+      throw new Error(
+        `"Rsvim.win.close" winId must be number type, but found ${winId} (${typeof winId})`,
+      );
+    }
+    // @ts-ignore Ignore warning
+    __InternalRsvimGlobalObject.win_close(winId);
+  }
+
+  /**
+   * Get the viewport of a window, i.e. the buffer lines displayed in it.
+   *
+   * @param {number} winId - The window ID.
+   * @returns {RsvimWinViewport} The viewport.
+   * @throws {@link !Error} if winId is not a number value, or the window doesn't exist.
+   */
+  getViewport(winId: number): RsvimWinViewport {
+    if (typeof winId !== "number") {
+      throw new Error(
+        `"Rsvim.win.getViewport" winId must be number type, but found ${winId} (${typeof winId})`,
+      );
+    }
+    // @ts-ignore Ignore warning
+    return __InternalRsvimGlobalObject.win_get_viewport(winId);
+  }
 }
 
 (function (globalThis: { Rsvim: Rsvim }) {
@@ -6,10 +6,13 @@ pub mod cli;
 pub mod defaults;
 pub mod envar;
 pub mod evloop;
+pub mod headless;
 pub mod js;
 pub mod locks;
 pub mod log;
+pub mod prelude;
 pub mod res;
 pub mod state;
+#[cfg(test)]
 pub mod test;
 pub mod ui;
@@ -1,55 +1,225 @@
 //! Logging utils.
+//!
+//! Since the terminal is used by the TUI, logs are always written into a log file, with
+//! size-based rotation. The log level can be adjusted at runtime with [`set_level`].
 
-use jiff::Zoned;
+use crate::cli::CliOpt;
+use crate::envar;
+use crate::res::AnyResult;
+
+use parking_lot::Mutex;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock};
 use tracing;
-use tracing_appender;
-use tracing_subscriber::{self, EnvFilter};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::{self, reload, EnvFilter, Registry};
+
+/// Default log level if neither `--log-level` nor `RUST_LOG` is provided.
+pub const DEFAULT_LOG_LEVEL: &str = "info";
+
+/// Max size of a log file before rotation, by default is 10MB.
+pub const LOG_FILE_MAX_SIZE: u64 = 10 * 1024 * 1024;
+
+/// Max count of old (rotated) log files to keep.
+pub const LOG_FILE_MAX_BACKUPS: usize = 5;
+
+/// Reload handle for the log level filter.
+pub type LogLevelHandle = reload::Handle<EnvFilter, Registry>;
+
+static LOG_LEVEL_HANDLE: OnceLock<LogLevelHandle> = OnceLock::new();
+
+#[derive(Debug)]
+struct RotatingFile {
+  path: PathBuf,
+  max_size: u64,
+  max_backups: usize,
+  file: File,
+  size: u64,
+}
+
+impl RotatingFile {
+  fn open(path: &Path) -> std::io::Result<File> {
+    if let Some(parent) = path.parent() {
+      std::fs::create_dir_all(parent)?;
+    }
+    OpenOptions::new().create(true).append(true).open(path)
+  }
+
+  fn backup_path(&self, index: usize) -> PathBuf {
+    let mut backup = self.path.clone().into_os_string();
+    backup.push(format!(".{}", index));
+    PathBuf::from(backup)
+  }
+
+  // Rotate `file.log` => `file.log.1`, `file.log.1` => `file.log.2`, etc.
+  fn rotate(&mut self) -> std::io::Result<()> {
+    self.file.flush()?;
+    if self.max_backups > 0 {
+      let _ = std::fs::remove_file(self.backup_path(self.max_backups));
+      for i in (1..self.max_backups).rev() {
+        let from = self.backup_path(i);
+        if from.exists() {
+          std::fs::rename(from, self.backup_path(i + 1))?;
+        }
+      }
+      std::fs::rename(&self.path, self.backup_path(1))?;
+    } else {
+      std::fs::remove_file(&self.path)?;
+    }
+    self.file = Self::open(&self.path)?;
+    self.size = 0;
+    Ok(())
+  }
+}
+
+#[derive(Debug, Clone)]
+/// The log file writer with size-based rotation.
+///
+/// When the log file size exceeds the `max_size`, it is renamed to `{file}.1` (and the older ones
+/// are shifted to `{file}.2`, `{file}.3`, etc.), at most `max_backups` old files are kept.
+pub struct RotatingFileWriter {
+  inner: Arc<Mutex<RotatingFile>>,
+}
+
+impl RotatingFileWriter {
+  pub fn new(path: &Path, max_size: u64, max_backups: usize) -> std::io::Result<Self> {
+    let file = RotatingFile::open(path)?;
+    let size = file.metadata()?.len();
+    Ok(RotatingFileWriter {
+      inner: Arc::new(Mutex::new(RotatingFile {
+        path: path.to_path_buf(),
+        max_size,
+        max_backups,
+        file,
+        size,
+      })),
+    })
+  }
+}
+
+impl Write for RotatingFileWriter {
+  fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+    let mut inner = self.inner.lock();
+    if inner.size > 0 && inner.size + buf.len() as u64 > inner.max_size {
+      inner.rotate()?;
+    }
+    let n = inner.file.write(buf)?;
+    inner.size += n as u64;
+    Ok(n)
+  }
+
+  fn flush(&mut self) -> std::io::Result<()> {
+    self.inner.lock().file.flush()
+  }
+}
+
+/// Default log file name.
+pub const DEFAULT_LOG_FILE_NAME: &str = "rsvim.log";
+
+/// Default log file path, i.e. `$XDG_CACHE_HOME/rsvim/rsvim.log`. The name is fixed so each run
+/// appends to the same file, and the rotation caps the disk usage.
+pub fn default_log_file() -> PathBuf {
+  envar::CACHE_DIR_PATH().join(DEFAULT_LOG_FILE_NAME)
+}
+
+fn make_env_filter(level: &str) -> AnyResult<EnvFilter> {
+  Ok(EnvFilter::try_new(level)?)
+}
+
+/// Make the log subscriber, returns the subscriber and the reload handle of its level filter.
+fn make_subscriber(
+  env_filter: EnvFilter,
+  writer: RotatingFileWriter,
+) -> (impl tracing::Subscriber + Send + Sync, LogLevelHandle) {
+  let (filter_layer, handle) = reload::Layer::new(env_filter);
+  let fmt_layer = tracing_subscriber::fmt::layer()
+    .with_file(true)
+    .with_line_number(true)
+    .with_thread_ids(true)
+    .with_thread_names(true)
+    .with_level(true)
+    .with_ansi(false)
+    .with_writer(move || writer.clone());
+  let subscriber = tracing_subscriber::registry()
+    .with(filter_layer)
+    .with(fmt_layer);
+  (subscriber, handle)
+}
+
+fn _set_level(handle: &LogLevelHandle, level: &str) -> AnyResult<()> {
+  let env_filter = make_env_filter(level)?;
+  handle.reload(env_filter)?;
+  Ok(())
+}
 
 /// Initialize logging.
 ///
-/// It uses `RUST_LOG` environment variable to control the logging level.
-/// Defaults to `INFO`.
-pub fn init() {
-  let env_filter = EnvFilter::from_default_env();
-
-  if env_filter.max_level_hint().is_some()
-    && env_filter.max_level_hint().unwrap() >= tracing::Level::TRACE
-  {
-    // If trace/debug log is enabled, write logs into file.
-    let now = Zoned::now();
-    let log_name = format!(
-      "rsvim-{:0>4}{:0>2}{:0>2}-{:0>2}{:0>2}{:0>2}-{:0>3}.log",
-      now.date().year(),
-      now.date().month(),
-      now.date().day(),
-      now.time().hour(),
-      now.time().minute(),
-      now.time().second(),
-      now.time().millisecond(),
-    );
-    let subscriber = tracing_subscriber::FmtSubscriber::builder()
-      .with_file(true)
-      .with_line_number(true)
-      .with_thread_ids(true)
-      .with_thread_names(true)
-      .with_level(true)
-      .with_ansi(false)
-      .with_env_filter(env_filter)
-      .with_writer(tracing_appender::rolling::never(".", log_name))
-      .finish();
-    tracing::subscriber::set_global_default(subscriber).unwrap();
-  } else {
-    // If trace/debug log is disabled, write logs into stderr.
-    let subscriber = tracing_subscriber::FmtSubscriber::builder()
-      .with_file(true)
-      .with_line_number(true)
-      .with_thread_ids(true)
-      .with_thread_names(true)
-      .with_level(true)
-      .with_ansi(false)
-      .with_env_filter(env_filter)
-      .with_writer(std::io::stderr)
-      .finish();
-    tracing::subscriber::set_global_default(subscriber).unwrap();
+/// The log level is controlled by (with priority from high to low):
+/// 1. The `--log-level` command line option.
+/// 2. The `RUST_LOG` environment variable.
+/// 3. Defaults to [`DEFAULT_LOG_LEVEL`].
+///
+/// The logs are written into the `--log-file` command line option, or the [`default_log_file`] if
+/// it is not provided.
+pub fn init(cli_opt: &CliOpt) -> AnyResult<()> {
+  let env_filter = match cli_opt.log_level() {
+    Some(level) => make_env_filter(level)?,
+    None => EnvFilter::try_from_default_env().or_else(|_| make_env_filter(DEFAULT_LOG_LEVEL))?,
+  };
+  let log_file = match cli_opt.log_file() {
+    Some(log_file) => PathBuf::from(log_file),
+    None => default_log_file(),
+  };
+  let writer = RotatingFileWriter::new(&log_file, LOG_FILE_MAX_SIZE, LOG_FILE_MAX_BACKUPS)?;
+
+  let (subscriber, handle) = make_subscriber(env_filter, writer);
+  tracing::subscriber::set_global_default(subscriber)?;
+  let _ = LOG_LEVEL_HANDLE.set(handle);
+  Ok(())
+}
+
+/// Set the log level at runtime, the `level` uses the same syntax with `RUST_LOG`, e.g. `debug` or
+/// `rsvim_core=trace`.
+///
+/// # Errors
+///
+/// If the logging is not initialized, or the `level` is invalid.
+pub fn set_level(level: &str) -> AnyResult<()> {
+  match LOG_LEVEL_HANDLE.get() {
+    Some(handle) => _set_level(handle, level),
+    None => anyhow::bail!("Logging is not initialized"),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn default_log_file1() {
+    let log_file = default_log_file();
+    assert_eq!(log_file, default_log_file());
+    assert!(log_file.ends_with(DEFAULT_LOG_FILE_NAME));
+  }
+
+  #[test]
+  fn rotate1() {
+    let tmpdir = assert_fs::TempDir::new().unwrap();
+    let log_file = tmpdir.path().join("rotate1.log");
+    let mut writer = RotatingFileWriter::new(&log_file, 10, 2).unwrap();
+    for i in 0..5 {
+      writer
+        .write_all(format!("line-{}-abc\n", i).as_bytes())
+        .unwrap();
+    }
+    writer.flush().unwrap();
+
+    let backup = |i: usize| PathBuf::from(format!("{}.{}", log_file.display(), i));
+    assert_eq!(std::fs::read_to_string(&log_file).unwrap(), "line-4-abc\n");
+    assert_eq!(std::fs::read_to_string(backup(1)).unwrap(), "line-3-abc\n");
+    assert_eq!(std::fs::read_to_string(backup(2)).unwrap(), "line-2-abc\n");
+    assert!(!backup(3).exists());
   }
 }
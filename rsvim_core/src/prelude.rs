@@ -0,0 +1,14 @@
+//! The prelude for embedding the editor, i.e. `use rsvim_core::prelude::*`.
+//!
+//! See [`headless`](crate::headless).
+
+pub use crate::buf::{Buffer, BufferArc, BufferId, BuffersManager, BuffersManagerArc};
+pub use crate::cart::{U16Pos, U16Size};
+pub use crate::headless::{Editor, EditorBuilder};
+pub use crate::res::IoResult;
+pub use crate::state::{State, StateArc};
+pub use crate::ui::canvas::{Cell, Frame};
+pub use crate::ui::tree::{Tree, TreeArc, TreeNode, TreeNodeId};
+pub use crate::ui::widget::Window;
+
+pub use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
@@ -1,5 +1,8 @@
 //! Results and errors.
 
+use crate::buf::BufferId;
+use crate::ui::tree::internal::InodeId;
+
 use thiserror::Error as ThisError;
 
 // anyhow {
@@ -41,17 +44,58 @@ pub type JsRuntimeResult<T> = std::result::Result<T, JsRuntimeErr>;
 
 // Buffer {
 
-// #[derive(Debug, ThisError)]
-// /// Vim buffer error code implemented by [`thiserror::Error`].
-// pub enum BufferErr {
-//   #[error("File path already exists: {0}")]
-//   FilePathAlreadyExists(PathBuf),
-//
-//   #[error("Io error: {0}")]
-//   IoErr(IoErr),
-// }
-//
-// /// [`std::result::Result`] with `T` if ok, [`TheBufferErr`] if error.
-// pub type BufferResult<T> = std::result::Result<T, BufferErr>;
+#[derive(Debug, ThisError)]
+/// Vim buffer error code implemented by [`thiserror::Error`].
+pub enum BufferErr {
+  #[error("E21: Cannot make changes, 'modifiable' is off")]
+  NotModifiable,
+
+  #[error("E45: 'readonly' option is set")]
+  ReadOnly,
+
+  #[error("E32: No file name")]
+  NoFileName,
+
+  #[error("E382: Cannot write, 'buftype' option is set")]
+  NotWritable,
+
+  #[error("E502: {0:?} is a directory")]
+  IsDirectory(std::path::PathBuf),
+
+  #[error("WARNING: The file {0:?} has been changed since reading it, add ! to override")]
+  ChangedExternally(std::path::PathBuf),
+
+  #[error("E966: Invalid line number: {0}")]
+  InvalidLine(usize),
+
+  #[error("E964: Invalid column number: {0}")]
+  InvalidColumn(usize),
+
+  #[error("E86: Buffer {0} does not exist")]
+  NotExist(BufferId),
+
+  #[error("E305: No swap file found for {0:?}")]
+  NoJournal(std::path::PathBuf),
+
+  #[error("Io error: {0}")]
+  IoErr(#[from] IoErr),
+}
+
+/// [`std::result::Result`] with `T` if ok, [`BufferErr`] if error.
+pub type BufferResult<T> = std::result::Result<T, BufferErr>;
 
 // Buffer }
+
+// Tree {
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ThisError)]
+/// UI tree error code implemented by [`thiserror::Error`].
+pub enum TreeErr {
+  #[error("Tree node {0} does not exist")]
+  NodeNotExist(InodeId),
+}
+
+/// [`std::result::Result`] with `T` if ok, [`TreeErr`] if error.
+pub type TreeResult<T> = std::result::Result<T, TreeErr>;
+
+// Tree }
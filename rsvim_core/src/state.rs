@@ -1,18 +1,50 @@
 //! Vim editing mode.
 
-use crossterm::event::Event;
+use crossterm::event::{Event, KeyCode};
 use parking_lot::RwLock;
 use std::sync::{Arc, Weak};
 use tracing::trace;
 
 use crate::buf::BuffersManagerArc;
+use crate::state::autocmd::AutocmdContext;
+use crate::state::change::ChangeRecorder;
+use crate::state::clipboard::ClipboardPut;
+use crate::state::command::{CommandLineKind, ExCommandLine, ExCommands};
+use crate::state::fsm::operator_pending::Operator;
+use crate::state::fsm::visual::current_buffer;
 use crate::state::fsm::{Stateful, StatefulDataAccess, StatefulValue};
+use crate::state::history::CmdHistories;
+use crate::state::jumplist::{Jump, JumpList};
+use crate::state::message::Message;
 use crate::state::mode::Mode;
-use crate::ui::tree::TreeArc;
+use crate::state::profile::RenderProfile;
+use crate::state::quit::QuitRequest;
+use crate::state::recording::MacroRecorder;
+use crate::state::register::Registers;
+use crate::state::search::Search;
+use crate::state::selection::{BufferPosition, Selection};
+use crate::state::substitute::SubstituteConfirm;
+use crate::ui::tree::{Tree, TreeArc};
+use crate::{envar, rlock, wlock};
 
+pub mod autocmd;
+pub mod change;
+pub mod clipboard;
 pub mod command;
 pub mod fsm;
+pub mod history;
+pub mod jumplist;
+pub mod message;
 pub mod mode;
+pub mod motion;
+pub mod profile;
+pub mod quit;
+pub mod recording;
+pub mod register;
+pub mod search;
+pub mod selection;
+pub mod set;
+pub mod substitute;
 
 #[derive(Debug, Clone)]
 pub struct State {
@@ -21,6 +53,77 @@ pub struct State {
 
   // Editing mode.
   mode: Mode,
+
+  // Visual mode selection.
+  selection: Option<Selection>,
+
+  // Registers for yank/delete/put.
+  registers: Registers,
+
+  // The register name for the next yank/delete/put, i.e. `"{name}`.
+  register_name: Option<char>,
+
+  // Whether the `"` is pressed and waiting for the register name.
+  register_name_pending: bool,
+
+  // The pending prefix key in normal mode, i.e. the `z` of `zt`/`zz`/`zb`.
+  pending_key: Option<char>,
+
+  // The count typed before a command or motion, i.e. the `2` of `2dd`.
+  count: Option<usize>,
+
+  // The operator waiting for a motion in operator-pending mode, and the count typed before it.
+  pending_operator: Option<(Operator, Option<usize>)>,
+
+  // The chars overwritten in replace mode, restored by `Backspace`. The `None` is a char appended
+  // after the end of line, or a line break.
+  replaced_chars: Vec<Option<char>>,
+
+  // The last change for repeating, i.e. `.`.
+  change_recorder: ChangeRecorder,
+
+  // The macro recording and the register executions, i.e. `q{reg}` and `@{reg}`.
+  macro_recorder: MacroRecorder,
+
+  // Ex commands registry.
+  ex_commands: ExCommands,
+
+  // The command line content typed in command-line mode, without the leading `:` or `/`.
+  command_line: String,
+
+  // The kind of the command line, i.e. Ex command or search pattern.
+  command_line_kind: CommandLineKind,
+
+  // The histories of the Ex commands and the search patterns, walked by `Up`/`Down`.
+  cmd_histories: CmdHistories,
+
+  // The search pattern and the incremental search state.
+  search: Search,
+
+  // The timings of the last rendered frames, i.e. `--profile`.
+  render_profile: RenderProfile,
+
+  // The cursor positions before the jumps, walked by `Ctrl-O`/`Ctrl-I`.
+  jump_list: JumpList,
+
+  // The substitute command waiting for confirming each match, i.e. the `c` flag.
+  substitute_confirm: Option<SubstituteConfirm>,
+
+  // The Ex commands waiting to be dispatched by the event loop, i.e. the user-defined commands and
+  // `:write`.
+  pending_ex_commands: Vec<ExCommandLine>,
+
+  // The autocmd events waiting to be dispatched to js runtime.
+  pending_autocmds: Vec<AutocmdContext>,
+
+  // The puts from the register `+`/`*` waiting for the event loop to read the clipboard.
+  pending_clipboard_puts: Vec<ClipboardPut>,
+
+  // The quit request waiting to be decided by the event loop, since it checks the modified buffers.
+  quit_request: Option<QuitRequest>,
+
+  // The message shown in the message area, it is cleared on the next key.
+  message: Option<Message>,
 }
 
 #[derive(Debug, Copy, Clone)]
@@ -47,6 +150,29 @@ impl State {
       stateful: StatefulValue::default(),
       last_stateful: StatefulValue::default(),
       mode: Mode::Normal,
+      selection: None,
+      registers: Registers::new(),
+      register_name: None,
+      register_name_pending: false,
+      pending_key: None,
+      count: None,
+      pending_operator: None,
+      replaced_chars: vec![],
+      change_recorder: ChangeRecorder::new(),
+      macro_recorder: MacroRecorder::new(),
+      ex_commands: ExCommands::new(),
+      command_line: String::new(),
+      command_line_kind: CommandLineKind::Ex,
+      cmd_histories: CmdHistories::new(rlock!(envar::GLOBAL_CONFIG()).history_size()),
+      search: Search::new(),
+      render_profile: RenderProfile::default(),
+      jump_list: JumpList::new(),
+      substitute_confirm: None,
+      pending_ex_commands: vec![],
+      pending_autocmds: vec![],
+      pending_clipboard_puts: vec![],
+      quit_request: None,
+      message: None,
     }
   }
 
@@ -70,20 +196,17 @@ impl State {
     event: Event,
   ) -> StateHandleResponse {
     // Update current mode.
-    let state_mode = match self.stateful {
-      StatefulValue::NormalMode(_) => Some(Mode::Normal),
-      StatefulValue::VisualMode(_) => Some(Mode::Visual),
-      StatefulValue::SelectMode(_) => Some(Mode::Select),
-      StatefulValue::OperatorPendingMode(_) => Some(Mode::OperatorPending),
-      StatefulValue::InsertMode(_) => Some(Mode::Insert),
-      StatefulValue::CommandLineMode(_) => Some(Mode::CommandLine),
-      StatefulValue::TerminalMode(_) => Some(Mode::Terminal),
-      _ => None,
-    };
-    if let Some(mode) = state_mode {
-      self.mode = mode;
+    self.mode = Self::stateful_mode(self.stateful);
+
+    // The message is cleared on the next key, and the key is recorded if it's recording a macro.
+    if let Event::Key(key_event) = event {
+      self.clear_message();
+      self.macro_recorder.record_key(key_event);
     }
 
+    // The jumps follow the edits since last event, i.e. made by js runtime.
+    self.sync_jump_list(&tree, &buffers);
+
     // Current stateful
     let stateful = self.stateful;
 
@@ -93,13 +216,336 @@ impl State {
 
     // Save current stateful
     self.last_stateful = stateful;
-    // Set next stateful
+    // Set next stateful, and the mode follows it, i.e. the cursor style is rendered with it.
     self.stateful = next_stateful;
+    self.mode = Self::stateful_mode(next_stateful);
+
+    // Fire `ModeChanged` event.
+    let (old_mode, new_mode) = (
+      Self::stateful_mode(stateful),
+      Self::stateful_mode(next_stateful),
+    );
+    if old_mode != new_mode {
+      self.push_pending_autocmd(AutocmdContext::mode_changed(old_mode, new_mode));
+    }
+
+    // The register `+`/`*` is used without the system clipboard.
+    if let Some(warning) = self.registers.take_clipboard_warning() {
+      self.echoerr(warning);
+    }
 
     StateHandleResponse::new(stateful, next_stateful)
   }
 
+  fn stateful_mode(stateful: StatefulValue) -> Mode {
+    match stateful {
+      StatefulValue::NormalMode(_) => Mode::Normal,
+      StatefulValue::VisualMode(_) => Mode::Visual,
+      StatefulValue::SelectMode(_) => Mode::Select,
+      StatefulValue::OperatorPendingMode(_) => Mode::OperatorPending,
+      StatefulValue::InsertMode(_) => Mode::Insert,
+      StatefulValue::ReplaceMode(_) => Mode::Replace,
+      StatefulValue::CommandLineMode(_) => Mode::CommandLine,
+      StatefulValue::TerminalMode(_) => Mode::Terminal,
+    }
+  }
+
   pub fn mode(&self) -> Mode {
     self.mode
   }
+
+  /// Get current FSM state.
+  pub fn stateful(&self) -> StatefulValue {
+    self.stateful
+  }
+
+  /// Get the visual mode selection, it only exists in visual mode.
+  pub fn selection(&self) -> &Option<Selection> {
+    &self.selection
+  }
+
+  pub fn set_selection(&mut self, selection: Option<Selection>) {
+    self.selection = selection;
+  }
+
+  pub fn registers(&self) -> &Registers {
+    &self.registers
+  }
+
+  pub fn registers_mut(&mut self) -> &mut Registers {
+    &mut self.registers
+  }
+
+  /// Handle the `"{name}` prefix that selects the register for the next yank/delete/put.
+  ///
+  /// # Returns
+  ///
+  /// It returns `true` if the `key_code` is consumed as part of the prefix.
+  pub fn handle_register_name(&mut self, key_code: KeyCode) -> bool {
+    if self.register_name_pending {
+      self.register_name_pending = false;
+      if let KeyCode::Char(name) = key_code {
+        if Registers::is_valid_name(name) {
+          self.register_name = Some(name);
+        }
+      }
+      return true;
+    }
+    if key_code == KeyCode::Char('"') {
+      self.register_name_pending = true;
+      return true;
+    }
+    false
+  }
+
+  /// Take the register name selected by the `"{name}` prefix, it only applies to the next
+  /// yank/delete/put.
+  pub fn take_register_name(&mut self) -> Option<char> {
+    self.register_name.take()
+  }
+
+  /// Get the register name selected by the `"{name}` prefix, without taking it.
+  pub fn register_name(&self) -> Option<char> {
+    self.register_name
+  }
+
+  pub fn set_register_name(&mut self, name: Option<char>) {
+    self.register_name = name;
+  }
+
+  /// Take the pending prefix key in normal mode, i.e. the `z` of `zt`/`zz`/`zb`.
+  pub fn take_pending_key(&mut self) -> Option<char> {
+    self.pending_key.take()
+  }
+
+  pub fn set_pending_key(&mut self, key: Option<char>) {
+    self.pending_key = key;
+  }
+
+  /// Save the char overwritten in replace mode, `None` if nothing is overwritten, i.e. the char is
+  /// appended after the end of line.
+  pub fn push_replaced_char(&mut self, c: Option<char>) {
+    self.replaced_chars.push(c);
+  }
+
+  /// Take the last char overwritten in replace mode, it returns `None` if no char is typed since
+  /// entering replace mode.
+  pub fn pop_replaced_char(&mut self) -> Option<Option<char>> {
+    self.replaced_chars.pop()
+  }
+
+  /// Forget the chars overwritten in replace mode, i.e. when leaving it.
+  pub fn clear_replaced_chars(&mut self) {
+    self.replaced_chars.clear();
+  }
+
+  /// Handle the count typed before a command or motion, i.e. the `2` of `2dd`. The `0` is part of
+  /// the count only if it's not the first digit.
+  ///
+  /// # Returns
+  ///
+  /// It returns `true` if the `key_code` is consumed as part of the count.
+  pub fn handle_count(&mut self, key_code: KeyCode) -> bool {
+    match key_code {
+      KeyCode::Char(c @ '0'..='9') if c != '0' || self.count.is_some() => {
+        let digit = c.to_digit(10).unwrap() as usize;
+        let count = self.count.unwrap_or(0);
+        self.count = Some(count.saturating_mul(10).saturating_add(digit));
+        true
+      }
+      _ => false,
+    }
+  }
+
+  /// Take the typed count, it only applies to the next command or motion.
+  pub fn take_count(&mut self) -> Option<usize> {
+    self.count.take()
+  }
+
+  /// Set the count back, i.e. keep it for the command after a prefix key, the `2` of `2gUw`.
+  pub fn set_count(&mut self, count: Option<usize>) {
+    self.count = count;
+  }
+
+  /// Take the operator waiting for a motion, and the count typed before it.
+  pub fn take_pending_operator(&mut self) -> Option<(Operator, Option<usize>)> {
+    self.pending_operator.take()
+  }
+
+  pub fn set_pending_operator(&mut self, operator: Option<(Operator, Option<usize>)>) {
+    self.pending_operator = operator;
+  }
+
+  pub fn change_recorder(&self) -> &ChangeRecorder {
+    &self.change_recorder
+  }
+
+  pub fn change_recorder_mut(&mut self) -> &mut ChangeRecorder {
+    &mut self.change_recorder
+  }
+
+  pub fn macro_recorder(&self) -> &MacroRecorder {
+    &self.macro_recorder
+  }
+
+  pub fn macro_recorder_mut(&mut self) -> &mut MacroRecorder {
+    &mut self.macro_recorder
+  }
+
+  pub fn ex_commands(&self) -> &ExCommands {
+    &self.ex_commands
+  }
+
+  pub fn ex_commands_mut(&mut self) -> &mut ExCommands {
+    &mut self.ex_commands
+  }
+
+  /// Get the command line content typed in command-line mode, without the leading `:` or `/`.
+  pub fn command_line(&self) -> &String {
+    &self.command_line
+  }
+
+  pub fn command_line_mut(&mut self) -> &mut String {
+    &mut self.command_line
+  }
+
+  /// Get the kind of the command line.
+  pub fn command_line_kind(&self) -> CommandLineKind {
+    self.command_line_kind
+  }
+
+  pub fn set_command_line_kind(&mut self, kind: CommandLineKind) {
+    self.command_line_kind = kind;
+  }
+
+  pub fn cmd_histories(&self) -> &CmdHistories {
+    &self.cmd_histories
+  }
+
+  pub fn cmd_histories_mut(&mut self) -> &mut CmdHistories {
+    &mut self.cmd_histories
+  }
+
+  pub fn render_profile(&self) -> &RenderProfile {
+    &self.render_profile
+  }
+
+  pub fn render_profile_mut(&mut self) -> &mut RenderProfile {
+    &mut self.render_profile
+  }
+
+  pub fn search(&self) -> &Search {
+    &self.search
+  }
+
+  pub fn search_mut(&mut self) -> &mut Search {
+    &mut self.search
+  }
+
+  /// The jump list of the cursor positions before the jumps, i.e. `Ctrl-O`/`Ctrl-I`.
+  pub fn jump_list(&self) -> &JumpList {
+    &self.jump_list
+  }
+
+  pub fn jump_list_mut(&mut self) -> &mut JumpList {
+    &mut self.jump_list
+  }
+
+  /// Record the `cursor` in the buffer of current window to the jump list, before a jump.
+  pub fn push_jump(&mut self, tree: &Tree, cursor: BufferPosition) {
+    if let Some(buffer) = current_buffer(tree) {
+      let mut buffer = wlock!(buffer);
+      let line_changes = buffer.take_line_changes();
+      self.jump_list.adjust(buffer.id(), &line_changes);
+      self.jump_list.push(Jump::new(buffer.id(), cursor));
+    }
+  }
+
+  // Adjust the jumps by the lines changed in all the buffers.
+  fn sync_jump_list(&mut self, tree: &TreeArc, buffers: &BuffersManagerArc) {
+    let mut buffers = rlock!(buffers).values().cloned().collect::<Vec<_>>();
+    buffers.extend(current_buffer(&rlock!(tree)));
+    for buffer in buffers {
+      let mut buffer = wlock!(buffer);
+      let line_changes = buffer.take_line_changes();
+      if !line_changes.is_empty() {
+        self.jump_list.adjust(buffer.id(), &line_changes);
+      }
+    }
+  }
+
+  /// Take the substitute command waiting for confirming the current match.
+  pub fn take_substitute_confirm(&mut self) -> Option<SubstituteConfirm> {
+    self.substitute_confirm.take()
+  }
+
+  pub fn set_substitute_confirm(&mut self, confirm: Option<SubstituteConfirm>) {
+    self.substitute_confirm = confirm;
+  }
+
+  /// Queue an Ex command that is dispatched by the event loop, i.e. the user-defined command since
+  /// the callback lives in js runtime, and `:write` since it fires the autocmds.
+  pub fn push_pending_ex_command(&mut self, command_line: ExCommandLine) {
+    self.pending_ex_commands.push(command_line);
+  }
+
+  /// Take all the queued Ex commands.
+  pub fn take_pending_ex_commands(&mut self) -> Vec<ExCommandLine> {
+    std::mem::take(&mut self.pending_ex_commands)
+  }
+
+  /// Queue an autocmd event, it is dispatched to js runtime by the event loop.
+  pub fn push_pending_autocmd(&mut self, ctx: AutocmdContext) {
+    self.pending_autocmds.push(ctx);
+  }
+
+  /// Take all the queued autocmd events.
+  pub fn take_pending_autocmds(&mut self) -> Vec<AutocmdContext> {
+    std::mem::take(&mut self.pending_autocmds)
+  }
+
+  /// Queue a put from the register `+`/`*`, it is put after the event loop reads the clipboard.
+  pub fn push_pending_clipboard_put(&mut self, put: ClipboardPut) {
+    self.pending_clipboard_puts.push(put);
+  }
+
+  /// Take all the queued puts from the register `+`/`*`.
+  pub fn take_pending_clipboard_puts(&mut self) -> Vec<ClipboardPut> {
+    std::mem::take(&mut self.pending_clipboard_puts)
+  }
+
+  /// Request to quit the editor, i.e. `:quit`, it is decided by the event loop.
+  pub fn request_quit(&mut self, force: bool) {
+    self.quit_request = Some(QuitRequest::new(force));
+  }
+
+  /// Take the quit request.
+  pub fn take_quit_request(&mut self) -> Option<QuitRequest> {
+    self.quit_request.take()
+  }
+
+  /// Whether the render is suppressed, i.e. it's replaying the keys of the register executions.
+  /// The intermediate renders are skipped if 'lazyredraw' is on.
+  pub fn is_render_suppressed(&self) -> bool {
+    self.macro_recorder.is_replaying()
+  }
+
+  /// Get the message shown in the message area.
+  pub fn message(&self) -> &Option<Message> {
+    &self.message
+  }
+
+  /// Show a normal message in the message area, i.e. `:echo`.
+  pub fn echo(&mut self, text: &str) {
+    self.message = Some(Message::info(text));
+  }
+
+  /// Show an error message in the message area, i.e. `:echoerr`.
+  pub fn echoerr(&mut self, text: &str) {
+    self.message = Some(Message::error(text));
+  }
+
+  pub fn clear_message(&mut self) {
+    self.message = None;
+  }
 }
@@ -0,0 +1,207 @@
+//! Autocmd events, i.e. the editor events that trigger the JS callbacks registered by
+//! `Rsvim.autocmd`.
+//!
+//! The callbacks are retained in the js runtime, the editor only produces the event contexts, and
+//! the event loop dispatches them to the js runtime.
+//!
+//! See: <https://vimhelp.org/autocmd.txt.html>.
+
+use crate::buf::BufferId;
+use crate::state::mode::Mode;
+
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+/// Autocmd event kind.
+pub enum AutocmdEvent {
+  /// After reading a file into a new buffer.
+  BufReadPost,
+  /// Before writing the buffer to a file, the callback can veto the write by returning `false` or
+  /// throwing an error.
+  BufWritePre,
+  /// After writing the buffer to a file.
+  BufWritePost,
+  /// After the editing mode changed.
+  ModeChanged,
+}
+
+impl Display for AutocmdEvent {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      AutocmdEvent::BufReadPost => write!(f, "BufReadPost"),
+      AutocmdEvent::BufWritePre => write!(f, "BufWritePre"),
+      AutocmdEvent::BufWritePost => write!(f, "BufWritePost"),
+      AutocmdEvent::ModeChanged => write!(f, "ModeChanged"),
+    }
+  }
+}
+
+impl FromStr for AutocmdEvent {
+  type Err = &'static str;
+
+  /// Parse `str` to enum.
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "BufReadPost" => Ok(AutocmdEvent::BufReadPost),
+      "BufWritePre" => Ok(AutocmdEvent::BufWritePre),
+      "BufWritePost" => Ok(AutocmdEvent::BufWritePost),
+      "ModeChanged" => Ok(AutocmdEvent::ModeChanged),
+      _ => Err("Invalid AutocmdEvent name"),
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// The context of a fired event, it is passed to the callbacks.
+pub struct AutocmdContext {
+  event: AutocmdEvent,
+  buf_id: Option<BufferId>,
+  // Absolute file name of the buffer.
+  file: Option<PathBuf>,
+  old_mode: Option<Mode>,
+  new_mode: Option<Mode>,
+}
+
+impl AutocmdContext {
+  /// Make context for buffer events, i.e. `BufReadPost`/`BufWritePre`/`BufWritePost`.
+  pub fn buffer(event: AutocmdEvent, buf_id: BufferId, file: Option<PathBuf>) -> Self {
+    AutocmdContext {
+      event,
+      buf_id: Some(buf_id),
+      file,
+      old_mode: None,
+      new_mode: None,
+    }
+  }
+
+  /// Make context for `ModeChanged` event.
+  pub fn mode_changed(old_mode: Mode, new_mode: Mode) -> Self {
+    AutocmdContext {
+      event: AutocmdEvent::ModeChanged,
+      buf_id: None,
+      file: None,
+      old_mode: Some(old_mode),
+      new_mode: Some(new_mode),
+    }
+  }
+
+  pub fn event(&self) -> AutocmdEvent {
+    self.event
+  }
+
+  pub fn buf_id(&self) -> Option<BufferId> {
+    self.buf_id
+  }
+
+  pub fn file(&self) -> Option<&Path> {
+    self.file.as_deref()
+  }
+
+  pub fn old_mode(&self) -> Option<Mode> {
+    self.old_mode
+  }
+
+  pub fn new_mode(&self) -> Option<Mode> {
+    self.new_mode
+  }
+
+  /// Whether the pattern matches the event. The buffer events match the absolute file name, the
+  /// `ModeChanged` event matches `{old_mode}:{new_mode}`, for example `Normal:Insert`.
+  ///
+  /// The event without a file name never matches a pattern.
+  pub fn matches(&self, pattern: &str) -> bool {
+    match self.event {
+      AutocmdEvent::ModeChanged => match (self.old_mode, self.new_mode) {
+        (Some(old_mode), Some(new_mode)) => {
+          glob_match(pattern, &format!("{}:{}", old_mode, new_mode))
+        }
+        _ => false,
+      },
+      _ => match &self.file {
+        Some(file) => glob_match(pattern, &file.to_string_lossy()),
+        None => false,
+      },
+    }
+  }
+}
+
+/// Match the text with a simple glob pattern, the `*` matches any chars (including the path
+/// separator), the `?` matches any single char, other chars match themselves.
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+  let pattern: Vec<char> = pattern.chars().collect();
+  let text: Vec<char> = text.chars().collect();
+
+  // The positions of last `*` in pattern, and the text position it starts to match.
+  let mut star: Option<(usize, usize)> = None;
+  let (mut p, mut t) = (0_usize, 0_usize);
+  while t < text.len() {
+    if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+      p += 1;
+      t += 1;
+    } else if p < pattern.len() && pattern[p] == '*' {
+      star = Some((p, t));
+      p += 1;
+    } else if let Some((star_p, star_t)) = star {
+      // Backtrack, let the last `*` match one more char.
+      star = Some((star_p, star_t + 1));
+      p = star_p + 1;
+      t = star_t + 1;
+    } else {
+      return false;
+    }
+  }
+  pattern[p..].iter().all(|c| *c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn glob_match1() {
+    assert!(glob_match("*.lock", "/home/rsvim/Cargo.lock"));
+    assert!(glob_match("*.lock", ".lock"));
+    assert!(!glob_match("*.lock", "/home/rsvim/Cargo.toml"));
+    assert!(!glob_match("*.lock", "/home/rsvim/Cargo.lock.bak"));
+    assert!(glob_match("/home/*/src/*.rs", "/home/rsvim/src/buf/opt.rs"));
+    assert!(glob_match("*.?s", "index.js"));
+    assert!(!glob_match("*.?s", "index.jsx"));
+    assert!(glob_match("*", ""));
+    assert!(glob_match("**a*", "bab"));
+    assert!(!glob_match("a", ""));
+  }
+
+  #[test]
+  fn matches1() {
+    let ctx = AutocmdContext::buffer(
+      AutocmdEvent::BufWritePre,
+      1,
+      Some(PathBuf::from("/home/rsvim/Cargo.lock")),
+    );
+    assert!(ctx.matches("*.lock"));
+    assert!(!ctx.matches("*.toml"));
+
+    let ctx = AutocmdContext::buffer(AutocmdEvent::BufWritePre, 1, None);
+    assert!(!ctx.matches("*"));
+
+    let ctx = AutocmdContext::mode_changed(Mode::Normal, Mode::Insert);
+    assert!(ctx.matches("*:Insert"));
+    assert!(ctx.matches("Normal:*"));
+    assert!(!ctx.matches("Insert:*"));
+  }
+
+  #[test]
+  fn event1() {
+    for event in [
+      AutocmdEvent::BufReadPost,
+      AutocmdEvent::BufWritePre,
+      AutocmdEvent::BufWritePost,
+      AutocmdEvent::ModeChanged,
+    ] {
+      assert_eq!(event.to_string().parse::<AutocmdEvent>(), Ok(event));
+    }
+    assert!("BufEnter".parse::<AutocmdEvent>().is_err());
+  }
+}
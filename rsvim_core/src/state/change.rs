@@ -0,0 +1,154 @@
+//! The change recorder for repeating the last change, i.e. `.`.
+//!
+//! See: <https://vimhelp.org/repeat.txt.html#.>.
+
+use crate::state::fsm::operator_pending::Operator;
+
+use crossterm::event::KeyCode;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+/// The buffer-changing command kinds.
+pub enum ChangeKind {
+  /// `i`, an insert session.
+  Insert,
+  /// `R`, a replace session.
+  Replace,
+  /// `r{char}`, replace the chars under the cursor with the char.
+  ReplaceChar { c: char },
+  /// An operator with its motion key, i.e. `dw`, or the operator key itself for whole lines, i.e.
+  /// `dd`.
+  Operator { operator: Operator, motion: char },
+  /// `p`/`P`, put the register content after/before the cursor.
+  Put { after: bool },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// The last buffer-changing command, it can be replayed at the cursor.
+pub struct Change {
+  kind: ChangeKind,
+  count: Option<usize>,
+  register_name: Option<char>,
+  inserted: Vec<KeyCode>,
+}
+
+impl Change {
+  pub fn new(kind: ChangeKind, count: Option<usize>, register_name: Option<char>) -> Self {
+    Change {
+      kind,
+      count,
+      register_name,
+      inserted: vec![],
+    }
+  }
+
+  pub fn kind(&self) -> ChangeKind {
+    self.kind
+  }
+
+  /// The count typed before the command.
+  pub fn count(&self) -> Option<usize> {
+    self.count
+  }
+
+  /// The register selected by the `"{name}` prefix.
+  pub fn register_name(&self) -> Option<char> {
+    self.register_name
+  }
+
+  /// The keys typed in insert (or replace) mode, i.e. the text inserted by `i` and `c`, without the
+  /// final `Esc`.
+  pub fn inserted(&self) -> &[KeyCode] {
+    &self.inserted
+  }
+
+  /// Replace the count, i.e. the count typed before `.` overrides the original one.
+  pub fn with_count(self, count: Option<usize>) -> Self {
+    Change { count, ..self }
+  }
+}
+
+#[derive(Debug, Clone, Default)]
+/// The change recorder, it records the last change, and the change that continues in insert mode
+/// until leaving it.
+pub struct ChangeRecorder {
+  last: Option<Change>,
+  pending: Option<Change>,
+}
+
+impl ChangeRecorder {
+  pub fn new() -> Self {
+    ChangeRecorder::default()
+  }
+
+  /// Get the last change.
+  pub fn last(&self) -> Option<&Change> {
+    self.last.as_ref()
+  }
+
+  /// Record a change finished at once, i.e. `dw` and `p`.
+  pub fn record(&mut self, change: Change) {
+    self.pending = None;
+    self.last = Some(change);
+  }
+
+  /// Start a change that continues in insert mode, i.e. `i` and `cw`. The keys typed in insert
+  /// mode are recorded until it's finished.
+  pub fn start(&mut self, change: Change) {
+    self.pending = Some(change);
+  }
+
+  /// Record a key typed in insert mode, it's ignored if no change is started.
+  pub fn record_insert_key(&mut self, key: KeyCode) {
+    if let Some(pending) = self.pending.as_mut() {
+      pending.inserted.push(key);
+    }
+  }
+
+  /// Finish the started change when leaving insert mode, it becomes the last change.
+  pub fn finish(&mut self) {
+    if let Some(pending) = self.pending.take() {
+      self.last = Some(pending);
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn recorder1() {
+    let mut recorder = ChangeRecorder::new();
+    assert!(recorder.last().is_none());
+
+    // The keys are ignored if no change is started.
+    recorder.record_insert_key(KeyCode::Char('a'));
+    recorder.finish();
+    assert!(recorder.last().is_none());
+
+    let delete_word = Change::new(
+      ChangeKind::Operator {
+        operator: Operator::Delete,
+        motion: 'w',
+      },
+      Some(2),
+      None,
+    );
+    recorder.record(delete_word.clone());
+    assert_eq!(recorder.last(), Some(&delete_word));
+
+    // The started change doesn't replace the last change until it's finished.
+    recorder.start(Change::new(ChangeKind::Insert, None, None));
+    recorder.record_insert_key(KeyCode::Char('a'));
+    recorder.record_insert_key(KeyCode::Enter);
+    assert_eq!(recorder.last(), Some(&delete_word));
+    recorder.finish();
+    let last = recorder.last().unwrap();
+    assert_eq!(last.kind(), ChangeKind::Insert);
+    assert_eq!(last.inserted(), &[KeyCode::Char('a'), KeyCode::Enter]);
+
+    let last = last.clone().with_count(Some(3));
+    assert_eq!(last.count(), Some(3));
+    assert_eq!(last.inserted(), &[KeyCode::Char('a'), KeyCode::Enter]);
+  }
+}
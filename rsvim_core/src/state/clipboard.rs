@@ -0,0 +1,464 @@
+//! System clipboard for the registers `+` and `*`.
+//!
+//! The registers are backed by a [`ClipboardProvider`], it's detected at startup (see
+//! [`detect_provider`]) or configured by the global config
+//! [`clipboard_commands`](crate::envar::global_config::GlobalConfig::clipboard_commands). Without
+//! a provider, the registers are internal registers, same with the other registers.
+//!
+//! The provider may block, i.e. runs an external command, thus the event loop calls it with
+//! [`write_clipboard`]/[`read_clipboard`], they run it in `spawn_blocking` with a timeout. The
+//! command is killed if it doesn't finish in the timeout.
+//!
+//! See: <https://vimhelp.org/gui_x11.txt.html#quoteplus>.
+
+use crate::envar::global_config::ClipboardCommands;
+use crate::res::AnyResult;
+use crate::state::register::SELECTION_REGISTER;
+use crate::ui::canvas::ShaderCommand;
+
+use std::fmt::Debug;
+use std::io::{Read, Write};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// The system clipboard.
+pub trait ClipboardProvider: Debug + Send + Sync {
+  /// The provider name, i.e. `osc52` or the copy command.
+  fn name(&self) -> &str;
+
+  /// Write the text of the register `+` or `*` to the clipboard, it may block until `timeout`.
+  ///
+  /// # Returns
+  ///
+  /// It returns the command to output to the terminal if the clipboard is written by the
+  /// terminal, i.e. OSC 52.
+  fn write(
+    &self,
+    register_name: char,
+    text: &str,
+    timeout: Duration,
+  ) -> AnyResult<Option<ShaderCommand>>;
+
+  /// Read the text of the register `+` or `*` from the clipboard, it may block until `timeout`.
+  fn read(&self, register_name: char, timeout: Duration) -> AnyResult<String>;
+
+  /// Whether the provider can read the clipboard, the registers are put from their internal
+  /// copies if not.
+  fn readable(&self) -> bool {
+    true
+  }
+}
+
+pub type ClipboardProviderArc = Arc<dyn ClipboardProvider>;
+
+#[derive(Debug, Copy, Clone, Default)]
+/// The provider writes the clipboard with the OSC 52 escape sequence, the terminal sets the
+/// clipboard (`c`) for the register `+`, or the primary selection (`p`) for the register `*`.
+///
+/// Reading the clipboard is unsupported, it needs to wait for the terminal's reply from the stdin,
+/// which is owned by the terminal event stream. Many terminals also disable it for security.
+///
+/// See: <https://invisible-island.net/xterm/ctlseqs/ctlseqs.html#h3-Operating-System-Commands>.
+pub struct Osc52Provider;
+
+impl ClipboardProvider for Osc52Provider {
+  fn name(&self) -> &str {
+    "osc52"
+  }
+
+  fn write(
+    &self,
+    register_name: char,
+    text: &str,
+    _timeout: Duration,
+  ) -> AnyResult<Option<ShaderCommand>> {
+    let selection = if register_name == SELECTION_REGISTER {
+      'p'
+    } else {
+      'c'
+    };
+    let sequence = format!(
+      "\x1b]52;{};{}\x07",
+      selection,
+      base64_encode(text.as_bytes())
+    );
+    Ok(Some(ShaderCommand::StylePrintString(
+      crossterm::style::Print(sequence),
+    )))
+  }
+
+  fn read(&self, _register_name: char, _timeout: Duration) -> AnyResult<String> {
+    anyhow::bail!("Reading clipboard is unsupported by OSC 52")
+  }
+
+  fn readable(&self) -> bool {
+    false
+  }
+}
+
+#[derive(Debug, Clone)]
+/// The provider writes/reads the clipboard with the external commands, i.e. `pbcopy`/`pbpaste`.
+/// The text is written to the stdin of the copy command, and read from the stdout of the paste
+/// command. The registers `+` and `*` share the same commands. The command is killed if it doesn't
+/// exit in the timeout, thus the copy command should fork to serve the clipboard, i.e. `xclip`.
+pub struct CommandProvider {
+  commands: ClipboardCommands,
+}
+
+impl CommandProvider {
+  pub fn new(commands: ClipboardCommands) -> Self {
+    CommandProvider { commands }
+  }
+}
+
+impl ClipboardProvider for CommandProvider {
+  fn name(&self) -> &str {
+    self.commands.copy().first().map_or("", |s| s.as_str())
+  }
+
+  fn write(
+    &self,
+    _register_name: char,
+    text: &str,
+    timeout: Duration,
+  ) -> AnyResult<Option<ShaderCommand>> {
+    let mut child = command(self.commands.copy())?
+      .stdin(Stdio::piped())
+      .stdout(Stdio::null())
+      .stderr(Stdio::null())
+      .spawn()?;
+    // The stdin is written in another thread, it fails once the command is killed.
+    let writer = child.stdin.take().map(|mut stdin| {
+      let bytes = text.as_bytes().to_vec();
+      std::thread::spawn(move || stdin.write_all(&bytes))
+    });
+    let status = wait_timeout(&mut child, timeout)?;
+    if let Some(Ok(result)) = writer.map(|writer| writer.join()) {
+      result?;
+    }
+    if !status.success() {
+      anyhow::bail!("Clipboard command {:?} failed: {}", self.name(), status);
+    }
+    Ok(None)
+  }
+
+  fn read(&self, _register_name: char, timeout: Duration) -> AnyResult<String> {
+    let mut child = command(self.commands.paste())?
+      .stdin(Stdio::null())
+      .stdout(Stdio::piped())
+      .stderr(Stdio::null())
+      .spawn()?;
+    // The stdout is read in another thread, it ends once the command exits or is killed.
+    let reader = child.stdout.take().map(|mut stdout| {
+      std::thread::spawn(move || {
+        let mut bytes = vec![];
+        stdout.read_to_end(&mut bytes).map(|_| bytes)
+      })
+    });
+    let status = wait_timeout(&mut child, timeout)?;
+    if !status.success() {
+      anyhow::bail!(
+        "Clipboard command {:?} failed: {}",
+        self.commands.paste().first().map_or("", |s| s.as_str()),
+        status
+      );
+    }
+    let bytes = match reader.map(|reader| reader.join()) {
+      Some(Ok(bytes)) => bytes?,
+      _ => vec![],
+    };
+    Ok(String::from_utf8(bytes)?)
+  }
+}
+
+// Wait for the command to exit, it's killed if it doesn't exit in `timeout`.
+fn wait_timeout(child: &mut Child, timeout: Duration) -> AnyResult<ExitStatus> {
+  let deadline = Instant::now() + timeout;
+  loop {
+    if let Some(status) = child.try_wait()? {
+      return Ok(status);
+    }
+    if Instant::now() >= deadline {
+      let _ = child.kill();
+      let _ = child.wait();
+      anyhow::bail!("Clipboard timeout after {:?}", timeout);
+    }
+    std::thread::sleep(Duration::from_millis(5));
+  }
+}
+
+// Make the command from the program and its arguments.
+fn command(args: &[String]) -> AnyResult<Command> {
+  let (program, args) = match args.split_first() {
+    Some(split) => split,
+    None => anyhow::bail!("Clipboard command is empty"),
+  };
+  let mut command = Command::new(program);
+  command.args(args);
+  Ok(command)
+}
+
+/// Detect the clipboard provider, in order:
+///
+/// 1. The configured `commands`.
+/// 2. The known commands found in `PATH`, i.e. `pbcopy`/`pbpaste` on macOS, `wl-copy`/`wl-paste`
+///    on Wayland, `xclip` or `xsel` on X11, and `win32yank.exe` on Windows (and WSL).
+/// 3. The OSC 52 if it runs in a terminal.
+///
+/// It returns `None` if there's no provider.
+pub fn detect_provider(
+  commands: Option<ClipboardCommands>,
+  in_terminal: bool,
+) -> Option<ClipboardProviderArc> {
+  if let Some(commands) = commands {
+    return Some(Arc::new(CommandProvider::new(commands)));
+  }
+
+  let has_env = |name: &str| std::env::var_os(name).is_some_and(|v| !v.is_empty());
+  let known: [(bool, &[&str], &[&str]); 5] = [
+    (cfg!(target_os = "macos"), &["pbcopy"], &["pbpaste"]),
+    (
+      has_env("WAYLAND_DISPLAY"),
+      &["wl-copy"],
+      &["wl-paste", "--no-newline"],
+    ),
+    (
+      has_env("DISPLAY"),
+      &["xclip", "-i", "-selection", "clipboard"],
+      &["xclip", "-o", "-selection", "clipboard"],
+    ),
+    (
+      has_env("DISPLAY"),
+      &["xsel", "-i", "-b"],
+      &["xsel", "-o", "-b"],
+    ),
+    (
+      true,
+      &["win32yank.exe", "-i", "--crlf"],
+      &["win32yank.exe", "-o", "--lf"],
+    ),
+  ];
+  for (enabled, copy, paste) in known {
+    if enabled && find_program(copy[0]) && find_program(paste[0]) {
+      let to_strings =
+        |args: &[&str]| -> Vec<String> { args.iter().map(|s| s.to_string()).collect() };
+      let commands = ClipboardCommands::new(to_strings(copy), to_strings(paste));
+      return Some(Arc::new(CommandProvider::new(commands)));
+    }
+  }
+
+  if in_terminal {
+    return Some(Arc::new(Osc52Provider));
+  }
+  None
+}
+
+// Whether the program is found in `PATH`.
+fn find_program(program: &str) -> bool {
+  std::env::var_os("PATH")
+    .is_some_and(|paths| std::env::split_paths(&paths).any(|dir| dir.join(program).is_file()))
+}
+
+/// Write the clipboard in `spawn_blocking`, see [`ClipboardProvider::write`].
+///
+/// # Errors
+///
+/// If the provider fails, or it doesn't finish in `timeout`.
+pub async fn write_clipboard(
+  provider: ClipboardProviderArc,
+  register_name: char,
+  text: String,
+  timeout: Duration,
+) -> AnyResult<Option<ShaderCommand>> {
+  run_blocking(
+    move || provider.write(register_name, &text, timeout),
+    timeout,
+  )
+  .await
+}
+
+/// Read the clipboard in `spawn_blocking`, see [`ClipboardProvider::read`].
+///
+/// # Errors
+///
+/// If the provider fails, or it doesn't finish in `timeout`.
+pub async fn read_clipboard(
+  provider: ClipboardProviderArc,
+  register_name: char,
+  timeout: Duration,
+) -> AnyResult<String> {
+  run_blocking(move || provider.read(register_name, timeout), timeout).await
+}
+
+// Run the blocking clipboard operation, it fails if it doesn't finish in `timeout`. The operation
+// keeps running in the blocking thread after timeout, but its result is dropped. The command
+// provider kills its command on the same timeout, thus the blocking thread is released.
+async fn run_blocking<T, F>(f: F, timeout: Duration) -> AnyResult<T>
+where
+  T: Send + 'static,
+  F: FnOnce() -> AnyResult<T> + Send + 'static,
+{
+  match tokio::time::timeout(timeout, tokio::task::spawn_blocking(f)).await {
+    Ok(result) => result?,
+    Err(_) => anyhow::bail!("Clipboard timeout after {:?}", timeout),
+  }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+/// The put from the register `+`/`*` waiting for the clipboard, i.e. `"+p`. It's put after the
+/// event loop reads the clipboard.
+pub struct ClipboardPut {
+  register_name: char,
+  after: bool,
+}
+
+impl ClipboardPut {
+  pub fn new(register_name: char, after: bool) -> Self {
+    ClipboardPut {
+      register_name,
+      after,
+    }
+  }
+
+  pub fn register_name(&self) -> char {
+    self.register_name
+  }
+
+  /// Put after (`p`) or before (`P`) the cursor.
+  pub fn after(&self) -> bool {
+    self.after
+  }
+}
+
+// Encode the bytes with the standard base64 alphabet and padding.
+fn base64_encode(bytes: &[u8]) -> String {
+  const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+  let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+  for chunk in bytes.chunks(3) {
+    let n = chunk
+      .iter()
+      .enumerate()
+      .fold(0_u32, |n, (i, b)| n | ((*b as u32) << (16 - 8 * i)));
+    for i in 0..4 {
+      if i <= chunk.len() {
+        encoded.push(ALPHABET[(n >> (18 - 6 * i)) as usize & 0x3f] as char);
+      } else {
+        encoded.push('=');
+      }
+    }
+  }
+  encoded
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use crate::state::register::CLIPBOARD_REGISTER;
+  use crate::test::clipboard::MockProvider;
+
+  fn block_on<F: std::future::Future>(future: F) -> F::Output {
+    tokio::runtime::Builder::new_current_thread()
+      .enable_all()
+      .build()
+      .unwrap()
+      .block_on(future)
+  }
+
+  #[test]
+  fn base64_encode1() {
+    assert_eq!(base64_encode(b""), "");
+    assert_eq!(base64_encode(b"f"), "Zg==");
+    assert_eq!(base64_encode(b"fo"), "Zm8=");
+    assert_eq!(base64_encode(b"foo"), "Zm9v");
+    assert_eq!(base64_encode(b"foobar"), "Zm9vYmFy");
+    assert_eq!(base64_encode("你好\n".as_bytes()), "5L2g5aW9Cg==");
+  }
+
+  #[test]
+  fn osc52_1() {
+    let provider = Osc52Provider;
+    assert!(!provider.readable());
+    let timeout = Duration::from_secs(5);
+    assert!(provider.read(CLIPBOARD_REGISTER, timeout).is_err());
+    let sequence = |register_name: char| match provider.write(register_name, "foo", timeout) {
+      Ok(Some(ShaderCommand::StylePrintString(crossterm::style::Print(s)))) => s,
+      _ => unreachable!(),
+    };
+    assert_eq!(sequence(CLIPBOARD_REGISTER), "\x1b]52;c;Zm9v\x07");
+    assert_eq!(sequence(SELECTION_REGISTER), "\x1b]52;p;Zm9v\x07");
+  }
+
+  #[test]
+  fn detect_provider1() {
+    let commands = ClipboardCommands::new(vec!["copy".to_string()], vec!["paste".to_string()]);
+    let provider = detect_provider(Some(commands), true).unwrap();
+    assert_eq!(provider.name(), "copy");
+    assert!(provider.readable());
+    assert!(CommandProvider::new(ClipboardCommands::new(vec![], vec![]))
+      .write(CLIPBOARD_REGISTER, "foo", Duration::from_secs(5))
+      .is_err());
+  }
+
+  #[cfg(unix)]
+  #[test]
+  fn command_provider1() {
+    let to_strings =
+      |args: &[&str]| -> Vec<String> { args.iter().map(|s| s.to_string()).collect() };
+    let timeout = Duration::from_secs(5);
+    let provider = CommandProvider::new(ClipboardCommands::new(
+      to_strings(&["cat"]),
+      to_strings(&["echo", "foo"]),
+    ));
+    assert!(provider
+      .write(CLIPBOARD_REGISTER, "foo", timeout)
+      .unwrap()
+      .is_none());
+    assert_eq!(provider.read(CLIPBOARD_REGISTER, timeout).unwrap(), "foo\n");
+
+    // The commands are killed after timeout.
+    let provider = CommandProvider::new(ClipboardCommands::new(
+      to_strings(&["sleep", "5"]),
+      to_strings(&["sleep", "5"]),
+    ));
+    let timeout = Duration::from_millis(10);
+    let start = Instant::now();
+    let written = provider.write(CLIPBOARD_REGISTER, "foo", timeout);
+    assert!(written.unwrap_err().to_string().contains("timeout"));
+    let read = provider.read(CLIPBOARD_REGISTER, timeout);
+    assert!(read.unwrap_err().to_string().contains("timeout"));
+    assert!(start.elapsed() < Duration::from_secs(5));
+  }
+
+  #[test]
+  fn write_read1() {
+    let provider = Arc::new(MockProvider::default());
+    let timeout = Duration::from_secs(5);
+    let written = block_on(write_clipboard(
+      provider.clone(),
+      CLIPBOARD_REGISTER,
+      "foo\n".to_string(),
+      timeout,
+    ));
+    assert!(written.unwrap().is_none());
+    assert_eq!(*provider.text.read(), "foo\n");
+    let read = block_on(read_clipboard(provider, CLIPBOARD_REGISTER, timeout));
+    assert_eq!(read.unwrap(), "foo\n");
+  }
+
+  #[test]
+  fn timeout1() {
+    let provider = Arc::new(MockProvider {
+      delay: Duration::from_millis(500),
+      ..Default::default()
+    });
+    let read = block_on(read_clipboard(
+      provider,
+      CLIPBOARD_REGISTER,
+      Duration::from_millis(10),
+    ));
+    assert!(read.unwrap_err().to_string().contains("timeout"));
+  }
+}
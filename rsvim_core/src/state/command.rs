@@ -7,6 +7,14 @@
 //! And if one day, we support other editor modes such as emacs, vscode, etc, different key codes
 //! will indicate different editor operations. Thus this layer will help to maintain different
 //! editor modes and internal core logics.
+//!
+//! It also maintains the Ex commands registry, i.e. the built-in and user-defined commands typed
+//! in command-line mode.
+
+use crate::res::AnyResult;
+
+use std::collections::BTreeMap;
+use std::ops::Range;
 
 #[derive(Debug, Copy, Clone)]
 // Editor operation commands.
@@ -16,3 +24,544 @@ pub enum Command {
   CursorMoveLeft(u16),
   CursorMoveRight(u16),
 }
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+/// The kind of the command line, i.e. the key that enters command-line mode.
+/// See: <https://vimhelp.org/cmdline.txt.html#cmdline-window>.
+pub enum CommandLineKind {
+  /// `:`, the Ex command.
+  #[default]
+  Ex,
+  /// `/`, the forward search pattern.
+  Search,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+/// Built-in Ex commands.
+pub enum BuiltinExCommand {
+  /// `:quit`, close the current window, or the current tab page if it's the last window in it, or
+  /// quit the editor if it's the last window.
+  Quit,
+  /// `:qall`, quit the editor.
+  QuitAll,
+  /// `:write`, write the current buffer to its file.
+  Write,
+  /// `:wq`, write the current buffer and quit.
+  WriteQuit,
+  /// `:xit`, same with `:wq`, but only write when the buffer is modified.
+  Exit,
+  /// `:edit`, reload the current buffer from its file.
+  Edit,
+  /// `:recover`, recover the current buffer from its recovery journal.
+  Recover,
+  /// `:nohlsearch`, stop the 'hlsearch' highlighting until the next search.
+  NoHlSearch,
+  /// `:substitute`, replace the pattern matches in the lines.
+  Substitute,
+  /// `:set`, set the editor options.
+  Set,
+  /// `:colorscheme`, switch the color scheme, or show the current one without argument.
+  ColorScheme,
+  /// `:buffer`, switch current window to the buffer.
+  Buffer,
+  /// `:bnext`, switch current window to the next listed buffer.
+  BufferNext,
+  /// `:bprevious`, switch current window to the previous listed buffer.
+  BufferPrev,
+  /// `:tabnew`, open a new tab page with the file, or an empty buffer without argument.
+  TabNew,
+  /// `:tabclose`, close the current tab page, or quit the editor if it's the last tab page.
+  TabClose,
+  /// `:resize`, set the height of the current window, or change it by `+N`/`-N`.
+  Resize,
+  /// `:profile`, show the render profile in a scratch buffer, see `--profile`.
+  Profile,
+  /// `:vertical`, execute the command with the vertical modifier, i.e. `:vertical resize` sets
+  /// the width of the current window.
+  Vertical,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+/// The handler of an Ex command.
+pub enum ExCommandHandler {
+  /// Built-in command, handled by the editor itself.
+  Builtin(BuiltinExCommand),
+  /// User-defined command, handled by the JS callback registered with `Rsvim.cmd.create`. The
+  /// callback is retained in [`JsRuntimeState`](crate::js::JsRuntimeState) by the command name.
+  User,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+/// A line number in the range of an Ex command.
+pub enum ExLineNumber {
+  /// `.`, the cursor line.
+  Current,
+  /// `$`, the last line.
+  Last,
+  /// `{number}`, the absolute line number, starts from 1.
+  Absolute(usize),
+}
+
+impl ExLineNumber {
+  // Parse the line number at the start of `s`, returns it and the remainder.
+  fn parse(s: &str) -> Option<(Self, &str)> {
+    if let Some(rest) = s.strip_prefix('.') {
+      return Some((ExLineNumber::Current, rest));
+    }
+    if let Some(rest) = s.strip_prefix('$') {
+      return Some((ExLineNumber::Last, rest));
+    }
+    let (number, rest) = parse_number(s)?;
+    Some((ExLineNumber::Absolute(number), rest))
+  }
+
+  // The line index, the absolute line number `0` is the same with `1`.
+  fn line_idx(&self, cursor_line_idx: usize, last_line_idx: usize) -> usize {
+    match self {
+      ExLineNumber::Current => cursor_line_idx,
+      ExLineNumber::Last => last_line_idx,
+      ExLineNumber::Absolute(number) => number.saturating_sub(1),
+    }
+  }
+}
+
+// Parse the decimal number at the start of `s`, returns it and the remainder.
+fn parse_number(s: &str) -> Option<(usize, &str)> {
+  let digits_len = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+  if digits_len == 0 {
+    return None;
+  }
+  let (digits, rest) = s.split_at(digits_len);
+  Some((digits.parse::<usize>().unwrap_or(usize::MAX), rest))
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+/// A line address in the range of an Ex command, i.e. the line number with the relative offset,
+/// for example `.+2` and `$-1`.
+/// See: <https://vimhelp.org/cmdline.txt.html#%3Arange>.
+pub struct ExAddress {
+  line: ExLineNumber,
+  offset: isize,
+}
+
+impl ExAddress {
+  pub fn new(line: ExLineNumber) -> Self {
+    ExAddress::with_offset(line, 0)
+  }
+
+  pub fn with_offset(line: ExLineNumber, offset: isize) -> Self {
+    ExAddress { line, offset }
+  }
+
+  pub fn line(&self) -> ExLineNumber {
+    self.line
+  }
+
+  pub fn offset(&self) -> isize {
+    self.offset
+  }
+
+  // Parse the address at the start of `s`, returns it and the remainder. The line number is the
+  // cursor line if it's omitted, i.e. `+2` is the same with `.+2`. The offsets are summed, and
+  // `+`/`-` without number is 1, i.e. `.++` is the same with `.+2`.
+  fn parse(s: &str) -> Option<(Self, &str)> {
+    let (line, mut rest) = match ExLineNumber::parse(s) {
+      Some((line, rest)) => (Some(line), rest),
+      None => (None, s),
+    };
+    let mut offset: Option<isize> = None;
+    loop {
+      let sign = match rest.chars().next() {
+        Some('+') => 1,
+        Some('-') => -1,
+        _ => break,
+      };
+      rest = &rest[1..];
+      let n = match parse_number(rest) {
+        Some((n, next)) => {
+          rest = next;
+          n
+        }
+        None => 1,
+      };
+      let n = isize::try_from(n).unwrap_or(isize::MAX);
+      offset = Some(offset.unwrap_or(0).saturating_add(sign * n));
+    }
+    if line.is_none() && offset.is_none() {
+      return None;
+    }
+    Some((
+      ExAddress::with_offset(line.unwrap_or(ExLineNumber::Current), offset.unwrap_or(0)),
+      rest,
+    ))
+  }
+
+  // The line index, it's `None` if the offset goes before the first line.
+  fn line_idx(&self, cursor_line_idx: usize, last_line_idx: usize) -> Option<usize> {
+    self
+      .line
+      .line_idx(cursor_line_idx, last_line_idx)
+      .checked_add_signed(self.offset)
+  }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+/// The line range of an Ex command, i.e. the `%` of `:%s/foo/bar/`.
+/// See: <https://vimhelp.org/cmdline.txt.html#cmdline-ranges>.
+pub enum ExRange {
+  /// `%`, all the lines.
+  All,
+  /// `{start},{end}`, or a single line `{start}`.
+  Lines(ExAddress, ExAddress),
+}
+
+impl ExRange {
+  // Parse the range at the start of `s`, returns it and the remainder.
+  fn parse(s: &str) -> Option<(Self, &str)> {
+    if let Some(rest) = s.strip_prefix('%') {
+      return Some((ExRange::All, rest));
+    }
+    let (start, rest) = ExAddress::parse(s)?;
+    match rest.strip_prefix(',').and_then(ExAddress::parse) {
+      Some((end, rest)) => Some((ExRange::Lines(start, end), rest)),
+      None => Some((ExRange::Lines(start, start), rest)),
+    }
+  }
+
+  /// Get the line index range, with the cursor line index and the last line index of the buffer.
+  /// The backwards range is swapped, i.e. `3,1` is the same with `1,3`.
+  ///
+  /// # Errors
+  ///
+  /// If the range is out of the buffer, i.e. beyond the last line or before the first line.
+  pub fn line_range(
+    &self,
+    cursor_line_idx: usize,
+    last_line_idx: usize,
+  ) -> AnyResult<Range<usize>> {
+    let (start, end) = match self {
+      ExRange::All => (0, last_line_idx),
+      ExRange::Lines(start, end) => match (
+        start.line_idx(cursor_line_idx, last_line_idx),
+        end.line_idx(cursor_line_idx, last_line_idx),
+      ) {
+        (Some(start), Some(end)) => (std::cmp::min(start, end), std::cmp::max(start, end)),
+        _ => anyhow::bail!("E16: Invalid range"),
+      },
+    };
+    if end > last_line_idx {
+      anyhow::bail!("E16: Invalid range");
+    }
+    Ok(start..end + 1)
+  }
+
+  /// Get the last line index of the range, the line beyond the last line stops at the last line,
+  /// and the line before the first line stops at the first line, i.e. the target line of
+  /// `:{number}`.
+  pub fn end_line_idx(&self, cursor_line_idx: usize, last_line_idx: usize) -> usize {
+    let end = match self {
+      ExRange::All => last_line_idx,
+      ExRange::Lines(start, end) => std::cmp::max(
+        start.line_idx(cursor_line_idx, last_line_idx).unwrap_or(0),
+        end.line_idx(cursor_line_idx, last_line_idx).unwrap_or(0),
+      ),
+    };
+    std::cmp::min(end, last_line_idx)
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// The Ex command line, i.e. `:{range}{name} {args}`.
+pub struct ExCommandLine {
+  range: Option<ExRange>,
+  name: String,
+  args: String,
+}
+
+impl ExCommandLine {
+  pub fn new(name: &str, args: &str) -> Self {
+    ExCommandLine {
+      range: None,
+      name: name.to_string(),
+      args: args.to_string(),
+    }
+  }
+
+  /// Parse the command line typed in command-line mode, the leading `:` and whitespaces are
+  /// ignored. The optional range goes first, then the command name is the leading alphanumeric
+  /// chars, the remainder is the arguments.
+  ///
+  /// It returns `None` if there's no command name, except the range alone, i.e. `:{number}` which
+  /// goes to the line.
+  pub fn parse(line: &str) -> Option<Self> {
+    let line = line.trim_start_matches(|c: char| c == ':' || c.is_whitespace());
+    let (range, line) = match ExRange::parse(line) {
+      Some((range, rest)) => (Some(range), rest.trim_start()),
+      None => (None, line),
+    };
+    let name_len = line
+      .find(|c: char| !c.is_ascii_alphanumeric())
+      .unwrap_or(line.len());
+    if name_len == 0 && (range.is_none() || !line.is_empty()) {
+      return None;
+    }
+    let (name, args) = line.split_at(name_len);
+    Some(ExCommandLine {
+      range,
+      ..ExCommandLine::new(name, args.trim())
+    })
+  }
+
+  /// The line range before the command name.
+  pub fn range(&self) -> Option<ExRange> {
+    self.range
+  }
+
+  /// The command name.
+  pub fn name(&self) -> &str {
+    &self.name
+  }
+
+  /// The arguments, i.e. the remainder of the command line.
+  pub fn args(&self) -> &str {
+    &self.args
+  }
+
+  /// Whether the command name is followed by `!`, i.e. `:write!`.
+  pub fn bang(&self) -> bool {
+    self.args.starts_with('!')
+  }
+}
+
+#[derive(Debug, Clone)]
+/// The Ex commands registry, maps from command name to its handler.
+///
+/// The built-in commands are lowercase, and the user-defined commands must start with an uppercase
+/// letter, thus they never conflict.
+/// See: <https://vimhelp.org/map.txt.html#user-commands>.
+pub struct ExCommands {
+  commands: BTreeMap<String, ExCommandHandler>,
+}
+
+impl ExCommands {
+  pub fn new() -> Self {
+    let commands = [
+      ("q", BuiltinExCommand::Quit),
+      ("quit", BuiltinExCommand::Quit),
+      ("qa", BuiltinExCommand::QuitAll),
+      ("qall", BuiltinExCommand::QuitAll),
+      ("quita", BuiltinExCommand::QuitAll),
+      ("quitall", BuiltinExCommand::QuitAll),
+      ("w", BuiltinExCommand::Write),
+      ("write", BuiltinExCommand::Write),
+      ("wq", BuiltinExCommand::WriteQuit),
+      ("x", BuiltinExCommand::Exit),
+      ("xit", BuiltinExCommand::Exit),
+      ("exi", BuiltinExCommand::Exit),
+      ("exit", BuiltinExCommand::Exit),
+      ("e", BuiltinExCommand::Edit),
+      ("edit", BuiltinExCommand::Edit),
+      ("rec", BuiltinExCommand::Recover),
+      ("recover", BuiltinExCommand::Recover),
+      ("noh", BuiltinExCommand::NoHlSearch),
+      ("nohlsearch", BuiltinExCommand::NoHlSearch),
+      ("s", BuiltinExCommand::Substitute),
+      ("substitute", BuiltinExCommand::Substitute),
+      ("se", BuiltinExCommand::Set),
+      ("set", BuiltinExCommand::Set),
+      ("colo", BuiltinExCommand::ColorScheme),
+      ("colorscheme", BuiltinExCommand::ColorScheme),
+      ("b", BuiltinExCommand::Buffer),
+      ("buffer", BuiltinExCommand::Buffer),
+      ("bn", BuiltinExCommand::BufferNext),
+      ("bnext", BuiltinExCommand::BufferNext),
+      ("bp", BuiltinExCommand::BufferPrev),
+      ("bprevious", BuiltinExCommand::BufferPrev),
+      ("bN", BuiltinExCommand::BufferPrev),
+      ("bNext", BuiltinExCommand::BufferPrev),
+      ("tabnew", BuiltinExCommand::TabNew),
+      ("tabc", BuiltinExCommand::TabClose),
+      ("tabclose", BuiltinExCommand::TabClose),
+      ("res", BuiltinExCommand::Resize),
+      ("resize", BuiltinExCommand::Resize),
+      ("prof", BuiltinExCommand::Profile),
+      ("profile", BuiltinExCommand::Profile),
+      ("vert", BuiltinExCommand::Vertical),
+      ("vertical", BuiltinExCommand::Vertical),
+    ]
+    .into_iter()
+    .map(|(name, command)| (name.to_string(), ExCommandHandler::Builtin(command)))
+    .collect();
+    ExCommands { commands }
+  }
+
+  /// Get the handler of the command.
+  pub fn get(&self, name: &str) -> Option<ExCommandHandler> {
+    self.commands.get(name).copied()
+  }
+
+  /// Whether the name is valid for user-defined command, i.e. it starts with an uppercase letter
+  /// and only contains alphanumeric chars.
+  pub fn is_valid_user_command_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+      Some(c) if c.is_ascii_uppercase() => chars.all(|c| c.is_ascii_alphanumeric()),
+      _ => false,
+    }
+  }
+
+  /// Register a user-defined command, an existing command is replaced only if `force` is `true`.
+  ///
+  /// # Errors
+  ///
+  /// If the name is invalid, or the command already exists and `force` is `false`.
+  pub fn create_user_command(&mut self, name: &str, force: bool) -> AnyResult<()> {
+    if !Self::is_valid_user_command_name(name) {
+      anyhow::bail!(
+        "E183: User defined commands must start with an uppercase letter: {}",
+        name
+      );
+    }
+    if !force && self.commands.contains_key(name) {
+      anyhow::bail!(
+        "E174: Command already exists: add ! to replace it: {}",
+        name
+      );
+    }
+    self
+      .commands
+      .insert(name.to_string(), ExCommandHandler::User);
+    Ok(())
+  }
+}
+
+impl Default for ExCommands {
+  fn default() -> Self {
+    ExCommands::new()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn parse1() {
+    let actual = ExCommandLine::parse(":Format  a b ").unwrap();
+    assert_eq!(actual.name(), "Format");
+    assert_eq!(actual.args(), "a b");
+    let actual = ExCommandLine::parse("q!").unwrap();
+    assert_eq!(actual.name(), "q");
+    assert_eq!(actual.args(), "!");
+    assert!(actual.bang());
+    assert!(!ExCommandLine::parse("w").unwrap().bang());
+    assert_eq!(ExCommandLine::parse("  "), None);
+    assert_eq!(ExCommandLine::parse(":"), None);
+  }
+
+  #[test]
+  fn parse_range1() {
+    let actual = ExCommandLine::parse(":%s/a/b/g").unwrap();
+    assert_eq!(actual.range(), Some(ExRange::All));
+    assert_eq!(actual.name(), "s");
+    assert_eq!(actual.args(), "/a/b/g");
+    assert_eq!(actual.range().unwrap().line_range(1, 4).unwrap(), 0..5);
+
+    let actual = ExCommandLine::parse(" 2,$ s").unwrap();
+    assert_eq!(
+      actual.range(),
+      Some(ExRange::Lines(
+        ExAddress::new(ExLineNumber::Absolute(2)),
+        ExAddress::new(ExLineNumber::Last)
+      ))
+    );
+    assert_eq!(actual.name(), "s");
+    assert_eq!(actual.range().unwrap().line_range(0, 4).unwrap(), 1..5);
+
+    // The backwards range is swapped.
+    let range = ExCommandLine::parse("3,.s").unwrap().range().unwrap();
+    assert_eq!(range.line_range(0, 4).unwrap(), 0..3);
+    let range = ExCommandLine::parse("6s").unwrap().range().unwrap();
+    assert!(range.line_range(0, 4).is_err());
+    assert_eq!(ExCommandLine::parse("q").unwrap().range(), None);
+  }
+
+  #[test]
+  fn parse_range_only1() {
+    // `:{number}` goes to the line.
+    let actual = ExCommandLine::parse(":10").unwrap();
+    assert_eq!(
+      actual.range(),
+      Some(ExRange::Lines(
+        ExAddress::new(ExLineNumber::Absolute(10)),
+        ExAddress::new(ExLineNumber::Absolute(10))
+      ))
+    );
+    assert_eq!(actual.name(), "");
+    assert_eq!(actual.range().unwrap().end_line_idx(0, 99), 9);
+    assert_eq!(actual.range().unwrap().end_line_idx(0, 4), 4);
+    let actual = ExCommandLine::parse("$").unwrap();
+    assert_eq!(actual.range().unwrap().end_line_idx(0, 99), 99);
+    let actual = ExCommandLine::parse("%").unwrap();
+    assert_eq!(actual.range().unwrap().end_line_idx(3, 99), 99);
+    assert_eq!(ExCommandLine::parse("10/a"), None);
+  }
+
+  #[test]
+  fn parse_range_offset1() {
+    let actual = ExCommandLine::parse(":.,.+2s/a/b/").unwrap();
+    assert_eq!(
+      actual.range(),
+      Some(ExRange::Lines(
+        ExAddress::new(ExLineNumber::Current),
+        ExAddress::with_offset(ExLineNumber::Current, 2)
+      ))
+    );
+    assert_eq!(actual.name(), "s");
+    assert_eq!(actual.args(), "/a/b/");
+    assert_eq!(actual.range().unwrap().line_range(1, 9).unwrap(), 1..4);
+
+    // The omitted line number is the cursor line, `+`/`-` without number is 1, and the offsets
+    // are summed.
+    let range = ExCommandLine::parse("-,+s").unwrap().range().unwrap();
+    assert_eq!(range.line_range(3, 9).unwrap(), 2..5);
+    let range = ExCommandLine::parse("2+3,$-1s").unwrap().range().unwrap();
+    assert_eq!(range.line_range(0, 9).unwrap(), 4..9);
+    let range = ExCommandLine::parse(".++-3").unwrap().range().unwrap();
+    assert_eq!(
+      range,
+      ExRange::Lines(
+        ExAddress::with_offset(ExLineNumber::Current, -1),
+        ExAddress::with_offset(ExLineNumber::Current, -1)
+      )
+    );
+
+    // Out of the buffer.
+    let range = ExCommandLine::parse(".-2s").unwrap().range().unwrap();
+    assert!(range.line_range(1, 9).is_err());
+    assert_eq!(range.end_line_idx(1, 9), 0);
+    let range = ExCommandLine::parse("$+1s").unwrap().range().unwrap();
+    assert!(range.line_range(1, 9).is_err());
+    assert_eq!(range.end_line_idx(1, 9), 9);
+  }
+
+  #[test]
+  fn create_user_command1() {
+    let mut commands = ExCommands::new();
+    assert_eq!(
+      commands.get("q"),
+      Some(ExCommandHandler::Builtin(BuiltinExCommand::Quit))
+    );
+    assert!(commands.get("Format").is_none());
+
+    assert!(commands.create_user_command("Format", false).is_ok());
+    assert_eq!(commands.get("Format"), Some(ExCommandHandler::User));
+    assert!(commands.create_user_command("Format", false).is_err());
+    assert!(commands.create_user_command("Format", true).is_ok());
+
+    assert!(commands.create_user_command("format", true).is_err());
+    assert!(commands.create_user_command("Format2", true).is_ok());
+    assert!(commands.create_user_command("Form-at", true).is_err());
+    assert!(commands.create_user_command("", true).is_err());
+  }
+}
@@ -6,11 +6,6 @@
 //!
 //! Each editing mode handles user keyboard/mouse inputs in a different way, this a finite-state
 //! machine (FSM) separates code logic in different modes. Each editing mode is a FSM state.
-//!
-//! Besides, there're some other internal states which are not editing modes or visible to
-//! user, but help maintaining the internal state of the editor:
-//!
-//! * Quit state: The editor should quit on this state.
 
 use crossterm::event::Event;
 
@@ -23,7 +18,7 @@ pub use crate::state::fsm::command_line::CommandLineStateful;
 pub use crate::state::fsm::insert::InsertStateful;
 pub use crate::state::fsm::normal::NormalStateful;
 pub use crate::state::fsm::operator_pending::OperatorPendingStateful;
-pub use crate::state::fsm::quit::QuitStateful;
+pub use crate::state::fsm::replace::ReplaceStateful;
 pub use crate::state::fsm::select::SelectStateful;
 pub use crate::state::fsm::terminal::TerminalStateful;
 pub use crate::state::fsm::visual::VisualStateful;
@@ -32,7 +27,7 @@ pub mod command_line;
 pub mod insert;
 pub mod normal;
 pub mod operator_pending;
-pub mod quit;
+pub mod replace;
 pub mod select;
 pub mod terminal;
 pub mod visual;
@@ -79,10 +74,9 @@ pub enum StatefulValue {
   SelectMode(SelectStateful),
   OperatorPendingMode(OperatorPendingStateful),
   InsertMode(InsertStateful),
+  ReplaceMode(ReplaceStateful),
   CommandLineMode(CommandLineStateful),
   TerminalMode(TerminalStateful),
-  // Internal states.
-  QuitState(QuitStateful),
 }
 
 impl Default for StatefulValue {
@@ -104,9 +98,9 @@ impl Stateful for StatefulValue {
       StatefulValue::SelectMode(s) => s.handle(data_access),
       StatefulValue::OperatorPendingMode(s) => s.handle(data_access),
       StatefulValue::InsertMode(s) => s.handle(data_access),
+      StatefulValue::ReplaceMode(s) => s.handle(data_access),
       StatefulValue::CommandLineMode(s) => s.handle(data_access),
       StatefulValue::TerminalMode(s) => s.handle(data_access),
-      StatefulValue::QuitState(s) => s.handle(data_access),
     }
   }
 }
@@ -1,13 +1,1810 @@
 //! The command-line mode.
 
+use crate::buf::{BufferArc, BufferId, BuffersManagerArc, ColorSchemes};
+use crate::envar;
+use crate::evloop::set_option;
+use crate::res::{AnyResult, BufferErr};
+use crate::state::command::{
+  BuiltinExCommand, CommandLineKind, ExAddress, ExCommandHandler, ExCommandLine, ExLineNumber,
+  ExRange,
+};
+use crate::state::fsm::normal::NormalStateful;
+use crate::state::fsm::visual::{current_buffer, cursor_position, sync_cursor, sync_viewport};
 use crate::state::fsm::{Stateful, StatefulDataAccess, StatefulValue};
+use crate::state::motion::{clamp_position, goto_line_target};
+use crate::state::search::{
+  set_search_highlights, try_compile_pattern, SearchDirection, INC_SEARCH_TIMEOUT,
+};
+use crate::state::selection::BufferPosition;
+use crate::state::set::parse_set_args;
+use crate::state::substitute::{substitute_lines, Substitute, SubstituteConfirm, SubstituteReport};
+use crate::state::State;
+use crate::ui::tree::internal::Inodeable;
+use crate::ui::tree::{FocusDirection, SplitDirection, Tree, TreeArc, TreeNode};
+use crate::ui::widget::window::{CursorLinePosition, Window};
+use crate::{rlock, wlock};
+
+use crossterm::event::{Event, KeyCode, KeyEventKind};
+use regex::Regex;
+use std::ops::Range;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Instant;
 
 #[derive(Debug, Copy, Clone, Default)]
 /// The command-line editing mode.
 pub struct CommandLineStateful {}
 
 impl Stateful for CommandLineStateful {
-  fn handle(&self, _data_access: StatefulDataAccess) -> StatefulValue {
+  fn handle(&self, data_access: StatefulDataAccess) -> StatefulValue {
+    let state = data_access.state;
+    let tree = data_access.tree;
+    let buffers = data_access.buffers;
+    let event = data_access.event;
+
+    if let Event::Key(key_event) = event {
+      if key_event.kind == KeyEventKind::Press {
+        if let Some(confirm) = state.take_substitute_confirm() {
+          let mut tree = wlock!(tree);
+          return self.confirm_substitute(state, &mut tree, confirm, key_event.code);
+        }
+        let kind = state.command_line_kind();
+        let search = kind == CommandLineKind::Search;
+        if !matches!(key_event.code, KeyCode::Up | KeyCode::Down) {
+          // Editing or leaving the command line ends the history navigation.
+          state.cmd_histories_mut().get_mut(kind).reset();
+        }
+        match key_event.code {
+          KeyCode::Esc => {
+            state.command_line_mut().clear();
+            if search {
+              let mut tree = wlock!(tree);
+              self.cancel_search(state, &mut tree);
+            }
+            return StatefulValue::NormalMode(NormalStateful::default());
+          }
+          KeyCode::Enter => {
+            let command_line = std::mem::take(state.command_line_mut());
+            state.cmd_histories_mut().get_mut(kind).push(&command_line);
+            if search {
+              let mut tree = wlock!(tree);
+              self.commit_search(state, &mut tree, &command_line);
+              return StatefulValue::NormalMode(NormalStateful::default());
+            }
+            return self.execute(state, &tree, &buffers, &command_line);
+          }
+          KeyCode::Backspace => {
+            // Delete the char, or back to normal mode if the command line is empty.
+            if state.command_line_mut().pop().is_none() {
+              if search {
+                let mut tree = wlock!(tree);
+                self.cancel_search(state, &mut tree);
+              }
+              return StatefulValue::NormalMode(NormalStateful::default());
+            }
+            if search {
+              let mut tree = wlock!(tree);
+              self.preview_search(state, &mut tree);
+            }
+          }
+          KeyCode::Up | KeyCode::Down => {
+            // Recall the older/newer history entry that starts with the typed text.
+            let typed = state.command_line().clone();
+            let history = state.cmd_histories_mut().get_mut(kind);
+            let entry = if key_event.code == KeyCode::Up {
+              history.older(&typed)
+            } else {
+              history.newer()
+            };
+            if let Some(entry) = entry {
+              *state.command_line_mut() = entry;
+              if search {
+                let mut tree = wlock!(tree);
+                self.preview_search(state, &mut tree);
+              }
+            }
+          }
+          KeyCode::Char(c) => {
+            state.command_line_mut().push(c);
+            if search {
+              let mut tree = wlock!(tree);
+              self.preview_search(state, &mut tree);
+            }
+          }
+          _ => { /* Skip */ }
+        }
+      }
+    }
+
+    StatefulValue::CommandLineMode(CommandLineStateful::default())
+  }
+}
+
+impl CommandLineStateful {
+  // Execute the command line typed in the command-line mode, the error is shown in the message
+  // area.
+  fn execute(
+    &self,
+    state: &mut State,
+    tree: &TreeArc,
+    buffers: &BuffersManagerArc,
+    command_line: &str,
+  ) -> StatefulValue {
+    match self.execute_ex_command(state, tree, buffers, command_line) {
+      Ok(next_stateful) => next_stateful,
+      Err(e) => {
+        state.echoerr(&e.to_string());
+        StatefulValue::NormalMode(NormalStateful::default())
+      }
+    }
+  }
+
+  /// Execute the Ex command line, i.e. the command-line mode and `Rsvim.cmd`. The built-in
+  /// commands are handled here, except `:write` (and `:wq`, `:xit`) that fires the autocmds. It is
+  /// queued along with the user-defined commands, and dispatched by the event loop. The quit
+  /// commands only request to quit, it's decided by the event loop.
+  ///
+  /// It returns the next state, i.e. the command-line mode for confirming the matches of
+  /// `:substitute`.
+  ///
+  /// # Errors
+  ///
+  /// If the command is unknown, or it fails.
+  pub fn execute_ex_command(
+    &self,
+    state: &mut State,
+    tree: &TreeArc,
+    buffers: &BuffersManagerArc,
+    command_line: &str,
+  ) -> AnyResult<StatefulValue> {
+    let command_line = match ExCommandLine::parse(command_line) {
+      Some(command_line) => command_line,
+      None => return Ok(StatefulValue::NormalMode(NormalStateful::default())),
+    };
+    // The range without command name goes to the (last) line of it, i.e. `:{number}` and `:$`.
+    if let (Some(range), "") = (command_line.range(), command_line.name()) {
+      let mut tree = wlock!(tree);
+      let line_number = current_buffer(&tree).and_then(|buffer| {
+        let cursor = cursor_position(&tree)?;
+        let last_line_idx = rlock!(buffer).last_line_idx();
+        Some(range.end_line_idx(cursor.line_idx(), last_line_idx) + 1)
+      });
+      if let Some(line_number) = line_number {
+        goto_line(state, &mut tree, Some(line_number), true);
+      }
+      return Ok(StatefulValue::NormalMode(NormalStateful::default()));
+    }
+    match state.ex_commands().get(command_line.name()) {
+      Some(ExCommandHandler::Builtin(BuiltinExCommand::Quit)) => {
+        // The last window closes the tab page, the last tab page cannot be closed, it quits the
+        // editor.
+        let mut tree = wlock!(tree);
+        let closed = tree
+          .focused_window_id()
+          .is_some_and(|window_id| tree.close_window(window_id))
+          || tree.close_tab();
+        if closed {
+          // The focused window may be shrunk by 'equalalways'.
+          keep_cursor_visible(&mut tree);
+        } else {
+          state.request_quit(command_line.bang());
+        }
+      }
+      Some(ExCommandHandler::Builtin(BuiltinExCommand::Buffer)) => {
+        let mut tree = wlock!(tree);
+        self.buffer(&mut tree, buffers, &command_line)?;
+      }
+      Some(ExCommandHandler::Builtin(
+        command @ (BuiltinExCommand::BufferNext | BuiltinExCommand::BufferPrev),
+      )) => {
+        let mut tree = wlock!(tree);
+        let next_buffer = current_buffer(&tree).and_then(|buffer| {
+          let buf_id = rlock!(buffer).id();
+          let buffers = rlock!(buffers);
+          let next_buf_id = if command == BuiltinExCommand::BufferNext {
+            buffers.next_listed(&buf_id)
+          } else {
+            buffers.prev_listed(&buf_id)
+          };
+          next_buf_id.and_then(|id| buffers.get(&id).cloned())
+        });
+        if let Some(next_buffer) = next_buffer {
+          switch_buffer(&mut tree, &next_buffer);
+        }
+      }
+      Some(ExCommandHandler::Builtin(BuiltinExCommand::TabNew)) => {
+        let mut tree = wlock!(tree);
+        self.tab_new(&mut tree, buffers, &command_line)?;
+      }
+      Some(ExCommandHandler::Builtin(BuiltinExCommand::TabClose)) => {
+        // The last tab page cannot be closed, it quits the editor.
+        if !wlock!(tree).close_tab() {
+          state.request_quit(command_line.bang());
+        }
+      }
+      Some(ExCommandHandler::Builtin(BuiltinExCommand::Resize)) => {
+        let mut tree = wlock!(tree);
+        self.resize(&mut tree, SplitDirection::Horizontal, command_line.args())?;
+      }
+      Some(ExCommandHandler::Builtin(BuiltinExCommand::Vertical)) => {
+        // Only `:resize` has the vertical variant, the other commands ignore the modifier.
+        let modified = ExCommandLine::parse(command_line.args());
+        match modified {
+          Some(modified)
+            if state.ex_commands().get(modified.name())
+              == Some(ExCommandHandler::Builtin(BuiltinExCommand::Resize)) =>
+          {
+            let mut tree = wlock!(tree);
+            self.resize(&mut tree, SplitDirection::Vertical, modified.args())?;
+          }
+          _ => return self.execute_ex_command(state, tree, buffers, command_line.args()),
+        }
+      }
+      Some(ExCommandHandler::Builtin(BuiltinExCommand::Profile)) => {
+        let mut tree = wlock!(tree);
+        self.profile(state, &mut tree, buffers, &command_line)?;
+      }
+      Some(ExCommandHandler::Builtin(BuiltinExCommand::QuitAll)) => {
+        state.request_quit(command_line.bang());
+      }
+      Some(ExCommandHandler::Builtin(BuiltinExCommand::NoHlSearch)) => {
+        state.search_mut().set_hl_search(None);
+        sync_search_highlights(state, &rlock!(tree));
+      }
+      Some(ExCommandHandler::Builtin(BuiltinExCommand::Substitute)) => {
+        let mut tree = wlock!(tree);
+        return self.substitute(state, &mut tree, &command_line);
+      }
+      Some(ExCommandHandler::Builtin(BuiltinExCommand::Set)) => {
+        let options = parse_set_args(command_line.args())?;
+        let mut tree = wlock!(tree);
+        let mut buffers = wlock!(buffers);
+        for option in options {
+          set_option(
+            &mut tree,
+            &mut buffers,
+            option.scope,
+            option.name,
+            option.value,
+          )?;
+        }
+      }
+      Some(ExCommandHandler::Builtin(BuiltinExCommand::Edit)) => {
+        let mut tree = wlock!(tree);
+        self.edit(&mut tree, buffers, &command_line)?;
+      }
+      Some(ExCommandHandler::Builtin(BuiltinExCommand::Recover)) => {
+        let mut tree = wlock!(tree);
+        self.recover(&mut tree, buffers, &command_line)?;
+      }
+      Some(ExCommandHandler::Builtin(BuiltinExCommand::ColorScheme)) => {
+        let name = command_line.args();
+        if name.is_empty() {
+          let current_name = rlock!(ColorSchemes::global()).current_name().to_string();
+          state.echo(&current_name);
+        } else {
+          wlock!(ColorSchemes::global()).switch(name)?;
+        }
+      }
+      Some(ExCommandHandler::Builtin(
+        BuiltinExCommand::Write | BuiltinExCommand::WriteQuit | BuiltinExCommand::Exit,
+      ))
+      | Some(ExCommandHandler::User) => state.push_pending_ex_command(command_line),
+      None => anyhow::bail!("E492: Not an editor command: {}", command_line.name()),
+    }
+    Ok(StatefulValue::NormalMode(NormalStateful::default()))
+  }
+}
+
+// Search {
+impl CommandLineStateful {
+  /// Start typing the search pattern, i.e. `/`. The cursor position and the viewport are saved,
+  /// they're restored if the search is cancelled.
+  pub fn start_search(state: &mut State, tree: &Tree) {
+    state.command_line_mut().clear();
+    state.set_command_line_kind(CommandLineKind::Search);
+    state.search_mut().set_origin(search_origin(tree));
+  }
+
+  // Preview the first match of the typed pattern with 'incsearch', the viewport scrolls to show
+  // it. The visible matches are highlighted, and the first match is highlighted with `IncSearch`.
+  // If the pattern doesn't match (yet), the cursor and viewport go back to the origin, and the
+  // highlights are cleared. The invalid pattern is shown in the message area.
+  fn preview_search(&self, state: &mut State, tree: &mut Tree) {
+    if !tree.inc_search() {
+      return;
+    }
+    let (cursor, start_line_idx) = match state.search().origin() {
+      Some(origin) => origin,
+      None => return,
+    };
+    let pattern =
+      match try_compile_pattern(state.command_line(), tree.ignore_case(), tree.smart_case()) {
+        Ok(pattern) => pattern,
+        Err(e) => {
+          state.echoerr(&e.to_string());
+          None
+        }
+      };
+    let deadline = Instant::now() + INC_SEARCH_TIMEOUT;
+    let found = pattern.as_ref().and_then(|pattern| {
+      search_from(
+        tree,
+        pattern,
+        cursor,
+        SearchDirection::Forward,
+        Some(deadline),
+      )
+    });
+    match (pattern, found) {
+      (Some(pattern), Some((next_cursor, char_range))) => {
+        jump_cursor(tree, next_cursor, None);
+        set_window_search_highlights(tree, Some(&pattern), tree.hl_search(), Some(char_range));
+      }
+      _ => {
+        jump_cursor(tree, cursor, Some(start_line_idx));
+        set_window_search_highlights(tree, None, false, None);
+      }
+    }
+  }
+
+  // Commit the search, the cursor moves to the first match, and all the matches are highlighted
+  // with 'hlsearch' until `:nohlsearch`. The empty pattern uses the last pattern.
+  fn commit_search(&self, state: &mut State, tree: &mut Tree, pattern: &str) {
+    let (cursor, start_line_idx) = match state.search().origin() {
+      Some(origin) => origin,
+      None => return,
+    };
+    state.search_mut().set_origin(None);
+
+    let last_pattern = state.search().pattern().clone();
+    let pattern = match (pattern.is_empty(), last_pattern) {
+      (false, _) => pattern.to_string(),
+      (true, Some(last_pattern)) => last_pattern,
+      (true, None) => {
+        state.echoerr("E35: No previous regular expression");
+        jump_cursor(tree, cursor, Some(start_line_idx));
+        sync_search_highlights(state, tree);
+        return;
+      }
+    };
+    let regex = match try_compile_pattern(&pattern, tree.ignore_case(), tree.smart_case()) {
+      Ok(Some(regex)) => regex,
+      Ok(None) | Err(_) => {
+        state.echoerr(&format!("E383: Invalid search string: {}", pattern));
+        jump_cursor(tree, cursor, Some(start_line_idx));
+        sync_search_highlights(state, tree);
+        return;
+      }
+    };
+
+    let hl_search = tree.hl_search().then(|| regex.clone());
+    state.search_mut().set_hl_search(hl_search);
+    match search_from(tree, &regex, cursor, SearchDirection::Forward, None) {
+      Some((next_cursor, _)) => {
+        state.push_jump(tree, cursor);
+        jump_cursor(tree, next_cursor, None);
+      }
+      None => {
+        state.echoerr(&format!("E486: Pattern not found: {}", pattern));
+        jump_cursor(tree, cursor, Some(start_line_idx));
+      }
+    }
+    state.search_mut().set_pattern(Some(pattern));
+    state.search_mut().set_direction(SearchDirection::Forward);
+    sync_search_highlights(state, tree);
+  }
+
+  /// Repeat the last committed search `count` times, i.e. `n`, or in the opposite direction if
+  /// `reverse`, i.e. `N`. The matches are highlighted again with 'hlsearch' after `:nohlsearch`.
+  pub fn search_next(state: &mut State, tree: &mut Tree, reverse: bool, count: usize) {
+    let pattern = match state.search().pattern().clone() {
+      Some(pattern) => pattern,
+      None => {
+        state.echoerr("E35: No previous regular expression");
+        return;
+      }
+    };
+    let regex = match try_compile_pattern(&pattern, tree.ignore_case(), tree.smart_case()) {
+      Ok(Some(regex)) => regex,
+      Ok(None) | Err(_) => {
+        state.echoerr(&format!("E383: Invalid search string: {}", pattern));
+        return;
+      }
+    };
+    let cursor = match cursor_position(tree) {
+      Some(cursor) => cursor,
+      None => return,
+    };
+    let direction = if reverse {
+      state.search().direction().reverse()
+    } else {
+      state.search().direction()
+    };
+
+    let hl_search = tree.hl_search().then(|| regex.clone());
+    state.search_mut().set_hl_search(hl_search);
+    let mut next_cursor = cursor;
+    for _ in 0..std::cmp::max(count, 1) {
+      match search_from(tree, &regex, next_cursor, direction, None) {
+        Some((position, _)) => next_cursor = position,
+        None => {
+          state.echoerr(&format!("E486: Pattern not found: {}", pattern));
+          sync_search_highlights(state, tree);
+          return;
+        }
+      }
+    }
+    if next_cursor != cursor {
+      state.push_jump(tree, cursor);
+      jump_cursor(tree, next_cursor, None);
+    }
+    sync_search_highlights(state, tree);
+  }
+
+  // Cancel the search, the cursor and viewport go back to the origin, and the highlights of the
+  // last committed pattern are restored.
+  fn cancel_search(&self, state: &mut State, tree: &mut Tree) {
+    if let Some((cursor, start_line_idx)) = state.search().origin() {
+      jump_cursor(tree, cursor, Some(start_line_idx));
+    }
+    state.search_mut().set_origin(None);
+    sync_search_highlights(state, tree);
+  }
+}
+// Search }
+
+// Edit {
+impl CommandLineStateful {
+  // Reload the current buffer from its file, i.e. `:edit`, the changes are discarded with `!`.
+  // Editing another file is not supported yet.
+  fn edit(
+    &self,
+    tree: &mut Tree,
+    buffers: &BuffersManagerArc,
+    command_line: &ExCommandLine,
+  ) -> AnyResult<()> {
+    let args = command_line.args().trim_start_matches('!').trim();
+    if !args.is_empty() {
+      anyhow::bail!("E488: Trailing characters: {}", args);
+    }
+    let (buffer, cursor) = match (current_buffer(tree), cursor_position(tree)) {
+      (Some(buffer), Some(cursor)) => (buffer, cursor),
+      _ => return Ok(()),
+    };
+    let (buf_id, modified) = {
+      let buffer = rlock!(buffer);
+      (buffer.id(), buffer.is_modified())
+    };
+    if modified && !command_line.bang() {
+      anyhow::bail!("E37: No write since last change (add ! to override)");
+    }
+    rlock!(buffers).reload_buffer(&buf_id)?;
+    sync_viewport(tree);
+    let next_cursor = clamp_position(&rlock!(buffer), cursor);
+    sync_cursor(tree, cursor, next_cursor);
+    Ok(())
+  }
+
+  // Recover the current buffer from its recovery journal, i.e. `:recover`. The buffer contents
+  // are replaced and it's modified, unless there's no journal.
+  fn recover(
+    &self,
+    tree: &mut Tree,
+    buffers: &BuffersManagerArc,
+    command_line: &ExCommandLine,
+  ) -> AnyResult<()> {
+    let args = command_line.args().trim();
+    if !args.is_empty() {
+      anyhow::bail!("E488: Trailing characters: {}", args);
+    }
+    let (buffer, cursor) = match (current_buffer(tree), cursor_position(tree)) {
+      (Some(buffer), Some(cursor)) => (buffer, cursor),
+      _ => return Ok(()),
+    };
+    let buf_id = rlock!(buffer).id();
+    rlock!(buffers).recover_buffer(&buf_id)?;
+    sync_viewport(tree);
+    let next_cursor = clamp_position(&rlock!(buffer), cursor);
+    sync_cursor(tree, cursor, next_cursor);
+    Ok(())
+  }
+}
+// Edit }
+
+// Buffers {
+impl CommandLineStateful {
+  // Switch current window to the buffer `N`, i.e. `:buffer N`. Without argument, it stays in the
+  // current buffer.
+  fn buffer(
+    &self,
+    tree: &mut Tree,
+    buffers: &BuffersManagerArc,
+    command_line: &ExCommandLine,
+  ) -> AnyResult<()> {
+    let args = command_line.args();
+    if args.is_empty() {
+      return Ok(());
+    }
+    let buf_id = match args.parse::<BufferId>() {
+      Ok(buf_id) => buf_id,
+      Err(_) => anyhow::bail!("E94: No matching buffer for {}", args),
+    };
+    let buffer = match rlock!(buffers).get(&buf_id) {
+      Some(buffer) => buffer.clone(),
+      None => return Err(BufferErr::NotExist(buf_id).into()),
+    };
+    switch_buffer(tree, &buffer);
+    Ok(())
+  }
+}
+// Buffers }
+
+// Profile {
+impl CommandLineStateful {
+  // Show the render profile in a new scratch buffer in current window, i.e. `:profile`. The buffer
+  // is not modifiable, each `:profile` creates a new one with the latest profile.
+  fn profile(
+    &self,
+    state: &State,
+    tree: &mut Tree,
+    buffers: &BuffersManagerArc,
+    command_line: &ExCommandLine,
+  ) -> AnyResult<()> {
+    let args = command_line.args().trim();
+    if !args.is_empty() {
+      anyhow::bail!("E488: Trailing characters: {}", args);
+    }
+    let profile = state.render_profile();
+    if !profile.enabled() {
+      anyhow::bail!("E750: Profiling is not enabled, start the editor with --profile");
+    }
+    let mut report = profile.report().join("\n");
+    report.push('\n');
+
+    let buffer = {
+      let mut buffers = wlock!(buffers);
+      let buf_id = buffers.new_scratch_buffer("[Profile]");
+      buffers.get(&buf_id).unwrap().clone()
+    };
+    {
+      let mut buffer = wlock!(buffer);
+      buffer.insert(0, &report)?;
+      buffer.set_modifiable(false);
+    }
+    switch_buffer(tree, &buffer);
+    Ok(())
+  }
+}
+// Profile }
+
+// Tab pages {
+impl CommandLineStateful {
+  // Open a new tab page with the file, with its existing buffer if any, or a new empty buffer
+  // without argument, i.e. `:tabnew [file]`.
+  fn tab_new(
+    &self,
+    tree: &mut Tree,
+    buffers: &BuffersManagerArc,
+    command_line: &ExCommandLine,
+  ) -> AnyResult<()> {
+    let filename = command_line.args();
+    let buffer = {
+      let mut buffers = wlock!(buffers);
+      let buf_id = if filename.is_empty() {
+        buffers.new_empty_buffer()
+      } else {
+        let filename = Path::new(filename);
+        match buffers.find_file_buffer(filename) {
+          Some(buf_id) => buf_id,
+          None => buffers.new_file_buffer(filename)?,
+        }
+      };
+      buffers.get(&buf_id).unwrap().clone()
+    };
+    tree.new_tab(Arc::downgrade(&buffer));
+    Ok(())
+  }
+}
+// Tab pages }
+
+// Windows {
+impl CommandLineStateful {
+  // Set the height (or width if vertical) of current window, i.e. `:resize [+-]N`. Without
+  // argument, the window is as high (or wide) as possible.
+  fn resize(&self, tree: &mut Tree, direction: SplitDirection, args: &str) -> AnyResult<()> {
+    let window_id = match tree.focused_window_id() {
+      Some(window_id) => window_id,
+      None => return Ok(()),
+    };
+    let shape = *tree.node(&window_id).unwrap().shape();
+    let current = match direction {
+      SplitDirection::Horizontal => shape.height(),
+      SplitDirection::Vertical => shape.width(),
+    } as usize;
+    let size = if args.is_empty() {
+      Some(usize::MAX)
+    } else if let Some(n) = args.strip_prefix('+') {
+      n.parse::<usize>().ok().map(|n| current.saturating_add(n))
+    } else if let Some(n) = args.strip_prefix('-') {
+      n.parse::<usize>().ok().map(|n| current.saturating_sub(n))
+    } else {
+      args.parse::<usize>().ok()
+    };
+    let size = match size {
+      Some(size) => size,
+      None => anyhow::bail!("E475: Invalid argument: {}", args),
+    };
+    resize_window(tree, direction, size);
+    Ok(())
+  }
+}
+// Windows }
+
+// Substitute {
+impl CommandLineStateful {
+  // Execute the substitute command, i.e. `:{range}s/{pattern}/{replacement}/{flags}`, the range is
+  // the cursor line by default. The empty pattern uses the last search pattern, and the pattern
+  // becomes the last search pattern.
+  //
+  // With the `c` flag, it waits for confirming each match in command-line mode. Otherwise all the
+  // matches are replaced at once.
+  fn substitute(
+    &self,
+    state: &mut State,
+    tree: &mut Tree,
+    command_line: &ExCommandLine,
+  ) -> AnyResult<StatefulValue> {
+    let normal = StatefulValue::NormalMode(NormalStateful::default());
+    let (buffer, cursor) = match (current_buffer(tree), cursor_position(tree)) {
+      (Some(buffer), Some(cursor)) => (buffer, cursor),
+      _ => return Ok(normal),
+    };
+    let substitute = Substitute::parse(command_line.args())?;
+    let last_pattern = state.search().pattern().clone();
+    let pattern = match (substitute.pattern(), last_pattern) {
+      ("", Some(last_pattern)) => last_pattern,
+      ("", None) => anyhow::bail!("E35: No previous regular expression"),
+      (pattern, _) => pattern.to_string(),
+    };
+    let flags = substitute.flags();
+    let (ignore_case, smart_case) = match flags.ignore_case {
+      Some(ignore_case) => (ignore_case, false),
+      None => (tree.ignore_case(), tree.smart_case()),
+    };
+    let regex = match try_compile_pattern(&pattern, ignore_case, smart_case) {
+      Ok(Some(regex)) => regex,
+      Ok(None) | Err(_) => anyhow::bail!("E383: Invalid search string: {}", pattern),
+    };
+    let current = ExAddress::new(ExLineNumber::Current);
+    let range = command_line
+      .range()
+      .unwrap_or(ExRange::Lines(current, current));
+    let line_range = range.line_range(cursor.line_idx(), rlock!(buffer).last_line_idx())?;
+    state.search_mut().set_pattern(Some(pattern.clone()));
+
+    if flags.confirm {
+      let mut confirm = SubstituteConfirm::new(
+        regex,
+        substitute.replacement(),
+        flags.global,
+        line_range,
+        cursor,
+      );
+      if confirm.find_next(&rlock!(buffer)).is_none() {
+        anyhow::bail!("E486: Pattern not found: {}", pattern);
+      }
+      return Ok(self.next_confirm(state, tree, confirm));
+    }
+
+    let report = substitute_lines(
+      &mut wlock!(buffer),
+      line_range,
+      &regex,
+      substitute.replacement(),
+      flags.global,
+    )?;
+    if report.substitutions == 0 {
+      anyhow::bail!("E486: Pattern not found: {}", pattern);
+    }
+    self.finish_substitute(state, tree, report);
+    Ok(normal)
+  }
+
+  // Handle the key when confirming the current match:
+  //
+  // - `y`: replace this match.
+  // - `n`: skip this match.
+  // - `a`: replace this and all the remaining matches.
+  // - `l`: replace this match and quit, i.e. the "last" one.
+  // - `q` and `Esc`: quit.
+  //
+  // Other keys are ignored, it keeps waiting for the confirmation.
+  fn confirm_substitute(
+    &self,
+    state: &mut State,
+    tree: &mut Tree,
+    mut confirm: SubstituteConfirm,
+    key_code: KeyCode,
+  ) -> StatefulValue {
+    let buffer = match current_buffer(tree) {
+      Some(buffer) => buffer,
+      None => return StatefulValue::NormalMode(NormalStateful::default()),
+    };
+    let result = match key_code {
+      KeyCode::Char('y') | KeyCode::Char('l') => confirm.replace(&mut wlock!(buffer)),
+      KeyCode::Char('n') => {
+        confirm.skip();
+        Ok(())
+      }
+      KeyCode::Char('a') => {
+        let mut buffer = wlock!(buffer);
+        loop {
+          if let Err(e) = confirm.replace(&mut buffer) {
+            break Err(e);
+          }
+          if confirm.find_next(&buffer).is_none() {
+            break Ok(());
+          }
+        }
+      }
+      KeyCode::Char('q') | KeyCode::Esc => Ok(()),
+      _ => {
+        state.echo(&confirm_prompt(&confirm));
+        state.set_substitute_confirm(Some(confirm));
+        return StatefulValue::CommandLineMode(CommandLineStateful::default());
+      }
+    };
+    sync_viewport(tree);
+    if let Err(e) = result {
+      self.finish_confirm(state, tree, confirm);
+      state.echoerr(&e.to_string());
+      return StatefulValue::NormalMode(NormalStateful::default());
+    }
+    match key_code {
+      KeyCode::Char('y') | KeyCode::Char('n') => {
+        let found = confirm.find_next(&rlock!(buffer)).is_some();
+        if found {
+          return self.next_confirm(state, tree, confirm);
+        }
+        self.finish_confirm(state, tree, confirm)
+      }
+      _ => self.finish_confirm(state, tree, confirm),
+    }
+  }
+
+  // Move the cursor to the current match, and wait for confirming it.
+  fn next_confirm(
+    &self,
+    state: &mut State,
+    tree: &mut Tree,
+    confirm: SubstituteConfirm,
+  ) -> StatefulValue {
+    if let Some(position) = confirm.current_position() {
+      jump_cursor(tree, position, None);
+    }
+    state.echo(&confirm_prompt(&confirm));
+    state.set_substitute_confirm(Some(confirm));
     StatefulValue::CommandLineMode(CommandLineStateful::default())
   }
+
+  // Finish confirming the matches, the cursor goes back to the origin if nothing is replaced.
+  fn finish_confirm(
+    &self,
+    state: &mut State,
+    tree: &mut Tree,
+    confirm: SubstituteConfirm,
+  ) -> StatefulValue {
+    let report = confirm.report();
+    if report.substitutions > 0 {
+      self.finish_substitute(state, tree, report);
+    } else {
+      jump_cursor(tree, confirm.origin(), None);
+    }
+    StatefulValue::NormalMode(NormalStateful::default())
+  }
+
+  // Move the cursor to the first non-blank char of the last substituted line, and report the
+  // substitutions.
+  fn finish_substitute(&self, state: &mut State, tree: &mut Tree, report: SubstituteReport) {
+    sync_viewport(tree);
+    if let (Some(buffer), Some(line_idx)) = (current_buffer(tree), report.last_line_idx) {
+      let char_idx = rlock!(buffer).first_non_blank_char(line_idx);
+      jump_cursor(tree, BufferPosition::new(line_idx, char_idx), None);
+    }
+    state.echo(&report.message());
+  }
+}
+
+// The prompt for confirming the current match.
+fn confirm_prompt(confirm: &SubstituteConfirm) -> String {
+  format!("replace with {} (y/n/a/q/l)?", confirm.replacement())
+}
+// Substitute }
+
+// The cursor position and the viewport start line of current window.
+fn search_origin(tree: &Tree) -> Option<(BufferPosition, usize)> {
+  let cursor = cursor_position(tree)?;
+  let window_id = tree.focused_window_id()?;
+  match tree.node(&window_id) {
+    Some(TreeNode::Window(window)) => {
+      let start_line_idx = rlock!(window.viewport()).start_line_idx();
+      Some((cursor, start_line_idx))
+    }
+    _ => None,
+  }
+}
+
+// Search the pattern in the buffer of current window from the next (or previous if backward) char
+// of `cursor`, returns the position and the char range (based on the whole buffer) of the first
+// match.
+fn search_from(
+  tree: &Tree,
+  pattern: &Regex,
+  cursor: BufferPosition,
+  direction: SearchDirection,
+  deadline: Option<Instant>,
+) -> Option<(BufferPosition, Range<usize>)> {
+  let buffer = current_buffer(tree)?;
+  let buffer = rlock!(buffer);
+  let char_idx = buffer.line_col_to_char_clamped(cursor.line_idx(), cursor.char_idx());
+  let char_range = match direction {
+    SearchDirection::Forward => buffer.search(pattern, char_idx, deadline)?,
+    SearchDirection::Backward => buffer.search_backward(pattern, char_idx, deadline)?,
+  };
+  let (line_idx, char_idx) = buffer.char_to_line_col(char_range.start)?;
+  Some((BufferPosition::new(line_idx, char_idx), char_range))
+}
+
+// Set the search highlights of the visible lines of current window, see
+// [`set_search_highlights`]. The search highlights are cleared if `pattern` is `None`.
+fn set_window_search_highlights(
+  tree: &Tree,
+  pattern: Option<&Regex>,
+  hl_search: bool,
+  current: Option<Range<usize>>,
+) {
+  let (buffer, window_id) = match (current_buffer(tree), tree.focused_window_id()) {
+    (Some(buffer), Some(window_id)) => (buffer, window_id),
+    _ => return,
+  };
+  let mut buffer = wlock!(buffer);
+  match (pattern, tree.node(&window_id)) {
+    (Some(pattern), Some(TreeNode::Window(window))) => {
+      let line_range = {
+        let viewport = window.viewport();
+        let viewport = rlock!(viewport);
+        viewport.start_line_idx()..viewport.end_line_idx()
+      };
+      set_search_highlights(&mut buffer, line_range, pattern, hl_search, current);
+    }
+    _ => buffer.highlights_mut().clear_search(),
+  }
+}
+
+/// Sync the 'hlsearch' highlights of the last committed pattern to the visible lines of current
+/// window, or clear them after `:nohlsearch`. It's called before each render, thus the highlights
+/// follow the viewport. While typing the search pattern, the highlights are maintained by the
+/// 'incsearch' preview instead.
+pub(crate) fn sync_search_highlights(state: &State, tree: &Tree) {
+  if state.search().origin().is_none() {
+    set_window_search_highlights(tree, state.search().hl_search().as_ref(), true, None);
+  }
+}
+
+// Go to the first non-blank char of the line `count` (the out of range `count` stops at the last
+// line), or the first/last line without `count`, i.e. `gg`, `G` and `:{number}`. The cursor
+// position before it is recorded to the jump list, and the line is revealed by `reveal_cursor`.
+pub(crate) fn goto_line(state: &mut State, tree: &mut Tree, count: Option<usize>, last: bool) {
+  let buffer = match current_buffer(tree) {
+    Some(buffer) => buffer,
+    None => return,
+  };
+  let cursor = match cursor_position(tree) {
+    Some(cursor) => cursor,
+    None => return,
+  };
+  let next_cursor = goto_line_target(&rlock!(buffer), count, last);
+  state.push_jump(tree, cursor);
+  if next_cursor != cursor {
+    reveal_cursor(tree, next_cursor);
+  }
+}
+
+// Move the cursor to the position and scroll the viewport to reveal it, close to Vim:
+//
+// 1. If the line is inside the viewport, the viewport doesn't scroll.
+// 2. If the line is outside of the viewport, but no more than a page (the viewport height) away
+//    from it, the viewport scrolls the minimal lines, i.e. the line is put at the top if it's
+//    above, or at the bottom if it's below (with 'scrolloff').
+// 3. Otherwise the line is put at the center, see `jump_cursor`.
+pub(crate) fn reveal_cursor(tree: &mut Tree, next_cursor: BufferPosition) {
+  let line_idx = next_cursor.line_idx();
+  let start_line_idx = match tree.focused_window_id().and_then(|id| tree.node(&id)) {
+    Some(TreeNode::Window(window)) => {
+      let viewport = window.viewport();
+      let viewport = rlock!(viewport);
+      let height = viewport.actual_shape().height() as usize;
+      let (start, end) = (viewport.start_line_idx(), viewport.end_line_idx());
+      if line_idx < start && start - line_idx <= height {
+        Some(viewport.start_line_for(line_idx, CursorLinePosition::Top))
+      } else if line_idx >= end && line_idx - end < height {
+        Some(viewport.start_line_for(line_idx, CursorLinePosition::Bottom))
+      } else {
+        None
+      }
+    }
+    _ => None,
+  };
+  jump_cursor(tree, next_cursor, start_line_idx);
+}
+
+// Move the cursor to the position, the cursor widget moves along with the cursor viewport.
+//
+// If `start_line_idx` is provided, the viewport scrolls to it first, i.e. restores the saved
+// viewport. Otherwise the viewport only scrolls if the position is outside of it, and the cursor
+// line is put at the center.
+pub(crate) fn jump_cursor(
+  tree: &mut Tree,
+  next_cursor: BufferPosition,
+  start_line_idx: Option<usize>,
+) {
+  let window_id = match tree.focused_window_id() {
+    Some(window_id) => window_id,
+    None => return,
+  };
+  let (line_idx, char_idx) = (next_cursor.line_idx(), next_cursor.char_idx());
+  let (cols, rows) = match tree.node(&window_id) {
+    Some(TreeNode::Window(window)) => {
+      let viewport = window.viewport();
+      let mut viewport = wlock!(viewport);
+      let cursor_viewport = *viewport.cursor();
+      if let Some(start_line_idx) = start_line_idx {
+        if viewport.start_line_idx() != start_line_idx {
+          viewport.sync_from_top_left(start_line_idx, 0);
+        }
+      }
+      let mut next_cursor_viewport = viewport.cursor_viewport_at(line_idx, char_idx);
+      if next_cursor_viewport.is_none() && start_line_idx.is_none() {
+        viewport.sync_from_top_left(line_idx, 0);
+        if let Some(centered_cursor_viewport) = viewport.cursor_viewport_at(line_idx, char_idx) {
+          viewport.set_cursor(centered_cursor_viewport);
+          viewport.scroll_cursor_line_to(CursorLinePosition::Center);
+          next_cursor_viewport = Some(*viewport.cursor());
+        }
+      }
+      match next_cursor_viewport {
+        Some(next_cursor_viewport) => {
+          viewport.set_cursor(next_cursor_viewport);
+          (
+            next_cursor_viewport.start_dcol_idx() as isize
+              - cursor_viewport.start_dcol_idx() as isize,
+            next_cursor_viewport.row_idx() as isize - cursor_viewport.row_idx() as isize,
+          )
+        }
+        None => return,
+      }
+    }
+    _ => return,
+  };
+  if let Some(cursor_id) = tree.cursor_id() {
+    tree.bounded_move_by(cursor_id, cols, rows);
+  }
+}
+
+/// Set the height (or width) of current window, see [`Tree::resize_window`]. The cursor is kept
+/// inside the window, i.e. the viewport scrolls if the cursor line is outside of it.
+pub(crate) fn resize_window(tree: &mut Tree, direction: SplitDirection, size: usize) {
+  if let Some(window_id) = tree.focused_window_id() {
+    if tree.resize_window(window_id, direction, size) {
+      keep_cursor_visible(tree);
+    }
+  }
+}
+
+/// Make the windows equally high and wide, see [`Tree::equalize_windows`].
+pub(crate) fn equalize_windows(tree: &mut Tree) {
+  tree.equalize_windows();
+  keep_cursor_visible(tree);
+}
+
+// Move the cursor widget to the cursor position after current window is resized.
+fn keep_cursor_visible(tree: &mut Tree) {
+  if let Some(cursor) = cursor_position(tree) {
+    jump_cursor(tree, cursor, None);
+  }
+}
+
+/// Switch current window to the buffer, i.e. `:buffer`. The view state of the previous buffer is
+/// saved in the window, and restored when the window switches back to it. Otherwise the cursor
+/// moves to the first line. See [`Window::set_buffer`].
+pub(crate) fn switch_buffer(tree: &mut Tree, buffer: &BufferArc) {
+  update_focused_window(tree, |window| window.set_buffer(Arc::downgrade(buffer)));
+}
+
+/// Move the focus to the next/previous window, or the window in the direction, i.e. `Ctrl-W w`/
+/// `Ctrl-W W` and `Ctrl-W h/j/k/l`. The view state of the window is restored, see
+/// [`Window::restore_view_state`].
+pub(crate) fn move_window_focus(tree: &mut Tree, direction: FocusDirection) {
+  if tree.move_focus(direction).is_some() {
+    update_focused_window(tree, |window| window.restore_view_state());
+  }
+}
+
+/// Update the focused window, and move the cursor widget along with the cursor in its viewport.
+pub(crate) fn update_focused_window(tree: &mut Tree, f: impl FnOnce(&mut Window)) {
+  let window_id = match tree.focused_window_id() {
+    Some(window_id) => window_id,
+    None => return,
+  };
+  let (cols, rows) = match tree.node_mut(&window_id) {
+    Some(TreeNode::Window(window)) => {
+      let viewport = window.viewport();
+      let cursor_viewport = *rlock!(viewport).cursor();
+      f(window);
+      let next_cursor_viewport = *rlock!(viewport).cursor();
+      (
+        next_cursor_viewport.start_dcol_idx() as isize - cursor_viewport.start_dcol_idx() as isize,
+        next_cursor_viewport.row_idx() as isize - cursor_viewport.row_idx() as isize,
+      )
+    }
+    _ => return,
+  };
+  if let Some(cursor_id) = tree.cursor_id() {
+    tree.bounded_move_by(cursor_id, cols, rows);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use crate::buf::highlight::DEFAULT_COLOR_SCHEME;
+  use crate::buf::{BufferArc, BuffersManager, HighlightSpan};
+  use crate::cart::U16Size;
+  use crate::state::autocmd::AutocmdContext;
+  use crate::state::message::Message;
+  use crate::state::mode::Mode;
+  use crate::state::quit::QuitRequest;
+  use crate::test::render::snapshot;
+  use crate::test::tree::{make_tree, make_window_tree};
+  use crate::ui::tree::TreeArc;
+
+  use crossterm::event::{KeyEvent, KeyModifiers};
+
+  fn type_keys(state: &mut State, keys: &str) {
+    let tree = Tree::to_arc(Tree::new(U16Size::new(20, 10)));
+    type_keys_in(state, &tree, keys);
+  }
+
+  fn type_keys_in(state: &mut State, tree: &TreeArc, keys: &str) {
+    let buffers = BuffersManager::to_arc(BuffersManager::new());
+    for c in keys.chars() {
+      let code = match c {
+        '\n' => KeyCode::Enter,
+        '\x08' => KeyCode::Backspace,
+        '\x1b' => KeyCode::Esc,
+        '↑' => KeyCode::Up,
+        '↓' => KeyCode::Down,
+        c => KeyCode::Char(c),
+      };
+      let event = Event::Key(KeyEvent::new(code, KeyModifiers::NONE));
+      state.handle(tree.clone(), buffers.clone(), event);
+    }
+  }
+
+  // The cursor position, viewport start line of current window, and the row of the cursor widget.
+  fn search_status(tree: &TreeArc) -> (BufferPosition, usize, isize) {
+    let tree = rlock!(tree);
+    let (cursor, start_line_idx) = search_origin(&tree).unwrap();
+    let cursor_id = tree.cursor_id().unwrap();
+    let cursor_row = tree.node(&cursor_id).unwrap().shape().min().y;
+    (cursor, start_line_idx, cursor_row)
+  }
+
+  // The search highlight spans of all the lines.
+  fn search_spans(buffer: &BufferArc) -> Vec<(usize, Vec<HighlightSpan>)> {
+    let buffer = rlock!(buffer);
+    (0..buffer.len_lines())
+      .filter_map(|line_idx| {
+        let spans = buffer.highlights().search(line_idx)?;
+        Some((line_idx, spans.clone()))
+      })
+      .collect()
+  }
+
+  #[test]
+  fn user_command1() {
+    let mut state = State::default();
+    state
+      .ex_commands_mut()
+      .create_user_command("Format", true)
+      .unwrap();
+
+    type_keys(&mut state, ":");
+    assert!(matches!(
+      state.stateful(),
+      StatefulValue::CommandLineMode(_)
+    ));
+    type_keys(&mut state, "Formax\x08t a  b");
+    assert_eq!(state.command_line(), "Format a  b");
+    type_keys(&mut state, "\n");
+    assert!(matches!(state.stateful(), StatefulValue::NormalMode(_)));
+    assert!(state.command_line().is_empty());
+    assert_eq!(
+      state.take_pending_ex_commands(),
+      vec![ExCommandLine::new("Format", "a  b")]
+    );
+    assert!(state.take_pending_ex_commands().is_empty());
+    assert!(state.message().is_none());
+  }
+
+  #[test]
+  fn unknown_command1() {
+    let mut state = State::default();
+    type_keys(&mut state, ":Unknown\n");
+    assert!(matches!(state.stateful(), StatefulValue::NormalMode(_)));
+    assert!(state.take_pending_ex_commands().is_empty());
+    assert_eq!(
+      state.message().clone(),
+      Some(Message::error("E492: Not an editor command: Unknown"))
+    );
+
+    // The message is cleared on the next key.
+    type_keys(&mut state, "j");
+    assert!(state.message().is_none());
+  }
+
+  #[test]
+  fn write1() {
+    let mut state = State::default();
+    type_keys(&mut state, ":w\n");
+    assert!(matches!(state.stateful(), StatefulValue::NormalMode(_)));
+    assert_eq!(
+      state.take_pending_ex_commands(),
+      vec![ExCommandLine::new("w", "")]
+    );
+    assert!(state.message().is_none());
+  }
+
+  #[test]
+  fn history1() {
+    let mut state = State::default();
+    type_keys(&mut state, ":w\n:set wrap\n:w\n:w\n");
+    assert_eq!(
+      state.cmd_histories().get(CommandLineKind::Ex).entries(),
+      &["w", "set wrap", "w"]
+    );
+
+    // Recall the entries, the typed text is the newest position.
+    type_keys(&mut state, ":↑");
+    assert_eq!(state.command_line(), "w");
+    type_keys(&mut state, "↑");
+    assert_eq!(state.command_line(), "set wrap");
+    type_keys(&mut state, "↓↓");
+    assert_eq!(state.command_line(), "");
+    type_keys(&mut state, "\x1b");
+
+    // Filtered by the typed prefix.
+    type_keys(&mut state, ":s↑");
+    assert_eq!(state.command_line(), "set wrap");
+    type_keys(&mut state, "↑");
+    assert_eq!(state.command_line(), "set wrap");
+    type_keys(&mut state, "↓");
+    assert_eq!(state.command_line(), "s");
+
+    // The search patterns have their own history.
+    type_keys(&mut state, "\x1b/↑");
+    assert_eq!(state.command_line(), "");
+    assert!(matches!(
+      state.stateful(),
+      StatefulValue::CommandLineMode(_)
+    ));
+  }
+
+  #[test]
+  fn mode_changed1() {
+    let mut state = State::default();
+    type_keys(&mut state, ":q\x08\x08");
+    assert_eq!(
+      state.take_pending_autocmds(),
+      vec![
+        AutocmdContext::mode_changed(Mode::Normal, Mode::CommandLine),
+        AutocmdContext::mode_changed(Mode::CommandLine, Mode::Normal),
+      ]
+    );
+    assert!(state.take_pending_autocmds().is_empty());
+  }
+
+  #[test]
+  fn quit1() {
+    let mut state = State::default();
+    type_keys(&mut state, ":q\n");
+    assert!(matches!(state.stateful(), StatefulValue::NormalMode(_)));
+    assert_eq!(state.take_quit_request(), Some(QuitRequest::new(false)));
+
+    let mut state = State::default();
+    type_keys(&mut state, ":q\x08\x08");
+    assert!(matches!(state.stateful(), StatefulValue::NormalMode(_)));
+    assert_eq!(state.take_quit_request(), None);
+
+    for (keys, force) in [(":q!\n", true), (":qa\n", false), (":qall!\n", true)] {
+      let mut state = State::default();
+      type_keys(&mut state, keys);
+      assert_eq!(state.take_quit_request(), Some(QuitRequest::new(force)));
+    }
+  }
+
+  #[test]
+  fn quit2() {
+    // The modified buffer rejects the quit request, but not the forced one.
+    let (tree, _buffer) = make_tree(vec!["Hello\n"]);
+    let tmpdir = assert_fs::TempDir::new().unwrap();
+    let mut buffers = BuffersManager::new();
+    let buf_id = buffers
+      .new_file_buffer(&tmpdir.path().join("quit2.txt"))
+      .unwrap();
+    wlock!(buffers.get(&buf_id).unwrap())
+      .insert(0, "Hello")
+      .unwrap();
+    let mut state = State::default();
+    type_keys_in(&mut state, &tree, ":q\n");
+    let request = state.take_quit_request().unwrap();
+    assert_eq!(
+      request.accept(&buffers).unwrap_err().to_string(),
+      "E37: No write since last change (add ! to override)"
+    );
+    type_keys_in(&mut state, &tree, ":q!\n");
+    let request = state.take_quit_request().unwrap();
+    assert!(request.accept(&buffers).is_ok());
+
+    // The `:wq` and `:xit` are dispatched by the event loop, since they write the buffer.
+    for keys in [":wq\n", ":x\n"] {
+      type_keys_in(&mut state, &tree, keys);
+      assert_eq!(state.take_quit_request(), None);
+      assert_eq!(state.take_pending_ex_commands().len(), 1);
+    }
+  }
+
+  #[test]
+  fn buffer1() {
+    let tmpdir = assert_fs::TempDir::new().unwrap();
+    let mut buffers = BuffersManager::new();
+    let buf_ids: Vec<BufferId> = ["a", "b"]
+      .iter()
+      .map(|name| {
+        let path = tmpdir.path().join(format!("{}.txt", name));
+        let lines: String = (0..30).map(|i| format!("{} {}\n", name, i)).collect();
+        std::fs::write(&path, lines).unwrap();
+        buffers.new_file_buffer(&path).unwrap()
+      })
+      .collect();
+    let tree = make_window_tree(buffers.get(&buf_ids[0]).unwrap());
+    let buffers = BuffersManager::to_arc(buffers);
+    let mut state = State::default();
+    let mut execute = |command_line: &str| {
+      CommandLineStateful::default().execute_ex_command(&mut state, &tree, &buffers, command_line)
+    };
+    let rows = |name: &str, start_line_idx: usize| -> Vec<String> {
+      (start_line_idx..start_line_idx + 10)
+        .map(|i| format!("{:<20}", format!("{} {}", name, i)))
+        .collect()
+    };
+
+    let mut keys_state = State::default();
+    type_keys_in(&mut keys_state, &tree, "20G");
+    let saved = search_status(&tree);
+    assert_eq!(saved.0, BufferPosition::new(19, 0));
+
+    // The new buffer starts from the first line.
+    execute(&format!("b {}", buf_ids[1])).unwrap();
+    assert_eq!(snapshot(&rlock!(tree), U16Size::new(20, 10)), rows("b", 0));
+    assert_eq!(search_status(&tree), (BufferPosition::new(0, 0), 0, 0));
+
+    // Switch back to the first buffer, the cursor and viewport are restored.
+    execute("bn").unwrap();
+    assert_eq!(search_status(&tree), saved);
+    assert_eq!(
+      snapshot(&rlock!(tree), U16Size::new(20, 10)),
+      rows("a", saved.1)
+    );
+    execute("bp").unwrap();
+    assert_eq!(search_status(&tree), (BufferPosition::new(0, 0), 0, 0));
+
+    assert_eq!(
+      execute("b 0").unwrap_err().to_string(),
+      "E86: Buffer 0 does not exist"
+    );
+    assert_eq!(
+      execute("b foo").unwrap_err().to_string(),
+      "E94: No matching buffer for foo"
+    );
+  }
+
+  #[test]
+  fn tab1() {
+    let (tree, _buffer) = make_tree(vec!["Hello\n"]);
+    let mut state = State::default();
+    type_keys_in(&mut state, &tree, ":tabnew\n:tabnew\n");
+    assert_eq!(rlock!(tree).tab_count(), 3);
+    assert_eq!(rlock!(tree).active_tab(), 2);
+    type_keys_in(&mut state, &tree, ":tabc\n");
+    assert_eq!(rlock!(tree).tab_count(), 2);
+    assert_eq!(rlock!(tree).active_tab(), 1);
+
+    // The last window closes the tab page.
+    type_keys_in(&mut state, &tree, ":q\n");
+    assert_eq!(rlock!(tree).tab_count(), 1);
+    assert_eq!(state.take_quit_request(), None);
+
+    // The last tab page quits the editor.
+    type_keys_in(&mut state, &tree, ":tabclose!\n");
+    assert_eq!(rlock!(tree).tab_count(), 1);
+    assert_eq!(state.take_quit_request(), Some(QuitRequest::new(true)));
+  }
+
+  #[test]
+  fn quit3() {
+    // Close the current window, unless it's the last window.
+    let (tree, _buffer) = make_tree(vec!["Hello\n"]);
+    {
+      let mut tree = wlock!(tree);
+      let window_id = tree.focused_window_id().unwrap();
+      tree
+        .split_window(window_id, SplitDirection::Horizontal)
+        .unwrap();
+    }
+    assert_eq!(rlock!(tree).window_ids().len(), 2);
+    let mut state = State::default();
+    type_keys_in(&mut state, &tree, ":q\n");
+    assert_eq!(rlock!(tree).window_ids().len(), 1);
+    assert_eq!(state.take_quit_request(), None);
+
+    type_keys_in(&mut state, &tree, ":q\n");
+    assert_eq!(rlock!(tree).window_ids().len(), 1);
+    assert_eq!(state.take_quit_request(), Some(QuitRequest::new(false)));
+  }
+  #[test]
+  fn resize1() {
+    let (tree, _buffer) = make_tree(vec!["Hello\n"]);
+    let (window1_id, window2_id) = {
+      let mut tree = wlock!(tree);
+      let window1_id = tree.focused_window_id().unwrap();
+      let window2_id = tree
+        .split_window(window1_id, SplitDirection::Vertical)
+        .unwrap();
+      (window1_id, window2_id)
+    };
+    let sizes = |tree: &TreeArc| {
+      let tree = rlock!(tree);
+      let size = |id| {
+        let shape = tree.node(&id).unwrap().shape();
+        (shape.width(), shape.height())
+      };
+      (size(window2_id), size(window1_id))
+    };
+    let mut state = State::default();
+
+    // The windows are side by side, their heights cannot change.
+    type_keys_in(&mut state, &tree, ":resize 3\n");
+    assert_eq!(sizes(&tree), ((10, 10), (10, 10)));
+    type_keys_in(&mut state, &tree, ":vertical resize 6\n");
+    assert_eq!(sizes(&tree), ((6, 10), (14, 10)));
+    type_keys_in(&mut state, &tree, ":vert res +2\n");
+    assert_eq!(sizes(&tree), ((8, 10), (12, 10)));
+    type_keys_in(&mut state, &tree, ":vert res\n");
+    assert_eq!(sizes(&tree), ((19, 10), (1, 10)));
+
+    let mut execute = |command_line: &str| {
+      let buffers = BuffersManager::to_arc(BuffersManager::new());
+      CommandLineStateful::default().execute_ex_command(&mut state, &tree, &buffers, command_line)
+    };
+    assert_eq!(
+      execute("resize x").unwrap_err().to_string(),
+      "E475: Invalid argument: x"
+    );
+  }
+
+  #[test]
+  fn inc_search1() {
+    let lines: Vec<String> = (0..40).map(|i| format!("line {}\n", i)).collect();
+    let (tree, buffer) = make_tree(lines.iter().map(|l| l.as_str()).collect());
+    let mut state = State::default();
+
+    type_keys_in(&mut state, &tree, "/");
+    assert!(matches!(
+      state.stateful(),
+      StatefulValue::CommandLineMode(_)
+    ));
+    assert_eq!(state.command_line_kind(), CommandLineKind::Search);
+
+    // The previewed match updates on each char, only the visible lines are highlighted.
+    type_keys_in(&mut state, &tree, "l");
+    assert_eq!(search_status(&tree), (BufferPosition::new(1, 0), 0, 1));
+    assert_eq!(
+      search_spans(&buffer),
+      (0..10)
+        .map(|line_idx| match line_idx {
+          1 => (line_idx, vec![HighlightSpan::inc_search(0, 1)]),
+          _ => (line_idx, vec![HighlightSpan::search(0, 1)]),
+        })
+        .collect::<Vec<_>>()
+    );
+    type_keys_in(&mut state, &tree, "ine 3");
+    assert_eq!(search_status(&tree), (BufferPosition::new(3, 0), 0, 3));
+    assert_eq!(
+      search_spans(&buffer),
+      vec![(3, vec![HighlightSpan::inc_search(0, 6)])]
+    );
+    // The viewport scrolls to put the match line at the center.
+    type_keys_in(&mut state, &tree, "0");
+    assert_eq!(search_status(&tree), (BufferPosition::new(30, 0), 26, 4));
+    assert_eq!(
+      search_spans(&buffer),
+      vec![(30, vec![HighlightSpan::inc_search(0, 7)])]
+    );
+    // No match, back to the origin.
+    type_keys_in(&mut state, &tree, "x");
+    assert_eq!(search_status(&tree), (BufferPosition::new(0, 0), 0, 0));
+    assert!(search_spans(&buffer).is_empty());
+    type_keys_in(&mut state, &tree, "\x08");
+    assert_eq!(search_status(&tree), (BufferPosition::new(30, 0), 26, 4));
+    assert_eq!(
+      search_spans(&buffer),
+      vec![(30, vec![HighlightSpan::inc_search(0, 7)])]
+    );
+
+    // Cancel, back to the origin.
+    type_keys_in(&mut state, &tree, "\x1b");
+    assert!(matches!(state.stateful(), StatefulValue::NormalMode(_)));
+    assert_eq!(search_status(&tree), (BufferPosition::new(0, 0), 0, 0));
+    assert!(search_spans(&buffer).is_empty());
+    assert!(state.search().pattern().is_none());
+  }
+
+  #[test]
+  fn inc_search2() {
+    let (tree, buffer) = make_tree(vec!["foo bar\n", "bar foo\n", "baz\n"]);
+    let mut state = State::default();
+
+    // Commit the search, the matches are highlighted with 'hlsearch'.
+    type_keys_in(&mut state, &tree, "/foo\n");
+    assert!(matches!(state.stateful(), StatefulValue::NormalMode(_)));
+    assert_eq!(search_status(&tree), (BufferPosition::new(1, 4), 0, 1));
+    assert_eq!(state.search().pattern().as_deref(), Some("foo"));
+    assert_eq!(state.search().hl_search().as_ref().unwrap().as_str(), "foo");
+    assert_eq!(
+      search_spans(&buffer),
+      vec![
+        (0, vec![HighlightSpan::search(0, 3)]),
+        (1, vec![HighlightSpan::search(4, 7)])
+      ]
+    );
+
+    // The empty pattern uses the last pattern, wraps around the end of buffer.
+    type_keys_in(&mut state, &tree, "/\n");
+    assert_eq!(search_status(&tree), (BufferPosition::new(0, 0), 0, 0));
+
+    // Pattern not found.
+    type_keys_in(&mut state, &tree, "/qux\n");
+    assert_eq!(search_status(&tree), (BufferPosition::new(0, 0), 0, 0));
+    assert_eq!(
+      state.message().clone(),
+      Some(Message::error("E486: Pattern not found: qux"))
+    );
+
+    // Without 'incsearch', the cursor doesn't move while typing.
+    wlock!(tree).set_inc_search(false);
+    type_keys_in(&mut state, &tree, "/baz");
+    assert_eq!(search_status(&tree), (BufferPosition::new(0, 0), 0, 0));
+    type_keys_in(&mut state, &tree, "\n");
+    assert_eq!(search_status(&tree), (BufferPosition::new(2, 0), 0, 2));
+  }
+
+  #[test]
+  fn inc_search3() {
+    let (tree, buffer) = make_tree(vec!["foo bar\n", "bar foo\n", "baz\n"]);
+    let mut state = State::default();
+
+    // The highlights are updated on each keystroke, the closest match after the cursor is
+    // highlighted with `IncSearch`.
+    type_keys_in(&mut state, &tree, "/b");
+    assert_eq!(
+      search_spans(&buffer),
+      vec![
+        (0, vec![HighlightSpan::inc_search(4, 5)]),
+        (1, vec![HighlightSpan::search(0, 1)]),
+        (2, vec![HighlightSpan::search(0, 1)])
+      ]
+    );
+    type_keys_in(&mut state, &tree, "a");
+    assert_eq!(
+      search_spans(&buffer),
+      vec![
+        (0, vec![HighlightSpan::inc_search(4, 6)]),
+        (1, vec![HighlightSpan::search(0, 2)]),
+        (2, vec![HighlightSpan::search(0, 2)])
+      ]
+    );
+    type_keys_in(&mut state, &tree, "z");
+    assert_eq!(search_status(&tree), (BufferPosition::new(2, 0), 0, 2));
+    assert_eq!(
+      search_spans(&buffer),
+      vec![(2, vec![HighlightSpan::inc_search(0, 3)])]
+    );
+
+    // The invalid pattern is shown in the message area, and the highlights are cleared.
+    type_keys_in(&mut state, &tree, "(");
+    assert!(matches!(
+      state.stateful(),
+      StatefulValue::CommandLineMode(_)
+    ));
+    assert_eq!(
+      state.message().clone(),
+      Some(Message::error("E383: Invalid search string: baz("))
+    );
+    assert_eq!(search_status(&tree), (BufferPosition::new(0, 0), 0, 0));
+    assert!(search_spans(&buffer).is_empty());
+    type_keys_in(&mut state, &tree, "\x08");
+    assert!(state.message().is_none());
+    assert_eq!(
+      search_spans(&buffer),
+      vec![(2, vec![HighlightSpan::inc_search(0, 3)])]
+    );
+
+    // Cancel, the highlights are cleared.
+    type_keys_in(&mut state, &tree, "\x1b");
+    assert!(search_spans(&buffer).is_empty());
+
+    // Commit, the highlights are kept until `:nohlsearch`.
+    type_keys_in(&mut state, &tree, "/bar\n");
+    assert_eq!(search_status(&tree), (BufferPosition::new(0, 4), 0, 0));
+    let bar_spans = vec![
+      (0, vec![HighlightSpan::search(4, 7)]),
+      (1, vec![HighlightSpan::search(0, 3)]),
+    ];
+    assert_eq!(search_spans(&buffer), bar_spans);
+    type_keys_in(&mut state, &tree, ":noh\n");
+    assert!(state.message().is_none());
+    assert!(state.search().hl_search().is_none());
+    assert!(search_spans(&buffer).is_empty());
+
+    // The next search highlights again.
+    type_keys_in(&mut state, &tree, "/\n");
+    assert_eq!(search_status(&tree), (BufferPosition::new(1, 0), 0, 1));
+    assert_eq!(search_spans(&buffer), bar_spans);
+
+    // Typing another pattern replaces the highlights, cancel restores the last ones.
+    type_keys_in(&mut state, &tree, "/foo");
+    assert_eq!(
+      search_spans(&buffer),
+      vec![
+        (0, vec![HighlightSpan::search(0, 3)]),
+        (1, vec![HighlightSpan::inc_search(4, 7)])
+      ]
+    );
+    type_keys_in(&mut state, &tree, "\x1b");
+    assert_eq!(search_status(&tree), (BufferPosition::new(1, 0), 0, 1));
+    assert_eq!(search_spans(&buffer), bar_spans);
+  }
+
+  #[test]
+  fn search_next1() {
+    let (tree, buffer) = make_tree(vec!["foo bar\n", "baz foo\n", "foo\n"]);
+    let mut state = State::default();
+
+    type_keys_in(&mut state, &tree, "n");
+    assert_eq!(
+      state.message().clone(),
+      Some(Message::error("E35: No previous regular expression"))
+    );
+
+    type_keys_in(&mut state, &tree, "/foo\n");
+    assert_eq!(search_status(&tree).0, BufferPosition::new(1, 4));
+    assert_eq!(state.search().direction(), SearchDirection::Forward);
+    type_keys_in(&mut state, &tree, "n");
+    assert_eq!(search_status(&tree).0, BufferPosition::new(2, 0));
+    // Wrap around the end of buffer.
+    type_keys_in(&mut state, &tree, "n");
+    assert_eq!(search_status(&tree).0, BufferPosition::new(0, 0));
+    // Wrap around the start of buffer, the direction is kept.
+    type_keys_in(&mut state, &tree, "N");
+    assert_eq!(search_status(&tree).0, BufferPosition::new(2, 0));
+    type_keys_in(&mut state, &tree, "N");
+    assert_eq!(search_status(&tree).0, BufferPosition::new(1, 4));
+    assert_eq!(state.search().direction(), SearchDirection::Forward);
+    type_keys_in(&mut state, &tree, "2n");
+    assert_eq!(search_status(&tree).0, BufferPosition::new(0, 0));
+
+    // `:nohlsearch` clears the highlights but keeps the pattern, `n` highlights again.
+    type_keys_in(&mut state, &tree, ":noh\n");
+    assert!(search_spans(&buffer).is_empty());
+    assert_eq!(state.search().pattern().as_deref(), Some("foo"));
+    type_keys_in(&mut state, &tree, "n");
+    assert_eq!(search_status(&tree).0, BufferPosition::new(1, 4));
+    assert_eq!(
+      search_spans(&buffer),
+      vec![
+        (0, vec![HighlightSpan::search(0, 3)]),
+        (1, vec![HighlightSpan::search(4, 7)]),
+        (2, vec![HighlightSpan::search(0, 3)]),
+      ]
+    );
+
+    type_keys_in(&mut state, &tree, "/qux\n");
+    type_keys_in(&mut state, &tree, "n");
+    assert_eq!(
+      state.message().clone(),
+      Some(Message::error("E486: Pattern not found: qux"))
+    );
+    assert_eq!(search_status(&tree).0, BufferPosition::new(1, 4));
+  }
+
+  fn buffer_text(buffer: &BufferArc) -> String {
+    rlock!(buffer).lines().map(|l| l.to_string()).collect()
+  }
+
+  #[test]
+  fn set1() {
+    let (tree, _buffer) = make_tree(vec!["foo\n"]);
+    let mut state = State::default();
+
+    type_keys_in(&mut state, &tree, ":set nowrap ic\n");
+    assert!(matches!(state.stateful(), StatefulValue::NormalMode(_)));
+    assert!(state.message().is_none());
+    assert!(!rlock!(tree).wrap());
+    assert!(rlock!(tree).ignore_case());
+
+    type_keys_in(&mut state, &tree, ":se number\n");
+    assert_eq!(
+      state.message().clone(),
+      Some(Message::error("E518: Unknown option: number"))
+    );
+    type_keys_in(&mut state, &tree, ":set wrap ts=0\n");
+    assert_eq!(
+      state.message().clone(),
+      Some(Message::error("E487: Argument must be positive: tabStop=0"))
+    );
+    // The options before the failed one are still set.
+    assert!(rlock!(tree).wrap());
+  }
+
+  #[test]
+  fn colorscheme1() {
+    let (tree, _buffer) = make_tree(vec!["foo\n"]);
+    let mut state = State::default();
+
+    // Show the current color scheme without argument.
+    type_keys_in(&mut state, &tree, ":colorscheme\n");
+    assert_eq!(
+      state.message().clone(),
+      Some(Message::info(DEFAULT_COLOR_SCHEME))
+    );
+    type_keys_in(&mut state, &tree, ":colo default\n");
+    assert!(matches!(state.stateful(), StatefulValue::NormalMode(_)));
+    assert!(state.message().is_none());
+    type_keys_in(&mut state, &tree, ":colo unknown\n");
+    assert_eq!(
+      state.message().clone(),
+      Some(Message::error("E185: Cannot find color scheme 'unknown'"))
+    );
+  }
+
+  #[test]
+  fn substitute1() {
+    let (tree, buffer) = make_tree(vec!["foo foo\n", "baz\n", "  a foo\n"]);
+    let mut state = State::default();
+
+    type_keys_in(&mut state, &tree, ":%s/foo/bar/g\n");
+    assert!(matches!(state.stateful(), StatefulValue::NormalMode(_)));
+    assert_eq!(buffer_text(&buffer), "bar bar\nbaz\n  a bar\n");
+    assert_eq!(
+      state.message().clone(),
+      Some(Message::info("3 substitutions on 2 lines"))
+    );
+    // The cursor moves to the last substituted line.
+    assert_eq!(search_status(&tree).0, BufferPosition::new(2, 2));
+    assert_eq!(state.search().pattern().as_deref(), Some("foo"));
+
+    // The cursor line only, and the empty replacement deletes the match.
+    type_keys_in(&mut state, &tree, ":s/bar//\n");
+    assert_eq!(buffer_text(&buffer), "bar bar\nbaz\n  a \n");
+    type_keys_in(&mut state, &tree, ":1,2s/qux/x/\n");
+    assert_eq!(
+      state.message().clone(),
+      Some(Message::error("E486: Pattern not found: qux"))
+    );
+    type_keys_in(&mut state, &tree, ":5s/a/b/\n");
+    assert_eq!(
+      state.message().clone(),
+      Some(Message::error("E16: Invalid range"))
+    );
+  }
+
+  #[test]
+  fn substitute_backref1() {
+    let (tree, buffer) = make_tree(vec!["hello world\n", "foo bar\n"]);
+    let mut state = State::default();
+
+    type_keys_in(&mut state, &tree, r":%s/(\w+) (\w+)/\2 \1-&/");
+    type_keys_in(&mut state, &tree, "\n");
+    assert_eq!(
+      buffer_text(&buffer),
+      "world hello-hello world\nbar foo-foo bar\n"
+    );
+    assert_eq!(
+      state.message().clone(),
+      Some(Message::info("2 substitutions on 2 lines"))
+    );
+  }
+
+  #[test]
+  fn substitute_range1() {
+    let (tree, buffer) = make_tree(vec!["Foo-1\n", "foo-2\n", "FOO-3\n", "foo-4\n"]);
+    let mut state = State::default();
+    let tick = rlock!(buffer).changed_tick();
+
+    // The relative range, the `i` flag, the `$N` groups and another delimiter.
+    type_keys_in(&mut state, &tree, ":.,.+2s#(foo)-(\\d)#$2:$1#i\n");
+    assert_eq!(buffer_text(&buffer), "1:Foo\n2:foo\n3:FOO\nfoo-4\n");
+    assert_eq!(
+      state.message().clone(),
+      Some(Message::info("3 substitutions on 3 lines"))
+    );
+    assert_eq!(search_status(&tree).0, BufferPosition::new(2, 0));
+    // All the lines are substituted in one edit.
+    assert_eq!(rlock!(buffer).changed_tick(), tick + 1);
+
+    // The empty pattern reuses the last pattern, it's case sensitive without the `i` flag.
+    type_keys_in(&mut state, &tree, ":$-1,$s##x#g\n");
+    assert_eq!(buffer_text(&buffer), "1:Foo\n2:foo\n3:FOO\nx\n");
+    assert_eq!(search_status(&tree).0, BufferPosition::new(3, 0));
+    type_keys_in(&mut state, &tree, ":.-4s/a/b/\n");
+    assert_eq!(
+      state.message().clone(),
+      Some(Message::error("E16: Invalid range"))
+    );
+  }
+
+  #[test]
+  fn substitute_confirm1() {
+    let (tree, buffer) = make_tree(vec!["a a\n", "a\n", "a\n"]);
+    let mut state = State::default();
+
+    type_keys_in(&mut state, &tree, ":%s/a/bc/gc\n");
+    assert!(matches!(
+      state.stateful(),
+      StatefulValue::CommandLineMode(_)
+    ));
+    assert_eq!(
+      state.message().clone(),
+      Some(Message::info("replace with bc (y/n/a/q/l)?"))
+    );
+    assert_eq!(search_status(&tree).0, BufferPosition::new(0, 0));
+
+    type_keys_in(&mut state, &tree, "y");
+    assert_eq!(buffer_text(&buffer), "bc a\na\na\n");
+    assert_eq!(search_status(&tree).0, BufferPosition::new(0, 3));
+    type_keys_in(&mut state, &tree, "n");
+    assert_eq!(search_status(&tree).0, BufferPosition::new(1, 0));
+    // Other keys are ignored.
+    type_keys_in(&mut state, &tree, "x");
+    assert!(matches!(
+      state.stateful(),
+      StatefulValue::CommandLineMode(_)
+    ));
+    type_keys_in(&mut state, &tree, "a");
+    assert!(matches!(state.stateful(), StatefulValue::NormalMode(_)));
+    assert_eq!(buffer_text(&buffer), "bc a\nbc\nbc\n");
+    assert_eq!(
+      state.message().clone(),
+      Some(Message::info("3 substitutions on 3 lines"))
+    );
+
+    // Quit without replacing, the cursor goes back.
+    type_keys_in(&mut state, &tree, ":%s/bc/d/c\nnq");
+    assert!(matches!(state.stateful(), StatefulValue::NormalMode(_)));
+    assert_eq!(buffer_text(&buffer), "bc a\nbc\nbc\n");
+    assert_eq!(search_status(&tree).0, BufferPosition::new(2, 0));
+  }
 }
@@ -1,13 +1,392 @@
 //! The insert mode.
 
+use crate::buf::Buffer;
+use crate::envar;
+use crate::res::BufferResult;
+use crate::state::fsm::normal::NormalStateful;
+use crate::state::fsm::visual::{
+  current_buffer, cursor_position, line_text_len, sync_cursor, sync_viewport,
+};
 use crate::state::fsm::{Stateful, StatefulDataAccess, StatefulValue};
+use crate::state::selection::BufferPosition;
+use crate::state::State;
+use crate::ui::tree::Tree;
+use crate::{rlock, wlock};
+
+use crossterm::event::{Event, KeyCode, KeyEventKind};
+use tracing::error;
 
 #[derive(Debug, Copy, Clone, Default)]
 /// The insert editing mode.
 pub struct InsertStateful {}
 
 impl Stateful for InsertStateful {
-  fn handle(&self, _data_access: StatefulDataAccess) -> StatefulValue {
+  fn handle(&self, data_access: StatefulDataAccess) -> StatefulValue {
+    let state = data_access.state;
+    let tree = data_access.tree;
+    let event = data_access.event;
+
+    if let Event::Key(key_event) = event {
+      if key_event.kind == KeyEventKind::Press {
+        // Record the typed keys for repeating the change, i.e. `.`.
+        match key_event.code {
+          KeyCode::Esc => state.change_recorder_mut().finish(),
+          code => state.change_recorder_mut().record_insert_key(code),
+        }
+        let mut tree = wlock!(tree);
+        match key_event.code {
+          KeyCode::Esc => {
+            return self.exit(&mut tree);
+          }
+          KeyCode::Up => {
+            self.move_cursor(&mut tree, -1, 0);
+          }
+          KeyCode::Down => {
+            self.move_cursor(&mut tree, 1, 0);
+          }
+          KeyCode::Left => {
+            self.move_cursor(&mut tree, 0, -1);
+          }
+          KeyCode::Right => {
+            self.move_cursor(&mut tree, 0, 1);
+          }
+          KeyCode::Enter => {
+            self.insert_line_break(state, &mut tree);
+          }
+          KeyCode::Backspace => {
+            self.delete_backward(state, &mut tree);
+          }
+          KeyCode::Tab => {
+            self.insert_text(state, &mut tree, "\t");
+          }
+          KeyCode::Char(c) => {
+            self.insert_text(state, &mut tree, c.encode_utf8(&mut [0; 4]));
+          }
+          _ => { /* Skip */ }
+        }
+      }
+    }
+
     StatefulValue::InsertMode(InsertStateful::default())
   }
 }
+
+impl InsertStateful {
+  // Back to normal mode, the cursor moves back to the left char, like Vim does.
+  pub(crate) fn exit(&self, tree: &mut Tree) -> StatefulValue {
+    if let Some(cursor) = cursor_position(tree) {
+      if cursor.char_idx() > 0 {
+        let next_cursor = BufferPosition::new(cursor.line_idx(), cursor.char_idx() - 1);
+        sync_cursor(tree, cursor, next_cursor);
+      }
+    }
+    StatefulValue::NormalMode(NormalStateful::default())
+  }
+
+  // Move the cursor by `rows` and `cols`, the cursor can stop after the last char of a line in
+  // insert mode.
+  pub(crate) fn move_cursor(&self, tree: &mut Tree, rows: isize, cols: isize) {
+    let buffer = match current_buffer(tree) {
+      Some(buffer) => buffer,
+      None => return,
+    };
+    let cursor = match cursor_position(tree) {
+      Some(cursor) => cursor,
+      None => return,
+    };
+    let next_cursor = {
+      let buffer = rlock!(buffer);
+      let last_line_idx = buffer.last_line_idx() as isize;
+      let line_idx = (cursor.line_idx() as isize + rows).clamp(0, last_line_idx) as usize;
+      let line_len = line_text_len(&buffer, line_idx) as isize;
+      let char_idx = (cursor.char_idx() as isize + cols).clamp(0, line_len) as usize;
+      BufferPosition::new(line_idx, char_idx)
+    };
+    if next_cursor != cursor {
+      sync_cursor(tree, cursor, next_cursor);
+    }
+  }
+
+  // Edit the buffer of current window with `edit`, which returns the next cursor position. Then
+  // re-sync the viewport and the cursor. The edit error is shown in the message area, i.e. the
+  // buffer is not 'modifiable'.
+  pub(crate) fn edit<F>(&self, state: &mut State, tree: &mut Tree, edit: F)
+  where
+    F: FnOnce(&mut Buffer, BufferPosition) -> BufferResult<Option<BufferPosition>>,
+  {
+    let buffer = match current_buffer(tree) {
+      Some(buffer) => buffer,
+      None => return,
+    };
+    let cursor = match cursor_position(tree) {
+      Some(cursor) => cursor,
+      None => return,
+    };
+    let next_cursor = {
+      let mut buffer = wlock!(buffer);
+      let next_cursor = match edit(&mut buffer, cursor) {
+        Ok(Some(next_cursor)) => next_cursor,
+        Ok(None) => return,
+        Err(e) => {
+          error!("Failed to edit buffer:{:?}", e);
+          state.echoerr(&e.to_string());
+          return;
+        }
+      };
+      // The cursor can move to the empty line after the trailing line break, i.e. insert a line
+      // break at the end of the last line, make it a line.
+      if let Err(e) = buffer.ensure_line(next_cursor.line_idx()) {
+        error!("Failed to ensure line {:?}:{:?}", next_cursor.line_idx(), e);
+      }
+      next_cursor
+    };
+    sync_viewport(tree);
+    sync_cursor(tree, cursor, next_cursor);
+  }
+
+  // Insert `text` at the cursor, the cursor moves after it.
+  fn insert_text(&self, state: &mut State, tree: &mut Tree, text: &str) {
+    self.edit(state, tree, |buffer, cursor| {
+      let char_idx = buffer.line_col_to_char_clamped(cursor.line_idx(), cursor.char_idx());
+      buffer.insert(char_idx, text)?;
+      Ok(Some(BufferPosition::new(
+        cursor.line_idx(),
+        cursor.char_idx() + text.chars().count(),
+      )))
+    });
+  }
+
+  // Insert a line break at the cursor. If 'autoindent' is set, the new line copies the indent of
+  // current line, and the cursor moves after the indent.
+  //
+  // See: <https://vimhelp.org/options.txt.html#%27autoindent%27>.
+  fn insert_line_break(&self, state: &mut State, tree: &mut Tree) {
+    self.edit(state, tree, |buffer, cursor| {
+      let line_idx = cursor.line_idx();
+      let indent = if buffer.auto_indent() {
+        let indent = buffer.line_indent(line_idx);
+        let indent_chars = std::cmp::min(indent.chars().count(), cursor.char_idx());
+        let indent: String = indent.chars().take(indent_chars).collect();
+        buffer.make_indent(buffer.indent_width(&indent))
+      } else {
+        String::new()
+      };
+      let char_idx = buffer.line_col_to_char_clamped(line_idx, cursor.char_idx());
+      buffer.insert(char_idx, &format!("\n{}", indent))?;
+      Ok(Some(BufferPosition::new(
+        line_idx + 1,
+        indent.chars().count(),
+      )))
+    });
+  }
+
+  // Delete the char before the cursor, at the start of a line, it joins current line to the
+  // previous line.
+  //
+  // If 'autoindent' is set and there're only indent before the cursor, it removes a whole indent
+  // step ('shiftwidth') instead of a single char.
+  fn delete_backward(&self, state: &mut State, tree: &mut Tree) {
+    self.edit(state, tree, |buffer, cursor| {
+      let line_idx = cursor.line_idx();
+      let line_start = buffer.line_to_char(line_idx);
+
+      if cursor.char_idx() == 0 {
+        if line_idx == 0 {
+          return Ok(None);
+        }
+        let prev_line_len = line_text_len(buffer, line_idx - 1);
+        let prev_line_end = buffer.line_to_char(line_idx - 1) + prev_line_len;
+        buffer.remove(prev_line_end..line_start)?;
+        return Ok(Some(BufferPosition::new(line_idx - 1, prev_line_len)));
+      }
+
+      let before: String = match buffer.get_line(line_idx) {
+        Some(line) => line.chars().take(cursor.char_idx()).collect(),
+        None => return Ok(None),
+      };
+      let (char_range, text) =
+        if buffer.auto_indent() && before.chars().all(|c| c == ' ' || c == '\t') {
+          let step = buffer.indent_step();
+          let width = buffer.indent_width(&before);
+          let indent = buffer.make_indent((width - 1) / step * step);
+          (line_start..line_start + cursor.char_idx(), indent)
+        } else {
+          let char_idx = line_start + cursor.char_idx();
+          (char_idx - 1..char_idx, String::new())
+        };
+      let next_char_idx = char_range.start - line_start + text.chars().count();
+      buffer.replace(char_range, &text)?;
+      Ok(Some(BufferPosition::new(line_idx, next_char_idx)))
+    });
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use crate::buf::BufferArc;
+  use crate::state::message::Message;
+  use crate::test::state::press;
+  use crate::test::tree::make_tree;
+  use crate::ui::tree::TreeArc;
+
+  fn type_text(state: &mut State, tree: &TreeArc, text: &str) {
+    for c in text.chars() {
+      press(state, tree, KeyCode::Char(c));
+    }
+  }
+
+  fn buffer_text(buffer: &BufferArc) -> String {
+    let mut text: Vec<u8> = vec![];
+    rlock!(buffer).write_to(&mut text).unwrap();
+    String::from_utf8(text).unwrap()
+  }
+
+  fn cursor(tree: &TreeArc) -> BufferPosition {
+    cursor_position(&rlock!(tree)).unwrap()
+  }
+
+  #[test]
+  fn insert1() {
+    let (tree, buffer) = make_tree(vec!["world\n"]);
+    let mut state = State::default();
+
+    press(&mut state, &tree, KeyCode::Char('i'));
+    assert!(matches!(state.stateful(), StatefulValue::InsertMode(_)));
+    type_text(&mut state, &tree, "hello ");
+    assert_eq!(buffer_text(&buffer), "hello world\n");
+    assert_eq!(cursor(&tree), BufferPosition::new(0, 6));
+
+    press(&mut state, &tree, KeyCode::Backspace);
+    assert_eq!(buffer_text(&buffer), "helloworld\n");
+    assert_eq!(cursor(&tree), BufferPosition::new(0, 5));
+    press(&mut state, &tree, KeyCode::Esc);
+    assert!(matches!(state.stateful(), StatefulValue::NormalMode(_)));
+    assert_eq!(cursor(&tree), BufferPosition::new(0, 4));
+  }
+
+  #[test]
+  fn autoindent1() {
+    let (tree, buffer) = make_tree(vec!["    foo\n", "bar\n"]);
+    let mut state = State::default();
+
+    press(&mut state, &tree, KeyCode::Char('i'));
+    for _ in 0..10 {
+      press(&mut state, &tree, KeyCode::Right);
+    }
+    assert_eq!(cursor(&tree), BufferPosition::new(0, 7));
+    press(&mut state, &tree, KeyCode::Enter);
+    assert_eq!(buffer_text(&buffer), "    foo\n    \nbar\n");
+    assert_eq!(cursor(&tree), BufferPosition::new(1, 4));
+
+    type_text(&mut state, &tree, "baz");
+    assert_eq!(buffer_text(&buffer), "    foo\n    baz\nbar\n");
+    assert_eq!(cursor(&tree), BufferPosition::new(1, 7));
+  }
+
+  #[test]
+  fn autoindent2() {
+    let (tree, buffer) = make_tree(vec!["\tfoo\n"]);
+    wlock!(buffer).set_tab_stop(4);
+    let mut state = State::default();
+
+    // The tab is kept.
+    press(&mut state, &tree, KeyCode::Char('i'));
+    for _ in 0..4 {
+      press(&mut state, &tree, KeyCode::Right);
+    }
+    press(&mut state, &tree, KeyCode::Enter);
+    assert_eq!(buffer_text(&buffer), "\tfoo\n\t\n");
+    assert_eq!(cursor(&tree), BufferPosition::new(1, 1));
+
+    // With 'expandtab', the tab is expanded to spaces.
+    wlock!(buffer).set_expand_tab(true);
+    press(&mut state, &tree, KeyCode::Enter);
+    assert_eq!(buffer_text(&buffer), "\tfoo\n\t\n    \n");
+    assert_eq!(cursor(&tree), BufferPosition::new(2, 4));
+
+    // Without 'autoindent', nothing is copied.
+    wlock!(buffer).set_auto_indent(false);
+    press(&mut state, &tree, KeyCode::Enter);
+    assert_eq!(buffer_text(&buffer), "\tfoo\n\t\n    \n\n");
+    assert_eq!(cursor(&tree), BufferPosition::new(3, 0));
+  }
+
+  #[test]
+  fn backspace1() {
+    let (tree, buffer) = make_tree(vec!["foo\n", "bar\n"]);
+    wlock!(buffer).set_expand_tab(true);
+    wlock!(buffer).set_shift_width(2);
+    let mut state = State::default();
+
+    press(&mut state, &tree, KeyCode::Char('i'));
+    type_text(&mut state, &tree, "     ");
+    assert_eq!(buffer_text(&buffer), "     foo\nbar\n");
+
+    // Removes an indent step.
+    press(&mut state, &tree, KeyCode::Backspace);
+    assert_eq!(buffer_text(&buffer), "    foo\nbar\n");
+    assert_eq!(cursor(&tree), BufferPosition::new(0, 4));
+    press(&mut state, &tree, KeyCode::Backspace);
+    assert_eq!(buffer_text(&buffer), "  foo\nbar\n");
+    press(&mut state, &tree, KeyCode::Backspace);
+    assert_eq!(buffer_text(&buffer), "foo\nbar\n");
+    assert_eq!(cursor(&tree), BufferPosition::new(0, 0));
+
+    // Joins lines at the start of line.
+    press(&mut state, &tree, KeyCode::Down);
+    assert_eq!(cursor(&tree), BufferPosition::new(1, 0));
+    press(&mut state, &tree, KeyCode::Backspace);
+    assert_eq!(buffer_text(&buffer), "foobar\n");
+    assert_eq!(cursor(&tree), BufferPosition::new(0, 3));
+  }
+
+  #[test]
+  fn noeol1() {
+    let (tree, buffer) = make_tree(vec!["foo\n", "bar"]);
+    let mut state = State::default();
+
+    // The cursor can't move to the empty line after the trailing line break.
+    press(&mut state, &tree, KeyCode::Char('i'));
+    press(&mut state, &tree, KeyCode::Down);
+    press(&mut state, &tree, KeyCode::Down);
+    assert_eq!(cursor(&tree), BufferPosition::new(1, 0));
+
+    // Breaking the last line without line break at its end adds a line.
+    for _ in 0..3 {
+      press(&mut state, &tree, KeyCode::Right);
+    }
+    press(&mut state, &tree, KeyCode::Enter);
+    assert_eq!(buffer_text(&buffer), "foo\nbar\n\n");
+    assert_eq!(cursor(&tree), BufferPosition::new(2, 0));
+    assert_eq!(rlock!(buffer).last_line_idx(), 2);
+
+    type_text(&mut state, &tree, "baz");
+    assert_eq!(buffer_text(&buffer), "foo\nbar\nbaz\n");
+  }
+
+  #[test]
+  fn not_modifiable1() {
+    let (tree, buffer) = make_tree(vec!["foo\n", "bar\n"]);
+    let mut state = State::default();
+    wlock!(buffer).set_modifiable(false);
+
+    press(&mut state, &tree, KeyCode::Char('i'));
+    type_text(&mut state, &tree, "x");
+    assert_eq!(buffer_text(&buffer), "foo\nbar\n");
+    assert_eq!(cursor(&tree), BufferPosition::new(0, 0));
+    assert_eq!(
+      state.message().clone(),
+      Some(Message::error(
+        "E21: Cannot make changes, 'modifiable' is off"
+      ))
+    );
+    press(&mut state, &tree, KeyCode::Enter);
+    assert_eq!(buffer_text(&buffer), "foo\nbar\n");
+
+    wlock!(buffer).set_modifiable(true);
+    type_text(&mut state, &tree, "x");
+    assert_eq!(buffer_text(&buffer), "xfoo\nbar\n");
+  }
+}
@@ -2,17 +2,38 @@
 
 #![allow(unused_imports)]
 
+use crate::buf::{Buffer, BufferArc, BufferKind, BuffersManagerArc, ExternalChange};
 use crate::envar;
-use crate::state::command::Command;
-use crate::state::fsm::quit::QuitStateful;
+use crate::state::change::{Change, ChangeKind};
+use crate::state::clipboard::ClipboardPut;
+use crate::state::command::{Command, CommandLineKind};
+use crate::state::fsm::command_line::{
+  equalize_windows, goto_line, jump_cursor, move_window_focus, resize_window, switch_buffer,
+  CommandLineStateful,
+};
+use crate::state::fsm::insert::InsertStateful;
+use crate::state::fsm::operator_pending::{create_fold, Operator, OperatorPendingStateful};
+use crate::state::fsm::replace::ReplaceStateful;
+use crate::state::fsm::visual::{
+  current_buffer, cursor_position, line_text_len, sync_cursor, sync_viewport, VisualStateful,
+};
 use crate::state::fsm::{Stateful, StatefulDataAccess, StatefulValue};
+use crate::state::jumplist::Jump;
 use crate::state::mode::Mode;
-use crate::ui::tree::TreeNode;
-use crate::ui::widget::window::CursorViewport;
-use crate::wlock;
+use crate::state::motion::{clamp_position, matching_bracket_target, percent_line_target, Motion};
+use crate::state::recording::{text_to_keys, MAX_EXECUTE_DEPTH};
+use crate::state::register::{Register, RegisterKind, Registers, UNNAMED_REGISTER};
+use crate::state::selection::{BufferPosition, VisualKind};
+use crate::state::State;
+use crate::ui::tree::internal::Inodeable;
+use crate::ui::tree::{FocusDirection, SplitDirection, Tree, TreeArc, TreeNode};
+use crate::ui::widget::window::{CursorLinePosition, CursorViewport, Folds, Viewport, ViewportArc};
+use crate::{rlock, wlock};
 
-use crossterm::event::{Event, KeyCode, KeyEventKind, KeyEventState, KeyModifiers};
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyEventKind, KeyEventState, KeyModifiers};
+use std::sync::Arc;
 use std::time::Duration;
+use tracing::error;
 
 #[derive(Debug, Copy, Clone, Default)]
 /// The normal editing mode.
@@ -20,35 +41,158 @@ pub struct NormalStateful {}
 
 impl Stateful for NormalStateful {
   fn handle(&self, data_access: StatefulDataAccess) -> StatefulValue {
-    let _state = data_access.state;
+    let state = data_access.state;
     let tree = data_access.tree;
+    let buffers = data_access.buffers;
     let event = data_access.event;
 
     match event {
-      Event::FocusGained => {}
+      Event::FocusGained => {
+        let tree = rlock!(tree);
+        self.check_external_changes(state, &tree);
+      }
       Event::FocusLost => {}
       Event::Key(key_event) => match key_event.kind {
         KeyEventKind::Press => {
+          if state.handle_register_name(key_event.code) {
+            return StatefulValue::NormalMode(NormalStateful::default());
+          }
+          if let Some(prefix) = state.take_pending_key() {
+            let count = state.take_count();
+            if prefix == CTRL_W {
+              // Window commands.
+              let mut tree = wlock!(tree);
+              self.window_command(&mut tree, key_event.code, count);
+              return StatefulValue::NormalMode(NormalStateful::default());
+            }
+            if let ('g', KeyCode::Char(c)) = (prefix, key_event.code) {
+              if let Some(operator) = Operator::from_g_key(c) {
+                // Case-change operators, wait for a motion.
+                state.set_pending_operator(Some((operator, count)));
+                return StatefulValue::OperatorPendingMode(OperatorPendingStateful::default());
+              }
+            }
+            if let ('g', KeyCode::Char(c @ (';' | ','))) = (prefix, key_event.code) {
+              // Change list
+              let mut tree = wlock!(tree);
+              self.walk_change_list(state, &mut tree, c == ';', count.unwrap_or(1));
+              return StatefulValue::NormalMode(NormalStateful::default());
+            }
+            if let ('g', KeyCode::Char('h')) = (prefix, key_event.code) {
+              // Toggle the hidden entries of the directory buffer.
+              let mut tree = wlock!(tree);
+              self.toggle_hidden(state, &mut tree);
+              return StatefulValue::NormalMode(NormalStateful::default());
+            }
+            if let ('g', KeyCode::Char(c @ ('t' | 'T'))) = (prefix, key_event.code) {
+              // Go to the next/previous tab page, `{count}gt` goes to the tab page `count`.
+              let mut tree = wlock!(tree);
+              match (c, count) {
+                ('t', Some(count)) => {
+                  tree.switch_tab(count.saturating_sub(1));
+                }
+                ('t', None) => {
+                  tree.next_tab();
+                }
+                _ => {
+                  for _ in 0..count.unwrap_or(1) {
+                    tree.prev_tab();
+                  }
+                }
+              }
+              return StatefulValue::NormalMode(NormalStateful::default());
+            }
+            if let ('g', KeyCode::Char('g')) = (prefix, key_event.code) {
+              // Go to the first line, or the line `count`.
+              let mut tree = wlock!(tree);
+              goto_line(state, &mut tree, count, false);
+              return StatefulValue::NormalMode(NormalStateful::default());
+            }
+            if prefix == 'r' {
+              // Replace `count` chars with the next key, other keys (i.e. `Esc`) cancel it.
+              let c = match key_event.code {
+                KeyCode::Char(c) => Some(c),
+                KeyCode::Tab => Some('\t'),
+                KeyCode::Enter => Some('\n'),
+                _ => None,
+              };
+              if let Some(c) = c {
+                let mut tree = wlock!(tree);
+                self.replace_chars(state, &mut tree, c, count.unwrap_or(1));
+              }
+              return StatefulValue::NormalMode(NormalStateful::default());
+            }
+            if let ('q', KeyCode::Char(name)) = (prefix, key_event.code) {
+              // Start recording into the register.
+              if Registers::is_valid_name(name) {
+                state.macro_recorder_mut().start(name);
+              }
+              return StatefulValue::NormalMode(NormalStateful::default());
+            }
+            if let ('@', KeyCode::Char(name)) = (prefix, key_event.code) {
+              // Execute the register, `@@` executes the last executed one.
+              return self.execute_register(state, name, count.unwrap_or(1));
+            }
+            if let ('z', KeyCode::Char(c)) = (prefix, key_event.code) {
+              if let Some(operator) = Operator::from_z_key(c) {
+                // Fold operator, wait for a motion.
+                state.set_pending_operator(Some((operator, count)));
+                return StatefulValue::OperatorPendingMode(OperatorPendingStateful::default());
+              }
+            }
+            if let ('z', KeyCode::Char('F')) = (prefix, key_event.code) {
+              // Create a fold for `count` lines.
+              let mut tree = wlock!(tree);
+              if let (Some(buffer), Some(cursor)) = (current_buffer(&tree), cursor_position(&tree))
+              {
+                let last_line_idx = rlock!(buffer).last_line_idx();
+                let end_line_idx = cursor.line_idx() + count.unwrap_or(1).max(1);
+                create_fold(
+                  &mut tree,
+                  cursor.line_idx()..end_line_idx.min(last_line_idx + 1),
+                );
+              }
+              return StatefulValue::NormalMode(NormalStateful::default());
+            }
+            if let ('z', KeyCode::Char(c @ ('o' | 'c' | 'a'))) = (prefix, key_event.code) {
+              // Open/close/toggle the fold at the cursor line.
+              let mut tree = wlock!(tree);
+              self.fold(state, &mut tree, |folds, line_idx| match c {
+                'o' => folds.open_at(line_idx),
+                'c' => folds.close_at(line_idx),
+                _ => folds.toggle_at(line_idx),
+              });
+              return StatefulValue::NormalMode(NormalStateful::default());
+            }
+            let position = match (prefix, key_event.code) {
+              ('z', KeyCode::Char('t')) => Some(CursorLinePosition::Top),
+              ('z', KeyCode::Char('z')) => Some(CursorLinePosition::Center),
+              ('z', KeyCode::Char('b')) => Some(CursorLinePosition::Bottom),
+              _ => None,
+            };
+            if let Some(position) = position {
+              // Reposition the cursor line
+              let mut tree = wlock!(tree);
+              self.scroll(&mut tree, |viewport| {
+                viewport.scroll_cursor_line_to(position)
+              });
+            }
+            return StatefulValue::NormalMode(NormalStateful::default());
+          }
+          if state.handle_count(key_event.code) {
+            return StatefulValue::NormalMode(NormalStateful::default());
+          }
+          let count = state.take_count();
           match key_event.code {
             KeyCode::Up | KeyCode::Char('k') => {
               // Up
               let mut tree = wlock!(tree);
-              match tree.cursor_id() {
-                Some(cursor_id) => {
-                  tree.bounded_move_up_by(cursor_id, 1);
-                }
-                None => { /* Skip */ }
-              }
+              self.move_lines(&mut tree, false, count.unwrap_or(1));
             }
             KeyCode::Down | KeyCode::Char('j') => {
               // Down
               let mut tree = wlock!(tree);
-              match tree.cursor_id() {
-                Some(cursor_id) => {
-                  tree.bounded_move_down_by(cursor_id, 1);
-                }
-                None => { /* Skip */ }
-              }
+              self.move_lines(&mut tree, true, count.unwrap_or(1));
             }
             KeyCode::Left | KeyCode::Char('h') => {
               // Left
@@ -70,6 +214,172 @@ impl Stateful for NormalStateful {
                 None => { /* Skip */ }
               }
             }
+            KeyCode::Char('f') | KeyCode::Char('b') | KeyCode::Char('d') | KeyCode::Char('u')
+              if key_event.modifiers.contains(KeyModifiers::CONTROL) =>
+            {
+              // Scroll
+              let mut tree = wlock!(tree);
+              match key_event.code {
+                KeyCode::Char('f') => self.scroll(&mut tree, Viewport::scroll_page_down),
+                KeyCode::Char('b') => self.scroll(&mut tree, Viewport::scroll_page_up),
+                KeyCode::Char('d') => self.scroll(&mut tree, Viewport::scroll_half_page_down),
+                _ => self.scroll(&mut tree, Viewport::scroll_half_page_up),
+              }
+            }
+            KeyCode::PageDown | KeyCode::PageUp => {
+              // Scroll
+              let mut tree = wlock!(tree);
+              match key_event.code {
+                KeyCode::PageDown => self.scroll(&mut tree, Viewport::scroll_page_down),
+                _ => self.scroll(&mut tree, Viewport::scroll_page_up),
+              }
+            }
+            KeyCode::Char(c @ ('w' | 'b' | 'e' | 'W' | 'B' | 'E' | '$' | '0'))
+              if !key_event.modifiers.contains(KeyModifiers::CONTROL) =>
+            {
+              // Motions
+              let motion = Motion::from_key(c).unwrap();
+              let mut tree = wlock!(tree);
+              self.motion(&mut tree, motion, count.unwrap_or(1));
+            }
+            KeyCode::Char('G') => {
+              // Go to the last line, or the line `count`.
+              let mut tree = wlock!(tree);
+              goto_line(state, &mut tree, count, true);
+            }
+            KeyCode::Char('%') => {
+              // Go to the matching bracket, or the line at `count` percentage.
+              let mut tree = wlock!(tree);
+              self.jump(state, &mut tree, |buffer, cursor| match count {
+                Some(count) => Some(percent_line_target(buffer, count)),
+                None => matching_bracket_target(buffer, cursor),
+              });
+            }
+            KeyCode::Char('o') | KeyCode::Char('i') | KeyCode::Tab
+              if key_event.code == KeyCode::Tab
+                || key_event.modifiers.contains(KeyModifiers::CONTROL) =>
+            {
+              // Jump list, the `Ctrl-I` is the same with `Tab` in terminal.
+              let back = key_event.code == KeyCode::Char('o');
+              let mut tree = wlock!(tree);
+              self.walk_jump_list(state, &mut tree, back, count.unwrap_or(1));
+            }
+            KeyCode::Char(c @ ('d' | 'y' | 'c'))
+              if !key_event.modifiers.contains(KeyModifiers::CONTROL) =>
+            {
+              // Operators, wait for a motion.
+              let operator = Operator::from_key(c).unwrap();
+              state.set_pending_operator(Some((operator, count)));
+              return StatefulValue::OperatorPendingMode(OperatorPendingStateful::default());
+            }
+            KeyCode::Char('x') => {
+              // Delete chars, i.e. `x` is `dl`.
+              state.set_pending_operator(Some((Operator::Delete, count)));
+              let event = Event::Key(KeyEvent::new(KeyCode::Char('l'), KeyModifiers::NONE));
+              return OperatorPendingStateful::default()
+                .handle(StatefulDataAccess::new(state, tree, buffers, event));
+            }
+            KeyCode::Char('v') | KeyCode::Char('V') => {
+              // Visual
+              let kind = match key_event.code {
+                KeyCode::Char('v') if key_event.modifiers.contains(KeyModifiers::CONTROL) => {
+                  VisualKind::Blockwise
+                }
+                KeyCode::Char('v') => VisualKind::Charwise,
+                _ => VisualKind::Linewise,
+              };
+              let mut tree = wlock!(tree);
+              if let Some(next_stateful) = VisualStateful::enter(state, &mut tree, kind) {
+                return next_stateful;
+              }
+            }
+            KeyCode::Char('i') => {
+              // Insert
+              state
+                .change_recorder_mut()
+                .start(Change::new(ChangeKind::Insert, count, None));
+              return StatefulValue::InsertMode(InsertStateful::default());
+            }
+            KeyCode::Char('R') => {
+              // Replace
+              state
+                .change_recorder_mut()
+                .start(Change::new(ChangeKind::Replace, count, None));
+              return StatefulValue::ReplaceMode(ReplaceStateful::default());
+            }
+            KeyCode::Char('r') => {
+              // Wait for the char to replace with, the count is kept for it.
+              state.set_count(count);
+              state.set_pending_key(Some('r'));
+            }
+            KeyCode::Char('.') => {
+              // Repeat the last change
+              self.repeat(state, &tree, &buffers, count);
+            }
+            KeyCode::Char('q') => {
+              // Stop recording, or wait for the register name to start recording.
+              match state.macro_recorder_mut().finish() {
+                Some((name, text)) => {
+                  let register = Register::new(RegisterKind::Charwise, text);
+                  state.registers_mut().yank(Some(name), register);
+                }
+                None => state.set_pending_key(Some('q')),
+              }
+            }
+            KeyCode::Char('@') => {
+              // Wait for the register name, the count is kept for it.
+              state.set_count(count);
+              state.set_pending_key(Some('@'));
+            }
+            KeyCode::Char('z') => {
+              // Wait for the next key, i.e. `zt`/`zz`/`zb` and the folds `zf`/`zo`/`zc`/`za`, the
+              // count is kept for `zf`/`zF`.
+              state.set_count(count);
+              state.set_pending_key(Some('z'));
+            }
+            KeyCode::Char('g') => {
+              // Wait for the next key, i.e. `gu`/`gU`/`g~`, the count is kept for it.
+              state.set_count(count);
+              state.set_pending_key(Some('g'));
+            }
+            KeyCode::Char('w') if key_event.modifiers.contains(KeyModifiers::CONTROL) => {
+              // Wait for the window command, i.e. `Ctrl-W +`, the count is kept for it.
+              state.set_count(count);
+              state.set_pending_key(Some(CTRL_W));
+            }
+            KeyCode::Char(':') => {
+              // Command-line
+              state.command_line_mut().clear();
+              state.set_command_line_kind(CommandLineKind::Ex);
+              return StatefulValue::CommandLineMode(CommandLineStateful::default());
+            }
+            KeyCode::Char('/') => {
+              // Search
+              let tree = rlock!(tree);
+              CommandLineStateful::start_search(state, &tree);
+              return StatefulValue::CommandLineMode(CommandLineStateful::default());
+            }
+            KeyCode::Char('n') | KeyCode::Char('N') => {
+              // Repeat the last search, `N` in the opposite direction.
+              let reverse = key_event.code == KeyCode::Char('N');
+              let mut tree = wlock!(tree);
+              CommandLineStateful::search_next(state, &mut tree, reverse, count.unwrap_or(1));
+            }
+            KeyCode::Char('p') | KeyCode::Char('P') => {
+              // Put
+              let after = key_event.code == KeyCode::Char('p');
+              let mut tree = wlock!(tree);
+              self.put(state, &mut tree, after);
+            }
+            KeyCode::Esc => {
+              // Cancel the pending operator, the count is already taken.
+              state.set_pending_operator(None);
+            }
+            KeyCode::Enter => {
+              // Open the entry of the directory buffer
+              let mut tree = wlock!(tree);
+              self.open_dir_entry(state, &mut tree, &buffers);
+            }
             _ => { /* Skip */ }
           }
         }
@@ -81,20 +391,671 @@ impl Stateful for NormalStateful {
       Event::Resize(_columns, _rows) => {}
     }
 
-    // if event == Event::Key(KeyCode::Char('c').into()) {
-    //   println!("Curosr position: {:?}\r", crossterm::cursor::position());
-    // }
+    StatefulValue::NormalMode(NormalStateful::default())
+  }
+}
+
+// The pending key of the window commands, i.e. `Ctrl-W`.
+const CTRL_W: char = '\u{17}';
+
+impl NormalStateful {
+  // The window commands after `Ctrl-W`, i.e. `+`/`-` changes the height of current window by
+  // `count`, `<`/`>` changes the width, `=` makes all the windows equally high and wide, and
+  // `w`/`W` (or `Ctrl-W`) moves the focus to the next/previous window, `h`/`j`/`k`/`l` (or the
+  // arrow keys) moves the focus to the `count`-th window on the left/below/above/right.
+  fn window_command(&self, tree: &mut Tree, code: KeyCode, count: Option<usize>) {
+    let window_id = match tree.focused_window_id() {
+      Some(window_id) => window_id,
+      None => return,
+    };
+    let shape = *tree.node(&window_id).unwrap().shape();
+    let (height, width) = (shape.height() as usize, shape.width() as usize);
+    let n = count.unwrap_or(1);
+    match code {
+      KeyCode::Char('+') => resize_window(tree, SplitDirection::Horizontal, height + n),
+      KeyCode::Char('-') => {
+        resize_window(tree, SplitDirection::Horizontal, height.saturating_sub(n))
+      }
+      KeyCode::Char('>') => resize_window(tree, SplitDirection::Vertical, width + n),
+      KeyCode::Char('<') => resize_window(tree, SplitDirection::Vertical, width.saturating_sub(n)),
+      KeyCode::Char('=') => equalize_windows(tree),
+      KeyCode::Char('w') => move_window_focus(tree, FocusDirection::Next),
+      KeyCode::Char('W') => move_window_focus(tree, FocusDirection::Prev),
+      KeyCode::Char('h') | KeyCode::Left => {
+        (0..n).for_each(|_| move_window_focus(tree, FocusDirection::Left))
+      }
+      KeyCode::Char('j') | KeyCode::Down => {
+        (0..n).for_each(|_| move_window_focus(tree, FocusDirection::Down))
+      }
+      KeyCode::Char('k') | KeyCode::Up => {
+        (0..n).for_each(|_| move_window_focus(tree, FocusDirection::Up))
+      }
+      KeyCode::Char('l') | KeyCode::Right => {
+        (0..n).for_each(|_| move_window_focus(tree, FocusDirection::Right))
+      }
+      _ => { /* Skip */ }
+    }
+  }
+
+  // Scroll the viewport of current window, i.e. `Ctrl-F`/`Ctrl-B`/`Ctrl-D`/`Ctrl-U` and
+  // `zt`/`zz`/`zb`, the cursor widget moves along with the cursor viewport.
+  fn scroll(&self, tree: &mut Tree, scroll: impl FnOnce(&mut Viewport)) {
+    let window_id = match tree.focused_window_id() {
+      Some(window_id) => window_id,
+      None => return,
+    };
+    let (cols, rows) = match tree.node(&window_id) {
+      Some(TreeNode::Window(window)) => {
+        let viewport = window.viewport();
+        let mut viewport = wlock!(viewport);
+        let cursor_viewport = *viewport.cursor();
+        scroll(&mut *viewport);
+        let next_cursor_viewport = viewport.cursor();
+        (
+          next_cursor_viewport.start_dcol_idx() as isize
+            - cursor_viewport.start_dcol_idx() as isize,
+          next_cursor_viewport.row_idx() as isize - cursor_viewport.row_idx() as isize,
+        )
+      }
+      _ => return,
+    };
+    if let Some(cursor_id) = tree.cursor_id() {
+      tree.bounded_move_by(cursor_id, cols, rows);
+    }
+  }
+
+  // Move the cursor to the target of the motion repeated `count` times, the viewport scrolls if the
+  // target is outside of it.
+  fn motion(&self, tree: &mut Tree, motion: Motion, count: usize) {
+    let buffer = match current_buffer(tree) {
+      Some(buffer) => buffer,
+      None => return,
+    };
+    let cursor = match cursor_position(tree) {
+      Some(cursor) => cursor,
+      None => return,
+    };
+    let next_cursor = {
+      let buffer = rlock!(buffer);
+      let next_cursor = motion.target_n(&buffer, cursor, count);
+      step_over_fold(tree, &buffer, cursor, next_cursor)
+    };
+    if next_cursor != cursor {
+      jump_cursor(tree, next_cursor, None);
+    }
+  }
+
+  // Move the cursor down/up `count` lines, i.e. `j`/`k`, a closed fold counts as a single line.
+  // The char index is kept if possible, and the viewport scrolls if the target is outside of it.
+  fn move_lines(&self, tree: &mut Tree, down: bool, count: usize) {
+    let (buffer, viewport) = match (current_buffer(tree), focused_viewport(tree)) {
+      (Some(buffer), Some(viewport)) => (buffer, viewport),
+      _ => return,
+    };
+    let cursor = match cursor_position(tree) {
+      Some(cursor) => cursor,
+      None => return,
+    };
+    let next_cursor = {
+      let buffer = rlock!(buffer);
+      let viewport = rlock!(viewport);
+      let folds = viewport.folds();
+      let mut line_idx = cursor.line_idx();
+      for _ in 0..count {
+        if down {
+          let next_line_idx = match folds.closed_fold_at(line_idx) {
+            Some(fold) => fold.end,
+            None => line_idx + 1,
+          };
+          if next_line_idx > buffer.last_line_idx() {
+            break;
+          }
+          line_idx = next_line_idx;
+        } else {
+          if line_idx == 0 {
+            break;
+          }
+          line_idx = match folds.closed_fold_at(line_idx - 1) {
+            Some(fold) => fold.start,
+            None => line_idx - 1,
+          };
+        }
+      }
+      clamp_position(&buffer, BufferPosition::new(line_idx, cursor.char_idx()))
+    };
+    if next_cursor != cursor {
+      jump_cursor(tree, next_cursor, None);
+    }
+  }
+
+  // Open/close/toggle the fold at the cursor line, i.e. `zo`/`zc`/`za`, the viewport is synced
+  // with the folds.
+  fn fold(&self, state: &mut State, tree: &mut Tree, f: impl FnOnce(&mut Folds, usize) -> bool) {
+    let cursor = match cursor_position(tree) {
+      Some(cursor) => cursor,
+      None => return,
+    };
+    let found = match focused_viewport(tree) {
+      Some(viewport) => f(wlock!(viewport).folds_mut(), cursor.line_idx()),
+      None => return,
+    };
+    if !found {
+      state.echoerr("E490: No fold found");
+      return;
+    }
+    sync_viewport(tree);
+    jump_cursor(tree, cursor, None);
+  }
 
-    // quit loop
-    if event == Event::Key(KeyCode::Esc.into()) {
-      // println!("ESC: {:?}\r", crossterm::cursor::position());
-      return StatefulValue::QuitState(QuitStateful::default());
+  // Move the cursor to the target of a jump, i.e. `%`, the cursor position before the jump is
+  // recorded to the jump list. The `G`/`gg` are handled by `goto_line`.
+  fn jump(
+    &self,
+    state: &mut State,
+    tree: &mut Tree,
+    target: impl FnOnce(&Buffer, BufferPosition) -> Option<BufferPosition>,
+  ) {
+    let buffer = match current_buffer(tree) {
+      Some(buffer) => buffer,
+      None => return,
+    };
+    let cursor = match cursor_position(tree) {
+      Some(cursor) => cursor,
+      None => return,
+    };
+    let next_cursor = match target(&rlock!(buffer), cursor) {
+      Some(next_cursor) => next_cursor,
+      None => return,
+    };
+    state.push_jump(tree, cursor);
+    if next_cursor != cursor {
+      jump_cursor(tree, next_cursor, None);
     }
+  }
+
+  // Walk back (`Ctrl-O`) or forward (`Ctrl-I`) `count` jumps in the jump list. The jumps in other
+  // buffers are skipped since the window doesn't switch buffers yet.
+  fn walk_jump_list(&self, state: &mut State, tree: &mut Tree, back: bool, count: usize) {
+    let buffer = match current_buffer(tree) {
+      Some(buffer) => buffer,
+      None => return,
+    };
+    let cursor = match cursor_position(tree) {
+      Some(cursor) => cursor,
+      None => return,
+    };
+    let buffer_id = rlock!(buffer).id();
+    let jump = if back {
+      state
+        .jump_list_mut()
+        .back(Jump::new(buffer_id, cursor), count)
+    } else {
+      state.jump_list_mut().forward(count)
+    };
+    let next_cursor = match jump {
+      Some(jump) if jump.buffer_id() == buffer_id => {
+        clamp_position(&rlock!(buffer), jump.position())
+      }
+      _ => return,
+    };
+    if next_cursor != cursor {
+      jump_cursor(tree, next_cursor, None);
+    }
+  }
+
+  // Walk back (`g;`) or forward (`g,`) `count` changes in the change list of current buffer. The
+  // position is clamped into the buffer, i.e. the line of the change was deleted.
+  fn walk_change_list(&self, state: &mut State, tree: &mut Tree, back: bool, count: usize) {
+    let buffer = match current_buffer(tree) {
+      Some(buffer) => buffer,
+      None => return,
+    };
+    let cursor = match cursor_position(tree) {
+      Some(cursor) => cursor,
+      None => return,
+    };
+    let next_cursor = {
+      let mut buffer = wlock!(buffer);
+      if buffer.change_list().is_empty() {
+        state.echoerr("E664: Changelist is empty");
+        return;
+      }
+      let change = if back {
+        buffer.change_list_mut().older(count)
+      } else {
+        buffer.change_list_mut().newer(count)
+      };
+      match change {
+        Some((line_idx, char_idx)) => {
+          clamp_position(&buffer, BufferPosition::new(line_idx, char_idx))
+        }
+        None if back => {
+          state.echoerr("E662: At start of changelist");
+          return;
+        }
+        None => {
+          state.echoerr("E663: At end of changelist");
+          return;
+        }
+      }
+    };
+    if next_cursor != cursor {
+      jump_cursor(tree, next_cursor, None);
+    }
+  }
+
+  // Open the entry at the cursor line of the directory buffer, i.e. `Enter` in netrw. The file or
+  // the directory is opened in current window, with its existing buffer if any, see
+  // [`switch_buffer`].
+  fn open_dir_entry(&self, state: &mut State, tree: &mut Tree, buffers: &BuffersManagerArc) {
+    let buffer = match current_buffer(tree) {
+      Some(buffer) => buffer,
+      None => return,
+    };
+    let cursor = match cursor_position(tree) {
+      Some(cursor) => cursor,
+      None => return,
+    };
+    let entry = match rlock!(buffer)
+      .dir_listing()
+      .and_then(|listing| listing.entry(cursor.line_idx()))
+    {
+      Some(entry) => entry.clone(),
+      None => return,
+    };
+    if !entry.openable() {
+      state.echoerr(&format!("Cannot open {}", entry.line()));
+      return;
+    }
+
+    let next_buffer = {
+      let mut buffers = wlock!(buffers);
+      let buf_id = match buffers.find_file_buffer(entry.path()) {
+        Some(buf_id) => {
+          // Re-read the directory, it may have changed since it was opened.
+          let buf = buffers.get(&buf_id).unwrap();
+          if let Err(e) = wlock!(buf).refresh_dir_listing() {
+            state.echoerr(&format!("Failed to read {:?}: {}", entry.path(), e));
+            return;
+          }
+          buf_id
+        }
+        None => match buffers.new_file_buffer(entry.path()) {
+          Ok(buf_id) => buf_id,
+          Err(e) => {
+            state.echoerr(&format!("Failed to open {:?}: {}", entry.path(), e));
+            return;
+          }
+        },
+      };
+      buffers.get(&buf_id).unwrap().clone()
+    };
+    switch_buffer(tree, &next_buffer);
+  }
+
+  // Toggle the 'showhidden' option of the directory buffer, i.e. `gh` in netrw, the listing is
+  // re-read with it.
+  fn toggle_hidden(&self, state: &mut State, tree: &mut Tree) {
+    let buffer = match current_buffer(tree) {
+      Some(buffer) => buffer,
+      None => return,
+    };
+    let cursor = match cursor_position(tree) {
+      Some(cursor) => cursor,
+      None => return,
+    };
+    {
+      let mut buffer = wlock!(buffer);
+      if buffer.kind() != BufferKind::Directory {
+        return;
+      }
+      let show_hidden = !buffer.show_hidden();
+      buffer.set_show_hidden(show_hidden);
+      if let Err(e) = buffer.refresh_dir_listing() {
+        state.echoerr(&e.to_string());
+        return;
+      }
+    }
+    sync_viewport(tree);
+    let next_cursor = clamp_position(&rlock!(buffer), cursor);
+    sync_cursor(tree, cursor, next_cursor);
+  }
+
+  // Check whether the files of the buffers in the windows are changed outside the editor, i.e.
+  // the editor gains focus. Same with Vim, a change is warned only once, and `:edit!` reloads it.
+  fn check_external_changes(&self, state: &mut State, tree: &Tree) {
+    for window_id in tree.window_ids().iter() {
+      let buffer = match tree.node(window_id) {
+        Some(TreeNode::Window(window)) => window.buffer().upgrade(),
+        _ => None,
+      };
+      let buffer = match buffer {
+        Some(buffer) => buffer,
+        None => continue,
+      };
+      let mut buffer = wlock!(buffer);
+      let last_change = buffer.external_change();
+      let change = buffer.check_external_change();
+      if change == last_change {
+        continue;
+      }
+      let filename = buffer.filename().clone().unwrap_or_default();
+      match change {
+        ExternalChange::Modified if buffer.is_modified() => state.echoerr(&format!(
+          "W12: Warning: File \"{}\" has changed and the buffer was changed in Rsvim as well",
+          filename.display()
+        )),
+        ExternalChange::Modified => state.echoerr(&format!(
+          "W11: Warning: File \"{}\" has changed since editing started, :edit! to reload",
+          filename.display()
+        )),
+        ExternalChange::Deleted => state.echoerr(&format!(
+          "E211: File \"{}\" no longer available",
+          filename.display()
+        )),
+        ExternalChange::Unchanged => {}
+      }
+    }
+  }
+
+  // Put the register content after (`p`) or before (`P`) the cursor. The register `+`/`*` is put
+  // after the event loop reads the clipboard, or from its internal copy if the clipboard is not
+  // readable.
+  fn put(&self, state: &mut State, tree: &mut Tree, after: bool) {
+    let selected_register_name = state.take_register_name();
+    let register_name = selected_register_name.unwrap_or(UNNAMED_REGISTER);
+    if Registers::is_clipboard_name(register_name) {
+      if state.registers().clipboard_readable() {
+        state.push_pending_clipboard_put(ClipboardPut::new(register_name, after));
+        return;
+      }
+      if state.registers().clipboard().is_none() {
+        state.registers_mut().clipboard_unavailable();
+      }
+    }
+    NormalStateful::put_register(state, tree, selected_register_name, after);
+  }
+
+  /// Put the register `selected_register_name` (or the unnamed register if it's `None`) after
+  /// (`p`) or before (`P`) the cursor. For linewise content, it is put as new lines below/above
+  /// the cursor line.
+  pub fn put_register(
+    state: &mut State,
+    tree: &mut Tree,
+    selected_register_name: Option<char>,
+    after: bool,
+  ) {
+    let register_name = selected_register_name.unwrap_or(UNNAMED_REGISTER);
+    let register = match state.registers().get(register_name) {
+      Some(register) if !register.text().is_empty() => register.clone(),
+      _ => return,
+    };
+    let buffer = match current_buffer(tree) {
+      Some(buffer) => buffer,
+      None => return,
+    };
+    let cursor = match cursor_position(tree) {
+      Some(cursor) => cursor,
+      None => return,
+    };
+
+    let next_cursor = {
+      let mut buffer = wlock!(buffer);
+      let line_idx = cursor.line_idx();
+      let line_start = buffer.line_to_char(line_idx);
+      let line_len = buffer.get_line(line_idx).map_or(0, |l| l.len_chars());
+      let text_len = line_text_len(&buffer, line_idx);
+      let (char_idx, text, next_cursor) = match register.kind() {
+        RegisterKind::Linewise if after => {
+          if text_len < line_len {
+            (
+              line_start + line_len,
+              register.text().to_string(),
+              BufferPosition::new(line_idx + 1, 0),
+            )
+          } else {
+            // The last line doesn't have a line break.
+            let text = format!("\n{}", register.text().trim_end_matches('\n'));
+            (
+              line_start + line_len,
+              text,
+              BufferPosition::new(line_idx + 1, 0),
+            )
+          }
+        }
+        RegisterKind::Linewise => (
+          line_start,
+          register.text().to_string(),
+          BufferPosition::new(line_idx, 0),
+        ),
+        RegisterKind::Charwise => {
+          let char_idx = if after {
+            std::cmp::min(cursor.char_idx() + 1, text_len)
+          } else {
+            std::cmp::min(cursor.char_idx(), text_len)
+          };
+          let text = register.text().to_string();
+          // The cursor stops at the last char of the put text, or its first char if the text
+          // contains multiple lines.
+          let next_char_idx = if text.contains('\n') {
+            char_idx
+          } else {
+            char_idx + text.chars().count() - 1
+          };
+          (
+            line_start + char_idx,
+            text,
+            BufferPosition::new(line_idx, next_char_idx),
+          )
+        }
+      };
+      if let Err(e) = buffer.insert(char_idx, &text) {
+        error!("Failed to put register {:?}:{:?}", register_name, e);
+        state.echoerr(&e.to_string());
+        return;
+      }
+      next_cursor
+    };
+    state.change_recorder_mut().record(Change::new(
+      ChangeKind::Put { after },
+      None,
+      selected_register_name,
+    ));
+
+    sync_viewport(tree);
+    sync_cursor(tree, cursor, next_cursor);
+  }
+
+  // Replace `count` chars from the cursor with `c`, i.e. `r{char}`, the cursor stops at the last
+  // replaced char. Like Vim, it fails if there're less than `count` chars from the cursor to the
+  // end of line, and `r<CR>` replaces the chars with a single line break.
+  //
+  // See: <https://vimhelp.org/change.txt.html#r>.
+  fn replace_chars(&self, state: &mut State, tree: &mut Tree, c: char, count: usize) {
+    let buffer = match current_buffer(tree) {
+      Some(buffer) => buffer,
+      None => return,
+    };
+    let cursor = match cursor_position(tree) {
+      Some(cursor) => cursor,
+      None => return,
+    };
+    let count = std::cmp::max(count, 1);
 
+    let next_cursor = {
+      let mut buffer = wlock!(buffer);
+      let line_idx = cursor.line_idx();
+      let end_char_idx = cursor.char_idx() + count;
+      if end_char_idx > line_text_len(&buffer, line_idx) {
+        return;
+      }
+      let (text, next_cursor) = if c == '\n' {
+        ("\n".to_string(), BufferPosition::new(line_idx + 1, 0))
+      } else {
+        (
+          c.to_string().repeat(count),
+          BufferPosition::new(line_idx, end_char_idx - 1),
+        )
+      };
+      if let Err(e) = buffer.replace_range(line_idx, cursor.char_idx(), end_char_idx, &text) {
+        error!("Failed to replace chars at {:?}:{:?}", cursor, e);
+        state.echoerr(&e.to_string());
+        return;
+      }
+      next_cursor
+    };
+    state.change_recorder_mut().record(Change::new(
+      ChangeKind::ReplaceChar { c },
+      Some(count),
+      None,
+    ));
+
+    sync_viewport(tree);
+    sync_cursor(tree, cursor, next_cursor);
+  }
+
+  // Repeat the last change at the cursor, i.e. `.`, the count overrides the count of the change.
+  // The change is replayed through the same modes as it was made, then it's recorded as the last
+  // change with the new count.
+  //
+  // See: <https://vimhelp.org/repeat.txt.html#.>.
+  fn repeat(
+    &self,
+    state: &mut State,
+    tree: &TreeArc,
+    buffers: &BuffersManagerArc,
+    count: Option<usize>,
+  ) {
+    let change = match state.change_recorder().last() {
+      Some(change) => change.clone(),
+      None => return,
+    };
+    let count = count.or(change.count());
+    let register_name = state.take_register_name().or(change.register_name());
+    // The keys are replayed in one go, the intermediate states are never rendered.
+    let send_key = |state: &mut State, stateful: StatefulValue, code: KeyCode| {
+      let event = Event::Key(KeyEvent::new(code, KeyModifiers::NONE));
+      stateful.handle(StatefulDataAccess::new(
+        state,
+        tree.clone(),
+        buffers.clone(),
+        event,
+      ))
+    };
+    let insert = StatefulValue::InsertMode(InsertStateful::default());
+    match change.kind() {
+      ChangeKind::Insert => {
+        for _ in 0..count.unwrap_or(1) {
+          for code in change.inserted() {
+            send_key(state, insert, *code);
+          }
+        }
+        send_key(state, insert, KeyCode::Esc);
+      }
+      ChangeKind::Replace => {
+        let replace = StatefulValue::ReplaceMode(ReplaceStateful::default());
+        for _ in 0..count.unwrap_or(1) {
+          for code in change.inserted() {
+            send_key(state, replace, *code);
+          }
+        }
+        send_key(state, replace, KeyCode::Esc);
+      }
+      ChangeKind::ReplaceChar { c } => {
+        let mut tree = wlock!(tree);
+        self.replace_chars(state, &mut tree, c, count.unwrap_or(1));
+      }
+      ChangeKind::Operator { operator, motion } => {
+        state.set_register_name(register_name);
+        state.set_pending_operator(Some((operator, count)));
+        let operator_pending =
+          StatefulValue::OperatorPendingMode(OperatorPendingStateful::default());
+        let next_stateful = send_key(state, operator_pending, KeyCode::Char(motion));
+        if let StatefulValue::InsertMode(_) = next_stateful {
+          for code in change.inserted() {
+            send_key(state, insert, *code);
+          }
+          send_key(state, insert, KeyCode::Esc);
+        }
+      }
+      ChangeKind::Put { after } => {
+        let mut tree = wlock!(tree);
+        for _ in 0..count.unwrap_or(1) {
+          state.set_register_name(register_name);
+          self.put(state, &mut tree, after);
+        }
+      }
+    }
+    state.change_recorder_mut().record(change.with_count(count));
+  }
+
+  // Execute the register `count` times, i.e. `@{reg}`. The keys are queued in the typeahead, the
+  // event loop replays them as if they're typed.
+  //
+  // See: <https://vimhelp.org/repeat.txt.html#%40>.
+  fn execute_register(&self, state: &mut State, name: char, count: usize) -> StatefulValue {
+    let name = match name {
+      '@' => match state.macro_recorder().last_executed() {
+        Some(name) => name,
+        None => {
+          state.echoerr("E748: No previously used register");
+          return StatefulValue::NormalMode(NormalStateful::default());
+        }
+      },
+      name => name,
+    };
+    let keys = match state.registers().get(name) {
+      Some(register) => text_to_keys(register.text()),
+      None => return StatefulValue::NormalMode(NormalStateful::default()),
+    };
+    let depth = state.macro_recorder().depth();
+    if depth >= MAX_EXECUTE_DEPTH {
+      // The rest queued keys are dropped as well.
+      state.macro_recorder_mut().clear_typeahead();
+      state.echoerr("E169: Command too recursive");
+      return StatefulValue::NormalMode(NormalStateful::default());
+    }
+    state.macro_recorder_mut().set_last_executed(name);
+
+    let keys: Vec<KeyEvent> = std::iter::repeat(keys).take(count).flatten().collect();
+    state.macro_recorder_mut().feed(&keys, depth + 1);
     StatefulValue::NormalMode(NormalStateful::default())
   }
 }
 
+// Get the viewport of current window.
+fn focused_viewport(tree: &Tree) -> Option<ViewportArc> {
+  let window_id = tree.focused_window_id()?;
+  match tree.node(&window_id) {
+    Some(TreeNode::Window(window)) => Some(window.viewport()),
+    _ => None,
+  }
+}
+
+// The cursor stays on the first line of a closed fold, thus a forward motion that cannot leave the
+// fold moves to the line after it, same with Vim that the closed fold behaves like a single line.
+fn step_over_fold(
+  tree: &Tree,
+  buffer: &Buffer,
+  cursor: BufferPosition,
+  next_cursor: BufferPosition,
+) -> BufferPosition {
+  let fold = match focused_viewport(tree) {
+    Some(viewport) => rlock!(viewport).folds().closed_fold_at(cursor.line_idx()),
+    None => None,
+  };
+  match fold {
+    Some(fold)
+      if next_cursor > cursor
+        && fold.contains(&next_cursor.line_idx())
+        && fold.end <= buffer.last_line_idx() =>
+    {
+      BufferPosition::new(fold.end, buffer.first_non_blank_char(fold.end))
+    }
+    _ => next_cursor,
+  }
+}
+
 //impl NormalStateful {
 //  fn handle_cursor_move(&self, data_access: StatefulDataAccess, command: Command) {
 //    let _state = data_access.state;
@@ -193,3 +1154,760 @@ impl Stateful for NormalStateful {
 //
 //  fn quit(&self, data_access: StatefulDataAccess) {}
 //}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use crate::buf::{BufferArc, BuffersManager};
+  use crate::cart::U16Size;
+  use crate::state::clipboard::ClipboardProvider;
+  use crate::state::message::Message;
+  use crate::state::register::{CLIPBOARD_WARNING, SELECTION_REGISTER};
+  use crate::test::clipboard::MockProvider;
+  use crate::test::render::snapshot;
+  use crate::test::tree::{make_tree, make_window_tree};
+  use crate::ui::tree::TreeNodeId;
+
+  use std::sync::Arc;
+
+  // Type the keys, the `\x0f` is `Ctrl-O`, the `\t` is `Tab` (i.e. `Ctrl-I`) and the `\x1b` is
+  // `Esc`.
+  fn type_keys(state: &mut State, tree: &TreeArc, keys: &str) {
+    let buffers = BuffersManager::to_arc(BuffersManager::new());
+    type_keys_in(state, tree, &buffers, keys);
+  }
+
+  // The keys queued by the register executions are replayed after each key, like the event loop.
+  fn type_keys_in(state: &mut State, tree: &TreeArc, buffers: &BuffersManagerArc, keys: &str) {
+    for c in keys.chars() {
+      let key_event = match c {
+        '\n' => KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE),
+        '\t' => KeyEvent::new(KeyCode::Tab, KeyModifiers::NONE),
+        '\x0f' => KeyEvent::new(KeyCode::Char('o'), KeyModifiers::CONTROL),
+        '\x17' => KeyEvent::new(KeyCode::Char('w'), KeyModifiers::CONTROL),
+        '\x1b' => KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE),
+        c => KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE),
+      };
+      state.handle(tree.clone(), buffers.clone(), Event::Key(key_event));
+      while let Some(key_event) = state.macro_recorder_mut().next_key() {
+        state.handle(tree.clone(), buffers.clone(), Event::Key(key_event));
+      }
+    }
+  }
+
+  fn cursor_of(tree: &TreeArc) -> (usize, usize) {
+    let cursor = cursor_position(&rlock!(tree)).unwrap();
+    (cursor.line_idx(), cursor.char_idx())
+  }
+
+  fn make_lines(n: usize) -> Vec<String> {
+    (0..n).map(|i| format!("line {}\n", i)).collect()
+  }
+
+  #[test]
+  fn jump_list1() {
+    let lines = make_lines(20);
+    let (tree, _buffer) = make_tree(lines.iter().map(|l| l.as_str()).collect());
+    let mut state = State::default();
+
+    type_keys(&mut state, &tree, "G");
+    assert_eq!(cursor_of(&tree), (19, 0));
+    type_keys(&mut state, &tree, "5G");
+    assert_eq!(cursor_of(&tree), (4, 0));
+    type_keys(&mut state, &tree, "/line 12\n");
+    assert_eq!(cursor_of(&tree), (12, 0));
+    assert_eq!(state.jump_list().len(), 3);
+
+    // Walk back, and stop at the oldest jump.
+    type_keys(&mut state, &tree, "\x0f");
+    assert_eq!(cursor_of(&tree), (4, 0));
+    type_keys(&mut state, &tree, "\x0f");
+    assert_eq!(cursor_of(&tree), (19, 0));
+    type_keys(&mut state, &tree, "\x0f\x0f");
+    assert_eq!(cursor_of(&tree), (0, 0));
+
+    // Walk forward, back to where it starts walking.
+    type_keys(&mut state, &tree, "\t");
+    assert_eq!(cursor_of(&tree), (19, 0));
+    type_keys(&mut state, &tree, "2\t");
+    assert_eq!(cursor_of(&tree), (12, 0));
+    type_keys(&mut state, &tree, "\t");
+    assert_eq!(cursor_of(&tree), (12, 0));
+
+    // Jumping after walking back truncates the newer jumps.
+    type_keys(&mut state, &tree, "2\x0f");
+    assert_eq!(cursor_of(&tree), (19, 0));
+    type_keys(&mut state, &tree, "gg");
+    assert_eq!(cursor_of(&tree), (0, 0));
+    type_keys(&mut state, &tree, "\t");
+    assert_eq!(cursor_of(&tree), (0, 0));
+    type_keys(&mut state, &tree, "\x0f");
+    assert_eq!(cursor_of(&tree), (19, 0));
+  }
+
+  #[test]
+  fn jump_list2() {
+    let lines = make_lines(20);
+    let (tree, buffer) = make_tree(lines.iter().map(|l| l.as_str()).collect());
+    let mut state = State::default();
+
+    type_keys(&mut state, &tree, "10Ggg");
+    assert_eq!(cursor_of(&tree), (0, 0));
+
+    // The jumps follow the deleted lines.
+    type_keys(&mut state, &tree, "dd");
+    assert_eq!(rlock!(buffer).get_line(0).unwrap().to_string(), "line 1\n");
+    type_keys(&mut state, &tree, "\x0f");
+    assert_eq!(cursor_of(&tree), (8, 0));
+    assert_eq!(rlock!(buffer).get_line(8).unwrap().to_string(), "line 9\n");
+  }
+
+  fn buffer_text(buffer: &BufferArc) -> String {
+    let mut text: Vec<u8> = vec![];
+    rlock!(buffer).write_to(&mut text).unwrap();
+    String::from_utf8(text).unwrap()
+  }
+
+  fn cursor_col(tree: &TreeArc) -> isize {
+    let tree = rlock!(tree);
+    let cursor_id = tree.cursor_id().unwrap();
+    tree.node(&cursor_id).unwrap().shape().min().x
+  }
+
+  #[test]
+  fn replace_char1() {
+    let (tree, buffer) = make_tree(vec!["a你b\n"]);
+    let mut state = State::default();
+    let first_row = |tree: &TreeArc| snapshot(&rlock!(tree), U16Size::new(20, 10))[0].clone();
+
+    // The wide char is replaced with an ASCII char, the cursor stays on it.
+    type_keys(&mut state, &tree, "lrx");
+    assert_eq!(rlock!(buffer).get_line(0).unwrap().to_string(), "axb\n");
+    assert_eq!(cursor_of(&tree), (0, 1));
+    assert_eq!(first_row(&tree), format!("axb{}", " ".repeat(17)));
+    type_keys(&mut state, &tree, "l");
+    assert_eq!(cursor_col(&tree), 2);
+
+    // The ASCII char is replaced with a wide char, the next char moves right.
+    type_keys(&mut state, &tree, "hr好");
+    assert_eq!(rlock!(buffer).get_line(0).unwrap().to_string(), "a好b\n");
+    assert_eq!(cursor_col(&tree), 1);
+    assert_eq!(first_row(&tree), format!("a好b{}", " ".repeat(16)));
+    type_keys(&mut state, &tree, "$");
+    assert_eq!(cursor_of(&tree), (0, 2));
+    assert_eq!(cursor_col(&tree), 3);
+
+    // `Esc` cancels it.
+    type_keys(&mut state, &tree, "r\x1b");
+    assert_eq!(rlock!(buffer).get_line(0).unwrap().to_string(), "a好b\n");
+    assert!(state.take_quit_request().is_none());
+  }
+
+  #[test]
+  fn replace_char2() {
+    let (tree, buffer) = make_tree(vec!["abcd\n", "\n", "ef\n"]);
+    let mut state = State::default();
+
+    // The cursor stops at the last replaced char.
+    type_keys(&mut state, &tree, "2rx");
+    assert_eq!(buffer_text(&buffer), "xxcd\n\nef\n");
+    assert_eq!(cursor_of(&tree), (0, 1));
+
+    // Fails if there're not enough chars to replace.
+    type_keys(&mut state, &tree, "4ry");
+    assert_eq!(buffer_text(&buffer), "xxcd\n\nef\n");
+    assert_eq!(cursor_of(&tree), (0, 1));
+    type_keys(&mut state, &tree, "3ry");
+    assert_eq!(buffer_text(&buffer), "xyyy\n\nef\n");
+    assert_eq!(cursor_of(&tree), (0, 3));
+
+    // Refuses on an empty line.
+    type_keys(&mut state, &tree, "jrz");
+    assert_eq!(buffer_text(&buffer), "xyyy\n\nef\n");
+
+    // Repeat with the count of the last replace.
+    type_keys(&mut state, &tree, "j0.");
+    assert_eq!(buffer_text(&buffer), "xyyy\n\nef\n");
+    type_keys(&mut state, &tree, "2.");
+    assert_eq!(buffer_text(&buffer), "xyyy\n\nyy\n");
+
+    // `r<CR>` replaces the chars with a line break.
+    type_keys(&mut state, &tree, "gg0r\n");
+    assert_eq!(buffer_text(&buffer), "\nyyy\n\nyy\n");
+    assert_eq!(cursor_of(&tree), (1, 0));
+  }
+
+  #[test]
+  fn delete_char1() {
+    let (tree, buffer) = make_tree(vec!["abcd\n", "\n", "ef\n"]);
+    let mut state = State::default();
+
+    type_keys(&mut state, &tree, "lx");
+    assert_eq!(buffer_text(&buffer), "acd\n\nef\n");
+    assert_eq!(cursor_of(&tree), (0, 1));
+    assert_eq!(state.registers().get(UNNAMED_REGISTER).unwrap().text(), "b");
+
+    // Stops at the end of line, the cursor stays on the last char.
+    type_keys(&mut state, &tree, "5x");
+    assert_eq!(buffer_text(&buffer), "a\n\nef\n");
+    assert_eq!(cursor_of(&tree), (0, 0));
+
+    // Refuses on an empty line.
+    type_keys(&mut state, &tree, "jx");
+    assert_eq!(buffer_text(&buffer), "a\n\nef\n");
+
+    // Repeat with the count of the last delete.
+    type_keys(&mut state, &tree, "j.");
+    assert_eq!(buffer_text(&buffer), "a\n\n\n");
+  }
+
+  #[test]
+  fn not_modifiable1() {
+    let (tree, buffer) = make_tree(vec!["foo\n"]);
+    let buffers = BuffersManager::to_arc(BuffersManager::new());
+    let mut state = State::default();
+
+    type_keys_in(&mut state, &tree, &buffers, ":set nomodifiable\n");
+    assert!(!rlock!(buffer).modifiable());
+    type_keys_in(&mut state, &tree, &buffers, "x");
+    assert_eq!(buffer_text(&buffer), "foo\n");
+    assert_eq!(
+      state.message().clone(),
+      Some(Message::error(
+        "E21: Cannot make changes, 'modifiable' is off"
+      ))
+    );
+
+    type_keys_in(&mut state, &tree, &buffers, ":set modifiable\nx");
+    assert_eq!(buffer_text(&buffer), "oo\n");
+  }
+
+  #[test]
+  fn put_clipboard1() {
+    let (tree, buffer) = make_tree(vec!["foo\n", "bar\n"]);
+    let mut state = State::default();
+    let line = |line_idx: usize| rlock!(buffer).get_line(line_idx).unwrap().to_string();
+
+    // Without the system clipboard, the register `+` is an internal register, and the warning is
+    // only shown once.
+    type_keys(&mut state, &tree, "\"+yy");
+    assert_eq!(
+      state.message().clone(),
+      Some(Message::error(CLIPBOARD_WARNING))
+    );
+    type_keys(&mut state, &tree, "j\"+p");
+    assert_eq!(state.message().clone(), None);
+    assert_eq!(line(2), "foo\n");
+    assert_eq!(cursor_of(&tree), (2, 0));
+
+    // With the system clipboard, the put waits for the event loop to read the clipboard.
+    let provider = Arc::new(MockProvider::default());
+    *provider.text.write() = "baz".to_string();
+    state.registers_mut().set_clipboard(Some(provider.clone()));
+    type_keys(&mut state, &tree, "gg\"*P");
+    let puts = state.take_pending_clipboard_puts();
+    assert_eq!(puts, vec![ClipboardPut::new(SELECTION_REGISTER, false)]);
+    assert_eq!(line(0), "foo\n");
+    let text = provider
+      .read(SELECTION_REGISTER, Duration::from_secs(5))
+      .unwrap();
+    state
+      .registers_mut()
+      .set_clipboard_content(SELECTION_REGISTER, text);
+    NormalStateful::put_register(
+      &mut state,
+      &mut wlock!(tree),
+      Some(puts[0].register_name()),
+      puts[0].after(),
+    );
+    assert_eq!(line(0), "bazfoo\n");
+    assert_eq!(cursor_of(&tree), (0, 2));
+  }
+
+  #[test]
+  fn goto_line1() {
+    let lines: Vec<String> = (0..100).map(|i| format!("\t line {}\n", i)).collect();
+    let (tree, _buffer) = make_tree(lines.iter().map(|l| l.as_str()).collect());
+    let mut state = State::default();
+    let start_line_of = |tree: &TreeArc| {
+      let tree = rlock!(tree);
+      let viewport = match tree.node(&tree.focused_window_id().unwrap()) {
+        Some(TreeNode::Window(window)) => window.viewport(),
+        _ => unreachable!(),
+      };
+      let start_line_idx = rlock!(viewport).start_line_idx();
+      start_line_idx
+    };
+
+    // On screen, the viewport doesn't scroll.
+    type_keys(&mut state, &tree, "5G");
+    assert_eq!(cursor_of(&tree), (4, 2));
+    assert_eq!(start_line_of(&tree), 0);
+
+    // Just offscreen below, the line is put at the bottom.
+    type_keys(&mut state, &tree, "15G");
+    assert_eq!(cursor_of(&tree), (14, 2));
+    assert_eq!(start_line_of(&tree), 5);
+
+    // Far offscreen, the line is put at the center.
+    type_keys(&mut state, &tree, "50G");
+    assert_eq!(cursor_of(&tree), (49, 2));
+    assert_eq!(start_line_of(&tree), 45);
+
+    // Just offscreen above, the line is put at the top.
+    type_keys(&mut state, &tree, "40G");
+    assert_eq!(cursor_of(&tree), (39, 2));
+    assert_eq!(start_line_of(&tree), 39);
+
+    // The `:{number}` is the same with `{number}G`.
+    type_keys(&mut state, &tree, ":10\n");
+    assert_eq!(cursor_of(&tree), (9, 2));
+    assert_eq!(start_line_of(&tree), 5);
+    type_keys(&mut state, &tree, "gg");
+    type_keys(&mut state, &tree, "10G");
+    assert_eq!(cursor_of(&tree), (9, 2));
+    assert_eq!(start_line_of(&tree), 0);
+
+    // The out of range line stops at the last line.
+    type_keys(&mut state, &tree, "200G");
+    assert_eq!(cursor_of(&tree), (99, 2));
+    type_keys(&mut state, &tree, "gg");
+    assert_eq!(cursor_of(&tree), (0, 2));
+    assert_eq!(start_line_of(&tree), 0);
+    type_keys(&mut state, &tree, ":200\n");
+    assert_eq!(cursor_of(&tree), (99, 2));
+    type_keys(&mut state, &tree, "G");
+    assert_eq!(cursor_of(&tree), (99, 2));
+  }
+
+  #[test]
+  fn jump_list3() {
+    let (tree, _buffer) = make_tree(vec!["foo(bar\n", "baz) {}\n", "qux\n"]);
+    let mut state = State::default();
+
+    type_keys(&mut state, &tree, "%");
+    assert_eq!(cursor_of(&tree), (1, 3));
+    type_keys(&mut state, &tree, "%");
+    assert_eq!(cursor_of(&tree), (0, 3));
+    type_keys(&mut state, &tree, "100%");
+    assert_eq!(cursor_of(&tree), (2, 0));
+    // The jump at line 0 is replaced by the newer jump at the same line.
+    type_keys(&mut state, &tree, "\x0f");
+    assert_eq!(cursor_of(&tree), (0, 3));
+    type_keys(&mut state, &tree, "\x0f");
+    assert_eq!(cursor_of(&tree), (1, 3));
+  }
+
+  #[test]
+  fn change_list1() {
+    let lines = make_lines(20);
+    let (tree, buffer) = make_tree(lines.iter().map(|l| l.as_str()).collect());
+    let mut state = State::default();
+
+    type_keys(&mut state, &tree, "3Gdw");
+    // The consecutive edits on the same line are merged.
+    type_keys(&mut state, &tree, "10Giab\x1b");
+    type_keys(&mut state, &tree, "15Gdd");
+    assert_eq!(
+      rlock!(buffer)
+        .change_list()
+        .changes()
+        .copied()
+        .collect::<Vec<_>>(),
+      vec![(2, 0), (9, 1), (14, 0)]
+    );
+    type_keys(&mut state, &tree, "gg");
+
+    type_keys(&mut state, &tree, "g;");
+    assert_eq!(cursor_of(&tree), (14, 0));
+    type_keys(&mut state, &tree, "g;");
+    assert_eq!(cursor_of(&tree), (9, 1));
+    type_keys(&mut state, &tree, "g;");
+    assert_eq!(cursor_of(&tree), (2, 0));
+    type_keys(&mut state, &tree, "g;");
+    assert_eq!(cursor_of(&tree), (2, 0));
+    assert_eq!(
+      state.message().clone(),
+      Some(Message::error("E662: At start of changelist"))
+    );
+
+    type_keys(&mut state, &tree, "g,");
+    assert_eq!(cursor_of(&tree), (9, 1));
+    type_keys(&mut state, &tree, "2g,");
+    assert_eq!(cursor_of(&tree), (9, 1));
+    assert_eq!(
+      state.message().clone(),
+      Some(Message::error("E663: At end of changelist"))
+    );
+    type_keys(&mut state, &tree, "g,");
+    assert_eq!(cursor_of(&tree), (14, 0));
+  }
+
+  #[test]
+  fn change_list2() {
+    let lines = make_lines(5);
+    let (tree, buffer) = make_tree(lines.iter().map(|l| l.as_str()).collect());
+    let mut state = State::default();
+
+    type_keys(&mut state, &tree, "g;");
+    assert_eq!(
+      state.message().clone(),
+      Some(Message::error("E664: Changelist is empty"))
+    );
+
+    type_keys(&mut state, &tree, "5Gdw");
+    type_keys(&mut state, &tree, "ggdw");
+    // The change at the deleted line moves to the line before, and the cursor lands inside the
+    // buffer.
+    type_keys(&mut state, &tree, "4G2dd");
+    assert_eq!(buffer_lines(&buffer), vec!["0\n", "line 1\n", "line 2\n"]);
+    type_keys(&mut state, &tree, "g;");
+    assert_eq!(cursor_of(&tree), (2, 5));
+    type_keys(&mut state, &tree, "g;");
+    assert_eq!(cursor_of(&tree), (0, 0));
+    type_keys(&mut state, &tree, "g;");
+    assert_eq!(cursor_of(&tree), (2, 0));
+  }
+
+  fn first_row_of_line(tree: &TreeArc, line_idx: usize) -> Option<u16> {
+    let viewport = focused_viewport(&rlock!(tree)).unwrap();
+    let viewport = rlock!(viewport);
+    viewport.first_row_of_line(line_idx)
+  }
+
+  #[test]
+  fn fold1() {
+    let lines = make_lines(10);
+    let (tree, _buffer) = make_tree(lines.iter().map(|l| l.as_str()).collect());
+    let mut state = State::default();
+
+    // Fold the lines 2-4, the lines below it shift up.
+    type_keys(&mut state, &tree, "jzf2j");
+    assert!(matches!(state.stateful(), StatefulValue::NormalMode(_)));
+    assert_eq!(cursor_of(&tree), (1, 0));
+    assert_eq!(first_row_of_line(&tree, 1), Some(1));
+    assert_eq!(first_row_of_line(&tree, 2), None);
+    assert_eq!(first_row_of_line(&tree, 4), Some(2));
+
+    // The cursor steps over the closed fold.
+    type_keys(&mut state, &tree, "j");
+    assert_eq!(cursor_of(&tree), (4, 0));
+    type_keys(&mut state, &tree, "k");
+    assert_eq!(cursor_of(&tree), (1, 0));
+    type_keys(&mut state, &tree, "w");
+    assert_eq!(cursor_of(&tree), (4, 0));
+    type_keys(&mut state, &tree, "3G");
+    assert_eq!(cursor_of(&tree), (1, 0));
+
+    type_keys(&mut state, &tree, "zo");
+    assert_eq!(first_row_of_line(&tree, 2), Some(2));
+    type_keys(&mut state, &tree, "jj");
+    assert_eq!(cursor_of(&tree), (3, 0));
+    type_keys(&mut state, &tree, "zc");
+    assert_eq!(cursor_of(&tree), (1, 0));
+    assert_eq!(first_row_of_line(&tree, 4), Some(2));
+    type_keys(&mut state, &tree, "za");
+    assert_eq!(first_row_of_line(&tree, 4), Some(4));
+    type_keys(&mut state, &tree, "za");
+    assert_eq!(first_row_of_line(&tree, 4), Some(2));
+
+    type_keys(&mut state, &tree, "ggzo");
+    assert_eq!(
+      state.message().clone(),
+      Some(Message::error("E490: No fold found"))
+    );
+
+    // Fold 3 lines from the cursor line.
+    type_keys(&mut state, &tree, "7G3zF");
+    assert_eq!(cursor_of(&tree), (6, 0));
+    assert_eq!(first_row_of_line(&tree, 9), Some(5));
+  }
+
+  // The lines of the buffer, without the empty line after the trailing line break.
+  fn buffer_lines(buffer: &BufferArc) -> Vec<String> {
+    let buffer = rlock!(buffer);
+    buffer
+      .lines()
+      .take(buffer.last_line_idx() + 1)
+      .map(|l| l.to_string())
+      .collect()
+  }
+
+  #[test]
+  fn esc1() {
+    let (tree, _buffer) = make_tree(vec!["a\n", "b\n", "c\n"]);
+    let mut state = State::default();
+
+    // `Esc` cancels the count, it doesn't quit the editor.
+    type_keys(&mut state, &tree, "2\x1bj");
+    assert_eq!(cursor_of(&tree), (1, 0));
+    assert!(state.take_quit_request().is_none());
+    assert!(matches!(state.stateful(), StatefulValue::NormalMode(_)));
+
+    // The macro with `Esc` in normal mode is replayed without quitting.
+    state.registers_mut().yank(
+      Some('a'),
+      Register::new(RegisterKind::Charwise, "\x1bj\x1b".to_string()),
+    );
+    type_keys(&mut state, &tree, "@a");
+    assert_eq!(cursor_of(&tree), (2, 0));
+    assert!(state.take_quit_request().is_none());
+  }
+
+  #[test]
+  fn macro1() {
+    let (tree, buffer) = make_tree(vec!["a\n", "b\n", "c\n", "d\n", "e\n", "f\n"]);
+    let mut state = State::default();
+
+    // Record an edit macro, it inserts `x` at the line start and goes to the next line.
+    type_keys(&mut state, &tree, "qa0ix\x1bjq");
+    assert_eq!(state.macro_recorder().recording(), None);
+    assert_eq!(state.registers().get('a').unwrap().text(), "0ix\x1bj");
+    assert_eq!(buffer_lines(&buffer)[..2], ["xa\n", "b\n"]);
+    assert_eq!(cursor_of(&tree), (1, 0));
+
+    // Replay it with count, then repeat the last executed register.
+    type_keys(&mut state, &tree, "2@a");
+    assert_eq!(cursor_of(&tree), (3, 0));
+    type_keys(&mut state, &tree, "@@");
+    assert_eq!(
+      buffer_lines(&buffer),
+      vec!["xa\n", "xb\n", "xc\n", "xd\n", "e\n", "f\n"]
+    );
+    assert_eq!(cursor_of(&tree), (4, 0));
+    assert!(matches!(state.stateful(), StatefulValue::NormalMode(_)));
+    assert!(!state.is_render_suppressed());
+
+    // The recursive macro stops at the max depth.
+    state.registers_mut().yank(
+      Some('b'),
+      Register::new(RegisterKind::Charwise, "@b".to_string()),
+    );
+    type_keys(&mut state, &tree, "@b");
+    assert_eq!(
+      state.message().clone(),
+      Some(Message::error("E169: Command too recursive"))
+    );
+    assert_eq!(state.macro_recorder().depth(), 0);
+  }
+
+  #[test]
+  fn external_change1() {
+    let tmpdir = assert_fs::TempDir::new().unwrap();
+    let filename = tmpdir.path().join("external_change1.txt");
+    std::fs::write(&filename, "Hello\n").unwrap();
+
+    let buffers = BuffersManager::to_arc(BuffersManager::new());
+    let buf_id = wlock!(buffers).new_file_buffer(&filename).unwrap();
+    let buffer = rlock!(buffers).get(&buf_id).unwrap().clone();
+    let tree = make_window_tree(&buffer);
+    let mut state = State::default();
+    state.handle(tree.clone(), buffers.clone(), Event::FocusGained);
+    assert!(state.message().is_none());
+
+    // Modified by another program, it's warned once.
+    std::fs::write(&filename, "Hello\nRSVIM!\n").unwrap();
+    state.handle(tree.clone(), buffers.clone(), Event::FocusGained);
+    assert_eq!(
+      state.message().clone(),
+      Some(Message::error(&format!(
+        "W11: Warning: File \"{}\" has changed since editing started, :edit! to reload",
+        filename.display()
+      )))
+    );
+    state.clear_message();
+    state.handle(tree.clone(), buffers.clone(), Event::FocusGained);
+    assert!(state.message().is_none());
+
+    // Reload it.
+    type_keys_in(&mut state, &tree, &buffers, "jx:edit\n");
+    assert_eq!(
+      state.message().clone(),
+      Some(Message::error(
+        "E37: No write since last change (add ! to override)"
+      ))
+    );
+    type_keys_in(&mut state, &tree, &buffers, ":e!\n");
+    assert_eq!(buffer_lines(&buffer), vec!["Hello\n", "RSVIM!\n"]);
+    assert!(!rlock!(buffer).is_modified());
+    assert_eq!(rlock!(buffer).external_change(), ExternalChange::Unchanged);
+
+    // Deleted by another program.
+    std::fs::remove_file(&filename).unwrap();
+    state.handle(tree.clone(), buffers.clone(), Event::FocusGained);
+    assert_eq!(
+      state.message().clone(),
+      Some(Message::error(&format!(
+        "E211: File \"{}\" no longer available",
+        filename.display()
+      )))
+    );
+  }
+
+  #[test]
+  fn open_dir_entry1() {
+    let tmpdir = assert_fs::TempDir::new().unwrap();
+    std::fs::create_dir(tmpdir.path().join("src")).unwrap();
+    std::fs::write(tmpdir.path().join("src").join("main.rs"), "fn main() {}\n").unwrap();
+    std::fs::write(tmpdir.path().join("README.md"), "readme\n").unwrap();
+    std::fs::write(tmpdir.path().join(".hidden"), "hidden\n").unwrap();
+
+    let buffers = BuffersManager::to_arc(BuffersManager::new());
+    let buf_id = wlock!(buffers).new_file_buffer(tmpdir.path()).unwrap();
+    let buffer = rlock!(buffers).get(&buf_id).unwrap().clone();
+    let tree = make_window_tree(&buffer);
+    let mut state = State::default();
+    assert_eq!(rlock!(buffer).kind(), BufferKind::Directory);
+    assert_eq!(
+      buffer_lines(&buffer),
+      vec!["../\n", "src/\n", "README.md\n"]
+    );
+    assert!(wlock!(buffer).insert(0, "x").is_err());
+
+    // Toggle the hidden entries.
+    type_keys_in(&mut state, &tree, &buffers, "gh");
+    assert_eq!(
+      buffer_lines(&buffer),
+      vec!["../\n", "src/\n", ".hidden\n", "README.md\n"]
+    );
+    type_keys_in(&mut state, &tree, &buffers, "gh");
+    assert_eq!(
+      buffer_lines(&buffer),
+      vec!["../\n", "src/\n", "README.md\n"]
+    );
+
+    // Descend into the subdirectory in the same window.
+    type_keys_in(&mut state, &tree, &buffers, "2G\n");
+    let current = current_buffer(&rlock!(tree)).unwrap();
+    assert_eq!(
+      rlock!(current).absolute_filename().as_deref(),
+      Some(tmpdir.path().join("src").as_path())
+    );
+    assert_eq!(buffer_lines(&current), vec!["../\n", "main.rs\n"]);
+    assert_eq!(cursor_of(&tree), (0, 0));
+
+    // The parent directory is opened with its existing buffer.
+    type_keys_in(&mut state, &tree, &buffers, "gg\n");
+    assert!(Arc::ptr_eq(
+      &current_buffer(&rlock!(tree)).unwrap(),
+      &buffer
+    ));
+    type_keys_in(&mut state, &tree, &buffers, "2G\n");
+    assert!(Arc::ptr_eq(
+      &current_buffer(&rlock!(tree)).unwrap(),
+      &current
+    ));
+    assert_eq!(rlock!(buffers).len(), 2);
+
+    // Open the file, the Enter does nothing in it.
+    type_keys_in(&mut state, &tree, &buffers, "G\n");
+    let current = current_buffer(&rlock!(tree)).unwrap();
+    assert_eq!(rlock!(current).kind(), BufferKind::File);
+    assert_eq!(buffer_lines(&current), vec!["fn main() {}\n", ""]);
+    type_keys_in(&mut state, &tree, &buffers, "\n");
+    assert!(Arc::ptr_eq(
+      &current_buffer(&rlock!(tree)).unwrap(),
+      &current
+    ));
+    assert_eq!(rlock!(buffers).len(), 3);
+  }
+
+  #[test]
+  fn window_command1() {
+    let lines = make_lines(20);
+    let (tree, _buffer) = make_tree(lines.iter().map(|l| l.as_str()).collect());
+    let window1_id = rlock!(tree).focused_window_id().unwrap();
+    let window2_id = wlock!(tree)
+      .split_window(window1_id, SplitDirection::Horizontal)
+      .unwrap();
+    let heights = |tree: &TreeArc| {
+      let tree = rlock!(tree);
+      (
+        tree.node(&window2_id).unwrap().shape().height(),
+        tree.node(&window1_id).unwrap().shape().height(),
+      )
+    };
+    let cursor_row = |tree: &TreeArc| {
+      let tree = rlock!(tree);
+      tree
+        .node(&tree.cursor_id().unwrap())
+        .unwrap()
+        .shape()
+        .min()
+        .y
+    };
+    let mut state = State::default();
+    type_keys(&mut state, &tree, "4j");
+    assert_eq!(cursor_row(&tree), 4);
+
+    // The cursor line is below the shrunk window, the viewport scrolls to it.
+    type_keys(&mut state, &tree, "3\x17-");
+    assert_eq!(heights(&tree), (2, 8));
+    assert_eq!(cursor_of(&tree), (4, 0));
+    assert_eq!(cursor_row(&tree), 0);
+
+    type_keys(&mut state, &tree, "\x17+");
+    assert_eq!(heights(&tree), (3, 7));
+    type_keys(&mut state, &tree, "\x17=");
+    assert_eq!(heights(&tree), (5, 5));
+    assert_eq!(cursor_of(&tree), (4, 0));
+  }
+
+  #[test]
+  fn window_focus1() {
+    let lines = make_lines(40);
+    let (tree, buffer) = make_tree(lines.iter().map(|l| l.as_str()).collect());
+    let window1_id = rlock!(tree).focused_window_id().unwrap();
+    let window2_id = wlock!(tree)
+      .split_window(window1_id, SplitDirection::Horizontal)
+      .unwrap();
+    let start_line_of = |tree: &TreeArc, window_id: TreeNodeId| {
+      let tree = rlock!(tree);
+      let viewport = match tree.node(&window_id) {
+        Some(TreeNode::Window(window)) => window.viewport(),
+        _ => unreachable!(),
+      };
+      let start_line_idx = rlock!(viewport).start_line_idx();
+      start_line_idx
+    };
+    let cursor_row = |tree: &TreeArc| {
+      let tree = rlock!(tree);
+      tree
+        .node(&tree.cursor_id().unwrap())
+        .unwrap()
+        .shape()
+        .min()
+        .y
+    };
+    let mut state = State::default();
+    assert_eq!(rlock!(tree).focused_window_id(), Some(window2_id));
+    type_keys(&mut state, &tree, "20G\x17w30G");
+    assert_eq!(rlock!(tree).focused_window_id(), Some(window1_id));
+    assert_eq!(cursor_of(&tree), (29, 0));
+    let (start_line1, start_line2) = (
+      start_line_of(&tree, window1_id),
+      start_line_of(&tree, window2_id),
+    );
+    assert_ne!(start_line1, start_line2);
+
+    // Each window keeps its own cursor and viewport.
+    type_keys(&mut state, &tree, "\x17w");
+    assert_eq!(rlock!(tree).focused_window_id(), Some(window2_id));
+    assert_eq!(cursor_of(&tree), (19, 0));
+    assert_eq!(start_line_of(&tree, window2_id), start_line2);
+    type_keys(&mut state, &tree, "\x17W");
+    assert_eq!(rlock!(tree).focused_window_id(), Some(window1_id));
+    assert_eq!(cursor_of(&tree), (29, 0));
+    assert_eq!(start_line_of(&tree, window1_id), start_line1);
+    assert_eq!(start_line_of(&tree, window2_id), start_line2);
+
+    // The buffer shrinks, the restored cursor and viewport are clamped.
+    wlock!(buffer).delete_lines(10..40).unwrap();
+    type_keys(&mut state, &tree, "\x17\x17");
+    assert_eq!(rlock!(tree).focused_window_id(), Some(window2_id));
+    assert_eq!(cursor_of(&tree), (9, 0));
+    assert_eq!(start_line_of(&tree, window2_id), 9);
+    assert_eq!(cursor_row(&tree), 0);
+  }
+}
@@ -1,13 +1,767 @@
 //! The operator-pending mode.
 
+use crate::buf::word::{char_class, CharClass};
+use crate::buf::Buffer;
+use crate::envar;
+use crate::state::change::{Change, ChangeKind};
+use crate::state::fsm::command_line::jump_cursor;
+use crate::state::fsm::insert::InsertStateful;
+use crate::state::fsm::normal::NormalStateful;
+use crate::state::fsm::visual::{
+  current_buffer, cursor_position, line_text_len, sync_cursor, sync_viewport,
+};
 use crate::state::fsm::{Stateful, StatefulDataAccess, StatefulValue};
+use crate::state::motion::{Motion, MotionRange, MotionWise};
+use crate::state::register::{Register, RegisterKind};
+use crate::state::selection::BufferPosition;
+use crate::state::State;
+use crate::ui::tree::{Tree, TreeNode};
+use crate::{rlock, wlock};
+
+use crossterm::event::{Event, KeyCode, KeyEventKind};
+use std::ops::Range;
+use tracing::{error, trace};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+/// The operators wait for a motion, and apply to the text spanned by it.
+/// See: <https://vimhelp.org/motion.txt.html#operator>.
+pub enum Operator {
+  /// `d`, delete the text into register.
+  Delete,
+  /// `y`, yank the text into register.
+  Yank,
+  /// `c`, delete the text into register, then start insert mode.
+  Change,
+  /// `gu`, make the text lowercase.
+  Lowercase,
+  /// `gU`, make the text uppercase.
+  Uppercase,
+  /// `g~`, switch the case of the text.
+  ToggleCase,
+  /// `zf`, create a closed fold over the lines.
+  Fold,
+}
+
+impl Operator {
+  /// Get the operator by key, i.e. `d`/`y`/`c`.
+  pub fn from_key(c: char) -> Option<Operator> {
+    match c {
+      'd' => Some(Operator::Delete),
+      'y' => Some(Operator::Yank),
+      'c' => Some(Operator::Change),
+      _ => None,
+    }
+  }
+
+  /// Get the operator by the key after the `g` prefix, i.e. `gu`/`gU`/`g~`.
+  pub fn from_g_key(c: char) -> Option<Operator> {
+    match c {
+      'u' => Some(Operator::Lowercase),
+      'U' => Some(Operator::Uppercase),
+      '~' => Some(Operator::ToggleCase),
+      _ => None,
+    }
+  }
+
+  /// Get the operator by the key after the `z` prefix, i.e. `zf`.
+  pub fn from_z_key(c: char) -> Option<Operator> {
+    match c {
+      'f' => Some(Operator::Fold),
+      _ => None,
+    }
+  }
+
+  /// The key of the operator, press it twice applies to whole lines, i.e. `dd`/`yy`/`cc`. For the
+  /// operators with the `g` prefix, it's the key after the prefix, i.e. `guu`/`gUU`/`g~~`. For
+  /// `zf`, it's `F`, i.e. `zfF` works like `zF`.
+  pub fn key(&self) -> char {
+    match self {
+      Operator::Delete => 'd',
+      Operator::Yank => 'y',
+      Operator::Change => 'c',
+      Operator::Lowercase => 'u',
+      Operator::Uppercase => 'U',
+      Operator::ToggleCase => '~',
+      Operator::Fold => 'F',
+    }
+  }
+
+  /// Whether the operator changes the case of the text, i.e. `gu`/`gU`/`g~`.
+  pub fn is_case_change(&self) -> bool {
+    matches!(
+      self,
+      Operator::Lowercase | Operator::Uppercase | Operator::ToggleCase
+    )
+  }
+
+  /// Change the case of the text, the text is returned as it is for other operators.
+  ///
+  /// NOTE: The case mapping is Unicode-aware, a char can be mapped to multiple chars, i.e. `ß` is
+  /// uppercased to `SS`.
+  pub fn change_case(&self, text: &str) -> String {
+    match self {
+      Operator::Lowercase => text.to_lowercase(),
+      Operator::Uppercase => text.to_uppercase(),
+      Operator::ToggleCase => {
+        let mut changed = String::with_capacity(text.len());
+        for c in text.chars() {
+          if c.is_lowercase() {
+            changed.extend(c.to_uppercase());
+          } else if c.is_uppercase() {
+            changed.extend(c.to_lowercase());
+          } else {
+            changed.push(c);
+          }
+        }
+        changed
+      }
+      _ => text.to_string(),
+    }
+  }
+}
 
 #[derive(Debug, Copy, Clone, Default)]
 /// The operator-pending editing mode.
 pub struct OperatorPendingStateful {}
 
 impl Stateful for OperatorPendingStateful {
-  fn handle(&self, _data_access: StatefulDataAccess) -> StatefulValue {
+  fn handle(&self, data_access: StatefulDataAccess) -> StatefulValue {
+    let state = data_access.state;
+    let tree = data_access.tree;
+    let event = data_access.event;
+
+    if let Event::Key(key_event) = event {
+      if key_event.kind == KeyEventKind::Press {
+        if state.handle_count(key_event.code) {
+          return StatefulValue::OperatorPendingMode(OperatorPendingStateful::default());
+        }
+        // The counts before the operator and the motion are multiplied, i.e. `2d3w` is `d6w`.
+        let motion_count = state.take_count();
+        let (operator, count) = match state.take_pending_operator() {
+          Some(pending) => pending,
+          None => return StatefulValue::NormalMode(NormalStateful::default()),
+        };
+        let count = count.unwrap_or(1).saturating_mul(motion_count.unwrap_or(1));
+
+        let mut tree = wlock!(tree);
+        let (buffer, cursor) = match (current_buffer(&tree), cursor_position(&tree)) {
+          (Some(buffer), Some(cursor)) => (buffer, cursor),
+          _ => return StatefulValue::NormalMode(NormalStateful::default()),
+        };
+        let range = {
+          let buffer = rlock!(buffer);
+          match key_event.code {
+            KeyCode::Char(c) if c == operator.key() => {
+              Some(MotionRange::lines(&buffer, cursor.line_idx(), count))
+            }
+            // The lines from the cursor line to `count` lines below/above it, it fails at the
+            // last/first line.
+            KeyCode::Char('j') if cursor.line_idx() < buffer.last_line_idx() => {
+              Some(MotionRange::lines(&buffer, cursor.line_idx(), count + 1))
+            }
+            KeyCode::Char('k') if cursor.line_idx() > 0 => {
+              let count = std::cmp::min(count, cursor.line_idx());
+              Some(MotionRange::lines(
+                &buffer,
+                cursor.line_idx() - count,
+                count + 1,
+              ))
+            }
+            // The `count` chars from the cursor, it stops at the end of line and fails at the
+            // empty line.
+            KeyCode::Char('l') if cursor.char_idx() < line_text_len(&buffer, cursor.line_idx()) => {
+              let start = buffer.line_to_char(cursor.line_idx()) + cursor.char_idx();
+              let len = std::cmp::min(
+                count,
+                line_text_len(&buffer, cursor.line_idx()) - cursor.char_idx(),
+              );
+              Some(MotionRange::new(start..start + len, MotionWise::Charwise))
+            }
+            KeyCode::Char(c) => Motion::from_key(c).map(|motion| {
+              let range = motion.range(&buffer, cursor, count);
+              match (operator, motion) {
+                (Operator::Change, Motion::NextWordStart { .. }) => {
+                  change_word_range(&buffer, range)
+                }
+                _ => range,
+              }
+            }),
+            // Cancel the operator, i.e. `Esc`.
+            _ => None,
+          }
+        };
+        trace!("Apply operator {:?} to {:?}", operator, range);
+        if let (Some(range), KeyCode::Char(motion)) = (range, key_event.code) {
+          // Record the change for repeating, i.e. `.`. The change operator continues in insert
+          // mode, until leaving it.
+          let change = Change::new(
+            ChangeKind::Operator { operator, motion },
+            Some(count),
+            state.register_name(),
+          );
+          match operator {
+            Operator::Yank | Operator::Fold => {}
+            Operator::Change => state.change_recorder_mut().start(change),
+            _ => state.change_recorder_mut().record(change),
+          }
+          return self.apply(state, &mut tree, operator, cursor, range);
+        }
+        // The invalid motion cancels the operator, and the register selected for it.
+        state.take_register_name();
+        return StatefulValue::NormalMode(NormalStateful::default());
+      }
+    }
+
     StatefulValue::OperatorPendingMode(OperatorPendingStateful::default())
   }
 }
+
+impl OperatorPendingStateful {
+  // Apply the operator to the range, the text is yanked or deleted into the register selected by
+  // the `"{name}` prefix. The case-change operators replace the text in place, without register.
+  // The fold operator creates a closed fold over the lines spanned by the range.
+  fn apply(
+    &self,
+    state: &mut State,
+    tree: &mut Tree,
+    operator: Operator,
+    cursor: BufferPosition,
+    range: MotionRange,
+  ) -> StatefulValue {
+    let buffer = match current_buffer(tree) {
+      Some(buffer) => buffer,
+      None => return StatefulValue::NormalMode(NormalStateful::default()),
+    };
+    let register_name = state.take_register_name();
+    if operator == Operator::Fold {
+      let lines = {
+        let buffer = rlock!(buffer);
+        let char_range = range.char_range();
+        let start_line_idx = buffer.char_to_line(char_range.start);
+        let end_char_idx = std::cmp::max(char_range.end.saturating_sub(1), char_range.start);
+        start_line_idx..buffer.char_to_line(end_char_idx) + 1
+      };
+      create_fold(tree, lines);
+      return StatefulValue::NormalMode(NormalStateful::default());
+    }
+    let register_kind = match range.wise() {
+      MotionWise::Charwise => RegisterKind::Charwise,
+      MotionWise::Linewise => RegisterKind::Linewise,
+    };
+
+    let next_cursor = {
+      let mut buffer = wlock!(buffer);
+      let char_range = range.char_range().clone();
+      let text = match buffer.get_slice(char_range.clone()) {
+        Some(text) => text.to_string(),
+        None => return StatefulValue::NormalMode(NormalStateful::default()),
+      };
+      let register = Register::new(register_kind, text.clone());
+      let start = position_of(&buffer, char_range.start);
+
+      if operator.is_case_change() {
+        let changed = operator.change_case(&text);
+        if changed != text {
+          if let Err(e) = buffer.replace(char_range, &changed) {
+            error!("Failed to apply operator {:?}:{:?}", operator, e);
+            state.echoerr(&e.to_string());
+            return StatefulValue::NormalMode(NormalStateful::default());
+          }
+        }
+        match range.wise() {
+          MotionWise::Charwise => start,
+          MotionWise::Linewise => BufferPosition::new(start.line_idx(), 0),
+        }
+      } else if operator == Operator::Yank {
+        state.registers_mut().yank(register_name, register);
+        match range.wise() {
+          MotionWise::Charwise => std::cmp::min(start, cursor),
+          MotionWise::Linewise => cursor,
+        }
+      } else {
+        // For linewise change, the last line break is kept as an empty line to insert text. For
+        // linewise delete, if the last line is deleted, the line break before it is deleted
+        // instead.
+        let mut removed = char_range.clone();
+        let ends_with_line_break = text.ends_with('\n');
+        match (operator, range.wise()) {
+          (Operator::Change, MotionWise::Linewise) if ends_with_line_break => {
+            removed.end -= 1;
+          }
+          (Operator::Delete, MotionWise::Linewise)
+            if removed.end == buffer.len_chars() && removed.start > 0 =>
+          {
+            removed.start -= 1;
+            if ends_with_line_break {
+              removed.end -= 1;
+            }
+          }
+          _ => {}
+        }
+        if let Err(e) = buffer.remove(removed.clone()) {
+          error!("Failed to apply operator {:?}:{:?}", operator, e);
+          state.echoerr(&e.to_string());
+          return StatefulValue::NormalMode(NormalStateful::default());
+        }
+        state.registers_mut().delete(register_name, register);
+
+        match (operator, range.wise()) {
+          (Operator::Change, _) => {
+            // Changing the last line without line break leaves the empty line after the line
+            // break before it, make it a line to insert text.
+            if let Err(e) = buffer.ensure_line(start.line_idx()) {
+              error!("Failed to apply operator {:?}:{:?}", operator, e);
+            }
+            start
+          }
+          (_, MotionWise::Charwise) => {
+            let last_char_idx = buffer.line_len_chars(start.line_idx()).saturating_sub(1);
+            BufferPosition::new(
+              start.line_idx(),
+              std::cmp::min(start.char_idx(), last_char_idx),
+            )
+          }
+          (_, MotionWise::Linewise) => {
+            // The cursor moves up if the last lines are deleted.
+            let line_idx = if removed.start < char_range.start {
+              start.line_idx() - 1
+            } else {
+              start.line_idx()
+            };
+            BufferPosition::new(line_idx, buffer.first_non_blank_char(line_idx))
+          }
+        }
+      }
+    };
+
+    sync_viewport(tree);
+    sync_cursor(tree, cursor, next_cursor);
+    match operator {
+      Operator::Change => StatefulValue::InsertMode(InsertStateful::default()),
+      _ => StatefulValue::NormalMode(NormalStateful::default()),
+    }
+  }
+}
+
+/// Create a closed fold over the lines in current window, i.e. `zf`/`zF`, the cursor moves to the
+/// summary row of the fold.
+pub(crate) fn create_fold(tree: &mut Tree, lines: Range<usize>) {
+  let cursor = match cursor_position(tree) {
+    Some(cursor) => cursor,
+    None => return,
+  };
+  if let Some(window_id) = tree.focused_window_id() {
+    if let Some(TreeNode::Window(window)) = tree.node(&window_id) {
+      let viewport = window.viewport();
+      wlock!(viewport).folds_mut().create(lines);
+    }
+  }
+  sync_viewport(tree);
+  jump_cursor(tree, cursor, None);
+}
+
+// Convert the char index (based on the whole buffer) to position.
+fn position_of(buffer: &Buffer, char_idx: usize) -> BufferPosition {
+  let (line_idx, char_idx) = buffer.char_to_line_col(char_idx).unwrap();
+  BufferPosition::new(line_idx, char_idx)
+}
+
+// Same with Vim, if the cursor is in a word, `cw` works like `ce` that doesn't change the blanks
+// after the word.
+// See: <https://vimhelp.org/change.txt.html#cw>.
+fn change_word_range(buffer: &Buffer, range: MotionRange) -> MotionRange {
+  let char_range = range.char_range().clone();
+  let text = match buffer.get_slice(char_range.clone()) {
+    Some(text) => text,
+    None => return range,
+  };
+  match text.chars().next() {
+    Some(c) if char_class(c, false) != CharClass::Blank => {}
+    _ => return range,
+  }
+  let trailing_blanks = text
+    .chars()
+    .rev()
+    .take_while(|c| char_class(*c, false) == CharClass::Blank)
+    .count();
+  MotionRange::new(
+    char_range.start..char_range.end - trailing_blanks,
+    range.wise(),
+  )
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use crate::buf::{BufferArc, BuffersManager};
+  use crate::state::register::{UNNAMED_REGISTER, YANK_REGISTER};
+  use crate::test::tree::make_tree;
+  use crate::ui::tree::TreeArc;
+
+  use crossterm::event::{KeyEvent, KeyModifiers};
+
+  fn type_keys(state: &mut State, tree: &TreeArc, keys: &str) {
+    let buffers = BuffersManager::to_arc(BuffersManager::new());
+    for c in keys.chars() {
+      let code = match c {
+        '\x1b' => KeyCode::Esc,
+        c => KeyCode::Char(c),
+      };
+      let event = Event::Key(KeyEvent::new(code, KeyModifiers::NONE));
+      state.handle(tree.clone(), buffers.clone(), event);
+    }
+  }
+
+  fn buffer_text(buffer: &BufferArc) -> String {
+    rlock!(buffer).lines().map(|l| l.to_string()).collect()
+  }
+
+  fn cursor_of(tree: &TreeArc) -> BufferPosition {
+    cursor_position(&rlock!(tree)).unwrap()
+  }
+
+  fn register_of(state: &State, name: char) -> (RegisterKind, String) {
+    let register = state.registers().get(name).unwrap();
+    (register.kind(), register.text().to_string())
+  }
+
+  #[test]
+  fn delete_word1() {
+    let (tree, buffer) = make_tree(vec!["foo bar baz\n", "qux\n"]);
+    let mut state = State::default();
+
+    type_keys(&mut state, &tree, "d");
+    assert!(matches!(
+      state.stateful(),
+      StatefulValue::OperatorPendingMode(_)
+    ));
+    type_keys(&mut state, &tree, "w");
+    assert!(matches!(state.stateful(), StatefulValue::NormalMode(_)));
+    assert_eq!(buffer_text(&buffer), "bar baz\nqux\n");
+    assert_eq!(
+      register_of(&state, UNNAMED_REGISTER),
+      (RegisterKind::Charwise, "foo ".to_string())
+    );
+    assert!(state.registers().get(YANK_REGISTER).is_none());
+
+    // The last word of the line doesn't join the next line.
+    type_keys(&mut state, &tree, "wdw");
+    assert_eq!(buffer_text(&buffer), "bar \nqux\n");
+    assert_eq!(cursor_of(&tree), BufferPosition::new(0, 3));
+    assert_eq!(
+      register_of(&state, UNNAMED_REGISTER),
+      (RegisterKind::Charwise, "baz".to_string())
+    );
+  }
+
+  #[test]
+  fn delete_word2() {
+    let (tree, buffer) = make_tree(vec!["a b c d e\n"]);
+    let mut state = State::default();
+
+    // The counts are multiplied.
+    type_keys(&mut state, &tree, "\"a2d2w");
+    assert_eq!(buffer_text(&buffer), "e\n");
+    assert_eq!(
+      register_of(&state, 'a'),
+      (RegisterKind::Charwise, "a b c d ".to_string())
+    );
+
+    // No more words, it stops at the end of buffer.
+    type_keys(&mut state, &tree, "dw");
+    assert_eq!(buffer_text(&buffer), "\n");
+    assert_eq!(
+      register_of(&state, UNNAMED_REGISTER),
+      (RegisterKind::Charwise, "e".to_string())
+    );
+  }
+
+  #[test]
+  fn delete_line1() {
+    let (tree, buffer) = make_tree(vec!["foo\n", "  bar\n", "baz\n", "qux\n"]);
+    let mut state = State::default();
+
+    type_keys(&mut state, &tree, "dd");
+    assert_eq!(buffer_text(&buffer), "  bar\nbaz\nqux\n");
+    assert_eq!(
+      register_of(&state, UNNAMED_REGISTER),
+      (RegisterKind::Linewise, "foo\n".to_string())
+    );
+    // The cursor moves to the first non-blank char.
+    assert_eq!(cursor_of(&tree), BufferPosition::new(0, 2));
+
+    type_keys(&mut state, &tree, "2dd");
+    assert_eq!(buffer_text(&buffer), "qux\n");
+    assert_eq!(
+      register_of(&state, UNNAMED_REGISTER),
+      (RegisterKind::Linewise, "  bar\nbaz\n".to_string())
+    );
+    assert_eq!(cursor_of(&tree), BufferPosition::new(0, 0));
+
+    // Delete the last lines, the cursor moves up.
+    let (tree, buffer) = make_tree(vec!["foo\n", "bar\n", "baz"]);
+    let mut state = State::default();
+    type_keys(&mut state, &tree, "j5dd");
+    assert_eq!(buffer_text(&buffer), "foo");
+    assert_eq!(
+      register_of(&state, UNNAMED_REGISTER),
+      (RegisterKind::Linewise, "bar\nbaz\n".to_string())
+    );
+    assert_eq!(cursor_of(&tree), BufferPosition::new(0, 0));
+  }
+
+  #[test]
+  fn delete_line_down_up1() {
+    let (tree, buffer) = make_tree(vec!["a\n", "b\n", "c\n", "d\n", "e\n"]);
+    let mut state = State::default();
+
+    type_keys(&mut state, &tree, "jdj");
+    assert_eq!(buffer_text(&buffer), "a\nd\ne\n");
+    assert_eq!(
+      register_of(&state, UNNAMED_REGISTER),
+      (RegisterKind::Linewise, "b\nc\n".to_string())
+    );
+    assert_eq!(cursor_of(&tree), BufferPosition::new(1, 0));
+
+    // The count stops at the first line.
+    type_keys(&mut state, &tree, "G5dk");
+    assert_eq!(buffer_text(&buffer), "");
+    assert_eq!(
+      register_of(&state, UNNAMED_REGISTER),
+      (RegisterKind::Linewise, "a\nd\ne\n".to_string())
+    );
+
+    // It fails at the last line.
+    let (tree, buffer) = make_tree(vec!["a\n", "b\n"]);
+    let mut state = State::default();
+    type_keys(&mut state, &tree, "jdj");
+    assert_eq!(buffer_text(&buffer), "a\nb\n");
+    type_keys(&mut state, &tree, "dk");
+    assert_eq!(buffer_text(&buffer), "");
+  }
+
+  #[test]
+  fn delete_line_end1() {
+    let (tree, buffer) = make_tree(vec!["foo bar\n", "baz qux\n", "end\n"]);
+    let mut state = State::default();
+
+    type_keys(&mut state, &tree, "wd$");
+    assert_eq!(buffer_text(&buffer), "foo \nbaz qux\nend\n");
+    assert_eq!(
+      register_of(&state, UNNAMED_REGISTER),
+      (RegisterKind::Charwise, "bar".to_string())
+    );
+    assert_eq!(cursor_of(&tree), BufferPosition::new(0, 3));
+
+    // With count, delete to the end of the `N - 1` lines downward.
+    type_keys(&mut state, &tree, "2d$");
+    assert_eq!(buffer_text(&buffer), "foo\nend\n");
+    assert_eq!(
+      register_of(&state, UNNAMED_REGISTER),
+      (RegisterKind::Charwise, " \nbaz qux".to_string())
+    );
+  }
+
+  #[test]
+  fn yank1() {
+    let (tree, buffer) = make_tree(vec!["foo bar\n", "baz\n"]);
+    let mut state = State::default();
+
+    type_keys(&mut state, &tree, "wyb");
+    assert_eq!(buffer_text(&buffer), "foo bar\nbaz\n");
+    assert_eq!(
+      register_of(&state, YANK_REGISTER),
+      (RegisterKind::Charwise, "foo ".to_string())
+    );
+    assert_eq!(cursor_of(&tree), BufferPosition::new(0, 0));
+
+    type_keys(&mut state, &tree, "2yy");
+    assert_eq!(
+      register_of(&state, YANK_REGISTER),
+      (RegisterKind::Linewise, "foo bar\nbaz\n".to_string())
+    );
+    assert_eq!(cursor_of(&tree), BufferPosition::new(0, 0));
+
+    // Cancel.
+    type_keys(&mut state, &tree, "y\x1b");
+    assert!(matches!(state.stateful(), StatefulValue::NormalMode(_)));
+  }
+
+  #[test]
+  fn change1() {
+    let (tree, buffer) = make_tree(vec!["foo bar\n", "  baz\n"]);
+    let mut state = State::default();
+
+    // `cw` doesn't change the blanks after the word.
+    type_keys(&mut state, &tree, "cw");
+    assert!(matches!(state.stateful(), StatefulValue::InsertMode(_)));
+    assert_eq!(buffer_text(&buffer), " bar\n  baz\n");
+    assert_eq!(
+      register_of(&state, UNNAMED_REGISTER),
+      (RegisterKind::Charwise, "foo".to_string())
+    );
+    assert_eq!(cursor_of(&tree), BufferPosition::new(0, 0));
+
+    // `cc` keeps an empty line.
+    type_keys(&mut state, &tree, "\x1bjcc");
+    assert!(matches!(state.stateful(), StatefulValue::InsertMode(_)));
+    assert_eq!(buffer_text(&buffer), " bar\n\n");
+    assert_eq!(cursor_of(&tree), BufferPosition::new(1, 0));
+  }
+
+  #[test]
+  fn repeat1() {
+    let (tree, buffer) = make_tree(vec!["foo bar baz\n"]);
+    let mut state = State::default();
+
+    type_keys(&mut state, &tree, "dw.");
+    assert!(matches!(state.stateful(), StatefulValue::NormalMode(_)));
+    assert_eq!(buffer_text(&buffer), "baz\n");
+    assert_eq!(
+      register_of(&state, UNNAMED_REGISTER),
+      (RegisterKind::Charwise, "bar ".to_string())
+    );
+  }
+
+  #[test]
+  fn repeat2() {
+    let (tree, buffer) = make_tree(vec!["a b c d e f\n"]);
+    let mut state = State::default();
+
+    type_keys(&mut state, &tree, "2dw.");
+    assert_eq!(buffer_text(&buffer), "e f\n");
+
+    // The count on `.` overrides the original count, and it's used by the next `.`.
+    type_keys(&mut state, &tree, "1.");
+    assert_eq!(buffer_text(&buffer), "f\n");
+    assert_eq!(state.change_recorder().last().unwrap().count(), Some(1));
+  }
+
+  #[test]
+  fn repeat_change1() {
+    let (tree, buffer) = make_tree(vec!["foo bar\n", "baz\n"]);
+    let mut state = State::default();
+
+    type_keys(&mut state, &tree, "cwxy\x1bw.");
+    assert!(matches!(state.stateful(), StatefulValue::NormalMode(_)));
+    assert_eq!(buffer_text(&buffer), "xy xy\nbaz\n");
+    assert_eq!(cursor_of(&tree), BufferPosition::new(0, 4));
+
+    // Yank is not a change.
+    type_keys(&mut state, &tree, "yy");
+    assert_eq!(
+      state.change_recorder().last().unwrap().kind(),
+      ChangeKind::Operator {
+        operator: Operator::Change,
+        motion: 'w'
+      }
+    );
+  }
+
+  #[test]
+  fn repeat_insert1() {
+    let (tree, buffer) = make_tree(vec!["x\n"]);
+    let mut state = State::default();
+
+    type_keys(&mut state, &tree, "iab\x1b");
+    assert_eq!(buffer_text(&buffer), "abx\n");
+    assert_eq!(cursor_of(&tree), BufferPosition::new(0, 1));
+
+    type_keys(&mut state, &tree, "2.");
+    assert_eq!(buffer_text(&buffer), "aababbx\n");
+    assert_eq!(cursor_of(&tree), BufferPosition::new(0, 4));
+  }
+
+  #[test]
+  fn change_case1() {
+    let (tree, buffer) = make_tree(vec!["straße foo\n", "bar\n"]);
+    let mut state = State::default();
+
+    type_keys(&mut state, &tree, "gUw");
+    assert!(matches!(state.stateful(), StatefulValue::NormalMode(_)));
+    assert_eq!(buffer_text(&buffer), "STRASSE foo\nbar\n");
+    assert_eq!(cursor_of(&tree), BufferPosition::new(0, 0));
+    assert!(state.registers().get(UNNAMED_REGISTER).is_none());
+
+    // Toggle the whole line, then repeat it on the next line.
+    type_keys(&mut state, &tree, "wg~~");
+    assert_eq!(buffer_text(&buffer), "strasse FOO\nbar\n");
+    assert_eq!(cursor_of(&tree), BufferPosition::new(0, 0));
+    type_keys(&mut state, &tree, "j.");
+    assert_eq!(buffer_text(&buffer), "strasse FOO\nBAR\n");
+
+    // The count before the `g` prefix.
+    type_keys(&mut state, &tree, "k2gue");
+    assert_eq!(buffer_text(&buffer), "strasse foo\nBAR\n");
+    assert_eq!(cursor_of(&tree), BufferPosition::new(0, 0));
+  }
+
+  #[test]
+  fn counts1() {
+    let (tree, buffer) = make_tree(vec![
+      "one two three four\n",
+      "a\n",
+      "b\n",
+      "c\n",
+      "last line\n",
+    ]);
+    let mut state = State::default();
+
+    type_keys(&mut state, &tree, "d2w");
+    assert_eq!(buffer_text(&buffer), "three four\na\nb\nc\nlast line\n");
+    assert_eq!(
+      register_of(&state, UNNAMED_REGISTER),
+      (RegisterKind::Charwise, "one two ".to_string())
+    );
+
+    type_keys(&mut state, &tree, "wy$");
+    assert_eq!(buffer_text(&buffer), "three four\na\nb\nc\nlast line\n");
+    assert_eq!(
+      register_of(&state, YANK_REGISTER),
+      (RegisterKind::Charwise, "four".to_string())
+    );
+    assert_eq!(cursor_of(&tree), BufferPosition::new(0, 6));
+
+    // Delete to the start of line.
+    type_keys(&mut state, &tree, "d0");
+    assert_eq!(buffer_text(&buffer), "four\na\nb\nc\nlast line\n");
+    assert_eq!(
+      register_of(&state, UNNAMED_REGISTER),
+      (RegisterKind::Charwise, "three ".to_string())
+    );
+    assert_eq!(cursor_of(&tree), BufferPosition::new(0, 0));
+
+    type_keys(&mut state, &tree, "j3dd");
+    assert_eq!(buffer_text(&buffer), "four\nlast line\n");
+    assert_eq!(
+      register_of(&state, UNNAMED_REGISTER),
+      (RegisterKind::Linewise, "a\nb\nc\n".to_string())
+    );
+    assert_eq!(cursor_of(&tree), BufferPosition::new(1, 0));
+  }
+
+  #[test]
+  fn cancel1() {
+    let (tree, buffer) = make_tree(vec!["foo bar\n", "baz\n"]);
+    let mut state = State::default();
+
+    // The `Esc` or an unrelated key cancels the operator, and the count and register for it.
+    for keys in ["d\x1b", "dx", "3d\x1b", "\"ad\x1b", "d2\x1b"] {
+      type_keys(&mut state, &tree, keys);
+      assert!(matches!(state.stateful(), StatefulValue::NormalMode(_)));
+      assert_eq!(buffer_text(&buffer), "foo bar\nbaz\n");
+      assert!(state.take_pending_operator().is_none());
+      assert!(state.take_count().is_none());
+      assert!(state.register_name().is_none());
+    }
+    assert!(state.registers().get('a').is_none());
+
+    type_keys(&mut state, &tree, "dw");
+    assert_eq!(buffer_text(&buffer), "bar\nbaz\n");
+    assert_eq!(
+      register_of(&state, UNNAMED_REGISTER),
+      (RegisterKind::Charwise, "foo ".to_string())
+    );
+  }
+}
@@ -0,0 +1,237 @@
+//! The replace mode.
+//!
+//! See: <https://vimhelp.org/insert.txt.html#Replace-mode>.
+
+use crate::envar;
+use crate::state::fsm::insert::InsertStateful;
+use crate::state::fsm::visual::line_text_len;
+use crate::state::fsm::{Stateful, StatefulDataAccess, StatefulValue};
+use crate::state::selection::BufferPosition;
+use crate::state::State;
+use crate::ui::tree::Tree;
+use crate::wlock;
+
+use crossterm::event::{Event, KeyCode, KeyEventKind};
+
+#[derive(Debug, Copy, Clone, Default)]
+/// The replace editing mode, i.e. `R`. The typed chars overwrite the chars under the cursor until
+/// the end of line, then they're appended. The `Backspace` restores the overwritten chars.
+pub struct ReplaceStateful {}
+
+impl Stateful for ReplaceStateful {
+  fn handle(&self, data_access: StatefulDataAccess) -> StatefulValue {
+    let state = data_access.state;
+    let tree = data_access.tree;
+    let event = data_access.event;
+
+    if let Event::Key(key_event) = event {
+      if key_event.kind == KeyEventKind::Press {
+        // Record the typed keys for repeating the change, i.e. `.`.
+        match key_event.code {
+          KeyCode::Esc => state.change_recorder_mut().finish(),
+          code => state.change_recorder_mut().record_insert_key(code),
+        }
+        let mut tree = wlock!(tree);
+        let insert = InsertStateful::default();
+        match key_event.code {
+          KeyCode::Esc => {
+            state.clear_replaced_chars();
+            return insert.exit(&mut tree);
+          }
+          KeyCode::Up | KeyCode::Down | KeyCode::Left | KeyCode::Right => {
+            // The overwritten chars are not restored after moving the cursor.
+            state.clear_replaced_chars();
+            let (rows, cols) = match key_event.code {
+              KeyCode::Up => (-1, 0),
+              KeyCode::Down => (1, 0),
+              KeyCode::Left => (0, -1),
+              _ => (0, 1),
+            };
+            insert.move_cursor(&mut tree, rows, cols);
+          }
+          KeyCode::Enter => {
+            self.insert_line_break(state, &mut tree);
+          }
+          KeyCode::Backspace => {
+            self.restore_backward(state, &mut tree);
+          }
+          KeyCode::Tab => {
+            self.replace_char(state, &mut tree, '\t');
+          }
+          KeyCode::Char(c) => {
+            self.replace_char(state, &mut tree, c);
+          }
+          _ => { /* Skip */ }
+        }
+      }
+    }
+
+    StatefulValue::ReplaceMode(ReplaceStateful::default())
+  }
+}
+
+impl ReplaceStateful {
+  // Overwrite the char under the cursor with `c`, or append it at the end of line. The cursor
+  // moves after it, and the overwritten char is saved for `Backspace`.
+  fn replace_char(&self, state: &mut State, tree: &mut Tree, c: char) {
+    let mut replaced: Option<Option<char>> = None;
+    InsertStateful::default().edit(state, tree, |buffer, cursor| {
+      let line_idx = cursor.line_idx();
+      let line_len = line_text_len(buffer, line_idx);
+      let start_char_idx = std::cmp::min(cursor.char_idx(), line_len);
+      let end_char_idx = std::cmp::min(start_char_idx + 1, line_len);
+      let text = buffer.replace_range(
+        line_idx,
+        start_char_idx,
+        end_char_idx,
+        c.encode_utf8(&mut [0; 4]),
+      )?;
+      replaced = Some(text.chars().next());
+      Ok(Some(BufferPosition::new(line_idx, start_char_idx + 1)))
+    });
+    if let Some(replaced) = replaced {
+      state.push_replaced_char(replaced);
+    }
+  }
+
+  // Insert a line break at the cursor, a line break never overwrites a char in replace mode.
+  fn insert_line_break(&self, state: &mut State, tree: &mut Tree) {
+    let mut inserted = false;
+    InsertStateful::default().edit(state, tree, |buffer, cursor| {
+      let line_idx = cursor.line_idx();
+      let char_idx = buffer.line_col_to_char_clamped(line_idx, cursor.char_idx());
+      buffer.insert(char_idx, "\n")?;
+      inserted = true;
+      Ok(Some(BufferPosition::new(line_idx + 1, 0)))
+    });
+    if inserted {
+      state.push_replaced_char(None);
+    }
+  }
+
+  // Restore the last overwritten char before the cursor, or delete the appended char (or line
+  // break). The cursor only moves left if nothing is typed since entering replace mode, like Vim.
+  fn restore_backward(&self, state: &mut State, tree: &mut Tree) {
+    let replaced = match state.pop_replaced_char() {
+      Some(replaced) => replaced,
+      None => {
+        InsertStateful::default().move_cursor(tree, 0, -1);
+        return;
+      }
+    };
+    InsertStateful::default().edit(state, tree, |buffer, cursor| {
+      let line_idx = cursor.line_idx();
+      if cursor.char_idx() == 0 {
+        // The line break typed in replace mode, join the lines back.
+        if line_idx == 0 {
+          return Ok(None);
+        }
+        let prev_line_len = line_text_len(buffer, line_idx - 1);
+        let prev_line_end = buffer.line_to_char(line_idx - 1) + prev_line_len;
+        let line_start = buffer.line_to_char(line_idx);
+        buffer.remove(prev_line_end..line_start)?;
+        return Ok(Some(BufferPosition::new(line_idx - 1, prev_line_len)));
+      }
+      let char_idx = cursor.char_idx() - 1;
+      let text = replaced.map(String::from).unwrap_or_default();
+      buffer.replace_range(line_idx, char_idx, char_idx + 1, &text)?;
+      Ok(Some(BufferPosition::new(line_idx, char_idx)))
+    });
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use crate::buf::BufferArc;
+  use crate::rlock;
+  use crate::state::fsm::visual::cursor_position;
+  use crate::state::mode::Mode;
+  use crate::test::state::press;
+  use crate::test::tree::make_tree;
+  use crate::ui::canvas::{cursor_style_eq, CursorStyle};
+  use crate::ui::tree::TreeArc;
+
+  fn type_text(state: &mut State, tree: &TreeArc, text: &str) {
+    for c in text.chars() {
+      press(state, tree, KeyCode::Char(c));
+    }
+  }
+
+  fn buffer_text(buffer: &BufferArc) -> String {
+    let mut text: Vec<u8> = vec![];
+    rlock!(buffer).write_to(&mut text).unwrap();
+    String::from_utf8(text).unwrap()
+  }
+
+  fn cursor(tree: &TreeArc) -> BufferPosition {
+    cursor_position(&rlock!(tree)).unwrap()
+  }
+
+  #[test]
+  fn replace1() {
+    let (tree, buffer) = make_tree(vec!["abc\n", "def\n"]);
+    let mut state = State::default();
+
+    press(&mut state, &tree, KeyCode::Char('l'));
+    press(&mut state, &tree, KeyCode::Char('R'));
+    assert!(matches!(state.stateful(), StatefulValue::ReplaceMode(_)));
+    assert_eq!(state.mode(), Mode::Replace);
+    assert!(cursor_style_eq(
+      &state.mode().cursor_style(),
+      &CursorStyle::BlinkingUnderScore
+    ));
+
+    // Overwrite until the end of line, then append.
+    type_text(&mut state, &tree, "xyz");
+    assert_eq!(buffer_text(&buffer), "axyz\ndef\n");
+    assert_eq!(cursor(&tree), BufferPosition::new(0, 4));
+
+    press(&mut state, &tree, KeyCode::Esc);
+    assert!(matches!(state.stateful(), StatefulValue::NormalMode(_)));
+    assert_eq!(state.mode(), Mode::Normal);
+    assert_eq!(cursor(&tree), BufferPosition::new(0, 3));
+
+    // Repeat at the next line.
+    press(&mut state, &tree, KeyCode::Char('j'));
+    press(&mut state, &tree, KeyCode::Char('0'));
+    press(&mut state, &tree, KeyCode::Char('.'));
+    assert_eq!(buffer_text(&buffer), "axyz\nxyz\n");
+  }
+
+  #[test]
+  fn backspace1() {
+    let (tree, buffer) = make_tree(vec!["ab你\n"]);
+    let mut state = State::default();
+
+    press(&mut state, &tree, KeyCode::Char('R'));
+    type_text(&mut state, &tree, "好xyz");
+    assert_eq!(buffer_text(&buffer), "好xyz\n");
+    press(&mut state, &tree, KeyCode::Enter);
+    type_text(&mut state, &tree, "w");
+    assert_eq!(buffer_text(&buffer), "好xyz\nw\n");
+    assert_eq!(cursor(&tree), BufferPosition::new(1, 1));
+
+    // The appended chars and line break are deleted, the overwritten chars are restored.
+    press(&mut state, &tree, KeyCode::Backspace);
+    press(&mut state, &tree, KeyCode::Backspace);
+    assert_eq!(buffer_text(&buffer), "好xyz\n");
+    assert_eq!(cursor(&tree), BufferPosition::new(0, 4));
+    press(&mut state, &tree, KeyCode::Backspace);
+    assert_eq!(buffer_text(&buffer), "好xy\n");
+    press(&mut state, &tree, KeyCode::Backspace);
+    assert_eq!(buffer_text(&buffer), "好x你\n");
+    press(&mut state, &tree, KeyCode::Backspace);
+    press(&mut state, &tree, KeyCode::Backspace);
+    assert_eq!(buffer_text(&buffer), "ab你\n");
+    assert_eq!(cursor(&tree), BufferPosition::new(0, 0));
+
+    // Nothing to restore, and the chars before entering replace mode are not deleted.
+    type_text(&mut state, &tree, "c");
+    press(&mut state, &tree, KeyCode::Right);
+    press(&mut state, &tree, KeyCode::Backspace);
+    assert_eq!(buffer_text(&buffer), "cb你\n");
+    assert_eq!(cursor(&tree), BufferPosition::new(0, 1));
+  }
+}
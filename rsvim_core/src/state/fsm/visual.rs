@@ -1,13 +1,665 @@
 //! The visual mode.
 
+use crate::buf::Buffer;
+use crate::envar;
+use crate::state::fsm::normal::NormalStateful;
+use crate::state::fsm::operator_pending::Operator;
 use crate::state::fsm::{Stateful, StatefulDataAccess, StatefulValue};
+use crate::state::register::{Register, RegisterKind};
+use crate::state::selection::{BufferPosition, Selection, VisualKind};
+use crate::state::State;
+use crate::ui::tree::{Tree, TreeNode};
+use crate::{rlock, wlock};
+
+use crossterm::event::{Event, KeyCode, KeyEventKind, KeyModifiers};
+use std::ops::Range;
+use tracing::{error, trace};
 
 #[derive(Debug, Copy, Clone, Default)]
 /// The visual editing mode.
 pub struct VisualStateful {}
 
 impl Stateful for VisualStateful {
-  fn handle(&self, _data_access: StatefulDataAccess) -> StatefulValue {
+  fn handle(&self, data_access: StatefulDataAccess) -> StatefulValue {
+    let state = data_access.state;
+    let tree = data_access.tree;
+    let event = data_access.event;
+
+    let mut selection = match state.selection() {
+      Some(selection) => *selection,
+      None => return StatefulValue::NormalMode(NormalStateful::default()),
+    };
+
+    let mut tree = wlock!(tree);
+
+    if let Event::Key(key_event) = event {
+      if key_event.kind == KeyEventKind::Press {
+        if state.handle_register_name(key_event.code) {
+          return StatefulValue::VisualMode(VisualStateful::default());
+        }
+        let ctrl = key_event.modifiers.contains(KeyModifiers::CONTROL);
+        match key_event.code {
+          KeyCode::Up | KeyCode::Char('k') => {
+            self.move_cursor(state, &mut tree, &mut selection, -1, 0);
+          }
+          KeyCode::Down | KeyCode::Char('j') => {
+            self.move_cursor(state, &mut tree, &mut selection, 1, 0);
+          }
+          KeyCode::Left | KeyCode::Char('h') => {
+            self.move_cursor(state, &mut tree, &mut selection, 0, -1);
+          }
+          KeyCode::Right | KeyCode::Char('l') => {
+            self.move_cursor(state, &mut tree, &mut selection, 0, 1);
+          }
+          KeyCode::Char('v') if ctrl => {
+            return self.switch_kind(state, &mut tree, selection, VisualKind::Blockwise);
+          }
+          KeyCode::Char('v') => {
+            return self.switch_kind(state, &mut tree, selection, VisualKind::Charwise);
+          }
+          KeyCode::Char('V') => {
+            return self.switch_kind(state, &mut tree, selection, VisualKind::Linewise);
+          }
+          KeyCode::Char('y') => {
+            self.yank(state, &mut tree, &selection);
+            return self.exit(state, &mut tree);
+          }
+          KeyCode::Char('d') | KeyCode::Char('x') => {
+            self.delete(state, &mut tree, &selection);
+            return self.exit(state, &mut tree);
+          }
+          KeyCode::Char(c @ ('u' | 'U' | '~')) => {
+            let operator = Operator::from_g_key(c).unwrap();
+            self.change_case(state, &mut tree, &selection, operator);
+            return self.exit(state, &mut tree);
+          }
+          KeyCode::Esc => {
+            return self.exit(state, &mut tree);
+          }
+          _ => { /* Skip */ }
+        }
+      }
+    }
+
     StatefulValue::VisualMode(VisualStateful::default())
   }
 }
+
+impl VisualStateful {
+  /// Enter visual mode from the cursor position of current window.
+  ///
+  /// # Returns
+  ///
+  /// It returns `None` if there is no current window.
+  pub fn enter(state: &mut State, tree: &mut Tree, kind: VisualKind) -> Option<StatefulValue> {
+    let cursor = cursor_position(tree)?;
+    let selection = Selection::new(kind, cursor, cursor);
+    trace!("Enter visual mode:{:?}", selection);
+    state.set_selection(Some(selection));
+    set_window_selection(tree, Some(selection));
+    Some(StatefulValue::VisualMode(VisualStateful::default()))
+  }
+
+  fn exit(&self, state: &mut State, tree: &mut Tree) -> StatefulValue {
+    state.set_selection(None);
+    set_window_selection(tree, None);
+    StatefulValue::NormalMode(NormalStateful::default())
+  }
+
+  // Press the same visual mode key again exits visual mode, otherwise switches the kind.
+  fn switch_kind(
+    &self,
+    state: &mut State,
+    tree: &mut Tree,
+    mut selection: Selection,
+    kind: VisualKind,
+  ) -> StatefulValue {
+    if selection.kind() == kind {
+      return self.exit(state, tree);
+    }
+    selection.set_kind(kind);
+    state.set_selection(Some(selection));
+    set_window_selection(tree, Some(selection));
+    StatefulValue::VisualMode(VisualStateful::default())
+  }
+
+  // Move the selection cursor by `lines` and `chars`, bounded by the buffer. The cursor widget
+  // moves along with it.
+  fn move_cursor(
+    &self,
+    state: &mut State,
+    tree: &mut Tree,
+    selection: &mut Selection,
+    lines: isize,
+    chars: isize,
+  ) {
+    let buffer = match current_buffer(tree) {
+      Some(buffer) => buffer,
+      None => return,
+    };
+    let cursor = selection.cursor();
+    let next_cursor = {
+      let buffer = rlock!(buffer);
+      let last_line_idx = buffer.last_line_idx();
+      let line_idx = cursor
+        .line_idx()
+        .saturating_add_signed(lines)
+        .min(last_line_idx);
+      let last_char_idx = line_text_len(&buffer, line_idx).saturating_sub(1);
+      let char_idx = cursor
+        .char_idx()
+        .saturating_add_signed(chars)
+        .min(last_char_idx);
+      BufferPosition::new(line_idx, char_idx)
+    };
+    if next_cursor == cursor {
+      return;
+    }
+
+    selection.set_cursor(next_cursor);
+    state.set_selection(Some(*selection));
+    set_window_selection(tree, Some(*selection));
+    sync_cursor(tree, cursor, next_cursor);
+  }
+
+  // Yank the selected texts into register, and move the cursor to the start of the selection.
+  fn yank(&self, state: &mut State, tree: &mut Tree, selection: &Selection) {
+    let buffer = match current_buffer(tree) {
+      Some(buffer) => buffer,
+      None => return,
+    };
+    let (text, start) = {
+      let buffer = rlock!(buffer);
+      (
+        selected_text(&buffer, selection),
+        selection_start(&buffer, selection),
+      )
+    };
+    trace!("Yank visual selection:{:?}", text);
+    let register_name = state.take_register_name();
+    let register = Register::new(register_kind(selection.kind()), text);
+    state.registers_mut().yank(register_name, register);
+    sync_cursor(tree, selection.cursor(), start);
+  }
+
+  // Delete the selected texts into register, and move the cursor to the start of the selection.
+  fn delete(&self, state: &mut State, tree: &mut Tree, selection: &Selection) {
+    let buffer = match current_buffer(tree) {
+      Some(buffer) => buffer,
+      None => return,
+    };
+    let start = {
+      let mut buffer = wlock!(buffer);
+      let start = selection_start(&buffer, selection);
+      let text = selected_text(&buffer, selection);
+      for char_range in selected_char_ranges(&buffer, selection).into_iter().rev() {
+        if let Err(e) = buffer.remove(char_range) {
+          error!("Failed to delete visual selection:{:?}", e);
+          state.echoerr(&e.to_string());
+          return;
+        }
+      }
+      let register_name = state.take_register_name();
+      let register = Register::new(register_kind(selection.kind()), text);
+      state.registers_mut().delete(register_name, register);
+      start
+    };
+
+    sync_viewport(tree);
+    sync_cursor(tree, selection.cursor(), start);
+  }
+
+  // Change the case of the selected texts, i.e. `u`/`U`/`~`, and move the cursor to the start of
+  // the selection.
+  fn change_case(
+    &self,
+    state: &mut State,
+    tree: &mut Tree,
+    selection: &Selection,
+    operator: Operator,
+  ) {
+    let buffer = match current_buffer(tree) {
+      Some(buffer) => buffer,
+      None => return,
+    };
+    let start = {
+      let mut buffer = wlock!(buffer);
+      let start = selection_start(&buffer, selection);
+      // Replace from the last range, since the text length can be changed, i.e. `ß` to `SS`.
+      for char_range in selected_char_ranges(&buffer, selection).into_iter().rev() {
+        let text = match buffer.get_slice(char_range.clone()) {
+          Some(text) => text.to_string(),
+          None => continue,
+        };
+        let changed = operator.change_case(&text);
+        if changed == text {
+          continue;
+        }
+        if let Err(e) = buffer.replace(char_range, &changed) {
+          error!("Failed to change case of visual selection:{:?}", e);
+          state.echoerr(&e.to_string());
+          return;
+        }
+      }
+      start
+    };
+
+    sync_viewport(tree);
+    sync_cursor(tree, selection.cursor(), start);
+  }
+}
+
+// The register content kind of the selection, the blockwise selection is put as charwise text.
+fn register_kind(kind: VisualKind) -> RegisterKind {
+  match kind {
+    VisualKind::Linewise => RegisterKind::Linewise,
+    VisualKind::Charwise | VisualKind::Blockwise => RegisterKind::Charwise,
+  }
+}
+
+// The top-left position of the selection, for blockwise it's the first selected char on the top
+// line.
+fn selection_start(buffer: &Buffer, selection: &Selection) -> BufferPosition {
+  let (start, _) = selection.ordered();
+  match selection.kind() {
+    VisualKind::Linewise => BufferPosition::new(start.line_idx(), 0),
+    VisualKind::Charwise => start,
+    VisualKind::Blockwise => {
+      let char_idx = selection
+        .line_char_range(buffer, start.line_idx())
+        .map(|char_range| char_range.start)
+        .unwrap_or(start.char_idx());
+      BufferPosition::new(start.line_idx(), char_idx)
+    }
+  }
+}
+
+/// Get the buffer of current window.
+pub(crate) fn current_buffer(tree: &Tree) -> Option<crate::buf::BufferArc> {
+  let window_id = tree.focused_window_id()?;
+  match tree.node(&window_id) {
+    Some(TreeNode::Window(window)) => window.buffer().upgrade(),
+    _ => None,
+  }
+}
+
+fn set_window_selection(tree: &mut Tree, selection: Option<Selection>) {
+  if let Some(window_id) = tree.focused_window_id() {
+    if let Some(TreeNode::Window(window)) = tree.node_mut(&window_id) {
+      window.set_selection(selection);
+    }
+  }
+}
+
+/// Get the cursor position in the buffer of current window.
+pub(crate) fn cursor_position(tree: &Tree) -> Option<BufferPosition> {
+  let window_id = tree.focused_window_id()?;
+  match tree.node(&window_id) {
+    Some(TreeNode::Window(window)) => {
+      let viewport = window.viewport();
+      let viewport = rlock!(viewport);
+      let cursor = viewport.cursor();
+      Some(BufferPosition::new(cursor.line_idx(), cursor.char_idx()))
+    }
+    _ => None,
+  }
+}
+
+/// Re-sync the viewport of current window after the buffer is changed.
+pub(crate) fn sync_viewport(tree: &mut Tree) {
+  if let Some(window_id) = tree.focused_window_id() {
+    if let Some(TreeNode::Window(window)) = tree.node(&window_id) {
+      let viewport = window.viewport();
+      let mut viewport = wlock!(viewport);
+      let start_line_idx = viewport.start_line_idx();
+      viewport.sync_from_top_left(start_line_idx, 0);
+    }
+  }
+}
+
+/// Move the cursor widget from `cursor` to `next_cursor`, and update the cursor viewport if the
+/// next cursor is inside the viewport.
+pub(crate) fn sync_cursor(tree: &mut Tree, cursor: BufferPosition, next_cursor: BufferPosition) {
+  if let Some(window_id) = tree.focused_window_id() {
+    if let Some(TreeNode::Window(window)) = tree.node(&window_id) {
+      let viewport = window.viewport();
+      let mut viewport = wlock!(viewport);
+      let next_cursor_viewport =
+        viewport.cursor_viewport_at(next_cursor.line_idx(), next_cursor.char_idx());
+      if let Some(next_cursor_viewport) = next_cursor_viewport {
+        // Move the cursor widget by the display columns/rows if possible, since a char can be
+        // wider than 1 column, i.e. tab and CJK.
+        let cursor_viewport = viewport.cursor();
+        let rows = next_cursor_viewport.row_idx() as isize - cursor_viewport.row_idx() as isize;
+        let cols = next_cursor_viewport.start_dcol_idx() as isize
+          - cursor_viewport.start_dcol_idx() as isize;
+        viewport.set_cursor(next_cursor_viewport);
+        if let Some(cursor_id) = tree.cursor_id() {
+          tree.bounded_move_by(cursor_id, cols, rows);
+        }
+        return;
+      }
+    }
+  }
+
+  if let Some(cursor_id) = tree.cursor_id() {
+    let rows = next_cursor.line_idx() as isize - cursor.line_idx() as isize;
+    let cols = next_cursor.char_idx() as isize - cursor.char_idx() as isize;
+    tree.bounded_move_by(cursor_id, cols, rows);
+  }
+}
+
+/// Chars count of the line, excluding the line break.
+pub(crate) fn line_text_len(buffer: &Buffer, line_idx: usize) -> usize {
+  buffer.line_len_chars(line_idx)
+}
+
+/// Get the selected char ranges (based on the whole buffer), ordered by line index.
+pub fn selected_char_ranges(buffer: &Buffer, selection: &Selection) -> Vec<Range<usize>> {
+  let line_range = selection.line_range();
+  let end_line_idx = std::cmp::min(line_range.end, buffer.len_lines());
+  let mut ranges: Vec<Range<usize>> = vec![];
+  for line_idx in line_range.start..end_line_idx {
+    if let Some(char_range) = selection.line_char_range(buffer, line_idx) {
+      if !char_range.is_empty() {
+        let line_start = buffer.line_to_char(line_idx);
+        ranges.push(line_start + char_range.start..line_start + char_range.end);
+      }
+    }
+  }
+  // Merge the continuous ranges, i.e. charwise and linewise selections.
+  ranges.into_iter().fold(vec![], |mut acc, r| {
+    match acc.last_mut() {
+      Some(last) if last.end == r.start => last.end = r.end,
+      _ => acc.push(r),
+    }
+    acc
+  })
+}
+
+/// Get the selected text, for blockwise selection each row is joined with line break.
+pub fn selected_text(buffer: &Buffer, selection: &Selection) -> String {
+  let line_range = selection.line_range();
+  let end_line_idx = std::cmp::min(line_range.end, buffer.len_lines());
+  let mut rows: Vec<String> = vec![];
+  for line_idx in line_range.start..end_line_idx {
+    let line = buffer.get_line(line_idx).unwrap();
+    if let Some(char_range) = selection.line_char_range(buffer, line_idx) {
+      rows.push(line.slice(char_range).to_string());
+    }
+  }
+  match selection.kind() {
+    VisualKind::Blockwise => rows.join("\n"),
+    _ => rows.concat(),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use crate::buf::BufferArc;
+  use crate::state::mode::Mode;
+  use crate::state::register::{UNNAMED_REGISTER, YANK_REGISTER};
+  use crate::test::state::{press, press_key};
+  use crate::test::tree::make_tree;
+  use crate::ui::tree::TreeArc;
+
+  use crossterm::event::KeyEvent;
+
+  fn buffer_text(buffer: &BufferArc) -> String {
+    let mut text: Vec<u8> = vec![];
+    rlock!(buffer).write_to(&mut text).unwrap();
+    String::from_utf8(text).unwrap()
+  }
+
+  #[test]
+  fn charwise1() {
+    let (tree, buffer) = make_tree(vec!["Hello, RSVIM!\n", "Second line.\n"]);
+    let mut state = State::default();
+
+    press(&mut state, &tree, KeyCode::Char('v'));
+    assert!(matches!(state.stateful(), StatefulValue::VisualMode(_)));
+    press(&mut state, &tree, KeyCode::Char('l'));
+    press(&mut state, &tree, KeyCode::Char('l'));
+    assert_eq!(state.mode(), Mode::Visual);
+
+    let selection = state.selection().unwrap();
+    assert_eq!(selection.kind(), VisualKind::Charwise);
+    assert_eq!(selection.anchor(), BufferPosition::new(0, 0));
+    assert_eq!(selection.cursor(), BufferPosition::new(0, 2));
+    assert_eq!(
+      selected_char_ranges(&rlock!(buffer), &selection),
+      vec![0..3]
+    );
+    assert_eq!(selected_text(&rlock!(buffer), &selection), "Hel");
+
+    // Cursor widget moves along with the selection.
+    {
+      let tree = rlock!(tree);
+      let cursor_id = tree.cursor_id().unwrap();
+      let cursor = tree.node(&cursor_id).unwrap();
+      assert_eq!(cursor.actual_shape().min().x, 2);
+    }
+
+    press(&mut state, &tree, KeyCode::Char('y'));
+    assert!(matches!(state.stateful(), StatefulValue::NormalMode(_)));
+    assert!(state.selection().is_none());
+    let register = state.registers().get(UNNAMED_REGISTER).unwrap();
+    assert_eq!(register.kind(), RegisterKind::Charwise);
+    assert_eq!(register.text(), "Hel");
+    assert_eq!(state.registers().get(YANK_REGISTER), Some(register));
+
+    // Cursor moves back to the selection start.
+    {
+      let tree = rlock!(tree);
+      let cursor_id = tree.cursor_id().unwrap();
+      let cursor = tree.node(&cursor_id).unwrap();
+      assert_eq!(cursor.actual_shape().min().x, 0);
+    }
+  }
+
+  #[test]
+  fn linewise1() {
+    let (tree, buffer) = make_tree(vec!["Hello, RSVIM!\n", "Second line.\n", "Third.\n"]);
+    let mut state = State::default();
+
+    press(&mut state, &tree, KeyCode::Char('V'));
+    press(&mut state, &tree, KeyCode::Char('l'));
+    press(&mut state, &tree, KeyCode::Char('j'));
+
+    let selection = state.selection().unwrap();
+    assert_eq!(selection.kind(), VisualKind::Linewise);
+    assert_eq!(selection.line_range(), 0..2);
+    assert_eq!(
+      selected_text(&rlock!(buffer), &selection),
+      "Hello, RSVIM!\nSecond line.\n"
+    );
+
+    press(&mut state, &tree, KeyCode::Char('d'));
+    assert!(matches!(state.stateful(), StatefulValue::NormalMode(_)));
+    assert_eq!(buffer_text(&buffer), "Third.\n");
+    let register = state.registers().get(UNNAMED_REGISTER).unwrap();
+    assert_eq!(register.kind(), RegisterKind::Linewise);
+    assert_eq!(register.text(), "Hello, RSVIM!\nSecond line.\n");
+    assert!(state.registers().get(YANK_REGISTER).is_none());
+  }
+
+  #[test]
+  fn named_register1() {
+    let (tree, _buffer) = make_tree(vec!["Hello\n"]);
+    let mut state = State::default();
+
+    press(&mut state, &tree, KeyCode::Char('v'));
+    press(&mut state, &tree, KeyCode::Char('l'));
+    press(&mut state, &tree, KeyCode::Char('"'));
+    press(&mut state, &tree, KeyCode::Char('a'));
+    press(&mut state, &tree, KeyCode::Char('y'));
+    assert!(matches!(state.stateful(), StatefulValue::NormalMode(_)));
+    assert_eq!(state.registers().get('a').unwrap().text(), "He");
+    assert_eq!(
+      state.registers().get(UNNAMED_REGISTER).unwrap().text(),
+      "He"
+    );
+    assert!(state.registers().get(YANK_REGISTER).is_none());
+  }
+
+  #[test]
+  fn blockwise1() {
+    let (tree, buffer) = make_tree(vec!["abcdef\n", "ghijkl\n", "mn\n"]);
+    let mut state = State::default();
+
+    press_key(
+      &mut state,
+      &tree,
+      KeyEvent::new(KeyCode::Char('v'), KeyModifiers::CONTROL),
+    );
+    press(&mut state, &tree, KeyCode::Char('l'));
+    press(&mut state, &tree, KeyCode::Char('j'));
+    press(&mut state, &tree, KeyCode::Char('j'));
+
+    let selection = state.selection().unwrap();
+    assert_eq!(selection.kind(), VisualKind::Blockwise);
+    assert_eq!(selection.line_range(), 0..3);
+    let text = selected_text(&rlock!(buffer), &selection);
+    assert_eq!(text, "ab\ngh\nmn");
+
+    press(&mut state, &tree, KeyCode::Char('d'));
+    assert_eq!(buffer_text(&buffer), "cdef\nijkl\n\n");
+  }
+
+  fn cursor_x(tree: &TreeArc) -> u16 {
+    let tree = rlock!(tree);
+    let cursor_id = tree.cursor_id().unwrap();
+    tree.node(&cursor_id).unwrap().actual_shape().min().x
+  }
+
+  fn cursor_y(tree: &TreeArc) -> u16 {
+    let tree = rlock!(tree);
+    let cursor_id = tree.cursor_id().unwrap();
+    tree.node(&cursor_id).unwrap().actual_shape().min().y
+  }
+
+  #[test]
+  fn put_linewise1() {
+    let (tree, buffer) = make_tree(vec!["Hello\n", "World\n"]);
+    let mut state = State::default();
+
+    press(&mut state, &tree, KeyCode::Char('V'));
+    press(&mut state, &tree, KeyCode::Char('y'));
+    press(&mut state, &tree, KeyCode::Char('p'));
+    assert_eq!(buffer_text(&buffer), "Hello\nHello\nWorld\n");
+    assert_eq!(cursor_y(&tree), 1);
+
+    press(&mut state, &tree, KeyCode::Char('P'));
+    assert_eq!(buffer_text(&buffer), "Hello\nHello\nHello\nWorld\n");
+    assert_eq!(cursor_y(&tree), 1);
+  }
+
+  #[test]
+  fn put_linewise2() {
+    // The last line doesn't have line break.
+    let (tree, buffer) = make_tree(vec!["Hello"]);
+    let mut state = State::default();
+
+    press(&mut state, &tree, KeyCode::Char('V'));
+    press(&mut state, &tree, KeyCode::Char('y'));
+    assert_eq!(
+      state.registers().get(UNNAMED_REGISTER).unwrap().text(),
+      "Hello\n"
+    );
+    press(&mut state, &tree, KeyCode::Char('p'));
+    assert_eq!(buffer_text(&buffer), "Hello\nHello");
+  }
+
+  #[test]
+  fn put_charwise1() {
+    let (tree, buffer) = make_tree(vec!["Hello\n"]);
+    let mut state = State::default();
+
+    press(&mut state, &tree, KeyCode::Char('v'));
+    press(&mut state, &tree, KeyCode::Char('l'));
+    press(&mut state, &tree, KeyCode::Char('y'));
+    assert_eq!(cursor_x(&tree), 0);
+
+    press(&mut state, &tree, KeyCode::Char('p'));
+    assert_eq!(buffer_text(&buffer), "HHeello\n");
+    assert_eq!(cursor_x(&tree), 2);
+
+    press(&mut state, &tree, KeyCode::Char('P'));
+    assert_eq!(buffer_text(&buffer), "HHHeeello\n");
+    assert_eq!(cursor_x(&tree), 3);
+  }
+
+  #[test]
+  fn put_named1() {
+    let (tree, buffer) = make_tree(vec!["abc\n"]);
+    let mut state = State::default();
+
+    // Yank `a` into register `x`, then yank `ab` into the unnamed register.
+    press(&mut state, &tree, KeyCode::Char('v'));
+    press(&mut state, &tree, KeyCode::Char('"'));
+    press(&mut state, &tree, KeyCode::Char('x'));
+    press(&mut state, &tree, KeyCode::Char('y'));
+    press(&mut state, &tree, KeyCode::Char('v'));
+    press(&mut state, &tree, KeyCode::Char('l'));
+    press(&mut state, &tree, KeyCode::Char('y'));
+
+    press(&mut state, &tree, KeyCode::Char('"'));
+    press(&mut state, &tree, KeyCode::Char('x'));
+    press(&mut state, &tree, KeyCode::Char('P'));
+    assert_eq!(buffer_text(&buffer), "aabc\n");
+  }
+
+  #[test]
+  fn switch_kind1() {
+    let (tree, _buffer) = make_tree(vec!["Hello\n"]);
+    let mut state = State::default();
+
+    press(&mut state, &tree, KeyCode::Char('v'));
+    press(&mut state, &tree, KeyCode::Char('V'));
+    assert_eq!(state.selection().unwrap().kind(), VisualKind::Linewise);
+    press(&mut state, &tree, KeyCode::Char('V'));
+    assert!(state.selection().is_none());
+    assert!(matches!(state.stateful(), StatefulValue::NormalMode(_)));
+
+    press(&mut state, &tree, KeyCode::Char('v'));
+    press(&mut state, &tree, KeyCode::Esc);
+    assert!(state.selection().is_none());
+    assert!(matches!(state.stateful(), StatefulValue::NormalMode(_)));
+  }
+
+  #[test]
+  fn modifiable1() {
+    let (tree, buffer) = make_tree(vec!["Hello\n"]);
+    wlock!(buffer).set_modifiable(false);
+    let mut state = State::default();
+
+    press(&mut state, &tree, KeyCode::Char('v'));
+    press(&mut state, &tree, KeyCode::Char('l'));
+    press(&mut state, &tree, KeyCode::Char('d'));
+    assert_eq!(buffer_text(&buffer), "Hello\n");
+    assert!(matches!(state.stateful(), StatefulValue::NormalMode(_)));
+  }
+
+  #[test]
+  fn change_case1() {
+    let (tree, buffer) = make_tree(vec!["HeLLo world\n", "Second line.\n"]);
+    let mut state = State::default();
+
+    press(&mut state, &tree, KeyCode::Char('v'));
+    for _ in 0..4 {
+      press(&mut state, &tree, KeyCode::Char('l'));
+    }
+    press(&mut state, &tree, KeyCode::Char('~'));
+    assert!(matches!(state.stateful(), StatefulValue::NormalMode(_)));
+    assert!(state.selection().is_none());
+    assert_eq!(buffer_text(&buffer), "hEllO world\nSecond line.\n");
+    assert!(state.registers().get(UNNAMED_REGISTER).is_none());
+
+    press(&mut state, &tree, KeyCode::Char('V'));
+    press(&mut state, &tree, KeyCode::Char('j'));
+    press(&mut state, &tree, KeyCode::Char('U'));
+    assert_eq!(buffer_text(&buffer), "HELLO WORLD\nSECOND LINE.\n");
+  }
+}
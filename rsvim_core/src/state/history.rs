@@ -0,0 +1,281 @@
+//! Command-line history, i.e. the executed Ex commands and search patterns.
+//!
+//! The histories are persisted to a file under the cache directory, each line is an entry with
+//! the leading `:` (Ex command) or `/` (search pattern), from the oldest to the newest.
+//!
+//! See: <https://vimhelp.org/cmdline.txt.html#cmdline-history>.
+
+use crate::res::IoResult;
+use crate::state::command::CommandLineKind;
+
+use std::collections::VecDeque;
+use std::path::Path;
+use tracing::trace;
+
+/// The file name of the persisted histories, under the cache directory.
+pub const HISTORY_FILE_NAME: &str = "history";
+
+#[derive(Debug, Clone)]
+/// The history of a command-line kind, the oldest entries are dropped when it's full.
+pub struct CmdHistory {
+  entries: VecDeque<String>,
+  max_size: usize,
+  // The index of the navigated entry, and the text typed before the navigation. The typed text is
+  // the newest position, and it filters the entries by prefix, same with Vim.
+  navigation: Option<(usize, String)>,
+}
+
+impl CmdHistory {
+  pub fn new(max_size: usize) -> Self {
+    CmdHistory {
+      entries: VecDeque::new(),
+      max_size,
+      navigation: None,
+    }
+  }
+
+  /// The entries, from the oldest to the newest.
+  pub fn entries(&self) -> &VecDeque<String> {
+    &self.entries
+  }
+
+  pub fn max_size(&self) -> usize {
+    self.max_size
+  }
+
+  /// Set the max size, the oldest entries are dropped if there're more.
+  pub fn set_max_size(&mut self, max_size: usize) {
+    self.max_size = max_size;
+    self.truncate();
+  }
+
+  fn truncate(&mut self) {
+    while self.entries.len() > self.max_size {
+      self.entries.pop_front();
+    }
+  }
+
+  /// Push the executed entry and end the navigation. The empty entry, or the same entry with the
+  /// newest one (i.e. the repeated command) is not pushed.
+  pub fn push(&mut self, entry: &str) {
+    self.reset();
+    if entry.is_empty() || self.entries.back().is_some_and(|last| last == entry) {
+      return;
+    }
+    self.entries.push_back(entry.to_string());
+    self.truncate();
+  }
+
+  /// Go to the older entry that starts with the text typed before the navigation, i.e. `Up`. The
+  /// `typed` is the current command line, it's saved when the navigation starts.
+  ///
+  /// It returns `None` if there's no older entry.
+  pub fn older(&mut self, typed: &str) -> Option<String> {
+    let len = self.entries.len();
+    let (index, prefix) = self
+      .navigation
+      .get_or_insert_with(|| (len, typed.to_string()));
+    match self
+      .entries
+      .range(..*index)
+      .rposition(|entry| entry.starts_with(prefix.as_str()))
+    {
+      Some(found) => {
+        *index = found;
+        Some(self.entries[found].clone())
+      }
+      None => {
+        if *index == len {
+          self.navigation = None;
+        }
+        None
+      }
+    }
+  }
+
+  /// Go to the newer entry that starts with the text typed before the navigation, i.e. `Down`.
+  /// After the newest entry, it goes back to the typed text and the navigation ends.
+  ///
+  /// It returns `None` if it's not navigating.
+  pub fn newer(&mut self) -> Option<String> {
+    let (index, prefix) = self.navigation.as_mut()?;
+    let start = *index + 1;
+    match self
+      .entries
+      .range(start..)
+      .position(|entry| entry.starts_with(prefix.as_str()))
+    {
+      Some(found) => {
+        *index = start + found;
+        Some(self.entries[start + found].clone())
+      }
+      None => self.navigation.take().map(|(_, typed)| typed),
+    }
+  }
+
+  /// End the navigation, i.e. the command line is edited or left.
+  pub fn reset(&mut self) {
+    self.navigation = None;
+  }
+}
+
+#[derive(Debug, Clone)]
+/// The histories of the Ex commands and the search patterns.
+pub struct CmdHistories {
+  ex: CmdHistory,
+  search: CmdHistory,
+}
+
+impl CmdHistories {
+  pub fn new(max_size: usize) -> Self {
+    CmdHistories {
+      ex: CmdHistory::new(max_size),
+      search: CmdHistory::new(max_size),
+    }
+  }
+
+  /// Get the history of the command-line kind.
+  pub fn get(&self, kind: CommandLineKind) -> &CmdHistory {
+    match kind {
+      CommandLineKind::Ex => &self.ex,
+      CommandLineKind::Search => &self.search,
+    }
+  }
+
+  pub fn get_mut(&mut self, kind: CommandLineKind) -> &mut CmdHistory {
+    match kind {
+      CommandLineKind::Ex => &mut self.ex,
+      CommandLineKind::Search => &mut self.search,
+    }
+  }
+
+  /// Load the histories from the file, the histories are empty if the file doesn't exist or it's
+  /// corrupted, i.e. it's not UTF-8 or has a line without the `:` or `/` prefix.
+  pub fn load(path: &Path, max_size: usize) -> Self {
+    let mut histories = CmdHistories::new(max_size);
+    let payload = match std::fs::read_to_string(path) {
+      Ok(payload) => payload,
+      Err(e) => {
+        trace!("Failed to read history file {:?}:{:?}", path, e);
+        return histories;
+      }
+    };
+    for line in payload.lines() {
+      let (kind, entry) = match line.chars().next() {
+        Some(':') => (CommandLineKind::Ex, &line[1..]),
+        Some('/') => (CommandLineKind::Search, &line[1..]),
+        _ => {
+          trace!("Ignored corrupted history file {:?}:{:?}", path, line);
+          return CmdHistories::new(max_size);
+        }
+      };
+      histories.get_mut(kind).push(entry);
+    }
+    histories
+  }
+
+  /// Save the histories to the file, the parent directories are created if not exist.
+  pub fn save(&self, path: &Path) -> IoResult<()> {
+    let mut payload = String::new();
+    for (prefix, history) in [(':', &self.ex), ('/', &self.search)] {
+      for entry in history.entries().iter() {
+        // The line break cannot be typed in the command line, but it breaks the file.
+        if !entry.contains(['\n', '\r']) {
+          payload.push(prefix);
+          payload.push_str(entry);
+          payload.push('\n');
+        }
+      }
+    }
+    if let Some(parent) = path.parent() {
+      std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, payload)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn make_history(entries: &[&str]) -> CmdHistory {
+    let mut history = CmdHistory::new(10);
+    for entry in entries.iter() {
+      history.push(entry);
+    }
+    history
+  }
+
+  #[test]
+  fn push1() {
+    let mut history = make_history(&["w", "w", "", "set wrap", "w"]);
+    assert_eq!(history.entries(), &["w", "set wrap", "w"]);
+
+    history.set_max_size(2);
+    assert_eq!(history.entries(), &["set wrap", "w"]);
+    history.push("q");
+    assert_eq!(history.entries(), &["w", "q"]);
+  }
+
+  #[test]
+  fn navigate1() {
+    let mut history = make_history(&["set wrap", "w", "set list", "s/a/b/"]);
+    assert_eq!(history.newer(), None);
+
+    // Without prefix.
+    assert_eq!(history.older(""), Some("s/a/b/".to_string()));
+    assert_eq!(history.older(""), Some("set list".to_string()));
+    assert_eq!(history.newer(), Some("s/a/b/".to_string()));
+    assert_eq!(history.newer(), Some("".to_string()));
+    assert_eq!(history.newer(), None);
+
+    // Filtered by the typed prefix, the typed text is the newest position.
+    assert_eq!(history.older("se"), Some("set list".to_string()));
+    assert_eq!(history.older("set list"), Some("set wrap".to_string()));
+    assert_eq!(history.older("set wrap"), None);
+    assert_eq!(history.newer(), Some("set list".to_string()));
+    assert_eq!(history.newer(), Some("se".to_string()));
+
+    // Nothing matches.
+    assert_eq!(history.older("x"), None);
+    assert_eq!(history.newer(), None);
+
+    // Pushing ends the navigation.
+    assert_eq!(history.older("w"), Some("w".to_string()));
+    history.push("w");
+    assert_eq!(history.newer(), None);
+  }
+
+  #[test]
+  fn persist1() {
+    let tmpdir = assert_fs::TempDir::new().unwrap();
+    let path = tmpdir.path().join("cache").join(HISTORY_FILE_NAME);
+
+    // The file doesn't exist.
+    let mut histories = CmdHistories::load(&path, 3);
+    assert!(histories.get(CommandLineKind::Ex).entries().is_empty());
+
+    for entry in ["a", "b", "c", "d"] {
+      histories.get_mut(CommandLineKind::Ex).push(entry);
+    }
+    histories.get_mut(CommandLineKind::Search).push("foo");
+    histories.save(&path).unwrap();
+    assert_eq!(
+      std::fs::read_to_string(&path).unwrap(),
+      ":b\n:c\n:d\n/foo\n"
+    );
+
+    // The size cap applies when loading.
+    let histories = CmdHistories::load(&path, 2);
+    assert_eq!(histories.get(CommandLineKind::Ex).entries(), &["c", "d"]);
+    assert_eq!(histories.get(CommandLineKind::Search).entries(), &["foo"]);
+
+    // The corrupted file is ignored.
+    std::fs::write(&path, ":a\ngarbage\n").unwrap();
+    let histories = CmdHistories::load(&path, 3);
+    assert!(histories.get(CommandLineKind::Ex).entries().is_empty());
+    std::fs::write(&path, [b':', 0xff, 0xfe, b'\n']).unwrap();
+    let histories = CmdHistories::load(&path, 3);
+    assert!(histories.get(CommandLineKind::Ex).entries().is_empty());
+  }
+}
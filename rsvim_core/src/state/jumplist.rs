@@ -0,0 +1,220 @@
+//! The jump list, i.e. the cursor positions before the jumps (`/`, `G`, `gg`, `%`), walked by
+//! `Ctrl-O`/`Ctrl-I`.
+//!
+//! See: <https://vimhelp.org/motion.txt.html#jumplist>.
+
+use crate::buf::{BufferId, LineChange};
+use crate::state::selection::BufferPosition;
+
+use std::collections::VecDeque;
+
+/// The max jumps in the jump list, the oldest jump is dropped when it's full, same with Vim.
+pub const JUMP_LIST_SIZE: usize = 100;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+/// A jump, i.e. the cursor position in a buffer.
+pub struct Jump {
+  buffer_id: BufferId,
+  position: BufferPosition,
+}
+
+impl Jump {
+  pub fn new(buffer_id: BufferId, position: BufferPosition) -> Self {
+    Jump {
+      buffer_id,
+      position,
+    }
+  }
+
+  pub fn buffer_id(&self) -> BufferId {
+    self.buffer_id
+  }
+
+  pub fn position(&self) -> BufferPosition {
+    self.position
+  }
+}
+
+#[derive(Debug, Clone, Default)]
+/// The jump list, the jumps are ordered from the oldest to the newest.
+///
+/// Walking back with `Ctrl-O` from the newest jump saves the cursor position as a jump first, thus
+/// `Ctrl-I` can go back to it. Jumping to a new position while walking discards the jumps newer
+/// than the current one, i.e. the branch is truncated.
+pub struct JumpList {
+  jumps: VecDeque<Jump>,
+
+  // The index of the current jump while walking the list, it equals to the jumps count if not
+  // walking.
+  current: usize,
+}
+
+impl JumpList {
+  pub fn new() -> Self {
+    JumpList::default()
+  }
+
+  pub fn len(&self) -> usize {
+    self.jumps.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.jumps.is_empty()
+  }
+
+  /// Get the jumps, from the oldest to the newest.
+  pub fn jumps(&self) -> impl Iterator<Item = &Jump> {
+    self.jumps.iter()
+  }
+
+  /// Get the index of the current jump while walking the list, or the jumps count if not walking.
+  pub fn current(&self) -> usize {
+    self.current
+  }
+
+  /// Record the cursor position before a jump as the newest jump. The jumps newer than the
+  /// current one are discarded, and the existing jump at the same line is removed, same with Vim.
+  pub fn push(&mut self, jump: Jump) {
+    self.jumps.truncate(self.current + 1);
+    self.jumps.retain(|j| {
+      j.buffer_id != jump.buffer_id || j.position.line_idx() != jump.position.line_idx()
+    });
+    self.jumps.push_back(jump);
+    while self.jumps.len() > JUMP_LIST_SIZE {
+      self.jumps.pop_front();
+    }
+    self.current = self.jumps.len();
+  }
+
+  /// Walk back `count` jumps from the `cursor`, i.e. `Ctrl-O`. It returns `None` if there're no
+  /// older jumps.
+  pub fn back(&mut self, cursor: Jump, count: usize) -> Option<Jump> {
+    let count = count.max(1);
+    if self.current == self.jumps.len() {
+      // Start walking from the newest jump, save the cursor position for `Ctrl-I`.
+      self.push(cursor);
+      self.current = self.jumps.len() - 1;
+    }
+    if self.current < count {
+      return None;
+    }
+    self.current -= count;
+    self.jumps.get(self.current).copied()
+  }
+
+  /// Walk forward `count` jumps, i.e. `Ctrl-I`. It returns `None` if there're no newer jumps.
+  pub fn forward(&mut self, count: usize) -> Option<Jump> {
+    let count = count.max(1);
+    if self.current + count >= self.jumps.len() {
+      return None;
+    }
+    self.current += count;
+    self.jumps.get(self.current).copied()
+  }
+
+  /// Follow the lines changed in the buffer, the jumps after the changed line are shifted, and the
+  /// jumps in the removed lines are moved to the changed line.
+  pub fn adjust(&mut self, buffer_id: BufferId, line_changes: &[LineChange]) {
+    for jump in self.jumps.iter_mut() {
+      if jump.buffer_id != buffer_id {
+        continue;
+      }
+      let line_idx = line_changes
+        .iter()
+        .fold(jump.position.line_idx(), |line_idx, change| {
+          change.adjust(line_idx)
+        });
+      jump.position = BufferPosition::new(line_idx, jump.position.char_idx());
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn jump(line_idx: usize, char_idx: usize) -> Jump {
+    Jump::new(1, BufferPosition::new(line_idx, char_idx))
+  }
+
+  fn lines(jump_list: &JumpList) -> Vec<usize> {
+    jump_list.jumps().map(|j| j.position().line_idx()).collect()
+  }
+
+  #[test]
+  fn walk1() {
+    let mut jump_list = JumpList::new();
+    assert_eq!(jump_list.back(jump(0, 0), 1), None);
+    let mut jump_list = JumpList::new();
+    jump_list.push(jump(1, 0));
+    jump_list.push(jump(5, 2));
+    jump_list.push(jump(9, 0));
+    assert_eq!(lines(&jump_list), vec![1, 5, 9]);
+    assert_eq!(jump_list.forward(1), None);
+
+    // The cursor is saved when it starts walking back.
+    assert_eq!(jump_list.back(jump(20, 3), 1), Some(jump(9, 0)));
+    assert_eq!(lines(&jump_list), vec![1, 5, 9, 20]);
+    assert_eq!(jump_list.back(jump(9, 0), 1), Some(jump(5, 2)));
+    assert_eq!(jump_list.back(jump(5, 2), 1), Some(jump(1, 0)));
+    assert_eq!(jump_list.back(jump(1, 0), 1), None);
+    assert_eq!(jump_list.current(), 0);
+    assert_eq!(jump_list.forward(2), Some(jump(9, 0)));
+    assert_eq!(jump_list.forward(1), Some(jump(20, 3)));
+    assert_eq!(jump_list.forward(1), None);
+    assert_eq!(jump_list.back(jump(20, 3), 3), Some(jump(1, 0)));
+  }
+
+  #[test]
+  fn push1() {
+    let mut jump_list = JumpList::new();
+    for line_idx in 0..5 {
+      jump_list.push(jump(line_idx, 0));
+    }
+    assert_eq!(jump_list.back(jump(5, 0), 3), Some(jump(2, 0)));
+
+    // Jumping after walking back truncates the newer jumps.
+    jump_list.push(jump(7, 0));
+    assert_eq!(lines(&jump_list), vec![0, 1, 2, 7]);
+    assert_eq!(jump_list.current(), 4);
+    assert_eq!(jump_list.forward(1), None);
+
+    // The jump at the same line is moved to the newest.
+    jump_list.push(jump(1, 4));
+    assert_eq!(lines(&jump_list), vec![0, 2, 7, 1]);
+
+    // The list is bounded.
+    for line_idx in 0..JUMP_LIST_SIZE + 10 {
+      jump_list.push(jump(line_idx + 10, 0));
+    }
+    assert_eq!(jump_list.len(), JUMP_LIST_SIZE);
+    assert_eq!(jump_list.jumps().next(), Some(&jump(20, 0)));
+  }
+
+  #[test]
+  fn adjust1() {
+    let mut jump_list = JumpList::new();
+    jump_list.push(jump(1, 0));
+    jump_list.push(jump(3, 1));
+    jump_list.push(jump(6, 2));
+    jump_list.push(Jump::new(2, BufferPosition::new(6, 0)));
+
+    jump_list.adjust(
+      1,
+      &[
+        LineChange {
+          line_idx: 0,
+          removed: 0,
+          inserted: 2,
+        },
+        LineChange {
+          line_idx: 4,
+          removed: 2,
+          inserted: 0,
+        },
+      ],
+    );
+    assert_eq!(lines(&jump_list), vec![3, 4, 6, 6]);
+    assert_eq!(jump_list.jumps().nth(1), Some(&jump(4, 1)));
+  }
+}
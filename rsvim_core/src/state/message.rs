@@ -0,0 +1,44 @@
+//! The message shown in the message area, i.e. the last row of the terminal.
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+/// Message kind.
+pub enum MessageKind {
+  /// Normal message, i.e. `:echo`.
+  Info,
+  /// Error message, i.e. `:echoerr`, it renders with the error highlight.
+  Error,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// One-line message.
+pub struct Message {
+  kind: MessageKind,
+  text: String,
+}
+
+impl Message {
+  pub fn new(kind: MessageKind, text: &str) -> Self {
+    Message {
+      kind,
+      text: text.to_string(),
+    }
+  }
+
+  /// Make a normal message.
+  pub fn info(text: &str) -> Self {
+    Message::new(MessageKind::Info, text)
+  }
+
+  /// Make an error message.
+  pub fn error(text: &str) -> Self {
+    Message::new(MessageKind::Error, text)
+  }
+
+  pub fn kind(&self) -> MessageKind {
+    self.kind
+  }
+
+  pub fn text(&self) -> &str {
+    &self.text
+  }
+}
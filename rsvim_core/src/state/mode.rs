@@ -1,5 +1,7 @@
 //! Vim editing mode.
 
+use crate::ui::canvas::CursorStyle;
+
 use ahash::AHashSet as HashSet;
 use std::fmt::Display;
 use std::str::FromStr;
@@ -17,6 +19,8 @@ pub enum Mode {
   OperatorPending,
   /// Insert mode.
   Insert,
+  /// Replace mode, i.e. `R`.
+  Replace,
   /// Command-line mode.
   CommandLine,
   /// Terminal mode.
@@ -31,6 +35,7 @@ impl Display for Mode {
       Mode::Select => write!(f, "Select"),
       Mode::OperatorPending => write!(f, "Operator-pending"),
       Mode::Insert => write!(f, "Insert"),
+      Mode::Replace => write!(f, "Replace"),
       Mode::CommandLine => write!(f, "Command-line"),
       Mode::Terminal => write!(f, "Terminal"),
     }
@@ -48,6 +53,7 @@ impl FromStr for Mode {
       "Select" => Ok(Mode::Visual),
       "Operator-pending" => Ok(Mode::OperatorPending),
       "Insert" => Ok(Mode::Insert),
+      "Replace" => Ok(Mode::Replace),
       "Command-line" => Ok(Mode::CommandLine),
       "Terminal" => Ok(Mode::Terminal),
       _ => Err("Invalid Mode name"),
@@ -82,10 +88,23 @@ impl Mode {
       Mode::Select,
       Mode::OperatorPending,
       Mode::Insert,
+      Mode::Replace,
       Mode::CommandLine,
       Mode::Terminal,
     ]
   }
+
+  /// The cursor style in the mode, i.e. a bar in insert mode and an underline in replace mode,
+  /// same with Vim's default 'guicursor'.
+  ///
+  /// See: <https://vimhelp.org/options.txt.html#%27guicursor%27>.
+  pub fn cursor_style(&self) -> CursorStyle {
+    match self {
+      Mode::Insert => CursorStyle::BlinkingBar,
+      Mode::Replace => CursorStyle::BlinkingUnderScore,
+      _ => CursorStyle::DefaultUserShape,
+    }
+  }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Default)]
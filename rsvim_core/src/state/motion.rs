@@ -0,0 +1,544 @@
+//! The cursor motions, i.e. `w`/`b`/`e`/`$`, and the jumps, i.e. `G`/`gg`/`%`.
+//!
+//! See: <https://vimhelp.org/motion.txt.html#word-motions>.
+
+use crate::buf::Buffer;
+use crate::state::selection::BufferPosition;
+
+use std::ops::Range;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+/// The cursor motion.
+pub enum Motion {
+  /// Move to the start of next word, i.e. `w`/`W`.
+  NextWordStart { big_word: bool },
+  /// Move to the start of previous word, i.e. `b`/`B`.
+  PrevWordStart { big_word: bool },
+  /// Move to the end of next word, i.e. `e`/`E`.
+  NextWordEnd { big_word: bool },
+  /// Move to the last char of the line, i.e. `$`. With count `N`, move to the `N - 1` lines
+  /// downward.
+  LineEnd,
+  /// Move to the first char of the line, i.e. `0`. The count is ignored.
+  LineStart,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+/// Whether the operator works on the chars, or the whole lines.
+/// See: <https://vimhelp.org/motion.txt.html#linewise>.
+pub enum MotionWise {
+  Charwise,
+  Linewise,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// The text spanned by a motion for operators, i.e. `dw`/`yy`.
+pub struct MotionRange {
+  char_range: Range<usize>,
+  wise: MotionWise,
+}
+
+impl MotionRange {
+  pub fn new(char_range: Range<usize>, wise: MotionWise) -> Self {
+    MotionRange { char_range, wise }
+  }
+
+  /// The char range based on the whole buffer. For linewise range, it contains the line breaks.
+  pub fn char_range(&self) -> &Range<usize> {
+    &self.char_range
+  }
+
+  pub fn wise(&self) -> MotionWise {
+    self.wise
+  }
+
+  /// Get the linewise range of `count` lines starts from the line, i.e. `dd`/`2yy`. The lines
+  /// beyond the last line are ignored.
+  pub fn lines(buffer: &Buffer, line_idx: usize, count: usize) -> Self {
+    let chars = BufferChars { buffer };
+    let start_line_idx = std::cmp::min(line_idx, chars.last_line_idx());
+    let end_line_idx = std::cmp::min(start_line_idx + count.max(1), chars.last_line_idx() + 1);
+    let end_char_idx = if end_line_idx < buffer.len_lines() {
+      buffer.line_to_char(end_line_idx)
+    } else {
+      buffer.len_chars()
+    };
+    MotionRange::new(
+      buffer.line_to_char(start_line_idx)..end_char_idx,
+      MotionWise::Linewise,
+    )
+  }
+}
+
+impl Motion {
+  /// Get the motion by key, i.e. `w`/`b`/`e`/`W`/`B`/`E`/`$`/`0`.
+  pub fn from_key(c: char) -> Option<Motion> {
+    match c {
+      'w' => Some(Motion::NextWordStart { big_word: false }),
+      'W' => Some(Motion::NextWordStart { big_word: true }),
+      'b' => Some(Motion::PrevWordStart { big_word: false }),
+      'B' => Some(Motion::PrevWordStart { big_word: true }),
+      'e' => Some(Motion::NextWordEnd { big_word: false }),
+      'E' => Some(Motion::NextWordEnd { big_word: true }),
+      '$' => Some(Motion::LineEnd),
+      '0' => Some(Motion::LineStart),
+      _ => None,
+    }
+  }
+
+  /// Whether the char at the target position is included by the operator, i.e. `de` deletes the
+  /// last char of the word but `dw` doesn't delete the first char of next word.
+  /// See: <https://vimhelp.org/motion.txt.html#inclusive>.
+  pub fn inclusive(&self) -> bool {
+    matches!(self, Motion::NextWordEnd { .. } | Motion::LineEnd)
+  }
+
+  /// Get the target position of the motion starts from the cursor. It stops at the first/last
+  /// char of the buffer if there is no more words.
+  pub fn target(&self, buffer: &Buffer, cursor: BufferPosition) -> BufferPosition {
+    let chars = BufferChars { buffer };
+    let (line_idx, char_idx) = (cursor.line_idx(), cursor.char_idx());
+    let word_target = |target: Option<(usize, usize)>, forward: bool| match target {
+      Some((line_idx, char_idx)) => BufferPosition::new(line_idx, char_idx),
+      None if forward => chars.line_end(chars.last_line_idx()),
+      None => BufferPosition::new(0, 0),
+    };
+    match *self {
+      Motion::NextWordStart { big_word } => {
+        word_target(buffer.next_word_start(line_idx, char_idx, big_word), true)
+      }
+      Motion::PrevWordStart { big_word } => {
+        word_target(buffer.prev_word_start(line_idx, char_idx, big_word), false)
+      }
+      Motion::NextWordEnd { big_word } => {
+        word_target(buffer.next_word_end(line_idx, char_idx, big_word), true)
+      }
+      Motion::LineEnd => chars.line_end(cursor.line_idx()),
+      Motion::LineStart => BufferPosition::new(cursor.line_idx(), 0),
+    }
+  }
+
+  /// Get the target position of the motion repeated `count` times, i.e. `3w`.
+  pub fn target_n(&self, buffer: &Buffer, cursor: BufferPosition, count: usize) -> BufferPosition {
+    if let Motion::LineEnd = self {
+      let chars = BufferChars { buffer };
+      let line_idx = cursor.line_idx().saturating_add(count.max(1) - 1);
+      return chars.line_end(std::cmp::min(line_idx, chars.last_line_idx()));
+    }
+    let mut target = cursor;
+    for _ in 0..count.max(1) {
+      let next = self.target(buffer, target);
+      if next == target {
+        break;
+      }
+      target = next;
+    }
+    target
+  }
+
+  /// Get the text spanned by the motion repeated `count` times for operators, i.e. `2dw`. The
+  /// word motions are charwise, and the char at the target is included only if the motion is
+  /// [inclusive](Motion::inclusive).
+  ///
+  /// Same with Vim, if the last word moved over by `w` is at the end of line, the range stops at
+  /// the end of line instead of the next word in next line. And if there's no more words, it
+  /// stops at the end of the last line.
+  /// See: <https://vimhelp.org/motion.txt.html#WORD>.
+  pub fn range(&self, buffer: &Buffer, cursor: BufferPosition, count: usize) -> MotionRange {
+    let chars = BufferChars { buffer };
+    let to_char_idx = |pos: BufferPosition| buffer.line_to_char(pos.line_idx()) + pos.char_idx();
+    let line_end_char_idx =
+      |line_idx: usize| buffer.line_to_char(line_idx) + buffer.line_len_chars(line_idx);
+    let start = to_char_idx(cursor);
+    let char_range = match *self {
+      Motion::NextWordStart { big_word } => {
+        let mut target = cursor;
+        let mut found = true;
+        for _ in 0..count.max(1) {
+          match buffer.next_word_start(target.line_idx(), target.char_idx(), big_word) {
+            Some((line_idx, char_idx)) => target = BufferPosition::new(line_idx, char_idx),
+            None => {
+              target = chars.line_end(chars.last_line_idx());
+              found = false;
+              break;
+            }
+          }
+        }
+        let end = if !found {
+          line_end_char_idx(target.line_idx())
+        } else if target.line_idx() > cursor.line_idx()
+          && target.char_idx() <= buffer.first_non_blank_char(target.line_idx())
+        {
+          line_end_char_idx(target.line_idx() - 1)
+        } else {
+          to_char_idx(target)
+        };
+        start..std::cmp::max(start, end)
+      }
+      Motion::PrevWordStart { .. } => to_char_idx(self.target_n(buffer, cursor, count))..start,
+      Motion::NextWordEnd { .. } => {
+        let target = self.target_n(buffer, cursor, count);
+        start
+          ..std::cmp::min(
+            to_char_idx(target) + 1,
+            line_end_char_idx(target.line_idx()),
+          )
+      }
+      Motion::LineEnd => {
+        let target = self.target_n(buffer, cursor, count);
+        start..std::cmp::max(start, line_end_char_idx(target.line_idx()))
+      }
+      Motion::LineStart => buffer.line_to_char(cursor.line_idx())..start,
+    };
+    MotionRange::new(char_range, MotionWise::Charwise)
+  }
+}
+
+/// Get the target position of `G`/`gg`, i.e. the first non-blank char of the line `count` (starts
+/// from 1), or the last/first line if there's no count. The count beyond the last line stops at
+/// the last line.
+/// See: <https://vimhelp.org/motion.txt.html#G>.
+pub fn goto_line_target(buffer: &Buffer, count: Option<usize>, last: bool) -> BufferPosition {
+  let last_line_idx = buffer.last_line_idx();
+  let line_idx = match count {
+    Some(count) => std::cmp::min(count.max(1) - 1, last_line_idx),
+    None if last => last_line_idx,
+    None => 0,
+  };
+  BufferPosition::new(line_idx, buffer.first_non_blank_char(line_idx))
+}
+
+/// Get the position inside the buffer, the line beyond the last line stops at the last line, and
+/// the char beyond the end of line stops at the last char, i.e. the saved positions after edits.
+pub fn clamp_position(buffer: &Buffer, pos: BufferPosition) -> BufferPosition {
+  let line_idx = std::cmp::min(pos.line_idx(), buffer.last_line_idx());
+  let char_idx = std::cmp::min(
+    pos.char_idx(),
+    buffer.line_len_chars(line_idx).saturating_sub(1),
+  );
+  BufferPosition::new(line_idx, char_idx)
+}
+
+/// Get the target position of `{count}%`, i.e. the first non-blank char of the line at `count`
+/// percentage of the buffer.
+/// See: <https://vimhelp.org/motion.txt.html#N%25>.
+pub fn percent_line_target(buffer: &Buffer, count: usize) -> BufferPosition {
+  let lines = buffer.last_line_idx() + 1;
+  let count = std::cmp::min(count, 100);
+  goto_line_target(buffer, Some((count * lines).div_ceil(100)), true)
+}
+
+/// Get the target position of `%`, i.e. the bracket matching the first bracket (`(`/`)`, `[`/`]`
+/// or `{`/`}`) at or after the cursor in the cursor line. It returns `None` if there's no bracket
+/// or it's unmatched.
+/// See: <https://vimhelp.org/motion.txt.html#%25>.
+pub fn matching_bracket_target(buffer: &Buffer, cursor: BufferPosition) -> Option<BufferPosition> {
+  const BRACKETS: [(char, char); 3] = [('(', ')'), ('[', ']'), ('{', '}')];
+
+  let chars = BufferChars { buffer };
+  let line = buffer.get_line(cursor.line_idx())?;
+  let line_len = buffer.line_len_chars(cursor.line_idx());
+  let (char_idx, c) = line
+    .chars()
+    .enumerate()
+    .take(line_len)
+    .skip(cursor.char_idx())
+    .find(|(_, c)| BRACKETS.iter().any(|(open, close)| c == open || c == close))?;
+  let (open, close) = *BRACKETS
+    .iter()
+    .find(|(open, close)| c == *open || c == *close)?;
+  let forward = c == open;
+
+  let char_at = |pos: BufferPosition| {
+    buffer
+      .get_line(pos.line_idx())
+      .filter(|_| pos.char_idx() < buffer.line_len_chars(pos.line_idx()))
+      .map(|line| line.char(pos.char_idx()))
+  };
+  let mut pos = BufferPosition::new(cursor.line_idx(), char_idx);
+  let mut depth = 0_usize;
+  loop {
+    match char_at(pos) {
+      Some(c) if c == open || c == close => {
+        if (c == open) == forward {
+          depth += 1;
+        } else {
+          depth -= 1;
+          if depth == 0 {
+            return Some(pos);
+          }
+        }
+      }
+      _ => {}
+    }
+    pos = if forward {
+      chars.next(pos)?
+    } else {
+      chars.prev(pos)?
+    };
+  }
+}
+
+// Iterates the char positions of a buffer, an empty line has only 1 position at char index 0.
+struct BufferChars<'a> {
+  buffer: &'a Buffer,
+}
+
+impl BufferChars<'_> {
+  // The last line index, the empty line after the last line break is not a line.
+  fn last_line_idx(&self) -> usize {
+    self.buffer.last_line_idx()
+  }
+
+  fn next(&self, pos: BufferPosition) -> Option<BufferPosition> {
+    if pos.char_idx() + 1 < self.buffer.line_len_chars(pos.line_idx()) {
+      Some(BufferPosition::new(pos.line_idx(), pos.char_idx() + 1))
+    } else if pos.line_idx() < self.last_line_idx() {
+      Some(BufferPosition::new(pos.line_idx() + 1, 0))
+    } else {
+      None
+    }
+  }
+
+  fn prev(&self, pos: BufferPosition) -> Option<BufferPosition> {
+    if pos.char_idx() > 0 {
+      Some(BufferPosition::new(pos.line_idx(), pos.char_idx() - 1))
+    } else if pos.line_idx() > 0 {
+      let line_idx = pos.line_idx() - 1;
+      let char_idx = self.buffer.line_len_chars(line_idx).saturating_sub(1);
+      Some(BufferPosition::new(line_idx, char_idx))
+    } else {
+      None
+    }
+  }
+
+  // The last char of the line, or char index 0 if the line is empty.
+  fn line_end(&self, line_idx: usize) -> BufferPosition {
+    BufferPosition::new(
+      line_idx,
+      self.buffer.line_len_chars(line_idx).saturating_sub(1),
+    )
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use crate::envar;
+  use crate::rlock;
+  use crate::test::buf::make_buffer_from_lines;
+
+  // Apply the motion repeatedly from `(0, 0)`, and collect the landing positions.
+  fn do_test_motion(lines: Vec<&str>, key: char, times: usize) -> Vec<(usize, usize)> {
+    let buffer = make_buffer_from_lines(lines);
+    let buffer = rlock!(buffer);
+    let motion = Motion::from_key(key).unwrap();
+    let mut cursor = match motion {
+      Motion::PrevWordStart { .. } => {
+        let line_idx = buffer.len_lines().saturating_sub(2);
+        BufferPosition::new(line_idx, buffer.line_len_chars(line_idx).saturating_sub(1))
+      }
+      _ => BufferPosition::new(0, 0),
+    };
+    (0..times)
+      .map(|_| {
+        cursor = motion.target(&buffer, cursor);
+        (cursor.line_idx(), cursor.char_idx())
+      })
+      .collect()
+  }
+
+  #[test]
+  fn next_word_start1() {
+    // 0         1         2
+    // 0123456789012345678901234
+    // foo.bar(baz_1, "qux") end
+    let lines = vec!["foo.bar(baz_1, \"qux\") end\n"];
+    assert_eq!(
+      do_test_motion(lines.clone(), 'w', 9),
+      vec![
+        (0, 3),
+        (0, 4),
+        (0, 7),
+        (0, 8),
+        (0, 13),
+        (0, 15),
+        (0, 16),
+        (0, 19),
+        (0, 22)
+      ]
+    );
+    // Stops at the last char.
+    assert_eq!(do_test_motion(lines.clone(), 'w', 11)[10], (0, 24));
+    assert_eq!(
+      do_test_motion(lines, 'W', 3),
+      vec![(0, 15), (0, 22), (0, 24)]
+    );
+  }
+
+  #[test]
+  fn next_word_start2() {
+    // Move across lines, the empty line is a word.
+    let lines = vec!["foo bar\n", "  baz\n", "\n", "qux;\n"];
+    assert_eq!(
+      do_test_motion(lines, 'w', 6),
+      vec![(0, 4), (1, 2), (2, 0), (3, 0), (3, 3), (3, 3)]
+    );
+  }
+
+  #[test]
+  fn prev_word_start1() {
+    let lines = vec!["foo.bar(baz_1, \"qux\") end\n"];
+    assert_eq!(
+      do_test_motion(lines.clone(), 'b', 10),
+      vec![
+        (0, 22),
+        (0, 19),
+        (0, 16),
+        (0, 15),
+        (0, 13),
+        (0, 8),
+        (0, 7),
+        (0, 4),
+        (0, 3),
+        (0, 0)
+      ]
+    );
+    assert_eq!(do_test_motion(lines.clone(), 'b', 12)[11], (0, 0));
+    assert_eq!(
+      do_test_motion(lines, 'B', 3),
+      vec![(0, 22), (0, 15), (0, 0)]
+    );
+
+    let lines = vec!["foo bar\n", "  baz\n", "\n", "qux;\n"];
+    assert_eq!(
+      do_test_motion(lines, 'b', 5),
+      vec![(3, 0), (2, 0), (1, 2), (0, 4), (0, 0)]
+    );
+  }
+
+  #[test]
+  fn next_word_end1() {
+    let lines = vec!["foo.bar(baz_1, \"qux\") end\n"];
+    assert_eq!(
+      do_test_motion(lines.clone(), 'e', 8),
+      vec![
+        (0, 2),
+        (0, 3),
+        (0, 6),
+        (0, 7),
+        (0, 12),
+        (0, 13),
+        (0, 15),
+        (0, 18)
+      ]
+    );
+    assert_eq!(
+      do_test_motion(lines, 'E', 3),
+      vec![(0, 13), (0, 20), (0, 24)]
+    );
+
+    // The empty lines are skipped.
+    let lines = vec!["foo\n", "\n", "  bar\n"];
+    assert_eq!(do_test_motion(lines, 'e', 3), vec![(0, 2), (2, 4), (2, 4)]);
+  }
+
+  #[test]
+  fn line_end1() {
+    let buffer = make_buffer_from_lines(vec!["foo bar\n", "\n", "baz\n"]);
+    let buffer = rlock!(buffer);
+    let motion = Motion::from_key('$').unwrap();
+    assert!(motion.inclusive());
+    let cursor = BufferPosition::new(0, 2);
+    assert_eq!(motion.target(&buffer, cursor), BufferPosition::new(0, 6));
+    assert_eq!(
+      motion.target_n(&buffer, cursor, 2),
+      BufferPosition::new(1, 0)
+    );
+    // The lines beyond the last line are ignored.
+    assert_eq!(
+      motion.target_n(&buffer, cursor, 10),
+      BufferPosition::new(2, 2)
+    );
+  }
+
+  #[test]
+  fn range1() {
+    let buffer = make_buffer_from_lines(vec!["foo bar\n", "  baz qux\n"]);
+    let buffer = rlock!(buffer);
+    let range = |key: char, line_idx: usize, char_idx: usize, count: usize| {
+      let motion = Motion::from_key(key).unwrap();
+      let range = motion.range(&buffer, BufferPosition::new(line_idx, char_idx), count);
+      assert_eq!(range.wise(), MotionWise::Charwise);
+      range.char_range().clone()
+    };
+    assert_eq!(range('w', 0, 0, 1), 0..4);
+    // The last word of the line stops at the end of line.
+    assert_eq!(range('w', 0, 4, 1), 4..7);
+    assert_eq!(range('w', 0, 0, 2), 0..7);
+    assert_eq!(range('w', 0, 0, 3), 0..14);
+    // No more words, stops at the end of the last line.
+    assert_eq!(range('w', 1, 6, 1), 14..17);
+    assert_eq!(range('w', 1, 6, 5), 14..17);
+    assert_eq!(range('e', 0, 0, 1), 0..3);
+    assert_eq!(range('e', 0, 0, 2), 0..7);
+    assert_eq!(range('b', 1, 2, 1), 4..10);
+    assert_eq!(range('$', 0, 4, 1), 4..7);
+    assert_eq!(range('$', 0, 4, 2), 4..17);
+    // The count is ignored.
+    assert_eq!(range('0', 1, 6, 3), 8..14);
+    assert_eq!(range('0', 1, 0, 1), 8..8);
+
+    let range = MotionRange::lines(&buffer, 0, 1);
+    assert_eq!(range.wise(), MotionWise::Linewise);
+    assert_eq!(range.char_range(), &(0..8));
+    assert_eq!(MotionRange::lines(&buffer, 1, 3).char_range(), &(8..18));
+  }
+
+  #[test]
+  fn goto_line1() {
+    let buffer = make_buffer_from_lines(vec!["foo\n", "  bar\n", "\tbaz\n", "qux\n"]);
+    let buffer = rlock!(buffer);
+    assert_eq!(
+      goto_line_target(&buffer, None, true),
+      BufferPosition::new(3, 0)
+    );
+    assert_eq!(
+      goto_line_target(&buffer, None, false),
+      BufferPosition::new(0, 0)
+    );
+    assert_eq!(
+      goto_line_target(&buffer, Some(2), true),
+      BufferPosition::new(1, 2)
+    );
+    assert_eq!(
+      goto_line_target(&buffer, Some(10), false),
+      BufferPosition::new(3, 0)
+    );
+    assert_eq!(percent_line_target(&buffer, 50), BufferPosition::new(1, 2));
+    assert_eq!(percent_line_target(&buffer, 60), BufferPosition::new(2, 1));
+    assert_eq!(
+      clamp_position(&buffer, BufferPosition::new(9, 9)),
+      BufferPosition::new(3, 2)
+    );
+  }
+
+  #[test]
+  fn matching_bracket1() {
+    let buffer = make_buffer_from_lines(vec!["fn foo(a: [u8]) {\n", "  (bar)\n", "}\n"]);
+    let buffer = rlock!(buffer);
+    let target = |line_idx: usize, char_idx: usize| {
+      matching_bracket_target(&buffer, BufferPosition::new(line_idx, char_idx))
+        .map(|pos| (pos.line_idx(), pos.char_idx()))
+    };
+    assert_eq!(target(0, 0), Some((0, 14)));
+    assert_eq!(target(0, 14), Some((0, 6)));
+    assert_eq!(target(0, 10), Some((0, 13)));
+    assert_eq!(target(0, 15), Some((2, 0)));
+    assert_eq!(target(2, 0), Some((0, 16)));
+    assert_eq!(target(1, 6), Some((1, 2)));
+    // No bracket after the cursor.
+    assert_eq!(target(1, 7), None);
+  }
+}
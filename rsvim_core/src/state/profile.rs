@@ -0,0 +1,268 @@
+//! Render profiling, i.e. the time spent in each phase of rendering the frames.
+//!
+//! It's enabled by the `--profile` command line option, the timings of the last frames are kept
+//! in a ring buffer, and reported by the `:profile` command and the `Rsvim.profile()` API. When
+//! it's disabled, the event loop only checks a flag for each frame, nothing is measured or
+//! allocated.
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// The max number of the recorded frames, the oldest frames are dropped when it's full.
+pub const PROFILE_MAX_FRAMES: usize = 512;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+/// The phases of rendering a frame, in the order they run.
+pub enum RenderPhase {
+  /// Collect the states shown on the widgets, i.e. the search highlights, the message, the tab
+  /// line and the gutters, and clear the stale regions of the canvas. The viewports are synced
+  /// when handling the events, not measured here.
+  Prepare,
+  /// Draw the widgets on the canvas.
+  Draw,
+  /// Diff the canvas frame with the previous one, i.e. compute the shader commands.
+  Diff,
+  /// Queue the shader commands and flush them to the terminal.
+  Flush,
+}
+
+impl RenderPhase {
+  /// All the phases, in the order they run.
+  pub const ALL: [RenderPhase; 4] = [
+    RenderPhase::Prepare,
+    RenderPhase::Draw,
+    RenderPhase::Diff,
+    RenderPhase::Flush,
+  ];
+
+  pub fn name(&self) -> &'static str {
+    match self {
+      RenderPhase::Prepare => "prepare",
+      RenderPhase::Draw => "draw",
+      RenderPhase::Diff => "diff",
+      RenderPhase::Flush => "flush",
+    }
+  }
+}
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+/// The time spent in each phase of a frame.
+pub struct FrameTiming {
+  phases: [Duration; RenderPhase::ALL.len()],
+}
+
+impl FrameTiming {
+  pub fn get(&self, phase: RenderPhase) -> Duration {
+    self.phases[phase as usize]
+  }
+
+  pub fn set(&mut self, phase: RenderPhase, value: Duration) {
+    self.phases[phase as usize] = value;
+  }
+
+  /// The time spent in all the phases.
+  pub fn total(&self) -> Duration {
+    self.phases.iter().sum()
+  }
+}
+
+#[derive(Debug, Clone)]
+/// Measure the phases of a frame, each [`lap`](FrameTimer::lap) ends a phase and starts the next
+/// one. It does nothing if it's disabled.
+pub struct FrameTimer {
+  started: Option<(Instant, FrameTiming)>,
+}
+
+impl FrameTimer {
+  pub fn start(enabled: bool) -> Self {
+    FrameTimer {
+      started: enabled.then(|| (Instant::now(), FrameTiming::default())),
+    }
+  }
+
+  /// End the phase, its time is from the end of the previous phase (or the start).
+  pub fn lap(&mut self, phase: RenderPhase) {
+    if let Some((last, timing)) = self.started.as_mut() {
+      let now = Instant::now();
+      timing.set(phase, now.duration_since(*last));
+      *last = now;
+    }
+  }
+
+  /// Finish the frame, it returns `None` if it's disabled.
+  pub fn finish(self) -> Option<FrameTiming> {
+    self.started.map(|(_, timing)| timing)
+  }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+/// The statistics of a phase over the recorded frames.
+pub struct PhaseStats {
+  pub min: Duration,
+  pub avg: Duration,
+  pub p99: Duration,
+}
+
+impl PhaseStats {
+  // The durations must not be empty.
+  fn from_durations(mut durations: Vec<Duration>) -> Self {
+    durations.sort_unstable();
+    let n = durations.len();
+    PhaseStats {
+      min: durations[0],
+      avg: durations.iter().sum::<Duration>() / n as u32,
+      p99: durations[(n * 99).div_ceil(100) - 1],
+    }
+  }
+}
+
+#[derive(Debug, Clone)]
+/// The render profile, i.e. the timings of the last frames.
+pub struct RenderProfile {
+  enabled: bool,
+  frames: VecDeque<FrameTiming>,
+  max_frames: usize,
+}
+
+impl RenderProfile {
+  pub fn new(max_frames: usize) -> Self {
+    RenderProfile {
+      enabled: false,
+      frames: VecDeque::new(),
+      max_frames,
+    }
+  }
+
+  pub fn enabled(&self) -> bool {
+    self.enabled
+  }
+
+  pub fn set_enabled(&mut self, value: bool) {
+    self.enabled = value;
+  }
+
+  /// The recorded frames, from the oldest to the newest.
+  pub fn frames(&self) -> &VecDeque<FrameTiming> {
+    &self.frames
+  }
+
+  /// Record the timing of a frame, the oldest frame is dropped if it's full.
+  pub fn record(&mut self, timing: FrameTiming) {
+    if self.frames.len() >= self.max_frames {
+      self.frames.pop_front();
+    }
+    self.frames.push_back(timing);
+  }
+
+  /// The statistics of the phase, it returns `None` if there's no recorded frame.
+  pub fn stats(&self, phase: RenderPhase) -> Option<PhaseStats> {
+    if self.frames.is_empty() {
+      return None;
+    }
+    let durations = self.frames.iter().map(|frame| frame.get(phase)).collect();
+    Some(PhaseStats::from_durations(durations))
+  }
+
+  /// The statistics of the whole frames.
+  pub fn total_stats(&self) -> Option<PhaseStats> {
+    if self.frames.is_empty() {
+      return None;
+    }
+    let durations = self.frames.iter().map(|frame| frame.total()).collect();
+    Some(PhaseStats::from_durations(durations))
+  }
+
+  /// Format the report, i.e. the min/avg/p99 milliseconds of each phase, one line for each phase.
+  pub fn report(&self) -> Vec<String> {
+    let millis = |d: Duration| d.as_secs_f64() * 1000.0;
+    let row = |name: &str, stats: Option<PhaseStats>| match stats {
+      Some(stats) => format!(
+        "{:<10}{:>12.3}{:>12.3}{:>12.3}",
+        name,
+        millis(stats.min),
+        millis(stats.avg),
+        millis(stats.p99)
+      ),
+      None => format!("{:<10}{:>12}{:>12}{:>12}", name, "-", "-", "-"),
+    };
+
+    let mut lines = vec![
+      format!("Render profile of the last {} frames", self.frames.len()),
+      String::new(),
+      format!(
+        "{:<10}{:>12}{:>12}{:>12}",
+        "phase", "min(ms)", "avg(ms)", "p99(ms)"
+      ),
+    ];
+    lines.extend(
+      RenderPhase::ALL
+        .iter()
+        .map(|phase| row(phase.name(), self.stats(*phase))),
+    );
+    lines.push(row("total", self.total_stats()));
+    lines
+  }
+}
+
+impl Default for RenderProfile {
+  fn default() -> Self {
+    RenderProfile::new(PROFILE_MAX_FRAMES)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn frame(millis: [u64; 4]) -> FrameTiming {
+    let mut timing = FrameTiming::default();
+    for (phase, ms) in RenderPhase::ALL.iter().zip(millis) {
+      timing.set(*phase, Duration::from_millis(ms));
+    }
+    timing
+  }
+
+  #[test]
+  fn render_profile1() {
+    let mut profile = RenderProfile::new(3);
+    assert!(!profile.enabled());
+    assert_eq!(profile.stats(RenderPhase::Draw), None);
+    assert!(profile.report()[4].ends_with('-'));
+
+    profile.record(frame([1, 10, 100, 1000]));
+    profile.record(frame([2, 20, 200, 2000]));
+    profile.record(frame([3, 30, 300, 3000]));
+    profile.record(frame([6, 60, 600, 6000]));
+    // The first frame is dropped.
+    assert_eq!(profile.frames().len(), 3);
+    assert_eq!(
+      profile.stats(RenderPhase::Prepare),
+      Some(PhaseStats {
+        min: Duration::from_millis(2),
+        avg: Duration::from_millis(3) + Duration::from_nanos(666_666),
+        p99: Duration::from_millis(6),
+      })
+    );
+    assert_eq!(
+      profile.total_stats().unwrap().min,
+      Duration::from_millis(2222)
+    );
+
+    let report = profile.report();
+    assert_eq!(report[0], "Render profile of the last 3 frames");
+    assert_eq!(report[3], "prepare          2.000       3.667       6.000");
+    assert_eq!(report[7], "total         2222.000    4073.667    6666.000");
+  }
+
+  #[test]
+  fn frame_timer1() {
+    assert_eq!(FrameTimer::start(false).finish(), None);
+
+    let mut timer = FrameTimer::start(true);
+    for phase in RenderPhase::ALL {
+      timer.lap(phase);
+    }
+    let timing = timer.finish().unwrap();
+    assert!(timing.total() >= timing.get(RenderPhase::Flush));
+  }
+}
@@ -0,0 +1,65 @@
+//! The quit request, i.e. `:quit`, `:qall` and `Esc` in normal mode.
+
+use crate::buf::BuffersManager;
+use crate::res::AnyResult;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+/// The request to quit the editor. It's decided by the event loop, since the editor cannot quit
+/// with the modified buffers unless it's forced, i.e. `:quit!`.
+/// See: <https://vimhelp.org/editing.txt.html#%3Aquit>.
+pub struct QuitRequest {
+  /// Quit even if there are modified buffers, the changes are lost.
+  pub force: bool,
+}
+
+impl QuitRequest {
+  pub fn new(force: bool) -> Self {
+    QuitRequest { force }
+  }
+
+  /// Check whether the editor can quit.
+  ///
+  /// # Errors
+  ///
+  /// If any buffer is modified, and the request is not forced.
+  pub fn accept(&self, buffers: &BuffersManager) -> AnyResult<()> {
+    if !self.force && buffers.any_modified() {
+      anyhow::bail!("E37: No write since last change (add ! to override)");
+    }
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn accept1() {
+    let tmpdir = assert_fs::TempDir::new().unwrap();
+    let filename = tmpdir.path().join("accept1.txt");
+    let mut buffers = BuffersManager::new();
+    let buf_id = buffers.new_file_buffer(&filename).unwrap();
+    assert!(QuitRequest::new(false).accept(&buffers).is_ok());
+
+    // The modified buffer prevents quitting, unless it's forced.
+    buffers
+      .get(&buf_id)
+      .unwrap()
+      .write()
+      .insert(0, "Hello")
+      .unwrap();
+    assert_eq!(
+      QuitRequest::new(false)
+        .accept(&buffers)
+        .unwrap_err()
+        .to_string(),
+      "E37: No write since last change (add ! to override)"
+    );
+    assert!(QuitRequest::new(true).accept(&buffers).is_ok());
+
+    // The written buffer is not modified.
+    buffers.write_buffer(&buf_id, false).unwrap();
+    assert!(QuitRequest::new(false).accept(&buffers).is_ok());
+  }
+}
@@ -0,0 +1,264 @@
+//! Macro recording and playback, i.e. `q{reg}` and `@{reg}`.
+//!
+//! The recorded keys are stored in the register as text, the same with Vim: the printable keys
+//! are the chars, `Ctrl-{letter}`, `Esc`, `Enter`, `Tab` and `Backspace` are the control chars,
+//! and the other special keys are the `0x80` byte followed by the two-char termcap name.
+//!
+//! See: <https://vimhelp.org/repeat.txt.html#recording>.
+
+use crossterm::event::{KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use std::collections::VecDeque;
+
+/// The max nested depth of the register executions, i.e. a recursive macro stops at it.
+pub const MAX_EXECUTE_DEPTH: usize = 100;
+
+// The leading char of the special keys, i.e. the `K_SPECIAL` in Vim.
+const K_SPECIAL: char = '\u{80}';
+
+// The special keys and their termcap names.
+const SPECIAL_KEYS: [(KeyCode, &str); 10] = [
+  (KeyCode::Up, "ku"),
+  (KeyCode::Down, "kd"),
+  (KeyCode::Left, "kl"),
+  (KeyCode::Right, "kr"),
+  (KeyCode::Delete, "kD"),
+  (KeyCode::Insert, "kI"),
+  (KeyCode::Home, "kh"),
+  (KeyCode::End, "@7"),
+  (KeyCode::PageUp, "kP"),
+  (KeyCode::PageDown, "kN"),
+];
+
+#[derive(Debug, Clone, Default)]
+/// The macro recorder, it records the typed keys into a register, and tracks the register
+/// executions.
+///
+/// The keys of a register execution are queued in the typeahead, the event loop replays them one
+/// by one like the typed keys, thus the intermediate states can be rendered, see 'lazyredraw'.
+pub struct MacroRecorder {
+  // The register being recorded into, and the recorded keys.
+  recording: Option<(char, Vec<KeyEvent>)>,
+  // The last executed register, i.e. `@@`.
+  last_executed: Option<char>,
+  // The nested depth of the register executions.
+  depth: usize,
+  // The queued keys to replay, and the nested depth they're replayed at.
+  typeahead: VecDeque<(KeyEvent, usize)>,
+}
+
+impl MacroRecorder {
+  pub fn new() -> Self {
+    MacroRecorder::default()
+  }
+
+  /// Start recording into the register `name`.
+  pub fn start(&mut self, name: char) {
+    self.recording = Some((name, vec![]));
+  }
+
+  /// The register being recorded into, or `None` if it's not recording.
+  pub fn recording(&self) -> Option<char> {
+    self.recording.as_ref().map(|(name, _)| *name)
+  }
+
+  /// Record a typed key, the keys replayed by the register executions are not recorded.
+  pub fn record_key(&mut self, key: KeyEvent) {
+    if self.depth > 0 || key.kind != KeyEventKind::Press {
+      return;
+    }
+    if let Some((_, keys)) = self.recording.as_mut() {
+      keys.push(key);
+    }
+  }
+
+  /// Stop recording, returns the register name and the recorded keys as text. The last key is
+  /// dropped, i.e. the `q` that stops the recording.
+  pub fn finish(&mut self) -> Option<(char, String)> {
+    let (name, mut keys) = self.recording.take()?;
+    keys.pop();
+    Some((name, keys_to_text(&keys)))
+  }
+
+  /// The last executed register.
+  pub fn last_executed(&self) -> Option<char> {
+    self.last_executed
+  }
+
+  pub fn set_last_executed(&mut self, name: char) {
+    self.last_executed = Some(name);
+  }
+
+  /// The nested depth of the register executions.
+  pub fn depth(&self) -> usize {
+    self.depth
+  }
+
+  pub fn set_depth(&mut self, depth: usize) {
+    self.depth = depth;
+  }
+
+  /// Queue the keys to replay at the nested `depth`. They go before the queued keys, i.e. the
+  /// register executed by a replayed key runs before the rest keys.
+  pub fn feed(&mut self, keys: &[KeyEvent], depth: usize) {
+    for key in keys.iter().rev() {
+      self.typeahead.push_front((*key, depth));
+    }
+  }
+
+  /// Take the next key to replay, the depth follows it. The depth is reset once all the queued
+  /// keys are replayed.
+  pub fn next_key(&mut self) -> Option<KeyEvent> {
+    match self.typeahead.pop_front() {
+      Some((key, depth)) => {
+        self.depth = depth;
+        Some(key)
+      }
+      None => {
+        self.depth = 0;
+        None
+      }
+    }
+  }
+
+  /// Drop the queued keys, i.e. the execution is aborted by an error.
+  pub fn clear_typeahead(&mut self) {
+    self.typeahead.clear();
+  }
+
+  /// Whether it's replaying the keys, i.e. there're queued keys or the last one is being handled.
+  pub fn is_replaying(&self) -> bool {
+    self.depth > 0 || !self.typeahead.is_empty()
+  }
+}
+
+/// Convert the keys to the register text, the keys that cannot be represented are dropped.
+pub fn keys_to_text(keys: &[KeyEvent]) -> String {
+  let mut text = String::new();
+  for key in keys.iter() {
+    match key.code {
+      KeyCode::Char(c) if key.modifiers.contains(KeyModifiers::CONTROL) => {
+        if c.is_ascii_alphabetic() {
+          text.push(((c.to_ascii_lowercase() as u8) & 0x1f) as char);
+        }
+      }
+      KeyCode::Char(c) => text.push(c),
+      KeyCode::Esc => text.push('\x1b'),
+      KeyCode::Enter => text.push('\r'),
+      KeyCode::Tab => text.push('\t'),
+      KeyCode::Backspace => text.push('\x7f'),
+      code => {
+        if let Some((_, name)) = SPECIAL_KEYS.iter().find(|(c, _)| *c == code) {
+          text.push(K_SPECIAL);
+          text.push_str(name);
+        }
+      }
+    }
+  }
+  text
+}
+
+/// Convert the register text to the keys, i.e. the reverse of [`keys_to_text`]. The line break
+/// is `Enter`, thus a linewise register can be executed as well.
+pub fn text_to_keys(text: &str) -> Vec<KeyEvent> {
+  let mut keys = vec![];
+  let mut chars = text.chars();
+  while let Some(c) = chars.next() {
+    let (code, modifiers) = match c {
+      '\x1b' => (KeyCode::Esc, KeyModifiers::NONE),
+      '\r' | '\n' => (KeyCode::Enter, KeyModifiers::NONE),
+      '\t' => (KeyCode::Tab, KeyModifiers::NONE),
+      '\x7f' => (KeyCode::Backspace, KeyModifiers::NONE),
+      '\x01'..='\x1a' => (
+        KeyCode::Char((c as u8 - 1 + b'a') as char),
+        KeyModifiers::CONTROL,
+      ),
+      K_SPECIAL => {
+        let name: String = chars.by_ref().take(2).collect();
+        match SPECIAL_KEYS.iter().find(|(_, n)| *n == name) {
+          Some((code, _)) => (*code, KeyModifiers::NONE),
+          None => continue,
+        }
+      }
+      c => (KeyCode::Char(c), KeyModifiers::NONE),
+    };
+    keys.push(KeyEvent::new(code, modifiers));
+  }
+  keys
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn key(code: KeyCode) -> KeyEvent {
+    KeyEvent::new(code, KeyModifiers::NONE)
+  }
+
+  #[test]
+  fn keys_text1() {
+    let keys = vec![
+      key(KeyCode::Char('i')),
+      key(KeyCode::Char('x')),
+      key(KeyCode::Enter),
+      key(KeyCode::Backspace),
+      key(KeyCode::Esc),
+      KeyEvent::new(KeyCode::Char('o'), KeyModifiers::CONTROL),
+      key(KeyCode::Up),
+      key(KeyCode::Tab),
+    ];
+    let text = keys_to_text(&keys);
+    assert_eq!(text, "ix\r\x7f\x1b\x0f\u{80}ku\t");
+    assert_eq!(text_to_keys(&text), keys);
+
+    // The line break of a linewise register.
+    assert_eq!(
+      text_to_keys("j\n"),
+      vec![key(KeyCode::Char('j')), key(KeyCode::Enter)]
+    );
+  }
+
+  #[test]
+  fn recorder1() {
+    let mut recorder = MacroRecorder::new();
+    recorder.record_key(key(KeyCode::Char('j')));
+    assert!(recorder.finish().is_none());
+
+    recorder.start('a');
+    assert_eq!(recorder.recording(), Some('a'));
+    recorder.record_key(key(KeyCode::Char('j')));
+    // The replayed keys are not recorded.
+    recorder.set_depth(1);
+    recorder.record_key(key(KeyCode::Char('k')));
+    recorder.set_depth(0);
+    recorder.record_key(key(KeyCode::Char('q')));
+    assert_eq!(recorder.finish(), Some(('a', "j".to_string())));
+    assert_eq!(recorder.recording(), None);
+  }
+
+  #[test]
+  fn typeahead1() {
+    let mut recorder = MacroRecorder::new();
+    assert!(!recorder.is_replaying());
+    recorder.feed(&text_to_keys("ab"), 1);
+    assert!(recorder.is_replaying());
+    assert_eq!(recorder.next_key(), Some(key(KeyCode::Char('a'))));
+
+    // The nested keys go first.
+    recorder.feed(&text_to_keys("xy"), 2);
+    assert_eq!(recorder.next_key(), Some(key(KeyCode::Char('x'))));
+    assert_eq!(recorder.depth(), 2);
+    assert_eq!(recorder.next_key(), Some(key(KeyCode::Char('y'))));
+    assert_eq!(recorder.next_key(), Some(key(KeyCode::Char('b'))));
+    assert_eq!(recorder.depth(), 1);
+    // The last key is still being handled.
+    assert!(recorder.is_replaying());
+    assert_eq!(recorder.next_key(), None);
+    assert_eq!(recorder.depth(), 0);
+    assert!(!recorder.is_replaying());
+
+    recorder.feed(&text_to_keys("ab"), 1);
+    recorder.clear_typeahead();
+    assert_eq!(recorder.next_key(), None);
+    assert!(!recorder.is_replaying());
+  }
+}
@@ -0,0 +1,370 @@
+//! Registers for yank, delete and put.
+//!
+//! See: <https://vimhelp.org/change.txt.html#registers>.
+
+use crate::state::clipboard::ClipboardProviderArc;
+
+use std::collections::BTreeMap;
+
+/// The unnamed register `"`.
+pub const UNNAMED_REGISTER: char = '"';
+
+/// The yank register `0`.
+pub const YANK_REGISTER: char = '0';
+
+/// The clipboard register `+`.
+pub const CLIPBOARD_REGISTER: char = '+';
+
+/// The selection register `*`, i.e. the primary selection on X11.
+pub const SELECTION_REGISTER: char = '*';
+
+/// The warning when the register `+`/`*` is used without the system clipboard.
+pub const CLIPBOARD_WARNING: &str =
+  "W23: Clipboard register not available, using the internal register";
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+/// The register content kinds.
+pub enum RegisterKind {
+  /// Charwise content is put inline, after (or before) the cursor.
+  Charwise,
+  /// Linewise content is always whole lines (end with line break), it is put as new lines below
+  /// (or above) the cursor line.
+  Linewise,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// The register content.
+pub struct Register {
+  kind: RegisterKind,
+  text: String,
+}
+
+impl Register {
+  /// Make a register content, for linewise content, the line break is appended if the `text`
+  /// doesn't end with it.
+  pub fn new(kind: RegisterKind, text: String) -> Self {
+    let text = match kind {
+      RegisterKind::Linewise if !text.ends_with('\n') => text + "\n",
+      _ => text,
+    };
+    Register { kind, text }
+  }
+
+  pub fn kind(&self) -> RegisterKind {
+    self.kind
+  }
+
+  pub fn text(&self) -> &str {
+    &self.text
+  }
+
+  // Append `other` to this register, the content becomes linewise if any one of them is linewise.
+  fn append(&mut self, other: Register) {
+    let kind = match (self.kind, other.kind) {
+      (RegisterKind::Charwise, RegisterKind::Charwise) => RegisterKind::Charwise,
+      _ => RegisterKind::Linewise,
+    };
+    let mut text = std::mem::take(&mut self.text);
+    if kind == RegisterKind::Linewise && !text.is_empty() && !text.ends_with('\n') {
+      text.push('\n');
+    }
+    text.push_str(&other.text);
+    *self = Register::new(kind, text);
+  }
+
+  /// Make a register content from the text read from the clipboard, it's linewise if the text
+  /// ends with line break.
+  pub fn from_clipboard(text: String) -> Self {
+    let kind = if text.ends_with('\n') {
+      RegisterKind::Linewise
+    } else {
+      RegisterKind::Charwise
+    };
+    Register::new(kind, text)
+  }
+}
+
+#[derive(Debug, Clone, Default)]
+/// The registers store, it contains:
+///
+/// 1. The unnamed register `"`, the last yanked or deleted text.
+/// 2. The yank register `0`, the last yanked text.
+/// 3. The named registers `a`-`z`, use the uppercase `A`-`Z` to append to them.
+/// 4. The clipboard registers `+` and `*`, they're written to the system clipboard by the event
+///    loop, see [`clipboard`](crate::state::clipboard). Their internal copies are used if there's
+///    no clipboard provider.
+pub struct Registers {
+  unnamed: Option<Register>,
+  yank: Option<Register>,
+  named: BTreeMap<char, Register>,
+  clipboard: Option<ClipboardProviderArc>,
+  // The internal copies of the registers `+` and `*`.
+  selections: BTreeMap<char, Register>,
+  // The registers `+` and `*` waiting to be written to the clipboard.
+  pending_clipboard_writes: Vec<(char, Register)>,
+  // Whether the clipboard warning is shown, and whether it's waiting to be shown.
+  clipboard_warned: bool,
+  clipboard_warning: bool,
+}
+
+impl Registers {
+  pub fn new() -> Self {
+    Registers::default()
+  }
+
+  /// Whether `name` is a valid register name.
+  pub fn is_valid_name(name: char) -> bool {
+    name == UNNAMED_REGISTER
+      || name == YANK_REGISTER
+      || name.is_ascii_alphabetic()
+      || Registers::is_clipboard_name(name)
+  }
+
+  /// Whether `name` is the clipboard register `+` or `*`.
+  pub fn is_clipboard_name(name: char) -> bool {
+    name == CLIPBOARD_REGISTER || name == SELECTION_REGISTER
+  }
+
+  /// Get register content by its `name`, the uppercase name is the same with the lowercase one.
+  pub fn get(&self, name: char) -> Option<&Register> {
+    match name {
+      UNNAMED_REGISTER => self.unnamed.as_ref(),
+      YANK_REGISTER => self.yank.as_ref(),
+      CLIPBOARD_REGISTER | SELECTION_REGISTER => self.selections.get(&name),
+      c if c.is_ascii_alphabetic() => self.named.get(&c.to_ascii_lowercase()),
+      _ => None,
+    }
+  }
+
+  /// Yank into the register `name`, or the yank register `0` if `name` is `None`. The unnamed
+  /// register is always updated.
+  ///
+  /// # Returns
+  ///
+  /// It returns `false` if the `name` is invalid.
+  pub fn yank(&mut self, name: Option<char>, register: Register) -> bool {
+    match name {
+      None | Some(UNNAMED_REGISTER) | Some(YANK_REGISTER) => {
+        self.yank = Some(register.clone());
+        self.unnamed = Some(register);
+        true
+      }
+      Some(name) if Registers::is_clipboard_name(name) => {
+        self.set_selection(name, register);
+        true
+      }
+      Some(name) => self.set_named(name, register),
+    }
+  }
+
+  /// Delete into the register `name`, the unnamed register is always updated. Unlike
+  /// [`yank`](Registers::yank), the yank register `0` is not changed.
+  ///
+  /// # Returns
+  ///
+  /// It returns `false` if the `name` is invalid.
+  pub fn delete(&mut self, name: Option<char>, register: Register) -> bool {
+    match name {
+      None | Some(UNNAMED_REGISTER) => {
+        self.unnamed = Some(register);
+        true
+      }
+      Some(YANK_REGISTER) => false,
+      Some(name) if Registers::is_clipboard_name(name) => {
+        self.set_selection(name, register);
+        true
+      }
+      Some(name) => self.set_named(name, register),
+    }
+  }
+
+  // Set the named register, the uppercase name appends to the register. The unnamed register
+  // points to the updated content.
+  fn set_named(&mut self, name: char, register: Register) -> bool {
+    if !name.is_ascii_alphabetic() {
+      return false;
+    }
+    let key = name.to_ascii_lowercase();
+    match self.named.get_mut(&key) {
+      Some(existed) if name.is_ascii_uppercase() => existed.append(register),
+      _ => {
+        self.named.insert(key, register);
+      }
+    }
+    self.unnamed = self.named.get(&key).cloned();
+    true
+  }
+
+  // Set the register `+`/`*`, its internal copy is always updated. It's queued to be written to
+  // the clipboard if there's a provider, otherwise the clipboard warning is shown.
+  fn set_selection(&mut self, name: char, register: Register) {
+    match self.clipboard {
+      Some(_) => self.pending_clipboard_writes.push((name, register.clone())),
+      None => self.clipboard_unavailable(),
+    }
+    self.selections.insert(name, register.clone());
+    self.unnamed = Some(register);
+  }
+
+  /// Get the clipboard provider, see [`detect_provider`](crate::state::clipboard::detect_provider).
+  pub fn clipboard(&self) -> Option<&ClipboardProviderArc> {
+    self.clipboard.as_ref()
+  }
+
+  pub fn set_clipboard(&mut self, clipboard: Option<ClipboardProviderArc>) {
+    self.clipboard = clipboard;
+  }
+
+  /// Whether the register `+`/`*` is put from the clipboard, instead of its internal copy.
+  pub fn clipboard_readable(&self) -> bool {
+    self.clipboard.as_ref().is_some_and(|c| c.readable())
+  }
+
+  /// Update the internal copy of the register `+`/`*` with the text read from the clipboard.
+  pub fn set_clipboard_content(&mut self, name: char, text: String) {
+    if Registers::is_clipboard_name(name) {
+      self.selections.insert(name, Register::from_clipboard(text));
+    }
+  }
+
+  /// Take the registers `+`/`*` waiting to be written to the clipboard.
+  pub fn take_clipboard_writes(&mut self) -> Vec<(char, Register)> {
+    std::mem::take(&mut self.pending_clipboard_writes)
+  }
+
+  /// The register `+`/`*` is used without clipboard provider, the warning is only shown once.
+  pub fn clipboard_unavailable(&mut self) {
+    if !self.clipboard_warned {
+      self.clipboard_warned = true;
+      self.clipboard_warning = true;
+    }
+  }
+
+  /// Take the clipboard warning waiting to be shown, see [`CLIPBOARD_WARNING`].
+  pub fn take_clipboard_warning(&mut self) -> Option<&'static str> {
+    std::mem::take(&mut self.clipboard_warning).then_some(CLIPBOARD_WARNING)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use crate::state::clipboard::ClipboardProvider;
+  use crate::test::clipboard::MockProvider;
+
+  use std::sync::Arc;
+  use std::time::Duration;
+
+  #[test]
+  fn register1() {
+    let r = Register::new(RegisterKind::Linewise, "hello".to_string());
+    assert_eq!(r.text(), "hello\n");
+    let r = Register::new(RegisterKind::Charwise, "hello".to_string());
+    assert_eq!(r.text(), "hello");
+  }
+
+  #[test]
+  fn yank_delete1() {
+    let mut regs = Registers::new();
+    assert!(regs.get(UNNAMED_REGISTER).is_none());
+
+    let yanked = Register::new(RegisterKind::Charwise, "abc".to_string());
+    assert!(regs.yank(None, yanked.clone()));
+    assert_eq!(regs.get(UNNAMED_REGISTER), Some(&yanked));
+    assert_eq!(regs.get(YANK_REGISTER), Some(&yanked));
+
+    // Delete doesn't change the yank register.
+    let deleted = Register::new(RegisterKind::Linewise, "line\n".to_string());
+    assert!(regs.delete(None, deleted.clone()));
+    assert_eq!(regs.get(UNNAMED_REGISTER), Some(&deleted));
+    assert_eq!(regs.get(YANK_REGISTER), Some(&yanked));
+
+    assert!(!regs.yank(Some('#'), yanked.clone()));
+    assert!(!regs.delete(Some(YANK_REGISTER), deleted));
+  }
+
+  #[test]
+  fn named1() {
+    let mut regs = Registers::new();
+    assert!(regs.yank(
+      Some('a'),
+      Register::new(RegisterKind::Charwise, "foo".to_string())
+    ));
+    assert_eq!(regs.get('a').unwrap().text(), "foo");
+    assert_eq!(regs.get('A').unwrap().text(), "foo");
+    assert_eq!(regs.get(UNNAMED_REGISTER).unwrap().text(), "foo");
+    assert!(regs.get(YANK_REGISTER).is_none());
+
+    // Uppercase appends.
+    assert!(regs.yank(
+      Some('A'),
+      Register::new(RegisterKind::Charwise, "bar".to_string())
+    ));
+    assert_eq!(regs.get('a').unwrap().text(), "foobar");
+    assert!(regs.yank(
+      Some('A'),
+      Register::new(RegisterKind::Linewise, "baz".to_string())
+    ));
+    let a = regs.get('a').unwrap();
+    assert_eq!(a.kind(), RegisterKind::Linewise);
+    assert_eq!(a.text(), "foobar\nbaz\n");
+    assert_eq!(regs.get(UNNAMED_REGISTER), Some(a));
+  }
+
+  #[test]
+  fn clipboard1() {
+    let mut regs = Registers::new();
+    let provider = Arc::new(MockProvider::default());
+    regs.set_clipboard(Some(provider.clone()));
+    assert!(regs.clipboard_readable());
+
+    // The yanked text is queued to be written to the clipboard.
+    let yanked = Register::new(RegisterKind::Linewise, "foo".to_string());
+    assert!(regs.yank(Some(CLIPBOARD_REGISTER), yanked.clone()));
+    assert_eq!(regs.get(CLIPBOARD_REGISTER), Some(&yanked));
+    assert_eq!(regs.get(UNNAMED_REGISTER), Some(&yanked));
+    assert!(regs.get(YANK_REGISTER).is_none());
+    let writes = regs.take_clipboard_writes();
+    assert_eq!(writes, vec![(CLIPBOARD_REGISTER, yanked)]);
+    assert!(regs.take_clipboard_writes().is_empty());
+    assert_eq!(regs.take_clipboard_warning(), None);
+
+    // Round trip through the provider, the kind follows the trailing line break.
+    let timeout = Duration::from_secs(5);
+    for (name, register) in writes {
+      provider.write(name, register.text(), timeout).unwrap();
+    }
+    regs.set_clipboard_content(
+      SELECTION_REGISTER,
+      provider.read(SELECTION_REGISTER, timeout).unwrap(),
+    );
+    let selection = regs.get(SELECTION_REGISTER).unwrap();
+    assert_eq!(selection.kind(), RegisterKind::Linewise);
+    assert_eq!(selection.text(), "foo\n");
+    regs.set_clipboard_content(CLIPBOARD_REGISTER, "bar".to_string());
+    assert_eq!(
+      regs.get(CLIPBOARD_REGISTER).unwrap().kind(),
+      RegisterKind::Charwise
+    );
+  }
+
+  #[test]
+  fn clipboard_fallback1() {
+    let mut regs = Registers::new();
+    assert!(!regs.clipboard_readable());
+
+    // Without provider, the internal register is used and the warning is shown once.
+    let deleted = Register::new(RegisterKind::Charwise, "foo".to_string());
+    assert!(regs.delete(Some(SELECTION_REGISTER), deleted.clone()));
+    assert_eq!(regs.get(SELECTION_REGISTER), Some(&deleted));
+    assert!(regs.get(CLIPBOARD_REGISTER).is_none());
+    assert!(regs.take_clipboard_writes().is_empty());
+    assert_eq!(regs.take_clipboard_warning(), Some(CLIPBOARD_WARNING));
+    assert_eq!(regs.take_clipboard_warning(), None);
+
+    assert!(regs.yank(Some(CLIPBOARD_REGISTER), deleted.clone()));
+    assert_eq!(regs.get(CLIPBOARD_REGISTER), Some(&deleted));
+    assert_eq!(regs.take_clipboard_warning(), None);
+  }
+}
@@ -0,0 +1,297 @@
+//! The search pattern typed in command-line mode, i.e. `/{pattern}`.
+//!
+//! See: <https://vimhelp.org/pattern.txt.html#search-commands>.
+
+use crate::buf::{Buffer, HighlightSpan};
+use crate::res::AnyResult;
+use crate::state::selection::BufferPosition;
+
+use regex::{Regex, RegexBuilder};
+use std::ops::Range;
+use std::time::Duration;
+
+/// The timeout of the incremental search while typing the pattern, same with the default
+/// 'redrawtime' of Vim.
+/// See: <https://vimhelp.org/options.txt.html#%27redrawtime%27>.
+pub const INC_SEARCH_TIMEOUT: Duration = Duration::from_millis(2000);
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+/// The direction of the search.
+pub enum SearchDirection {
+  #[default]
+  Forward,
+  Backward,
+}
+
+impl SearchDirection {
+  /// The opposite direction, i.e. `N`.
+  pub fn reverse(&self) -> Self {
+    match self {
+      SearchDirection::Forward => SearchDirection::Backward,
+      SearchDirection::Backward => SearchDirection::Forward,
+    }
+  }
+}
+
+#[derive(Debug, Clone, Default)]
+/// The search state.
+pub struct Search {
+  // The last committed search pattern.
+  pattern: Option<String>,
+
+  // The direction of the last committed search, `n` repeats it and `N` reverses it.
+  direction: SearchDirection,
+
+  // The cursor position and the viewport start line when the search starts, they're restored if
+  // the search is cancelled, or the typed pattern doesn't match.
+  origin: Option<(BufferPosition, usize)>,
+
+  // The 'hlsearch' pattern, its matches are highlighted until `:nohlsearch`.
+  hl_search: Option<Regex>,
+}
+
+impl Search {
+  pub fn new() -> Self {
+    Search::default()
+  }
+
+  /// Get the last committed search pattern.
+  pub fn pattern(&self) -> &Option<String> {
+    &self.pattern
+  }
+
+  pub fn set_pattern(&mut self, pattern: Option<String>) {
+    self.pattern = pattern;
+  }
+
+  /// Get the direction of the last committed search.
+  pub fn direction(&self) -> SearchDirection {
+    self.direction
+  }
+
+  pub fn set_direction(&mut self, direction: SearchDirection) {
+    self.direction = direction;
+  }
+
+  /// Get the cursor position and the viewport start line when the search starts.
+  pub fn origin(&self) -> Option<(BufferPosition, usize)> {
+    self.origin
+  }
+
+  pub fn set_origin(&mut self, origin: Option<(BufferPosition, usize)>) {
+    self.origin = origin;
+  }
+
+  /// Get the 'hlsearch' pattern, i.e. the last committed pattern if its matches are highlighted.
+  pub fn hl_search(&self) -> &Option<Regex> {
+    &self.hl_search
+  }
+
+  pub fn set_hl_search(&mut self, pattern: Option<Regex>) {
+    self.hl_search = pattern;
+  }
+}
+
+/// Compile the search pattern to regex.
+///
+/// The case is ignored if `ignore_case` is `true`, unless `smart_case` is also `true` and the
+/// pattern contains uppercase chars. A `\c` in the pattern always ignores case, and a `\C` always
+/// matches case, they're removed from the pattern.
+/// See: <https://vimhelp.org/pattern.txt.html#%2F%5Cc>.
+///
+/// It returns `None` if the pattern is empty or invalid, i.e. the pattern is still being typed.
+pub fn compile_pattern(pattern: &str, ignore_case: bool, smart_case: bool) -> Option<Regex> {
+  try_compile_pattern(pattern, ignore_case, smart_case)
+    .ok()
+    .flatten()
+}
+
+/// Same with [`compile_pattern`], but it returns the error if the pattern is invalid, and `None`
+/// if the pattern is empty.
+pub fn try_compile_pattern(
+  pattern: &str,
+  ignore_case: bool,
+  smart_case: bool,
+) -> AnyResult<Option<Regex>> {
+  let mut stripped = String::with_capacity(pattern.len());
+  let mut has_upper = false;
+  let (mut lower_flag, mut upper_flag) = (false, false);
+  let mut chars = pattern.chars();
+  while let Some(c) = chars.next() {
+    match c {
+      '\\' => match chars.next() {
+        Some('c') => lower_flag = true,
+        Some('C') => upper_flag = true,
+        // The escaped chars don't count for 'smartcase', i.e. `\S`.
+        Some(escaped) => {
+          stripped.push(c);
+          stripped.push(escaped);
+        }
+        None => stripped.push(c),
+      },
+      _ => {
+        has_upper = has_upper || c.is_uppercase();
+        stripped.push(c);
+      }
+    }
+  }
+  if stripped.is_empty() {
+    return Ok(None);
+  }
+
+  // Same with Vim, the `\c` wins if both `\c` and `\C` are present.
+  let case_insensitive = if lower_flag {
+    true
+  } else if upper_flag {
+    false
+  } else {
+    ignore_case && !(smart_case && has_upper)
+  };
+  match RegexBuilder::new(&stripped)
+    .case_insensitive(case_insensitive)
+    .build()
+  {
+    Ok(regex) => Ok(Some(regex)),
+    Err(_) => anyhow::bail!("E383: Invalid search string: {}", pattern),
+  }
+}
+
+/// Set the search highlight spans of the lines in range, i.e. the visible lines of a window. The
+/// existing search spans of the buffer are cleared.
+///
+/// All the matches are highlighted with [`HighlightSpan::search`] if `hl_search` is `true`, and
+/// the `current` match (i.e. the char range based on the whole buffer) is highlighted with
+/// [`HighlightSpan::inc_search`] instead.
+pub fn set_search_highlights(
+  buffer: &mut Buffer,
+  line_range: Range<usize>,
+  pattern: &Regex,
+  hl_search: bool,
+  current: Option<Range<usize>>,
+) {
+  buffer.highlights_mut().clear_search();
+  for line_idx in line_range {
+    let line_slice = match buffer.get_line(line_idx) {
+      Some(line_slice) => line_slice,
+      None => break,
+    };
+    let line_start = buffer.line_to_char(line_idx);
+    let text = line_slice.to_string();
+    let spans = pattern
+      .find_iter(&text)
+      .filter(|m| !m.is_empty())
+      .filter_map(|m| {
+        let start_char = line_slice.byte_to_char(m.start());
+        let end_char = line_slice.byte_to_char(m.end());
+        if current == Some(line_start + start_char..line_start + end_char) {
+          Some(HighlightSpan::inc_search(start_char, end_char))
+        } else if hl_search {
+          Some(HighlightSpan::search(start_char, end_char))
+        } else {
+          None
+        }
+      })
+      .collect::<Vec<_>>();
+    buffer.highlights_mut().set_search(line_idx, spans);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use crate::envar;
+  use crate::test::buf::make_buffer_from_lines;
+  use crate::wlock;
+
+  #[test]
+  fn compile_pattern1() {
+    assert!(compile_pattern("", false, false).is_none());
+    assert!(compile_pattern("foo(", false, false).is_none());
+    assert!(compile_pattern("\\c", false, false).is_none());
+    assert!(compile_pattern("foo", false, false)
+      .unwrap()
+      .is_match("a foo b"));
+    assert!(compile_pattern("fo+\\(", false, false)
+      .unwrap()
+      .is_match("fooo("));
+  }
+
+  #[test]
+  fn try_compile_pattern1() {
+    assert!(try_compile_pattern("", false, false).unwrap().is_none());
+    assert!(try_compile_pattern("foo", false, false).unwrap().is_some());
+    assert_eq!(
+      try_compile_pattern("foo(", false, false)
+        .unwrap_err()
+        .to_string(),
+      "E383: Invalid search string: foo("
+    );
+  }
+
+  #[test]
+  fn set_search_highlights1() {
+    let buffer = make_buffer_from_lines(vec!["foo bar foo\n", "bar\n", "foo\n"]);
+    let mut buffer = wlock!(buffer);
+    let pattern = Regex::new("foo").unwrap();
+
+    set_search_highlights(&mut buffer, 0..2, &pattern, true, Some(8..11));
+    assert_eq!(
+      buffer.highlights().search(0),
+      Some(&vec![
+        HighlightSpan::search(0, 3),
+        HighlightSpan::inc_search(8, 11)
+      ])
+    );
+    assert!(buffer.highlights().search(1).is_none());
+    // Out of the line range.
+    assert!(buffer.highlights().search(2).is_none());
+
+    // Only the current match without 'hlsearch'.
+    set_search_highlights(&mut buffer, 0..3, &pattern, false, Some(16..19));
+    assert!(buffer.highlights().search(0).is_none());
+    assert_eq!(
+      buffer.highlights().search(2),
+      Some(&vec![HighlightSpan::inc_search(0, 3)])
+    );
+  }
+
+  #[test]
+  fn ignore_case1() {
+    assert!(!compile_pattern("foo", false, false)
+      .unwrap()
+      .is_match("Foo"));
+    assert!(compile_pattern("foo", true, false).unwrap().is_match("Foo"));
+    assert!(compile_pattern("Foo", true, false).unwrap().is_match("FOO"));
+
+    // `\c`/`\C` overrides the options.
+    assert!(compile_pattern("foo\\c", false, false)
+      .unwrap()
+      .is_match("FOO"));
+    assert!(!compile_pattern("\\Cfoo", true, false)
+      .unwrap()
+      .is_match("FOO"));
+    assert!(compile_pattern("\\C\\cfoo", false, false)
+      .unwrap()
+      .is_match("FOO"));
+  }
+
+  #[test]
+  fn smart_case1() {
+    assert!(compile_pattern("foo", true, true).unwrap().is_match("FOO"));
+    // Case-sensitive if the pattern has uppercase chars.
+    assert!(!compile_pattern("Foo", true, true).unwrap().is_match("FOO"));
+    assert!(compile_pattern("Foo", true, true)
+      .unwrap()
+      .is_match("a Foo"));
+    // The escaped chars are not uppercase chars.
+    assert!(compile_pattern("foo\\S", true, true)
+      .unwrap()
+      .is_match("FOO!"));
+    // Without 'ignorecase', 'smartcase' has no effect.
+    assert!(!compile_pattern("foo", false, true).unwrap().is_match("FOO"));
+    assert!(compile_pattern("Foo\\c", true, true)
+      .unwrap()
+      .is_match("FOO"));
+  }
+}
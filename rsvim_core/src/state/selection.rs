@@ -0,0 +1,399 @@
+//! Visual mode selection.
+
+use crate::buf::Buffer;
+use crate::ui::widget::window::Viewport;
+
+use std::cmp::{max, min};
+use std::ops::Range;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+/// The visual mode kinds.
+pub enum VisualKind {
+  /// Charwise visual, i.e. `v`.
+  Charwise,
+  /// Linewise visual, i.e. `V`.
+  Linewise,
+  /// Blockwise visual, i.e. `Ctrl-V`.
+  Blockwise,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+/// The position in a buffer, i.e. the line index and the char index (based on the line), both
+/// start from 0.
+pub struct BufferPosition {
+  line_idx: usize,
+  char_idx: usize,
+}
+
+impl BufferPosition {
+  pub fn new(line_idx: usize, char_idx: usize) -> Self {
+    BufferPosition { line_idx, char_idx }
+  }
+
+  /// Get line index, starts from 0.
+  pub fn line_idx(&self) -> usize {
+    self.line_idx
+  }
+
+  /// Get char index (based on the line), starts from 0.
+  pub fn char_idx(&self) -> usize {
+    self.char_idx
+  }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+/// The visual mode selection, it is between the `anchor` (where the visual mode starts) and the
+/// `cursor` (where the cursor is now). Both positions are inclusive.
+///
+/// See: <https://vimhelp.org/visual.txt.html>.
+pub struct Selection {
+  kind: VisualKind,
+  anchor: BufferPosition,
+  cursor: BufferPosition,
+}
+
+impl Selection {
+  pub fn new(kind: VisualKind, anchor: BufferPosition, cursor: BufferPosition) -> Self {
+    Selection {
+      kind,
+      anchor,
+      cursor,
+    }
+  }
+
+  pub fn kind(&self) -> VisualKind {
+    self.kind
+  }
+
+  pub fn set_kind(&mut self, kind: VisualKind) {
+    self.kind = kind;
+  }
+
+  /// Where the selection starts, i.e. the cursor position when entering visual mode.
+  pub fn anchor(&self) -> BufferPosition {
+    self.anchor
+  }
+
+  /// Where the cursor is now, motions extend the selection by moving it.
+  pub fn cursor(&self) -> BufferPosition {
+    self.cursor
+  }
+
+  pub fn set_cursor(&mut self, cursor: BufferPosition) {
+    self.cursor = cursor;
+  }
+
+  /// The smaller one of `anchor` and `cursor`.
+  pub fn start(&self) -> BufferPosition {
+    min(self.anchor, self.cursor)
+  }
+
+  /// The bigger one of `anchor` and `cursor`.
+  pub fn end(&self) -> BufferPosition {
+    max(self.anchor, self.cursor)
+  }
+
+  /// The top-left and the bottom-right positions of the selection. For blockwise, they're the
+  /// corners of the block (by char indexes), the selected chars on each line are decided by the
+  /// display columns, see [`line_char_range`](Selection::line_char_range).
+  pub fn ordered(&self) -> (BufferPosition, BufferPosition) {
+    match self.kind {
+      VisualKind::Charwise | VisualKind::Linewise => (self.start(), self.end()),
+      VisualKind::Blockwise => (
+        BufferPosition::new(
+          self.start().line_idx,
+          min(self.anchor.char_idx, self.cursor.char_idx),
+        ),
+        BufferPosition::new(
+          self.end().line_idx,
+          max(self.anchor.char_idx, self.cursor.char_idx),
+        ),
+      ),
+    }
+  }
+
+  /// The selected line indexes.
+  pub fn line_range(&self) -> Range<usize> {
+    self.start().line_idx..self.end().line_idx + 1
+  }
+
+  /// The selected (left-inclusive, right-exclusive) char indexes on the line `line_idx`.
+  ///
+  /// For charwise and linewise, the line break can be selected. For blockwise, the line break is
+  /// never selected, and the display columns of the block are the same across lines, a wide char
+  /// (i.e. tab or CJK) is selected if any of its cells is inside the block.
+  ///
+  /// # Returns
+  ///
+  /// It returns `None` if the line is not selected, or not in the buffer.
+  pub fn line_char_range(&self, buffer: &Buffer, line_idx: usize) -> Option<Range<usize>> {
+    if !self.line_range().contains(&line_idx) {
+      return None;
+    }
+    let line_len = buffer.get_line(line_idx)?.len_chars();
+    let start = self.start();
+    let end = self.end();
+    let range = match self.kind {
+      VisualKind::Charwise => {
+        let start_char_idx = if line_idx == start.line_idx {
+          start.char_idx
+        } else {
+          0
+        };
+        let end_char_idx = if line_idx == end.line_idx {
+          end.char_idx + 1
+        } else {
+          line_len
+        };
+        min(start_char_idx, line_len)..min(end_char_idx, line_len)
+      }
+      VisualKind::Linewise => 0..line_len,
+      VisualKind::Blockwise => {
+        let block = self.block_dcolumns(buffer);
+        let dcolumns = line_dcolumns(buffer, line_idx);
+        let mut selected = dcolumns
+          .iter()
+          .enumerate()
+          .filter(|(_, (start_dcol, end_dcol))| {
+            *start_dcol < block.end && max(*end_dcol, start_dcol + 1) > block.start
+          })
+          .map(|(char_idx, _)| char_idx);
+        match selected.next() {
+          Some(first) => first..selected.last().unwrap_or(first) + 1,
+          None => dcolumns.len()..dcolumns.len(),
+        }
+      }
+    };
+    Some(range)
+  }
+
+  /// Whether the char at `char_idx` of the line `line_idx` is selected.
+  pub fn contains(&self, buffer: &Buffer, line_idx: usize, char_idx: usize) -> bool {
+    self
+      .line_char_range(buffer, line_idx)
+      .is_some_and(|range| range.contains(&char_idx))
+  }
+
+  /// The selected cells in the viewport, i.e. the row index and the (left-inclusive,
+  /// right-exclusive) column indexes of each row, both are based on the window.
+  pub fn cells_in_viewport(&self, viewport: &Viewport, buffer: &Buffer) -> Vec<(u16, Range<u16>)> {
+    let mut cells = vec![];
+    for (line_idx, line_viewport) in viewport.lines().range(self.line_range()) {
+      let char_range = match self.line_char_range(buffer, *line_idx) {
+        Some(char_range) if !char_range.is_empty() => char_range,
+        _ => continue,
+      };
+      for (row_idx, row) in line_viewport.rows().iter() {
+        let dcolumns = row
+          .char2dcolumns()
+          .range(char_range.clone())
+          .map(|(_, dcolumns)| *dcolumns)
+          .reduce(|(start1, end1), (start2, end2)| (min(start1, start2), max(end1, end2)));
+        let start_column = viewport.row_start_column(*line_idx, *row_idx);
+        if let (Some((start_dcol, end_dcol)), Some(start_column)) = (dcolumns, start_column) {
+          if end_dcol > start_dcol {
+            let left = start_column + (start_dcol - row.start_dcol_idx()) as u16;
+            let right = start_column + (end_dcol - row.start_dcol_idx()) as u16;
+            cells.push((*row_idx, left..right));
+          }
+        }
+      }
+    }
+    cells
+  }
+
+  // Blockwise display columns, left-inclusive and right-exclusive. The block covers all the cells
+  // of both the anchor char and the cursor char.
+  fn block_dcolumns(&self, buffer: &Buffer) -> Range<usize> {
+    let (anchor_start, anchor_end) = char_dcolumns(buffer, self.anchor);
+    let (cursor_start, cursor_end) = char_dcolumns(buffer, self.cursor);
+    min(anchor_start, cursor_start)..max(anchor_end, cursor_end)
+  }
+}
+
+// The (start and end) display columns of each char on the line, excluding the line break.
+fn line_dcolumns(buffer: &Buffer, line_idx: usize) -> Vec<(usize, usize)> {
+  let mut dcol = 0_usize;
+  match buffer.get_line(line_idx) {
+    Some(line) => line
+      .chars()
+      .take(buffer.line_len_chars(line_idx))
+      .map(|c| {
+        let start_dcol = dcol;
+        dcol += buffer.char_width(c);
+        (start_dcol, dcol)
+      })
+      .collect(),
+    None => vec![],
+  }
+}
+
+// The display columns of the char at the position, the position after the line end occupies one
+// column after the last char.
+fn char_dcolumns(buffer: &Buffer, pos: BufferPosition) -> (usize, usize) {
+  let dcolumns = line_dcolumns(buffer, pos.line_idx);
+  match dcolumns.get(pos.char_idx) {
+    Some((start_dcol, end_dcol)) => (*start_dcol, max(*end_dcol, start_dcol + 1)),
+    None => {
+      let width = dcolumns.last().map(|(_, end_dcol)| *end_dcol).unwrap_or(0);
+      (width, width + 1)
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use crate::buf::BufferArc;
+  use crate::cart::U16Rect;
+  use crate::envar;
+  use crate::rlock;
+  use crate::test::buf::make_buffer_from_lines;
+  use crate::ui::widget::window::{ViewportOptions, WindowLocalOptions};
+
+  use std::sync::Arc;
+
+  fn make_viewport(buffer: &BufferArc, width: u16, height: u16, wrap: bool) -> Viewport {
+    let window_options = WindowLocalOptions::builder().wrap(wrap).build();
+    let viewport_options = ViewportOptions::from(&window_options);
+    let shape = U16Rect::new((0, 0), (width, height));
+    Viewport::new(&viewport_options, Arc::downgrade(buffer), &shape)
+  }
+
+  #[test]
+  fn charwise1() {
+    let buffer = make_buffer_from_lines(vec!["abc\n", "012345678\n", "012345678\n"]);
+    let buffer = rlock!(buffer);
+    let mut sel = Selection::new(
+      VisualKind::Charwise,
+      BufferPosition::new(1, 3),
+      BufferPosition::new(1, 3),
+    );
+    assert_eq!(sel.line_char_range(&buffer, 1), Some(3..4));
+    sel.set_cursor(BufferPosition::new(2, 1));
+    assert_eq!(sel.line_range(), 1..3);
+    assert_eq!(sel.line_char_range(&buffer, 0), None);
+    assert_eq!(sel.line_char_range(&buffer, 1), Some(3..10));
+    assert_eq!(sel.line_char_range(&buffer, 2), Some(0..2));
+    assert!(sel.contains(&buffer, 1, 9));
+    assert!(sel.contains(&buffer, 2, 1));
+    assert!(!sel.contains(&buffer, 2, 2));
+    assert!(!sel.contains(&buffer, 1, 2));
+
+    // Cursor moves before anchor.
+    sel.set_cursor(BufferPosition::new(1, 0));
+    assert_eq!(sel.start(), BufferPosition::new(1, 0));
+    assert_eq!(sel.end(), BufferPosition::new(1, 3));
+    assert_eq!(sel.ordered(), (sel.start(), sel.end()));
+    assert_eq!(sel.line_char_range(&buffer, 1), Some(0..4));
+  }
+
+  #[test]
+  fn linewise1() {
+    let buffer = make_buffer_from_lines(vec![
+      "\n",
+      "\n",
+      "012345\n",
+      "01234567890\n",
+      "01234567890\n",
+    ]);
+    let buffer = rlock!(buffer);
+    let sel = Selection::new(
+      VisualKind::Linewise,
+      BufferPosition::new(3, 5),
+      BufferPosition::new(2, 1),
+    );
+    assert_eq!(sel.line_range(), 2..4);
+    assert_eq!(sel.line_char_range(&buffer, 2), Some(0..7));
+    assert_eq!(sel.line_char_range(&buffer, 3), Some(0..12));
+    assert_eq!(sel.line_char_range(&buffer, 4), None);
+    assert!(sel.contains(&buffer, 2, 0));
+    assert!(sel.contains(&buffer, 3, 11));
+  }
+
+  #[test]
+  fn blockwise1() {
+    let buffer = make_buffer_from_lines(vec!["012345678\n", "abc\n", "ab\n"]);
+    let buffer = rlock!(buffer);
+    let sel = Selection::new(
+      VisualKind::Blockwise,
+      BufferPosition::new(0, 4),
+      BufferPosition::new(2, 2),
+    );
+    assert_eq!(
+      sel.ordered(),
+      (BufferPosition::new(0, 2), BufferPosition::new(2, 4))
+    );
+    assert_eq!(sel.line_char_range(&buffer, 0), Some(2..5));
+    assert_eq!(sel.line_char_range(&buffer, 1), Some(2..3));
+    assert_eq!(sel.line_char_range(&buffer, 2), Some(2..2));
+    assert!(sel.contains(&buffer, 0, 4));
+    assert!(!sel.contains(&buffer, 0, 5));
+    assert!(sel.contains(&buffer, 1, 2));
+    assert!(!sel.contains(&buffer, 3, 3));
+  }
+
+  #[test]
+  fn blockwise2() {
+    // The tab occupies the display columns 1..9, and the CJK chars occupy 2 columns each.
+    let buffer = make_buffer_from_lines(vec!["a\tb\n", "0123456789\n", "你好吗\n"]);
+    let viewport = make_viewport(&buffer, 20, 3, false);
+    let buffer = rlock!(buffer);
+
+    // The block (columns 0..4) crosses the tab, thus the whole tab is selected.
+    let sel = Selection::new(
+      VisualKind::Blockwise,
+      BufferPosition::new(0, 0),
+      BufferPosition::new(1, 3),
+    );
+    assert_eq!(sel.line_char_range(&buffer, 0), Some(0..2));
+    assert_eq!(sel.line_char_range(&buffer, 1), Some(0..4));
+    assert_eq!(
+      sel.cells_in_viewport(&viewport, &buffer),
+      vec![(0, 0..9), (1, 0..4)]
+    );
+
+    // The block (columns 3..6) covers half of the `好`, thus it's selected.
+    let sel = Selection::new(
+      VisualKind::Blockwise,
+      BufferPosition::new(1, 3),
+      BufferPosition::new(2, 2),
+    );
+    assert_eq!(sel.line_char_range(&buffer, 1), Some(3..6));
+    assert_eq!(sel.line_char_range(&buffer, 2), Some(1..3));
+    assert_eq!(
+      sel.cells_in_viewport(&viewport, &buffer),
+      vec![(1, 3..6), (2, 2..6)]
+    );
+
+    // The cursor on the tab covers all its columns, i.e. 1..9.
+    let sel = Selection::new(
+      VisualKind::Blockwise,
+      BufferPosition::new(0, 1),
+      BufferPosition::new(1, 1),
+    );
+    assert_eq!(sel.line_char_range(&buffer, 1), Some(1..9));
+    assert_eq!(
+      sel.cells_in_viewport(&viewport, &buffer),
+      vec![(0, 1..9), (1, 1..9)]
+    );
+  }
+
+  #[test]
+  fn linewise2() {
+    // The first line is wrapped into two rows.
+    let buffer = make_buffer_from_lines(vec!["Hello, RSVIM!\n", "ab\n", "cd\n"]);
+    let viewport = make_viewport(&buffer, 10, 4, true);
+    let buffer = rlock!(buffer);
+    let sel = Selection::new(
+      VisualKind::Linewise,
+      BufferPosition::new(0, 5),
+      BufferPosition::new(1, 0),
+    );
+    assert_eq!(
+      sel.cells_in_viewport(&viewport, &buffer),
+      vec![(0, 0..10), (1, 0..3), (2, 0..2)]
+    );
+  }
+}
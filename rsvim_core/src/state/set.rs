@@ -0,0 +1,221 @@
+//! The `:set` command, i.e. set the editor options by their Vim names.
+//!
+//! See: <https://vimhelp.org/options.txt.html#%3Aset>.
+
+use crate::js::msg::{OptionScope, OptionValue};
+use crate::res::AnyResult;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+// The value type of the option.
+enum OptionKind {
+  Bool,
+  Number,
+  String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// An option to set, parsed from the `:set` arguments.
+pub struct SetOption {
+  /// The option scope.
+  pub scope: OptionScope,
+  /// The option name, same with the `Rsvim.opt` property.
+  pub name: &'static str,
+  /// The option value.
+  pub value: OptionValue,
+}
+
+// Find the option supported by `:set` by its Vim name or short name, returns the scope, the name
+// of the `Rsvim.opt` property and the value type.
+fn find_option(name: &str) -> Option<(OptionScope, &'static str, OptionKind)> {
+  let option = match name {
+    "wrap" => (OptionScope::Window, "wrap", OptionKind::Bool),
+    "linebreak" | "lbr" => (OptionScope::Window, "lineBreak", OptionKind::Bool),
+    "list" => (OptionScope::Window, "list", OptionKind::Bool),
+    "listchars" | "lcs" => (OptionScope::Window, "listChars", OptionKind::String),
+    "fillchars" | "fcs" => (OptionScope::Window, "fillChars", OptionKind::String),
+    "showbreak" | "sbr" => (OptionScope::Window, "showBreak", OptionKind::String),
+    "scroll" | "scr" => (OptionScope::Window, "scroll", OptionKind::Number),
+    "scrolloff" | "so" => (OptionScope::Window, "scrollOff", OptionKind::Number),
+    "foldmethod" | "fdm" => (OptionScope::Window, "foldMethod", OptionKind::String),
+    "foldlevel" | "fdl" => (OptionScope::Window, "foldLevel", OptionKind::Number),
+    "foldenable" | "fen" => (OptionScope::Window, "foldEnable", OptionKind::Bool),
+    "foldmarker" | "fmr" => (OptionScope::Window, "foldMarker", OptionKind::String),
+    "cursorline" | "cul" => (OptionScope::Window, "cursorLine", OptionKind::Bool),
+    "number" | "nu" => (OptionScope::Window, "number", OptionKind::Bool),
+    "foldcolumn" | "fdc" => (OptionScope::Window, "foldColumn", OptionKind::Number),
+    "signcolumn" | "scl" => (OptionScope::Window, "signColumn", OptionKind::String),
+    "winfixheight" | "wfh" => (OptionScope::Window, "winFixHeight", OptionKind::Bool),
+    "winfixwidth" | "wfw" => (OptionScope::Window, "winFixWidth", OptionKind::Bool),
+    "incsearch" | "is" => (OptionScope::Global, "incSearch", OptionKind::Bool),
+    "hlsearch" | "hls" => (OptionScope::Global, "hlSearch", OptionKind::Bool),
+    "ignorecase" | "ic" => (OptionScope::Global, "ignoreCase", OptionKind::Bool),
+    "smartcase" | "scs" => (OptionScope::Global, "smartCase", OptionKind::Bool),
+    "title" => (OptionScope::Global, "title", OptionKind::Bool),
+    "termguicolors" | "tgc" => (OptionScope::Global, "termGuiColors", OptionKind::Bool),
+    "lazyredraw" | "lz" => (OptionScope::Global, "lazyRedraw", OptionKind::Bool),
+    "splitbelow" | "sb" => (OptionScope::Global, "splitBelow", OptionKind::Bool),
+    "splitright" | "spr" => (OptionScope::Global, "splitRight", OptionKind::Bool),
+    "winminheight" | "wmh" => (OptionScope::Global, "winMinHeight", OptionKind::Number),
+    "winminwidth" | "wmw" => (OptionScope::Global, "winMinWidth", OptionKind::Number),
+    "equalalways" | "ea" => (OptionScope::Global, "equalAlways", OptionKind::Bool),
+    "modifiable" | "ma" => (OptionScope::Buffer, "modifiable", OptionKind::Bool),
+    "readonly" | "ro" => (OptionScope::Buffer, "readonly", OptionKind::Bool),
+    "tabstop" | "ts" => (OptionScope::Buffer, "tabStop", OptionKind::Number),
+    "buftype" | "bt" => (OptionScope::Buffer, "bufType", OptionKind::String),
+    _ => return None,
+  };
+  Some(option)
+}
+
+// Split the arguments by the whitespaces, the whitespace and backslash escaped by a backslash are
+// kept in the argument, i.e. `listchars=eol:\ `.
+fn split_args(args: &str) -> Vec<String> {
+  let mut result = vec![];
+  let mut current = String::new();
+  let mut chars = args.chars();
+  while let Some(c) = chars.next() {
+    match c {
+      '\\' => match chars.next() {
+        Some(next) if next == '\\' || next.is_whitespace() => current.push(next),
+        Some(next) => {
+          current.push(c);
+          current.push(next);
+        }
+        None => current.push(c),
+      },
+      c if c.is_whitespace() => {
+        if !current.is_empty() {
+          result.push(std::mem::take(&mut current));
+        }
+      }
+      c => current.push(c),
+    }
+  }
+  if !current.is_empty() {
+    result.push(current);
+  }
+  result
+}
+
+// Parse a single argument, i.e. `{option}`, `no{option}` or `{option}={value}`.
+fn parse_arg(arg: &str) -> AnyResult<SetOption> {
+  let (name, value) = match arg.find(['=', ':']) {
+    Some(i) => (&arg[..i], Some(&arg[i + 1..])),
+    None => (arg, None),
+  };
+
+  let (option, negate) = match (find_option(name), name.strip_prefix("no")) {
+    (Some(option), _) => (option, false),
+    (None, Some(name)) => match find_option(name) {
+      Some(option) => (option, true),
+      None => anyhow::bail!("E518: Unknown option: {}", arg),
+    },
+    (None, None) => anyhow::bail!("E518: Unknown option: {}", arg),
+  };
+  let (scope, name, kind) = option;
+
+  let value = match (kind, value) {
+    (OptionKind::Bool, None) => OptionValue::Bool(!negate),
+    (OptionKind::Number, Some(value)) if !negate => match value.parse::<usize>() {
+      Ok(value) => OptionValue::Number(value),
+      Err(_) => anyhow::bail!("E521: Number required after =: {}", arg),
+    },
+    (OptionKind::String, Some(value)) if !negate => OptionValue::String(value.to_string()),
+    _ => anyhow::bail!("E474: Invalid argument: {}", arg),
+  };
+  Ok(SetOption { scope, name, value })
+}
+
+/// Parse the `:set` arguments, multiple options are separated by whitespaces:
+///
+/// - `{option}`: Set the boolean option.
+/// - `no{option}`: Reset the boolean option.
+/// - `{option}={value}` or `{option}:{value}`: Set the number or string option.
+///
+/// Both the full name and the short name of the option work, i.e. `tabstop` and `ts`.
+///
+/// # Errors
+///
+/// If any argument is invalid, then no option is set.
+pub fn parse_set_args(args: &str) -> AnyResult<Vec<SetOption>> {
+  split_args(args).iter().map(|arg| parse_arg(arg)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn parse_set_args1() {
+    let actual = parse_set_args("ts=2 nowrap  lbr listchars=eol:$,trail:\\ ").unwrap();
+    assert_eq!(
+      actual,
+      vec![
+        SetOption {
+          scope: OptionScope::Buffer,
+          name: "tabStop",
+          value: OptionValue::Number(2)
+        },
+        SetOption {
+          scope: OptionScope::Window,
+          name: "wrap",
+          value: OptionValue::Bool(false)
+        },
+        SetOption {
+          scope: OptionScope::Window,
+          name: "lineBreak",
+          value: OptionValue::Bool(true)
+        },
+        SetOption {
+          scope: OptionScope::Window,
+          name: "listChars",
+          value: OptionValue::String("eol:$,trail: ".to_string())
+        },
+      ]
+    );
+    assert_eq!(parse_set_args("").unwrap(), vec![]);
+    assert_eq!(
+      parse_set_args("bt=nofile").unwrap(),
+      vec![SetOption {
+        scope: OptionScope::Buffer,
+        name: "bufType",
+        value: OptionValue::String("nofile".to_string())
+      }]
+    );
+
+    assert_eq!(
+      parse_set_args("nu fdc=2 scl=yes").unwrap(),
+      vec![
+        SetOption {
+          scope: OptionScope::Window,
+          name: "number",
+          value: OptionValue::Bool(true)
+        },
+        SetOption {
+          scope: OptionScope::Window,
+          name: "foldColumn",
+          value: OptionValue::Number(2)
+        },
+        SetOption {
+          scope: OptionScope::Window,
+          name: "signColumn",
+          value: OptionValue::String("yes".to_string())
+        },
+      ]
+    );
+
+    let error = |args: &str| parse_set_args(args).unwrap_err().to_string();
+    assert_eq!(
+      error("relativenumber"),
+      "E518: Unknown option: relativenumber"
+    );
+    assert_eq!(error("wrap unknown"), "E518: Unknown option: unknown");
+    assert_eq!(
+      error("tabstop=x"),
+      "E521: Number required after =: tabstop=x"
+    );
+    assert_eq!(error("tabstop"), "E474: Invalid argument: tabstop");
+    assert_eq!(error("notabstop"), "E474: Invalid argument: notabstop");
+    assert_eq!(error("wrap=1"), "E474: Invalid argument: wrap=1");
+  }
+}
@@ -0,0 +1,515 @@
+//! The substitute command, i.e. `:{range}s/{pattern}/{replacement}/{flags}`.
+//!
+//! The pattern is matched line by line, thus a pattern can't match across lines for now. The
+//! replacement supports these special chars:
+//!
+//! - `&` and `\0`, the whole matched text.
+//! - `\1` to `\9`, the text matched by the capture group, `$1` to `$9` work too.
+//! - `\n` and `\r`, a line break, i.e. split the line.
+//! - `\t`, a tab.
+//! - `\&` and `\\`, the literal `&` and `\`.
+//!
+//! See: <https://vimhelp.org/change.txt.html#%3Asubstitute>.
+
+use crate::buf::Buffer;
+use crate::res::{AnyResult, BufferResult};
+use crate::state::selection::BufferPosition;
+
+use regex::{Captures, Regex};
+use std::ops::Range;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+/// The flags after the replacement.
+/// See: <https://vimhelp.org/change.txt.html#%3As_flags>.
+pub struct SubstituteFlags {
+  /// `g`, replace all the matches in the line, instead of the first one.
+  pub global: bool,
+  /// `i`, ignore case, `I` doesn't ignore case. Otherwise, it follows the 'ignorecase' and
+  /// 'smartcase' options.
+  pub ignore_case: Option<bool>,
+  /// `c`, confirm each substitution.
+  pub confirm: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// The parsed arguments of the substitute command.
+pub struct Substitute {
+  pattern: String,
+  replacement: String,
+  flags: SubstituteFlags,
+}
+
+impl Substitute {
+  /// Parse the arguments, i.e. `/{pattern}/{replacement}/{flags}`. The delimiter can be any
+  /// single-byte char except letters, digits, `\`, `"`, `|` and whitespace. The trailing
+  /// delimiters can be omitted, i.e. `/{pattern}` deletes the matches.
+  ///
+  /// # Errors
+  ///
+  /// If there's no pattern, the delimiter is invalid, or the flags are unknown.
+  pub fn parse(args: &str) -> AnyResult<Self> {
+    let mut chars = args.chars();
+    let delimiter = match chars.next() {
+      None => anyhow::bail!("E35: No previous regular expression"),
+      Some(c) if c.is_alphanumeric() || matches!(c, '\\' | '"' | '|') || c.is_whitespace() => {
+        anyhow::bail!("E146: Regular expressions can't be delimited by letters")
+      }
+      Some(c) => c,
+    };
+    let rest = chars.as_str();
+    let (pattern, rest) = split_at_delimiter(rest, delimiter);
+    let (replacement, rest) = split_at_delimiter(rest.unwrap_or(""), delimiter);
+
+    let mut flags = SubstituteFlags::default();
+    let rest = rest.unwrap_or("");
+    for c in rest.trim_end().chars() {
+      match c {
+        'g' => flags.global = true,
+        'i' => flags.ignore_case = Some(true),
+        'I' => flags.ignore_case = Some(false),
+        'c' => flags.confirm = true,
+        _ => anyhow::bail!("E488: Trailing characters: {}", rest),
+      }
+    }
+    Ok(Substitute {
+      pattern,
+      replacement,
+      flags,
+    })
+  }
+
+  /// The pattern, it's empty if the last search pattern is used.
+  pub fn pattern(&self) -> &str {
+    &self.pattern
+  }
+
+  /// The replacement, the special chars are not expanded.
+  pub fn replacement(&self) -> &str {
+    &self.replacement
+  }
+
+  pub fn flags(&self) -> SubstituteFlags {
+    self.flags
+  }
+}
+
+// Split the text at the first delimiter that is not escaped by `\`, the escaped delimiter is
+// unescaped, other escaped chars are kept as it is. Returns the text before the delimiter, and the
+// remainder after it if the delimiter is found.
+fn split_at_delimiter(s: &str, delimiter: char) -> (String, Option<&str>) {
+  let mut text = String::new();
+  let mut chars = s.char_indices();
+  while let Some((i, c)) = chars.next() {
+    match c {
+      '\\' => match chars.next() {
+        Some((_, escaped)) if escaped == delimiter => text.push(escaped),
+        Some((_, escaped)) => {
+          text.push(c);
+          text.push(escaped);
+        }
+        None => text.push(c),
+      },
+      c if c == delimiter => return (text, Some(&s[i + c.len_utf8()..])),
+      c => text.push(c),
+    }
+  }
+  (text, None)
+}
+
+/// Expand the special chars in the replacement with the captures of a match.
+pub fn expand_replacement(replacement: &str, captures: &Captures) -> String {
+  let mut expanded = String::with_capacity(replacement.len());
+  let mut chars = replacement.chars();
+  while let Some(c) = chars.next() {
+    match c {
+      '&' => expanded.push_str(captures.get(0).map_or("", |m| m.as_str())),
+      '$' => match chars.clone().next().and_then(|d| d.to_digit(10)) {
+        Some(group) => {
+          chars.next();
+          expanded.push_str(captures.get(group as usize).map_or("", |m| m.as_str()));
+        }
+        None => expanded.push(c),
+      },
+      '\\' => match chars.next() {
+        Some(d @ '0'..='9') => {
+          let group = d.to_digit(10).unwrap() as usize;
+          expanded.push_str(captures.get(group).map_or("", |m| m.as_str()));
+        }
+        Some('n') | Some('r') => expanded.push('\n'),
+        Some('t') => expanded.push('\t'),
+        Some(escaped) => expanded.push(escaped),
+        None => expanded.push(c),
+      },
+      c => expanded.push(c),
+    }
+  }
+  expanded
+}
+
+// The text of the line, excluding the trailing line break.
+fn line_text(buffer: &Buffer, line_idx: usize) -> String {
+  match buffer.get_line(line_idx) {
+    Some(line) => line.slice(..buffer.line_len_chars(line_idx)).to_string(),
+    None => String::new(),
+  }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+/// The result of the substitute command.
+pub struct SubstituteReport {
+  /// The substitutions count.
+  pub substitutions: usize,
+  /// The changed lines count.
+  pub lines: usize,
+  /// The line index of the last substitution.
+  pub last_line_idx: Option<usize>,
+}
+
+impl SubstituteReport {
+  // Count a substitution in the line.
+  fn count(&mut self, line_idx: usize) {
+    self.substitutions += 1;
+    if self.last_line_idx != Some(line_idx) {
+      self.lines += 1;
+      self.last_line_idx = Some(line_idx);
+    }
+  }
+
+  /// The message of the result, i.e. `3 substitutions on 2 lines`.
+  pub fn message(&self) -> String {
+    let plural = |n: usize, word: &str| {
+      if n == 1 {
+        format!("{} {}", n, word)
+      } else {
+        format!("{} {}s", n, word)
+      }
+    };
+    format!(
+      "{} on {}",
+      plural(self.substitutions, "substitution"),
+      plural(self.lines, "line")
+    )
+  }
+}
+
+/// Substitute the matches in the lines. All the changed lines are replaced in one buffer edit.
+pub fn substitute_lines(
+  buffer: &mut Buffer,
+  line_range: Range<usize>,
+  pattern: &Regex,
+  replacement: &str,
+  global: bool,
+) -> BufferResult<SubstituteReport> {
+  let mut report = SubstituteReport::default();
+  // The changed lines between the first and the last changed line, with their line breaks.
+  let mut changed_text = String::new();
+  let mut changed_lines: Option<Range<usize>> = None;
+  let mut unchanged_text = String::new();
+  // The lines added by the line breaks in the replacement.
+  let mut added_lines = 0_usize;
+
+  for line_idx in line_range {
+    let text = line_text(buffer, line_idx);
+    let line_break = match buffer.get_line(line_idx) {
+      Some(line) => line.slice(buffer.line_len_chars(line_idx)..).to_string(),
+      None => break,
+    };
+
+    let mut substituted = String::with_capacity(text.len());
+    let mut last_end = 0;
+    let mut substitutions = 0;
+    for captures in pattern.captures_iter(&text) {
+      let m = captures.get(0).unwrap();
+      substituted.push_str(&text[last_end..m.start()]);
+      substituted.push_str(&expand_replacement(replacement, &captures));
+      last_end = m.end();
+      substitutions += 1;
+      if !global {
+        break;
+      }
+    }
+
+    if substitutions == 0 {
+      if changed_lines.is_some() {
+        unchanged_text.push_str(&text);
+        unchanged_text.push_str(&line_break);
+      }
+      continue;
+    }
+    substituted.push_str(&text[last_end..]);
+    for _ in 0..substitutions {
+      report.count(line_idx + added_lines);
+    }
+    added_lines += substituted.matches('\n').count();
+
+    changed_text.push_str(&unchanged_text);
+    unchanged_text.clear();
+    changed_text.push_str(&substituted);
+    changed_text.push_str(&line_break);
+    changed_lines = match changed_lines {
+      Some(lines) => Some(lines.start..line_idx + 1),
+      None => Some(line_idx..line_idx + 1),
+    };
+  }
+
+  if let Some(lines) = changed_lines {
+    let start_char_idx = buffer.line_to_char(lines.start);
+    let end_char_idx = if lines.end < buffer.len_lines() {
+      buffer.line_to_char(lines.end)
+    } else {
+      buffer.len_chars()
+    };
+    buffer.replace(start_char_idx..end_char_idx, &changed_text)?;
+  }
+  Ok(report)
+}
+
+#[derive(Debug, Clone)]
+/// The substitute command with the `c` flag, it waits for confirming each match.
+pub struct SubstituteConfirm {
+  pattern: Regex,
+  replacement: String,
+  global: bool,
+  // The current line index, and the end line index (exclusive).
+  line_idx: usize,
+  end_line_idx: usize,
+  // The byte index in current line to find the next match.
+  byte_idx: usize,
+  // The byte range of the current match in current line, and its position.
+  current: Option<(Range<usize>, BufferPosition)>,
+  // The cursor position when the command starts.
+  origin: BufferPosition,
+  report: SubstituteReport,
+}
+
+impl SubstituteConfirm {
+  pub fn new(
+    pattern: Regex,
+    replacement: &str,
+    global: bool,
+    line_range: Range<usize>,
+    origin: BufferPosition,
+  ) -> Self {
+    SubstituteConfirm {
+      pattern,
+      replacement: replacement.to_string(),
+      global,
+      line_idx: line_range.start,
+      end_line_idx: line_range.end,
+      byte_idx: 0,
+      current: None,
+      origin,
+      report: SubstituteReport::default(),
+    }
+  }
+
+  /// The replacement, the special chars are not expanded.
+  pub fn replacement(&self) -> &str {
+    &self.replacement
+  }
+
+  /// The cursor position when the command starts.
+  pub fn origin(&self) -> BufferPosition {
+    self.origin
+  }
+
+  pub fn report(&self) -> SubstituteReport {
+    self.report
+  }
+
+  /// The position of the current match.
+  pub fn current_position(&self) -> Option<BufferPosition> {
+    self.current.as_ref().map(|(_, position)| *position)
+  }
+
+  /// Find the next match, returns its position. It returns `None` if there are no more matches.
+  pub fn find_next(&mut self, buffer: &Buffer) -> Option<BufferPosition> {
+    self.current = None;
+    while self.line_idx < self.end_line_idx && self.line_idx < buffer.len_lines() {
+      let text = line_text(buffer, self.line_idx);
+      // Moved forward after an empty match, it may be inside a multi-byte char.
+      while self.byte_idx < text.len() && !text.is_char_boundary(self.byte_idx) {
+        self.byte_idx += 1;
+      }
+      if self.byte_idx <= text.len() {
+        if let Some(m) = self.pattern.find_at(&text, self.byte_idx) {
+          let char_idx = text[..m.start()].chars().count();
+          let position = BufferPosition::new(self.line_idx, char_idx);
+          self.current = Some((m.range(), position));
+          return Some(position);
+        }
+      }
+      self.next_line();
+    }
+    None
+  }
+
+  /// Replace the current match, then the next match is found after the replaced text.
+  pub fn replace(&mut self, buffer: &mut Buffer) -> BufferResult<()> {
+    let current = match self.current.take() {
+      Some((current, _)) => current,
+      None => return Ok(()),
+    };
+    let text = line_text(buffer, self.line_idx);
+    let replaced = match self.pattern.captures_at(&text, current.start) {
+      Some(captures) => expand_replacement(&self.replacement, &captures),
+      None => return Ok(()),
+    };
+    let line_start = buffer.line_to_char(self.line_idx);
+    let start_char_idx = line_start + text[..current.start].chars().count();
+    let end_char_idx = line_start + text[..current.end].chars().count();
+    buffer.replace(start_char_idx..end_char_idx, &replaced)?;
+    self.report.count(self.line_idx);
+
+    // The line breaks in the replacement split the line.
+    let added_lines = replaced.matches('\n').count();
+    self.line_idx += added_lines;
+    self.end_line_idx += added_lines;
+    self.byte_idx = match replaced.rfind('\n') {
+      Some(i) => replaced.len() - i - 1,
+      None => current.start + replaced.len(),
+    };
+    self.advance(current.is_empty());
+    Ok(())
+  }
+
+  /// Skip the current match.
+  pub fn skip(&mut self) {
+    if let Some((current, _)) = self.current.take() {
+      self.byte_idx = current.end;
+      self.advance(current.is_empty());
+    }
+  }
+
+  // Go to the next line if not `global`, or move forward after an empty match, otherwise it
+  // matches at the same position again.
+  fn advance(&mut self, empty_match: bool) {
+    if !self.global {
+      self.next_line();
+    } else if empty_match {
+      self.byte_idx += 1;
+    }
+  }
+
+  fn next_line(&mut self) {
+    self.line_idx += 1;
+    self.byte_idx = 0;
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use crate::envar;
+  use crate::test::buf::make_buffer_from_lines;
+  use crate::wlock;
+
+  fn buffer_text(buffer: &Buffer) -> String {
+    buffer.lines().map(|l| l.to_string()).collect()
+  }
+
+  #[test]
+  fn parse1() {
+    let actual = Substitute::parse("/foo/bar/g").unwrap();
+    assert_eq!(actual.pattern(), "foo");
+    assert_eq!(actual.replacement(), "bar");
+    assert_eq!(
+      actual.flags(),
+      SubstituteFlags {
+        global: true,
+        ..Default::default()
+      }
+    );
+
+    // The escaped delimiter, and the omitted trailing delimiters.
+    let actual = Substitute::parse(r"#a\#b#c\1").unwrap();
+    assert_eq!(actual.pattern(), "a#b");
+    assert_eq!(actual.replacement(), r"c\1");
+    assert_eq!(actual.flags(), SubstituteFlags::default());
+    let actual = Substitute::parse("/foo").unwrap();
+    assert_eq!(actual.pattern(), "foo");
+    assert_eq!(actual.replacement(), "");
+
+    let actual = Substitute::parse("/foo//Igc").unwrap();
+    assert_eq!(
+      actual.flags(),
+      SubstituteFlags {
+        global: true,
+        ignore_case: Some(false),
+        confirm: true,
+      }
+    );
+
+    assert!(Substitute::parse("").is_err());
+    assert!(Substitute::parse("afooabara").is_err());
+    assert!(Substitute::parse("/foo/bar/x").is_err());
+  }
+
+  #[test]
+  fn expand_replacement1() {
+    let pattern = Regex::new(r"(\w+) (\w+)").unwrap();
+    let captures = pattern.captures("hello world").unwrap();
+    assert_eq!(
+      expand_replacement(r"\2 \1 [&] \& \\ \n\t", &captures),
+      "world hello [hello world] & \\ \n\t"
+    );
+    // The `$` style groups, the `$` without digit is literal.
+    assert_eq!(
+      expand_replacement("$2-$1 $0 $$ $", &captures),
+      "world-hello hello world $$ $"
+    );
+  }
+
+  #[test]
+  fn substitute_lines1() {
+    let buffer = make_buffer_from_lines(vec!["foo foo\n", "bar\n", "foo\n"]);
+    let mut buffer = wlock!(buffer);
+    let pattern = Regex::new("foo").unwrap();
+    let tick = buffer.changed_tick();
+
+    let report = substitute_lines(&mut buffer, 0..3, &pattern, "x", true).unwrap();
+    assert_eq!(buffer_text(&buffer), "x x\nbar\nx\n");
+    assert_eq!(report.substitutions, 3);
+    assert_eq!(report.lines, 2);
+    assert_eq!(report.last_line_idx, Some(2));
+    assert_eq!(report.message(), "3 substitutions on 2 lines");
+    // All the lines are replaced in one edit.
+    assert_eq!(buffer.changed_tick(), tick + 1);
+
+    // The line break in the replacement splits the line, and the empty replacement deletes.
+    let pattern = Regex::new(" ").unwrap();
+    let report = substitute_lines(&mut buffer, 0..1, &pattern, r"\n", false).unwrap();
+    assert_eq!(buffer_text(&buffer), "x\nx\nbar\nx\n");
+    assert_eq!(report.message(), "1 substitution on 1 line");
+    let pattern = Regex::new("x").unwrap();
+    let report = substitute_lines(&mut buffer, 0..4, &pattern, "", false).unwrap();
+    assert_eq!(buffer_text(&buffer), "\n\nbar\n\n");
+    assert_eq!(report.last_line_idx, Some(3));
+
+    // No match, the buffer is not changed.
+    let tick = buffer.changed_tick();
+    let pattern = Regex::new("qux").unwrap();
+    let report = substitute_lines(&mut buffer, 0..4, &pattern, "", true).unwrap();
+    assert_eq!(report, SubstituteReport::default());
+    assert_eq!(buffer.changed_tick(), tick);
+  }
+
+  #[test]
+  fn confirm1() {
+    let buffer = make_buffer_from_lines(vec!["a-a\n", "b\n", "a\n"]);
+    let mut buffer = wlock!(buffer);
+    let pattern = Regex::new("a").unwrap();
+    let mut confirm = SubstituteConfirm::new(pattern, "xy", true, 0..3, BufferPosition::new(0, 0));
+
+    assert_eq!(confirm.find_next(&buffer), Some(BufferPosition::new(0, 0)));
+    confirm.replace(&mut buffer).unwrap();
+    assert_eq!(confirm.find_next(&buffer), Some(BufferPosition::new(0, 3)));
+    confirm.skip();
+    assert_eq!(confirm.find_next(&buffer), Some(BufferPosition::new(2, 0)));
+    confirm.replace(&mut buffer).unwrap();
+    assert_eq!(confirm.find_next(&buffer), None);
+    assert_eq!(buffer_text(&buffer), "xy-a\nb\nxy\n");
+    assert_eq!(confirm.report().message(), "2 substitutions on 2 lines");
+  }
+}
@@ -3,4 +3,9 @@
 //! NOTE: This module should be only used in unit tests, not some where else.
 
 pub mod buf;
+pub mod clipboard;
+pub mod js;
 pub mod log;
+pub mod render;
+pub mod state;
+pub mod tree;
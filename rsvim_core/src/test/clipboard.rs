@@ -0,0 +1,37 @@
+//! Clipboard utils for testing.
+
+use crate::res::AnyResult;
+use crate::state::clipboard::ClipboardProvider;
+use crate::ui::canvas::ShaderCommand;
+
+use parking_lot::RwLock;
+use std::time::Duration;
+
+#[derive(Debug, Default)]
+/// The clipboard in memory, each write/read blocks for the `delay`.
+pub struct MockProvider {
+  pub text: RwLock<String>,
+  pub delay: Duration,
+}
+
+impl ClipboardProvider for MockProvider {
+  fn name(&self) -> &str {
+    "mock"
+  }
+
+  fn write(
+    &self,
+    _register_name: char,
+    text: &str,
+    _timeout: Duration,
+  ) -> AnyResult<Option<ShaderCommand>> {
+    std::thread::sleep(self.delay);
+    *self.text.write() = text.to_string();
+    Ok(None)
+  }
+
+  fn read(&self, _register_name: char, _timeout: Duration) -> AnyResult<String> {
+    std::thread::sleep(self.delay);
+    Ok(self.text.read().clone())
+  }
+}
@@ -0,0 +1,117 @@
+//! Js runtime utils for testing.
+//!
+//! NOTE: This module should only be used in unit tests, not some where else.
+
+#[cfg(test)]
+use crate::buf::BuffersManagerArc;
+#[cfg(test)]
+use crate::js::msg::{EventLoopToJsRuntimeMessage, JsRuntimeToEventLoopMessage};
+#[cfg(test)]
+use crate::js::{JsRuntime, JsRuntimeOptions, SnapshotData};
+#[cfg(test)]
+use crate::state::StateArc;
+#[cfg(test)]
+use crate::ui::tree::TreeArc;
+
+#[cfg(test)]
+use tokio::sync::mpsc::{Receiver, Sender};
+
+#[cfg(test)]
+/// Create js runtime in test mode, with the snapshot built in current process.
+pub fn make_js_runtime(tree: TreeArc, buffers: BuffersManagerArc, state: StateArc) -> JsRuntime {
+  let (js_runtime, _master_recv_from_js_runtime, _master_send_to_js_runtime) =
+    make_js_runtime_with_channels(tree, buffers, state);
+  js_runtime
+}
+
+#[cfg(test)]
+/// Create js runtime in test mode, with the snapshot built in current process. Also returns the
+/// event loop side of the channels, i.e. the receiver of js runtime messages and the sender to js
+/// runtime.
+pub fn make_js_runtime_with_channels(
+  tree: TreeArc,
+  buffers: BuffersManagerArc,
+  state: StateArc,
+) -> (
+  JsRuntime,
+  Receiver<JsRuntimeToEventLoopMessage>,
+  Sender<EventLoopToJsRuntimeMessage>,
+) {
+  let options = JsRuntimeOptions {
+    test_mode: true,
+    ..Default::default()
+  };
+  new_js_runtime(options, Some(make_snapshot()), tree, buffers, state)
+}
+
+#[cfg(test)]
+/// The snapshot built in current process, it's built only once.
+pub fn make_snapshot() -> SnapshotData {
+  use crate::js::JsRuntimeForSnapshot;
+
+  use once_cell::sync::Lazy;
+
+  static SNAPSHOT: Lazy<Box<[u8]>> = Lazy::new(|| {
+    let snapshot = JsRuntimeForSnapshot::new().create_snapshot();
+    snapshot.to_vec().into_boxed_slice()
+  });
+
+  SnapshotData::new(&SNAPSHOT)
+}
+
+#[cfg(test)]
+/// Create js runtime with the options and snapshot.
+pub fn make_js_runtime_with(
+  options: JsRuntimeOptions,
+  snapshot: Option<SnapshotData>,
+  tree: TreeArc,
+  buffers: BuffersManagerArc,
+  state: StateArc,
+) -> JsRuntime {
+  let (js_runtime, _master_recv_from_js_runtime, _master_send_to_js_runtime) =
+    new_js_runtime(options, snapshot, tree, buffers, state);
+  js_runtime
+}
+
+#[cfg(test)]
+fn new_js_runtime(
+  options: JsRuntimeOptions,
+  snapshot: Option<SnapshotData>,
+  tree: TreeArc,
+  buffers: BuffersManagerArc,
+  state: StateArc,
+) -> (
+  JsRuntime,
+  Receiver<JsRuntimeToEventLoopMessage>,
+  Sender<EventLoopToJsRuntimeMessage>,
+) {
+  use crate::cli::CliOpt;
+  use crate::envar;
+
+  use parking_lot::RwLock;
+  use std::sync::Arc;
+  use std::time::Instant;
+  use tokio::sync::mpsc::channel;
+
+  let channel_buf_size = envar::CHANNEL_BUF_SIZE();
+  let (js_runtime_send_to_master, master_recv_from_js_runtime) = channel(channel_buf_size);
+  let (master_send_to_js_runtime, js_runtime_recv_from_master) = channel(channel_buf_size);
+  let js_runtime = JsRuntime::new(
+    options,
+    snapshot,
+    Instant::now(),
+    0,
+    js_runtime_send_to_master,
+    js_runtime_recv_from_master,
+    CliOpt::default(),
+    Arc::new(RwLock::new(vec![])),
+    tree,
+    buffers,
+    state,
+  );
+  (
+    js_runtime,
+    master_recv_from_js_runtime,
+    master_send_to_js_runtime,
+  )
+}
@@ -0,0 +1,194 @@
+//! Headless rendering utils for testing, i.e. draw the widget tree without a terminal and dump
+//! the canvas frame as strings for snapshot tests.
+
+use crate::cart::{U16Pos, U16Size};
+use crate::ui::canvas::{Canvas, CanvasArc, Cell, Frame};
+use crate::ui::tree::Tree;
+
+use crossterm::style::{Attribute, Color};
+use unicode_width::UnicodeWidthStr;
+
+/// Draw the widget tree to a new canvas, and dump the rows (symbols only).
+pub fn snapshot(tree: &Tree, size: U16Size) -> Vec<String> {
+  let canvas = draw(tree, size);
+  let canvas = canvas.read();
+  frame_snapshot(canvas.frame())
+}
+
+/// Draw the widget tree to a new canvas, and dump the rows with styles, see
+/// [`frame_snapshot_styled`].
+pub fn snapshot_styled(tree: &Tree, size: U16Size) -> Vec<String> {
+  let canvas = draw(tree, size);
+  let canvas = canvas.read();
+  frame_snapshot_styled(canvas.frame())
+}
+
+/// Dump the rows of the frame (symbols only).
+///
+/// A wide symbol (e.g. a CJK char or an expanded tab) covers its continuation cells, which are
+/// rendered as nothing, thus every row has the same display width with the frame. The empty
+/// cells not covered by any symbol are rendered as spaces.
+pub fn frame_snapshot(frame: &Frame) -> Vec<String> {
+  rows(frame)
+    .into_iter()
+    .map(|row| row.iter().map(|(symbol, _)| symbol.as_str()).collect())
+    .collect()
+}
+
+/// Dump the rows of the frame with styles, i.e. the cells not in the default style are wrapped
+/// in markups like `<fg=Red,bg=#0000ff,attrs=Bold+Reverse>text</>`.
+pub fn frame_snapshot_styled(frame: &Frame) -> Vec<String> {
+  rows(frame)
+    .into_iter()
+    .map(|row| {
+      let mut result = String::new();
+      let mut i = 0_usize;
+      while i < row.len() {
+        let style = markup(row[i].1);
+        let mut j = i;
+        let mut text = String::new();
+        while j < row.len() && markup(row[j].1) == style {
+          text.push_str(&row[j].0);
+          j += 1;
+        }
+        match style {
+          Some(style) => result.push_str(&format!("<{}>{}</>", style, text)),
+          None => result.push_str(&text),
+        }
+        i = j;
+      }
+      result
+    })
+    .collect()
+}
+
+fn draw(tree: &Tree, size: U16Size) -> CanvasArc {
+  let canvas = Canvas::to_arc(Canvas::new(size));
+  tree.draw(canvas.clone());
+  canvas
+}
+
+// The rendered symbols and cells of each row, the continuation cells of the wide symbols are
+// skipped.
+fn rows(frame: &Frame) -> Vec<Vec<(String, &Cell)>> {
+  let size = frame.size();
+  (0..size.height())
+    .map(|y| {
+      let mut row = vec![];
+      let mut x = 0_u16;
+      while x < size.width() {
+        let cell = frame.get_cell(U16Pos::new(x, y));
+        let symbol = cell.symbol();
+        if symbol.is_empty() {
+          row.push((" ".to_string(), cell));
+          x += 1;
+        } else {
+          row.push((symbol.to_string(), cell));
+          x += symbol.width().max(1) as u16;
+        }
+      }
+      row
+    })
+    .collect()
+}
+
+fn color(color: Color) -> String {
+  match color {
+    Color::Rgb { r, g, b } => format!("#{:02x}{:02x}{:02x}", r, g, b),
+    color => format!("{:?}", color),
+  }
+}
+
+// The markup of the cell style, or `None` if it's the default style.
+fn markup(cell: &Cell) -> Option<String> {
+  let mut styles = vec![];
+  if cell.fg() != Color::Reset {
+    styles.push(format!("fg={}", color(cell.fg())));
+  }
+  if cell.bg() != Color::Reset {
+    styles.push(format!("bg={}", color(cell.bg())));
+  }
+  let attrs = Attribute::iterator()
+    .filter(|a| *a != Attribute::Reset && cell.attrs().has(*a))
+    .map(|a| format!("{:?}", a))
+    .collect::<Vec<_>>();
+  if !attrs.is_empty() {
+    styles.push(format!("attrs={}", attrs.join("+")));
+  }
+  if styles.is_empty() {
+    None
+  } else {
+    Some(styles.join(","))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use crate::cart::IRect;
+  use crate::state::message::Message;
+  use crate::test::buf::make_buffer_from_lines;
+  use crate::ui::tree::TreeNode;
+  use crate::ui::widget::{MessageArea, Window};
+
+  use crossterm::style::Attributes;
+  use std::sync::Arc;
+
+  #[test]
+  fn snapshot1() {
+    let buffer = make_buffer_from_lines(vec!["你好, RSVIM!\n", "a\tb\n"]);
+    let mut tree = Tree::new(U16Size::new(12, 4));
+    let root_id = tree.root_id();
+    let window = Window::new(
+      IRect::new((0, 0), (12, 3)),
+      Arc::downgrade(&buffer),
+      tree.local_options(),
+    );
+    tree
+      .bounded_insert(&root_id, TreeNode::Window(window))
+      .unwrap();
+    let message_area = MessageArea::new(IRect::new((0, 3), (12, 4)));
+    tree
+      .bounded_insert(&root_id, TreeNode::MessageArea(message_area))
+      .unwrap();
+    tree.set_message(Some(Message::error("E32")));
+
+    // The wide chars occupy two columns, the expanded tab occupies eight columns.
+    let actual = snapshot(&tree, U16Size::new(12, 4));
+    assert_eq!(
+      actual,
+      vec![
+        "你好, RSVIM!",
+        "a        b  ",
+        "~           ",
+        "E32         "
+      ]
+    );
+    for row in actual.iter() {
+      assert_eq!(row.width(), 12);
+    }
+
+    // Only the error message is styled.
+    let actual = snapshot_styled(&tree, U16Size::new(12, 4));
+    assert_eq!(actual[0], "你好, RSVIM!");
+    assert_eq!(actual[3], "<fg=Red,attrs=Bold>E32</>         ");
+  }
+
+  #[test]
+  fn frame_snapshot_styled1() {
+    let mut frame = Frame::new(U16Size::new(4, 1), Default::default());
+    let red = Cell::new(
+      "a".into(),
+      Color::Rgb { r: 255, g: 0, b: 0 },
+      Color::Reset,
+      Attributes::default(),
+    );
+    frame.set_cells_at(
+      U16Pos::new(0, 0),
+      vec![red.clone(), red, Cell::space(), Cell::empty()],
+    );
+    assert_eq!(frame_snapshot(&frame), vec!["aa  "]);
+    assert_eq!(frame_snapshot_styled(&frame), vec!["<fg=#ff0000>aa</>  "]);
+  }
+}
@@ -0,0 +1,19 @@
+//! State utils for testing.
+
+use crate::buf::BuffersManager;
+use crate::state::State;
+use crate::ui::tree::TreeArc;
+
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+
+/// Handle the key without modifiers, see [`press_key`].
+pub fn press(state: &mut State, tree: &TreeArc, code: KeyCode) {
+  press_key(state, tree, KeyEvent::new(code, KeyModifiers::NONE));
+}
+
+/// Handle the key, the buffers manager is empty, i.e. the keys only edit the buffers shown in the
+/// windows.
+pub fn press_key(state: &mut State, tree: &TreeArc, key: KeyEvent) {
+  let buffers = BuffersManager::to_arc(BuffersManager::new());
+  state.handle(tree.clone(), buffers, Event::Key(key));
+}
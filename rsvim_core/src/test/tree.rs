@@ -0,0 +1,41 @@
+//! Tree utils for testing.
+
+use crate::buf::BufferArc;
+use crate::cart::{IRect, U16Size};
+use crate::test::buf::make_buffer_from_lines;
+use crate::ui::tree::internal::Inodeable;
+use crate::ui::tree::{Tree, TreeArc, TreeNode, TreeNodeId};
+use crate::ui::widget::cursor::Cursor;
+use crate::ui::widget::window::Window;
+
+use std::sync::Arc;
+
+/// Insert a window that fills the tree and shows the buffer, along with the cursor inside it.
+/// Returns the window ID.
+pub fn insert_window(tree: &mut Tree, buffer: &BufferArc) -> TreeNodeId {
+  let root_id = tree.root_id();
+  let shape = *tree.node(&root_id).unwrap().shape();
+  let window = Window::new(shape, Arc::downgrade(buffer), tree.local_options());
+  let window_id = window.id();
+  tree
+    .bounded_insert(&root_id, TreeNode::Window(window))
+    .unwrap();
+  let cursor = Cursor::new(IRect::new((0, 0), (1, 1)));
+  tree
+    .bounded_insert(&window_id, TreeNode::Cursor(cursor))
+    .unwrap();
+  window_id
+}
+
+/// Create a 20x10 tree with a window shows the buffer.
+pub fn make_window_tree(buffer: &BufferArc) -> TreeArc {
+  let mut tree = Tree::new(U16Size::new(20, 10));
+  insert_window(&mut tree, buffer);
+  Tree::to_arc(tree)
+}
+
+/// Create a 20x10 tree with a window shows the buffer of the lines.
+pub fn make_tree(lines: Vec<&str>) -> (TreeArc, BufferArc) {
+  let buffer = make_buffer_from_lines(lines);
+  (make_window_tree(&buffer), buffer)
+}
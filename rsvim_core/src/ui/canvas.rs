@@ -1,8 +1,9 @@
 //! Canvas.
 
-use crate::cart::{U16Pos, U16Size};
+use crate::cart::{U16Pos, U16Rect, U16Size};
 
 // Re-export
+pub use crate::ui::canvas::color::ColorSupport;
 pub use crate::ui::canvas::frame::cell::Cell;
 pub use crate::ui::canvas::frame::cursor::{
   cursor_style_eq, Cursor, CursorStyle, CursorStyleFormatter,
@@ -11,6 +12,7 @@ pub use crate::ui::canvas::frame::Frame;
 
 use compact_str::ToCompactString;
 use crossterm;
+use crossterm::style::{Attribute, Attributes, Color};
 use geo::point;
 use parking_lot::RwLock;
 use std::fmt;
@@ -19,6 +21,7 @@ use std::slice::Iter;
 use std::sync::Arc;
 use tracing::trace;
 
+pub mod color;
 pub mod frame;
 pub mod internal;
 
@@ -33,6 +36,11 @@ pub mod internal;
 pub struct Canvas {
   frame: Frame,
   prev_frame: Frame,
+  title: Option<String>,
+  prev_title: Option<String>,
+  color_scheme_version: usize,
+  color_support: ColorSupport,
+  repaint: bool,
 }
 
 pub type CanvasArc = Arc<RwLock<Canvas>>;
@@ -43,6 +51,11 @@ impl Canvas {
     Canvas {
       prev_frame: Frame::new(size, Cursor::default()),
       frame: Frame::new(size, Cursor::default()),
+      title: None,
+      prev_title: None,
+      color_scheme_version: 0,
+      color_support: ColorSupport::default(),
+      repaint: false,
     }
   }
 
@@ -107,6 +120,57 @@ impl Canvas {
 
   // Previous frame }
 
+  /// Get the terminal title.
+  pub fn title(&self) -> Option<&str> {
+    self.title.as_deref()
+  }
+
+  /// Set the terminal title, it's output on next [`shade`](Canvas::shade) only if it's changed,
+  /// thus the title escape sequence is not printed on every render.
+  pub fn set_title(&mut self, title: &str) {
+    self.title = Some(title.to_string());
+  }
+
+  /// Set the version of the color schemes, if it's changed, i.e. the color scheme is switched,
+  /// the whole frame is marked dirty thus all the rows are compared on next
+  /// [`shade`](Canvas::shade).
+  pub fn set_color_scheme_version(&mut self, version: usize) {
+    if self.color_scheme_version != version {
+      self.color_scheme_version = version;
+      self.frame.set_dirty_rows();
+    }
+  }
+
+  /// Clear the cells of current frame and mark the whole frame dirty, i.e. the whole screen is
+  /// redrawn, the cells not drawn by any widget are empty.
+  pub fn clear(&mut self) {
+    let size = self.size();
+    let n = size.width() as usize * size.height() as usize;
+    self.frame.set_empty_cells_at(U16Pos::new(0, 0), n);
+    self.frame.set_dirty_rows();
+  }
+
+  /// Blank the rectangle region of current frame, i.e. the stale cells left by a widget that's
+  /// shrunk or moved away. See [`Frame::clear_region`].
+  pub fn clear_region(&mut self, rect: U16Rect) {
+    self.frame.clear_region(rect, Cell::space());
+  }
+
+  /// Get the colors the terminal supports, by default is truecolor.
+  pub fn color_support(&self) -> ColorSupport {
+    self.color_support
+  }
+
+  /// Set the colors the terminal supports, the colors of the cells are downsampled to it on
+  /// [`shade`](Canvas::shade). If it's changed, all the cells are printed on next shade, since
+  /// the cells are unchanged but their output are changed.
+  pub fn set_color_support(&mut self, color_support: ColorSupport) {
+    if self.color_support != color_support {
+      self.color_support = color_support;
+      self.repaint = true;
+    }
+  }
+
   /// Get the shader commands that should print to the terminal device, it internally uses a
   /// diff-algorithm to reduce the outputs.
   pub fn shade(&mut self) -> Shader {
@@ -125,6 +189,15 @@ impl Canvas {
     let mut cursor_shaders = self._shade_cursor();
     shader.append(&mut cursor_shaders);
 
+    // For title
+    if self.title != self.prev_title {
+      if let Some(title) = &self.title {
+        shader.push(ShaderCommand::TerminalSetTitle(
+          crossterm::terminal::SetTitle(title.clone()),
+        ));
+      }
+    }
+
     // Finish shade.
     self._shade_done();
 
@@ -135,8 +208,10 @@ impl Canvas {
   pub fn _shade_done(&mut self) {
     // Save current frame.
     self.prev_frame = self.frame.clone();
+    self.prev_title = self.title.clone();
     // Reset the `dirty` fields.
     self.frame.reset_dirty_rows();
+    self.repaint = false;
   }
 
   /// Shade cursor and append results into shader vector.
@@ -181,7 +256,10 @@ impl Canvas {
 
   /// Shade cells and append results into shader vector.
   pub fn _shade_cells(&mut self) -> Vec<ShaderCommand> {
-    if self.size() == self.prev_size() {
+    if self.repaint {
+      // When the color support is changed, print all the cells.
+      self._repaint_all()
+    } else if self.size() == self.prev_size() {
       // When terminal size remains the same, use dirty-marks diff-algorithm.
       self._dirty_marks_diff()
     } else {
@@ -213,7 +291,30 @@ impl Canvas {
     col_end_at
   }
 
-  pub fn _make_print_shaders(&self, row: u16, start_col: u16, end_col: u16) -> Vec<ShaderCommand> {
+  /// Print all the cells on current frame, without comparing with previous frame.
+  pub fn _repaint_all(&self) -> Vec<ShaderCommand> {
+    let size = self.size();
+    let mut style = PrintStyle::default();
+    let mut shaders = vec![];
+    if size.width() > 0 {
+      for row in 0..size.height() {
+        let mut print_shaders = self._make_print_shaders(row, 0, size.width(), &mut style);
+        shaders.append(&mut print_shaders);
+      }
+    }
+    style.reset(&mut shaders);
+    shaders
+  }
+
+  /// Print the cells in the row between `[start_col, end_col)`, the `style` is the terminal style
+  /// before printing, and it is updated to the style after printing.
+  pub fn _make_print_shaders(
+    &self,
+    row: u16,
+    start_col: u16,
+    end_col: u16,
+    style: &mut PrintStyle,
+  ) -> Vec<ShaderCommand> {
     let frame = self.frame();
     let mut shaders = Vec::new();
 
@@ -222,23 +323,36 @@ impl Canvas {
       point!(x: start_col, y: row),
       end_col as usize - start_col as usize,
     );
-    let new_contents = new_cells
-      .iter()
-      .map(|c| {
-        if c.symbol().is_empty() {
-          " ".to_compact_string()
-        } else {
-          c.symbol().clone()
-        }
-      })
-      .collect::<Vec<_>>()
-      .join("");
     shaders.push(ShaderCommand::CursorMoveTo(crossterm::cursor::MoveTo(
       start_col, row,
     )));
-    shaders.push(ShaderCommand::StylePrintString(crossterm::style::Print(
-      new_contents.to_string(),
-    )));
+
+    // Print the continuous cells with the same style together, the style commands are only output
+    // when the style changes.
+    for cells in
+      new_cells.chunk_by(|a, b| a.fg() == b.fg() && a.bg() == b.bg() && a.attrs() == b.attrs())
+    {
+      let new_contents = cells
+        .iter()
+        .map(|c| {
+          if c.symbol().is_empty() {
+            " ".to_compact_string()
+          } else {
+            c.symbol().clone()
+          }
+        })
+        .collect::<Vec<_>>()
+        .join("");
+      let next_style = PrintStyle {
+        fg: self.color_support.downsample(cells[0].fg()),
+        bg: self.color_support.downsample(cells[0].bg()),
+        attrs: cells[0].attrs(),
+      };
+      style.switch_to(next_style, &mut shaders);
+      shaders.push(ShaderCommand::StylePrintString(crossterm::style::Print(
+        new_contents.to_string(),
+      )));
+    }
     shaders
   }
 
@@ -255,6 +369,7 @@ impl Canvas {
     trace!("brute force diff, size:{:?}", size);
 
     let mut shaders = vec![];
+    let mut style = PrintStyle::default();
 
     if !frame.zero_sized() {
       for row in 0..size.height() {
@@ -273,13 +388,14 @@ impl Canvas {
           let col_end_at = self._next_same_cell_in_row(row, col);
 
           if col_end_at > col {
-            let mut print_shaders = self._make_print_shaders(row, col, col_end_at);
+            let mut print_shaders = self._make_print_shaders(row, col, col_end_at, &mut style);
             shaders.append(&mut print_shaders);
             col = col_end_at;
           }
         }
       }
     }
+    style.reset(&mut shaders);
 
     shaders
   }
@@ -296,6 +412,7 @@ impl Canvas {
     trace!("dirty marks diff, size:{:?}", size);
 
     let mut shaders = vec![];
+    let mut style = PrintStyle::default();
 
     if !frame.zero_sized() {
       for (row, dirty) in frame.dirty_rows().iter().enumerate() {
@@ -315,7 +432,8 @@ impl Canvas {
             let col_end_at = self._next_same_cell_in_row(row as u16, col);
 
             if col_end_at > col {
-              let mut print_shaders = self._make_print_shaders(row as u16, col, col_end_at);
+              let mut print_shaders =
+                self._make_print_shaders(row as u16, col, col_end_at, &mut style);
               shaders.append(&mut print_shaders);
               col = col_end_at;
             }
@@ -323,11 +441,68 @@ impl Canvas {
         }
       }
     }
+    style.reset(&mut shaders);
 
     shaders
   }
 }
 
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+/// The style of the terminal while printing the cells. The style commands are only output when
+/// the style changes, thus the cells with the same style share one style setting, and the style is
+/// reset at the end of the frame so it never leaks to the terminal.
+pub struct PrintStyle {
+  pub fg: Color,
+  pub bg: Color,
+  pub attrs: Attributes,
+}
+
+impl Default for PrintStyle {
+  fn default() -> Self {
+    PrintStyle {
+      fg: Color::Reset,
+      bg: Color::Reset,
+      attrs: Attributes::default(),
+    }
+  }
+}
+
+impl PrintStyle {
+  /// Output the commands to switch the terminal style to `next`. The attributes cannot be unset
+  /// one by one, thus the style is reset first if any attribute is removed.
+  pub fn switch_to(&mut self, next: PrintStyle, shaders: &mut Vec<ShaderCommand>) {
+    if *self == next {
+      return;
+    }
+    if next == PrintStyle::default()
+      || (self.attrs != Attributes::default() && self.attrs != next.attrs)
+    {
+      self.reset(shaders);
+    }
+    if self.attrs != next.attrs {
+      shaders.push(ShaderCommand::StyleSetAttributes(
+        crossterm::style::SetAttributes(next.attrs),
+      ));
+    }
+    if self.fg != next.fg || self.bg != next.bg {
+      shaders.push(ShaderCommand::StyleSetColors(crossterm::style::SetColors(
+        crossterm::style::Colors::new(next.fg, next.bg),
+      )));
+    }
+    *self = next;
+  }
+
+  /// Output the command to reset the terminal style, if it's not the default style.
+  pub fn reset(&mut self, shaders: &mut Vec<ShaderCommand>) {
+    if *self != PrintStyle::default() {
+      shaders.push(ShaderCommand::StyleSetAttribute(
+        crossterm::style::SetAttribute(Attribute::Reset),
+      ));
+      *self = PrintStyle::default();
+    }
+  }
+}
+
 #[derive(Clone)]
 /// Shader command enums.
 ///
@@ -378,6 +553,7 @@ pub enum ShaderCommand {
   TerminalScrollDown(crossterm::terminal::ScrollDown),
   TerminalScrollUp(crossterm::terminal::ScrollUp),
   TerminalSetSize(crossterm::terminal::SetSize),
+  TerminalSetTitle(crossterm::terminal::SetTitle<String>),
 }
 
 impl fmt::Debug for ShaderCommand {
@@ -510,6 +686,9 @@ impl fmt::Debug for ShaderCommand {
       ShaderCommand::TerminalSetSize(command) => {
         format!("TerminalSetSize({:?})", command)
       }
+      ShaderCommand::TerminalSetTitle(command) => {
+        format!("TerminalSetTitle({:?})", command)
+      }
     };
     let s = format!("ShaderCommand::{}", s);
     f.debug_struct(&s).finish()
@@ -546,6 +725,15 @@ impl Shader {
   }
 }
 
+impl IntoIterator for Shader {
+  type Item = ShaderCommand;
+  type IntoIter = std::vec::IntoIter<ShaderCommand>;
+
+  fn into_iter(self) -> Self::IntoIter {
+    self.commands.into_iter()
+  }
+}
+
 #[cfg(test)]
 mod tests {
   use compact_str::CompactString;
@@ -596,6 +784,37 @@ mod tests {
     );
   }
 
+  #[test]
+  fn shade_title1() {
+    INIT.call_once(test_log_init);
+    let mut can = Canvas::new(U16Size::new(10, 10));
+    let titles = |shader: &Shader| {
+      shader
+        .iter()
+        .filter_map(|command| match command {
+          ShaderCommand::TerminalSetTitle(crossterm::terminal::SetTitle(title)) => {
+            Some(title.clone())
+          }
+          _ => None,
+        })
+        .collect::<Vec<_>>()
+    };
+
+    // No title is set.
+    assert!(titles(&can.shade()).is_empty());
+
+    can.set_title("a.txt — rsvim");
+    assert_eq!(titles(&can.shade()), vec!["a.txt — rsvim"]);
+    // The unchanged title is not output again.
+    can.set_title("a.txt — rsvim");
+    assert!(titles(&can.shade()).is_empty());
+    assert!(titles(&can.shade()).is_empty());
+
+    can.set_title("a.txt + — rsvim");
+    assert_eq!(titles(&can.shade()), vec!["a.txt + — rsvim"]);
+    assert_eq!(can.title(), Some("a.txt + — rsvim"));
+  }
+
   #[test]
   fn _shade_cursor1() {
     INIT.call_once(test_log_init);
@@ -848,7 +1067,7 @@ mod tests {
     let col = 2;
     let row = 3;
     let col_end_at = can._next_same_cell_in_row(row, col);
-    let shaders = can._make_print_shaders(row, col, col_end_at);
+    let shaders = can._make_print_shaders(row, col, col_end_at, &mut PrintStyle::default());
     info!("shader:{:?}", shaders);
     assert_eq!(shaders.len(), 2);
     assert!(matches!(
@@ -864,6 +1083,178 @@ mod tests {
     }
   }
 
+  #[test]
+  fn _make_print_shader2() {
+    INIT.call_once(test_log_init);
+    let mut can = Canvas::new(U16Size::new(10, 10));
+
+    let styled = |c: char| {
+      Cell::new(
+        c.to_compact_string(),
+        Color::Red,
+        Color::Reset,
+        Attributes::from(Attribute::Bold),
+      )
+    };
+    can.frame_mut().set_cells_at(
+      point!(x:2,y:3),
+      vec![
+        Cell::with_char('A'),
+        styled('B'),
+        styled('C'),
+        Cell::with_char('D'),
+      ],
+    );
+    let col = 2;
+    let row = 3;
+    let col_end_at = can._next_same_cell_in_row(row, col);
+    let shaders = can._make_print_shaders(row, col, col_end_at, &mut PrintStyle::default());
+    info!("shader:{:?}", shaders);
+    assert_eq!(shaders.len(), 7);
+    assert!(matches!(
+      shaders[0],
+      ShaderCommand::CursorMoveTo(crossterm::cursor::MoveTo(_, _))
+    ));
+    let contents = shaders
+      .iter()
+      .filter_map(|shader| match shader {
+        ShaderCommand::StylePrintString(crossterm::style::Print(contents)) => {
+          Some(contents.clone())
+        }
+        _ => None,
+      })
+      .collect::<Vec<_>>();
+    assert_eq!(contents, vec!["A", "BC", "D"]);
+    assert!(matches!(
+      shaders[2],
+      ShaderCommand::StyleSetAttributes(crossterm::style::SetAttributes(_))
+    ));
+    assert!(matches!(
+      shaders[3],
+      ShaderCommand::StyleSetColors(crossterm::style::SetColors(_))
+    ));
+    // The bold attribute is removed by resetting the style.
+    assert!(matches!(
+      shaders[5],
+      ShaderCommand::StyleSetAttribute(crossterm::style::SetAttribute(Attribute::Reset))
+    ));
+  }
+
+  #[test]
+  fn _make_print_shader3() {
+    INIT.call_once(test_log_init);
+    let mut can = Canvas::new(U16Size::new(30, 2));
+    can.frame_mut().set_cells_at(
+      point!(x:5,y:1),
+      (0..20).map(|_| Cell::with_char('a')).collect::<Vec<_>>(),
+    );
+    can._shade_done();
+
+    // Only the colors are changed, the run of cells is printed with one style setting.
+    can.frame_mut().set_cells_at(
+      point!(x:5,y:1),
+      (0..20)
+        .map(|_| {
+          Cell::new(
+            CompactString::new("a"),
+            Color::Red,
+            Color::Blue,
+            Attributes::default(),
+          )
+        })
+        .collect::<Vec<_>>(),
+    );
+    let shaders = can._dirty_marks_diff();
+    info!("shader:{:?}", shaders);
+    assert_eq!(shaders.len(), 4);
+    assert!(matches!(
+      shaders[0],
+      ShaderCommand::CursorMoveTo(crossterm::cursor::MoveTo(5, 1))
+    ));
+    match &shaders[1] {
+      ShaderCommand::StyleSetColors(crossterm::style::SetColors(colors)) => {
+        assert_eq!(colors.foreground, Some(Color::Red));
+        assert_eq!(colors.background, Some(Color::Blue));
+      }
+      _ => unreachable!(),
+    }
+    match &shaders[2] {
+      ShaderCommand::StylePrintString(crossterm::style::Print(contents)) => {
+        assert_eq!(*contents, "a".repeat(20));
+      }
+      _ => unreachable!(),
+    }
+    // The style is reset at the end of the frame.
+    assert!(matches!(
+      shaders[3],
+      ShaderCommand::StyleSetAttribute(crossterm::style::SetAttribute(Attribute::Reset))
+    ));
+    assert_eq!(can._brute_force_diff().len(), 4);
+  }
+
+  #[test]
+  fn color_scheme_version1() {
+    INIT.call_once(test_log_init);
+    let mut can = Canvas::new(U16Size::new(10, 3));
+    can._shade_done();
+    assert!(can.frame().dirty_rows().iter().all(|dirty| !*dirty));
+
+    // The whole frame is dirty when the version changes.
+    can.set_color_scheme_version(1);
+    assert!(can.frame().dirty_rows().iter().all(|dirty| *dirty));
+    can._shade_done();
+    can.set_color_scheme_version(1);
+    assert!(can.frame().dirty_rows().iter().all(|dirty| !*dirty));
+  }
+
+  #[test]
+  fn color_support1() {
+    INIT.call_once(test_log_init);
+    let mut can = Canvas::new(U16Size::new(3, 2));
+    let rgb = Color::Rgb {
+      r: 0xff,
+      g: 0x80,
+      b: 0x00,
+    };
+    can.frame_mut().set_cells_at(
+      point!(x:0,y:0),
+      vec![Cell::new(
+        CompactString::new("A"),
+        rgb,
+        Color::Reset,
+        Attributes::default(),
+      )],
+    );
+    let colors = |shader: &Shader| {
+      shader
+        .iter()
+        .filter_map(|command| match command {
+          ShaderCommand::StyleSetColors(crossterm::style::SetColors(colors)) => colors.foreground,
+          _ => None,
+        })
+        .collect::<Vec<_>>()
+    };
+
+    // The truecolor passes through the RGB colors.
+    assert_eq!(colors(&can.shade()), vec![rgb]);
+
+    // All the cells are printed again with the downsampled colors.
+    can.set_color_support(ColorSupport::Ansi256);
+    let shader = can.shade();
+    assert_eq!(colors(&shader), vec![Color::AnsiValue(208)]);
+    let contents = shader
+      .iter()
+      .filter_map(|command| match command {
+        ShaderCommand::StylePrintString(crossterm::style::Print(contents)) => {
+          Some(contents.clone())
+        }
+        _ => None,
+      })
+      .collect::<Vec<_>>();
+    assert_eq!(contents, vec!["A", "  ", "   "]);
+    assert!(colors(&can.shade()).is_empty());
+  }
+
   #[test]
   fn diff1() {
     INIT.call_once(test_log_init);
@@ -0,0 +1,191 @@
+//! The terminal color support, i.e. truecolor, 256 colors or 16 colors.
+
+use crossterm::style::Color;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+/// The colors the terminal supports, the colors are downsampled to the nearest supported color
+/// when printing to the terminal.
+pub enum ColorSupport {
+  /// The 24-bit RGB colors.
+  #[default]
+  TrueColor,
+  /// The 256 colors palette.
+  Ansi256,
+  /// The 16 ANSI colors.
+  Ansi16,
+}
+
+// The RGB values of the 16 ANSI colors, same with the xterm defaults.
+const ANSI_16: [(Color, (u8, u8, u8)); 16] = [
+  (Color::Black, (0, 0, 0)),
+  (Color::DarkRed, (205, 0, 0)),
+  (Color::DarkGreen, (0, 205, 0)),
+  (Color::DarkYellow, (205, 205, 0)),
+  (Color::DarkBlue, (0, 0, 238)),
+  (Color::DarkMagenta, (205, 0, 205)),
+  (Color::DarkCyan, (0, 205, 205)),
+  (Color::Grey, (229, 229, 229)),
+  (Color::DarkGrey, (127, 127, 127)),
+  (Color::Red, (255, 0, 0)),
+  (Color::Green, (0, 255, 0)),
+  (Color::Yellow, (255, 255, 0)),
+  (Color::Blue, (92, 92, 255)),
+  (Color::Magenta, (255, 0, 255)),
+  (Color::Cyan, (0, 255, 255)),
+  (Color::White, (255, 255, 255)),
+];
+
+// The levels of the 6x6x6 color cube, i.e. the index `16 + 36 * r + 6 * g + b`.
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+impl ColorSupport {
+  /// Detect the color support by the `COLORTERM` and `TERM` environment variables:
+  ///
+  /// 1. `COLORTERM` is `truecolor` or `24bit`, or `TERM` ends with `-direct`: truecolor.
+  /// 2. `TERM` contains `256color`: 256 colors.
+  /// 3. Otherwise: 16 colors.
+  pub fn detect(colorterm: Option<&str>, term: Option<&str>) -> Self {
+    match (colorterm, term) {
+      (Some("truecolor" | "24bit"), _) => ColorSupport::TrueColor,
+      (_, Some(term)) if term.ends_with("-direct") => ColorSupport::TrueColor,
+      (_, Some(term)) if term.contains("256color") => ColorSupport::Ansi256,
+      _ => ColorSupport::Ansi16,
+    }
+  }
+
+  /// Downsample the color to the nearest supported color, the named colors are unchanged.
+  pub fn downsample(&self, color: Color) -> Color {
+    match (self, color) {
+      (ColorSupport::TrueColor, _) => color,
+      (ColorSupport::Ansi256, Color::Rgb { r, g, b }) => color_256((r, g, b)),
+      (ColorSupport::Ansi16, Color::Rgb { r, g, b }) => color_16((r, g, b)),
+      (ColorSupport::Ansi16, Color::AnsiValue(value)) => color_16(ansi_rgb(value)),
+      _ => color,
+    }
+  }
+}
+
+// The squared distance between two RGB colors.
+fn distance((r1, g1, b1): (u8, u8, u8), (r2, g2, b2): (u8, u8, u8)) -> i32 {
+  [(r1, r2), (g1, g2), (b1, b2)]
+    .iter()
+    .map(|(c1, c2)| (*c1 as i32 - *c2 as i32).pow(2))
+    .sum()
+}
+
+// The RGB value of the 256 colors palette.
+fn ansi_rgb(value: u8) -> (u8, u8, u8) {
+  match value {
+    0..16 => ANSI_16[value as usize].1,
+    16..232 => {
+      let i = (value - 16) as usize;
+      (
+        CUBE_LEVELS[i / 36],
+        CUBE_LEVELS[(i / 6) % 6],
+        CUBE_LEVELS[i % 6],
+      )
+    }
+    _ => {
+      let gray = 8 + 10 * (value - 232);
+      (gray, gray, gray)
+    }
+  }
+}
+
+/// Convert the RGB color to the nearest color of the 256 colors palette, i.e. the 6x6x6 color cube
+/// or the grayscale ramp.
+pub fn color_256(rgb: (u8, u8, u8)) -> Color {
+  let (r, g, b) = rgb;
+  let nearest_level = |c: u8| {
+    (0..CUBE_LEVELS.len())
+      .min_by_key(|i| (CUBE_LEVELS[*i] as i32 - c as i32).abs())
+      .unwrap()
+  };
+  let (ri, gi, bi) = (nearest_level(r), nearest_level(g), nearest_level(b));
+  let cube = (CUBE_LEVELS[ri], CUBE_LEVELS[gi], CUBE_LEVELS[bi]);
+  // The grayscale ramp, i.e. the index `232 + i` is the gray `8 + 10 * i`.
+  let average = (r as i32 + g as i32 + b as i32) / 3;
+  let gray_idx = ((average - 8 + 5) / 10).clamp(0, 23);
+  let gray = (8 + 10 * gray_idx) as u8;
+  if distance(rgb, (gray, gray, gray)) < distance(rgb, cube) {
+    Color::AnsiValue(232 + gray_idx as u8)
+  } else {
+    Color::AnsiValue((16 + 36 * ri + 6 * gi + bi) as u8)
+  }
+}
+
+/// Convert the RGB color to the nearest color of the 16 ANSI colors.
+pub fn color_16(rgb: (u8, u8, u8)) -> Color {
+  ANSI_16
+    .iter()
+    .min_by_key(|(_, ansi)| distance(rgb, *ansi))
+    .map(|(color, _)| *color)
+    .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn detect1() {
+    let detect = ColorSupport::detect;
+    assert_eq!(
+      detect(Some("truecolor"), Some("xterm-256color")),
+      ColorSupport::TrueColor
+    );
+    assert_eq!(detect(Some("24bit"), None), ColorSupport::TrueColor);
+    assert_eq!(detect(None, Some("xterm-direct")), ColorSupport::TrueColor);
+    assert_eq!(detect(None, Some("xterm-256color")), ColorSupport::Ansi256);
+    assert_eq!(detect(Some(""), Some("xterm")), ColorSupport::Ansi16);
+    assert_eq!(detect(None, None), ColorSupport::Ansi16);
+  }
+
+  #[test]
+  fn downsample1() {
+    let rgb = |r, g, b| Color::Rgb { r, g, b };
+
+    // The truecolor passes through the RGB colors.
+    let truecolor = ColorSupport::TrueColor;
+    assert_eq!(
+      truecolor.downsample(rgb(0xff, 0x80, 0x00)),
+      rgb(0xff, 0x80, 0x00)
+    );
+    assert_eq!(truecolor.downsample(Color::Red), Color::Red);
+
+    let ansi256 = ColorSupport::Ansi256;
+    assert_eq!(ansi256.downsample(rgb(0, 0, 0)), Color::AnsiValue(16));
+    assert_eq!(
+      ansi256.downsample(rgb(255, 255, 255)),
+      Color::AnsiValue(231)
+    );
+    assert_eq!(
+      ansi256.downsample(rgb(0xff, 0x80, 0x00)),
+      Color::AnsiValue(208)
+    );
+    assert_eq!(
+      ansi256.downsample(rgb(0x87, 0xaf, 0xd7)),
+      Color::AnsiValue(110)
+    );
+    // The gray is nearer to the grayscale ramp.
+    assert_eq!(
+      ansi256.downsample(rgb(128, 128, 128)),
+      Color::AnsiValue(244)
+    );
+    assert_eq!(ansi256.downsample(rgb(30, 30, 30)), Color::AnsiValue(234));
+    // The other colors are unchanged.
+    assert_eq!(ansi256.downsample(Color::Red), Color::Red);
+    assert_eq!(
+      ansi256.downsample(Color::AnsiValue(208)),
+      Color::AnsiValue(208)
+    );
+
+    let ansi16 = ColorSupport::Ansi16;
+    assert_eq!(ansi16.downsample(rgb(250, 10, 10)), Color::Red);
+    assert_eq!(ansi16.downsample(rgb(200, 0, 0)), Color::DarkRed);
+    assert_eq!(ansi16.downsample(rgb(128, 128, 128)), Color::DarkGrey);
+    assert_eq!(ansi16.downsample(Color::AnsiValue(196)), Color::Red);
+    assert_eq!(ansi16.downsample(Color::AnsiValue(4)), Color::DarkBlue);
+    assert_eq!(ansi16.downsample(Color::Red), Color::Red);
+  }
+}
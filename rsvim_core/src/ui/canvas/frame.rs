@@ -5,7 +5,7 @@ use geo::point;
 use std::ops::Range;
 // use tracing::debug;
 
-use crate::cart::{U16Pos, U16Size};
+use crate::cart::{U16Pos, U16Rect, U16Size};
 use crate::ui::canvas::frame::cell::Cell;
 use crate::ui::canvas::frame::cursor::Cursor;
 use crate::ui::canvas::internal::iframe::Iframe;
@@ -218,6 +218,12 @@ impl Frame {
     self.iframe.try_set_empty_cells_at(pos, n)
   }
 
+  /// Fill the rectangle region with the cell (i.e. [`Cell::space`] to blank it), and mark the rows
+  /// dirty. The part outside of the frame is ignored.
+  pub fn clear_region(&mut self, rect: U16Rect, cell: Cell) {
+    self.iframe.clear_region(rect, cell)
+  }
+
   /// Get dirty rows.
   pub fn dirty_rows(&self) -> &Vec<bool> {
     self.iframe.dirty_rows()
@@ -230,6 +236,11 @@ impl Frame {
     self.iframe.reset_dirty_rows()
   }
 
+  /// Mark all the rows dirty, i.e. the whole frame is compared on next flush.
+  pub fn set_dirty_rows(&mut self) {
+    self.iframe.set_dirty_rows()
+  }
+
   /// Get cursor.
   pub fn cursor(&self) -> &Cursor {
     &self.cursor
@@ -546,4 +557,31 @@ mod tests {
       assert_eq!(actual, expect);
     }
   }
+
+  #[test]
+  fn clear_region1() {
+    let frame_size = U16Size::new(10, 10);
+    let mut frame = Frame::new(frame_size, Cursor::default());
+    frame.set_cells_at(point!(x: 0, y: 0), vec![Cell::with_char('a'); 100]);
+    frame.reset_dirty_rows();
+
+    frame.clear_region(U16Rect::new((2, 3), (5, 6)), Cell::with_char('x'));
+    let symbols = frame.raw_symbols();
+    for (y, row) in symbols.iter().enumerate() {
+      for (x, symbol) in row.iter().enumerate() {
+        let cleared = (2..5).contains(&x) && (3..6).contains(&y);
+        assert_eq!(symbol, if cleared { "x" } else { "a" });
+      }
+    }
+    let dirty_rows = frame.dirty_rows();
+    for (y, dirty) in dirty_rows.iter().enumerate() {
+      assert_eq!(*dirty, (3..6).contains(&y));
+    }
+
+    // The part outside of the frame is ignored.
+    frame.clear_region(U16Rect::new((8, 8), (12, 12)), Cell::space());
+    assert_eq!(frame.raw_symbols()[9].join(""), "aaaaaaaa  ");
+    frame.clear_region(U16Rect::new((10, 0), (12, 2)), Cell::space());
+    assert_eq!(frame.raw_symbols()[0].join(""), "aaaaaaaaaa");
+  }
 }
@@ -4,6 +4,7 @@
 
 use compact_str::{CompactString, ToCompactString};
 use crossterm::style::{Attributes, Color};
+use unicode_width::UnicodeWidthStr;
 
 #[derive(Debug, Clone, Eq, PartialEq)]
 /// Single character/grapheme rendering unit, it accepts ansi/unicode/emoji/nerd font symbol.
@@ -16,6 +17,9 @@ pub struct Cell {
   bg: Color,
   // Attributes: underline, bold, italic, etc.
   attrs: Attributes,
+  // Whether it's the guard cell of a wide symbol, i.e. the trailing cells after a symbol wider
+  // than 1 column (CJK, expanded tab, etc).
+  wide_guard: bool,
 }
 
 impl Cell {
@@ -24,19 +28,32 @@ impl Cell {
     &self.symbol
   }
 
-  /// Set symbol.
+  /// Set symbol, it's no longer a guard cell.
   pub fn set_symbol(&mut self, symbol: CompactString) {
     self.symbol = symbol;
+    self.wide_guard = false;
   }
 
-  /// Set symbol by char.
+  /// Set symbol by char, it's no longer a guard cell.
   pub fn set_char(&mut self, ch: char) {
     self.symbol = ch.to_compact_string();
+    self.wide_guard = false;
   }
 
-  /// Set symbol by str.
+  /// Set symbol by str, it's no longer a guard cell.
   pub fn set_str(&mut self, s: &str) {
     self.symbol = CompactString::new(s);
+    self.wide_guard = false;
+  }
+
+  /// Get the display width of the symbol, i.e. how many columns it occupies in the terminal.
+  pub fn symbol_width(&self) -> usize {
+    self.symbol.width()
+  }
+
+  /// Whether it's the guard cell of a wide symbol, see [`Cell::wide_guard`].
+  pub fn is_wide_guard(&self) -> bool {
+    self.wide_guard
   }
 
   /// Get foreground color.
@@ -85,6 +102,7 @@ impl Cell {
       fg,
       bg,
       attrs,
+      wide_guard: false,
     }
   }
 
@@ -95,6 +113,7 @@ impl Cell {
       fg: Color::Reset,
       bg: Color::Reset,
       attrs: Attributes::default(),
+      wide_guard: false,
     }
   }
 
@@ -105,6 +124,7 @@ impl Cell {
       fg: Color::Reset,
       bg: Color::Reset,
       attrs: Attributes::default(),
+      wide_guard: false,
     }
   }
 
@@ -114,6 +134,22 @@ impl Cell {
       fg: Color::Reset,
       bg: Color::Reset,
       attrs: Attributes::default(),
+      wide_guard: false,
+    }
+  }
+
+  /// Make the guard cell of the wide `symbol` cell, it's placed in the trailing columns of the
+  /// wide symbol. It has an empty symbol (thus prints nothing) and the same style with the symbol.
+  ///
+  /// NOTE: The [`Frame`](crate::ui::canvas::Frame) writes the guard cells when a wide symbol is
+  /// set, and clears the orphaned ones when the wide symbol is overwritten.
+  pub fn wide_guard(symbol: &Cell) -> Self {
+    Cell {
+      symbol: CompactString::const_new(""),
+      fg: symbol.fg,
+      bg: symbol.bg,
+      attrs: symbol.attrs,
+      wide_guard: true,
     }
   }
 
@@ -123,6 +159,7 @@ impl Cell {
       fg: Color::Reset,
       bg: Color::Reset,
       attrs: Attributes::default(),
+      wide_guard: false,
     }
   }
 }
@@ -183,4 +220,23 @@ mod tests {
       assert!(cs[0] == expect);
     }
   }
+
+  #[test]
+  fn wide_guard1() {
+    let mut c = Cell::new("你".into(), Color::Red, Color::Reset, Attributes::default());
+    assert_eq!(c.symbol_width(), 2);
+    assert!(!c.is_wide_guard());
+
+    let mut guard = Cell::wide_guard(&c);
+    assert!(guard.is_wide_guard());
+    assert_eq!(guard.symbol(), "");
+    assert_eq!(guard.symbol_width(), 0);
+    assert_eq!(guard.fg(), Color::Red);
+    assert_ne!(guard, Cell::empty());
+
+    guard.set_char(' ');
+    assert!(!guard.is_wide_guard());
+    c.set_char('a');
+    assert_eq!(c.symbol_width(), 1);
+  }
 }
@@ -5,7 +5,7 @@ use geo::point;
 use std::ops::Range;
 use tracing::trace;
 
-use crate::cart::{U16Pos, U16Size};
+use crate::cart::{U16Pos, U16Rect, U16Size};
 use crate::ui::canvas::frame::cell::Cell;
 
 #[derive(Debug, Clone)]
@@ -166,7 +166,7 @@ impl Iframe {
         cell,
         old_cell
       );
-      self.cells[index] = cell;
+      self.put_cell(index, cell);
       self.dirty_rows[pos.y() as usize] = true;
       Some(old_cell)
     } else {
@@ -288,12 +288,71 @@ impl Iframe {
         pos.y(),
         end_at.y() + 1
       );
-      Some(self.cells.splice(range, cells).collect())
+      let old_cells = self.cells[range.clone()].to_vec();
+      for (index, cell) in range.zip(cells) {
+        self.put_cell(index, cell);
+      }
+      Some(old_cells)
     } else {
       None
     }
   }
 
+  // Wide symbols {
+
+  // Put the cell at the index, and keep the wide symbols consistent in the row, i.e. a wide symbol
+  // is always followed by its guard cells:
+  //
+  // 1. If a guard cell is overwritten, its wide symbol is cut and blanked.
+  // 2. If a wide symbol is overwritten, its orphaned guard cells are blanked.
+  // 3. If the cell is a wide symbol, its guard cells are written to the following columns. If it
+  //    doesn't fit in the rest of the row, it's blanked.
+  fn put_cell(&mut self, index: usize, cell: Cell) {
+    let width = self.size.width() as usize;
+    let row_start = index - index % width;
+    let row_end = row_start + width;
+
+    let old_cell = &self.cells[index];
+    if old_cell.is_wide_guard() && !cell.is_wide_guard() {
+      let mut symbol_index = index;
+      while symbol_index > row_start && self.cells[symbol_index].is_wide_guard() {
+        symbol_index -= 1;
+      }
+      if !self.cells[symbol_index].is_wide_guard() {
+        self.blank_wide_symbol(symbol_index, row_end);
+      }
+    } else if old_cell.symbol_width() > 1 {
+      self.blank_wide_symbol(index, row_end);
+    }
+
+    let symbol_width = cell.symbol_width();
+    if symbol_width > 1 {
+      if index + symbol_width > row_end {
+        self.cells[index] = blank(&cell);
+        return;
+      }
+      let guard = Cell::wide_guard(&cell);
+      self.cells[index] = cell;
+      for guard_index in index + 1..index + symbol_width {
+        self.put_cell(guard_index, guard.clone());
+      }
+    } else {
+      self.cells[index] = cell;
+    }
+  }
+
+  // Blank the wide symbol at the index and all its guard cells.
+  fn blank_wide_symbol(&mut self, index: usize, row_end: usize) {
+    self.cells[index] = blank(&self.cells[index]);
+    let mut guard_index = index + 1;
+    while guard_index < row_end && self.cells[guard_index].is_wide_guard() {
+      self.cells[guard_index] = blank(&self.cells[guard_index]);
+      guard_index += 1;
+    }
+  }
+
+  // Wide symbols }
+
   /// Set (replace) empty cells at a range.
   ///
   /// # Panics
@@ -309,6 +368,19 @@ impl Iframe {
     self.try_set_cells_at(pos, vec![Cell::empty(); n])
   }
 
+  /// Fill the rectangle region with the cell, the part outside of the frame is ignored.
+  pub fn clear_region(&mut self, rect: U16Rect, cell: Cell) {
+    let right = std::cmp::min(rect.max().x, self.size.width());
+    let bottom = std::cmp::min(rect.max().y, self.size.height());
+    if rect.min().x >= right {
+      return;
+    }
+    let width = (right - rect.min().x) as usize;
+    for y in rect.min().y..bottom {
+      self.set_cells_at(point!(x: rect.min().x, y: y), vec![cell.clone(); width]);
+    }
+  }
+
   /// Get dirty rows.
   pub fn dirty_rows(&self) -> &Vec<bool> {
     &self.dirty_rows
@@ -320,6 +392,18 @@ impl Iframe {
   pub fn reset_dirty_rows(&mut self) {
     self.dirty_rows = vec![false; self.size.height() as usize];
   }
+
+  /// Mark all the rows dirty.
+  pub fn set_dirty_rows(&mut self) {
+    self.dirty_rows = vec![true; self.size.height() as usize];
+  }
+}
+
+// The space cell in the same style.
+fn blank(cell: &Cell) -> Cell {
+  let mut cell = cell.clone();
+  cell.set_char(' ');
+  cell
 }
 
 #[cfg(test)]
@@ -642,4 +726,47 @@ mod tests {
       assert_eq!(actual, expect);
     }
   }
+
+  #[test]
+  fn wide_symbol1() {
+    let mut frame = Iframe::new(U16Size::new(6, 2));
+    let symbols = |frame: &Iframe, y: usize| frame.raw_symbols()[y].join("|");
+    let guards = |frame: &Iframe, y: u16| {
+      (0..6)
+        .map(|x| frame.get_cell(point!(x: x, y: y)).is_wide_guard())
+        .collect::<Vec<_>>()
+    };
+
+    // The wide symbols are followed by the guard cells.
+    frame.set_cell(point!(x: 0, y: 0), Cell::with_char('你'));
+    frame.set_cell(point!(x: 2, y: 0), Cell::with_char('好'));
+    assert_eq!(symbols(&frame, 0), "你||好|||");
+    assert_eq!(
+      guards(&frame, 0),
+      vec![false, true, false, true, false, false]
+    );
+
+    // Overwrite a wide symbol with a narrow one, the orphaned guard cell is cleared.
+    frame.set_cell(point!(x: 0, y: 0), Cell::with_char('a'));
+    assert_eq!(symbols(&frame, 0), "a| |好|||");
+    assert_eq!(
+      guards(&frame, 0),
+      vec![false, false, false, true, false, false]
+    );
+
+    // Overwrite the guard cell, the cut wide symbol is cleared.
+    frame.set_cell(point!(x: 3, y: 0), Cell::with_char('b'));
+    assert_eq!(symbols(&frame, 0), "a| | |b||");
+    assert_eq!(guards(&frame, 0), vec![false; 6]);
+
+    // A wide symbol overlaps the next wide symbol, and the one doesn't fit in the row is blank.
+    frame.set_cells_at(point!(x: 1, y: 1), vec![Cell::with_char('世')]);
+    frame.set_cell(point!(x: 2, y: 1), Cell::with_char('界'));
+    frame.set_cell(point!(x: 5, y: 1), Cell::with_char('你'));
+    assert_eq!(symbols(&frame, 1), "| |界||| ");
+    assert_eq!(
+      guards(&frame, 1),
+      vec![false, false, false, true, false, false]
+    );
+  }
 }
@@ -2,18 +2,24 @@
 
 #![allow(dead_code)]
 
+use crate::buf::{BufferId, BufferWk};
 use crate::cart::{IRect, U16Rect, U16Size};
 use crate::envar;
-use crate::ui::canvas::{Canvas, CanvasArc};
-use crate::ui::tree::internal::{InodeId, Inodeable, Itree};
-use crate::ui::widget::window::WindowLocalOptions;
-use crate::ui::widget::{Cursor, RootContainer, Widgetable, Window};
+use crate::res::{TreeErr, TreeResult};
+use crate::state::message::Message;
+use crate::ui::canvas::{Canvas, CanvasArc, CursorStyle};
+use crate::ui::tree::internal::{InodeId, Inodeable, Itree, ItreeIter};
+use crate::ui::widget::window::{
+  FillChars, FoldMarker, FoldMethod, ListChars, SignColumn, WindowLocalOptions,
+};
+use crate::ui::widget::{Cursor, MessageArea, RootContainer, TabLine, Widgetable, Window};
+use crate::{rlock, wlock};
 
 // Re-export
 pub use crate::ui::tree::opt::{WindowGlobalOptions, WindowGlobalOptionsBuilder};
 
 use parking_lot::RwLock;
-use std::collections::BTreeSet;
+use std::collections::{BTreeSet, HashMap};
 use std::sync::{Arc, Weak};
 // use tracing::trace;
 
@@ -26,6 +32,8 @@ pub enum TreeNode {
   RootContainer(RootContainer),
   Window(Window),
   Cursor(Cursor),
+  MessageArea(MessageArea),
+  TabLine(TabLine),
 }
 
 macro_rules! tree_node_generate_dispatch {
@@ -34,6 +42,8 @@ macro_rules! tree_node_generate_dispatch {
       TreeNode::RootContainer(n) => n.$method_name(),
       TreeNode::Window(n) => n.$method_name(),
       TreeNode::Cursor(n) => n.$method_name(),
+      TreeNode::MessageArea(n) => n.$method_name(),
+      TreeNode::TabLine(n) => n.$method_name(),
     }
   };
 }
@@ -44,6 +54,8 @@ impl TreeNode {
       TreeNode::RootContainer(n) => n.id(),
       TreeNode::Window(n) => n.id(),
       TreeNode::Cursor(n) => n.id(),
+      TreeNode::MessageArea(n) => n.id(),
+      TreeNode::TabLine(n) => n.id(),
     }
   }
 }
@@ -109,6 +121,8 @@ impl Widgetable for TreeNode {
       TreeNode::RootContainer(w) => w.draw(canvas),
       TreeNode::Window(w) => w.draw(canvas),
       TreeNode::Cursor(w) => w.draw(canvas),
+      TreeNode::MessageArea(w) => w.draw(canvas),
+      TreeNode::TabLine(w) => w.draw(canvas),
     }
   }
 }
@@ -218,8 +232,36 @@ pub struct Tree {
 
   // All [`Window`](crate::ui::widget::Window) node IDs.
   window_ids: BTreeSet<TreeNodeId>,
+
+  // The cursor shapes of the unfocused windows, restored when the window is focused again.
+  window_cursor_shapes: HashMap<TreeNodeId, IRect>,
+
+  // Whether the windows are resized manually, the 'equalalways' doesn't apply on closing windows
+  // until the next split.
+  manual_resized: bool,
   // Cursor and window state }
 
+  // [`MessageArea`](crate::ui::widget::MessageArea) node ID.
+  message_area_id: Option<TreeNodeId>,
+
+  // Tab pages state {
+
+  // All the tab pages, the windows of the active one are in the tree, thus its entry is empty.
+  tab_pages: Vec<TabPage>,
+
+  // The index of the active tab page.
+  active_tab: usize,
+
+  // [`TabLine`](crate::ui::widget::TabLine) node ID, it's shown when there're multiple tab pages.
+  tab_line_id: Option<TreeNodeId>,
+
+  // Whether the whole screen needs to redraw, i.e. the active tab page is switched.
+  redraw: bool,
+  // Tab pages state }
+
+  // The regions left by the windows that are shrunk or moved, they're cleared before next draw.
+  stale_regions: Vec<U16Rect>,
+
   // Global options for windows.
   global_options: WindowGlobalOptions,
 
@@ -252,6 +294,14 @@ impl Tree {
       base: Itree::new(root_node),
       cursor_id: None,
       window_ids: BTreeSet::new(),
+      window_cursor_shapes: HashMap::new(),
+      manual_resized: false,
+      message_area_id: None,
+      tab_pages: vec![TabPage::default()],
+      active_tab: 0,
+      tab_line_id: None,
+      redraw: false,
+      stale_regions: vec![],
       global_options: WindowGlobalOptions::default(),
       local_options: WindowLocalOptions::default(),
     }
@@ -322,9 +372,67 @@ impl Tree {
     self.cursor_id = cursor_id;
   }
 
-  /// Get current window node ID.
+  /// Set the style of the cursor widget, i.e. it follows the editing mode, see
+  /// [`Mode::cursor_style`](crate::state::mode::Mode::cursor_style).
+  pub fn set_cursor_style(&mut self, style: CursorStyle) {
+    if let Some(TreeNode::Cursor(cursor)) = self.cursor_id.and_then(|id| self.base.node_mut(&id)) {
+      cursor.set_style(style);
+    }
+  }
+
+  /// Get current window node ID, i.e. the [focused window](Tree::focused_window_id).
   /// NOTE: A window is called the current window because it has cursor inside it.
   pub fn current_window_id(&self) -> Option<TreeNodeId> {
+    self.focused_window_id()
+  }
+
+  /// Get all the window widget IDs.
+  pub fn window_ids(&self) -> &BTreeSet<TreeNodeId> {
+    &self.window_ids
+  }
+
+  /// Get the message area node ID.
+  pub fn message_area_id(&self) -> Option<TreeNodeId> {
+    self.message_area_id
+  }
+
+  /// Set the message shown in the message area widget, it does nothing if there's no message
+  /// area.
+  pub fn set_message(&mut self, message: Option<Message>) {
+    if let Some(message_area_id) = self.message_area_id {
+      if let Some(TreeNode::MessageArea(message_area)) = self.base.node_mut(&message_area_id) {
+        message_area.set_message(message);
+      }
+    }
+  }
+}
+// Node {
+
+// Focus {
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+/// The direction to move the window focus, see [`Tree::move_focus`].
+pub enum FocusDirection {
+  /// The next window in tree order, i.e. `Ctrl-W w`.
+  Next,
+  /// The previous window in tree order, i.e. `Ctrl-W W`.
+  Prev,
+  /// The window on the left side, i.e. `Ctrl-W h`.
+  Left,
+  /// The window below, i.e. `Ctrl-W j`.
+  Down,
+  /// The window above, i.e. `Ctrl-W k`.
+  Up,
+  /// The window on the right side, i.e. `Ctrl-W l`.
+  Right,
+}
+
+impl Tree {
+  /// Get the focused window node ID.
+  ///
+  /// The focused window is the one receives the keys, it's the window that owns the cursor
+  /// widget.
+  pub fn focused_window_id(&self) -> Option<TreeNodeId> {
     if let Some(cursor_id) = self.cursor_id {
       let mut id = cursor_id;
       while let Some(parent_id) = self.parent_id(&id) {
@@ -338,19 +446,921 @@ impl Tree {
     None
   }
 
-  /// Get all the window widget IDs.
-  pub fn window_ids(&self) -> &BTreeSet<TreeNodeId> {
-    &self.window_ids
+  /// Set the focused window, the cursor widget is re-parented to the window.
+  ///
+  /// The cursor position and the view state (see [`Window::save_view_state`]) in the previous
+  /// focused window are saved, and restored when it is focused again. A window that never been
+  /// focused places the cursor at its top-left corner.
+  ///
+  /// # Returns
+  ///
+  /// It returns `false` if the `window_id` is not a window, or there's no cursor widget.
+  pub fn set_focused_window_id(&mut self, window_id: TreeNodeId) -> bool {
+    if !self.window_ids.contains(&window_id) {
+      return false;
+    }
+    let cursor_id = match self.cursor_id {
+      Some(cursor_id) => cursor_id,
+      None => return false,
+    };
+
+    if let Some(focused_window_id) = self.focused_window_id() {
+      if focused_window_id == window_id {
+        return true;
+      }
+      let cursor_shape = *self.node(&cursor_id).unwrap().shape();
+      self
+        .window_cursor_shapes
+        .insert(focused_window_id, cursor_shape);
+      if let Some(TreeNode::Window(window)) = self.node_mut(&focused_window_id) {
+        window.save_view_state();
+      }
+    }
+
+    let mut cursor_node = self.base.remove(cursor_id).unwrap();
+    *cursor_node.shape_mut() = match self.window_cursor_shapes.remove(&window_id) {
+      Some(cursor_shape) => cursor_shape,
+      None => self.text_top_left(window_id),
+    };
+    self.bounded_insert(&window_id, cursor_node).unwrap();
+    true
+  }
+
+  /// Move the focus to the next/previous window in tree order (i.e. by the window IDs), it wraps
+  /// around at the last/first window. Or move the focus to the adjacent window in the direction,
+  /// see [`Tree::adjacent_window_id`].
+  ///
+  /// # Returns
+  ///
+  /// The new focused window ID, or `None` if there's no focused window, or there's no window in
+  /// the direction.
+  pub fn move_focus(&mut self, direction: FocusDirection) -> Option<TreeNodeId> {
+    let focused_window_id = self.focused_window_id()?;
+    let target_id = match direction {
+      FocusDirection::Next => *self
+        .window_ids
+        .range(focused_window_id + 1..)
+        .next()
+        .or(self.window_ids.first())?,
+      FocusDirection::Prev => *self
+        .window_ids
+        .range(..focused_window_id)
+        .next_back()
+        .or(self.window_ids.last())?,
+      _ => self.adjacent_window_id(focused_window_id, direction)?,
+    };
+    self.set_focused_window_id(target_id);
+    Some(target_id)
+  }
+
+  /// Find the window adjacent to the window in the direction, i.e. its edge touches the window's
+  /// edge on that side, and their spans overlap. If there're several such windows, the one that
+  /// spans the cursor's row (for left/right) or column (for up/down) is picked, otherwise the
+  /// nearest one.
+  ///
+  /// NOTE: [`FocusDirection::Next`] and [`FocusDirection::Prev`] have no adjacent window.
+  pub fn adjacent_window_id(
+    &self,
+    window_id: TreeNodeId,
+    direction: FocusDirection,
+  ) -> Option<TreeNodeId> {
+    let shape = match self.node(&window_id) {
+      Some(TreeNode::Window(window)) => *window.shape(),
+      _ => return None,
+    };
+    let (min, max) = (shape.min(), shape.max());
+    // The cursor position relative to the parent, i.e. in the same coordinates with the windows.
+    let cursor = match self.cursor_id.and_then(|cursor_id| self.node(&cursor_id)) {
+      Some(cursor) if self.focused_window_id() == Some(window_id) => {
+        let cursor_min = cursor.shape().min();
+        (min.x + cursor_min.x, min.y + cursor_min.y)
+      }
+      _ => (min.x, min.y),
+    };
+
+    // The distance from the cursor to the span, it's zero if the span contains the cursor.
+    let distance = |start: isize, end: isize, at: isize| {
+      if at < start {
+        start - at
+      } else if at >= end {
+        at - end + 1
+      } else {
+        0
+      }
+    };
+    self
+      .window_ids
+      .iter()
+      .filter(|id| **id != window_id)
+      .filter_map(|id| {
+        let other = *self.node(id)?.shape();
+        let (other_min, other_max) = (other.min(), other.max());
+        let rows_overlap = other_min.y < max.y && min.y < other_max.y;
+        let columns_overlap = other_min.x < max.x && min.x < other_max.x;
+        let adjacent = match direction {
+          FocusDirection::Left => other_max.x == min.x && rows_overlap,
+          FocusDirection::Right => other_min.x == max.x && rows_overlap,
+          FocusDirection::Up => other_max.y == min.y && columns_overlap,
+          FocusDirection::Down => other_min.y == max.y && columns_overlap,
+          FocusDirection::Next | FocusDirection::Prev => false,
+        };
+        if !adjacent {
+          return None;
+        }
+        let d = match direction {
+          FocusDirection::Left | FocusDirection::Right => {
+            distance(other_min.y, other_max.y, cursor.1)
+          }
+          _ => distance(other_min.x, other_max.x, cursor.0),
+        };
+        Some((d, *id))
+      })
+      .min()
+      .map(|(_, id)| id)
+  }
+
+  /// Remove the saved view states of the buffer in all windows (including the windows in other
+  /// tab pages), it should be called when the buffer is removed from the
+  /// [`BuffersManager`](crate::buf::BuffersManager).
+  pub fn forget_buffer(&mut self, buf_id: BufferId) {
+    let window_ids: Vec<TreeNodeId> = self.window_ids.iter().copied().collect();
+    for window_id in window_ids {
+      if let Some(TreeNode::Window(window)) = self.node_mut(&window_id) {
+        window.forget_buffer(buf_id);
+      }
+    }
+    for tab_page in self.tab_pages.iter_mut() {
+      for window in tab_page.windows.iter_mut() {
+        if let TreeNode::Window(window) = window {
+          window.forget_buffer(buf_id);
+        }
+      }
+    }
   }
 }
-// Node {
+// Focus }
+
+// Split {
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+/// The direction to split a window, see [`Tree::split_window`].
+pub enum SplitDirection {
+  /// Split the window into the top and bottom windows, i.e. `:split`.
+  Horizontal,
+  /// Split the window into the left and right windows, i.e. `:vsplit`.
+  Vertical,
+}
+
+impl Tree {
+  /// Set the shape of a window, i.e. move and resize it. Its content, viewport and cursor are
+  /// updated with the new shape.
+  ///
+  /// # Returns
+  ///
+  /// It returns `false` if the `window_id` is not a window.
+  pub fn reshape_window(&mut self, window_id: TreeNodeId, shape: IRect) -> bool {
+    if !self.window_ids.contains(&window_id) {
+      return false;
+    }
+    let old_actual_shape = *self.base.node(&window_id).unwrap().actual_shape();
+    self.base.reshape(window_id, shape);
+    if let Some(TreeNode::Window(window)) = self.base.node_mut(&window_id) {
+      window.sync_shape();
+      if *window.actual_shape() != old_actual_shape {
+        self.stale_regions.push(old_actual_shape);
+      }
+    }
+    true
+  }
+
+  /// Compose the gutters of the windows again, i.e. the gutter options, the lines count or the
+  /// signs of the buffers are changed, see [`Window::sync_gutter`]. The cursor widget (and the
+  /// saved cursor position) inside the window is moved along with the text content.
+  pub fn sync_gutters(&mut self) {
+    let window_ids: Vec<TreeNodeId> = self.window_ids.iter().copied().collect();
+    for window_id in window_ids {
+      let cols = match self.base.node_mut(&window_id) {
+        Some(TreeNode::Window(window)) => window.sync_gutter(),
+        _ => continue,
+      };
+      if cols == 0 {
+        continue;
+      }
+      if let Some(cursor_shape) = self.window_cursor_shapes.get_mut(&window_id) {
+        *cursor_shape = IRect::new(
+          (cursor_shape.min().x + cols, cursor_shape.min().y),
+          (cursor_shape.max().x + cols, cursor_shape.max().y),
+        );
+      }
+      if let (Some(cursor_id), Some(focused_window_id)) = (self.cursor_id, self.focused_window_id())
+      {
+        if focused_window_id == window_id {
+          self.bounded_move_by(cursor_id, cols, 0);
+        }
+      }
+    }
+  }
+
+  // The cursor shape at the top-left corner of the text content in the window, i.e. after the
+  // gutter.
+  fn text_top_left(&self, window_id: TreeNodeId) -> IRect {
+    let x = match self.node(&window_id) {
+      Some(TreeNode::Window(window)) => window.gutter_width() as isize,
+      _ => 0,
+    };
+    IRect::new((x, 0), (x + 1, 1))
+  }
+
+  /// Split a window into two windows, the new window shows the same buffer from the same top line.
+  /// Same with Vim the new window is on the top/left half, or the bottom/right half if the
+  /// 'splitbelow'/'splitright' option is on. If the split window is focused, the focus moves to
+  /// the new window. If the 'equalalways' option is on, all the windows are made the same size
+  /// (see [`Tree::equalize_windows`]).
+  ///
+  /// # Returns
+  ///
+  /// The new window ID, or `None` if the `window_id` is not a window, or it's too small to split.
+  pub fn split_window(
+    &mut self,
+    window_id: TreeNodeId,
+    direction: SplitDirection,
+  ) -> Option<TreeNodeId> {
+    let (parent_id, shape, buffer, options, start_line_idx) = match self.node(&window_id) {
+      Some(TreeNode::Window(window)) => (
+        *self.parent_id(&window_id)?,
+        *window.shape(),
+        window.buffer(),
+        window.options().clone(),
+        rlock!(window.viewport()).start_line_idx(),
+      ),
+      _ => return None,
+    };
+    let (min, max) = (shape.min(), shape.max());
+    let (first, second, new_second) = match direction {
+      SplitDirection::Horizontal if shape.height() >= 2 => {
+        let y = min.y + shape.height() / 2;
+        (
+          IRect::new((min.x, min.y), (max.x, y)),
+          IRect::new((min.x, y), (max.x, max.y)),
+          self.split_below(),
+        )
+      }
+      SplitDirection::Vertical if shape.width() >= 2 => {
+        let x = min.x + shape.width() / 2;
+        (
+          IRect::new((min.x, min.y), (x, max.y)),
+          IRect::new((x, min.y), (max.x, max.y)),
+          self.split_right(),
+        )
+      }
+      _ => return None,
+    };
+    let (new_shape, shape) = if new_second {
+      (second, first)
+    } else {
+      (first, second)
+    };
+
+    self.reshape_window(window_id, shape);
+    let mut window = Window::new(new_shape, buffer, &options);
+    wlock!(window.viewport()).sync_from_top_left(start_line_idx, 0);
+    let new_window_id = window.id();
+    self
+      .bounded_insert(&parent_id, TreeNode::Window(window))
+      .unwrap();
+    if self.focused_window_id() == Some(window_id) {
+      // The cursor stays at the same position in the new window, if it fits.
+      if let Some(cursor_id) = self.cursor_id {
+        let cursor_shape = *self.node(&cursor_id).unwrap().shape();
+        if cursor_shape.max().x <= new_shape.width() && cursor_shape.max().y <= new_shape.height() {
+          self
+            .window_cursor_shapes
+            .insert(new_window_id, cursor_shape);
+        }
+      }
+      self.set_focused_window_id(new_window_id);
+    }
+    if self.equal_always() {
+      self.manual_resized = false;
+      self.equalize_windows();
+    }
+    Some(new_window_id)
+  }
+
+  /// Close a window, its space is given to the adjacent window that shares the whole edge with
+  /// it, i.e. the window split from the same window. If there's no such window, the space is
+  /// left empty.
+  ///
+  /// If the 'equalalways' option is on, all the windows are made the same size (see
+  /// [`Tree::equalize_windows`]), unless they're resized manually (see [`Tree::resize_window`])
+  /// since the last split.
+  ///
+  /// NOTE: The focus is transferred when closing the focused window, see [`Tree::remove`].
+  ///
+  /// # Returns
+  ///
+  /// It returns `false` if the `window_id` is not a window, or it's the last window.
+  pub fn close_window(&mut self, window_id: TreeNodeId) -> bool {
+    if !self.window_ids.contains(&window_id) || self.window_ids.len() <= 1 {
+      return false;
+    }
+    let shape = *self.node(&window_id).unwrap().shape();
+    let (min, max) = (shape.min(), shape.max());
+    let adjacent = self.window_ids.iter().find_map(|id| {
+      if *id == window_id {
+        return None;
+      }
+      let other = *self.node(id).unwrap().shape();
+      let (other_min, other_max) = (other.min(), other.max());
+      let same_columns = other_min.x == min.x && other_max.x == max.x;
+      let same_rows = other_min.y == min.y && other_max.y == max.y;
+      if same_columns && (other_max.y == min.y || other_min.y == max.y) {
+        let merged = IRect::new(
+          (min.x, std::cmp::min(min.y, other_min.y)),
+          (max.x, std::cmp::max(max.y, other_max.y)),
+        );
+        Some((*id, merged))
+      } else if same_rows && (other_max.x == min.x || other_min.x == max.x) {
+        let merged = IRect::new(
+          (std::cmp::min(min.x, other_min.x), min.y),
+          (std::cmp::max(max.x, other_max.x), max.y),
+        );
+        Some((*id, merged))
+      } else {
+        None
+      }
+    });
+
+    self.remove(window_id);
+    if let Some((adjacent_id, merged)) = adjacent {
+      self.reshape_window(adjacent_id, merged);
+    }
+    if self.equal_always() && !self.manual_resized {
+      self.equalize_windows();
+    }
+    true
+  }
+}
+// Split }
+
+// Resize {
+
+// The range of the shape along the direction, and the range across it, i.e. the rows and the
+// columns for the windows split by `SplitDirection::Horizontal`.
+fn split_ranges(shape: &IRect, direction: SplitDirection) -> ((isize, isize), (isize, isize)) {
+  let (min, max) = (shape.min(), shape.max());
+  match direction {
+    SplitDirection::Horizontal => ((min.y, max.y), (min.x, max.x)),
+    SplitDirection::Vertical => ((min.x, max.x), (min.y, max.y)),
+  }
+}
+
+// The inverse of `split_ranges`.
+fn split_shape(along: (isize, isize), across: (isize, isize), direction: SplitDirection) -> IRect {
+  match direction {
+    SplitDirection::Horizontal => IRect::new((across.0, along.0), (across.1, along.1)),
+    SplitDirection::Vertical => IRect::new((along.0, across.0), (along.1, across.1)),
+  }
+}
+
+impl Tree {
+  // The minimal size of the windows along the direction, i.e. 'winminheight' or 'winminwidth'.
+  fn window_min_size(&self, direction: SplitDirection) -> isize {
+    let size = match direction {
+      SplitDirection::Horizontal => self.win_min_height(),
+      SplitDirection::Vertical => self.win_min_width(),
+    };
+    std::cmp::max(size, 1) as isize
+  }
+
+  // Whether the window keeps its size along the direction when the windows are made the same
+  // size, i.e. 'winfixheight' or 'winfixwidth'.
+  fn window_fixed(&self, window_id: TreeNodeId, direction: SplitDirection) -> bool {
+    match (self.node(&window_id), direction) {
+      (Some(TreeNode::Window(window)), SplitDirection::Horizontal) => window.win_fix_height(),
+      (Some(TreeNode::Window(window)), SplitDirection::Vertical) => window.win_fix_width(),
+      _ => false,
+    }
+  }
+
+  // The windows on the edge of the window, they're after (or before) the window along the
+  // direction, and exactly cover the edge. The windows that only cover part of the edge, or go
+  // beyond it, cannot be resized along with the window.
+  fn window_edge_neighbors(
+    &self,
+    window_id: TreeNodeId,
+    direction: SplitDirection,
+    after: bool,
+  ) -> Option<Vec<(TreeNodeId, (isize, isize), (isize, isize))>> {
+    let shape = *self.node(&window_id)?.shape();
+    let (along, across) = split_ranges(&shape, direction);
+    let edge = if after { along.1 } else { along.0 };
+    let neighbors: Vec<(TreeNodeId, (isize, isize), (isize, isize))> = self
+      .window_ids
+      .iter()
+      .filter(|id| **id != window_id)
+      .filter_map(|id| {
+        let (other_along, other_across) = split_ranges(self.node(id).unwrap().shape(), direction);
+        let on_edge = if after {
+          other_along.0 == edge
+        } else {
+          other_along.1 == edge
+        };
+        let inside = other_across.0 >= across.0 && other_across.1 <= across.1;
+        (on_edge && inside).then_some((*id, other_along, other_across))
+      })
+      .collect();
+    let covered: isize = neighbors
+      .iter()
+      .map(|(_, _, other_across)| other_across.1 - other_across.0)
+      .sum();
+    (!neighbors.is_empty() && covered == across.1 - across.0).then_some(neighbors)
+  }
+
+  /// Set the size of a window along the direction, i.e. the height for
+  /// [`SplitDirection::Horizontal`] (`:resize`), the width for [`SplitDirection::Vertical`]
+  /// (`:vertical resize`). The windows after it (or before it if there's none) give or take the
+  /// space, thus the total area is conserved. The windows cannot be smaller than 'winminheight'
+  /// or 'winminwidth'. The 'equalalways' doesn't apply on closing windows until the next split.
+  ///
+  /// # Returns
+  ///
+  /// It returns `false` if the `window_id` is not a window, or there're no windows that exactly
+  /// cover its bottom/right (or top/left) edge.
+  pub fn resize_window(
+    &mut self,
+    window_id: TreeNodeId,
+    direction: SplitDirection,
+    size: usize,
+  ) -> bool {
+    if !self.window_ids.contains(&window_id) {
+      return false;
+    }
+    let (along, across) = split_ranges(self.node(&window_id).unwrap().shape(), direction);
+    let (after, neighbors) = match self.window_edge_neighbors(window_id, direction, true) {
+      Some(neighbors) => (true, neighbors),
+      None => match self.window_edge_neighbors(window_id, direction, false) {
+        Some(neighbors) => (false, neighbors),
+        None => return false,
+      },
+    };
+
+    let min_size = self.window_min_size(direction);
+    let available = neighbors
+      .iter()
+      .map(|(_, other_along, _)| other_along.1 - other_along.0 - min_size)
+      .min()
+      .unwrap_or(0);
+    let size = std::cmp::max(size.min(isize::MAX as usize) as isize, min_size);
+    let delta = std::cmp::min(size - (along.1 - along.0), std::cmp::max(available, 0));
+
+    let (along, edge) = if after {
+      ((along.0, along.1 + delta), along.1 + delta)
+    } else {
+      ((along.0 - delta, along.1), along.0 - delta)
+    };
+    self.reshape_window(window_id, split_shape(along, across, direction));
+    for (id, other_along, other_across) in neighbors {
+      let other_along = if after {
+        (edge, other_along.1)
+      } else {
+        (other_along.0, edge)
+      };
+      self.reshape_window(id, split_shape(other_along, other_across, direction));
+    }
+    self.manual_resized = true;
+    true
+  }
+
+  /// Make the windows (almost) equally high and wide, i.e. `Ctrl-W =`. The windows stacked in
+  /// the same columns share their total height evenly, and the windows side by side in the same
+  /// rows share their total width evenly. The windows with 'winfixheight' ('winfixwidth') keep
+  /// their heights (widths), the others share the rest.
+  pub fn equalize_windows(&mut self) {
+    for direction in [SplitDirection::Horizontal, SplitDirection::Vertical] {
+      let mut windows: Vec<(TreeNodeId, (isize, isize), (isize, isize))> = self
+        .window_ids
+        .iter()
+        .map(|id| {
+          let (along, across) = split_ranges(self.node(id).unwrap().shape(), direction);
+          (*id, along, across)
+        })
+        .collect();
+      windows.sort_by_key(|(_, along, across)| (*across, along.0));
+
+      // Split the windows into the chains, each chain is stacked along the direction.
+      let mut chains: Vec<Vec<(TreeNodeId, (isize, isize), (isize, isize))>> = vec![];
+      for window in windows {
+        match chains.last_mut() {
+          Some(chain)
+            if chain
+              .last()
+              .is_some_and(|(_, along, across)| *across == window.2 && along.1 == window.1 .0) =>
+          {
+            chain.push(window)
+          }
+          _ => chains.push(vec![window]),
+        }
+      }
+
+      for chain in chains.into_iter().filter(|chain| chain.len() > 1) {
+        let fixed: Vec<bool> = chain
+          .iter()
+          .map(|(id, _, _)| self.window_fixed(*id, direction))
+          .collect();
+        let n = fixed.iter().filter(|fixed| !**fixed).count() as isize;
+        if n == 0 {
+          continue;
+        }
+        let start = chain.first().unwrap().1 .0;
+        let total: isize = chain
+          .iter()
+          .zip(fixed.iter())
+          .filter(|(_, fixed)| !**fixed)
+          .map(|((_, along, _), _)| along.1 - along.0)
+          .sum();
+        let mut lo = start;
+        let mut i = 0;
+        for ((id, along, across), fixed) in chain.into_iter().zip(fixed) {
+          let size = if fixed {
+            along.1 - along.0
+          } else {
+            i += 1;
+            total / n + if i <= total % n { 1 } else { 0 }
+          };
+          self.reshape_window(id, split_shape((lo, lo + size), across, direction));
+          lo += size;
+        }
+      }
+    }
+  }
+}
+// Resize }
+
+// Layout {
+impl Tree {
+  /// Initialize the default layout: a window shows the `buffer` and fills the terminal except the
+  /// last row, the cursor is inside the window, and the message area is on the last row.
+  pub fn init_layout(&mut self, buffer: BufferWk) {
+    let root_id = self.root_id();
+    let shape = *self.node(&root_id).unwrap().shape();
+    let window_height = std::cmp::max(shape.height() - 1, 0);
+    let window = Window::new(
+      IRect::new((0, 0), (shape.width(), window_height)),
+      buffer,
+      &self.local_options,
+    );
+    let window_id = window.id();
+    self
+      .bounded_insert(&root_id, TreeNode::Window(window))
+      .unwrap();
+    let cursor = Cursor::new(self.text_top_left(window_id));
+    self
+      .bounded_insert(&window_id, TreeNode::Cursor(cursor))
+      .unwrap();
+    let message_area = MessageArea::new(IRect::new(
+      (0, window_height),
+      (shape.width(), shape.height()),
+    ));
+    self
+      .bounded_insert(&root_id, TreeNode::MessageArea(message_area))
+      .unwrap();
+  }
+
+  /// Resize the tree to the terminal size, i.e. the terminal is resized. The message area stays
+  /// on the last row, the windows on the right/bottom edge are stretched to the new edge, while
+  /// the other windows keep their shapes. The windows of the inactive tab pages are resized as
+  /// well, and the whole screen needs to redraw.
+  pub fn resize(&mut self, terminal_size: U16Size) {
+    let root_id = self.root_id();
+    let old_shape = *self.node(&root_id).unwrap().shape();
+    let (width, height) = (
+      terminal_size.width() as isize,
+      terminal_size.height() as isize,
+    );
+    let message_height = self
+      .message_area_id
+      .map(|id| self.node(&id).unwrap().shape().height())
+      .unwrap_or(0);
+    let old_bottom = old_shape.height() - message_height;
+    let bottom = std::cmp::max(height - message_height, 0);
+    let root = self.base.node_mut(&root_id).unwrap();
+    *root.shape_mut() = IRect::new((0, 0), (width, height));
+    *root.actual_shape_mut() =
+      U16Rect::new((0, 0), (terminal_size.width(), terminal_size.height()));
+
+    let stretch = |shape: IRect| {
+      let (min, max) = (shape.min(), shape.max());
+      let max_x = if max.x >= old_shape.width() {
+        width
+      } else {
+        max.x
+      };
+      let max_y = if max.y >= old_bottom { bottom } else { max.y };
+      IRect::new(
+        (std::cmp::min(min.x, max_x), std::cmp::min(min.y, max_y)),
+        (max_x, max_y),
+      )
+    };
+    let window_ids: Vec<TreeNodeId> = self.window_ids.iter().copied().collect();
+    for window_id in window_ids {
+      let shape = stretch(*self.node(&window_id).unwrap().shape());
+      self.reshape_window(window_id, shape);
+    }
+    for tab_page in self.tab_pages.iter_mut() {
+      for window in tab_page.windows.iter_mut() {
+        *window.shape_mut() = stretch(*window.shape());
+      }
+    }
+    if let Some(message_area_id) = self.message_area_id {
+      self
+        .base
+        .reshape(message_area_id, IRect::new((0, bottom), (width, height)));
+    }
+    if let Some(tab_line_id) = self.tab_line_id {
+      self
+        .base
+        .reshape(tab_line_id, IRect::new((0, 0), (width, 1)));
+    }
+    self.redraw = true;
+  }
+}
+// Layout }
+
+// Tab pages {
+
+#[derive(Debug, Clone, Default)]
+// A tab page, i.e. a window layout. The windows of the inactive tab page are detached from the
+// tree.
+struct TabPage {
+  // The detached window nodes.
+  windows: Vec<TreeNode>,
+
+  // The focused window ID, the cursor goes back to it when the tab page is active again.
+  focused_window_id: Option<TreeNodeId>,
+
+  // The top row of the window layout, it's changed when the tab line is shown or hidden.
+  top: isize,
+}
+
+impl Tree {
+  /// Tab pages count, there's always at least one tab page.
+  pub fn tab_count(&self) -> usize {
+    self.tab_pages.len()
+  }
+
+  /// The index of the active tab page, starts from 0.
+  pub fn active_tab(&self) -> usize {
+    self.active_tab
+  }
+
+  /// Get the tab line node ID.
+  pub fn tab_line_id(&self) -> Option<TreeNodeId> {
+    self.tab_line_id
+  }
+
+  /// Whether the whole screen needs to redraw, it's reset after it's taken.
+  pub fn take_redraw(&mut self) -> bool {
+    std::mem::take(&mut self.redraw)
+  }
+
+  /// Take the regions left by the windows that are shrunk or moved, they need to be cleared on
+  /// the canvas (see [`Canvas::clear_region`](crate::ui::canvas::Canvas::clear_region)) before
+  /// next draw, since no widget covers them.
+  pub fn take_stale_regions(&mut self) -> Vec<U16Rect> {
+    std::mem::take(&mut self.stale_regions)
+  }
+
+  // The top row of the windows, i.e. below the tab line.
+  fn tab_top(&self) -> isize {
+    if self.tab_line_id.is_some() {
+      1
+    } else {
+      0
+    }
+  }
+
+  // Show the tab line on the first row if there're multiple tab pages, otherwise hide it, same
+  // with the default 'showtabline'.
+  fn sync_tab_line_visible(&mut self) {
+    match (self.tab_pages.len() > 1, self.tab_line_id) {
+      (true, None) => {
+        let root_id = self.root_id();
+        let width = self.node(&root_id).unwrap().shape().width();
+        let tab_line = TabLine::new(IRect::new((0, 0), (width, 1)));
+        self
+          .bounded_insert(&root_id, TreeNode::TabLine(tab_line))
+          .unwrap();
+      }
+      (false, Some(tab_line_id)) => {
+        self.remove(tab_line_id);
+      }
+      _ => { /* Skip */ }
+    }
+  }
+
+  // Detach the windows of the active tab page from the tree, returns the tab page and the cursor
+  // node. The cursor position in the focused window is saved.
+  fn detach_tab(&mut self) -> (TabPage, Option<TreeNode>) {
+    let focused_window_id = self.focused_window_id();
+    let cursor = match (self.cursor_id, focused_window_id) {
+      (Some(cursor_id), Some(window_id)) => {
+        let cursor_shape = *self.node(&cursor_id).unwrap().shape();
+        self.window_cursor_shapes.insert(window_id, cursor_shape);
+        self.remove_guard(&cursor_id);
+        self.base.remove(cursor_id)
+      }
+      _ => None,
+    };
+    let window_ids: Vec<TreeNodeId> = self.window_ids.iter().copied().collect();
+    let windows = window_ids
+      .into_iter()
+      .filter_map(|window_id| {
+        self.remove_guard(&window_id);
+        self.base.remove(window_id)
+      })
+      .collect();
+    let tab_page = TabPage {
+      windows,
+      focused_window_id,
+      top: self.tab_top(),
+    };
+    (tab_page, cursor)
+  }
+
+  // Attach the windows of the tab page to the tree, the windows on the top row are moved if the
+  // tab line is shown/hidden since they're detached. The viewports are rebuilt since the buffers
+  // may be changed in other tab pages, and the whole screen needs to redraw.
+  fn attach_tab(&mut self, tab_page: TabPage, cursor: Option<TreeNode>) {
+    let root_id = self.root_id();
+    let top = self.tab_top();
+    for window in tab_page.windows {
+      let window_id = window.id();
+      let shape = *window.shape();
+      self.bounded_insert(&root_id, window).unwrap();
+      let shape = if shape.min().y == tab_page.top {
+        IRect::new((shape.min().x, top), (shape.max().x, shape.max().y))
+      } else {
+        shape
+      };
+      self.reshape_window(window_id, shape);
+    }
+    if let (Some(mut cursor), Some(window_id)) = (cursor, tab_page.focused_window_id) {
+      *cursor.shape_mut() = match self.window_cursor_shapes.remove(&window_id) {
+        Some(cursor_shape) => cursor_shape,
+        None => self.text_top_left(window_id),
+      };
+      self.bounded_insert(&window_id, cursor).unwrap();
+    }
+    self.redraw = true;
+    self.sync_tab_line();
+  }
+
+  /// Open a new tab page after the active one, i.e. `:tabnew`. It has one window shows the
+  /// `buffer`, which fills the space between the tab line and the message area. The new tab page
+  /// is active.
+  ///
+  /// # Returns
+  ///
+  /// The window ID of the new tab page, or `None` if there's no cursor widget.
+  pub fn new_tab(&mut self, buffer: BufferWk) -> Option<TreeNodeId> {
+    self.cursor_id?;
+    let (tab_page, cursor) = self.detach_tab();
+    self.tab_pages[self.active_tab] = tab_page;
+    self.active_tab += 1;
+    self.tab_pages.insert(self.active_tab, TabPage::default());
+    self.sync_tab_line_visible();
+
+    let root_id = self.root_id();
+    let root_shape = *self.node(&root_id).unwrap().shape();
+    let bottom = match self.message_area_id {
+      Some(message_area_id) => self.node(&message_area_id).unwrap().shape().min().y,
+      None => root_shape.max().y,
+    };
+    let shape = IRect::new((0, self.tab_top()), (root_shape.width(), bottom));
+    let window = Window::new(shape, buffer, &self.local_options);
+    let window_id = window.id();
+    self
+      .bounded_insert(&root_id, TreeNode::Window(window))
+      .unwrap();
+    let tab_page = TabPage {
+      windows: vec![],
+      focused_window_id: Some(window_id),
+      top: self.tab_top(),
+    };
+    self.attach_tab(tab_page, cursor);
+    Some(window_id)
+  }
+
+  /// Switch to the tab page at `index`, starts from 0.
+  ///
+  /// # Returns
+  ///
+  /// It returns `false` if the `index` is out of range.
+  pub fn switch_tab(&mut self, index: usize) -> bool {
+    if index >= self.tab_pages.len() {
+      return false;
+    }
+    if index != self.active_tab {
+      let (tab_page, cursor) = self.detach_tab();
+      self.tab_pages[self.active_tab] = tab_page;
+      self.active_tab = index;
+      let tab_page = std::mem::take(&mut self.tab_pages[index]);
+      self.attach_tab(tab_page, cursor);
+    }
+    true
+  }
+
+  /// Switch to the next tab page, i.e. `gt`, it wraps around at the last tab page.
+  ///
+  /// # Returns
+  ///
+  /// The index of the new active tab page.
+  pub fn next_tab(&mut self) -> usize {
+    self.switch_tab((self.active_tab + 1) % self.tab_pages.len());
+    self.active_tab
+  }
+
+  /// Switch to the previous tab page, i.e. `gT`, it wraps around at the first tab page.
+  ///
+  /// # Returns
+  ///
+  /// The index of the new active tab page.
+  pub fn prev_tab(&mut self) -> usize {
+    let len = self.tab_pages.len();
+    self.switch_tab((self.active_tab + len - 1) % len);
+    self.active_tab
+  }
+
+  /// Close the active tab page and all its windows, i.e. `:tabclose`. The next tab page is active,
+  /// or the previous one if it is the last tab page.
+  ///
+  /// # Returns
+  ///
+  /// It returns `false` if it's the last tab page, i.e. it quits the editor.
+  pub fn close_tab(&mut self) -> bool {
+    if self.tab_pages.len() <= 1 {
+      return false;
+    }
+    let (tab_page, cursor) = self.detach_tab();
+    for window in tab_page.windows.iter() {
+      self.window_cursor_shapes.remove(&window.id());
+    }
+    self.tab_pages.remove(self.active_tab);
+    self.active_tab = std::cmp::min(self.active_tab, self.tab_pages.len() - 1);
+    self.sync_tab_line_visible();
+    let tab_page = std::mem::take(&mut self.tab_pages[self.active_tab]);
+    self.attach_tab(tab_page, cursor);
+    true
+  }
+
+  /// Update the labels of the tab line, i.e. the buffer name of the focused window in each tab page
+  /// and whether it's modified. It does nothing if there's no tab line.
+  pub fn sync_tab_line(&mut self) {
+    let tab_line_id = match self.tab_line_id {
+      Some(tab_line_id) => tab_line_id,
+      None => return,
+    };
+    let labels = (0..self.tab_pages.len())
+      .map(|index| {
+        let buffer = if index == self.active_tab {
+          match self.focused_window_id().and_then(|id| self.node(&id)) {
+            Some(TreeNode::Window(window)) => window.buffer().upgrade(),
+            _ => None,
+          }
+        } else {
+          let tab_page = &self.tab_pages[index];
+          tab_page.windows.iter().find_map(|node| match node {
+            TreeNode::Window(window) if Some(window.id()) == tab_page.focused_window_id => {
+              window.buffer().upgrade()
+            }
+            _ => None,
+          })
+        };
+        match buffer {
+          Some(buffer) => {
+            let buffer = rlock!(buffer);
+            let modified = if buffer.is_modified() { "+ " } else { "" };
+            format!(" {}{} ", modified, buffer.display_name())
+          }
+          None => " [No Name] ".to_string(),
+        }
+      })
+      .collect();
+    if let Some(TreeNode::TabLine(tab_line)) = self.base.node_mut(&tab_line_id) {
+      tab_line.set_labels(labels, self.active_tab);
+    }
+  }
+}
+// Tab pages }
 
 // Insert/Remove {
 impl Tree {
   // This method handles some special requirements when insert a widget node:
   //
   // 1. When insert a cursor widget, it's parent widget must be a window widget.
-  // 2. Maintain the cursor widget ID and window widget IDs when insert.
+  // 2. Maintain the cursor widget ID, window widget IDs, message area widget ID and tab line widget
+  //    ID when insert.
   fn insert_guard(&mut self, node: &TreeNode, parent_id: &TreeNodeId) {
     match node {
       TreeNode::Cursor(cursor) => {
@@ -365,6 +1375,12 @@ impl Tree {
       TreeNode::Window(window) => {
         self.window_ids.insert(window.id());
       }
+      TreeNode::MessageArea(message_area) => {
+        self.message_area_id = Some(message_area.id());
+      }
+      TreeNode::TabLine(tab_line) => {
+        self.tab_line_id = Some(tab_line.id());
+      }
       _ => { /* Skip */ }
     }
   }
@@ -372,36 +1388,105 @@ impl Tree {
   // This method handles some special requirements when remove a widget node:
   //
   // 1. When insert a cursor widget, it's parent widget must be a window widget.
-  // 2. Maintain the cursor widget ID and window widget IDs when remove.
+  // 2. Maintain the cursor widget ID, window widget IDs, message area widget ID and tab line widget
+  //    ID when remove.
   fn remove_guard(&mut self, id: &TreeNodeId) {
     // If the removed ID is cursor ID, remove it.
     if self.cursor_id == Some(*id) {
       self.cursor_id = None;
     }
+    if self.message_area_id == Some(*id) {
+      self.message_area_id = None;
+    }
+    if self.tab_line_id == Some(*id) {
+      self.tab_line_id = None;
+    }
     self.window_ids.remove(id);
   }
 
   /// See [`Itree::insert`].
-  pub fn insert(&mut self, parent_id: &TreeNodeId, child_node: TreeNode) -> Option<TreeNode> {
+  ///
+  /// # Errors
+  ///
+  /// It returns [`TreeErr::NodeNotExist`] if `parent_id` doesn't exist, and the tree is unchanged.
+  pub fn insert(
+    &mut self,
+    parent_id: &TreeNodeId,
+    child_node: TreeNode,
+  ) -> TreeResult<Option<TreeNode>> {
+    self.parent_guard(parent_id)?;
     self.insert_guard(&child_node, parent_id);
-    self.base.insert(parent_id, child_node)
+    let child_id = child_node.id();
+    let result = self.base.insert(parent_id, child_node)?;
+    self.sync_window_shape(child_id);
+    Ok(result)
   }
 
   /// See [`Itree::bounded_insert`].
+  ///
+  /// # Errors
+  ///
+  /// It returns [`TreeErr::NodeNotExist`] if `parent_id` doesn't exist, and the tree is unchanged.
   pub fn bounded_insert(
     &mut self,
     parent_id: &TreeNodeId,
     child_node: TreeNode,
-  ) -> Option<TreeNode> {
+  ) -> TreeResult<Option<TreeNode>> {
+    self.parent_guard(parent_id)?;
     self.insert_guard(&child_node, parent_id);
-    self.base.bounded_insert(parent_id, child_node)
+    let child_id = child_node.id();
+    let result = self.base.bounded_insert(parent_id, child_node)?;
+    self.sync_window_shape(child_id);
+    Ok(result)
+  }
+
+  // The `insert_guard` updates the widget IDs, make sure the parent exists before it.
+  fn parent_guard(&self, parent_id: &TreeNodeId) -> TreeResult<()> {
+    match self.base.node(parent_id) {
+      Some(_) => Ok(()),
+      None => Err(TreeErr::NodeNotExist(*parent_id)),
+    }
+  }
+
+  // The window is created before it's inserted, sync its content and viewport to the actual shape
+  // in the tree, i.e. it can be partially outside of its parent.
+  fn sync_window_shape(&mut self, id: TreeNodeId) {
+    if let Some(TreeNode::Window(window)) = self.base.node_mut(&id) {
+      window.sync_shape();
+    }
   }
 
-  /// See [`Itree::remove`].
+  /// See [`Itree::remove`], the descendants of the removed node are removed as well.
+  ///
+  /// NOTE: When removing the focused window, the focus is transferred to the next window (in tree
+  /// order), or the previous one if it is the last window.
   pub fn remove(&mut self, id: TreeNodeId) -> Option<TreeNode> {
-    self.remove_guard(&id);
+    if self.focused_window_id() == Some(id) {
+      let sibling_id = self
+        .window_ids
+        .range(id + 1..)
+        .next()
+        .or(self.window_ids.range(..id).next_back())
+        .copied();
+      if let Some(sibling_id) = sibling_id {
+        self.set_focused_window_id(sibling_id);
+      }
+    }
+    let removed_ids: Vec<TreeNodeId> = ItreeIter::new(&self.base, Some(id))
+      .map(|node| node.id())
+      .collect();
+    for removed_id in removed_ids.iter() {
+      self.window_cursor_shapes.remove(removed_id);
+      self.remove_guard(removed_id);
+    }
     self.base.remove(id)
   }
+
+  /// Set the z-index of a node, i.e. move it to the new position in the render order of its
+  /// siblings, see [`Itree::reorder_child`].
+  pub fn set_zindex(&mut self, id: TreeNodeId, zindex: usize) -> Option<usize> {
+    self.base.reorder_child(id, zindex)
+  }
 }
 // Insert/Remove }
 
@@ -483,6 +1568,230 @@ impl Tree {
   pub fn set_line_break(&mut self, value: bool) {
     self.local_options.set_line_break(value);
   }
+
+  pub fn fill_chars(&self) -> FillChars {
+    self.local_options.fill_chars()
+  }
+
+  pub fn set_fill_chars(&mut self, value: FillChars) {
+    self.local_options.set_fill_chars(value);
+  }
+
+  pub fn list(&self) -> bool {
+    self.local_options.list()
+  }
+
+  pub fn set_list(&mut self, value: bool) {
+    self.local_options.set_list(value);
+  }
+
+  pub fn list_chars(&self) -> ListChars {
+    self.local_options.list_chars()
+  }
+
+  pub fn set_list_chars(&mut self, value: ListChars) {
+    self.local_options.set_list_chars(value);
+  }
+
+  pub fn show_break(&self) -> &str {
+    self.local_options.show_break()
+  }
+
+  pub fn set_show_break(&mut self, value: &str) {
+    self.local_options.set_show_break(value.to_string());
+  }
+
+  pub fn scroll(&self) -> usize {
+    self.local_options.scroll()
+  }
+
+  pub fn set_scroll(&mut self, value: usize) {
+    self.local_options.set_scroll(value);
+  }
+
+  pub fn scroll_off(&self) -> usize {
+    self.local_options.scroll_off()
+  }
+
+  pub fn set_scroll_off(&mut self, value: usize) {
+    self.local_options.set_scroll_off(value);
+  }
+
+  pub fn fold_method(&self) -> FoldMethod {
+    self.local_options.fold_method()
+  }
+
+  pub fn set_fold_method(&mut self, value: FoldMethod) {
+    self.local_options.set_fold_method(value);
+  }
+
+  pub fn fold_level(&self) -> usize {
+    self.local_options.fold_level()
+  }
+
+  pub fn set_fold_level(&mut self, value: usize) {
+    self.local_options.set_fold_level(value);
+  }
+
+  pub fn fold_enable(&self) -> bool {
+    self.local_options.fold_enable()
+  }
+
+  pub fn set_fold_enable(&mut self, value: bool) {
+    self.local_options.set_fold_enable(value);
+  }
+
+  pub fn fold_marker(&self) -> &FoldMarker {
+    self.local_options.fold_marker()
+  }
+
+  pub fn set_fold_marker(&mut self, value: FoldMarker) {
+    self.local_options.set_fold_marker(value);
+  }
+
+  pub fn cursor_line(&self) -> bool {
+    self.local_options.cursor_line()
+  }
+
+  pub fn set_cursor_line(&mut self, value: bool) {
+    self.local_options.set_cursor_line(value);
+  }
+
+  pub fn number(&self) -> bool {
+    self.local_options.number()
+  }
+
+  pub fn set_number(&mut self, value: bool) {
+    self.local_options.set_number(value);
+  }
+
+  pub fn fold_column(&self) -> usize {
+    self.local_options.fold_column()
+  }
+
+  pub fn set_fold_column(&mut self, value: usize) {
+    self.local_options.set_fold_column(value);
+  }
+
+  pub fn sign_column(&self) -> SignColumn {
+    self.local_options.sign_column()
+  }
+
+  pub fn set_sign_column(&mut self, value: SignColumn) {
+    self.local_options.set_sign_column(value);
+  }
+
+  pub fn win_fix_height(&self) -> bool {
+    self.local_options.win_fix_height()
+  }
+
+  pub fn set_win_fix_height(&mut self, value: bool) {
+    self.local_options.set_win_fix_height(value);
+  }
+
+  pub fn win_fix_width(&self) -> bool {
+    self.local_options.win_fix_width()
+  }
+
+  pub fn set_win_fix_width(&mut self, value: bool) {
+    self.local_options.set_win_fix_width(value);
+  }
+
+  pub fn inc_search(&self) -> bool {
+    self.global_options.inc_search()
+  }
+
+  pub fn set_inc_search(&mut self, value: bool) {
+    self.global_options.set_inc_search(value);
+  }
+
+  pub fn hl_search(&self) -> bool {
+    self.global_options.hl_search()
+  }
+
+  pub fn set_hl_search(&mut self, value: bool) {
+    self.global_options.set_hl_search(value);
+  }
+
+  pub fn ignore_case(&self) -> bool {
+    self.global_options.ignore_case()
+  }
+
+  pub fn set_ignore_case(&mut self, value: bool) {
+    self.global_options.set_ignore_case(value);
+  }
+
+  pub fn smart_case(&self) -> bool {
+    self.global_options.smart_case()
+  }
+
+  pub fn set_smart_case(&mut self, value: bool) {
+    self.global_options.set_smart_case(value);
+  }
+
+  pub fn title(&self) -> bool {
+    self.global_options.title()
+  }
+
+  pub fn set_title(&mut self, value: bool) {
+    self.global_options.set_title(value);
+  }
+
+  pub fn term_gui_colors(&self) -> bool {
+    self.global_options.term_gui_colors()
+  }
+
+  pub fn set_term_gui_colors(&mut self, value: bool) {
+    self.global_options.set_term_gui_colors(value);
+  }
+
+  pub fn lazy_redraw(&self) -> bool {
+    self.global_options.lazy_redraw()
+  }
+
+  pub fn set_lazy_redraw(&mut self, value: bool) {
+    self.global_options.set_lazy_redraw(value);
+  }
+
+  pub fn split_below(&self) -> bool {
+    self.global_options.split_below()
+  }
+
+  pub fn set_split_below(&mut self, value: bool) {
+    self.global_options.set_split_below(value);
+  }
+
+  pub fn split_right(&self) -> bool {
+    self.global_options.split_right()
+  }
+
+  pub fn set_split_right(&mut self, value: bool) {
+    self.global_options.set_split_right(value);
+  }
+
+  pub fn win_min_height(&self) -> usize {
+    self.global_options.win_min_height()
+  }
+
+  pub fn set_win_min_height(&mut self, value: usize) {
+    self.global_options.set_win_min_height(value);
+  }
+
+  pub fn win_min_width(&self) -> usize {
+    self.global_options.win_min_width()
+  }
+
+  pub fn set_win_min_width(&mut self, value: usize) {
+    self.global_options.set_win_min_width(value);
+  }
+
+  pub fn equal_always(&self) -> bool {
+    self.global_options.equal_always()
+  }
+
+  pub fn set_equal_always(&mut self, value: bool) {
+    self.global_options.set_equal_always(value);
+  }
 }
 // Global options }
 
@@ -501,11 +1810,18 @@ impl Tree {
 
 #[cfg(test)]
 mod tests {
-  use crate::cart::U16Size;
+  use crate::buf::{BufferArc, BuffersManager};
+  use crate::cart::{U16Rect, U16Size};
+  use crate::state::State;
+  use crate::test::buf::make_buffer_from_lines;
   // use crate::test::log::init as test_log_init;
+  use crate::test::render::{snapshot, snapshot_styled};
+  use crate::{rlock, wlock};
 
   use super::*;
 
+  use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+
   #[test]
   fn new() {
     // test_log_init();
@@ -515,4 +1831,750 @@ mod tests {
     assert!(tree.is_empty());
     assert!(tree.len() == 1);
   }
+
+  // Make a tree with two windows (left and right), and the cursor is inside the left one.
+  fn make_tree() -> (TreeArc, TreeNodeId, TreeNodeId, TreeNodeId) {
+    let buffer = make_buffer_from_lines(vec!["Hello, RSVIM!\n"]);
+    let mut tree = Tree::new(U16Size::new(20, 10));
+    let root_id = tree.root_id();
+    let window1 = Window::new(
+      IRect::new((0, 0), (10, 10)),
+      Arc::downgrade(&buffer),
+      tree.local_options(),
+    );
+    let window1_id = window1.id();
+    tree
+      .bounded_insert(&root_id, TreeNode::Window(window1))
+      .unwrap();
+    let window2 = Window::new(
+      IRect::new((10, 0), (20, 10)),
+      Arc::downgrade(&buffer),
+      tree.local_options(),
+    );
+    let window2_id = window2.id();
+    tree
+      .bounded_insert(&root_id, TreeNode::Window(window2))
+      .unwrap();
+    let cursor = Cursor::new(IRect::new((0, 0), (1, 1)));
+    let cursor_id = cursor.id();
+    tree
+      .bounded_insert(&window1_id, TreeNode::Cursor(cursor))
+      .unwrap();
+    (Tree::to_arc(tree), window1_id, window2_id, cursor_id)
+  }
+
+  fn press(state: &mut State, tree: &TreeArc, code: KeyCode) {
+    let buffers = BuffersManager::to_arc(BuffersManager::new());
+    let event = Event::Key(KeyEvent::new(code, KeyModifiers::empty()));
+    state.handle(tree.clone(), buffers, event);
+  }
+
+  fn cursor_shape(tree: &TreeArc, cursor_id: TreeNodeId) -> IRect {
+    *rlock!(tree).node(&cursor_id).unwrap().shape()
+  }
+
+  #[test]
+  fn focus1() {
+    let (tree, window1_id, window2_id, cursor_id) = make_tree();
+    let mut tree = wlock!(tree);
+    assert_eq!(tree.focused_window_id(), Some(window1_id));
+    assert_eq!(tree.parent_id(&cursor_id), Some(&window1_id));
+
+    assert_eq!(tree.move_focus(FocusDirection::Next), Some(window2_id));
+    assert_eq!(tree.focused_window_id(), Some(window2_id));
+    assert_eq!(tree.parent_id(&cursor_id), Some(&window2_id));
+    assert_eq!(tree.move_focus(FocusDirection::Next), Some(window1_id));
+    assert_eq!(tree.move_focus(FocusDirection::Prev), Some(window2_id));
+    assert_eq!(tree.move_focus(FocusDirection::Prev), Some(window1_id));
+
+    assert!(tree.set_focused_window_id(window2_id));
+    assert_eq!(tree.focused_window_id(), Some(window2_id));
+    assert!(!tree.set_focused_window_id(cursor_id));
+    assert_eq!(tree.focused_window_id(), Some(window2_id));
+  }
+
+  #[test]
+  fn focus2() {
+    let (tree, window1_id, window2_id, cursor_id) = make_tree();
+    let mut state = State::default();
+
+    press(&mut state, &tree, KeyCode::Char('l'));
+    press(&mut state, &tree, KeyCode::Char('l'));
+    assert_eq!(cursor_shape(&tree, cursor_id), IRect::new((2, 0), (3, 1)));
+
+    // Motions in the second window don't affect the first window's cursor.
+    wlock!(tree).set_focused_window_id(window2_id);
+    assert_eq!(cursor_shape(&tree, cursor_id), IRect::new((0, 0), (1, 1)));
+    press(&mut state, &tree, KeyCode::Char('l'));
+    assert_eq!(cursor_shape(&tree, cursor_id), IRect::new((1, 0), (2, 1)));
+    assert_eq!(
+      rlock!(tree)
+        .node(&cursor_id)
+        .unwrap()
+        .actual_shape()
+        .min()
+        .x,
+      11
+    );
+
+    // The first window's cursor is restored.
+    wlock!(tree).set_focused_window_id(window1_id);
+    assert_eq!(cursor_shape(&tree, cursor_id), IRect::new((2, 0), (3, 1)));
+    wlock!(tree).set_focused_window_id(window2_id);
+    assert_eq!(cursor_shape(&tree, cursor_id), IRect::new((1, 0), (2, 1)));
+  }
+
+  #[test]
+  fn focus3() {
+    // A 2x2 grid of windows:
+    //
+    // +------+------+
+    // |  1   |  2   |
+    // +------+------+
+    // |  3   |  4   |
+    // +------+------+
+    let buffer = make_buffer_from_lines(vec!["Hello, RSVIM!\n"]);
+    let mut tree = Tree::new(U16Size::new(20, 10));
+    let root_id = tree.root_id();
+    let window_ids: Vec<TreeNodeId> = [
+      ((0, 0), (10, 5)),
+      ((10, 0), (20, 5)),
+      ((0, 5), (10, 10)),
+      ((10, 5), (20, 10)),
+    ]
+    .into_iter()
+    .map(|(min, max)| {
+      let window = Window::new(
+        IRect::new(min, max),
+        Arc::downgrade(&buffer),
+        tree.local_options(),
+      );
+      let window_id = window.id();
+      tree
+        .bounded_insert(&root_id, TreeNode::Window(window))
+        .unwrap();
+      window_id
+    })
+    .collect();
+    let (window1_id, window2_id, window3_id, window4_id) =
+      (window_ids[0], window_ids[1], window_ids[2], window_ids[3]);
+    let cursor = Cursor::new(IRect::new((0, 0), (1, 1)));
+    tree
+      .bounded_insert(&window1_id, TreeNode::Cursor(cursor))
+      .unwrap();
+
+    assert_eq!(tree.move_focus(FocusDirection::Left), None);
+    assert_eq!(tree.move_focus(FocusDirection::Up), None);
+    assert_eq!(tree.move_focus(FocusDirection::Right), Some(window2_id));
+    assert_eq!(tree.move_focus(FocusDirection::Right), None);
+    assert_eq!(tree.move_focus(FocusDirection::Down), Some(window4_id));
+    assert_eq!(tree.move_focus(FocusDirection::Down), None);
+    assert_eq!(tree.move_focus(FocusDirection::Left), Some(window3_id));
+    assert_eq!(tree.move_focus(FocusDirection::Up), Some(window1_id));
+    assert_eq!(tree.move_focus(FocusDirection::Down), Some(window3_id));
+    assert_eq!(tree.move_focus(FocusDirection::Right), Some(window4_id));
+    assert_eq!(tree.move_focus(FocusDirection::Up), Some(window2_id));
+    assert_eq!(tree.move_focus(FocusDirection::Left), Some(window1_id));
+    assert_eq!(tree.focused_window_id(), Some(window1_id));
+
+    // The left windows are merged, the right window is picked by the cursor's row.
+    tree.remove(window3_id);
+    tree.reshape_window(window1_id, IRect::new((0, 0), (10, 10)));
+    assert_eq!(
+      tree.adjacent_window_id(window1_id, FocusDirection::Right),
+      Some(window2_id)
+    );
+    let cursor_id = tree.cursor_id().unwrap();
+    tree.bounded_move_down_by(cursor_id, 7);
+    assert_eq!(tree.move_focus(FocusDirection::Right), Some(window4_id));
+    assert_eq!(tree.move_focus(FocusDirection::Left), Some(window1_id));
+    assert_eq!(
+      tree.adjacent_window_id(window1_id, FocusDirection::Next),
+      None
+    );
+  }
+
+  #[test]
+  fn focus3() {
+    let (tree, window1_id, window2_id, cursor_id) = make_tree();
+    let mut tree = wlock!(tree);
+
+    // Remove the focused window, the focus moves to the next window.
+    tree.remove(window1_id);
+    assert_eq!(tree.focused_window_id(), Some(window2_id));
+    assert_eq!(tree.parent_id(&cursor_id), Some(&window2_id));
+    assert_eq!(tree.window_ids().len(), 1);
+
+    // Remove the last window, the cursor inside it is removed as well.
+    tree.remove(window2_id);
+    assert_eq!(tree.cursor_id(), None);
+    assert!(tree.node(&cursor_id).is_none());
+    assert!(tree.window_ids().is_empty());
+
+    // Remove an unfocused window doesn't change the focus.
+    let (tree, window1_id, window2_id, _cursor_id) = make_tree();
+    let mut tree2 = wlock!(tree);
+    tree2.remove(window2_id);
+    assert_eq!(tree2.focused_window_id(), Some(window1_id));
+  }
+
+  fn window_shape(tree: &Tree, window_id: TreeNodeId) -> IRect {
+    *tree.node(&window_id).unwrap().shape()
+  }
+
+  fn viewport_lines(tree: &Tree, window_id: TreeNodeId) -> (usize, usize) {
+    match tree.node(&window_id).unwrap() {
+      TreeNode::Window(window) => {
+        let viewport = window.viewport();
+        let viewport = rlock!(viewport);
+        (viewport.start_line_idx(), viewport.end_line_idx())
+      }
+      _ => unreachable!(),
+    }
+  }
+
+  // Make a tree with one window fills the terminal, and the cursor is inside it.
+  fn make_split_tree(buffer: &BufferArc) -> (Tree, TreeNodeId, TreeNodeId) {
+    let mut tree = Tree::new(U16Size::new(20, 10));
+    let root_id = tree.root_id();
+    let window = Window::new(
+      IRect::new((0, 0), (20, 10)),
+      Arc::downgrade(buffer),
+      tree.local_options(),
+    );
+    let window_id = window.id();
+    tree
+      .bounded_insert(&root_id, TreeNode::Window(window))
+      .unwrap();
+    let cursor = Cursor::new(IRect::new((0, 1), (1, 2)));
+    let cursor_id = cursor.id();
+    tree
+      .bounded_insert(&window_id, TreeNode::Cursor(cursor))
+      .unwrap();
+    (tree, window_id, cursor_id)
+  }
+
+  #[test]
+  fn split1() {
+    let lines: Vec<String> = (0..20).map(|i| format!("line {}\n", i)).collect();
+    let buffer = make_buffer_from_lines(lines.iter().map(|l| l.as_str()).collect());
+    let (mut tree, window1_id, cursor_id) = make_split_tree(&buffer);
+    assert_eq!(viewport_lines(&tree, window1_id), (0, 10));
+
+    // The new window is on the top half, and it's focused.
+    let window2_id = tree
+      .split_window(window1_id, SplitDirection::Horizontal)
+      .unwrap();
+    assert_eq!(window_shape(&tree, window2_id), IRect::new((0, 0), (20, 5)));
+    assert_eq!(
+      window_shape(&tree, window1_id),
+      IRect::new((0, 5), (20, 10))
+    );
+    assert_eq!(viewport_lines(&tree, window1_id), (0, 5));
+    assert_eq!(viewport_lines(&tree, window2_id), (0, 5));
+    assert_eq!(tree.focused_window_id(), Some(window2_id));
+    assert_eq!(tree.parent_id(&cursor_id), Some(&window2_id));
+    assert_eq!(
+      *tree.node(&cursor_id).unwrap().shape(),
+      IRect::new((0, 1), (1, 2))
+    );
+
+    // Split the bottom window into left and right.
+    let window3_id = tree
+      .split_window(window1_id, SplitDirection::Vertical)
+      .unwrap();
+    assert_eq!(
+      window_shape(&tree, window3_id),
+      IRect::new((0, 5), (10, 10))
+    );
+    assert_eq!(
+      window_shape(&tree, window1_id),
+      IRect::new((10, 5), (20, 10))
+    );
+    assert_eq!(tree.focused_window_id(), Some(window2_id));
+    assert_eq!(
+      *tree.node(&window1_id).unwrap().actual_shape(),
+      U16Rect::new((10, 5), (20, 10))
+    );
+
+    // Close the right window, the left window takes its space.
+    assert!(tree.close_window(window1_id));
+    assert_eq!(
+      window_shape(&tree, window3_id),
+      IRect::new((0, 5), (20, 10))
+    );
+    // Close the top window, the bottom window takes the whole space.
+    assert!(tree.close_window(window2_id));
+    assert_eq!(
+      window_shape(&tree, window3_id),
+      IRect::new((0, 0), (20, 10))
+    );
+    assert_eq!(viewport_lines(&tree, window3_id), (0, 10));
+    assert_eq!(tree.focused_window_id(), Some(window3_id));
+
+    // The last window cannot be closed.
+    assert!(!tree.close_window(window3_id));
+    assert!(!tree.close_window(cursor_id));
+    assert!(tree
+      .split_window(cursor_id, SplitDirection::Vertical)
+      .is_none());
+
+    // The top window has no adjacent window that shares its bottom edge, the space is empty.
+    let (mut tree, window1_id, _cursor_id) = make_split_tree(&buffer);
+    let window2_id = tree
+      .split_window(window1_id, SplitDirection::Horizontal)
+      .unwrap();
+    let window3_id = tree
+      .split_window(window1_id, SplitDirection::Vertical)
+      .unwrap();
+    assert!(tree.close_window(window2_id));
+    assert_eq!(tree.window_ids().len(), 2);
+    assert_eq!(
+      window_shape(&tree, window3_id),
+      IRect::new((0, 5), (10, 10))
+    );
+    assert_eq!(
+      window_shape(&tree, window1_id),
+      IRect::new((10, 5), (20, 10))
+    );
+  }
+  #[test]
+  fn split2() {
+    let buffer = make_buffer_from_lines(vec!["line 0\n"]);
+
+    // The 'splitbelow' is off by default, the new window occupies the top half.
+    let (mut tree, window1_id, _cursor_id) = make_split_tree(&buffer);
+    assert!(!tree.split_below());
+    let window2_id = tree
+      .split_window(window1_id, SplitDirection::Horizontal)
+      .unwrap();
+    assert_eq!(window_shape(&tree, window2_id), IRect::new((0, 0), (20, 5)));
+    assert_eq!(
+      window_shape(&tree, window1_id),
+      IRect::new((0, 5), (20, 10))
+    );
+
+    // The 'splitbelow' is on, the split window keeps the top half.
+    let (mut tree, window1_id, _cursor_id) = make_split_tree(&buffer);
+    tree.set_split_below(true);
+    let window2_id = tree
+      .split_window(window1_id, SplitDirection::Horizontal)
+      .unwrap();
+    assert_eq!(window_shape(&tree, window1_id), IRect::new((0, 0), (20, 5)));
+    assert_eq!(
+      window_shape(&tree, window2_id),
+      IRect::new((0, 5), (20, 10))
+    );
+    assert_eq!(tree.focused_window_id(), Some(window2_id));
+
+    // The 'splitright' is on, the new window is on the right half.
+    tree.set_split_right(true);
+    let window3_id = tree
+      .split_window(window2_id, SplitDirection::Vertical)
+      .unwrap();
+    assert_eq!(
+      window_shape(&tree, window2_id),
+      IRect::new((0, 5), (10, 10))
+    );
+    assert_eq!(
+      window_shape(&tree, window3_id),
+      IRect::new((10, 5), (20, 10))
+    );
+  }
+  #[test]
+  fn resize_window1() {
+    let buffer = make_buffer_from_lines(vec!["line 0\n"]);
+    let (mut tree, window1_id, _cursor_id) = make_split_tree(&buffer);
+    let window2_id = tree
+      .split_window(window1_id, SplitDirection::Horizontal)
+      .unwrap();
+    let heights = |tree: &Tree| {
+      (
+        window_shape(tree, window2_id).height(),
+        window_shape(tree, window1_id).height(),
+      )
+    };
+
+    // The top window grows by 3 rows, the bottom window gives them.
+    assert!(tree.resize_window(window2_id, SplitDirection::Horizontal, 5 + 3));
+    assert_eq!(heights(&tree), (8, 2));
+    assert_eq!(
+      window_shape(&tree, window1_id),
+      IRect::new((0, 8), (20, 10))
+    );
+
+    // The bottom window keeps the 'winminheight' rows.
+    assert!(tree.resize_window(window2_id, SplitDirection::Horizontal, 100));
+    assert_eq!(heights(&tree), (9, 1));
+    tree.set_win_min_height(3);
+    assert!(tree.resize_window(window2_id, SplitDirection::Horizontal, 0));
+    assert_eq!(heights(&tree), (3, 7));
+
+    // The bottom window has no windows below it, it takes the rows from the top window.
+    assert!(tree.resize_window(window1_id, SplitDirection::Horizontal, 6));
+    assert_eq!(heights(&tree), (4, 6));
+    assert_eq!(window_shape(&tree, window2_id), IRect::new((0, 0), (20, 4)));
+
+    // The windows are equally high.
+    tree.equalize_windows();
+    assert_eq!(heights(&tree), (5, 5));
+
+    // Split the bottom window into left and right, the width is resized between them, while the
+    // top window has no windows on its left/right.
+    let window3_id = tree
+      .split_window(window1_id, SplitDirection::Vertical)
+      .unwrap();
+    assert!(tree.resize_window(window3_id, SplitDirection::Vertical, 14));
+    assert_eq!(
+      window_shape(&tree, window3_id),
+      IRect::new((0, 5), (14, 10))
+    );
+    assert_eq!(
+      window_shape(&tree, window1_id),
+      IRect::new((14, 5), (20, 10))
+    );
+    assert!(!tree.resize_window(window2_id, SplitDirection::Vertical, 10));
+    tree.equalize_windows();
+    assert_eq!(window_shape(&tree, window3_id).width(), 10);
+    assert_eq!(window_shape(&tree, window1_id).width(), 10);
+    assert_eq!(window_shape(&tree, window2_id).width(), 20);
+
+    // The left window cannot change the height alone.
+    assert!(!tree.resize_window(window3_id, SplitDirection::Horizontal, 3));
+  }
+
+  #[test]
+  fn equal_always1() {
+    let buffer = make_buffer_from_lines(vec!["line 0\n"]);
+    let heights = |tree: &Tree, window_ids: &[TreeNodeId]| -> Vec<isize> {
+      window_ids
+        .iter()
+        .map(|id| window_shape(tree, *id).height())
+        .collect()
+    };
+
+    // The 'equalalways' is on by default, the windows are made the same size after each split.
+    let (mut tree, window1_id, _cursor_id) = make_split_tree(&buffer);
+    assert!(tree.equal_always());
+    let window2_id = tree
+      .split_window(window1_id, SplitDirection::Horizontal)
+      .unwrap();
+    let window3_id = tree
+      .split_window(window1_id, SplitDirection::Horizontal)
+      .unwrap();
+    assert_eq!(
+      heights(&tree, &[window2_id, window3_id, window1_id]),
+      vec![4, 3, 3]
+    );
+    let window4_id = tree
+      .split_window(window1_id, SplitDirection::Horizontal)
+      .unwrap();
+    assert_eq!(
+      heights(&tree, &[window2_id, window3_id, window4_id, window1_id]),
+      vec![3, 3, 2, 2]
+    );
+    assert_eq!(
+      window_shape(&tree, window1_id),
+      IRect::new((0, 8), (20, 10))
+    );
+
+    // The windows are resized manually, closing a window doesn't make them the same size.
+    assert!(tree.resize_window(window2_id, SplitDirection::Horizontal, 6));
+    assert!(tree.close_window(window4_id));
+    assert_eq!(
+      heights(&tree, &[window2_id, window3_id, window1_id]),
+      vec![5, 1, 4]
+    );
+    // Until the next split.
+    let window5_id = tree
+      .split_window(window1_id, SplitDirection::Horizontal)
+      .unwrap();
+    assert_eq!(
+      heights(&tree, &[window2_id, window3_id, window5_id, window1_id]),
+      vec![3, 3, 2, 2]
+    );
+    assert!(tree.close_window(window5_id));
+    assert_eq!(
+      heights(&tree, &[window2_id, window3_id, window1_id]),
+      vec![4, 3, 3]
+    );
+
+    // The 'equalalways' is off.
+    let (mut tree, window1_id, _cursor_id) = make_split_tree(&buffer);
+    tree.set_equal_always(false);
+    let window2_id = tree
+      .split_window(window1_id, SplitDirection::Horizontal)
+      .unwrap();
+    let window3_id = tree
+      .split_window(window1_id, SplitDirection::Horizontal)
+      .unwrap();
+    assert_eq!(
+      heights(&tree, &[window2_id, window3_id, window1_id]),
+      vec![5, 2, 3]
+    );
+
+    // The window with 'winfixheight' keeps its height.
+    let (mut tree, window1_id, _cursor_id) = make_split_tree(&buffer);
+    let window2_id = tree
+      .split_window(window1_id, SplitDirection::Horizontal)
+      .unwrap();
+    match tree.node_mut(&window2_id) {
+      Some(TreeNode::Window(window)) => window.set_win_fix_height(true),
+      _ => unreachable!(),
+    }
+    let window3_id = tree
+      .split_window(window1_id, SplitDirection::Horizontal)
+      .unwrap();
+    assert_eq!(
+      heights(&tree, &[window2_id, window3_id, window1_id]),
+      vec![5, 3, 2]
+    );
+  }
+
+  // The buffer name of the focused window.
+  fn focused_buffer_name(tree: &TreeArc) -> String {
+    let tree = rlock!(tree);
+    match tree.node(&tree.focused_window_id().unwrap()).unwrap() {
+      TreeNode::Window(window) => rlock!(window.buffer().upgrade().unwrap()).display_name(),
+      _ => unreachable!(),
+    }
+  }
+
+  fn tab_line_styled(tree: &TreeArc) -> String {
+    let tree = rlock!(tree);
+    snapshot_styled(&tree, U16Size::new(20, 6)).remove(0)
+  }
+
+  #[test]
+  fn resize1() {
+    let buffer = make_buffer_from_lines(vec!["a\n"]);
+    let mut tree = Tree::new(U16Size::new(20, 6));
+    tree.init_layout(Arc::downgrade(&buffer));
+    let window1_id = tree.focused_window_id().unwrap();
+    let window2_id = tree
+      .split_window(window1_id, SplitDirection::Vertical)
+      .unwrap();
+    let left = window_shape(&tree, window2_id);
+    let right = window_shape(&tree, window1_id);
+    assert_eq!(left.min(), (0, 0).into());
+    assert_eq!(right.max(), (20, 5).into());
+    tree.take_redraw();
+
+    // The windows on the right/bottom edge are stretched, the message area is on the last row.
+    tree.resize(U16Size::new(30, 8));
+    assert_eq!(
+      window_shape(&tree, window2_id),
+      IRect::new((0, 0), (left.max().x, 7))
+    );
+    assert_eq!(
+      window_shape(&tree, window1_id),
+      IRect::new(right.min(), (30, 7))
+    );
+    let message_area_id = tree.message_area_id().unwrap();
+    assert_eq!(
+      *tree.node(&message_area_id).unwrap().shape(),
+      IRect::new((0, 7), (30, 8))
+    );
+    assert!(tree.take_redraw());
+  }
+
+  #[test]
+  fn tab1() {
+    let buffers: Vec<BufferArc> = ["a", "b", "c"]
+      .iter()
+      .map(|name| {
+        let buffer = make_buffer_from_lines(vec![format!("{}\n", name).as_str()]);
+        wlock!(buffer).set_name(Some(name.to_string()));
+        buffer
+      })
+      .collect();
+    let size = U16Size::new(20, 6);
+    let mut tree = Tree::new(size);
+    let root_id = tree.root_id();
+    let window1 = Window::new(
+      IRect::new((0, 0), (20, 5)),
+      Arc::downgrade(&buffers[0]),
+      tree.local_options(),
+    );
+    let window1_id = window1.id();
+    tree
+      .bounded_insert(&root_id, TreeNode::Window(window1))
+      .unwrap();
+    let cursor = Cursor::new(IRect::new((0, 0), (1, 1)));
+    let cursor_id = cursor.id();
+    tree
+      .bounded_insert(&window1_id, TreeNode::Cursor(cursor))
+      .unwrap();
+    let message_area = MessageArea::new(IRect::new((0, 5), (20, 6)));
+    tree
+      .bounded_insert(&root_id, TreeNode::MessageArea(message_area))
+      .unwrap();
+    assert_eq!(tree.tab_count(), 1);
+    assert!(tree.tab_line_id().is_none());
+
+    // The new tab pages are below the tab line, only the active one's window is in the tree.
+    let window2_id = tree.new_tab(Arc::downgrade(&buffers[1])).unwrap();
+    let window3_id = tree.new_tab(Arc::downgrade(&buffers[2])).unwrap();
+    assert_eq!((tree.tab_count(), tree.active_tab()), (3, 2));
+    assert_eq!(tree.window_ids().len(), 1);
+    assert_eq!(tree.parent_id(&cursor_id), Some(&window3_id));
+    assert!(tree.node(&window2_id).is_none());
+    assert_eq!(window_shape(&tree, window3_id), IRect::new((0, 1), (20, 5)));
+    assert!(tree.take_redraw());
+    assert!(!tree.take_redraw());
+    assert_eq!(
+      snapshot(&tree, size),
+      vec![
+        " a  b  c            ",
+        "c                   ",
+        "~                   ",
+        "~                   ",
+        "~                   ",
+        "                    ",
+      ]
+    );
+    let tree = Tree::to_arc(tree);
+    assert_eq!(
+      tab_line_styled(&tree),
+      "<bg=DarkGrey,attrs=Underlined> a  b </><attrs=Bold> c </><attrs=Reverse>           </>"
+    );
+
+    // Cycle the tab pages, the first window is moved below the tab line.
+    let mut state = State::default();
+    press(&mut state, &tree, KeyCode::Char('g'));
+    press(&mut state, &tree, KeyCode::Char('t'));
+    assert_eq!(rlock!(tree).active_tab(), 0);
+    assert_eq!(focused_buffer_name(&tree), "a");
+    assert_eq!(rlock!(tree).parent_id(&cursor_id), Some(&window1_id));
+    assert_eq!(
+      window_shape(&rlock!(tree), window1_id),
+      IRect::new((0, 1), (20, 5))
+    );
+    assert_eq!(
+      tab_line_styled(&tree),
+      "<attrs=Bold> a </><bg=DarkGrey,attrs=Underlined> b  c </><attrs=Reverse>           </>"
+    );
+    press(&mut state, &tree, KeyCode::Char('g'));
+    press(&mut state, &tree, KeyCode::Char('T'));
+    assert_eq!(focused_buffer_name(&tree), "c");
+    for c in ['2', 'g', 't'] {
+      press(&mut state, &tree, KeyCode::Char(c));
+    }
+    assert_eq!(rlock!(tree).active_tab(), 1);
+    assert_eq!(focused_buffer_name(&tree), "b");
+    assert_eq!(rlock!(tree).parent_id(&cursor_id), Some(&window2_id));
+
+    // Close the middle tab page, the next tab page is active.
+    let mut tree = wlock!(tree);
+    assert!(tree.close_tab());
+    assert_eq!((tree.tab_count(), tree.active_tab()), (2, 1));
+    assert_eq!(tree.focused_window_id(), Some(window3_id));
+    assert_eq!(snapshot(&tree, size)[0], " a  c               ");
+
+    // Close the last but one tab page, the tab line is hidden.
+    assert!(tree.close_tab());
+    assert_eq!(tree.tab_count(), 1);
+    assert!(tree.tab_line_id().is_none());
+    assert_eq!(tree.focused_window_id(), Some(window1_id));
+    assert_eq!(window_shape(&tree, window1_id), IRect::new((0, 0), (20, 5)));
+    assert!(!tree.close_tab());
+  }
+
+  #[test]
+  fn draw_clipped1() {
+    let size = U16Size::new(12, 5);
+    let mut tree = Tree::new(size);
+    let root_id = tree.root_id();
+
+    // The float is partially outside of the left and bottom edges.
+    let buffer1 = make_buffer_from_lines(vec!["0123456789abc\n", "你好世界ABC\n"]);
+    let window1 = Window::new(
+      IRect::new((-3, 2), (7, 8)),
+      Arc::downgrade(&buffer1),
+      tree.local_options(),
+    );
+    let window1_id = window1.id();
+    tree.insert(&root_id, TreeNode::Window(window1)).unwrap();
+    // The float is partially outside of the right edge.
+    let buffer2 = make_buffer_from_lines(vec!["ab你好世界\n"]);
+    let window2 = Window::new(
+      IRect::new((5, 0), (15, 2)),
+      Arc::downgrade(&buffer2),
+      tree.local_options(),
+    );
+    tree.insert(&root_id, TreeNode::Window(window2)).unwrap();
+
+    assert_eq!(
+      *tree.node(&window1_id).unwrap().actual_shape(),
+      U16Rect::new((0, 2), (7, 5))
+    );
+    // The viewport is still based on the whole window width, i.e. the lines wrap at 10 columns.
+    if let Some(TreeNode::Window(window)) = tree.node(&window1_id) {
+      let viewport = window.viewport();
+      let viewport = rlock!(viewport);
+      assert_eq!(viewport.lines().get(&0).unwrap().rows().len(), 2);
+    }
+
+    // Only the visible slices are rendered, the wide chars cut by the edges are spaces.
+    assert_eq!(
+      snapshot(&tree, size),
+      vec![
+        "     ab你好 ",
+        "     ~      ",
+        "3456789     ",
+        "            ",
+        " 世界AB     ",
+      ]
+    );
+  }
+
+  #[test]
+  fn stale_regions1() {
+    let (tree, window1_id, _, _) = make_tree();
+    let mut tree = wlock!(tree);
+    assert!(tree.take_stale_regions().is_empty());
+
+    // The shrunk window leaves its previous region.
+    tree.reshape_window(window1_id, IRect::new((0, 0), (10, 5)));
+    assert_eq!(
+      tree.take_stale_regions(),
+      vec![U16Rect::new((0, 0), (10, 10))]
+    );
+    assert!(tree.take_stale_regions().is_empty());
+
+    // The shape is not changed.
+    tree.reshape_window(window1_id, IRect::new((0, 0), (10, 5)));
+    assert!(tree.take_stale_regions().is_empty());
+  }
+
+  #[test]
+  fn sync_gutters1() {
+    let buffer = make_buffer_from_lines(vec!["a\n"; 999]);
+    let mut tree = Tree::new(U16Size::new(20, 6));
+    tree.set_number(true);
+    tree.init_layout(Arc::downgrade(&buffer));
+    let window_id = tree.focused_window_id().unwrap();
+    let cursor_id = tree.cursor_id().unwrap();
+
+    // The cursor starts after the 'number' column, i.e. "999 ".
+    assert_eq!(
+      *tree.node(&cursor_id).unwrap().shape(),
+      IRect::new((4, 0), (5, 1))
+    );
+
+    // The 'number' column is widened for the 1000th line, the cursor moves along with the text.
+    wlock!(buffer).append_lines(0, &["b".to_string()]).unwrap();
+    tree.sync_gutters();
+    match tree.node(&window_id).unwrap() {
+      TreeNode::Window(window) => {
+        assert_eq!(window.gutter_width(), 5);
+        assert_eq!(rlock!(window.viewport()).actual_shape().width(), 15);
+      }
+      _ => unreachable!(),
+    }
+    assert_eq!(
+      *tree.node(&cursor_id).unwrap().shape(),
+      IRect::new((5, 0), (6, 1))
+    );
+  }
 }
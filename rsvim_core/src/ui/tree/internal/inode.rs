@@ -177,6 +177,13 @@ pub struct InodeBase {
   visible: bool,
 }
 
+impl Default for InodeBase {
+  /// Make a node with empty shape at top-left corner.
+  fn default() -> Self {
+    InodeBase::new(IRect::new((0, 0), (0, 0)))
+  }
+}
+
 impl InodeBase {
   pub fn new(shape: IRect) -> Self {
     let actual_shape = geo_rect_as!(shape, u16);
@@ -298,6 +305,36 @@ mod tests {
     assert_eq!(*n2.borrow().shape(), IRect::new((1, 2), (3, 4)));
   }
 
+  #[test]
+  fn default1() {
+    let n1 = InodeBase::default();
+    let n2 = InodeBase::default();
+    assert!(n1.id() < n2.id());
+    assert_eq!(*n1.depth(), 0);
+    assert_eq!(*n1.zindex(), 0);
+    assert!(*n1.enabled());
+    assert!(*n1.visible());
+    assert_eq!(*n1.shape(), IRect::new((0, 0), (0, 0)));
+    assert_eq!(*n1.actual_shape(), U16Rect::new((0, 0), (0, 0)));
+  }
+
+  #[test]
+  fn attributes1() {
+    let mut n1 = InodeBase::new(IRect::new((1, 2), (3, 4)));
+    *n1.depth_mut() = 2;
+    *n1.zindex_mut() = 5;
+    *n1.shape_mut() = IRect::new((2, 3), (4, 5));
+    *n1.actual_shape_mut() = U16Rect::new((2, 3), (4, 5));
+    *n1.enabled_mut() = false;
+    *n1.visible_mut() = false;
+    assert_eq!(*n1.depth(), 2);
+    assert_eq!(*n1.zindex(), 5);
+    assert_eq!(*n1.shape(), IRect::new((2, 3), (4, 5)));
+    assert_eq!(*n1.actual_shape(), U16Rect::new((2, 3), (4, 5)));
+    assert!(!*n1.enabled());
+    assert!(!*n1.visible());
+  }
+
   #[test]
   fn next_node_id1() {
     assert!(next_node_id() > 0);
@@ -2,6 +2,7 @@
 
 use crate::cart::{IPos, IRect, U16Rect};
 use crate::geo_rect_as;
+use crate::res::{TreeErr, TreeResult};
 use crate::ui::tree::internal::shapes;
 use crate::ui::tree::internal::{InodeId, Inodeable};
 
@@ -296,10 +297,10 @@ where
   /// 1. `None` if the `child_node` doesn't exist.
   /// 2. The previous node on the same `child_node` ID, i.e. the inserted key.
   ///
-  /// # Panics
+  /// # Errors
   ///
-  /// If `parent_id` doesn't exist.
-  pub fn insert(&mut self, parent_id: &InodeId, mut child_node: T) -> Option<T> {
+  /// It returns [`TreeErr::NodeNotExist`] if `parent_id` doesn't exist, and the tree is unchanged.
+  pub fn insert(&mut self, parent_id: &InodeId, mut child_node: T) -> TreeResult<Option<T>> {
     // trace!(
     //   "parent_id:{:?}, node_ids:{:?}, children_ids:{:?}",
     //   parent_id,
@@ -307,14 +308,8 @@ where
     //   self.children_ids
     // );
 
-    // Panics if `parent_id` not exists.
-    assert!(self.nodes.contains_key(parent_id));
-    assert!(
-      self.children_ids.contains_key(parent_id),
-      "children_ids {:?} doesn't contains parent_id {:?}",
-      self.children_ids,
-      parent_id
-    );
+    // Never creates orphan edges if `parent_id` not exists.
+    self.parent_guard(parent_id)?;
 
     // Insert node.
     let child_id = child_node.id();
@@ -324,34 +319,8 @@ where
     self.children_ids.insert(child_id, vec![]);
 
     // Maps its parent => the inserted child.
-    //
-    // It inserts child ID to the `children_ids` vector of the parent, sorted by the Z-index.
-    // For the children that have the same z-index value, it inserts at the end of those children.
     self.parent_ids.insert(child_id, *parent_id);
-    let higher_zindex_pos: Vec<usize> = self
-      .children_ids
-      .get(parent_id)
-      .unwrap()
-      .iter()
-      .enumerate()
-      .filter(|(_index, cid)| match self.nodes.get(cid) {
-        Some(cnode) => *cnode.zindex() > child_zindex,
-        None => false,
-      })
-      .map(|(index, _cid)| index)
-      .collect();
-    match higher_zindex_pos.first() {
-      Some(insert_pos) => {
-        self
-          .children_ids
-          .get_mut(parent_id)
-          .unwrap()
-          .insert(*insert_pos, child_id);
-      }
-      None => {
-        self.children_ids.get_mut(parent_id).unwrap().push(child_id);
-      }
-    }
+    self.insert_sorted(parent_id, child_id, child_zindex);
 
     // Update the inserted child attributes:
     // 1. Depth.
@@ -381,7 +350,51 @@ where
     } // unsafe
 
     // Maps inserted child ID => the struct itself.
-    self.nodes.insert(child_id, child_node)
+    Ok(self.nodes.insert(child_id, child_node))
+  }
+
+  // Make sure the `parent_id` is a node of the tree.
+  fn parent_guard(&self, parent_id: &InodeId) -> TreeResult<()> {
+    if self.nodes.contains_key(parent_id) && self.children_ids.contains_key(parent_id) {
+      Ok(())
+    } else {
+      Err(TreeErr::NodeNotExist(*parent_id))
+    }
+  }
+
+  // Insert child ID to the `children_ids` vector of the parent, sorted by the z-index. For the
+  // children that have the same z-index value, it inserts at the end of those children, i.e. the
+  // insertion order is preserved.
+  fn insert_sorted(&mut self, parent_id: &InodeId, child_id: InodeId, child_zindex: usize) {
+    let nodes = &self.nodes;
+    let children_ids = self.children_ids.get_mut(parent_id).unwrap();
+    let insert_pos = children_ids.partition_point(|cid| match nodes.get(cid) {
+      Some(cnode) => *cnode.zindex() <= child_zindex,
+      None => true,
+    });
+    children_ids.insert(insert_pos, child_id);
+  }
+
+  /// Set the z-index of a node, and move it to the new sorted position in the children vector of
+  /// its parent. For the children that have the same z-index value, it's moved to the end of
+  /// those children, i.e. it's rendered later.
+  ///
+  /// # Returns
+  ///
+  /// 1. `None` if the node `id` doesn't exist.
+  /// 2. The previous z-index of the node.
+  pub fn reorder_child(&mut self, id: InodeId, zindex: usize) -> Option<usize> {
+    let node = self.nodes.get_mut(&id)?;
+    let old_zindex = std::mem::replace(node.zindex_mut(), zindex);
+
+    // The root node has no siblings.
+    if let Some(parent_id) = self.parent_ids.get(&id).copied() {
+      let children_ids = self.children_ids.get_mut(&parent_id).unwrap();
+      children_ids.retain(|cid| *cid != id);
+      self.insert_sorted(&parent_id, id, zindex);
+    }
+
+    Some(old_zindex)
   }
 
   /// Insert a node to the tree.
@@ -400,12 +413,15 @@ where
   /// 1. `None` if the `child_node` doesn't exist.
   /// 2. The previous node on the same `child_node` ID, i.e. the inserted key.
   ///
-  /// # Panics
+  /// # Errors
   ///
-  /// If `parent_id` doesn't exist.
-  pub fn bounded_insert(&mut self, parent_id: &InodeId, mut child_node: T) -> Option<T> {
-    // Panics if `parent_id` not exists.
-    assert!(self.nodes.contains_key(parent_id));
+  /// It returns [`TreeErr::NodeNotExist`] if `parent_id` doesn't exist, and the tree is unchanged.
+  pub fn bounded_insert(
+    &mut self,
+    parent_id: &InodeId,
+    mut child_node: T,
+  ) -> TreeResult<Option<T>> {
+    self.parent_guard(parent_id)?;
 
     let parent_node = self.nodes.get(parent_id).unwrap();
     let parent_actual_shape = parent_node.actual_shape();
@@ -418,10 +434,9 @@ where
 
   /// Remove a node by its ID.
   ///
-  /// This operation breaks the connection between the removed node and its parent.
-  ///
-  /// But the relationships between the removed node and its descendants still remains in the tree,
-  /// thus once you insert it back in the same tree, its descendants are still connected with the removed node.
+  /// This operation breaks the connection between the removed node and its parent, and all its
+  /// descendants are removed from the tree as well, thus the nodes collection always equals to the
+  /// nodes reachable from the root.
   ///
   /// # Returns
   ///
@@ -460,6 +475,15 @@ where
           }
           None => { /* Skip */ }
         }
+
+        // Remove all the descendants.
+        let mut queue: VecDeque<InodeId> = VecDeque::new();
+        queue.extend(self.children_ids.remove(&id).unwrap_or_default());
+        while let Some(descendant_id) = queue.pop_front() {
+          self.nodes.remove(&descendant_id);
+          self.parent_ids.remove(&descendant_id);
+          queue.extend(self.children_ids.remove(&descendant_id).unwrap_or_default());
+        }
         Some(removed)
       }
       None => None,
@@ -663,6 +687,29 @@ where
     }
   }
 
+  /// Set the shape of a node, i.e. move and resize it.
+  ///
+  /// NOTE: This operation also updates all descendants attributes (same with the
+  /// [`insert`](Itree::insert) method).
+  ///
+  /// # Returns
+  ///
+  /// 1. The previous shape if successfully.
+  /// 2. `None` if the node `id` doesn't exist, or it's the root node.
+  pub fn reshape(&mut self, id: InodeId, shape: IRect) -> Option<IRect> {
+    let parent_id = *self.parent_ids.get(&id)?;
+    let node = self.nodes.get_mut(&id)?;
+    let previous_shape = *node.shape();
+    *node.shape_mut() = shape;
+
+    // Update all the descendants attributes under the `id` node.
+    unsafe {
+      self.update_descendant_attributes(id, parent_id);
+    }
+
+    Some(previous_shape)
+  }
+
   /// Get the relative position of a node based on its parent.
   ///
   /// It returns the position enum, see [`InodeRelativePosition`].
@@ -837,11 +884,11 @@ mod tests {
      * ```
      */
     let mut tree = Itree::new(n1);
-    tree.insert(&nid1, n2);
-    tree.insert(&nid1, n3);
-    tree.insert(&nid2, n4);
-    tree.insert(&nid2, n5);
-    tree.insert(&nid3, n6);
+    tree.insert(&nid1, n2).unwrap();
+    tree.insert(&nid1, n3).unwrap();
+    tree.insert(&nid2, n4).unwrap();
+    tree.insert(&nid2, n5).unwrap();
+    tree.insert(&nid3, n6).unwrap();
 
     assert!(tree.root_id() == nid1);
     let n1 = tree.node(&nid1).unwrap();
@@ -961,14 +1008,14 @@ mod tests {
      * ```
      */
     let mut tree = Itree::new(n1);
-    tree.insert(&nid1, n2);
-    tree.insert(&nid1, n3);
-    tree.insert(&nid2, n4);
-    tree.insert(&nid2, n5);
-    tree.insert(&nid3, n6);
-    tree.insert(&nid5, n7);
-    tree.insert(&nid7, n8);
-    tree.insert(&nid7, n9);
+    tree.insert(&nid1, n2).unwrap();
+    tree.insert(&nid1, n3).unwrap();
+    tree.insert(&nid2, n4).unwrap();
+    tree.insert(&nid2, n5).unwrap();
+    tree.insert(&nid3, n6).unwrap();
+    tree.insert(&nid5, n7).unwrap();
+    tree.insert(&nid7, n8).unwrap();
+    tree.insert(&nid7, n9).unwrap();
 
     assert!(tree.root_id() == nid1);
     let n1 = tree.node(&nid1).unwrap();
@@ -1125,14 +1172,14 @@ mod tests {
      * ```
      */
     let mut tree = Itree::new(n1);
-    tree.insert(&nid1, n2);
-    tree.insert(&nid1, n3);
-    tree.insert(&nid2, n4);
-    tree.insert(&nid2, n5);
-    tree.insert(&nid3, n6);
-    tree.insert(&nid5, n7);
-    tree.insert(&nid7, n8);
-    tree.insert(&nid7, n9);
+    tree.insert(&nid1, n2).unwrap();
+    tree.insert(&nid1, n3).unwrap();
+    tree.insert(&nid2, n4).unwrap();
+    tree.insert(&nid2, n5).unwrap();
+    tree.insert(&nid3, n6).unwrap();
+    tree.insert(&nid5, n7).unwrap();
+    tree.insert(&nid7, n8).unwrap();
+    tree.insert(&nid7, n9).unwrap();
 
     assert!(tree.root_id() == nid1);
     let n1 = tree.node(&nid1).unwrap();
@@ -1212,11 +1259,11 @@ mod tests {
      * ```
      */
     let mut tree = Itree::new(n1);
-    tree.insert(&nid1, n2);
-    tree.insert(&nid1, n3);
-    tree.insert(&nid2, n4);
-    tree.insert(&nid4, n5);
-    tree.insert(&nid5, n6);
+    tree.insert(&nid1, n2).unwrap();
+    tree.insert(&nid1, n3).unwrap();
+    tree.insert(&nid2, n4).unwrap();
+    tree.insert(&nid4, n5).unwrap();
+    tree.insert(&nid5, n6).unwrap();
 
     assert!(tree.root_id() == nid1);
     let n1 = tree.node(&nid1).unwrap();
@@ -1263,7 +1310,7 @@ mod tests {
      */
     let mut tree = Itree::new(nodes[0]);
     for node in nodes.iter().skip(1) {
-      tree.insert(&nodes_ids[0], *node);
+      tree.insert(&nodes_ids[0], *node).unwrap();
     }
 
     assert!(tree.root_id() == nodes_ids[0]);
@@ -1295,6 +1342,136 @@ mod tests {
     }
   }
 
+  #[test]
+  fn reorder_child1() {
+    // test_log_init();
+
+    let shape = IRect::new((0, 0), (10, 10));
+    let n1 = TestValue::new(1, shape);
+    let nid1 = n1.id();
+    let mut children: Vec<TestValue> = [2, 3, 4]
+      .iter()
+      .map(|value| TestValue::new(*value, shape))
+      .collect();
+    *children[0].zindex_mut() = 5;
+    *children[1].zindex_mut() = 1;
+    *children[2].zindex_mut() = 10;
+    let nid2 = children[0].id();
+    let nid3 = children[1].id();
+    let nid4 = children[2].id();
+
+    let mut tree = Itree::new(n1);
+    for child in children.iter() {
+      tree.insert(&nid1, *child).unwrap();
+    }
+    let visited = |tree: &Itree<TestValue>| tree.iter().map(|n| n.id()).collect::<Vec<_>>();
+    assert_eq!(tree.children_ids(&nid1).unwrap(), &vec![nid3, nid2, nid4]);
+    assert_eq!(visited(&tree), vec![nid1, nid3, nid2, nid4]);
+
+    // Bump the lowest one to the highest.
+    assert_eq!(tree.reorder_child(nid3, 20), Some(1));
+    assert_eq!(*tree.node(&nid3).unwrap().zindex(), 20);
+    assert_eq!(tree.children_ids(&nid1).unwrap(), &vec![nid2, nid4, nid3]);
+    assert_eq!(visited(&tree), vec![nid1, nid2, nid4, nid3]);
+
+    // The same z-index, it's placed after the existing ones.
+    assert_eq!(tree.reorder_child(nid2, 10), Some(5));
+    assert_eq!(tree.children_ids(&nid1).unwrap(), &vec![nid4, nid2, nid3]);
+
+    // The inserted child with the same z-index is placed after the existing ones.
+    let mut n5 = TestValue::new(5, shape);
+    *n5.zindex_mut() = 10;
+    let nid5 = n5.id();
+    tree.insert(&nid1, n5).unwrap();
+    assert_eq!(
+      tree.children_ids(&nid1).unwrap(),
+      &vec![nid4, nid2, nid5, nid3]
+    );
+
+    // The root node and the not exist node.
+    assert_eq!(tree.reorder_child(nid1, 3), Some(0));
+    assert_eq!(tree.reorder_child(InodeId::MAX, 3), None);
+  }
+
+  #[test]
+  fn insert3() {
+    // test_log_init();
+
+    let s1 = IRect::new((0, 0), (10, 10));
+    let n1 = TestValue::new(1, s1);
+    let nid1 = n1.id();
+
+    let s2 = IRect::new((5, 5), (15, 15));
+    let mut n2 = TestValue::new(2, s2);
+    *n2.zindex_mut() = 3;
+    *n2.visible_mut() = false;
+    let nid2 = n2.id();
+
+    let s3 = IRect::new((-2, -2), (3, 3));
+    let mut n3 = TestValue::new(3, s3);
+    *n3.enabled_mut() = false;
+    let nid3 = n3.id();
+
+    let mut tree = Itree::new(n1);
+    assert!(tree.insert(&nid1, n2).unwrap().is_none()).unwrap();
+    assert!(tree.insert(&nid1, n3).unwrap().is_none()).unwrap();
+
+    // Higher z-index is placed after lower z-index.
+    assert_eq!(tree.children_ids(&nid1).unwrap(), &vec![nid3, nid2]);
+
+    let n2 = tree.node(&nid2).unwrap();
+    assert_eq!(*n2.depth(), 1);
+    assert_eq!(*n2.zindex(), 3);
+    assert!(!*n2.visible());
+    assert!(*n2.enabled());
+    assert_eq!(*n2.shape(), s2);
+    assert_eq!(*n2.actual_shape(), U16Rect::new((5, 5), (10, 10)));
+
+    let n3 = tree.node(&nid3).unwrap();
+    assert_eq!(*n3.depth(), 1);
+    assert_eq!(*n3.zindex(), 0);
+    assert!(*n3.visible());
+    assert!(!*n3.enabled());
+    assert_eq!(*n3.shape(), s3);
+    assert_eq!(*n3.actual_shape(), U16Rect::new((0, 0), (3, 3)));
+
+    let removed2 = tree.remove(nid2).unwrap();
+    assert_eq!(*removed2.zindex(), 3);
+    assert!(!*removed2.visible());
+    assert!(tree.node(&nid2).is_none());
+    assert!(tree.parent_id(&nid2).is_none());
+    assert_eq!(tree.children_ids(&nid1).unwrap(), &vec![nid3]);
+  }
+
+  #[test]
+  fn insert4() {
+    // test_log_init();
+
+    let s1 = IRect::new((0, 0), (10, 10));
+    let n1 = TestValue::new(1, s1);
+    let n2 = TestValue::new(2, s1);
+    let n3 = TestValue::new(3, s1);
+    let nid2 = n2.id();
+    let nid3 = n3.id();
+    let mut tree = Itree::new(n1);
+
+    // Inserting under a non-existent parent never creates orphan edges.
+    let parent_id = nid2 + 1000;
+    assert_eq!(
+      tree.insert(&parent_id, n2).unwrap_err(),
+      TreeErr::NodeNotExist(parent_id)
+    );
+    assert_eq!(
+      tree.bounded_insert(&parent_id, n3).unwrap_err(),
+      TreeErr::NodeNotExist(parent_id)
+    );
+    assert_eq!(tree.len(), 1);
+    assert!(tree.node(&nid2).is_none());
+    assert!(tree.parent_id(&nid2).is_none());
+    assert!(tree.children_ids(&nid3).is_none());
+    assert!(tree.children_ids(&parent_id).is_none());
+  }
+
   fn make_tree(n: usize) -> (Vec<InodeId>, Itree<TestValue>) {
     let mut value = 1;
     let mut node_ids: Vec<InodeId> = vec![];
@@ -1310,7 +1487,7 @@ mod tests {
       let node = TestValue::new(value, s);
       let node_id = node.id();
       value += 1;
-      tree.insert(&root_id, node);
+      tree.insert(&root_id, node).unwrap();
       node_ids.push(node_id);
     }
 
@@ -1369,6 +1546,58 @@ mod tests {
     tree.remove(node_ids[0]);
   }
 
+  #[test]
+  fn remove3() {
+    // test_log_init();
+
+    // A deep chain: 1 -> 2 -> ... -> 300, and a sibling chain under the root.
+    let s = IRect::new((0, 0), (10, 10));
+    let root = TestValue::new(0, s);
+    let root_id = root.id();
+    let mut tree = Itree::new(root);
+    let mut chain_ids: Vec<InodeId> = vec![];
+    let mut parent_id = root_id;
+    for value in 1..=300 {
+      let node = TestValue::new(value, s);
+      let node_id = node.id();
+      tree.insert(&parent_id, node).unwrap();
+      chain_ids.push(node_id);
+      parent_id = node_id;
+    }
+    let sibling = TestValue::new(1000, s);
+    let sibling_id = sibling.id();
+    tree.insert(&root_id, sibling).unwrap();
+    assert_eq!(tree.len(), 302);
+    assert_eq!(tree.iter().count(), tree.len());
+
+    // Remove the middle subtree, all its descendants are removed.
+    let removed = tree.remove(chain_ids[149]).unwrap();
+    assert_node_value_eq!(removed, 150);
+    assert_eq!(tree.len(), 151);
+    assert_eq!(tree.iter().count(), tree.len());
+    for (i, id) in chain_ids.iter().enumerate() {
+      if i < 149 {
+        let node = tree.node(id).unwrap();
+        assert_node_value_eq!(node, i as i32 + 1);
+        assert_eq!(*node.depth(), i + 1);
+      } else {
+        assert!(tree.node(id).is_none());
+        assert!(tree.parent_id(id).is_none());
+        assert!(tree.children_ids(id).is_none());
+      }
+    }
+    assert!(tree.children_ids(&chain_ids[148]).unwrap().is_empty());
+    assert_eq!(tree.node(&sibling_id).unwrap().value, 1000);
+    assert!(tree.remove(chain_ids[200]).is_none());
+
+    // The tree still works after removal.
+    let node = TestValue::new(2000, s);
+    let node_id = node.id();
+    tree.insert(&chain_ids[148], node).unwrap();
+    assert_eq!(*tree.node(&node_id).unwrap().depth(), 150);
+    assert_eq!(tree.iter().count(), tree.len());
+  }
+
   #[test]
   fn get1() {
     // test_log_init();
@@ -1424,14 +1653,14 @@ mod tests {
      * ```
      */
     let mut tree = Itree::new(n1);
-    tree.insert(&nid1, n2);
-    tree.insert(&nid1, n3);
-    tree.insert(&nid2, n4);
-    tree.insert(&nid2, n5);
-    tree.insert(&nid3, n6);
-    tree.insert(&nid5, n7);
-    tree.insert(&nid7, n8);
-    tree.insert(&nid7, n9);
+    tree.insert(&nid1, n2).unwrap();
+    tree.insert(&nid1, n3).unwrap();
+    tree.insert(&nid2, n4).unwrap();
+    tree.insert(&nid2, n5).unwrap();
+    tree.insert(&nid3, n6).unwrap();
+    tree.insert(&nid5, n7).unwrap();
+    tree.insert(&nid7, n8).unwrap();
+    tree.insert(&nid7, n9).unwrap();
 
     assert!(nid1 == tree.root_id());
     let n1 = tree.node(&nid1).unwrap();
@@ -1503,11 +1732,11 @@ mod tests {
      * ```
      */
     let mut tree = Itree::new(n1);
-    tree.insert(&nid1, n2);
-    tree.insert(&nid1, n3);
-    tree.insert(&nid2, n4);
-    tree.insert(&nid4, n5);
-    tree.insert(&nid5, n6);
+    tree.insert(&nid1, n2).unwrap();
+    tree.insert(&nid1, n3).unwrap();
+    tree.insert(&nid2, n4).unwrap();
+    tree.insert(&nid4, n5).unwrap();
+    tree.insert(&nid5, n6).unwrap();
 
     let n1 = tree.node(&nid1).unwrap();
     let n2 = tree.node(&nid2).unwrap();
@@ -1558,8 +1787,8 @@ mod tests {
      * ```
      */
     let mut tree = Itree::new(n1);
-    tree.insert(&nid1, n2);
-    tree.insert(&nid2, n3);
+    tree.insert(&nid1, n2).unwrap();
+    tree.insert(&nid2, n3).unwrap();
 
     let n1 = tree.node(&nid1).unwrap();
     let n2 = tree.node(&nid2).unwrap();
@@ -1603,6 +1832,44 @@ mod tests {
     }
   }
 
+  #[test]
+  fn reshape1() {
+    let n1 = TestValue::new(1, IRect::new((0, 0), (20, 20)));
+    let nid1 = n1.id();
+    let n2 = TestValue::new(2, IRect::new((0, 0), (10, 10)));
+    let nid2 = n2.id();
+    let n3 = TestValue::new(3, IRect::new((1, 1), (3, 3)));
+    let nid3 = n3.id();
+
+    let mut tree = Itree::new(n1);
+    tree.insert(&nid1, n2).unwrap();
+    tree.insert(&nid2, n3).unwrap();
+
+    // The root node cannot be reshaped.
+    assert_eq!(tree.reshape(nid1, IRect::new((0, 0), (5, 5))), None);
+
+    // Resize n2, its descendants are clipped by the new shape.
+    assert_eq!(
+      tree.reshape(nid2, IRect::new((0, 0), (2, 2))),
+      Some(IRect::new((0, 0), (10, 10)))
+    );
+    assert_eq!(
+      *tree.node(&nid2).unwrap().actual_shape(),
+      U16Rect::new((0, 0), (2, 2))
+    );
+    assert_eq!(
+      *tree.node(&nid3).unwrap().actual_shape(),
+      U16Rect::new((1, 1), (2, 2))
+    );
+
+    // Move n2, its descendants move with it.
+    tree.reshape(nid2, IRect::new((10, 5), (20, 15)));
+    assert_eq!(
+      *tree.node(&nid3).unwrap().actual_shape(),
+      U16Rect::new((11, 6), (13, 8))
+    );
+  }
+
   #[test]
   fn bounded_move_by1() {
     // test_log_init();
@@ -1630,8 +1897,8 @@ mod tests {
      * ```
      */
     let mut tree = Itree::new(n1);
-    tree.insert(&nid1, n2);
-    tree.insert(&nid2, n3);
+    tree.insert(&nid1, n2).unwrap();
+    tree.insert(&nid2, n3).unwrap();
 
     let n1 = tree.node(&nid1).unwrap();
     let n2 = tree.node(&nid2).unwrap();
@@ -8,7 +8,20 @@ use regex::Regex;
 
 #[derive(Debug, Clone)]
 /// Global window options.
-pub struct WindowGlobalOptions {}
+pub struct WindowGlobalOptions {
+  inc_search: bool,
+  hl_search: bool,
+  ignore_case: bool,
+  smart_case: bool,
+  title: bool,
+  term_gui_colors: bool,
+  lazy_redraw: bool,
+  split_below: bool,
+  split_right: bool,
+  win_min_height: usize,
+  win_min_width: usize,
+  equal_always: bool,
+}
 
 impl Default for WindowGlobalOptions {
   fn default() -> Self {
@@ -20,15 +33,240 @@ impl WindowGlobalOptions {
   pub fn builder() -> WindowGlobalOptionsBuilder {
     WindowGlobalOptionsBuilder::default()
   }
+
+  /// The 'incsearch' option, highlight the match while typing the search pattern, default to
+  /// `true`.
+  /// See: <https://vimhelp.org/options.txt.html#%27incsearch%27>.
+  pub fn inc_search(&self) -> bool {
+    self.inc_search
+  }
+
+  pub fn set_inc_search(&mut self, value: bool) {
+    self.inc_search = value;
+  }
+
+  /// The 'hlsearch' option, highlight all the matches of the last search pattern, default to
+  /// `true`.
+  /// See: <https://vimhelp.org/options.txt.html#%27hlsearch%27>.
+  pub fn hl_search(&self) -> bool {
+    self.hl_search
+  }
+
+  pub fn set_hl_search(&mut self, value: bool) {
+    self.hl_search = value;
+  }
+
+  /// The 'ignorecase' option, ignore case in search patterns, default to `false`.
+  /// See: <https://vimhelp.org/options.txt.html#%27ignorecase%27>.
+  pub fn ignore_case(&self) -> bool {
+    self.ignore_case
+  }
+
+  pub fn set_ignore_case(&mut self, value: bool) {
+    self.ignore_case = value;
+  }
+
+  /// The 'smartcase' option, override 'ignorecase' if the search pattern contains uppercase
+  /// chars, default to `false`.
+  /// See: <https://vimhelp.org/options.txt.html#%27smartcase%27>.
+  pub fn smart_case(&self) -> bool {
+    self.smart_case
+  }
+
+  pub fn set_smart_case(&mut self, value: bool) {
+    self.smart_case = value;
+  }
+
+  /// The 'title' option, set the terminal title to the file name of current window, default to
+  /// `true`.
+  /// See: <https://vimhelp.org/options.txt.html#%27title%27>.
+  pub fn title(&self) -> bool {
+    self.title
+  }
+
+  pub fn set_title(&mut self, value: bool) {
+    self.title = value;
+  }
+
+  /// The 'termguicolors' option, use the RGB colors, otherwise the RGB colors are downsampled to
+  /// the 256 colors (or 16 colors if the terminal doesn't support 256 colors). Default to `false`,
+  /// but it's turned on at startup if the terminal supports truecolor.
+  /// See: <https://vimhelp.org/options.txt.html#%27termguicolors%27>.
+  pub fn term_gui_colors(&self) -> bool {
+    self.term_gui_colors
+  }
+
+  pub fn set_term_gui_colors(&mut self, value: bool) {
+    self.term_gui_colors = value;
+  }
+
+  /// The 'lazyredraw' option, skip the intermediate renders while the render is suppressed, i.e.
+  /// replaying the keys, only the final state is rendered. Default to `false`.
+  /// See: <https://vimhelp.org/options.txt.html#%27lazyredraw%27>.
+  pub fn lazy_redraw(&self) -> bool {
+    self.lazy_redraw
+  }
+
+  pub fn set_lazy_redraw(&mut self, value: bool) {
+    self.lazy_redraw = value;
+  }
+
+  /// The 'splitbelow' option, the new window of the horizontal split is below the split window,
+  /// default to `false`.
+  /// See: <https://vimhelp.org/options.txt.html#%27splitbelow%27>.
+  pub fn split_below(&self) -> bool {
+    self.split_below
+  }
+
+  pub fn set_split_below(&mut self, value: bool) {
+    self.split_below = value;
+  }
+
+  /// The 'splitright' option, the new window of the vertical split is on the right of the split
+  /// window, default to `false`.
+  /// See: <https://vimhelp.org/options.txt.html#%27splitright%27>.
+  pub fn split_right(&self) -> bool {
+    self.split_right
+  }
+
+  pub fn set_split_right(&mut self, value: bool) {
+    self.split_right = value;
+  }
+
+  /// The 'winminheight' option, the minimal height of the windows when resizing them, default to
+  /// `1`.
+  /// See: <https://vimhelp.org/options.txt.html#%27winminheight%27>.
+  pub fn win_min_height(&self) -> usize {
+    self.win_min_height
+  }
+
+  pub fn set_win_min_height(&mut self, value: usize) {
+    self.win_min_height = value;
+  }
+
+  /// The 'winminwidth' option, the minimal width of the windows when resizing them, default to
+  /// `1`.
+  /// See: <https://vimhelp.org/options.txt.html#%27winminwidth%27>.
+  pub fn win_min_width(&self) -> usize {
+    self.win_min_width
+  }
+
+  pub fn set_win_min_width(&mut self, value: usize) {
+    self.win_min_width = value;
+  }
+
+  /// The 'equalalways' option, all the windows are made the same size after splitting or closing
+  /// a window, default to `true`.
+  /// See: <https://vimhelp.org/options.txt.html#%27equalalways%27>.
+  pub fn equal_always(&self) -> bool {
+    self.equal_always
+  }
+
+  pub fn set_equal_always(&mut self, value: bool) {
+    self.equal_always = value;
+  }
 }
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone)]
 /// Global window options builder.
-pub struct WindowGlobalOptionsBuilder {}
+pub struct WindowGlobalOptionsBuilder {
+  inc_search: bool,
+  hl_search: bool,
+  ignore_case: bool,
+  smart_case: bool,
+  title: bool,
+  term_gui_colors: bool,
+  lazy_redraw: bool,
+  split_below: bool,
+  split_right: bool,
+  win_min_height: usize,
+  win_min_width: usize,
+  equal_always: bool,
+}
 
 impl WindowGlobalOptionsBuilder {
+  pub fn inc_search(&mut self, value: bool) -> &mut Self {
+    self.inc_search = value;
+    self
+  }
+  pub fn hl_search(&mut self, value: bool) -> &mut Self {
+    self.hl_search = value;
+    self
+  }
+  pub fn ignore_case(&mut self, value: bool) -> &mut Self {
+    self.ignore_case = value;
+    self
+  }
+  pub fn smart_case(&mut self, value: bool) -> &mut Self {
+    self.smart_case = value;
+    self
+  }
+  pub fn title(&mut self, value: bool) -> &mut Self {
+    self.title = value;
+    self
+  }
+  pub fn term_gui_colors(&mut self, value: bool) -> &mut Self {
+    self.term_gui_colors = value;
+    self
+  }
+  pub fn lazy_redraw(&mut self, value: bool) -> &mut Self {
+    self.lazy_redraw = value;
+    self
+  }
+  pub fn split_below(&mut self, value: bool) -> &mut Self {
+    self.split_below = value;
+    self
+  }
+  pub fn split_right(&mut self, value: bool) -> &mut Self {
+    self.split_right = value;
+    self
+  }
+  pub fn win_min_height(&mut self, value: usize) -> &mut Self {
+    self.win_min_height = value;
+    self
+  }
+  pub fn win_min_width(&mut self, value: usize) -> &mut Self {
+    self.win_min_width = value;
+    self
+  }
+  pub fn equal_always(&mut self, value: bool) -> &mut Self {
+    self.equal_always = value;
+    self
+  }
   pub fn build(&self) -> WindowGlobalOptions {
-    WindowGlobalOptions {}
+    WindowGlobalOptions {
+      inc_search: self.inc_search,
+      hl_search: self.hl_search,
+      ignore_case: self.ignore_case,
+      smart_case: self.smart_case,
+      title: self.title,
+      term_gui_colors: self.term_gui_colors,
+      lazy_redraw: self.lazy_redraw,
+      split_below: self.split_below,
+      split_right: self.split_right,
+      win_min_height: self.win_min_height,
+      win_min_width: self.win_min_width,
+      equal_always: self.equal_always,
+    }
+  }
+}
+
+impl Default for WindowGlobalOptionsBuilder {
+  fn default() -> Self {
+    WindowGlobalOptionsBuilder {
+      inc_search: defaults::win::INC_SEARCH,
+      hl_search: defaults::win::HL_SEARCH,
+      ignore_case: defaults::win::IGNORE_CASE,
+      smart_case: defaults::win::SMART_CASE,
+      title: defaults::win::TITLE,
+      term_gui_colors: defaults::win::TERM_GUI_COLORS,
+      lazy_redraw: defaults::win::LAZY_REDRAW,
+      split_below: defaults::win::SPLIT_BELOW,
+      split_right: defaults::win::SPLIT_RIGHT,
+      win_min_height: defaults::win::WIN_MIN_HEIGHT,
+      win_min_width: defaults::win::WIN_MIN_WIDTH,
+      equal_always: defaults::win::EQUAL_ALWAYS,
+    }
   }
 }
 
@@ -38,7 +276,44 @@ mod tests {
 
   #[test]
   fn default1() {
-    let _opt1 = WindowGlobalOptions::builder().build();
-    let _opt2 = WindowGlobalOptionsBuilder::default().build();
+    let opt1 = WindowGlobalOptions::builder().build();
+    assert!(opt1.inc_search());
+    assert!(opt1.hl_search());
+    assert!(!opt1.ignore_case());
+    assert!(!opt1.smart_case());
+    assert!(opt1.title());
+    assert!(!opt1.term_gui_colors());
+    assert!(!opt1.lazy_redraw());
+    assert!(!opt1.split_below());
+    assert!(!opt1.split_right());
+    assert_eq!(opt1.win_min_height(), 1);
+    assert_eq!(opt1.win_min_width(), 1);
+    assert!(opt1.equal_always());
+    let opt2 = WindowGlobalOptionsBuilder::default()
+      .inc_search(false)
+      .hl_search(false)
+      .ignore_case(true)
+      .smart_case(true)
+      .title(false)
+      .term_gui_colors(true)
+      .lazy_redraw(true)
+      .split_below(true)
+      .split_right(true)
+      .win_min_height(2)
+      .win_min_width(3)
+      .equal_always(false)
+      .build();
+    assert!(!opt2.inc_search());
+    assert!(!opt2.hl_search());
+    assert!(opt2.ignore_case());
+    assert!(opt2.smart_case());
+    assert!(!opt2.title());
+    assert!(opt2.term_gui_colors());
+    assert!(opt2.lazy_redraw());
+    assert!(opt2.split_below());
+    assert!(opt2.split_right());
+    assert_eq!(opt2.win_min_height(), 2);
+    assert_eq!(opt2.win_min_width(), 3);
+    assert!(!opt2.equal_always());
   }
 }
@@ -6,11 +6,15 @@ use crate::ui::canvas::Canvas;
 
 // Re-export
 pub use crate::ui::widget::cursor::Cursor;
+pub use crate::ui::widget::message_area::MessageArea;
 pub use crate::ui::widget::root::RootContainer;
+pub use crate::ui::widget::tab_line::TabLine;
 pub use crate::ui::widget::window::Window;
 
 pub mod cursor;
+pub mod message_area;
 pub mod root;
+pub mod tab_line;
 pub mod window;
 
 /// Base trait for all UI widgets.
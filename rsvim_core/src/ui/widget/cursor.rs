@@ -27,6 +27,14 @@ impl Cursor {
       style: CursorStyle::DefaultUserShape,
     }
   }
+
+  pub fn style(&self) -> CursorStyle {
+    self.style
+  }
+
+  pub fn set_style(&mut self, style: CursorStyle) {
+    self.style = style;
+  }
 }
 
 impl Debug for Cursor {
@@ -0,0 +1,130 @@
+//! Message area widget.
+
+use crate::buf::ColorSchemes;
+use crate::cart::{IRect, U16Pos, U16Rect};
+use crate::envar;
+use crate::state::message::{Message, MessageKind};
+use crate::ui::canvas::{Canvas, Cell};
+use crate::ui::tree::internal::{InodeBase, InodeId, Inodeable};
+use crate::ui::widget::Widgetable;
+use crate::{inode_generate_impl, rlock};
+
+use compact_str::ToCompactString;
+use crossterm::style::Color;
+use tracing::trace;
+use unicode_width::UnicodeWidthChar;
+
+#[derive(Debug, Clone)]
+/// The message area widget, it shows the latest message on the last row of the terminal.
+///
+/// NOTE: The message longer than the widget width is truncated.
+pub struct MessageArea {
+  base: InodeBase,
+  message: Option<Message>,
+}
+
+impl MessageArea {
+  pub fn new(shape: IRect) -> Self {
+    MessageArea {
+      base: InodeBase::new(shape),
+      message: None,
+    }
+  }
+
+  pub fn message(&self) -> &Option<Message> {
+    &self.message
+  }
+
+  pub fn set_message(&mut self, message: Option<Message>) {
+    self.message = message;
+  }
+}
+
+inode_generate_impl!(MessageArea, base);
+
+impl Widgetable for MessageArea {
+  fn draw(&self, canvas: &mut Canvas) {
+    let actual_shape = self.actual_shape();
+    let upos: U16Pos = actual_shape.min().into();
+    let width = actual_shape.width() as usize;
+
+    // If size is zero, exit.
+    if actual_shape.height() == 0 || width == 0 {
+      trace!("Draw message area, actual shape is zero");
+      return;
+    }
+
+    let mut cells: Vec<Cell> = Vec::with_capacity(width);
+    if let Some(message) = &self.message {
+      let group = match message.kind() {
+        MessageKind::Info => "Normal",
+        MessageKind::Error => "ErrorMsg",
+      };
+      let (fg, bg, attrs) = rlock!(ColorSchemes::global())
+        .resolve(group)
+        .unwrap_or_default();
+      let (fg, bg) = (fg.unwrap_or(Color::Reset), bg.unwrap_or(Color::Reset));
+      for c in message.text().chars() {
+        let c_width = c.width().unwrap_or(0);
+        if c_width == 0 {
+          continue;
+        }
+        if cells.len() + c_width > width {
+          break;
+        }
+        let cell = Cell::new(c.to_compact_string(), fg, bg, attrs);
+        let guard = Cell::wide_guard(&cell);
+        cells.push(cell);
+        // The wide char occupies more than 1 cell, the rest cells are the guard cells.
+        cells.extend((1..c_width).map(|_| guard.clone()));
+      }
+    }
+    cells.resize(width, Cell::space());
+
+    canvas.frame_mut().set_cells_at(upos, cells);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use crate::cart::U16Size;
+  use crate::test::render::{frame_snapshot, frame_snapshot_styled};
+
+  fn make_drawn_canvas(size: U16Size, message: Option<Message>) -> Canvas {
+    let shape = IRect::new((0, 0), (size.width() as isize, size.height() as isize));
+    let mut message_area = MessageArea::new(shape);
+    message_area.set_message(message);
+    let mut canvas = Canvas::new(size);
+    message_area.draw(&mut canvas);
+    canvas
+  }
+
+  #[test]
+  fn draw1() {
+    let canvas = make_drawn_canvas(U16Size::new(10, 1), Some(Message::info("Hello")));
+    assert_eq!(frame_snapshot(canvas.frame()), vec!["Hello     "]);
+    assert_eq!(frame_snapshot_styled(canvas.frame()), vec!["Hello     "]);
+
+    // Truncated to width.
+    let canvas = make_drawn_canvas(U16Size::new(10, 1), Some(Message::info("Hello, RSVIM!")));
+    assert_eq!(frame_snapshot(canvas.frame()), vec!["Hello, RSV"]);
+
+    let canvas = make_drawn_canvas(U16Size::new(10, 1), None);
+    assert_eq!(frame_snapshot(canvas.frame()), vec!["          "]);
+  }
+
+  #[test]
+  fn draw2() {
+    let canvas = make_drawn_canvas(
+      U16Size::new(10, 1),
+      Some(Message::error("E32: No file name")),
+    );
+    assert_eq!(frame_snapshot(canvas.frame()), vec!["E32: No fi"]);
+    assert_eq!(
+      frame_snapshot_styled(canvas.frame()),
+      vec!["<fg=Red,attrs=Bold>E32: No fi</>"]
+    );
+  }
+}
@@ -0,0 +1,167 @@
+//! Tab line widget.
+
+use crate::buf::ColorSchemes;
+use crate::cart::{IRect, U16Pos};
+use crate::envar;
+use crate::ui::canvas::{Canvas, Cell};
+use crate::ui::tree::internal::{InodeBase, InodeId, Inodeable};
+use crate::ui::widget::Widgetable;
+use crate::{inode_generate_impl, rlock};
+
+use compact_str::ToCompactString;
+use crossterm::style::{Attributes, Color};
+use tracing::trace;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+#[derive(Debug, Clone)]
+/// The tab line widget, it lists the tab pages on the first row of the terminal, the active one is
+/// emphasized.
+///
+/// NOTE: When the labels are wider than the widget, each label is truncated to the same width and
+/// ends with `>`.
+///
+/// See: <https://vimhelp.org/tabpage.txt.html#tabline>.
+pub struct TabLine {
+  base: InodeBase,
+  labels: Vec<String>,
+  active: usize,
+}
+
+impl TabLine {
+  pub fn new(shape: IRect) -> Self {
+    TabLine {
+      base: InodeBase::new(shape),
+      labels: vec![],
+      active: 0,
+    }
+  }
+
+  /// The labels of the tab pages.
+  pub fn labels(&self) -> &Vec<String> {
+    &self.labels
+  }
+
+  /// The index of the active tab page.
+  pub fn active(&self) -> usize {
+    self.active
+  }
+
+  pub fn set_labels(&mut self, labels: Vec<String>, active: usize) {
+    self.labels = labels;
+    self.active = active;
+  }
+}
+
+inode_generate_impl!(TabLine, base);
+
+// The colors and attributes of the highlight group.
+fn resolve_style(group: &str) -> (Color, Color, Attributes) {
+  let (fg, bg, attrs) = rlock!(ColorSchemes::global())
+    .resolve(group)
+    .unwrap_or_default();
+  (
+    fg.unwrap_or(Color::Reset),
+    bg.unwrap_or(Color::Reset),
+    attrs,
+  )
+}
+
+impl Widgetable for TabLine {
+  fn draw(&self, canvas: &mut Canvas) {
+    let actual_shape = self.actual_shape();
+    let upos: U16Pos = actual_shape.min().into();
+    let width = actual_shape.width() as usize;
+
+    // If size is zero, exit.
+    if actual_shape.height() == 0 || width == 0 {
+      trace!("Draw tab line, actual shape is zero");
+      return;
+    }
+
+    let total_width: usize = self.labels.iter().map(|label| label.width()).sum();
+    let max_label_width = if total_width > width {
+      std::cmp::max(width / self.labels.len(), 1)
+    } else {
+      usize::MAX
+    };
+
+    let mut cells: Vec<Cell> = Vec::with_capacity(width);
+    for (i, label) in self.labels.iter().enumerate() {
+      let group = if i == self.active {
+        "TabLineSel"
+      } else {
+        "TabLine"
+      };
+      let (fg, bg, attrs) = resolve_style(group);
+      let truncated = label.width() > max_label_width;
+      let limit = if truncated {
+        max_label_width - 1
+      } else {
+        label.width()
+      };
+      let start = cells.len();
+      for c in label.chars() {
+        let c_width = c.width().unwrap_or(0);
+        if c_width == 0 {
+          continue;
+        }
+        if cells.len() - start + c_width > limit {
+          break;
+        }
+        let cell = Cell::new(c.to_compact_string(), fg, bg, attrs);
+        let guard = Cell::wide_guard(&cell);
+        cells.push(cell);
+        // The wide char occupies more than 1 cell, the rest cells are the guard cells.
+        cells.extend((1..c_width).map(|_| guard.clone()));
+      }
+      if truncated {
+        // Pad the cut wide char, then mark the label is truncated.
+        cells.resize(start + limit, Cell::new(" ".into(), fg, bg, attrs));
+        cells.push(Cell::new(">".into(), fg, bg, attrs));
+      }
+    }
+    // There're more tab pages than the columns.
+    cells.truncate(width);
+    let (fg, bg, attrs) = resolve_style("TabLineFill");
+    cells.resize(width, Cell::new(" ".into(), fg, bg, attrs));
+
+    canvas.frame_mut().set_cells_at(upos, cells);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use crate::cart::U16Size;
+  use crate::test::render::{frame_snapshot, frame_snapshot_styled};
+
+  fn make_drawn_canvas(width: u16, labels: Vec<&str>, active: usize) -> Canvas {
+    let mut tab_line = TabLine::new(IRect::new((0, 0), (width as isize, 1)));
+    tab_line.set_labels(labels.iter().map(|l| l.to_string()).collect(), active);
+    let mut canvas = Canvas::new(U16Size::new(width, 1));
+    tab_line.draw(&mut canvas);
+    canvas
+  }
+
+  #[test]
+  fn draw1() {
+    let canvas = make_drawn_canvas(16, vec![" a ", " + b "], 1);
+    assert_eq!(frame_snapshot(canvas.frame()), vec![" a  + b         "]);
+    assert_eq!(
+      frame_snapshot_styled(canvas.frame()),
+      vec!["<bg=DarkGrey,attrs=Underlined> a </><attrs=Bold> + b </><attrs=Reverse>        </>"]
+    );
+  }
+
+  #[test]
+  fn draw2() {
+    // Each label is truncated to 5 columns.
+    let canvas = make_drawn_canvas(16, vec![" foo.rs ", " 你好.txt ", " c "], 0);
+    assert_eq!(frame_snapshot(canvas.frame()), vec![" foo> 你 > c    "]);
+
+    // More tab pages than the columns.
+    let canvas = make_drawn_canvas(2, vec![" a ", " b ", " c "], 0);
+    assert_eq!(frame_snapshot(canvas.frame()), vec![">>"]);
+  }
+}
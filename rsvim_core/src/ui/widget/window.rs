@@ -1,28 +1,38 @@
 //! Vim window.
 
-use crate::buf::BufferWk;
-use crate::cart::{IRect, U16Rect};
+use crate::buf::{Buffer, BufferId, BufferWk};
+use crate::cart::{IRect, U16Pos, U16Rect};
 use crate::envar;
+use crate::state::motion::clamp_position;
+use crate::state::selection::{BufferPosition, Selection};
 use crate::ui::canvas::Canvas;
 use crate::ui::tree::internal::{InodeId, Inodeable, Itree};
 use crate::ui::widget::window::content::WindowContent;
+use crate::ui::widget::window::gutter::WindowGutter;
 use crate::ui::widget::window::root::WindowRootContainer;
 use crate::ui::widget::Widgetable;
-use crate::wlock;
+use crate::{rlock, wlock};
 
 // Re-export
+pub use crate::ui::widget::window::fold::{Fold, Folds};
+pub use crate::ui::widget::window::gutter::GutterWidths;
 pub use crate::ui::widget::window::opt::{
-  ViewportOptions, WindowLocalOptions, WindowOptionsBuilder,
+  FillChars, FoldMarker, FoldMethod, ListChars, SignColumn, ViewportOptions, WindowLocalOptions,
+  WindowOptionsBuilder,
 };
 pub use crate::ui::widget::window::viewport::{
-  CursorViewport, LineViewport, RowViewport, Viewport, ViewportArc,
+  CursorLinePosition, CursorViewport, LineViewport, RowViewport, Viewport, ViewportArc,
 };
 
+use geo::point;
+use std::collections::HashMap;
 use std::convert::From;
 use std::sync::Arc;
 // use tracing::trace;
 
 pub mod content;
+pub mod fold;
+pub mod gutter;
 pub mod opt;
 pub mod root;
 pub mod viewport;
@@ -37,6 +47,9 @@ pub struct Window {
   // The Window content widget ID.
   content_id: InodeId,
 
+  // The Window gutter widget ID.
+  gutter_id: InodeId,
+
   // Buffer.
   buffer: BufferWk,
 
@@ -46,6 +59,42 @@ pub struct Window {
 
   // Viewport.
   viewport: ViewportArc,
+
+  // The saved view states of the buffers shown in the window, restored when the window shows the
+  // buffer again.
+  view_states: HashMap<BufferId, ViewState>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+/// The cursor position and the viewport anchor of a buffer shown in a window. It's saved when the
+/// focus leaves the window or the window switches to another buffer, and restored when returning.
+pub struct ViewState {
+  /// The cursor line index.
+  pub cursor_line: usize,
+  /// The cursor char index in the line.
+  pub cursor_char: usize,
+  /// The top line of the viewport, i.e. `start_line` of [`Viewport`].
+  pub start_line: usize,
+  /// The first column of the viewport, i.e. `start_dcolumn` of [`Viewport`]. It's always 0 since
+  /// the viewport doesn't scroll horizontally.
+  pub start_bcolumn: usize,
+}
+
+impl ViewState {
+  /// Clamp the view state inside the buffer, the buffer may have shrunk since it was saved. The
+  /// top line doesn't go below the cursor line.
+  pub fn clamp(&self, buffer: &Buffer) -> ViewState {
+    let cursor = clamp_position(
+      buffer,
+      BufferPosition::new(self.cursor_line, self.cursor_char),
+    );
+    ViewState {
+      cursor_line: cursor.line_idx(),
+      cursor_char: cursor.char_idx(),
+      start_line: std::cmp::min(self.start_line, cursor.line_idx()),
+      start_bcolumn: self.start_bcolumn,
+    }
+  }
 }
 
 impl Window {
@@ -55,29 +104,62 @@ impl Window {
     let window_root = WindowRootContainer::new(shape);
     let window_root_id = window_root.id();
     let window_root_node = WindowNode::WindowRootContainer(window_root);
-    let window_root_actual_shape = *window_root_node.actual_shape();
 
-    let viewport_options = ViewportOptions {
-      wrap: options.wrap(),
-      line_break: options.line_break(),
+    let gutter_widths = match buffer.upgrade() {
+      Some(buffer) => GutterWidths::new(&options, &rlock!(buffer)),
+      None => GutterWidths::default(),
     };
-    let viewport = Viewport::new(&viewport_options, buffer.clone(), &window_root_actual_shape);
+    let gutter_width = gutter_width(shape, gutter_widths);
+
+    let viewport_options = ViewportOptions::from(&options);
+    let mut viewport = Viewport::new(
+      &viewport_options,
+      buffer.clone(),
+      &logical_shape(shape, gutter_width),
+    );
+    viewport.folds_mut().set_options(&options);
+    viewport.sync_from_top_left(0, 0);
     let viewport = Viewport::to_arc(viewport);
 
     let mut base = Itree::new(window_root_node);
 
-    let window_content = WindowContent::new(shape, buffer.clone(), Arc::downgrade(&viewport));
+    // The gutter is on the left of the content, they fill the whole window, their shapes are
+    // relative to the window.
+    let ((gutter_shape, gutter_clipped), (content_shape, content_clipped)) =
+      split_gutter(shape, gutter_width);
+    let mut window_gutter =
+      WindowGutter::new(gutter_shape, buffer.clone(), Arc::downgrade(&viewport));
+    window_gutter.set_widths(gutter_widths, gutter_width);
+    window_gutter.set_clipped_top_left(gutter_clipped);
+    let window_gutter_id = window_gutter.id();
+    let window_gutter_node = WindowNode::WindowGutter(window_gutter);
+
+    let mut window_content =
+      WindowContent::new(content_shape, buffer.clone(), Arc::downgrade(&viewport));
+    window_content.set_fill_chars(options.fill_chars());
+    window_content.set_list(options.list());
+    window_content.set_list_chars(options.list_chars());
+    window_content.set_show_break(options.show_break());
+    window_content.set_cursor_line(options.cursor_line());
+    window_content.set_clipped_top_left(content_clipped);
     let window_content_id = window_content.id();
     let window_content_node = WindowNode::WindowContent(window_content);
 
-    base.bounded_insert(&window_root_id, window_content_node);
+    base
+      .bounded_insert(&window_root_id, window_gutter_node)
+      .unwrap();
+    base
+      .bounded_insert(&window_root_id, window_content_node)
+      .unwrap();
 
     Window {
       base,
       content_id: window_content_id,
+      gutter_id: window_gutter_id,
       buffer,
       options,
       viewport,
+      view_states: HashMap::new(),
     }
   }
 }
@@ -180,7 +262,21 @@ impl Window {
   pub fn set_options(&mut self, options: &WindowLocalOptions) {
     self.options = options.clone();
     let viewport_options = ViewportOptions::from(&self.options);
-    wlock!(self.viewport).set_options(&viewport_options);
+    let mut viewport = wlock!(self.viewport);
+    viewport.set_options(&viewport_options);
+    viewport.folds_mut().set_options(&self.options);
+    drop(viewport);
+    let fill_chars = self.options.fill_chars();
+    let list = self.options.list();
+    let list_chars = self.options.list_chars();
+    let show_break = self.options.show_break().to_string();
+    let cursor_line = self.options.cursor_line();
+    let content = self.content_mut();
+    content.set_fill_chars(fill_chars);
+    content.set_list(list);
+    content.set_list_chars(list_chars);
+    content.set_show_break(&show_break);
+    content.set_cursor_line(cursor_line);
   }
 
   pub fn wrap(&self) -> bool {
@@ -203,6 +299,160 @@ impl Window {
     wlock!(self.viewport).set_options(&viewport_options);
   }
 
+  pub fn fill_chars(&self) -> FillChars {
+    self.options.fill_chars()
+  }
+
+  pub fn set_fill_chars(&mut self, value: FillChars) {
+    self.options.set_fill_chars(value);
+    self.content_mut().set_fill_chars(value);
+  }
+
+  pub fn list(&self) -> bool {
+    self.options.list()
+  }
+
+  pub fn set_list(&mut self, value: bool) {
+    self.options.set_list(value);
+    self.content_mut().set_list(value);
+  }
+
+  pub fn list_chars(&self) -> ListChars {
+    self.options.list_chars()
+  }
+
+  pub fn set_list_chars(&mut self, value: ListChars) {
+    self.options.set_list_chars(value);
+    self.content_mut().set_list_chars(value);
+  }
+
+  pub fn show_break(&self) -> &str {
+    self.options.show_break()
+  }
+
+  pub fn set_show_break(&mut self, value: &str) {
+    self.options.set_show_break(value.to_string());
+    let viewport_options = ViewportOptions::from(&self.options);
+    wlock!(self.viewport).set_options(&viewport_options);
+    self.content_mut().set_show_break(value);
+  }
+
+  pub fn scroll(&self) -> usize {
+    self.options.scroll()
+  }
+
+  pub fn set_scroll(&mut self, value: usize) {
+    self.options.set_scroll(value);
+    let viewport_options = ViewportOptions::from(&self.options);
+    wlock!(self.viewport).set_options(&viewport_options);
+  }
+
+  pub fn scroll_off(&self) -> usize {
+    self.options.scroll_off()
+  }
+
+  pub fn set_scroll_off(&mut self, value: usize) {
+    self.options.set_scroll_off(value);
+    let viewport_options = ViewportOptions::from(&self.options);
+    wlock!(self.viewport).set_options(&viewport_options);
+  }
+
+  pub fn fold_method(&self) -> FoldMethod {
+    self.options.fold_method()
+  }
+
+  /// Set the 'foldmethod' option, the folds are computed again.
+  pub fn set_fold_method(&mut self, value: FoldMethod) {
+    self.options.set_fold_method(value);
+    self.sync_folds();
+  }
+
+  pub fn fold_level(&self) -> usize {
+    self.options.fold_level()
+  }
+
+  pub fn set_fold_level(&mut self, value: usize) {
+    self.options.set_fold_level(value);
+    self.sync_folds();
+  }
+
+  pub fn fold_enable(&self) -> bool {
+    self.options.fold_enable()
+  }
+
+  pub fn set_fold_enable(&mut self, value: bool) {
+    self.options.set_fold_enable(value);
+    self.sync_folds();
+  }
+
+  pub fn fold_marker(&self) -> &FoldMarker {
+    self.options.fold_marker()
+  }
+
+  pub fn set_fold_marker(&mut self, value: FoldMarker) {
+    self.options.set_fold_marker(value);
+    self.sync_folds();
+  }
+
+  pub fn cursor_line(&self) -> bool {
+    self.options.cursor_line()
+  }
+
+  pub fn set_cursor_line(&mut self, value: bool) {
+    self.options.set_cursor_line(value);
+    self.content_mut().set_cursor_line(value);
+  }
+
+  pub fn number(&self) -> bool {
+    self.options.number()
+  }
+
+  /// Set the 'number' option. The gutter is composed again on next
+  /// [`sync_gutter`](Window::sync_gutter), same with the 'foldcolumn' and 'signcolumn' options.
+  pub fn set_number(&mut self, value: bool) {
+    self.options.set_number(value);
+  }
+
+  pub fn fold_column(&self) -> usize {
+    self.options.fold_column()
+  }
+
+  pub fn set_fold_column(&mut self, value: usize) {
+    self.options.set_fold_column(value);
+  }
+
+  pub fn sign_column(&self) -> SignColumn {
+    self.options.sign_column()
+  }
+
+  pub fn set_sign_column(&mut self, value: SignColumn) {
+    self.options.set_sign_column(value);
+  }
+
+  pub fn win_fix_height(&self) -> bool {
+    self.options.win_fix_height()
+  }
+
+  pub fn set_win_fix_height(&mut self, value: bool) {
+    self.options.set_win_fix_height(value);
+  }
+
+  pub fn win_fix_width(&self) -> bool {
+    self.options.win_fix_width()
+  }
+
+  pub fn set_win_fix_width(&mut self, value: bool) {
+    self.options.set_win_fix_width(value);
+  }
+
+  // Update the fold options of the viewport, and sync it from its current top line.
+  fn sync_folds(&mut self) {
+    let mut viewport = wlock!(self.viewport);
+    viewport.folds_mut().set_options(&self.options);
+    let start_line_idx = viewport.start_line_idx();
+    viewport.sync_from_top_left(start_line_idx, 0);
+  }
+
   /// Get viewport.
   pub fn viewport(&self) -> ViewportArc {
     self.viewport.clone()
@@ -212,17 +462,264 @@ impl Window {
   pub fn buffer(&self) -> BufferWk {
     self.buffer.clone()
   }
+
+  /// Set buffer, i.e. the window switches to another buffer. The view state of the current buffer
+  /// is saved, and the saved view state of the new buffer is restored (see
+  /// [`restore_view_state`](Window::restore_view_state)). Otherwise the viewport starts from the
+  /// first line of it.
+  pub fn set_buffer(&mut self, buffer: BufferWk) {
+    self.save_view_state();
+    self.buffer = buffer.clone();
+    self.content_mut().set_buffer(buffer.clone());
+    self.gutter_mut().set_buffer(buffer.clone());
+    let mut viewport = wlock!(self.viewport);
+    viewport.set_buffer(buffer);
+    viewport.folds_mut().clear();
+    drop(viewport);
+    self.restore_view_state();
+  }
+
+  /// Update the content and viewport after the window is moved or resized, i.e. its shape is
+  /// changed by [`Tree::reshape_window`](crate::ui::tree::Tree::reshape_window), or it's inserted
+  /// to the tree. The viewport starts from its current top line.
+  ///
+  /// NOTE: The viewport is based on the whole (logical) shape except the gutter even if the
+  /// window is partially outside of its parent, the content only renders the visible slice of it.
+  pub fn sync_shape(&mut self) {
+    let shape = *self.shape();
+    let gutter_widths = self.gutter().widths();
+    let gutter_width = gutter_width(shape, gutter_widths);
+    let ((gutter_shape, gutter_clipped), (content_shape, content_clipped)) =
+      split_gutter(shape, gutter_width);
+    self.base.reshape(self.gutter_id, gutter_shape);
+    let gutter = self.gutter_mut();
+    gutter.set_widths(gutter_widths, gutter_width);
+    gutter.set_clipped_top_left(gutter_clipped);
+    self.base.reshape(self.content_id, content_shape);
+    self.content_mut().set_clipped_top_left(content_clipped);
+    let mut viewport = wlock!(self.viewport);
+    viewport.set_actual_shape(&logical_shape(shape, gutter_width));
+    let start_line_idx = viewport.start_line_idx();
+    viewport.sync_from_top_left(start_line_idx, 0);
+  }
 }
 // Options }
 
+// The logical shape of the viewport, i.e. the whole size of the window shape except the gutter.
+fn logical_shape(shape: IRect, gutter_width: u16) -> U16Rect {
+  let width = std::cmp::max(shape.width(), 0) as u16 - gutter_width;
+  let height = std::cmp::max(shape.height(), 0) as u16;
+  U16Rect::new((0, 0), (width, height))
+}
+
+// The whole width of the gutter, at least 1 column is left for the content.
+fn gutter_width(shape: IRect, gutter_widths: GutterWidths) -> u16 {
+  let width = std::cmp::max(shape.width(), 0) as u16;
+  std::cmp::min(gutter_widths.total(), width.saturating_sub(1))
+}
+
+// The shapes and the clipped columns/rows of the gutter and the content, the shapes are relative
+// to the window. If the window is partially outside of its parent on the left, the clipped columns
+// are taken from the gutter first, thus the shape of the gutter only covers its visible slice.
+fn split_gutter(shape: IRect, gutter_width: u16) -> ((IRect, U16Pos), (IRect, U16Pos)) {
+  let clipped = clipped_top_left(shape);
+  let width = std::cmp::max(shape.width(), 0);
+  let height = std::cmp::max(shape.height(), 0);
+  let gutter_clipped_x = std::cmp::min(clipped.x(), gutter_width);
+  let gutter_visible_width = (gutter_width - gutter_clipped_x) as isize;
+  let gutter_shape = IRect::new((0, 0), (gutter_visible_width, height));
+  let content_shape = IRect::new(
+    (gutter_visible_width, 0),
+    (gutter_visible_width + width - gutter_width as isize, height),
+  );
+  (
+    (gutter_shape, point!(x: gutter_clipped_x, y: clipped.y())),
+    (
+      content_shape,
+      point!(x: clipped.x() - gutter_clipped_x, y: clipped.y()),
+    ),
+  )
+}
+
+// The clipped columns on the left and rows on the top, i.e. the negative position of the window
+// shape, which is relative to its parent.
+fn clipped_top_left(shape: IRect) -> U16Pos {
+  let x = std::cmp::max(-shape.min().x, 0) as u16;
+  let y = std::cmp::max(-shape.min().y, 0) as u16;
+  point!(x: x, y: y)
+}
+
+// View state {
+impl Window {
+  /// Get the view state of current buffer.
+  pub fn view_state(&self) -> ViewState {
+    let viewport = rlock!(self.viewport);
+    let cursor = viewport.cursor();
+    ViewState {
+      cursor_line: cursor.line_idx(),
+      cursor_char: cursor.char_idx(),
+      start_line: viewport.start_line_idx(),
+      start_bcolumn: 0,
+    }
+  }
+
+  /// Save the view state of current buffer, i.e. the focus leaves the window.
+  pub fn save_view_state(&mut self) {
+    if let Some(buffer) = self.buffer.upgrade() {
+      let buf_id = rlock!(buffer).id();
+      let view_state = self.view_state();
+      self.view_states.insert(buf_id, view_state);
+    }
+  }
+
+  /// Get the saved view state of the buffer.
+  pub fn saved_view_state(&self, buf_id: BufferId) -> Option<ViewState> {
+    self.view_states.get(&buf_id).copied()
+  }
+
+  /// Restore the saved view state of current buffer, i.e. the focus returns to the window. It's
+  /// clamped inside the buffer (see [`ViewState::clamp`]), the viewport starts from the first line
+  /// if there's no saved view state.
+  ///
+  /// NOTE: Only the viewport is updated, the cursor widget is not moved.
+  pub fn restore_view_state(&mut self) {
+    let view_state = match self.buffer.upgrade() {
+      Some(buffer) => {
+        let buffer = rlock!(buffer);
+        self
+          .saved_view_state(buffer.id())
+          .map(|view_state| view_state.clamp(&buffer))
+          .unwrap_or_default()
+      }
+      None => ViewState::default(),
+    };
+    let mut viewport = wlock!(self.viewport);
+    viewport.sync_from_top_left(view_state.start_line, view_state.start_bcolumn);
+    let cursor = viewport
+      .cursor_viewport_at(view_state.cursor_line, view_state.cursor_char)
+      .or_else(|| viewport.cursor_viewport_at(viewport.start_line_idx(), 0))
+      .unwrap_or(CursorViewport::new(0..1, 0, 0, 0));
+    viewport.set_cursor(cursor);
+  }
+
+  /// Remove the saved view state of the buffer, i.e. the buffer is removed.
+  pub fn forget_buffer(&mut self, buf_id: BufferId) {
+    self.view_states.remove(&buf_id);
+  }
+}
+// View state }
+
 // Viewport {
-impl Window {}
+impl Window {
+  /// Scroll the viewport to put the line at the top row, i.e. `zt` on the line. See
+  /// [`Viewport::scroll_line_to`].
+  pub fn top_on_line(&mut self, line_idx: usize) {
+    wlock!(self.viewport).scroll_line_to(line_idx, CursorLinePosition::Top);
+  }
+
+  /// Scroll the viewport to put the line at the middle row, i.e. `zz` on the line. The wrapped
+  /// rows of the lines above it are counted.
+  pub fn center_on_line(&mut self, line_idx: usize) {
+    wlock!(self.viewport).scroll_line_to(line_idx, CursorLinePosition::Center);
+  }
+
+  /// Scroll the viewport to put the line at the bottom row, i.e. `zb` on the line.
+  pub fn bottom_on_line(&mut self, line_idx: usize) {
+    wlock!(self.viewport).scroll_line_to(line_idx, CursorLinePosition::Bottom);
+  }
+}
 // Viewport }
 
+// Content {
+impl Window {
+  fn content(&self) -> &WindowContent {
+    match self.base.node(&self.content_id).unwrap() {
+      WindowNode::WindowContent(content) => content,
+      _ => unreachable!("Window content widget must be WindowContent"),
+    }
+  }
+
+  fn content_mut(&mut self) -> &mut WindowContent {
+    match self.base.node_mut(&self.content_id).unwrap() {
+      WindowNode::WindowContent(content) => content,
+      _ => unreachable!("Window content widget must be WindowContent"),
+    }
+  }
+}
+// Content }
+
+// Gutter {
+impl Window {
+  fn gutter(&self) -> &WindowGutter {
+    match self.base.node(&self.gutter_id).unwrap() {
+      WindowNode::WindowGutter(gutter) => gutter,
+      _ => unreachable!("Window gutter widget must be WindowGutter"),
+    }
+  }
+
+  fn gutter_mut(&mut self) -> &mut WindowGutter {
+    match self.base.node_mut(&self.gutter_id).unwrap() {
+      WindowNode::WindowGutter(gutter) => gutter,
+      _ => unreachable!("Window gutter widget must be WindowGutter"),
+    }
+  }
+
+  /// Get the widths of the gutter columns.
+  pub fn gutter_widths(&self) -> GutterWidths {
+    self.gutter().widths()
+  }
+
+  /// Get the whole width of the gutter, i.e. the text content starts from this column of the
+  /// window.
+  pub fn gutter_width(&self) -> u16 {
+    self.gutter().width()
+  }
+
+  /// Compose the gutter columns again, i.e. the gutter options, the lines count or the signs of
+  /// the buffer are changed. The content and viewport are shrunk (or expanded) if the gutter width
+  /// is changed, see [`GutterWidths`].
+  ///
+  /// # Returns
+  ///
+  /// The changed columns of the gutter width, i.e. the cursor widget inside the window should be
+  /// moved by it.
+  pub fn sync_gutter(&mut self) -> isize {
+    let gutter_widths = match self.buffer.upgrade() {
+      Some(buffer) => GutterWidths::new(&self.options, &rlock!(buffer)),
+      None => GutterWidths::default(),
+    };
+    if gutter_widths == self.gutter_widths() {
+      return 0;
+    }
+    let old_gutter_width = self.gutter_width();
+    self
+      .gutter_mut()
+      .set_widths(gutter_widths, old_gutter_width);
+    self.sync_shape();
+    self.gutter_width() as isize - old_gutter_width as isize
+  }
+}
+// Gutter }
+
+// Selection {
+impl Window {
+  /// Get the visual mode selection.
+  pub fn selection(&self) -> &Option<Selection> {
+    self.content().selection()
+  }
+
+  /// Set the visual mode selection.
+  pub fn set_selection(&mut self, selection: Option<Selection>) {
+    self.content_mut().set_selection(selection);
+  }
+}
+// Selection }
+
 #[derive(Debug, Clone)]
 /// The value holder for each window widget.
 pub enum WindowNode {
   WindowRootContainer(WindowRootContainer),
+  WindowGutter(WindowGutter),
   WindowContent(WindowContent),
 }
 
@@ -230,6 +727,7 @@ macro_rules! window_node_generate_dispatch {
   ($self_name:ident,$method_name:ident) => {
     match $self_name {
       WindowNode::WindowRootContainer(n) => n.$method_name(),
+      WindowNode::WindowGutter(n) => n.$method_name(),
       WindowNode::WindowContent(n) => n.$method_name(),
     }
   };
@@ -294,6 +792,7 @@ impl Widgetable for WindowNode {
   fn draw(&self, canvas: &mut Canvas) {
     match self {
       WindowNode::WindowRootContainer(w) => w.draw(canvas),
+      WindowNode::WindowGutter(w) => w.draw(canvas),
       WindowNode::WindowContent(w) => w.draw(canvas),
     }
   }
@@ -313,11 +812,13 @@ mod tests {
   use std::sync::Once;
   use tracing::info;
 
-  use crate::buf::{Buffer, BufferArc};
+  use crate::buf::sign::DEFAULT_SIGN_PRIORITY;
+  use crate::buf::{Buffer, BufferArc, Sign};
   use crate::cart::U16Size;
   use crate::test::buf::{make_buffer_from_lines, make_empty_buffer};
   #[allow(dead_code)]
   use crate::test::log::init as test_log_init;
+  use crate::test::render::frame_snapshot;
   use crate::ui::tree::Tree;
 
   fn make_window_from_size(
@@ -332,12 +833,7 @@ mod tests {
   }
 
   fn do_test_draw(actual: &Canvas, expect: &[&str]) {
-    let actual = actual
-      .frame()
-      .raw_symbols()
-      .iter()
-      .map(|cs| cs.join(""))
-      .collect::<Vec<_>>();
+    let actual = frame_snapshot(actual.frame());
     info!("actual:{}", actual.len());
     for a in actual.iter() {
       info!("{:?}", a);
@@ -357,6 +853,100 @@ mod tests {
     }
   }
 
+  #[test]
+  fn center_on_line1() {
+    test_log_init();
+
+    // The lines 0-5 occupy 3 rows each with 'wrap', the other lines occupy 1 row.
+    let lines: Vec<String> = (0..20)
+      .map(|i| {
+        if i < 6 {
+          format!("{:x<24}\n", format!("line {} ", i))
+        } else {
+          format!("line {}\n", i)
+        }
+      })
+      .collect();
+    let buffer = make_buffer_from_lines(lines.iter().map(|l| l.as_str()).collect());
+    let terminal_size = U16Size::new(10, 10);
+
+    // zz on line 6, the 4 rows above it are lines 2-5 without 'wrap'.
+    let window_local_options = WindowLocalOptions::builder().wrap(false).build();
+    let mut window = make_window_from_size(terminal_size, buffer.clone(), &window_local_options);
+    window.center_on_line(6);
+    let viewport = window.viewport();
+    assert_eq!(rlock!(viewport).start_line_idx(), 2);
+    assert_eq!(rlock!(viewport).cursor().line_idx(), 6);
+    assert_eq!(rlock!(viewport).cursor().row_idx(), 4);
+
+    // zz on line 6, only the line 5 fits in the 4 rows above it with 'wrap'.
+    let window_local_options = WindowLocalOptions::builder()
+      .wrap(true)
+      .line_break(false)
+      .build();
+    let mut window = make_window_from_size(terminal_size, buffer.clone(), &window_local_options);
+    window.center_on_line(6);
+    let viewport = window.viewport();
+    assert_eq!(rlock!(viewport).start_line_idx(), 5);
+    assert_eq!(rlock!(viewport).cursor().line_idx(), 6);
+    assert_eq!(rlock!(viewport).cursor().row_idx(), 3);
+
+    // zt and zb on line 6, the cursor stays at line 6 since it's inside the viewport.
+    window.top_on_line(6);
+    assert_eq!(rlock!(viewport).start_line_idx(), 6);
+    assert_eq!(rlock!(viewport).cursor().row_idx(), 0);
+    window.bottom_on_line(6);
+    assert_eq!(rlock!(viewport).start_line_idx(), 3);
+    assert_eq!(rlock!(viewport).cursor().row_idx(), 9);
+  }
+
+  #[test]
+  fn view_state1() {
+    test_log_init();
+
+    let lines_a: Vec<String> = (0..30).map(|i| format!("a {}\n", i)).collect();
+    let lines_b: Vec<String> = (0..30).map(|i| format!("b {}\n", i)).collect();
+    let buffer_a = make_buffer_from_lines(lines_a.iter().map(|l| l.as_str()).collect());
+    let buffer_b = make_buffer_from_lines(lines_b.iter().map(|l| l.as_str()).collect());
+    let (buf_id_a, buf_id_b) = (rlock!(buffer_a).id(), rlock!(buffer_b).id());
+    let terminal_size = U16Size::new(10, 10);
+    let window_local_options = WindowLocalOptions::builder().build();
+    let mut window = make_window_from_size(terminal_size, buffer_a.clone(), &window_local_options);
+
+    window.top_on_line(15);
+    let saved = ViewState {
+      cursor_line: 15,
+      cursor_char: 0,
+      start_line: 15,
+      start_bcolumn: 0,
+    };
+    assert_eq!(window.view_state(), saved);
+
+    // The new buffer starts from the first line, the view state of the previous one is saved.
+    window.set_buffer(Arc::downgrade(&buffer_b));
+    assert_eq!(window.view_state(), ViewState::default());
+    assert_eq!(window.saved_view_state(buf_id_a), Some(saved));
+
+    // The lines are deleted, the restored view state is clamped.
+    wlock!(buffer_a).delete_lines(5..30).unwrap();
+    window.set_buffer(Arc::downgrade(&buffer_a));
+    let clamped = ViewState {
+      cursor_line: 4,
+      cursor_char: 0,
+      start_line: 4,
+      start_bcolumn: 0,
+    };
+    assert_eq!(window.view_state(), clamped);
+    assert_eq!(rlock!(window.viewport()).cursor().row_idx(), 0);
+
+    assert_eq!(
+      window.saved_view_state(buf_id_b),
+      Some(ViewState::default())
+    );
+    window.forget_buffer(buf_id_b);
+    assert_eq!(window.saved_view_state(buf_id_b), None);
+  }
+
   #[test]
   fn draw_after_init1() {
     test_log_init();
@@ -378,9 +968,9 @@ mod tests {
       "  2. When ",
       "     * The",
       "     * The",
-      "          ",
-      "          ",
-      "          ",
+      "~         ",
+      "~         ",
+      "~         ",
     ];
 
     let terminal_size = U16Size::new(10, 10);
@@ -390,4 +980,100 @@ mod tests {
     window.draw(&mut actual);
     do_test_draw(&actual, &expect);
   }
+
+  #[test]
+  fn draw_gutter1() {
+    test_log_init();
+
+    let buffer = make_buffer_from_lines(vec![
+      "fn main() {\n",
+      "  let x = 1;\n",
+      "  let long = \"this line is long\";\n",
+      "}\n",
+    ]);
+    wlock!(buffer)
+      .place_sign(0, Sign::new("test", ">>", DEFAULT_SIGN_PRIORITY))
+      .unwrap();
+    let expect = vec![
+      ">>    1 fn main() { ",
+      "  -   2   let x = 1;",
+      "  |   3   let long =",
+      "  |      \"this line ",
+      "  |     is long\";   ",
+      "      4 }           ",
+      "        ~           ",
+    ];
+
+    let terminal_size = U16Size::new(20, 7);
+    let window_local_options = WindowLocalOptions::builder()
+      .number(true)
+      .fold_column(2)
+      .sign_column(SignColumn::Yes)
+      .build();
+    let window = make_window_from_size(terminal_size, buffer.clone(), &window_local_options);
+    // The gutter is composed of the sign, fold and number columns, the viewport is shrunk by it.
+    assert_eq!(
+      window.gutter_widths(),
+      GutterWidths {
+        sign: 2,
+        fold: 2,
+        number: 4
+      }
+    );
+    assert_eq!(window.gutter_width(), 8);
+    let viewport = window.viewport();
+    assert_eq!(rlock!(viewport).actual_shape().width(), 12);
+
+    {
+      let mut viewport = wlock!(viewport);
+      viewport.folds_mut().create(1..3);
+      viewport.folds_mut().open_at(1);
+      viewport.sync_from_top_left(0, 0);
+    }
+    let mut actual = Canvas::new(terminal_size);
+    window.draw(&mut actual);
+    do_test_draw(&actual, &expect);
+  }
+
+  #[test]
+  fn draw_signs1() {
+    test_log_init();
+
+    let buffer = make_buffer_from_lines(vec!["foo\n", "bar\n"]);
+    {
+      let mut buffer = wlock!(buffer);
+      buffer.place_sign(1, Sign::new("git", "+", 5)).unwrap();
+      buffer
+        .place_sign(1, Sign::new("lsp", "E", 20).with_line_highlight("Search"))
+        .unwrap();
+    }
+    let terminal_size = U16Size::new(10, 3);
+    let window_local_options = WindowLocalOptions::builder()
+      .sign_column(SignColumn::Auto)
+      .build();
+    let window = make_window_from_size(terminal_size, buffer.clone(), &window_local_options);
+    let row_bgs = |canvas: &Canvas, y: u16| {
+      (2..10)
+        .map(|x| canvas.frame().get_cell(point!(x: x, y: y)).bg())
+        .collect::<Vec<_>>()
+    };
+    let search_bg = crate::buf::ColorScheme::default()
+      .resolve("Search")
+      .unwrap()
+      .1
+      .unwrap();
+
+    // The sign with the higher priority is displayed, and its line highlight tints the line.
+    let mut actual = Canvas::new(terminal_size);
+    window.draw(&mut actual);
+    do_test_draw(&actual, &["  foo     ", "E bar     ", "  ~       "]);
+    assert_eq!(row_bgs(&actual, 0), vec![crossterm::style::Color::Reset; 8]);
+    assert_eq!(row_bgs(&actual, 1), vec![search_bg; 8]);
+
+    wlock!(buffer).unplace_sign(1, "lsp");
+    let mut actual = Canvas::new(terminal_size);
+    window.draw(&mut actual);
+    do_test_draw(&actual, &["  foo     ", "+ bar     ", "  ~       "]);
+    assert_eq!(row_bgs(&actual, 1), vec![crossterm::style::Color::Reset; 8]);
+  }
 }
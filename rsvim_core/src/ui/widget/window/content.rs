@@ -1,17 +1,22 @@
 //! Vim window's text content widget.
 
-use crate::buf::BufferWk;
-use crate::cart::{IRect, U16Pos, U16Rect};
+use crate::buf::highlight::{apply_style, HighlightStyle};
+use crate::buf::{BufferWk, ColorSchemes};
+use crate::cart::{IRect, U16Pos, U16Rect, U16Size};
 use crate::envar;
+use crate::state::selection::Selection;
 use crate::ui::canvas::{Canvas, Cell};
 use crate::ui::tree::internal::{InodeBase, InodeId, Inodeable};
+use crate::ui::widget::window::opt::{FillChars, ListChars};
 use crate::ui::widget::window::viewport::ViewportWk;
 use crate::ui::widget::Widgetable;
 use crate::{inode_generate_impl, rlock};
 
+use compact_str::ToCompactString;
 use geo::point;
 use std::convert::From;
 use tracing::trace;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 
 #[derive(Debug, Clone)]
 /// The widget contains text contents for Vim window.
@@ -23,6 +28,27 @@ pub struct WindowContent {
 
   // Viewport.
   viewport: ViewportWk,
+
+  // Visual mode selection.
+  selection: Option<Selection>,
+
+  // The 'fillchars' option.
+  fill_chars: FillChars,
+
+  // The 'list' option.
+  list: bool,
+
+  // The 'listchars' option.
+  list_chars: ListChars,
+
+  // The 'showbreak' option.
+  show_break: String,
+
+  // The 'cursorline' option.
+  cursor_line: bool,
+
+  // The columns on the left and rows on the top that are clipped, i.e. outside of the parent.
+  clipped_top_left: U16Pos,
 }
 
 impl WindowContent {
@@ -33,15 +59,101 @@ impl WindowContent {
       base,
       buffer,
       viewport,
+      selection: None,
+      fill_chars: FillChars::default(),
+      list: false,
+      list_chars: ListChars::default(),
+      show_break: String::new(),
+      cursor_line: false,
+      clipped_top_left: point!(x: 0_u16, y: 0_u16),
     }
   }
+
+  /// Set the buffer, i.e. the window switches to another buffer.
+  pub fn set_buffer(&mut self, buffer: BufferWk) {
+    self.buffer = buffer;
+  }
+
+  /// Get the visual mode selection.
+  pub fn selection(&self) -> &Option<Selection> {
+    &self.selection
+  }
+
+  /// Set the visual mode selection, the selected cells are rendered in reverse.
+  pub fn set_selection(&mut self, selection: Option<Selection>) {
+    self.selection = selection;
+  }
+
+  /// Get the 'fillchars' option.
+  pub fn fill_chars(&self) -> FillChars {
+    self.fill_chars
+  }
+
+  /// Set the 'fillchars' option, the `eob` char is rendered on the rows below the end of buffer.
+  pub fn set_fill_chars(&mut self, fill_chars: FillChars) {
+    self.fill_chars = fill_chars;
+  }
+
+  /// Get the 'list' option.
+  pub fn list(&self) -> bool {
+    self.list
+  }
+
+  /// Set the 'list' option, the tabs, trailing spaces and line ends are rendered with the
+  /// 'listchars'.
+  pub fn set_list(&mut self, list: bool) {
+    self.list = list;
+  }
+
+  /// Get the 'listchars' option.
+  pub fn list_chars(&self) -> ListChars {
+    self.list_chars
+  }
+
+  /// Set the 'listchars' option.
+  pub fn set_list_chars(&mut self, list_chars: ListChars) {
+    self.list_chars = list_chars;
+  }
+
+  /// Get the 'showbreak' option.
+  pub fn show_break(&self) -> &str {
+    &self.show_break
+  }
+
+  /// Set the 'showbreak' option, it's rendered at the start of the wrapped rows.
+  pub fn set_show_break(&mut self, show_break: &str) {
+    self.show_break = show_break.to_string();
+  }
+
+  /// Get the 'cursorline' option.
+  pub fn cursor_line(&self) -> bool {
+    self.cursor_line
+  }
+
+  /// Set the 'cursorline' option, the rows of the cursor line are rendered with the cursor line
+  /// background.
+  pub fn set_cursor_line(&mut self, cursor_line: bool) {
+    self.cursor_line = cursor_line;
+  }
+
+  /// Get the clipped columns on the left and rows on the top.
+  pub fn clipped_top_left(&self) -> U16Pos {
+    self.clipped_top_left
+  }
+
+  /// Set the clipped columns on the left and rows on the top, i.e. the window is partially outside
+  /// of its parent. Only the visible slice of the content is rendered, while the viewport is still
+  /// based on the whole (logical) shape.
+  pub fn set_clipped_top_left(&mut self, clipped_top_left: U16Pos) {
+    self.clipped_top_left = clipped_top_left;
+  }
 }
 
 inode_generate_impl!(WindowContent, base);
 
 impl Widgetable for WindowContent {
   fn draw(&self, canvas: &mut Canvas) {
-    let actual_shape = self.actual_shape();
+    let actual_shape = *self.actual_shape();
     let upos: U16Pos = actual_shape.min().into();
     let height = actual_shape.height();
     let width = actual_shape.width();
@@ -52,15 +164,60 @@ impl Widgetable for WindowContent {
       return;
     }
 
-    let viewport = self.viewport.upgrade().unwrap();
-    let viewport = rlock!(viewport);
-
-    // If viewport has no lines.
-    if viewport.end_line_idx() <= viewport.start_line_idx() {
-      trace!("Draw window content, viewport is empty");
+    // The viewport is based on the logical shape, if the widget is partially outside of its
+    // parent, draw the whole logical shape on a scratch canvas and only copy the visible slice.
+    let shape = *self.shape();
+    let logical_size = U16Size::new(shape.width() as u16, shape.height() as u16);
+    if logical_size.width() == width
+      && logical_size.height() == height
+      && self.clipped_top_left == point!(x: 0_u16, y: 0_u16)
+    {
+      self.draw_at(canvas, upos, width, height);
       return;
     }
 
+    trace!(
+      "Draw window content, actual shape:{:?} is clipped from logical size:{:?}",
+      actual_shape,
+      logical_size
+    );
+    let mut scratch = Canvas::new(logical_size);
+    self.draw_at(
+      &mut scratch,
+      point!(x: 0_u16, y: 0_u16),
+      logical_size.width(),
+      logical_size.height(),
+    );
+    for y in 0..height {
+      let src_upos = point!(x: self.clipped_top_left.x(), y: self.clipped_top_left.y() + y);
+      if src_upos.x() + width > logical_size.width() || src_upos.y() >= logical_size.height() {
+        break;
+      }
+      let mut cells = scratch
+        .frame()
+        .get_cells_at(src_upos, width as usize)
+        .to_vec();
+      // The wide symbol cut by the edges is rendered as a space, i.e. the continuation cell on the
+      // left edge, and the symbol overflowing the right edge.
+      for (x, cell) in cells.iter_mut().enumerate() {
+        let symbol_width = cell.symbol().width();
+        if (x == 0 && symbol_width == 0) || x + symbol_width > width as usize {
+          cell.set_symbol(" ".to_compact_string());
+        }
+      }
+      canvas
+        .frame_mut()
+        .set_cells_at(point!(x: upos.x(), y: upos.y() + y), cells);
+    }
+  }
+}
+
+impl WindowContent {
+  // Draw the content of the `width` x `height` rows, starting from the `upos` of the canvas.
+  fn draw_at(&self, canvas: &mut Canvas, upos: U16Pos, width: u16, height: u16) {
+    let viewport = self.viewport.upgrade().unwrap();
+    let viewport = rlock!(viewport);
+
     trace!(
       "Draw window content, actual shape:{:?}, viewport:{:?}",
       actual_shape,
@@ -69,10 +226,38 @@ impl Widgetable for WindowContent {
 
     let buffer = self.buffer.upgrade().unwrap();
     let buffer = rlock!(buffer);
+    let styles = ContentStyles::new();
+
+    // The 'showbreak' is ignored if the window is too narrow, same with the viewport.
+    let show_break_width = match self.show_break.width() {
+      w if w < width as usize => w as u16,
+      _ => 0_u16,
+    };
+
+    // The highlights are applied in layers, from bottom to top: the line highlight of the sign, the
+    // 'cursorline', the syntax tokens, the highlight spans, the search spans and the visual
+    // selection. The upper layer only overrides the parts it specifies, i.e. a span without bg
+    // keeps the 'cursorline' bg.
+    let cursor_line_rows = if self.cursor_line {
+      viewport.cursor_line_rows().unwrap_or_default()
+    } else {
+      0..0
+    };
 
     let mut row_idx = 0_u16;
     let mut line_idx = viewport.start_line_idx();
-    let mut lines_slice = buffer.get_lines_at(line_idx).unwrap();
+    let mut lines_slice = buffer.get_lines_at(line_idx);
+
+    // If the buffer is empty, the line 0 still exists but has nothing to render, clear the first
+    // row instead of rendering the `eob` fill char.
+    if buffer.len_chars() == 0 {
+      trace!("Draw window content, buffer is empty");
+      let row = U16Rect::new(upos, point!(x: upos.x() + width, y: upos.y() + 1));
+      canvas
+        .frame_mut()
+        .clear_region(row, styles.cell(' ', styles.normal));
+      row_idx += 1;
+    }
 
     while line_idx < viewport.end_line_idx() {
       debug_assert!(row_idx < height);
@@ -80,7 +265,7 @@ impl Widgetable for WindowContent {
       let mut start_fills_count = 0_usize;
       let mut end_fills_count = 0_usize;
 
-      let line_slice = lines_slice.next().unwrap();
+      let line_slice = lines_slice.as_mut().unwrap().next().unwrap();
       let line_viewport = viewport.lines().get(&line_idx).unwrap();
 
       trace!(
@@ -92,6 +277,75 @@ impl Widgetable for WindowContent {
 
       let row_viewport = line_viewport.rows();
 
+      // The closed fold is rendered as a summary row, the rest lines of it have no rows.
+      if let Some(fold) = viewport.folds().closed_fold_at(line_idx) {
+        if let Some((r_idx, _)) = row_viewport.first_key_value() {
+          debug_assert_eq!(*r_idx, row_idx);
+          let folded_lines = fold.end.min(buffer.last_line_idx() + 1) - fold.start;
+          let text = fold_text(&line_slice.to_string(), folded_lines);
+          let mut col_idx = 0_u16;
+          let mut cells: Vec<Cell> = vec![];
+          for c in text.chars() {
+            let c_width = c.width().unwrap_or(0) as u16;
+            if col_idx + c_width > width {
+              break;
+            }
+            let cell = Cell::from(c);
+            let guard = Cell::wide_guard(&cell);
+            cells.push(cell);
+            // The wide char occupies more than 1 cell, the rest cells are the guard cells.
+            cells.extend((1..c_width).map(|_| guard.clone()));
+            col_idx += c_width;
+          }
+          cells.extend(
+            std::iter::repeat('-')
+              .take((width - col_idx) as usize)
+              .map(Cell::from),
+          );
+          for cell in cells.iter_mut() {
+            set_highlight_style(cell, styles.folded);
+          }
+          canvas
+            .frame_mut()
+            .set_cells_at(point!(x: upos.x(), y: row_idx + upos.y()), cells);
+          row_idx += 1;
+        }
+        line_idx += 1;
+        continue;
+      }
+
+      // The 'listchars' only changes the rendered symbols, the display width is still the same.
+      let list_chars = if self.list {
+        self.list_chars
+      } else {
+        ListChars::default()
+      };
+      // The line length without the line break, and the start of the trailing spaces.
+      let line_end_char_idx = line_slice
+        .chars()
+        .enumerate()
+        .filter(|(_, c)| *c != '\n' && *c != '\r')
+        .last()
+        .map(|(i, _)| i + 1)
+        .unwrap_or(0);
+      let trail_start_char_idx = match list_chars.trail() {
+        Some(_) => line_slice
+          .chars()
+          .take(line_end_char_idx)
+          .enumerate()
+          .filter(|(_, c)| *c != ' ')
+          .last()
+          .map(|(i, _)| i + 1)
+          .unwrap_or(0),
+        None => line_end_char_idx,
+      };
+
+      // The sign tints all the rows of the line.
+      let sign_line_style = buffer
+        .signs()
+        .line_highlight(line_idx)
+        .map(|group| styles.resolve(group));
+
       if !row_viewport.is_empty() {
         let first_row = row_viewport.first_key_value().unwrap();
         let last_row = row_viewport.last_key_value().unwrap();
@@ -103,6 +357,14 @@ impl Widgetable for WindowContent {
           debug_assert!(row_idx < height);
 
           let mut col_idx = 0_u16;
+          // The 'cursorline' is the bottom layer of the highlights, above the `Normal` style.
+          let mut row_style = styles.normal;
+          if let Some(sign_line_style) = sign_line_style {
+            apply_style(&mut row_style, sign_line_style);
+          }
+          if cursor_line_rows.contains(&row_idx) {
+            apply_style(&mut row_style, styles.cursor_line);
+          }
 
           let start_fills = if row_idx == first_row_idx && line_viewport.start_filled_columns() > 0
           {
@@ -120,11 +382,38 @@ impl Widgetable for WindowContent {
             0_u16
           };
 
-          // Render start fills.
+          // Render 'showbreak' at the start of the continuation rows, i.e. the rows except the first
+          // one of the line.
+          let show_break = if row_idx != first_row_idx {
+            show_break_width
+          } else {
+            0_u16
+          };
+          if show_break > 0 {
+            let mut x = col_idx;
+            for c in self.show_break.chars() {
+              let mut cell = styles.cell(c, row_style);
+              set_highlight_style(&mut cell, styles.non_text);
+              canvas
+                .frame_mut()
+                .set_cell(point!(x: x + upos.x(), y: row_idx + upos.y()), cell);
+              x += c.width().unwrap_or(0) as u16;
+            }
+            col_idx += show_break;
+          }
+
+          // Render start fills, they're the rest cells of the previous char clipped by the
+          // horizontal scroll, thus in the same highlight style with it.
           if start_fills > 0 {
+            let clipped_char_idx = r.start_char_idx().saturating_sub(1);
+            let style = buffer.highlights().style_at(line_idx, clipped_char_idx);
             let cells = std::iter::repeat('>')
               .take(start_fills as usize)
-              .map(Cell::from)
+              .map(|c| {
+                let mut cell = styles.cell(c, row_style);
+                set_highlight_style(&mut cell, style);
+                cell
+              })
               .collect::<Vec<_>>();
             let cells_upos = point!(x: col_idx + upos.x(), y: row_idx + upos.y());
             canvas.frame_mut().set_cells_at(cells_upos, cells);
@@ -148,11 +437,40 @@ impl Widgetable for WindowContent {
               let c = chars_slice.next().unwrap();
               let (unicode_symbol, unicode_width) = buffer.char_symbol(c);
 
-              let cell = Cell::with_symbol(unicode_symbol);
+              let (unicode_symbol, rest_symbol) = match (c, list_chars.tab(), list_chars.trail()) {
+                ('\t', Some((lead, fill)), _) => (lead.to_compact_string(), Some(fill)),
+                (' ', _, Some(trail)) if char_idx >= trail_start_char_idx => {
+                  (trail.to_compact_string(), None)
+                }
+                _ => (unicode_symbol, None),
+              };
+
+              let mut cell = Cell::with_symbol(unicode_symbol);
+              set_highlight_style(&mut cell, row_style);
+              set_highlight_style(&mut cell, buffer.highlights().style_at(line_idx, char_idx));
+              // The rest cells of the tab are rendered with the fill char, in the same style.
+              // Otherwise they're the guard cells of the wide symbol, thus the previous frame's
+              // symbols in them are never printed after the wide symbol.
+              // A char wider than the rest of the row is truncated, see the layout engine.
+              let visible_width = std::cmp::min(unicode_width, (width - col_idx) as usize);
+              let rest_cells = (1..visible_width)
+                .map(|_| match rest_symbol {
+                  Some(fill) => {
+                    let mut fill_cell = cell.clone();
+                    fill_cell.set_symbol(fill.to_compact_string());
+                    fill_cell
+                  }
+                  None => Cell::wide_guard(&cell),
+                })
+                .collect::<Vec<_>>();
               let cell_upos = point!(x: col_idx + upos.x(), y: row_idx + upos.y());
               canvas.frame_mut().set_cell(cell_upos, cell);
+              if !rest_cells.is_empty() {
+                let cells_upos = point!(x: col_idx + 1 + upos.x(), y: row_idx + upos.y());
+                canvas.frame_mut().set_cells_at(cells_upos, rest_cells);
+              }
 
-              col_idx += unicode_width as u16;
+              col_idx += visible_width as u16;
               char_idx += 1;
               total_width += unicode_width;
             }
@@ -170,12 +488,18 @@ impl Widgetable for WindowContent {
 
           // Render left empty parts.
           let occupied_length =
-            (r.end_dcol_idx() - r.start_dcol_idx()) as u16 + start_fills + end_fills;
+            (r.end_dcol_idx() - r.start_dcol_idx()) as u16 + show_break + start_fills + end_fills;
           if width > occupied_length {
             let left_length = width - occupied_length;
-            let cells = std::iter::repeat(' ')
+            // The `eol` char is rendered right after the end of line, if the row contains it.
+            let eol = list_chars
+              .eol()
+              .filter(|_| r.end_char_idx() >= line_end_char_idx && end_fills == 0);
+            let cells = eol
+              .into_iter()
+              .chain(std::iter::repeat(' '))
               .take(left_length as usize)
-              .map(Cell::from)
+              .map(|c| styles.cell(c, row_style))
               .collect::<Vec<_>>();
             let cells_upos = point!(x: col_idx + upos.x(), y: row_idx + upos.y());
             canvas.frame_mut().set_cells_at(cells_upos, cells);
@@ -191,11 +515,17 @@ impl Widgetable for WindowContent {
             );
           }
 
-          // Render end fills.
+          // Render end fills, they're the first cells of the next char that cannot fully show in
+          // the row, thus in the same highlight style with it.
           if end_fills > 0 {
+            let style = buffer.highlights().style_at(line_idx, r.end_char_idx());
             let cells = std::iter::repeat('<')
               .take(end_fills as usize)
-              .map(Cell::from)
+              .map(|c| {
+                let mut cell = styles.cell(c, row_style);
+                set_highlight_style(&mut cell, style);
+                cell
+              })
               .collect::<Vec<_>>();
             let cells_upos = point!(x: col_idx + upos.x(), y: row_idx + upos.y());
             canvas.frame_mut().set_cells_at(cells_upos, cells);
@@ -219,11 +549,24 @@ impl Widgetable for WindowContent {
       line_idx += 1;
     }
 
-    // If buffer has no more lines, render empty spaces to left parts of the window content.
+    // The visual selection is the top layer, it's applied to the selected cells, i.e. all the
+    // cells of the wide chars.
+    if let Some(selection) = &self.selection {
+      for (row_idx, columns) in selection.cells_in_viewport(&viewport, &buffer) {
+        for col_idx in columns {
+          let cell_upos = point!(x: col_idx + upos.x(), y: row_idx + upos.y());
+          let mut cell = canvas.frame().get_cell(cell_upos).clone();
+          set_highlight_style(&mut cell, styles.visual);
+          canvas.frame_mut().set_cell(cell_upos, cell);
+        }
+      }
+    }
+
+    // If buffer has no more lines, render the `eob` fill char to the first column of the left rows
+    // in the `EndOfBuffer` style, and clear the rest columns.
     while row_idx < height {
-      let cells = std::iter::repeat(' ')
-        .take(width as usize)
-        .map(Cell::from)
+      let cells = std::iter::once(styles.cell(self.fill_chars.eob(), styles.end_of_buffer))
+        .chain(std::iter::repeat(styles.cell(' ', styles.normal)).take(width as usize - 1))
         .collect::<Vec<_>>();
       let cells_upos = point!(x: upos.x(), y: row_idx + upos.y());
       canvas.frame_mut().set_cells_at(cells_upos, cells);
@@ -232,22 +575,99 @@ impl Widgetable for WindowContent {
   }
 }
 
+/// The styles of the UI highlight groups rendered by the window content, they're resolved by the
+/// current color scheme once for each draw.
+struct ContentStyles {
+  /// `Normal`, the text and the empty parts of the rows.
+  normal: HighlightStyle,
+  /// `NonText`, the 'showbreak'.
+  non_text: HighlightStyle,
+  /// `EndOfBuffer`, the `eob` fill char of the rows below the end of buffer.
+  end_of_buffer: HighlightStyle,
+  /// `Folded`, the summary row of the closed fold.
+  folded: HighlightStyle,
+  /// `CursorLine`, the rows of the cursor line with 'cursorline'.
+  cursor_line: HighlightStyle,
+  /// `Visual`, the visual mode selection.
+  visual: HighlightStyle,
+}
+
+impl ContentStyles {
+  fn new() -> Self {
+    let color_schemes = rlock!(ColorSchemes::global());
+    let resolve = |group: &str| color_schemes.resolve(group).unwrap_or_default();
+    ContentStyles {
+      normal: resolve("Normal"),
+      non_text: resolve("NonText"),
+      end_of_buffer: resolve("EndOfBuffer"),
+      folded: resolve("Folded"),
+      cursor_line: resolve("CursorLine"),
+      visual: resolve("Visual"),
+    }
+  }
+
+  /// Resolve the highlight group that's not one of the fixed groups, i.e. the line highlight of
+  /// the signs.
+  fn resolve(&self, group: &str) -> HighlightStyle {
+    rlock!(ColorSchemes::global())
+      .resolve(group)
+      .unwrap_or_default()
+  }
+
+  /// Make the cell of the char in the style.
+  fn cell(&self, c: char, style: HighlightStyle) -> Cell {
+    let mut cell = Cell::from(c);
+    set_highlight_style(&mut cell, style);
+    cell
+  }
+}
+
+/// The summary text of the closed fold, i.e. the folded lines count and the first line without
+/// the leading blanks, same with Vim's default 'foldtext'. The tabs are displayed as spaces.
+fn fold_text(first_line: &str, folded_lines: usize) -> String {
+  let line = first_line
+    .trim_end_matches(['\n', '\r'])
+    .trim_start()
+    .replace('\t', " ");
+  let unit = if folded_lines == 1 { "line" } else { "lines" };
+  format!("+--{:>3} {}: {}", folded_lines, unit, line)
+}
+
+/// Set the cell style from the highlight spans, the `None` colors are kept unchanged.
+pub(super) fn set_highlight_style(cell: &mut Cell, style: HighlightStyle) {
+  let (fg, bg, attrs) = style;
+  if let Some(fg) = fg {
+    cell.set_fg(fg);
+  }
+  if let Some(bg) = bg {
+    cell.set_bg(bg);
+  }
+  cell.set_attrs(attrs);
+}
+
 #[allow(unused_imports)]
 #[cfg(test)]
 mod tests {
   use super::*;
 
-  use crate::buf::BufferArc;
+  use crate::buf::{BufferArc, ColorScheme, HighlightSpan, SyntaxToken};
   use crate::cart::U16Size;
+  use crate::state::selection::{BufferPosition, Selection, VisualKind};
   use crate::test::buf::{make_buffer_from_lines, make_empty_buffer};
   use crate::test::log::init as test_log_init;
+  use crate::test::render::{frame_snapshot, frame_snapshot_styled};
   use crate::ui::tree::Tree;
-  use crate::ui::widget::window::{Viewport, ViewportOptions, WindowLocalOptions};
+  use crate::ui::widget::window::{
+    FillChars, ListChars, Viewport, ViewportOptions, WindowLocalOptions,
+  };
+  use crate::wlock;
 
   use compact_str::ToCompactString;
+  use crossterm::style::{Attribute, Attributes, Color};
   use ropey::{Rope, RopeBuilder};
   use std::fs::File;
   use std::io::{BufReader, BufWriter};
+  use std::str::FromStr;
   use std::sync::Arc;
   use tracing::info;
 
@@ -256,6 +676,18 @@ mod tests {
     window_options: WindowLocalOptions,
     buffer: BufferArc,
   ) -> Canvas {
+    let mut canvas = Canvas::new(terminal_size);
+    draw_window_content(&mut canvas, window_options, buffer);
+    canvas
+  }
+
+  // Draw the window content on the existing canvas, i.e. over the previous frame.
+  fn draw_window_content(
+    canvas: &mut Canvas,
+    window_options: WindowLocalOptions,
+    buffer: BufferArc,
+  ) {
+    let terminal_size = canvas.size();
     let mut tree = Tree::new(terminal_size);
     tree.set_local_options(&window_options);
     let actual_shape = U16Rect::new((0, 0), (terminal_size.width(), terminal_size.height()));
@@ -269,21 +701,17 @@ mod tests {
         terminal_size.height() as isize,
       ),
     );
-    let window_content =
+    let mut window_content =
       WindowContent::new(shape, Arc::downgrade(&buffer), Arc::downgrade(&viewport));
-    let mut canvas = Canvas::new(terminal_size);
-    window_content.draw(&mut canvas);
-    canvas
+    window_content.set_fill_chars(window_options.fill_chars());
+    window_content.set_list(window_options.list());
+    window_content.set_list_chars(window_options.list_chars());
+    window_content.draw(canvas);
   }
 
   #[allow(clippy::too_many_arguments)]
   fn do_test_draw_from_top_left(actual: &Canvas, expect: &[&str]) {
-    let actual = actual
-      .frame()
-      .raw_symbols()
-      .iter()
-      .map(|cs| cs.join(""))
-      .collect::<Vec<_>>();
+    let actual = frame_snapshot(actual.frame());
     info!("actual:{}", actual.len());
     for a in actual.iter() {
       info!("{:?}", a);
@@ -324,9 +752,9 @@ mod tests {
       "  2. When ",
       "     * The",
       "     * The",
-      "          ",
-      "          ",
-      "          ",
+      "~         ",
+      "~         ",
+      "~         ",
     ];
 
     let terminal_size = U16Size::new(10, 10);
@@ -386,9 +814,9 @@ mod tests {
       "  2. When the line is too long to",
       "     * The extra parts are been t",
       "     * The extra parts are split ",
-      "                                 ",
-      "                                 ",
-      "                                 ",
+      "~                                ",
+      "~                                ",
+      "~                                ",
     ];
 
     let terminal_size = U16Size::new(33, 10);
@@ -419,19 +847,19 @@ mod tests {
       "  2. When the line is too long ",
       "     * The extra parts are been",
       "     * The extra parts are spli",
-      "                               ",
-      "                               ",
-      "                               ",
-      "                               ",
-      "                               ",
-      "                               ",
-      "                               ",
-      "                               ",
-      "                               ",
-      "                               ",
-      "                               ",
-      "                               ",
-      "                               ",
+      "~                              ",
+      "~                              ",
+      "~                              ",
+      "~                              ",
+      "~                              ",
+      "~                              ",
+      "~                              ",
+      "~                              ",
+      "~                              ",
+      "~                              ",
+      "~                              ",
+      "~                              ",
+      "~                              ",
     ];
 
     let terminal_size = U16Size::new(31, 20);
@@ -447,25 +875,25 @@ mod tests {
     let buffer = make_empty_buffer();
     let expect = vec![
       "                               ",
-      "                               ",
-      "                               ",
-      "                               ",
-      "                               ",
-      "                               ",
-      "                               ",
-      "                               ",
-      "                               ",
-      "                               ",
-      "                               ",
-      "                               ",
-      "                               ",
-      "                               ",
-      "                               ",
-      "                               ",
-      "                               ",
-      "                               ",
-      "                               ",
-      "                               ",
+      "~                              ",
+      "~                              ",
+      "~                              ",
+      "~                              ",
+      "~                              ",
+      "~                              ",
+      "~                              ",
+      "~                              ",
+      "~                              ",
+      "~                              ",
+      "~                              ",
+      "~                              ",
+      "~                              ",
+      "~                              ",
+      "~                              ",
+      "~                              ",
+      "~                              ",
+      "~                              ",
+      "~                              ",
     ];
 
     let terminal_size = U16Size::new(31, 20);
@@ -539,14 +967,14 @@ mod tests {
     let buffer = make_empty_buffer();
     let expect = vec![
       "                    ",
-      "                    ",
-      "                    ",
-      "                    ",
-      "                    ",
-      "                    ",
-      "                    ",
-      "                    ",
-      "                    ",
+      "~                   ",
+      "~                   ",
+      "~                   ",
+      "~                   ",
+      "~                   ",
+      "~                   ",
+      "~                   ",
+      "~                   ",
     ];
 
     let terminal_size = U16Size::new(20, 9);
@@ -738,13 +1166,13 @@ mod tests {
     let buffer = make_empty_buffer();
     let expect = vec![
       "                    ",
-      "                    ",
-      "                    ",
-      "                    ",
-      "                    ",
-      "                    ",
-      "                    ",
-      "                    ",
+      "~                   ",
+      "~                   ",
+      "~                   ",
+      "~                   ",
+      "~                   ",
+      "~                   ",
+      "~                   ",
     ];
 
     let terminal_size = U16Size::new(20, 8);
@@ -846,4 +1274,604 @@ mod tests {
     let actual = make_window_content_drawn_canvas(terminal_size, window_options, buffer.clone());
     do_test_draw_from_top_left(&actual, &expect);
   }
+
+  #[test]
+  fn draw_eob1() {
+    test_log_init();
+
+    let buffer = make_buffer_from_lines(vec!["1\n", "2\n", "3\n", "4\n", "5\n"]);
+    let expect = vec![
+      "1    ", "2    ", "3    ", "4    ", "5    ", "~    ", "~    ", "~    ", "~    ", "~    ",
+    ];
+
+    let terminal_size = U16Size::new(5, 10);
+    let window_options = WindowLocalOptions::builder().build();
+    let actual = make_window_content_drawn_canvas(terminal_size, window_options, buffer.clone());
+    do_test_draw_from_top_left(&actual, &expect);
+  }
+
+  #[test]
+  fn draw_eob2() {
+    test_log_init();
+
+    let buffer = make_buffer_from_lines(vec!["1\n", "2\n", "3\n", "4\n", "5\n"]);
+    let terminal_size = U16Size::new(5, 10);
+
+    let expect = vec![
+      "1    ", "2    ", "3    ", "4    ", "5    ", "     ", "     ", "     ", "     ", "     ",
+    ];
+    let window_options = WindowLocalOptions::builder()
+      .fill_chars(FillChars::from_str("eob: ").unwrap())
+      .build();
+    let actual = make_window_content_drawn_canvas(terminal_size, window_options, buffer.clone());
+    do_test_draw_from_top_left(&actual, &expect);
+
+    let expect = vec![
+      "1    ", "2    ", "3    ", "4    ", "5    ", "-    ", "-    ", "-    ", "-    ", "-    ",
+    ];
+    let window_options = WindowLocalOptions::builder()
+      .fill_chars(FillChars::from_str("eob:-").unwrap())
+      .build();
+    let actual = make_window_content_drawn_canvas(terminal_size, window_options, buffer.clone());
+    do_test_draw_from_top_left(&actual, &expect);
+  }
+
+  #[test]
+  fn draw_eob3() {
+    test_log_init();
+
+    // Empty buffer, the line 0 exists but is empty.
+    let buffer = make_empty_buffer();
+    let expect = vec!["     ", "~    ", "~    ", "~    "];
+
+    let terminal_size = U16Size::new(5, 4);
+    let window_options = WindowLocalOptions::builder().build();
+    let actual = make_window_content_drawn_canvas(terminal_size, window_options, buffer.clone());
+    do_test_draw_from_top_left(&actual, &expect);
+  }
+
+  #[test]
+  fn draw_eob4() {
+    test_log_init();
+
+    // The rows below the end of buffer are fully cleared after the buffer shrinks.
+    let buffer = make_buffer_from_lines(vec!["abcd\n", "efgh\n", "ijkl\n"]);
+    let terminal_size = U16Size::new(5, 4);
+    let window_options = WindowLocalOptions::builder().build();
+    let actual_shape = U16Rect::new((0, 0), (terminal_size.width(), terminal_size.height()));
+    let viewport_options = ViewportOptions::from(&window_options);
+    let viewport = Viewport::new(&viewport_options, Arc::downgrade(&buffer), &actual_shape);
+    let viewport = Viewport::to_arc(viewport);
+    let shape = IRect::new((0, 0), (5, 4));
+    let window_content =
+      WindowContent::new(shape, Arc::downgrade(&buffer), Arc::downgrade(&viewport));
+    let mut canvas = Canvas::new(terminal_size);
+    window_content.draw(&mut canvas);
+    do_test_draw_from_top_left(&canvas, &["abcd ", "efgh ", "ijkl ", "~    "]);
+
+    wlock!(buffer).remove(5..15).unwrap();
+    wlock!(viewport).sync_from_top_left(0, 0);
+    window_content.draw(&mut canvas);
+    do_test_draw_from_top_left(&canvas, &["abcd ", "~    ", "~    ", "~    "]);
+  }
+
+  #[test]
+  fn draw_eob5() {
+    test_log_init();
+
+    let terminal_size = U16Size::new(5, 4);
+    let window_options = WindowLocalOptions::builder().build();
+
+    // The `eob` fill char starts right after the last line, with or without the trailing line
+    // break.
+    let buffer = make_buffer_from_lines(vec!["1\n", "2\n"]);
+    let actual =
+      make_window_content_drawn_canvas(terminal_size, window_options.clone(), buffer.clone());
+    do_test_draw_from_top_left(&actual, &["1    ", "2    ", "~    ", "~    "]);
+
+    let buffer = make_buffer_from_lines(vec!["1\n", "2"]);
+    let actual =
+      make_window_content_drawn_canvas(terminal_size, window_options.clone(), buffer.clone());
+    do_test_draw_from_top_left(&actual, &["1    ", "2    ", "~    ", "~    "]);
+
+    // An empty last line is rendered.
+    let buffer = make_buffer_from_lines(vec!["1\n", "2\n", "\n"]);
+    let actual = make_window_content_drawn_canvas(terminal_size, window_options, buffer.clone());
+    do_test_draw_from_top_left(&actual, &["1    ", "2    ", "     ", "~    "]);
+  }
+
+  #[test]
+  fn draw_eob6() {
+    test_log_init();
+
+    // The `eob` fill char is in the `EndOfBuffer` style, the rest columns are not.
+    let buffer = make_buffer_from_lines(vec!["1\n", "2\n"]);
+    let terminal_size = U16Size::new(5, 5);
+    let window_options = WindowLocalOptions::builder().build();
+    let actual = make_window_content_drawn_canvas(terminal_size, window_options, buffer.clone());
+    assert_eq!(
+      frame_snapshot_styled(actual.frame()),
+      vec![
+        "1    ",
+        "2    ",
+        "<fg=Blue,attrs=Bold>~</>    ",
+        "<fg=Blue,attrs=Bold>~</>    ",
+        "<fg=Blue,attrs=Bold>~</>    ",
+      ]
+    );
+  }
+
+  #[test]
+  fn draw_search1() {
+    test_log_init();
+
+    let buffer = make_buffer_from_lines(vec!["foo bar\n", "bar foo\n"]);
+    let terminal_size = U16Size::new(8, 3);
+    let window_options = WindowLocalOptions::builder().build();
+    let actual_shape = U16Rect::new((0, 0), (terminal_size.width(), terminal_size.height()));
+    let viewport_options = ViewportOptions::from(&window_options);
+    let viewport = Viewport::new(&viewport_options, Arc::downgrade(&buffer), &actual_shape);
+    let viewport = Viewport::to_arc(viewport);
+    let shape = IRect::new((0, 0), (8, 3));
+    let window_content =
+      WindowContent::new(shape, Arc::downgrade(&buffer), Arc::downgrade(&viewport));
+    {
+      let mut buffer = wlock!(buffer);
+      let highlights = buffer.highlights_mut();
+      highlights.set_search(0, vec![HighlightSpan::search(0, 3)]);
+      highlights.set_search(1, vec![HighlightSpan::inc_search(4, 7)]);
+    }
+    let mut canvas = Canvas::new(terminal_size);
+    window_content.draw(&mut canvas);
+    do_test_draw_from_top_left(&canvas, &["foo bar ", "bar foo ", "~       "]);
+
+    let cell_at = |x: u16, y: u16| canvas.frame().get_cell(point!(x: x, y: y)).clone();
+    for x in 0..3 {
+      assert_eq!(cell_at(x, 0).bg(), Color::Yellow);
+      assert_eq!(cell_at(x, 0).fg(), Color::Black);
+    }
+    assert_eq!(cell_at(3, 0).bg(), Color::Reset);
+    assert_eq!(cell_at(0, 1).bg(), Color::Reset);
+    // The 'incsearch' match is rendered in reverse, instead of the 'hlsearch' highlight.
+    for x in 4..7 {
+      assert!(cell_at(x, 1).attrs().has(Attribute::Reverse));
+      assert_eq!(cell_at(x, 1).bg(), Color::Reset);
+    }
+    assert!(!cell_at(0, 0).attrs().has(Attribute::Reverse));
+  }
+
+  #[test]
+  fn draw_list1() {
+    test_log_init();
+
+    let buffer = make_buffer_from_lines(vec!["a\tb  \n", "c\n"]);
+    let terminal_size = U16Size::new(16, 3);
+
+    // The tab is rendered as spaces, the trailing spaces are not changed.
+    let window_options = WindowLocalOptions::builder().list(false).build();
+    let actual = make_window_content_drawn_canvas(terminal_size, window_options, buffer.clone());
+    do_test_draw_from_top_left(
+      &actual,
+      &["a        b      ", "c               ", "~               "],
+    );
+
+    // The default 'listchars'.
+    let window_options = WindowLocalOptions::builder().list(true).build();
+    let actual = make_window_content_drawn_canvas(terminal_size, window_options, buffer.clone());
+    do_test_draw_from_top_left(
+      &actual,
+      &["a»       b··    ", "c               ", "~               "],
+    );
+    let symbols = actual.frame().raw_symbols();
+    assert_eq!(symbols[0][1], "»");
+    for x in 2..9 {
+      assert_eq!(symbols[0][x], " ");
+    }
+    assert_eq!(symbols[0][9], "b");
+    assert_eq!(symbols[0][10], "·");
+    assert_eq!(symbols[0][11], "·");
+
+    let window_options = WindowLocalOptions::builder()
+      .list(true)
+      .list_chars(ListChars::from_str("tab:>-,trail:.,eol:$").unwrap())
+      .build();
+    let actual = make_window_content_drawn_canvas(terminal_size, window_options, buffer.clone());
+    do_test_draw_from_top_left(
+      &actual,
+      &["a>-------b..$   ", "c$              ", "~               "],
+    );
+
+    // The 'listchars' has no effect if 'list' is off.
+    let window_options = WindowLocalOptions::builder()
+      .list(false)
+      .list_chars(ListChars::from_str("tab:>-,trail:.,eol:$").unwrap())
+      .build();
+    let actual = make_window_content_drawn_canvas(terminal_size, window_options, buffer.clone());
+    do_test_draw_from_top_left(
+      &actual,
+      &["a        b      ", "c               ", "~               "],
+    );
+  }
+
+  #[test]
+  fn draw_cells1() {
+    test_log_init();
+
+    let buffer = make_buffer_from_lines(vec!["\t你好x\n", "ab\n"]);
+    let terminal_size = U16Size::new(16, 3);
+    let window_options = WindowLocalOptions::builder().wrap(false).build();
+
+    // Draw over a previous frame full of symbols, all of them are overwritten.
+    let mut canvas = Canvas::new(terminal_size);
+    for y in 0..terminal_size.height() {
+      let cells = std::iter::repeat('z')
+        .take(terminal_size.width() as usize)
+        .map(Cell::from)
+        .collect::<Vec<_>>();
+      canvas.frame_mut().set_cells_at(point!(x: 0, y: y), cells);
+    }
+    draw_window_content(&mut canvas, window_options, buffer.clone());
+    do_test_draw_from_top_left(
+      &canvas,
+      &["        你好x   ", "ab              ", "~               "],
+    );
+
+    // The tab is expanded to 'tabstop' spaces, the wide symbols are followed by blank
+    // continuation cells, and the cells past the line end are cleared.
+    let symbols = canvas.frame().raw_symbols();
+    assert_eq!(symbols[0][0], " ".repeat(8));
+    for x in 1..8 {
+      assert_eq!(symbols[0][x], "");
+    }
+    assert_eq!(symbols[0][8], "你");
+    assert_eq!(symbols[0][9], "");
+    assert_eq!(symbols[0][10], "好");
+    assert_eq!(symbols[0][11], "");
+    assert_eq!(symbols[0][12], "x");
+    for x in 13..16 {
+      assert_eq!(symbols[0][x], " ");
+    }
+    assert_eq!(symbols[1][0], "a");
+    assert_eq!(symbols[1][1], "b");
+    assert_eq!(symbols[1][2], " ");
+  }
+
+  #[test]
+  fn draw_wide_chars1() {
+    test_log_init();
+
+    let terminal_size = U16Size::new(6, 2);
+    let window_options = WindowLocalOptions::builder().build();
+    let mut canvas = Canvas::new(terminal_size);
+    draw_window_content(
+      &mut canvas,
+      window_options.clone(),
+      make_buffer_from_lines(vec!["你好\n"]),
+    );
+    do_test_draw_from_top_left(&canvas, &["你好  ", "~     "]);
+    let frame = canvas.frame();
+    assert_eq!(frame.get_cell(point!(x: 0, y: 0)).symbol(), "你");
+    assert_eq!(frame.get_cell(point!(x: 2, y: 0)).symbol(), "好");
+    for x in 0..6 {
+      assert_eq!(
+        frame.get_cell(point!(x: x, y: 0)).is_wide_guard(),
+        x == 1 || x == 3
+      );
+    }
+
+    // The wide chars are overwritten by the narrow ones, no guard cells are left.
+    draw_window_content(
+      &mut canvas,
+      window_options,
+      make_buffer_from_lines(vec!["a好c\n"]),
+    );
+    do_test_draw_from_top_left(&canvas, &["a好c  ", "~     "]);
+    let frame = canvas.frame();
+    assert_eq!(frame.get_cell(point!(x: 1, y: 0)).symbol(), "好");
+    for x in 0..6 {
+      assert_eq!(frame.get_cell(point!(x: x, y: 0)).is_wide_guard(), x == 2);
+    }
+  }
+
+  #[test]
+  fn draw_highlight1() {
+    test_log_init();
+
+    let buffer = make_buffer_from_lines(vec!["ab\tcd\n"]);
+    wlock!(buffer).highlights_mut().set(
+      0,
+      vec![
+        HighlightSpan::new(0, 3, Some(Color::Red), None, Attributes::default()),
+        HighlightSpan::new(
+          2,
+          4,
+          None,
+          Some(Color::Blue),
+          Attributes::from(Attribute::Bold),
+        ),
+      ],
+    );
+    let terminal_size = U16Size::new(16, 2);
+
+    let window_options = WindowLocalOptions::builder().build();
+    let actual = make_window_content_drawn_canvas(terminal_size, window_options, buffer.clone());
+    do_test_draw_from_top_left(&actual, &["ab        cd    ", "~               "]);
+    let cell_at = |x: u16| actual.frame().get_cell(point!(x: x, y: 0)).clone();
+    for x in 0..2 {
+      assert_eq!(cell_at(x).fg(), Color::Red);
+      assert_eq!(cell_at(x).bg(), Color::Reset);
+      assert_eq!(cell_at(x).attrs(), Attributes::default());
+    }
+    // The tab is covered by both spans.
+    assert_eq!(cell_at(2).fg(), Color::Red);
+    assert_eq!(cell_at(2).bg(), Color::Blue);
+    assert!(cell_at(2).attrs().has(Attribute::Bold));
+    assert_eq!(cell_at(10).fg(), Color::Reset);
+    assert_eq!(cell_at(10).bg(), Color::Blue);
+    assert_eq!(cell_at(11).fg(), Color::Reset);
+    assert_eq!(cell_at(11).bg(), Color::Reset);
+
+    // The expanded tab cells in 'list' mode are in the same style.
+    let window_options = WindowLocalOptions::builder()
+      .list(true)
+      .list_chars(ListChars::from_str("tab:>-").unwrap())
+      .build();
+    let actual = make_window_content_drawn_canvas(terminal_size, window_options, buffer.clone());
+    do_test_draw_from_top_left(&actual, &["ab>-------cd    ", "~               "]);
+    let cell_at = |x: u16| actual.frame().get_cell(point!(x: x, y: 0)).clone();
+    for x in 2..10 {
+      assert_eq!(cell_at(x).fg(), Color::Red);
+      assert_eq!(cell_at(x).bg(), Color::Blue);
+    }
+    assert_eq!(cell_at(10).fg(), Color::Reset);
+  }
+
+  #[test]
+  fn draw_syntax1() {
+    test_log_init();
+
+    let buffer = make_buffer_from_lines(vec!["// hi\n", "let x = 1;\n"]);
+    {
+      let mut buffer = wlock!(buffer);
+      let highlights = buffer.highlights_mut();
+      highlights.set_syntax(0, vec![SyntaxToken::new(0, 5, "Comment")]);
+      highlights.set_syntax(
+        1,
+        vec![
+          SyntaxToken::new(0, 3, "Keyword"),
+          SyntaxToken::new(4, 5, "Type"),
+          SyntaxToken::new(8, 9, "Number"),
+        ],
+      );
+    }
+    let terminal_size = U16Size::new(12, 3);
+
+    let window_options = WindowLocalOptions::builder().build();
+    let actual = make_window_content_drawn_canvas(terminal_size, window_options, buffer.clone());
+    do_test_draw_from_top_left(&actual, &["// hi       ", "let x = 1;  ", "~           "]);
+    let cell_at = |x: u16, y: u16| actual.frame().get_cell(point!(x: x, y: y)).clone();
+    for x in 0..5 {
+      assert_eq!(cell_at(x, 0).fg(), Color::Blue);
+    }
+    assert_eq!(cell_at(5, 0).fg(), Color::Reset);
+    // The groups are resolved through the links, i.e. `Keyword` is linked to `Statement`.
+    for x in 0..3 {
+      assert_eq!(cell_at(x, 1).fg(), Color::Yellow);
+    }
+    assert_eq!(cell_at(3, 1).fg(), Color::Reset);
+    assert_eq!(cell_at(4, 1).fg(), Color::Green);
+    assert_eq!(cell_at(4, 1).bg(), Color::Reset);
+    assert!(cell_at(4, 1).attrs().has(Attribute::Bold));
+    assert_eq!(cell_at(8, 1).fg(), Color::Red);
+    assert_eq!(cell_at(9, 1).fg(), Color::Reset);
+  }
+
+  #[test]
+  fn draw_cursor_line1() {
+    test_log_init();
+
+    let buffer = make_buffer_from_lines(vec!["hello world\n", "ab\n", "c\n"]);
+    {
+      let mut buffer = wlock!(buffer);
+      let highlights = buffer.highlights_mut();
+      highlights.set(
+        1,
+        vec![HighlightSpan::new(
+          0,
+          1,
+          Some(Color::Red),
+          None,
+          Attributes::default(),
+        )],
+      );
+      highlights.set_search(1, vec![HighlightSpan::search(1, 2)]);
+    }
+    let terminal_size = U16Size::new(6, 4);
+    let window_options = WindowLocalOptions::builder().cursor_line(true).build();
+    let actual_shape = U16Rect::new((0, 0), (terminal_size.width(), terminal_size.height()));
+    let viewport_options = ViewportOptions::from(&window_options);
+    let viewport = Viewport::new(&viewport_options, Arc::downgrade(&buffer), &actual_shape);
+    let viewport = Viewport::to_arc(viewport);
+    let shape = IRect::new((0, 0), (6, 4));
+    let mut window_content =
+      WindowContent::new(shape, Arc::downgrade(&buffer), Arc::downgrade(&viewport));
+    window_content.set_cursor_line(window_options.cursor_line());
+    let row_bgs = |canvas: &Canvas, y: u16| {
+      (0..6)
+        .map(|x| canvas.frame().get_cell(point!(x: x, y: y)).bg())
+        .collect::<Vec<_>>()
+    };
+    let cursor_line_bg = ColorScheme::default()
+      .resolve("CursorLine")
+      .unwrap()
+      .1
+      .unwrap();
+
+    // The cursor is on the wrapped line 0, both of its rows are highlighted.
+    let mut canvas = Canvas::new(terminal_size);
+    window_content.draw(&mut canvas);
+    do_test_draw_from_top_left(&canvas, &["hello ", "world ", "ab    ", "c     "]);
+    assert_eq!(rlock!(viewport).cursor_line_rows(), Some(0..2));
+    assert_eq!(row_bgs(&canvas, 0), vec![cursor_line_bg; 6]);
+    assert_eq!(row_bgs(&canvas, 1), vec![cursor_line_bg; 6]);
+    assert_eq!(row_bgs(&canvas, 2), vec![Color::Reset; 6]);
+    assert_eq!(row_bgs(&canvas, 3), vec![Color::Reset; 6]);
+
+    // Move the cursor to line 1, the other highlights are layered above the 'cursorline'.
+    canvas._shade_done();
+    {
+      let mut viewport = wlock!(viewport);
+      let cursor = viewport.cursor_viewport_at(1, 0).unwrap();
+      viewport.set_cursor(cursor);
+    }
+    window_content.draw(&mut canvas);
+    assert_eq!(rlock!(viewport).cursor_line_rows(), Some(2..3));
+    assert_eq!(row_bgs(&canvas, 0), vec![Color::Reset; 6]);
+    assert_eq!(row_bgs(&canvas, 1), vec![Color::Reset; 6]);
+    let mut expect = vec![cursor_line_bg; 6];
+    expect[1] = Color::Yellow;
+    assert_eq!(row_bgs(&canvas, 2), expect);
+    assert_eq!(canvas.frame().get_cell(point!(x: 0, y: 2)).fg(), Color::Red);
+    assert_eq!(row_bgs(&canvas, 3), vec![Color::Reset; 6]);
+
+    // Only the rows of the old and new cursor lines are changed.
+    let changed_rows = (0..4)
+      .filter(|y| {
+        (0..6).any(|x| {
+          let pos = point!(x: x, y: *y);
+          canvas.frame().get_cell(pos) != canvas.prev_frame().get_cell(pos)
+        })
+      })
+      .collect::<Vec<_>>();
+    assert_eq!(changed_rows, vec![0, 1, 2]);
+
+    // The 'cursorline' is off.
+    window_content.set_cursor_line(false);
+    window_content.draw(&mut canvas);
+    assert_eq!(row_bgs(&canvas, 2)[0], Color::Reset);
+  }
+
+  #[test]
+  fn draw_visual1() {
+    test_log_init();
+
+    let buffer = make_buffer_from_lines(vec!["Hello, RSVIM!\n", "a\tb\n", "cd\n"]);
+    let terminal_size = U16Size::new(10, 4);
+    let window_options = WindowLocalOptions::builder().wrap(true).build();
+    let actual_shape = U16Rect::new((0, 0), (terminal_size.width(), terminal_size.height()));
+    let viewport_options = ViewportOptions::from(&window_options);
+    let viewport = Viewport::new(&viewport_options, Arc::downgrade(&buffer), &actual_shape);
+    let viewport = Viewport::to_arc(viewport);
+    let shape = IRect::new((0, 0), (10, 4));
+    let mut window_content =
+      WindowContent::new(shape, Arc::downgrade(&buffer), Arc::downgrade(&viewport));
+
+    // The linewise selection covers both rows of the wrapped line.
+    window_content.set_selection(Some(Selection::new(
+      VisualKind::Linewise,
+      BufferPosition::new(0, 3),
+      BufferPosition::new(0, 3),
+    )));
+    let mut canvas = Canvas::new(terminal_size);
+    window_content.draw(&mut canvas);
+    assert_eq!(
+      frame_snapshot(canvas.frame()),
+      vec!["Hello, RSV", "IM!       ", "a        b", "cd        "]
+    );
+    assert_eq!(
+      frame_snapshot_styled(canvas.frame()),
+      vec![
+        "<attrs=Reverse>Hello, RSV</>",
+        "<attrs=Reverse>IM!</>       ",
+        "a        b",
+        "cd        "
+      ]
+    );
+
+    // The blockwise selection covers all the cells of the tab.
+    window_content.set_selection(Some(Selection::new(
+      VisualKind::Blockwise,
+      BufferPosition::new(2, 0),
+      BufferPosition::new(1, 1),
+    )));
+    let mut canvas = Canvas::new(terminal_size);
+    window_content.draw(&mut canvas);
+    assert_eq!(
+      frame_snapshot_styled(canvas.frame()),
+      vec![
+        "Hello, RSV",
+        "IM!       ",
+        "<attrs=Reverse>a        </>b",
+        "<attrs=Reverse>cd</>        "
+      ]
+    );
+  }
+
+  #[test]
+  fn draw_fold1() {
+    test_log_init();
+
+    let buffer = make_buffer_from_lines(vec![
+      "line 1\n",
+      "  line 2\n",
+      "line 3\n",
+      "line 4\n",
+      "line 5\n",
+      "line 6\n",
+    ]);
+    let terminal_size = U16Size::new(20, 5);
+    let shape = IRect::new((0, 0), (20, 5));
+    let actual_shape = U16Rect::new((0, 0), (20, 5));
+    let viewport_options = ViewportOptions::from(&WindowLocalOptions::builder().build());
+    let viewport = Viewport::new(&viewport_options, Arc::downgrade(&buffer), &actual_shape);
+    let viewport = Viewport::to_arc(viewport);
+    let window_content =
+      WindowContent::new(shape, Arc::downgrade(&buffer), Arc::downgrade(&viewport));
+
+    // Close the fold over the lines 2-4, the summary row is in the `Folded` style.
+    {
+      let mut viewport = wlock!(viewport);
+      viewport.folds_mut().create(1..4);
+      viewport.sync_from_top_left(0, 0);
+    }
+    let mut actual = Canvas::new(terminal_size);
+    window_content.draw(&mut actual);
+    do_test_draw_from_top_left(
+      &actual,
+      &[
+        "line 1              ",
+        "+--  3 lines: line 2",
+        "line 5              ",
+        "line 6              ",
+        "~                   ",
+      ],
+    );
+    let cell = actual.frame().get_cell(point!(x: 0, y: 1)).clone();
+    assert_eq!(cell.fg(), Color::DarkBlue);
+    assert_eq!(cell.bg(), Color::Grey);
+
+    // The rest of the summary row is filled with `-`.
+    let buffer = make_buffer_from_lines(vec!["a\n", "b\n"]);
+    let viewport = Viewport::new(&viewport_options, Arc::downgrade(&buffer), &actual_shape);
+    let viewport = Viewport::to_arc(viewport);
+    let window_content =
+      WindowContent::new(shape, Arc::downgrade(&buffer), Arc::downgrade(&viewport));
+    {
+      let mut viewport = wlock!(viewport);
+      viewport.folds_mut().create(0..1);
+      viewport.sync_from_top_left(0, 0);
+    }
+    let mut actual = Canvas::new(terminal_size);
+    window_content.draw(&mut actual);
+    do_test_draw_from_top_left(
+      &actual,
+      &[
+        "+--  1 line: a------",
+        "b                   ",
+        "~                   ",
+        "~                   ",
+        "~                   ",
+      ],
+    );
+  }
 }
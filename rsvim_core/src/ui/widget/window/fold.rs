@@ -0,0 +1,438 @@
+//! The folds of a window, i.e. `zf`/`zo`/`zc`/`za`, and the folds computed by the 'foldmethod'.
+//!
+//! See: <https://vimhelp.org/fold.txt.html#fold-methods>.
+
+use crate::buf::Buffer;
+use crate::ui::widget::window::opt::{FoldMarker, FoldMethod, WindowLocalOptions};
+
+use std::ops::Range;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// A fold, i.e. the range of buffer lines that can be closed into a single row.
+pub struct Fold {
+  lines: Range<usize>,
+  closed: bool,
+}
+
+impl Fold {
+  pub fn new(lines: Range<usize>, closed: bool) -> Self {
+    Fold { lines, closed }
+  }
+
+  /// The folded lines, the end is exclusive.
+  pub fn lines(&self) -> &Range<usize> {
+    &self.lines
+  }
+
+  pub fn is_closed(&self) -> bool {
+    self.closed
+  }
+
+  pub fn contains(&self, line_idx: usize) -> bool {
+    self.lines.contains(&line_idx)
+  }
+}
+
+#[derive(Debug, Clone)]
+/// The folds of a window, they're ordered by the start line, and the outer fold is before the
+/// inner folds with the same start line, thus the nested folds follow the fold containing them.
+///
+/// The lines inside a closed fold are hidden in the viewport, except its first line is displayed
+/// as a summary row, i.e. `+--  3 lines: text-----`.
+///
+/// With the "indent" and "marker" 'foldmethod', the folds are computed from the buffer text, and
+/// they're computed again after the buffer is changed. The folds with a level higher than the
+/// 'foldlevel' are initially closed, and all folds are displayed as open when the 'foldenable' is
+/// off.
+pub struct Folds {
+  folds: Vec<Fold>,
+  method: FoldMethod,
+  level: usize,
+  enable: bool,
+  marker: FoldMarker,
+  // The buffer changed tick of the computed folds, `None` if they need to be computed again.
+  computed_tick: Option<usize>,
+}
+
+impl Folds {
+  pub fn new() -> Self {
+    Folds::default()
+  }
+
+  pub fn len(&self) -> usize {
+    self.folds.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.folds.is_empty()
+  }
+
+  pub fn iter(&self) -> impl Iterator<Item = &Fold> {
+    self.folds.iter()
+  }
+
+  /// Remove all the folds, i.e. the window switches to another buffer.
+  pub fn clear(&mut self) {
+    self.folds.clear();
+    self.computed_tick = None;
+  }
+
+  /// Set the 'foldmethod', 'foldlevel', 'foldenable' and 'foldmarker' options. The folds are
+  /// computed again on next [`update`](Folds::update) if the method, level or marker is changed.
+  pub fn set_options(&mut self, options: &WindowLocalOptions) {
+    if self.method != options.fold_method()
+      || self.level != options.fold_level()
+      || self.marker != *options.fold_marker()
+    {
+      self.computed_tick = None;
+    }
+    self.method = options.fold_method();
+    self.level = options.fold_level();
+    self.enable = options.fold_enable();
+    self.marker = options.fold_marker().clone();
+  }
+
+  /// Compute the folds from the buffer, if the 'foldmethod' is not "manual" and the buffer is
+  /// changed after last computation. The open/closed state of an unchanged fold is kept, the new
+  /// folds are closed if their level is higher than the 'foldlevel'.
+  pub fn update(&mut self, buffer: &Buffer) {
+    if self.method == FoldMethod::Manual || self.computed_tick == Some(buffer.changed_tick()) {
+      return;
+    }
+    let levels = match self.method {
+      FoldMethod::Manual => unreachable!(),
+      FoldMethod::Indent => indent_folds(buffer),
+      FoldMethod::Marker => marker_folds(buffer, &self.marker),
+    };
+    let keep_states = self.computed_tick.is_some();
+    let mut folds = levels
+      .into_iter()
+      .map(|(lines, level)| {
+        let closed = match self.folds.iter().find(|f| keep_states && f.lines == lines) {
+          Some(old) => old.closed,
+          None => level > self.level,
+        };
+        Fold::new(lines, closed)
+      })
+      .collect::<Vec<_>>();
+    folds.sort_by_key(key);
+    self.folds = folds;
+    self.computed_tick = Some(buffer.changed_tick());
+  }
+
+  /// Create a closed fold over the lines, i.e. `zf`. The empty range is ignored.
+  pub fn create(&mut self, lines: Range<usize>) {
+    if lines.is_empty() {
+      return;
+    }
+    let fold = Fold::new(lines, true);
+    let idx = self.folds.partition_point(|f| key(f) <= key(&fold));
+    self.folds.insert(idx, fold);
+  }
+
+  /// Get the lines of the outermost closed fold containing the line, i.e. the lines hidden
+  /// together with it.
+  pub fn closed_fold_at(&self, line_idx: usize) -> Option<Range<usize>> {
+    if !self.enable {
+      return None;
+    }
+    self
+      .folds
+      .iter()
+      .find(|f| f.closed && f.contains(line_idx))
+      .map(|f| f.lines.clone())
+  }
+
+  /// Open the outermost closed fold containing the line, i.e. `zo`. It returns `false` if there's
+  /// no such fold.
+  pub fn open_at(&mut self, line_idx: usize) -> bool {
+    match self
+      .folds
+      .iter_mut()
+      .find(|f| f.closed && f.contains(line_idx))
+    {
+      Some(fold) => {
+        fold.closed = false;
+        true
+      }
+      None => false,
+    }
+  }
+
+  /// Close the innermost visible open fold containing the line, i.e. `zc`. The folds inside a
+  /// closed fold are not visible. It returns `false` if there's no such fold.
+  pub fn close_at(&mut self, line_idx: usize) -> bool {
+    let mut found: Option<usize> = None;
+    for (i, fold) in self.folds.iter().enumerate() {
+      if !fold.contains(line_idx) {
+        continue;
+      }
+      if fold.closed {
+        break;
+      }
+      found = Some(i);
+    }
+    match found {
+      Some(i) => {
+        self.folds[i].closed = true;
+        true
+      }
+      None => false,
+    }
+  }
+
+  /// Open the closed fold containing the line, otherwise close the open fold, i.e. `za`. It
+  /// returns `false` if there's no fold containing the line.
+  pub fn toggle_at(&mut self, line_idx: usize) -> bool {
+    if self.closed_fold_at(line_idx).is_some() {
+      self.open_at(line_idx)
+    } else {
+      self.close_at(line_idx)
+    }
+  }
+}
+
+impl Default for Folds {
+  fn default() -> Self {
+    let options = WindowLocalOptions::default();
+    Folds {
+      folds: vec![],
+      method: options.fold_method(),
+      level: options.fold_level(),
+      enable: options.fold_enable(),
+      marker: options.fold_marker().clone(),
+      computed_tick: None,
+    }
+  }
+}
+
+// The folds by indentation, each 'shiftwidth' of indentation is one level, and the lines with a
+// level greater than or equal to `n` form a fold of level `n`. The blank lines use the lower level
+// of the lines above and below them.
+fn indent_folds(buffer: &Buffer) -> Vec<(Range<usize>, usize)> {
+  let step = buffer.indent_step().max(1);
+  let len_lines = buffer.last_line_idx() + 1;
+  let levels = (0..len_lines)
+    .map(|line_idx| {
+      let blank = buffer
+        .get_line(line_idx)
+        .map(|line| line.chars().all(|c| c.is_whitespace()))
+        .unwrap_or(true);
+      if blank {
+        None
+      } else {
+        Some(buffer.indent_width(&buffer.line_indent(line_idx)) / step)
+      }
+    })
+    .collect::<Vec<_>>();
+  let levels = (0..len_lines)
+    .map(|line_idx| match levels[line_idx] {
+      Some(level) => level,
+      None => {
+        let above = levels[..line_idx].iter().rev().find_map(|level| *level);
+        let below = levels[line_idx + 1..].iter().find_map(|level| *level);
+        above.unwrap_or(0).min(below.unwrap_or(0))
+      }
+    })
+    .collect::<Vec<_>>();
+
+  let mut result = vec![];
+  let max_level = levels.iter().copied().max().unwrap_or(0);
+  for level in 1..=max_level {
+    let mut start: Option<usize> = None;
+    for (line_idx, line_level) in levels.iter().enumerate() {
+      match (start, *line_level >= level) {
+        (None, true) => start = Some(line_idx),
+        (Some(s), false) => {
+          result.push((s..line_idx, level));
+          start = None;
+        }
+        _ => {}
+      }
+    }
+    if let Some(s) = start {
+      result.push((s..len_lines, level));
+    }
+  }
+  result
+}
+
+// The folds between the start and end markers, the markers can be nested, and the level of a fold
+// is its nesting depth. The unclosed markers are closed at the end of buffer.
+fn marker_folds(buffer: &Buffer, marker: &FoldMarker) -> Vec<(Range<usize>, usize)> {
+  let len_lines = buffer.last_line_idx() + 1;
+  let mut result = vec![];
+  let mut starts: Vec<usize> = vec![];
+  for line_idx in 0..len_lines {
+    let line = match buffer.get_line(line_idx) {
+      Some(line) => line.to_string(),
+      None => break,
+    };
+    let mut markers = line
+      .match_indices(marker.start())
+      .map(|(i, _)| (i, true))
+      .chain(line.match_indices(marker.end()).map(|(i, _)| (i, false)))
+      .collect::<Vec<_>>();
+    markers.sort();
+    for (_, is_start) in markers {
+      if is_start {
+        starts.push(line_idx);
+      } else if let Some(start) = starts.pop() {
+        result.push((start..line_idx + 1, starts.len() + 1));
+      }
+    }
+  }
+  while let Some(start) = starts.pop() {
+    result.push((start..len_lines, starts.len() + 1));
+  }
+  result
+}
+
+// The sort key of the fold, the outer fold is before the inner folds with the same start line.
+fn key(fold: &Fold) -> (usize, std::cmp::Reverse<usize>) {
+  (fold.lines.start, std::cmp::Reverse(fold.lines.end))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use crate::envar;
+  use crate::test::buf::make_buffer_from_lines;
+  use crate::{rlock, wlock};
+
+  use std::str::FromStr;
+
+  #[test]
+  fn create1() {
+    let mut folds = Folds::new();
+    folds.create(5..8);
+    folds.create(1..4);
+    folds.create(1..10);
+    folds.create(3..3);
+    assert_eq!(
+      folds.iter().map(|f| f.lines().clone()).collect::<Vec<_>>(),
+      vec![1..10, 1..4, 5..8]
+    );
+    assert!(folds.iter().all(|f| f.is_closed()));
+
+    // The outermost closed fold hides the inner folds.
+    assert_eq!(folds.closed_fold_at(0), None);
+    assert_eq!(folds.closed_fold_at(6), Some(1..10));
+    assert_eq!(folds.closed_fold_at(10), None);
+  }
+
+  #[test]
+  fn open_close1() {
+    let mut folds = Folds::new();
+    folds.create(1..10);
+    folds.create(2..5);
+
+    // Open one level at a time.
+    assert!(folds.open_at(3));
+    assert_eq!(folds.closed_fold_at(3), Some(2..5));
+    assert_eq!(folds.closed_fold_at(6), None);
+    assert!(folds.open_at(3));
+    assert_eq!(folds.closed_fold_at(3), None);
+    assert!(!folds.open_at(3));
+
+    // Close the innermost fold first.
+    assert!(folds.close_at(3));
+    assert_eq!(folds.closed_fold_at(3), Some(2..5));
+    assert!(folds.close_at(3));
+    assert_eq!(folds.closed_fold_at(3), Some(1..10));
+    assert!(!folds.close_at(3));
+    assert!(!folds.close_at(0));
+
+    assert!(folds.toggle_at(7));
+    assert_eq!(folds.closed_fold_at(7), None);
+    assert!(folds.toggle_at(7));
+    assert_eq!(folds.closed_fold_at(7), Some(1..10));
+    assert!(!folds.toggle_at(10));
+  }
+
+  fn fold_lines(folds: &Folds) -> Vec<(Range<usize>, bool)> {
+    folds
+      .iter()
+      .map(|f| (f.lines().clone(), f.is_closed()))
+      .collect()
+  }
+
+  #[test]
+  fn indent1() {
+    let buf = make_buffer_from_lines(vec![
+      "fn main() {\n",
+      "  if x {\n",
+      "    a();\n",
+      "\n",
+      "    b();\n",
+      "  }\n",
+      "  c();\n",
+      "}\n",
+    ]);
+    wlock!(buf).set_shift_width(2);
+    let mut options = WindowLocalOptions::default();
+    options.set_fold_method(FoldMethod::Indent);
+    options.set_fold_level(1);
+
+    let mut folds = Folds::new();
+    folds.set_options(&options);
+    folds.update(&rlock!(buf));
+    // The blank line 3 is inside the nested block, only the nested fold is closed.
+    assert_eq!(fold_lines(&folds), vec![(1..7, false), (2..5, true)]);
+    assert_eq!(folds.closed_fold_at(3), Some(2..5));
+    assert_eq!(folds.closed_fold_at(1), None);
+
+    // The fold states are kept if the folds are unchanged after the buffer is changed.
+    assert!(folds.open_at(3));
+    wlock!(buf).insert(0, "// ").unwrap();
+    folds.update(&rlock!(buf));
+    assert_eq!(fold_lines(&folds), vec![(1..7, false), (2..5, false)]);
+    wlock!(buf).insert(0, "// main\n").unwrap();
+    folds.update(&rlock!(buf));
+    assert_eq!(fold_lines(&folds), vec![(2..8, false), (3..6, true)]);
+
+    // All folds are closed with 'foldlevel' 0, and none is displayed with 'nofoldenable'.
+    options.set_fold_level(0);
+    options.set_fold_enable(false);
+    folds.set_options(&options);
+    folds.update(&rlock!(buf));
+    assert_eq!(fold_lines(&folds), vec![(2..8, true), (3..6, true)]);
+    assert_eq!(folds.closed_fold_at(4), None);
+  }
+
+  #[test]
+  fn marker1() {
+    let buf = make_buffer_from_lines(vec![
+      "a\n",
+      "// section {{{\n",
+      "b\n",
+      "// inner <<\n",
+      "c\n",
+      "// >> }}}\n",
+      "d\n",
+      "// unclosed {{{\n",
+      "e\n",
+    ]);
+    let mut options = WindowLocalOptions::default();
+    options.set_fold_method(FoldMethod::Marker);
+
+    let mut folds = Folds::new();
+    folds.set_options(&options);
+    folds.update(&rlock!(buf));
+    assert_eq!(fold_lines(&folds), vec![(1..6, true), (7..9, true)]);
+    assert_eq!(folds.closed_fold_at(4), Some(1..6));
+    assert_eq!(folds.closed_fold_at(6), None);
+
+    options.set_fold_marker(FoldMarker::from_str("<<,>>").unwrap());
+    folds.set_options(&options);
+    folds.update(&rlock!(buf));
+    assert_eq!(fold_lines(&folds), vec![(3..6, true)]);
+
+    // The computed folds are kept after switching back to "manual".
+    options.set_fold_method(FoldMethod::Manual);
+    folds.set_options(&options);
+    folds.update(&rlock!(buf));
+    assert_eq!(fold_lines(&folds), vec![(3..6, true)]);
+  }
+}
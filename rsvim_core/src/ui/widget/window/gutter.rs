@@ -0,0 +1,374 @@
+//! Vim window's gutter widget, i.e. the 'signcolumn', 'foldcolumn' and 'number' columns on the
+//! left of the text content.
+
+use crate::buf::highlight::HighlightStyle;
+use crate::buf::{Buffer, BufferWk, ColorSchemes};
+use crate::cart::{IRect, U16Pos};
+use crate::envar;
+use crate::ui::canvas::{Canvas, Cell};
+use crate::ui::tree::internal::{InodeBase, InodeId, Inodeable};
+use crate::ui::widget::window::content::set_highlight_style;
+use crate::ui::widget::window::fold::Folds;
+use crate::ui::widget::window::opt::{SignColumn, WindowLocalOptions};
+use crate::ui::widget::window::viewport::{Viewport, ViewportWk};
+use crate::ui::widget::Widgetable;
+use crate::{inode_generate_impl, rlock};
+
+use compact_str::ToCompactString;
+use geo::point;
+use std::collections::BTreeMap;
+use tracing::trace;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+/// The width of the 'signcolumn'.
+pub const SIGN_COLUMN_WIDTH: u16 = 2;
+
+/// The max width of the 'foldcolumn'.
+pub const FOLD_COLUMN_MAX_WIDTH: u16 = 12;
+
+/// The min width of the 'number' column, i.e. the default 'numberwidth', including the space
+/// after the line number.
+pub const NUMBER_MIN_WIDTH: u16 = 4;
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+/// The widths of the gutter columns, they're composed from left to right: the 'signcolumn', the
+/// 'foldcolumn' and the 'number' column.
+pub struct GutterWidths {
+  /// The 'signcolumn' width, it's 0 if the 'signcolumn' is "no", or it's "auto" and the buffer has
+  /// no signs.
+  pub sign: u16,
+  /// The 'foldcolumn' width.
+  pub fold: u16,
+  /// The 'number' column width, it's 0 if the 'number' is off. Otherwise it fits the last line
+  /// number and a space, and it's at least [`NUMBER_MIN_WIDTH`].
+  pub number: u16,
+}
+
+impl GutterWidths {
+  /// Compute the widths of the gutter columns for the buffer shown in a window with the options.
+  pub fn new(options: &WindowLocalOptions, buffer: &Buffer) -> Self {
+    let sign = match options.sign_column() {
+      SignColumn::Yes => SIGN_COLUMN_WIDTH,
+      SignColumn::Auto if !buffer.signs().is_empty() => SIGN_COLUMN_WIDTH,
+      _ => 0,
+    };
+    let fold = std::cmp::min(options.fold_column(), FOLD_COLUMN_MAX_WIDTH as usize) as u16;
+    let number = if options.number() {
+      let digits = (buffer.last_line_idx() + 1).to_string().len() as u16;
+      std::cmp::max(digits + 1, NUMBER_MIN_WIDTH)
+    } else {
+      0
+    };
+    GutterWidths { sign, fold, number }
+  }
+
+  /// The total width of the gutter, i.e. the text content is shrunk by it.
+  pub fn total(&self) -> u16 {
+    self.sign + self.fold + self.number
+  }
+}
+
+#[derive(Debug, Clone)]
+/// The widget renders the gutter columns for Vim window, see [`GutterWidths`].
+pub struct WindowGutter {
+  base: InodeBase,
+
+  // Buffer.
+  buffer: BufferWk,
+
+  // Viewport.
+  viewport: ViewportWk,
+
+  // The widths of the gutter columns.
+  widths: GutterWidths,
+
+  // The whole width of the gutter, it can be less than the total of the `widths` if the window is
+  // too narrow.
+  width: u16,
+
+  // The columns on the left and rows on the top that are clipped, i.e. outside of the parent.
+  clipped_top_left: U16Pos,
+}
+
+impl WindowGutter {
+  /// Make window gutter.
+  pub fn new(shape: IRect, buffer: BufferWk, viewport: ViewportWk) -> Self {
+    let base = InodeBase::new(shape);
+    WindowGutter {
+      base,
+      buffer,
+      viewport,
+      widths: GutterWidths::default(),
+      width: 0,
+      clipped_top_left: point!(x: 0_u16, y: 0_u16),
+    }
+  }
+
+  /// Set the buffer, i.e. the window switches to another buffer.
+  pub fn set_buffer(&mut self, buffer: BufferWk) {
+    self.buffer = buffer;
+  }
+
+  /// Get the widths of the gutter columns.
+  pub fn widths(&self) -> GutterWidths {
+    self.widths
+  }
+
+  /// Get the whole width of the gutter.
+  pub fn width(&self) -> u16 {
+    self.width
+  }
+
+  /// Set the widths of the gutter columns, and the whole width of the gutter. The columns
+  /// exceeding the whole width are not rendered.
+  pub fn set_widths(&mut self, widths: GutterWidths, width: u16) {
+    self.widths = widths;
+    self.width = width;
+  }
+
+  /// Get the clipped columns on the left and rows on the top.
+  pub fn clipped_top_left(&self) -> U16Pos {
+    self.clipped_top_left
+  }
+
+  /// Set the clipped columns on the left and rows on the top, i.e. the window is partially outside
+  /// of its parent. The shape of the gutter only covers the visible slice of it.
+  pub fn set_clipped_top_left(&mut self, clipped_top_left: U16Pos) {
+    self.clipped_top_left = clipped_top_left;
+  }
+}
+
+inode_generate_impl!(WindowGutter, base);
+
+impl Widgetable for WindowGutter {
+  fn draw(&self, canvas: &mut Canvas) {
+    let actual_shape = *self.actual_shape();
+    let upos: U16Pos = actual_shape.min().into();
+    let height = actual_shape.height();
+    let width = actual_shape.width();
+
+    // If size is zero, exit.
+    if height == 0 || width == 0 {
+      trace!("Draw window gutter, actual shape is zero");
+      return;
+    }
+
+    let viewport = self.viewport.upgrade().unwrap();
+    let viewport = rlock!(viewport);
+    let buffer = self.buffer.upgrade().unwrap();
+    let buffer = rlock!(buffer);
+    let styles = GutterStyles::new();
+    let rows = line_rows(&viewport, &buffer);
+
+    for y in 0..height {
+      let row_idx = self.clipped_top_left.y() + y;
+      let mut cells = match rows.get(&row_idx) {
+        Some((line_idx, first_row)) => {
+          self.row_cells(&styles, &viewport, &buffer, *line_idx, *first_row)
+        }
+        // The rows below the end of buffer are blank.
+        None => vec![],
+      };
+      cells.resize(self.width as usize, styles.cell(' ', styles.normal));
+      let mut cells = cells
+        .into_iter()
+        .skip(self.clipped_top_left.x() as usize)
+        .take(width as usize)
+        .collect::<Vec<_>>();
+      // The wide sign text cut by the edges is rendered as a space, same with the content.
+      for (x, cell) in cells.iter_mut().enumerate() {
+        let symbol_width = cell.symbol().width();
+        if (x == 0 && symbol_width == 0) || x + symbol_width > width as usize {
+          cell.set_symbol(" ".to_compact_string());
+        }
+      }
+      canvas
+        .frame_mut()
+        .set_cells_at(point!(x: upos.x(), y: upos.y() + y), cells);
+    }
+  }
+}
+
+impl WindowGutter {
+  // The cells of the gutter columns of the row, the sign text and the line number are only
+  // rendered in the first row of the line, i.e. the wrapped rows are blank.
+  fn row_cells(
+    &self,
+    styles: &GutterStyles,
+    viewport: &Viewport,
+    buffer: &Buffer,
+    line_idx: usize,
+    first_row: bool,
+  ) -> Vec<Cell> {
+    let mut cells: Vec<Cell> = vec![];
+
+    let sign = buffer
+      .signs()
+      .top(line_idx)
+      .filter(|_| first_row)
+      .map(|sign| sign.glyph())
+      .unwrap_or_default();
+    let mut sign_width = 0_u16;
+    for c in sign.chars() {
+      let c_width = c.width().unwrap_or(0) as u16;
+      if c_width == 0 || sign_width + c_width > self.widths.sign {
+        break;
+      }
+      let cell = styles.cell(c, styles.sign_column);
+      let guard = Cell::wide_guard(&cell);
+      cells.push(cell);
+      cells.extend((1..c_width).map(|_| guard.clone()));
+      sign_width += c_width;
+    }
+    cells.extend((sign_width..self.widths.sign).map(|_| styles.cell(' ', styles.sign_column)));
+
+    if self.widths.fold > 0 {
+      let marker = fold_marker(viewport.folds(), line_idx, first_row);
+      cells.push(styles.cell(marker, styles.fold_column));
+      cells.extend((1..self.widths.fold).map(|_| styles.cell(' ', styles.fold_column)));
+    }
+
+    if self.widths.number > 0 {
+      let number = if first_row {
+        format!("{:>1$} ", line_idx + 1, self.widths.number as usize - 1)
+      } else {
+        " ".repeat(self.widths.number as usize)
+      };
+      cells.extend(number.chars().map(|c| styles.cell(c, styles.line_nr)));
+    }
+
+    cells
+  }
+}
+
+// The buffer line of each row in the viewport, and whether the row is the first row of the line.
+// The closed fold has only one row, i.e. the summary row of its first line.
+fn line_rows(viewport: &Viewport, buffer: &Buffer) -> BTreeMap<u16, (usize, bool)> {
+  let mut rows = BTreeMap::new();
+  // If the buffer is empty, the line 0 still occupies the first row.
+  if buffer.len_chars() == 0 {
+    rows.insert(0_u16, (0_usize, true));
+  }
+  for (line_idx, line_viewport) in viewport.lines() {
+    let first_row_idx = line_viewport.rows().first_key_value().map(|(r, _)| *r);
+    for row_idx in line_viewport.rows().keys() {
+      rows.insert(*row_idx, (*line_idx, Some(*row_idx) == first_row_idx));
+    }
+  }
+  rows
+}
+
+// The 'foldcolumn' marker of the row: `+` for the closed fold, `-` for the first line of the open
+// fold, `|` for the other lines inside the fold.
+fn fold_marker(folds: &Folds, line_idx: usize, first_row: bool) -> char {
+  if first_row {
+    if folds.closed_fold_at(line_idx).is_some() {
+      return '+';
+    }
+    if folds.iter().any(|fold| fold.lines().start == line_idx) {
+      return '-';
+    }
+  }
+  if folds.iter().any(|fold| fold.contains(line_idx)) {
+    '|'
+  } else {
+    ' '
+  }
+}
+
+/// The styles of the UI highlight groups rendered by the window gutter, they're resolved by the
+/// current color scheme once for each draw.
+struct GutterStyles {
+  /// `Normal`, the rows below the end of buffer.
+  normal: HighlightStyle,
+  /// `SignColumn`, the 'signcolumn'.
+  sign_column: HighlightStyle,
+  /// `FoldColumn`, the 'foldcolumn'.
+  fold_column: HighlightStyle,
+  /// `LineNr`, the 'number' column.
+  line_nr: HighlightStyle,
+}
+
+impl GutterStyles {
+  fn new() -> Self {
+    let color_schemes = rlock!(ColorSchemes::global());
+    let resolve = |group: &str| color_schemes.resolve(group).unwrap_or_default();
+    GutterStyles {
+      normal: resolve("Normal"),
+      sign_column: resolve("SignColumn"),
+      fold_column: resolve("FoldColumn"),
+      line_nr: resolve("LineNr"),
+    }
+  }
+
+  /// Make the cell of the char in the style.
+  fn cell(&self, c: char, style: HighlightStyle) -> Cell {
+    let mut cell = Cell::from(c);
+    set_highlight_style(&mut cell, style);
+    cell
+  }
+}
+
+#[allow(unused_imports)]
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use crate::buf::sign::{Sign, DEFAULT_SIGN_PRIORITY};
+  use crate::test::buf::{make_buffer_from_lines, make_empty_buffer};
+  use crate::test::log::init as test_log_init;
+  use crate::wlock;
+
+  #[test]
+  fn widths1() {
+    test_log_init();
+
+    let lines: Vec<String> = (0..100).map(|i| format!("line {}\n", i)).collect();
+    let buffer = make_buffer_from_lines(lines.iter().map(|l| l.as_str()).collect());
+
+    // All the gutter columns are off by default, the 'signcolumn' is "auto" without signs.
+    let options = WindowLocalOptions::builder().build();
+    let widths = GutterWidths::new(&options, &rlock!(buffer));
+    assert_eq!(widths, GutterWidths::default());
+    assert_eq!(widths.total(), 0);
+
+    // The 'number' column fits "100" and a space, composed with the 'foldcolumn' and the
+    // 'signcolumn'.
+    let options = WindowLocalOptions::builder()
+      .number(true)
+      .fold_column(2)
+      .sign_column(SignColumn::Yes)
+      .build();
+    let widths = GutterWidths::new(&options, &rlock!(buffer));
+    assert_eq!(
+      widths,
+      GutterWidths {
+        sign: 2,
+        fold: 2,
+        number: 4
+      }
+    );
+    assert_eq!(widths.total(), 8);
+
+    // The "auto" 'signcolumn' only reserves the space when there're signs.
+    let options = WindowLocalOptions::builder()
+      .number(true)
+      .fold_column(20)
+      .sign_column(SignColumn::Auto)
+      .build();
+    assert_eq!(GutterWidths::new(&options, &rlock!(buffer)).total(), 16);
+    wlock!(buffer)
+      .place_sign(3, Sign::new("test", ">>", DEFAULT_SIGN_PRIORITY))
+      .unwrap();
+    assert_eq!(GutterWidths::new(&options, &rlock!(buffer)).total(), 18);
+    let options = WindowLocalOptions::builder()
+      .sign_column(SignColumn::No)
+      .build();
+    assert_eq!(GutterWidths::new(&options, &rlock!(buffer)).total(), 0);
+
+    // The 'number' column is at least 4 columns.
+    let buffer = make_buffer_from_lines(vec!["a\n", "b\n"]);
+    let options = WindowLocalOptions::builder().number(true).build();
+    assert_eq!(GutterWidths::new(&options, &rlock!(buffer)).number, 4);
+  }
+}
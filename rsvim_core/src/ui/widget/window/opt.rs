@@ -1,12 +1,33 @@
 //! Window local options.
 
 use crate::defaults;
+use crate::res::{AnyErr, AnyResult};
+
+use std::fmt::Display;
+use std::str::FromStr;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 
 #[derive(Debug, Clone)]
 /// Window options.
 pub struct WindowLocalOptions {
   wrap: bool,
   line_break: bool,
+  fill_chars: FillChars,
+  list: bool,
+  list_chars: ListChars,
+  show_break: String,
+  scroll: usize,
+  scroll_off: usize,
+  fold_method: FoldMethod,
+  fold_level: usize,
+  fold_enable: bool,
+  fold_marker: FoldMarker,
+  cursor_line: bool,
+  number: bool,
+  fold_column: usize,
+  sign_column: SignColumn,
+  win_fix_height: bool,
+  win_fix_width: bool,
 }
 
 impl Default for WindowLocalOptions {
@@ -39,12 +60,193 @@ impl WindowLocalOptions {
   pub fn set_line_break(&mut self, value: bool) {
     self.line_break = value;
   }
+
+  /// The 'fillchars' option, default to `"eob:~"`.
+  /// See: <https://vimhelp.org/options.txt.html#%27fillchars%27>.
+  pub fn fill_chars(&self) -> FillChars {
+    self.fill_chars
+  }
+
+  pub fn set_fill_chars(&mut self, value: FillChars) {
+    self.fill_chars = value;
+  }
+
+  /// The 'list' option, show tabs and trailing spaces with the 'listchars', default to `false`.
+  /// See: <https://vimhelp.org/options.txt.html#%27list%27>.
+  pub fn list(&self) -> bool {
+    self.list
+  }
+
+  pub fn set_list(&mut self, value: bool) {
+    self.list = value;
+  }
+
+  /// The 'listchars' option, default to `"tab:» ,trail:·"`.
+  /// See: <https://vimhelp.org/options.txt.html#%27listchars%27>.
+  pub fn list_chars(&self) -> ListChars {
+    self.list_chars
+  }
+
+  pub fn set_list_chars(&mut self, value: ListChars) {
+    self.list_chars = value;
+  }
+
+  /// The 'showbreak' option, the string to put at the start of the wrapped rows, default to `""`.
+  /// See: <https://vimhelp.org/options.txt.html#%27showbreak%27>.
+  pub fn show_break(&self) -> &str {
+    &self.show_break
+  }
+
+  pub fn set_show_break(&mut self, value: String) {
+    self.show_break = value;
+  }
+
+  /// The 'scroll' option, the rows to scroll with `Ctrl-D`/`Ctrl-U`, default to `0`, i.e. half of
+  /// the window height.
+  /// See: <https://vimhelp.org/options.txt.html#%27scroll%27>.
+  pub fn scroll(&self) -> usize {
+    self.scroll
+  }
+
+  pub fn set_scroll(&mut self, value: usize) {
+    self.scroll = value;
+  }
+
+  /// The 'scrolloff' option, the minimal lines to keep above and below the cursor, default to `0`.
+  /// See: <https://vimhelp.org/options.txt.html#%27scrolloff%27>.
+  pub fn scroll_off(&self) -> usize {
+    self.scroll_off
+  }
+
+  pub fn set_scroll_off(&mut self, value: usize) {
+    self.scroll_off = value;
+  }
+
+  /// The 'foldmethod' option, the kind of folding, default to `"manual"`.
+  /// See: <https://vimhelp.org/options.txt.html#%27foldmethod%27>.
+  pub fn fold_method(&self) -> FoldMethod {
+    self.fold_method
+  }
+
+  pub fn set_fold_method(&mut self, value: FoldMethod) {
+    self.fold_method = value;
+  }
+
+  /// The 'foldlevel' option, the folds with a higher level are closed, default to `0`, i.e. all
+  /// the computed folds are closed.
+  /// See: <https://vimhelp.org/options.txt.html#%27foldlevel%27>.
+  pub fn fold_level(&self) -> usize {
+    self.fold_level
+  }
+
+  pub fn set_fold_level(&mut self, value: usize) {
+    self.fold_level = value;
+  }
+
+  /// The 'foldenable' option, all folds are open when it is off, default to `true`.
+  /// See: <https://vimhelp.org/options.txt.html#%27foldenable%27>.
+  pub fn fold_enable(&self) -> bool {
+    self.fold_enable
+  }
+
+  pub fn set_fold_enable(&mut self, value: bool) {
+    self.fold_enable = value;
+  }
+
+  /// The 'foldmarker' option, the markers of the "marker" 'foldmethod', default to `"{{{,}}}"`.
+  /// See: <https://vimhelp.org/options.txt.html#%27foldmarker%27>.
+  pub fn fold_marker(&self) -> &FoldMarker {
+    &self.fold_marker
+  }
+
+  pub fn set_fold_marker(&mut self, value: FoldMarker) {
+    self.fold_marker = value;
+  }
+
+  /// The 'cursorline' option, highlight the screen line of the cursor, default to `false`.
+  /// See: <https://vimhelp.org/options.txt.html#%27cursorline%27>.
+  pub fn cursor_line(&self) -> bool {
+    self.cursor_line
+  }
+
+  pub fn set_cursor_line(&mut self, value: bool) {
+    self.cursor_line = value;
+  }
+
+  /// The 'number' option, show the line number in front of each line, default to `false`.
+  /// See: <https://vimhelp.org/options.txt.html#%27number%27>.
+  pub fn number(&self) -> bool {
+    self.number
+  }
+
+  pub fn set_number(&mut self, value: bool) {
+    self.number = value;
+  }
+
+  /// The 'foldcolumn' option, the width of the column that indicates the open and closed folds,
+  /// default to `0`, i.e. no fold column.
+  /// See: <https://vimhelp.org/options.txt.html#%27foldcolumn%27>.
+  pub fn fold_column(&self) -> usize {
+    self.fold_column
+  }
+
+  pub fn set_fold_column(&mut self, value: usize) {
+    self.fold_column = value;
+  }
+
+  /// The 'signcolumn' option, whether to show the column of the signs, default to `"auto"`.
+  /// See: <https://vimhelp.org/options.txt.html#%27signcolumn%27>.
+  pub fn sign_column(&self) -> SignColumn {
+    self.sign_column
+  }
+
+  pub fn set_sign_column(&mut self, value: SignColumn) {
+    self.sign_column = value;
+  }
+
+  /// The 'winfixheight' option, keep the window height when the windows are made the same size,
+  /// i.e. `Ctrl-W =` and 'equalalways', default to `false`.
+  /// See: <https://vimhelp.org/options.txt.html#%27winfixheight%27>.
+  pub fn win_fix_height(&self) -> bool {
+    self.win_fix_height
+  }
+
+  pub fn set_win_fix_height(&mut self, value: bool) {
+    self.win_fix_height = value;
+  }
+
+  /// The 'winfixwidth' option, keep the window width when the windows are made the same size,
+  /// default to `false`.
+  /// See: <https://vimhelp.org/options.txt.html#%27winfixwidth%27>.
+  pub fn win_fix_width(&self) -> bool {
+    self.win_fix_width
+  }
+
+  pub fn set_win_fix_width(&mut self, value: bool) {
+    self.win_fix_width = value;
+  }
 }
 
 /// The builder for [`WindowLocalOptions`].
 pub struct WindowOptionsBuilder {
   wrap: bool,
   line_break: bool,
+  fill_chars: FillChars,
+  list: bool,
+  list_chars: ListChars,
+  show_break: String,
+  scroll: usize,
+  scroll_off: usize,
+  fold_method: FoldMethod,
+  fold_level: usize,
+  fold_enable: bool,
+  fold_marker: FoldMarker,
+  cursor_line: bool,
+  number: bool,
+  fold_column: usize,
+  sign_column: SignColumn,
+  win_fix_height: bool,
+  win_fix_width: bool,
 }
 
 impl WindowOptionsBuilder {
@@ -56,10 +258,90 @@ impl WindowOptionsBuilder {
     self.line_break = value;
     self
   }
+  pub fn fill_chars(&mut self, value: FillChars) -> &mut Self {
+    self.fill_chars = value;
+    self
+  }
+  pub fn list(&mut self, value: bool) -> &mut Self {
+    self.list = value;
+    self
+  }
+  pub fn list_chars(&mut self, value: ListChars) -> &mut Self {
+    self.list_chars = value;
+    self
+  }
+  pub fn show_break(&mut self, value: &str) -> &mut Self {
+    self.show_break = value.to_string();
+    self
+  }
+  pub fn scroll(&mut self, value: usize) -> &mut Self {
+    self.scroll = value;
+    self
+  }
+  pub fn scroll_off(&mut self, value: usize) -> &mut Self {
+    self.scroll_off = value;
+    self
+  }
+  pub fn fold_method(&mut self, value: FoldMethod) -> &mut Self {
+    self.fold_method = value;
+    self
+  }
+  pub fn fold_level(&mut self, value: usize) -> &mut Self {
+    self.fold_level = value;
+    self
+  }
+  pub fn fold_enable(&mut self, value: bool) -> &mut Self {
+    self.fold_enable = value;
+    self
+  }
+  pub fn fold_marker(&mut self, value: FoldMarker) -> &mut Self {
+    self.fold_marker = value;
+    self
+  }
+  pub fn cursor_line(&mut self, value: bool) -> &mut Self {
+    self.cursor_line = value;
+    self
+  }
+  pub fn number(&mut self, value: bool) -> &mut Self {
+    self.number = value;
+    self
+  }
+  pub fn fold_column(&mut self, value: usize) -> &mut Self {
+    self.fold_column = value;
+    self
+  }
+  pub fn sign_column(&mut self, value: SignColumn) -> &mut Self {
+    self.sign_column = value;
+    self
+  }
+  pub fn win_fix_height(&mut self, value: bool) -> &mut Self {
+    self.win_fix_height = value;
+    self
+  }
+  pub fn win_fix_width(&mut self, value: bool) -> &mut Self {
+    self.win_fix_width = value;
+    self
+  }
   pub fn build(&self) -> WindowLocalOptions {
     WindowLocalOptions {
       wrap: self.wrap,
       line_break: self.line_break,
+      fill_chars: self.fill_chars,
+      list: self.list,
+      list_chars: self.list_chars,
+      show_break: self.show_break.clone(),
+      scroll: self.scroll,
+      scroll_off: self.scroll_off,
+      fold_method: self.fold_method,
+      fold_level: self.fold_level,
+      fold_enable: self.fold_enable,
+      fold_marker: self.fold_marker.clone(),
+      cursor_line: self.cursor_line,
+      number: self.number,
+      fold_column: self.fold_column,
+      sign_column: self.sign_column,
+      win_fix_height: self.win_fix_height,
+      win_fix_width: self.win_fix_width,
     }
   }
 }
@@ -69,7 +351,297 @@ impl Default for WindowOptionsBuilder {
     WindowOptionsBuilder {
       wrap: defaults::win::WRAP,
       line_break: defaults::win::LINE_BREAK,
+      fill_chars: FillChars::from_str(defaults::win::FILL_CHARS).unwrap(),
+      list: defaults::win::LIST,
+      list_chars: ListChars::from_str(defaults::win::LIST_CHARS).unwrap(),
+      show_break: defaults::win::SHOW_BREAK.to_string(),
+      scroll: defaults::win::SCROLL,
+      scroll_off: defaults::win::SCROLL_OFF,
+      fold_method: FoldMethod::from_str(defaults::win::FOLD_METHOD).unwrap(),
+      fold_level: defaults::win::FOLD_LEVEL,
+      fold_enable: defaults::win::FOLD_ENABLE,
+      fold_marker: FoldMarker::from_str(defaults::win::FOLD_MARKER).unwrap(),
+      cursor_line: defaults::win::CURSOR_LINE,
+      number: defaults::win::NUMBER,
+      fold_column: defaults::win::FOLD_COLUMN,
+      sign_column: SignColumn::from_str(defaults::win::SIGN_COLUMN).unwrap(),
+      win_fix_height: defaults::win::WIN_FIX_HEIGHT,
+      win_fix_width: defaults::win::WIN_FIX_WIDTH,
+    }
+  }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+/// The 'fillchars' option, the characters to fill the special items of a window.
+///
+/// It is parsed from a comma separated list of `{item}:{char}`, e.g. `"eob: "`. The omitted items
+/// use the default characters. For now only the `eob` item is supported:
+///
+/// * `eob`: Empty lines below the end of a buffer, default to `~`.
+///
+/// See: <https://vimhelp.org/options.txt.html#%27fillchars%27>.
+pub struct FillChars {
+  eob: char,
+}
+
+impl Default for FillChars {
+  fn default() -> Self {
+    FillChars { eob: '~' }
+  }
+}
+
+impl FillChars {
+  /// The `eob` item.
+  pub fn eob(&self) -> char {
+    self.eob
+  }
+
+  pub fn set_eob(&mut self, value: char) {
+    self.eob = value;
+  }
+}
+
+impl FromStr for FillChars {
+  type Err = AnyErr;
+
+  fn from_str(s: &str) -> AnyResult<Self> {
+    let mut fill_chars = FillChars::default();
+    for item in s.split(',').filter(|item| !item.is_empty()) {
+      let (name, value) = match item.split_once(':') {
+        Some((name, value)) => (name, value),
+        None => anyhow::bail!("E474: Invalid argument: fillchars={}", s),
+      };
+      let mut chars = value.chars();
+      let c = match (chars.next(), chars.next()) {
+        (Some(c), None) => c,
+        _ => anyhow::bail!("E474: Invalid argument: fillchars={}", s),
+      };
+      match name {
+        "eob" => fill_chars.eob = c,
+        _ => anyhow::bail!("E474: Invalid argument: fillchars={}", s),
+      }
+    }
+    Ok(fill_chars)
+  }
+}
+
+impl Display for FillChars {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "eob:{}", self.eob)
+  }
+}
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+/// The 'listchars' option, the characters to show the invisible chars in 'list' mode.
+///
+/// It is parsed from a comma separated list of `{item}:{chars}`, e.g. `"tab:> ,trail:-"`. The
+/// omitted items are not shown, i.e. they're rendered as if 'list' is off. For now these items are
+/// supported:
+///
+/// * `tab`: Two chars, the tab is shown as the first char, followed by the second char repeated
+///   for the rest of the tab width.
+/// * `trail`: One char, the trailing spaces at the end of a line are shown as it.
+/// * `eol`: One char, it is shown after the end of each line.
+///
+/// All chars must be single-width, since the 'list' mode only changes the rendered symbols, not
+/// the display width.
+///
+/// See: <https://vimhelp.org/options.txt.html#%27listchars%27>.
+pub struct ListChars {
+  tab: Option<(char, char)>,
+  trail: Option<char>,
+  eol: Option<char>,
+}
+
+impl ListChars {
+  /// The `tab` item.
+  pub fn tab(&self) -> Option<(char, char)> {
+    self.tab
+  }
+
+  pub fn set_tab(&mut self, value: Option<(char, char)>) {
+    self.tab = value;
+  }
+
+  /// The `trail` item.
+  pub fn trail(&self) -> Option<char> {
+    self.trail
+  }
+
+  pub fn set_trail(&mut self, value: Option<char>) {
+    self.trail = value;
+  }
+
+  /// The `eol` item.
+  pub fn eol(&self) -> Option<char> {
+    self.eol
+  }
+
+  pub fn set_eol(&mut self, value: Option<char>) {
+    self.eol = value;
+  }
+}
+
+impl FromStr for ListChars {
+  type Err = AnyErr;
+
+  fn from_str(s: &str) -> AnyResult<Self> {
+    let mut list_chars = ListChars::default();
+    for item in s.split(',').filter(|item| !item.is_empty()) {
+      let (name, value) = match item.split_once(':') {
+        Some((name, value)) => (name, value),
+        None => anyhow::bail!("E474: Invalid argument: listchars={}", s),
+      };
+      let chars = value.chars().collect::<Vec<_>>();
+      if chars.iter().any(|c| c.width() != Some(1)) {
+        anyhow::bail!("E474: Invalid argument: listchars={}", s);
+      }
+      match (name, chars.as_slice()) {
+        ("tab", [lead, fill]) => list_chars.tab = Some((*lead, *fill)),
+        ("trail", [c]) => list_chars.trail = Some(*c),
+        ("eol", [c]) => list_chars.eol = Some(*c),
+        _ => anyhow::bail!("E474: Invalid argument: listchars={}", s),
+      }
+    }
+    Ok(list_chars)
+  }
+}
+
+impl Display for ListChars {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    let mut items = vec![];
+    if let Some((lead, fill)) = self.tab {
+      items.push(format!("tab:{lead}{fill}"));
     }
+    if let Some(trail) = self.trail {
+      items.push(format!("trail:{trail}"));
+    }
+    if let Some(eol) = self.eol {
+      items.push(format!("eol:{eol}"));
+    }
+    write!(f, "{}", items.join(","))
+  }
+}
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+/// The 'foldmethod' option, the kind of folding used for a window:
+///
+/// * `manual`: The folds are created by the `zf`/`zF` commands.
+/// * `indent`: The lines with the same indentation form a fold, each 'shiftwidth' of indentation
+///   is one fold level.
+/// * `marker`: The lines between the 'foldmarker' markers form a fold.
+///
+/// See: <https://vimhelp.org/options.txt.html#%27foldmethod%27>.
+pub enum FoldMethod {
+  #[default]
+  Manual,
+  Indent,
+  Marker,
+}
+
+impl FromStr for FoldMethod {
+  type Err = AnyErr;
+
+  fn from_str(s: &str) -> AnyResult<Self> {
+    match s {
+      "manual" => Ok(FoldMethod::Manual),
+      "indent" => Ok(FoldMethod::Indent),
+      "marker" => Ok(FoldMethod::Marker),
+      _ => anyhow::bail!("E474: Invalid argument: foldmethod={}", s),
+    }
+  }
+}
+
+impl Display for FoldMethod {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      FoldMethod::Manual => write!(f, "manual"),
+      FoldMethod::Indent => write!(f, "indent"),
+      FoldMethod::Marker => write!(f, "marker"),
+    }
+  }
+}
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+/// The 'signcolumn' option, whether to show the column of the signs:
+///
+/// * `auto`: Only when there are signs in the buffer.
+/// * `no`: Never.
+/// * `yes`: Always.
+///
+/// See: <https://vimhelp.org/options.txt.html#%27signcolumn%27>.
+pub enum SignColumn {
+  #[default]
+  Auto,
+  No,
+  Yes,
+}
+
+impl FromStr for SignColumn {
+  type Err = AnyErr;
+
+  fn from_str(s: &str) -> AnyResult<Self> {
+    match s {
+      "auto" => Ok(SignColumn::Auto),
+      "no" => Ok(SignColumn::No),
+      "yes" => Ok(SignColumn::Yes),
+      _ => anyhow::bail!("E474: Invalid argument: signcolumn={}", s),
+    }
+  }
+}
+
+impl Display for SignColumn {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      SignColumn::Auto => write!(f, "auto"),
+      SignColumn::No => write!(f, "no"),
+      SignColumn::Yes => write!(f, "yes"),
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// The 'foldmarker' option, the start and end markers of the "marker" 'foldmethod'.
+///
+/// It is parsed from the two non-empty markers separated by a comma, e.g. `"{{{,}}}"`.
+///
+/// See: <https://vimhelp.org/options.txt.html#%27foldmarker%27>.
+pub struct FoldMarker {
+  start: String,
+  end: String,
+}
+
+impl FoldMarker {
+  /// The start marker.
+  pub fn start(&self) -> &str {
+    &self.start
+  }
+
+  /// The end marker.
+  pub fn end(&self) -> &str {
+    &self.end
+  }
+}
+
+impl FromStr for FoldMarker {
+  type Err = AnyErr;
+
+  fn from_str(s: &str) -> AnyResult<Self> {
+    match s.split_once(',') {
+      Some((start, end)) if !start.is_empty() && !end.is_empty() && !end.contains(',') => {
+        Ok(FoldMarker {
+          start: start.to_string(),
+          end: end.to_string(),
+        })
+      }
+      _ => anyhow::bail!("E536: Comma required: foldmarker={}", s),
+    }
+  }
+}
+
+impl Display for FoldMarker {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "{},{}", self.start, self.end)
   }
 }
 
@@ -78,6 +650,10 @@ impl Default for WindowOptionsBuilder {
 pub struct ViewportOptions {
   pub wrap: bool,
   pub line_break: bool,
+  /// The display width of the 'showbreak', the wrapped rows are indented by it.
+  pub show_break_width: usize,
+  pub scroll: usize,
+  pub scroll_off: usize,
 }
 
 impl From<&WindowLocalOptions> for ViewportOptions {
@@ -85,6 +661,9 @@ impl From<&WindowLocalOptions> for ViewportOptions {
     Self {
       wrap: value.wrap(),
       line_break: value.line_break(),
+      show_break_width: UnicodeWidthStr::width(value.show_break()),
+      scroll: value.scroll(),
+      scroll_off: value.scroll_off(),
     }
   }
 }
@@ -103,5 +682,82 @@ mod tests {
     let opt2 = WindowLocalOptions::builder().build();
     assert!(opt2.wrap());
     assert!(!opt2.line_break());
+    assert_eq!(opt2.fill_chars().eob(), '~');
+    assert!(!opt2.list());
+    assert_eq!(opt2.list_chars().tab(), Some(('»', ' ')));
+    assert_eq!(opt2.list_chars().trail(), Some('·'));
+    assert_eq!(opt2.list_chars().eol(), None);
+    assert_eq!(opt2.show_break(), "");
+    assert_eq!(opt2.scroll(), 0);
+    assert_eq!(opt2.scroll_off(), 0);
+    assert_eq!(opt2.fold_method(), FoldMethod::Manual);
+    assert_eq!(opt2.fold_level(), 0);
+    assert!(opt2.fold_enable());
+    assert_eq!(opt2.fold_marker().to_string(), "{{{,}}}");
+    assert!(!opt2.cursor_line());
+    assert!(!opt2.number());
+    assert_eq!(opt2.fold_column(), 0);
+    assert_eq!(opt2.sign_column(), SignColumn::Auto);
+    assert!(!opt2.win_fix_height());
+    assert!(!opt2.win_fix_width());
+
+    let opt3 = WindowLocalOptions::builder()
+      .scroll(5)
+      .scroll_off(3)
+      .show_break("↪ ")
+      .build();
+    let viewport_opt3 = ViewportOptions::from(&opt3);
+    assert_eq!(viewport_opt3.show_break_width, 2);
+    assert_eq!(viewport_opt3.scroll, 5);
+    assert_eq!(viewport_opt3.scroll_off, 3);
+  }
+
+  #[test]
+  pub fn fill_chars1() {
+    assert_eq!(FillChars::from_str("eob: ").unwrap().eob(), ' ');
+    assert_eq!(FillChars::from_str("eob:-").unwrap().eob(), '-');
+    assert_eq!(FillChars::from_str("").unwrap(), FillChars::default());
+    assert_eq!(FillChars::from_str("eob:~").unwrap().to_string(), "eob:~");
+    assert!(FillChars::from_str("eob").is_err());
+    assert!(FillChars::from_str("eob:ab").is_err());
+    assert!(FillChars::from_str("eob:").is_err());
+    assert!(FillChars::from_str("unknown:x").is_err());
+  }
+
+  #[test]
+  pub fn list_chars1() {
+    let list_chars = ListChars::from_str("tab:>-,trail:.,eol:$").unwrap();
+    assert_eq!(list_chars.tab(), Some(('>', '-')));
+    assert_eq!(list_chars.trail(), Some('.'));
+    assert_eq!(list_chars.eol(), Some('$'));
+    assert_eq!(list_chars.to_string(), "tab:>-,trail:.,eol:$");
+    assert_eq!(ListChars::from_str("").unwrap(), ListChars::default());
+    assert_eq!(ListChars::from_str("eol:$").unwrap().to_string(), "eol:$");
+    assert!(ListChars::from_str("tab").is_err());
+    assert!(ListChars::from_str("tab:>").is_err());
+    assert!(ListChars::from_str("tab:>--").is_err());
+    assert!(ListChars::from_str("trail:").is_err());
+    assert!(ListChars::from_str("trail:ab").is_err());
+    assert!(ListChars::from_str("eol:\t").is_err());
+    // The wide chars are not allowed.
+    assert!(ListChars::from_str("trail:好").is_err());
+    assert!(ListChars::from_str("unknown:x").is_err());
+  }
+
+  #[test]
+  pub fn fold_method_marker1() {
+    assert_eq!(FoldMethod::from_str("indent").unwrap(), FoldMethod::Indent);
+    assert_eq!(
+      FoldMethod::from_str("marker").unwrap().to_string(),
+      "marker"
+    );
+    assert!(FoldMethod::from_str("syntax").is_err());
+
+    let marker = FoldMarker::from_str("<<,>>").unwrap();
+    assert_eq!(marker.start(), "<<");
+    assert_eq!(marker.end(), ">>");
+    assert!(FoldMarker::from_str("{{{").is_err());
+    assert!(FoldMarker::from_str(",}}}").is_err());
+    assert!(FoldMarker::from_str("a,b,c").is_err());
   }
 }
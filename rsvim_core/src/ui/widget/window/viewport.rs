@@ -2,8 +2,9 @@
 
 use crate::buf::BufferWk;
 use crate::cart::U16Rect;
-//use crate::envar;
-//use crate::rlock;
+use crate::envar;
+use crate::rlock;
+use crate::ui::widget::window::fold::Folds;
 use crate::ui::widget::window::ViewportOptions;
 
 use parking_lot::RwLock;
@@ -12,6 +13,7 @@ use std::ops::Range;
 use std::sync::{Arc, Weak};
 // use tracing::trace;
 
+pub mod layout;
 pub mod sync;
 
 #[derive(Debug, Clone)]
@@ -466,16 +468,38 @@ pub struct Viewport {
 
   // Cursor position (if has).
   cursor: CursorViewport,
+
+  // Folds.
+  folds: Folds,
 }
 
 pub type ViewportArc = Arc<RwLock<Viewport>>;
 pub type ViewportWk = Weak<RwLock<Viewport>>;
 
 impl Viewport {
-  /// Make new instance.
+  /// Make new instance, the viewport starts from the first line, i.e. starts from 0.
   pub fn new(options: &ViewportOptions, buffer: BufferWk, actual_shape: &U16Rect) -> Self {
-    // By default the viewport start from the first line, i.e. starts from 0.
-    let (line_idx_range, lines) = sync::from_top_left(options, buffer.clone(), actual_shape, 0, 0);
+    Self::with_anchor(options, buffer, actual_shape, 0, 0)
+  }
+
+  /// Make new instance anchored at the top-left corner, i.e. `start_line` and `start_dcolumn`.
+  /// The cursor is at the first char of the top row.
+  pub fn with_anchor(
+    options: &ViewportOptions,
+    buffer: BufferWk,
+    actual_shape: &U16Rect,
+    start_line: usize,
+    start_dcolumn: usize,
+  ) -> Self {
+    let folds = Folds::new();
+    let (line_idx_range, lines) = sync::from_top_left(
+      options,
+      buffer.clone(),
+      &folds,
+      actual_shape,
+      start_line,
+      start_dcolumn,
+    );
     let cursor = if line_idx_range.is_empty() {
       assert!(lines.is_empty());
       CursorViewport::new(0..1, 0, 0, 0)
@@ -525,6 +549,7 @@ impl Viewport {
       end_line_idx: line_idx_range.end_line_idx(),
       lines,
       cursor,
+      folds,
     }
   }
 
@@ -639,11 +664,89 @@ impl Viewport {
     self.cursor = cursor;
   }
 
+  /// Get the rows count of the line inside the viewport, for the partially visible last line, it
+  /// only counts the visible rows.
+  ///
+  /// It returns `None` if the line is not inside the viewport.
+  pub fn rows_for_line(&self, line_idx: usize) -> Option<u16> {
+    self._internal_check();
+    self
+      .lines
+      .get(&line_idx)
+      .map(|line_viewport| line_viewport.rows().len() as u16)
+  }
+
+  /// Get the window row where the line starts.
+  ///
+  /// It returns `None` if the line is not inside the viewport.
+  pub fn first_row_of_line(&self, line_idx: usize) -> Option<u16> {
+    self._internal_check();
+    self
+      .lines
+      .get(&line_idx)
+      .and_then(|line_viewport| line_viewport.rows().first_key_value())
+      .map(|(row_idx, _)| *row_idx)
+  }
+
+  /// Get the window column where the chars of the row start, i.e. after the start fills of the
+  /// first row, or after the 'showbreak' of the continuation rows.
+  ///
+  /// It returns `None` if the row of the line is not inside the viewport.
+  pub fn row_start_column(&self, line_idx: usize, row_idx: u16) -> Option<u16> {
+    let line_viewport = self.lines.get(&line_idx)?;
+    let (first_row_idx, _) = line_viewport.rows().first_key_value()?;
+    if row_idx == *first_row_idx {
+      Some(line_viewport.start_filled_columns() as u16)
+    } else if line_viewport.rows().contains_key(&row_idx) {
+      Some(sync::show_break_width(
+        &self.options,
+        self.actual_shape.width(),
+      ))
+    } else {
+      None
+    }
+  }
+
+  /// Get the window rows of the cursor line, i.e. all the wrapped rows of it, for the line inside
+  /// a closed fold, it is the summary row of the fold.
+  ///
+  /// It returns `None` if the cursor line is not inside the viewport.
+  pub fn cursor_line_rows(&self) -> Option<Range<u16>> {
+    let line_idx = self.cursor().line_idx();
+    let start_row = self.first_row_of_line(line_idx)?;
+    let rows = self.rows_for_line(line_idx)?;
+    Some(start_row..start_row + rows)
+  }
+
+  /// Get the total rows count of all the lines inside the viewport.
+  pub fn total_rows(&self) -> u16 {
+    self._internal_check();
+    self
+      .lines
+      .values()
+      .map(|line_viewport| line_viewport.rows().len() as u16)
+      .sum()
+  }
+
+  /// Get the folds.
+  pub fn folds(&self) -> &Folds {
+    &self.folds
+  }
+
+  /// Get the mutable folds, the viewport needs to sync after they're changed.
+  pub fn folds_mut(&mut self) -> &mut Folds {
+    &mut self.folds
+  }
+
   /// Sync from top-left corner, i.e. `start_line` and `start_dcolumn`.
   pub fn sync_from_top_left(&mut self, start_line: usize, start_dcolumn: usize) {
+    if let Some(buffer) = self.buffer.upgrade() {
+      self.folds.update(&rlock!(buffer));
+    }
     let (line_idx_range, lines) = sync::from_top_left(
       &self.options,
       self.buffer.clone(),
+      &self.folds,
       &self.actual_shape,
       start_line,
       start_dcolumn,
@@ -654,6 +757,288 @@ impl Viewport {
   }
 }
 
+// Scroll {
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+/// The target position of the cursor line in the viewport, i.e. `zt`/`zz`/`zb`.
+pub enum CursorLinePosition {
+  Top,
+  Center,
+  Bottom,
+}
+
+/// The overlapped lines when scrolling by pages.
+/// See: <https://vimhelp.org/scroll.txt.html#CTRL-F>.
+const PAGE_OVERLAP_LINES: usize = 2;
+
+impl Viewport {
+  /// Get the cursor viewport of the char in the line, for the empty line, it is the first row of
+  /// the line. For the line inside a closed fold, it is the summary row of the fold.
+  ///
+  /// It returns `None` if the char is not inside the viewport.
+  pub fn cursor_viewport_at(&self, line_idx: usize, char_idx: usize) -> Option<CursorViewport> {
+    // The cursor inside a closed fold stays at the start of its summary row.
+    let (line_idx, char_idx) = match self.folds.closed_fold_at(line_idx) {
+      Some(fold) => (fold.start, 0),
+      None => (line_idx, char_idx),
+    };
+    let line_viewport = self.lines.get(&line_idx)?;
+    let found = line_viewport.rows().iter().find_map(|(row_idx, row)| {
+      row
+        .char2dcolumns()
+        .get(&char_idx)
+        .map(|(start_dcol, end_dcol)| {
+          CursorViewport::new(*start_dcol..*end_dcol, char_idx, *row_idx, line_idx)
+        })
+    });
+    match found {
+      Some(cursor) => Some(cursor),
+      None if char_idx == 0 => {
+        let (row_idx, _) = line_viewport.rows().first_key_value()?;
+        Some(CursorViewport::new(0..1, 0, *row_idx, line_idx))
+      }
+      None => None,
+    }
+  }
+
+  // The last line index of the buffer.
+  fn buffer_last_line_idx(&self) -> usize {
+    match self.buffer.upgrade() {
+      Some(buffer) => rlock!(buffer).last_line_idx(),
+      None => 0,
+    }
+  }
+
+  // The rows count of the line when it is displayed from the top of the viewport, i.e. at most the
+  // height of the viewport.
+  fn line_rows(&self, line_idx: usize) -> usize {
+    match self.buffer.upgrade() {
+      Some(buffer) => sync::line_rows(
+        &self.options,
+        &rlock!(buffer),
+        &self.folds,
+        &self.actual_shape,
+        line_idx,
+      ) as usize,
+      None => 1,
+    }
+  }
+
+  // The last line that is fully visible, the partially visible last line doesn't count unless it
+  // is the only line in the viewport.
+  fn last_visible_line_idx(&self) -> usize {
+    let last_line_idx = self.end_line_idx.saturating_sub(1);
+    if last_line_idx > self.start_line_idx
+      && (self.rows_for_line(last_line_idx).unwrap_or(0) as usize) < self.line_rows(last_line_idx)
+    {
+      last_line_idx - 1
+    } else {
+      last_line_idx
+    }
+  }
+
+  // Scroll the viewport to start from `start_line_idx`, and put the cursor on the first non-blank
+  // char of the `cursor_line_idx`, which is bounded inside the viewport and keeps the
+  // 'scrolloff' lines above and below it, except at the beginning and end of the buffer.
+  fn scroll_to(&mut self, start_line_idx: usize, cursor_line_idx: usize) {
+    self.sync_from_top_left(start_line_idx, 0);
+    if self.lines.is_empty() {
+      return;
+    }
+    let last_visible_line_idx = self.last_visible_line_idx();
+    let scroll_off = std::cmp::min(
+      self.options.scroll_off,
+      (last_visible_line_idx - self.start_line_idx) / 2,
+    );
+    let top_line_idx = if self.start_line_idx == 0 {
+      0
+    } else {
+      self.start_line_idx + scroll_off
+    };
+    let bottom_line_idx = if last_visible_line_idx >= self.buffer_last_line_idx() {
+      last_visible_line_idx
+    } else {
+      last_visible_line_idx - scroll_off
+    };
+    let cursor_line_idx = cursor_line_idx.clamp(top_line_idx, bottom_line_idx);
+    let char_idx = match self.buffer.upgrade() {
+      Some(buffer) => rlock!(buffer).first_non_blank_char(cursor_line_idx),
+      None => 0,
+    };
+    if let Some(cursor) = self.cursor_viewport_at(cursor_line_idx, char_idx) {
+      self.cursor = cursor;
+    }
+  }
+
+  /// Scroll down a page, i.e. `Ctrl-F`. The last 2 lines become the top lines, and the viewport
+  /// can scroll until the last line of buffer is the top line.
+  ///
+  /// The cursor is kept inside the viewport, on the first non-blank char of its line.
+  pub fn scroll_page_down(&mut self) {
+    let start_line_idx = (self.last_visible_line_idx() + 1)
+      .saturating_sub(PAGE_OVERLAP_LINES)
+      .max(self.start_line_idx + 1)
+      .min(self.buffer_last_line_idx());
+    self.scroll_to(start_line_idx, self.cursor.line_idx());
+  }
+
+  /// Scroll up a page, i.e. `Ctrl-B`. The top 2 lines become the bottom lines.
+  ///
+  /// The cursor is kept inside the viewport, on the first non-blank char of its line.
+  pub fn scroll_page_up(&mut self) {
+    if self.start_line_idx == 0 {
+      return;
+    }
+    let height = self.actual_shape.height() as usize;
+    let bottom_line_idx =
+      (self.start_line_idx + PAGE_OVERLAP_LINES - 1).min(self.buffer_last_line_idx());
+    let mut start_line_idx = bottom_line_idx + 1;
+    let mut rows = 0_usize;
+    while start_line_idx > 0 {
+      let line_rows = self.line_rows(start_line_idx - 1);
+      if rows + line_rows > height && start_line_idx <= bottom_line_idx {
+        break;
+      }
+      rows += line_rows;
+      start_line_idx -= 1;
+    }
+    let start_line_idx = start_line_idx.min(self.start_line_idx - 1);
+    self.scroll_to(start_line_idx, self.cursor.line_idx());
+  }
+
+  /// Scroll down by `rows` rows, i.e. `Ctrl-D`. Since the viewport starts from a whole line, a
+  /// wrapped line occupies multiple rows. The cursor moves down the same lines, or `rows` lines if
+  /// the viewport cannot scroll anymore.
+  pub fn scroll_down_by(&mut self, rows: usize) {
+    let last_line_idx = self.buffer_last_line_idx();
+    let mut start_line_idx = self.start_line_idx;
+    let mut scrolled_rows = 0_usize;
+    while scrolled_rows < rows && start_line_idx < last_line_idx {
+      scrolled_rows += self.line_rows(start_line_idx);
+      start_line_idx += 1;
+    }
+    let lines = match start_line_idx - self.start_line_idx {
+      0 => rows,
+      lines => lines,
+    };
+    let cursor_line_idx = (self.cursor.line_idx() + lines).min(last_line_idx);
+    self.scroll_to(start_line_idx, cursor_line_idx);
+  }
+
+  /// Scroll up by `rows` rows, i.e. `Ctrl-U`. Similar to [`scroll_down_by`](Viewport::scroll_down_by).
+  pub fn scroll_up_by(&mut self, rows: usize) {
+    let mut start_line_idx = self.start_line_idx;
+    let mut scrolled_rows = 0_usize;
+    while scrolled_rows < rows && start_line_idx > 0 {
+      start_line_idx -= 1;
+      scrolled_rows += self.line_rows(start_line_idx);
+    }
+    let lines = match self.start_line_idx - start_line_idx {
+      0 => rows,
+      lines => lines,
+    };
+    let cursor_line_idx = self.cursor.line_idx().saturating_sub(lines);
+    self.scroll_to(start_line_idx, cursor_line_idx);
+  }
+
+  // The rows to scroll by `Ctrl-D`/`Ctrl-U`, i.e. the 'scroll' option, or half of the viewport
+  // height if it is `0`.
+  fn scroll_rows(&self) -> usize {
+    match self.options.scroll {
+      0 => std::cmp::max(self.actual_shape.height() as usize / 2, 1),
+      rows => rows,
+    }
+  }
+
+  /// Scroll down half a page (or the 'scroll' option rows), see
+  /// [`scroll_down_by`](Viewport::scroll_down_by).
+  pub fn scroll_half_page_down(&mut self) {
+    self.scroll_down_by(self.scroll_rows());
+  }
+
+  /// Scroll up half a page (or the 'scroll' option rows), see
+  /// [`scroll_up_by`](Viewport::scroll_up_by).
+  pub fn scroll_half_page_up(&mut self) {
+    self.scroll_up_by(self.scroll_rows());
+  }
+
+  /// Get the start line of the viewport that puts the line at the top, center or bottom of the
+  /// viewport, keeping the 'scrolloff' lines above/below it.
+  ///
+  /// With 'wrap', the rows of the lines above (and below) it are counted. At the beginning of the
+  /// buffer, the viewport cannot scroll further and the line cannot reach the target position.
+  pub fn start_line_for(&self, line_idx: usize, position: CursorLinePosition) -> usize {
+    let height = self.actual_shape.height() as usize;
+    let scroll_off = self.options.scroll_off;
+
+    if position == CursorLinePosition::Top {
+      return line_idx.saturating_sub(scroll_off);
+    }
+
+    // The buffer is locked once for all the lines, and each line is placed only once.
+    let buffer = match self.buffer.upgrade() {
+      Some(buffer) => buffer,
+      None => return line_idx,
+    };
+    let buffer = rlock!(buffer);
+    let line_rows = |line_idx: usize| {
+      sync::line_rows(
+        &self.options,
+        &buffer,
+        &self.folds,
+        &self.actual_shape,
+        line_idx,
+      ) as usize
+    };
+
+    let target_rows = line_rows(line_idx);
+    let max_rows_above = match position {
+      CursorLinePosition::Center => height.saturating_sub(target_rows) / 2,
+      _ => {
+        let rows_below: usize = (line_idx + 1..=buffer.last_line_idx())
+          .take(scroll_off)
+          .map(line_rows)
+          .sum();
+        height.saturating_sub(target_rows + rows_below)
+      }
+    };
+    let mut start_line_idx = line_idx;
+    let mut rows_above = 0_usize;
+    while start_line_idx > 0 {
+      let rows = line_rows(start_line_idx - 1);
+      if rows_above + rows > max_rows_above {
+        break;
+      }
+      rows_above += rows;
+      start_line_idx -= 1;
+    }
+    start_line_idx
+  }
+
+  /// Scroll the viewport to put the line at the top, center or bottom of the viewport, see
+  /// [`start_line_for`](Viewport::start_line_for). The cursor stays at its position in the buffer
+  /// if it's still inside the viewport, otherwise it moves to the first char of the line.
+  pub fn scroll_line_to(&mut self, line_idx: usize, position: CursorLinePosition) {
+    let (cursor_line_idx, cursor_char_idx) = (self.cursor.line_idx(), self.cursor.char_idx());
+    let start_line_idx = self.start_line_for(line_idx, position);
+    self.sync_from_top_left(start_line_idx, 0);
+    let cursor = self
+      .cursor_viewport_at(cursor_line_idx, cursor_char_idx)
+      .or_else(|| self.cursor_viewport_at(line_idx, 0));
+    if let Some(cursor) = cursor {
+      self.cursor = cursor;
+    }
+  }
+
+  /// Scroll the viewport to put the cursor line at the top (`zt`), center (`zz`) or bottom (`zb`)
+  /// of the viewport, see [`scroll_line_to`](Viewport::scroll_line_to). The cursor position in
+  /// the buffer doesn't change.
+  pub fn scroll_cursor_line_to(&mut self, position: CursorLinePosition) {
+    self.scroll_line_to(self.cursor.line_idx(), position);
+  }
+}
+// Scroll }
+
 //#[derive(Debug, Clone, Copy)]
 // /// The vertical offset for viewport/cursor move up/down.
 //pub enum ViewportVerticalOffset {
@@ -920,7 +1305,7 @@ impl Viewport {
 mod tests {
   use super::*;
 
-  use crate::buf::BufferArc;
+  use crate::buf::{AmbiguousWidth, BufferArc};
   use crate::cart::{IRect, U16Size};
   use crate::envar;
   use crate::rlock;
@@ -929,7 +1314,9 @@ mod tests {
   use crate::test::log::init as test_log_init;
   use crate::ui::tree::internal::Inodeable;
   use crate::ui::tree::Tree;
+  use crate::ui::widget::window::viewport::layout::layout_char_count;
   use crate::ui::widget::window::{Window, WindowLocalOptions};
+  use crate::wlock;
 
   use compact_str::ToCompactString;
   use ropey::{Rope, RopeBuilder};
@@ -994,6 +1381,20 @@ mod tests {
       expect_end_fills.len()
     );
 
+    // The rows are continuous from the first row of window.
+    assert!(actual.total_rows() as usize <= expect.len());
+    let mut expect_first_row = 0_u16;
+    for line_idx in actual.start_line_idx()..actual.end_line_idx() {
+      let rows = actual.rows_for_line(line_idx).unwrap();
+      if rows > 0 {
+        assert_eq!(actual.first_row_of_line(line_idx), Some(expect_first_row));
+      }
+      expect_first_row += rows;
+    }
+    assert_eq!(actual.total_rows(), expect_first_row);
+    assert_eq!(actual.rows_for_line(actual.end_line_idx()), None);
+    assert_eq!(actual.first_row_of_line(actual.end_line_idx()), None);
+
     let buffer = buffer.read();
     let buflines = buffer.get_lines_at(actual.start_line_idx()).unwrap();
     let total_lines = expect_end_line - expect_start_line;
@@ -1104,29 +1505,20 @@ mod tests {
       "  2. When ",
       "     * The",
       "     * The",
-      "",
     ];
     let size = U16Size::new(10, 10);
     let options = WindowLocalOptions::builder().wrap(false).build();
     let actual = make_viewport_from_size(size, buffer.clone(), &options);
-    let expect_fills: BTreeMap<usize, usize> = vec![
-      (0, 0),
-      (1, 0),
-      (2, 0),
-      (3, 0),
-      (4, 0),
-      (5, 0),
-      (6, 0),
-      (7, 0),
-    ]
-    .into_iter()
-    .collect();
+    let expect_fills: BTreeMap<usize, usize> =
+      vec![(0, 0), (1, 0), (2, 0), (3, 0), (4, 0), (5, 0), (6, 0)]
+        .into_iter()
+        .collect();
     do_test_sync_from_top_left(
       buffer.clone(),
       &actual,
       &expect,
       0,
-      8,
+      7,
       &expect_fills,
       &expect_fills,
     );
@@ -1153,29 +1545,20 @@ mod tests {
       "  2. When the line is too l",
       "     * The extra parts are ",
       "     * The extra parts are ",
-      "",
     ];
     let size = U16Size::new(27, 15);
     let options = WindowLocalOptions::builder().wrap(false).build();
     let actual = make_viewport_from_size(size, buffer.clone(), &options);
-    let expect_fills: BTreeMap<usize, usize> = vec![
-      (0, 0),
-      (1, 0),
-      (2, 0),
-      (3, 0),
-      (4, 0),
-      (5, 0),
-      (6, 0),
-      (7, 0),
-    ]
-    .into_iter()
-    .collect();
+    let expect_fills: BTreeMap<usize, usize> =
+      vec![(0, 0), (1, 0), (2, 0), (3, 0), (4, 0), (5, 0), (6, 0)]
+        .into_iter()
+        .collect();
     do_test_sync_from_top_left(
       buffer.clone(),
       &actual,
       &expect,
       0,
-      8,
+      7,
       &expect_fills,
       &expect_fills,
     );
@@ -1266,7 +1649,6 @@ mod tests {
       "\t2.",
       "\t", // 2 fills for '\t'
       "\t", // 2 fills for '\t'
-      "",
     ];
 
     let size = U16Size::new(10, 10);
@@ -1282,7 +1664,6 @@ mod tests {
       (6, 0),
       (7, 0),
       (8, 0),
-      (9, 0),
     ]
     .into_iter()
     .collect();
@@ -1296,7 +1677,6 @@ mod tests {
       (6, 0),
       (7, 2),
       (8, 2),
-      (9, 0),
     ]
     .into_iter()
     .collect();
@@ -1305,7 +1685,7 @@ mod tests {
       &actual,
       &expect,
       0,
-      10,
+      9,
       &expect_start_fills,
       &expect_end_fills,
     );
@@ -1391,6 +1771,210 @@ mod tests {
     do_test_sync_from_top_left(buffer, &actual, &expect, 0, 3, &expect_fills, &expect_fills);
   }
 
+  #[test]
+  fn sync_from_top_left_wrap_nolinebreak_show_break1() {
+    test_log_init();
+
+    let buffer = make_buffer_from_lines(vec![
+      "Hello, RSVIM!\n",
+      "This is a quite simple and small test lines.\n",
+      "But still it contains several things we want to test:\n",
+      "  1. When the line is small enough to completely put inside a row of the window content widget, then the line-wrap and word-wrap doesn't affect the rendering.\n",
+      "  2. When the line is too long to be completely put in a row of the window content widget, there're multiple cases:\n",
+      "     * The extra parts are been truncated if both line-wrap and word-wrap options are not set.\n",
+      "     * The extra parts are split into the next row, if either line-wrap or word-wrap options are been set. If the extra parts are still too long to put in the next row, repeat this operation again and again. This operation also eats more rows in the window, thus it may contains less lines in the buffer.\n",
+    ]);
+    // The continuation rows are narrowed by the 'showbreak' width.
+    let expect = vec![
+      "Hello, RSV",
+      "IM!\n",
+      "This is a ",
+      "quite si",
+      "mple and",
+      " small t",
+      "est line",
+      "s.\n",
+      "But still ",
+      "it conta",
+    ];
+
+    let size = U16Size::new(10, 10);
+    let options = WindowLocalOptions::builder()
+      .wrap(true)
+      .line_break(false)
+      .show_break("> ")
+      .build();
+    let actual = make_viewport_from_size(size, buffer.clone(), &options);
+    let start_char_idxs = actual
+      .lines()
+      .get(&1)
+      .unwrap()
+      .rows()
+      .values()
+      .map(|r| r.start_char_idx())
+      .collect::<Vec<_>>();
+    assert_eq!(start_char_idxs, vec![0, 10, 18, 26, 34, 42]);
+    let expect_fills: BTreeMap<usize, usize> = vec![(0, 0), (1, 0), (2, 0)].into_iter().collect();
+    do_test_sync_from_top_left(buffer, &actual, &expect, 0, 3, &expect_fills, &expect_fills);
+  }
+
+  #[test]
+  fn sync_from_top_left_wrap_nolinebreak_ambiguous_width1() {
+    test_log_init();
+
+    let buffer = make_buffer_from_lines(vec!["§λ─§λ─\n", "abc\n"]);
+    let size = U16Size::new(4, 5);
+    let options = WindowLocalOptions::builder()
+      .wrap(true)
+      .line_break(false)
+      .build();
+    let start_char_idxs = |viewport: &Viewport| {
+      viewport
+        .lines()
+        .get(&0)
+        .unwrap()
+        .rows()
+        .values()
+        .map(|r| r.start_char_idx())
+        .collect::<Vec<_>>()
+    };
+
+    // The ambiguous width characters occupy 1 cell by default.
+    let actual = make_viewport_from_size(size, buffer.clone(), &options);
+    assert_eq!(start_char_idxs(&actual), vec![0, 4]);
+    let expect = vec!["§λ─§", "λ─\n", "abc\n"];
+    let expect_fills: BTreeMap<usize, usize> = vec![(0, 0), (1, 0)].into_iter().collect();
+    do_test_sync_from_top_left(
+      buffer.clone(),
+      &actual,
+      &expect,
+      0,
+      2,
+      &expect_fills,
+      &expect_fills,
+    );
+
+    // A viewport built after toggling the 'ambiwidth' option has the new row boundaries.
+    wlock!(buffer).set_ambiguous_width(AmbiguousWidth::Double);
+    let actual = make_viewport_from_size(size, buffer.clone(), &options);
+    assert_eq!(start_char_idxs(&actual), vec![0, 2, 4]);
+    let expect = vec!["§λ", "─§", "λ─\n", "abc\n"];
+    do_test_sync_from_top_left(buffer, &actual, &expect, 0, 2, &expect_fills, &expect_fills);
+  }
+
+  #[test]
+  fn sync_from_top_left_eol1() {
+    test_log_init();
+
+    let size = U16Size::new(10, 5);
+    let options = WindowLocalOptions::builder().build();
+    let expect_fills: BTreeMap<usize, usize> = vec![(0, 0), (1, 0)].into_iter().collect();
+    let line_keys = |viewport: &Viewport| viewport.lines().keys().copied().collect::<Vec<_>>();
+
+    // The empty line after the trailing line break is not in the viewport.
+    let buffer = make_buffer_from_lines(vec!["abc\n", "def\n"]);
+    assert_eq!(rlock!(buffer).len_lines(), 3);
+    let actual = make_viewport_from_size(size, buffer.clone(), &options);
+    assert_eq!(actual.end_line_idx(), 2);
+    assert_eq!(line_keys(&actual), vec![0, 1]);
+    assert_eq!(actual.total_rows(), 2);
+    let expect = vec!["abc\n", "def\n"];
+    do_test_sync_from_top_left(buffer, &actual, &expect, 0, 2, &expect_fills, &expect_fills);
+
+    // Without the trailing line break.
+    let buffer = make_buffer_from_lines(vec!["abc\n", "def"]);
+    assert_eq!(rlock!(buffer).len_lines(), 2);
+    let actual = make_viewport_from_size(size, buffer.clone(), &options);
+    assert_eq!(actual.end_line_idx(), 2);
+    assert_eq!(line_keys(&actual), vec![0, 1]);
+    assert_eq!(actual.total_rows(), 2);
+    let expect = vec!["abc\n", "def"];
+    do_test_sync_from_top_left(buffer, &actual, &expect, 0, 2, &expect_fills, &expect_fills);
+
+    // An empty last line is a line.
+    let buffer = make_buffer_from_lines(vec!["abc\n", "def\n", "\n"]);
+    let actual = make_viewport_from_size(size, buffer.clone(), &options);
+    assert_eq!(actual.end_line_idx(), 3);
+    assert_eq!(line_keys(&actual), vec![0, 1, 2]);
+    assert_eq!(actual.total_rows(), 3);
+  }
+
+  #[test]
+  fn sync_from_top_left_clamp1() {
+    test_log_init();
+
+    let buffer = make_buffer_from_lines(vec![
+      "line 0\n",
+      "line 1\n",
+      "line 2\n",
+      "line 3\n",
+      "line 4\n",
+      "line 5\n",
+      "line 6\n",
+      "line 7\n",
+      "aaa bbb ccc ddd eee\n",
+      "last\n",
+    ]);
+    let size = U16Size::new(10, 5);
+    let cases = [
+      (
+        WindowLocalOptions::builder().wrap(false).build(),
+        vec!["line 5\n", "line 6\n", "line 7\n", "aaa bbb cc", "last\n"],
+        5,
+      ),
+      (
+        WindowLocalOptions::builder()
+          .wrap(true)
+          .line_break(false)
+          .build(),
+        vec![
+          "line 6\n",
+          "line 7\n",
+          "aaa bbb cc",
+          "c ddd eee\n",
+          "last\n",
+        ],
+        6,
+      ),
+      (
+        WindowLocalOptions::builder()
+          .wrap(true)
+          .line_break(true)
+          .build(),
+        vec!["line 7\n", "aaa bbb ", "ccc ddd ", "eee\n", "last\n"],
+        7,
+      ),
+    ];
+
+    // The `start_line` is outside of the buffer, the tail of the buffer fills the window.
+    for (options, expect, expect_start_line) in cases {
+      let mut actual = make_viewport_from_size(size, buffer.clone(), &options);
+      actual.sync_from_top_left(100, 0);
+      let expect_fills: BTreeMap<usize, usize> = (expect_start_line..10)
+        .map(|line_idx| (line_idx, 0))
+        .collect();
+      do_test_sync_from_top_left(
+        buffer.clone(),
+        &actual,
+        &expect,
+        expect_start_line,
+        10,
+        &expect_fills,
+        &expect_fills,
+      );
+      assert_eq!(actual.total_rows(), 5);
+    }
+
+    // An empty buffer still shows the empty line.
+    let buffer = make_buffer_from_lines(vec![]);
+    let options = WindowLocalOptions::builder().build();
+    let mut actual = make_viewport_from_size(size, buffer, &options);
+    actual.sync_from_top_left(100, 0);
+    assert_eq!(actual.start_line_idx(), 0);
+    assert_eq!(actual.end_line_idx(), 1);
+    assert_eq!(actual.total_rows(), 1);
+  }
+
   #[test]
   fn sync_from_top_left_wrap_nolinebreak2() {
     let buffer = make_buffer_from_lines(vec![
@@ -1418,7 +2002,6 @@ mod tests {
       "ong to be completely put in",
       " a row of the window conten",
       "t widget, there're multiple",
-      "",
     ];
 
     let size = U16Size::new(27, 15);
@@ -1661,6 +2244,161 @@ mod tests {
     );
   }
 
+  // The grapheme clusters are never split into two rows.
+  #[test]
+  fn sync_from_top_left_wrap_nolinebreak10() {
+    test_log_init();
+
+    let buffer = make_buffer_from_lines(vec![
+      "abcde\u{0301}fg\n",
+      "abc\u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467}d\n",
+    ]);
+    let expect = vec![
+      "abcde\u{0301}f",
+      "g\n",
+      "abc",
+      "\u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467}",
+      "d\n",
+    ];
+
+    let size = U16Size::new(6, 5);
+    let options = WindowLocalOptions::builder()
+      .wrap(true)
+      .line_break(false)
+      .build();
+    let actual = make_viewport_from_size(size, buffer.clone(), &options);
+    let expect_start_fills: BTreeMap<usize, usize> = vec![(0, 0), (1, 0)].into_iter().collect();
+    let expect_end_fills: BTreeMap<usize, usize> = vec![(0, 0), (1, 0)].into_iter().collect();
+    do_test_sync_from_top_left(
+      buffer,
+      &actual,
+      &expect,
+      0,
+      2,
+      &expect_start_fills,
+      &expect_end_fills,
+    );
+  }
+
+  #[test]
+  fn sync_from_top_left_wrap_nolinebreak11() {
+    test_log_init();
+
+    let buffer = make_buffer_from_lines(vec!["abc\u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467}d\n"]);
+    let expect = vec!["abc", "\u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467}"];
+
+    let size = U16Size::new(6, 2);
+    let options = WindowLocalOptions::builder()
+      .wrap(true)
+      .line_break(false)
+      .build();
+    let actual = make_viewport_from_size(size, buffer.clone(), &options);
+    let expect_start_fills: BTreeMap<usize, usize> = vec![(0, 0)].into_iter().collect();
+    let expect_end_fills: BTreeMap<usize, usize> = vec![(0, 0)].into_iter().collect();
+    do_test_sync_from_top_left(
+      buffer,
+      &actual,
+      &expect,
+      0,
+      1,
+      &expect_start_fills,
+      &expect_end_fills,
+    );
+  }
+
+  #[test]
+  fn sync_from_top_left_wrap_linebreak12() {
+    test_log_init();
+
+    let buffer = make_buffer_from_lines(vec![
+      "abcde\u{0301}fg\n",
+      "abc\u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467}d\n",
+    ]);
+    let expect = vec![
+      "abcde\u{0301}f",
+      "g\n",
+      "abc",
+      "\u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467}",
+      "d\n",
+    ];
+
+    let size = U16Size::new(6, 5);
+    let options = WindowLocalOptions::builder()
+      .wrap(true)
+      .line_break(true)
+      .build();
+    let actual = make_viewport_from_size(size, buffer.clone(), &options);
+    let expect_start_fills: BTreeMap<usize, usize> = vec![(0, 0), (1, 0)].into_iter().collect();
+    let expect_end_fills: BTreeMap<usize, usize> = vec![(0, 0), (1, 0)].into_iter().collect();
+    do_test_sync_from_top_left(
+      buffer,
+      &actual,
+      &expect,
+      0,
+      2,
+      &expect_start_fills,
+      &expect_end_fills,
+    );
+  }
+
+  #[test]
+  fn rows_for_line1() {
+    test_log_init();
+
+    let buffer = make_buffer_from_lines(vec![
+      "Hello, RSVIM!\n",
+      "This is a quite simple and small test lines.\n",
+      "But still it contains several things we want to test:\n",
+      "  1. When the line is small enough to completely put inside a row of the window content widget, then the line-wrap and word-wrap doesn't affect the rendering.\n",
+      "  2. When the line is too long to be completely put in a row of the window content widget, there're multiple cases:\n",
+    ]);
+
+    // Same with `sync_from_top_left_wrap_nolinebreak2`.
+    let size = U16Size::new(27, 15);
+    let options = WindowLocalOptions::builder()
+      .wrap(true)
+      .line_break(false)
+      .build();
+    let actual = make_viewport_from_size(size, buffer, &options);
+    let expect_rows = [(0, 1), (1, 2), (2, 2), (3, 6), (4, 4)];
+    let expect_first_rows = [(0, 0), (1, 1), (2, 3), (3, 5), (4, 11)];
+    for (line_idx, rows) in expect_rows {
+      assert_eq!(actual.rows_for_line(line_idx), Some(rows));
+    }
+    for (line_idx, row) in expect_first_rows {
+      assert_eq!(actual.first_row_of_line(line_idx), Some(row));
+    }
+    // The last line is partially visible.
+    assert_eq!(actual.total_rows(), 15);
+    assert_eq!(actual.rows_for_line(5), None);
+    assert_eq!(actual.first_row_of_line(5), None);
+  }
+
+  #[test]
+  fn rows_for_line2() {
+    test_log_init();
+
+    let buffer = make_buffer_from_lines(vec![
+      "Hello, RSVIM!\n",
+      "This is a quite simple and small test lines.\n",
+      "But still it contains several things we want to test:\n",
+    ]);
+
+    // Same with `sync_from_top_left_wrap_linebreak1`.
+    let size = U16Size::new(10, 10);
+    let options = WindowLocalOptions::builder()
+      .wrap(true)
+      .line_break(true)
+      .build();
+    let actual = make_viewport_from_size(size, buffer, &options);
+    assert_eq!(actual.rows_for_line(0), Some(2));
+    assert_eq!(actual.rows_for_line(1), Some(6));
+    assert_eq!(actual.rows_for_line(2), Some(2));
+    assert_eq!(actual.first_row_of_line(1), Some(2));
+    assert_eq!(actual.first_row_of_line(2), Some(8));
+    assert_eq!(actual.total_rows(), 10);
+  }
+
   #[test]
   fn sync_from_top_left_wrap_linebreak1() {
     test_log_init();
@@ -1708,6 +2446,53 @@ mod tests {
     );
   }
 
+  #[test]
+  fn sync_from_top_left_wrap_linebreak_show_break1() {
+    test_log_init();
+
+    let buffer = make_buffer_from_lines(vec![
+      "Hello, RSVIM!\n",
+      "This is a quite simple and small test lines.\n",
+      "But still it contains several things we want to test:\n",
+      "  1. When the line is small enough to completely put inside a row of the window content widget, then the line-wrap and word-wrap doesn't affect the rendering.\n",
+      "  2. When the line is too long to be completely put in a row of the window content widget, there're multiple cases:\n",
+      "     * The extra parts are been truncated if both line-wrap and word-wrap options are not set.\n",
+      "     * The extra parts are split into the next row, if either line-wrap or word-wrap options are been set. If the extra parts are still too long to put in the next row, repeat this operation again and again. This operation also eats more rows in the window, thus it may contains less lines in the buffer.\n",
+    ]);
+    // The continuation rows are narrowed by the 'showbreak' width.
+    let expect = vec![
+      "Hello, ",
+      "RSVIM!\n",
+      "This is a ",
+      "quite ",
+      "simple ",
+      "and ",
+      "small ",
+      "test ",
+      "lines.\n",
+      "But still ",
+    ];
+
+    let size = U16Size::new(10, 10);
+    let options = WindowLocalOptions::builder()
+      .wrap(true)
+      .line_break(true)
+      .show_break("> ")
+      .build();
+    let actual = make_viewport_from_size(size, buffer.clone(), &options);
+    let start_char_idxs = actual
+      .lines()
+      .get(&1)
+      .unwrap()
+      .rows()
+      .values()
+      .map(|r| r.start_char_idx())
+      .collect::<Vec<_>>();
+    assert_eq!(start_char_idxs, vec![0, 10, 16, 23, 27, 33, 38]);
+    let expect_fills: BTreeMap<usize, usize> = vec![(0, 0), (1, 0), (2, 0)].into_iter().collect();
+    do_test_sync_from_top_left(buffer, &actual, &expect, 0, 3, &expect_fills, &expect_fills);
+  }
+
   #[test]
   fn sync_from_top_left_wrap_linebreak2() {
     test_log_init();
@@ -1785,7 +2570,6 @@ mod tests {
       "widget, then the line-wrap and ",
       "word-wrap doesn't affect the ",
       "rendering.\n",
-      "",
     ];
 
     let size = U16Size::new(31, 11);
@@ -2177,4 +2961,469 @@ mod tests {
       &expect_end_fills,
     );
   }
+
+  fn make_scroll_lines(n: usize) -> Vec<String> {
+    (0..n)
+      .map(|i| {
+        if i + 1 < n {
+          format!("  line {}\n", i)
+        } else {
+          format!("  line {}", i)
+        }
+      })
+      .collect()
+  }
+
+  #[test]
+  fn scroll_page1() {
+    test_log_init();
+
+    let lines = make_scroll_lines(50);
+    let buffer = make_buffer_from_lines(lines.iter().map(|l| l.as_str()).collect());
+    let size = U16Size::new(10, 10);
+    let options = WindowLocalOptions::builder().wrap(false).build();
+    let mut actual = make_viewport_from_size(size, buffer, &options);
+    assert_eq!(actual.start_line_idx(), 0);
+
+    // Ctrl-F
+    for expect_start_line in [8, 16, 24, 32, 40] {
+      actual.scroll_page_down();
+      assert_eq!(actual.start_line_idx(), expect_start_line);
+      assert_eq!(actual.end_line_idx(), expect_start_line + 10);
+      assert_eq!(actual.cursor().line_idx(), expect_start_line);
+      assert_eq!(actual.cursor().char_idx(), 2);
+    }
+
+    // Ctrl-B, the cursor stays if it is still inside the viewport.
+    for (expect_start_line, expect_cursor_line) in [(32, 40), (24, 33), (16, 25), (8, 17), (0, 9)] {
+      actual.scroll_page_up();
+      assert_eq!(actual.start_line_idx(), expect_start_line);
+      assert_eq!(actual.cursor().line_idx(), expect_cursor_line);
+      assert_eq!(actual.cursor().char_idx(), 2);
+    }
+    actual.scroll_page_up();
+    assert_eq!(actual.start_line_idx(), 0);
+  }
+
+  #[test]
+  fn scroll_page2() {
+    test_log_init();
+
+    let lines = make_scroll_lines(50);
+    let buffer = make_buffer_from_lines(lines.iter().map(|l| l.as_str()).collect());
+    let size = U16Size::new(10, 10);
+    let options = WindowLocalOptions::builder().wrap(false).build();
+    let mut actual = make_viewport_from_size(size, buffer, &options);
+
+    // The final page clamps until the last line is the top line.
+    for expect_start_line in [8, 16, 24, 32, 40, 48, 49, 49] {
+      actual.scroll_page_down();
+      assert_eq!(actual.start_line_idx(), expect_start_line);
+    }
+    assert_eq!(actual.end_line_idx(), 50);
+    assert_eq!(actual.cursor().line_idx(), 49);
+
+    actual.scroll_page_up();
+    assert_eq!(actual.start_line_idx(), 40);
+    assert_eq!(actual.cursor().line_idx(), 49);
+  }
+
+  #[test]
+  fn scroll_page3() {
+    test_log_init();
+
+    // The buffer is shorter than a page.
+    let lines = make_scroll_lines(5);
+    let buffer = make_buffer_from_lines(lines.iter().map(|l| l.as_str()).collect());
+    let size = U16Size::new(10, 10);
+    let options = WindowLocalOptions::builder().wrap(false).build();
+    let mut actual = make_viewport_from_size(size, buffer, &options);
+
+    actual.scroll_page_down();
+    assert_eq!(actual.start_line_idx(), 3);
+    assert_eq!(actual.end_line_idx(), 5);
+    assert_eq!(actual.cursor().line_idx(), 3);
+    actual.scroll_page_down();
+    assert_eq!(actual.start_line_idx(), 4);
+    actual.scroll_page_up();
+    assert_eq!(actual.start_line_idx(), 0);
+    assert_eq!(actual.cursor().line_idx(), 4);
+  }
+
+  #[test]
+  fn scroll_half_page1() {
+    test_log_init();
+
+    let lines = make_scroll_lines(50);
+    let buffer = make_buffer_from_lines(lines.iter().map(|l| l.as_str()).collect());
+    let size = U16Size::new(10, 10);
+    let options = WindowLocalOptions::builder().wrap(false).build();
+    let mut actual = make_viewport_from_size(size, buffer, &options);
+
+    // Ctrl-D
+    for expect_start_line in [5, 10, 15] {
+      actual.scroll_half_page_down();
+      assert_eq!(actual.start_line_idx(), expect_start_line);
+      assert_eq!(actual.cursor().line_idx(), expect_start_line);
+      assert_eq!(actual.cursor().char_idx(), 2);
+    }
+
+    // Ctrl-U
+    for expect_start_line in [10, 5, 0] {
+      actual.scroll_half_page_up();
+      assert_eq!(actual.start_line_idx(), expect_start_line);
+      assert_eq!(actual.cursor().line_idx(), expect_start_line);
+    }
+
+    // The viewport cannot scroll anymore, only the cursor moves.
+    actual.scroll_half_page_down();
+    actual.scroll_half_page_up();
+    actual.scroll_half_page_up();
+    assert_eq!(actual.start_line_idx(), 0);
+    assert_eq!(actual.cursor().line_idx(), 0);
+  }
+
+  #[test]
+  fn scroll_half_page2() {
+    test_log_init();
+
+    // The 2nd line occupies 3 rows.
+    let buffer = make_buffer_from_lines(vec![
+      "  line 0\n",
+      "  a very very long line 1\n",
+      "  line 2\n",
+      "  line 3\n",
+      "  line 4\n",
+      "  line 5\n",
+      "  line 6\n",
+      "  line 7\n",
+      "  line 8\n",
+      "  line 9\n",
+      "  line 10\n",
+      "  line 11",
+    ]);
+    let size = U16Size::new(10, 10);
+    let options = WindowLocalOptions::builder()
+      .wrap(true)
+      .line_break(false)
+      .build();
+    let mut actual = make_viewport_from_size(size, buffer, &options);
+    assert_eq!(actual.rows_for_line(1), Some(3));
+
+    actual.scroll_half_page_down();
+    assert_eq!(actual.start_line_idx(), 3);
+    assert_eq!(actual.cursor().line_idx(), 3);
+
+    actual.scroll_half_page_up();
+    assert_eq!(actual.start_line_idx(), 0);
+    assert_eq!(actual.cursor().line_idx(), 0);
+  }
+
+  #[test]
+  fn scroll_option1() {
+    test_log_init();
+
+    let lines = make_scroll_lines(100);
+    let buffer = make_buffer_from_lines(lines.iter().map(|l| l.as_str()).collect());
+    let size = U16Size::new(20, 20);
+    let options = WindowLocalOptions::builder().wrap(false).build();
+    let mut actual = make_viewport_from_size(size, buffer, &options);
+
+    // Ctrl-D scrolls half of the window height by default.
+    for i in 1..10 {
+      actual.scroll_half_page_down();
+      assert_eq!(actual.start_line_idx(), i * 10);
+      assert_eq!(actual.cursor().line_idx(), i * 10);
+    }
+
+    // Clamps at the end of the buffer.
+    actual.scroll_half_page_down();
+    assert_eq!(actual.start_line_idx(), 99);
+    assert_eq!(actual.cursor().line_idx(), 99);
+    actual.scroll_half_page_down();
+    assert_eq!(actual.start_line_idx(), 99);
+    assert_eq!(actual.cursor().line_idx(), 99);
+  }
+
+  #[test]
+  fn scroll_option2() {
+    test_log_init();
+
+    let lines = make_scroll_lines(100);
+    let buffer = make_buffer_from_lines(lines.iter().map(|l| l.as_str()).collect());
+    let size = U16Size::new(20, 20);
+    let options = WindowLocalOptions::builder()
+      .wrap(false)
+      .scroll(5)
+      .scroll_off(3)
+      .build();
+    let mut actual = make_viewport_from_size(size, buffer, &options);
+
+    // Ctrl-D
+    actual.scroll_half_page_down();
+    assert_eq!(actual.start_line_idx(), 5);
+    assert_eq!(actual.cursor().line_idx(), 8);
+    assert_eq!(actual.cursor().char_idx(), 2);
+    actual.scroll_half_page_down();
+    assert_eq!(actual.start_line_idx(), 10);
+    assert_eq!(actual.cursor().line_idx(), 13);
+
+    // Ctrl-F
+    actual.scroll_page_down();
+    assert_eq!(actual.start_line_idx(), 28);
+    assert_eq!(actual.cursor().line_idx(), 31);
+
+    // Ctrl-B
+    actual.scroll_page_up();
+    assert_eq!(actual.start_line_idx(), 10);
+    assert_eq!(actual.cursor().line_idx(), 26);
+
+    // Ctrl-U
+    actual.scroll_half_page_up();
+    assert_eq!(actual.start_line_idx(), 5);
+    assert_eq!(actual.cursor().line_idx(), 21);
+    actual.scroll_half_page_up();
+    actual.scroll_half_page_up();
+    assert_eq!(actual.start_line_idx(), 0);
+    assert_eq!(actual.cursor().line_idx(), 11);
+  }
+
+  #[test]
+  fn scroll_option3() {
+    test_log_init();
+
+    // Each line occupies 2 rows.
+    let lines: Vec<String> = (0..100)
+      .map(|i| format!("  wrapped line {}\n", i))
+      .collect();
+    let buffer = make_buffer_from_lines(lines.iter().map(|l| l.as_str()).collect());
+    let size = U16Size::new(10, 20);
+    let options = WindowLocalOptions::builder()
+      .wrap(true)
+      .line_break(false)
+      .build();
+    let mut actual = make_viewport_from_size(size, buffer, &options);
+    assert_eq!(actual.end_line_idx(), 10);
+
+    // Ctrl-D scrolls 10 rows, i.e. 5 lines.
+    actual.scroll_half_page_down();
+    assert_eq!(actual.start_line_idx(), 5);
+    assert_eq!(actual.end_line_idx(), 15);
+    assert_eq!(actual.cursor().line_idx(), 5);
+
+    // Ctrl-F
+    actual.scroll_page_down();
+    assert_eq!(actual.start_line_idx(), 13);
+    assert_eq!(actual.cursor().line_idx(), 13);
+
+    // Ctrl-U
+    actual.scroll_half_page_up();
+    assert_eq!(actual.start_line_idx(), 8);
+    assert_eq!(actual.cursor().line_idx(), 8);
+  }
+
+  fn move_cursor_to(actual: &mut Viewport, start_line_idx: usize, line_idx: usize) {
+    actual.sync_from_top_left(start_line_idx, 0);
+    let cursor = actual.cursor_viewport_at(line_idx, 2).unwrap();
+    actual.set_cursor(cursor);
+  }
+
+  #[test]
+  fn scroll_cursor_line1() {
+    test_log_init();
+
+    let lines = make_scroll_lines(100);
+    let buffer = make_buffer_from_lines(lines.iter().map(|l| l.as_str()).collect());
+    let size = U16Size::new(20, 20);
+    let options = WindowLocalOptions::builder().wrap(false).build();
+    let mut actual = make_viewport_from_size(size, buffer, &options);
+
+    // zz
+    move_cursor_to(&mut actual, 40, 50);
+    actual.scroll_cursor_line_to(CursorLinePosition::Center);
+    assert_eq!(actual.start_line_idx(), 41);
+    assert_eq!(actual.cursor().line_idx(), 50);
+    assert_eq!(actual.cursor().char_idx(), 2);
+    assert_eq!(actual.cursor().row_idx(), 9);
+
+    // zt
+    actual.scroll_cursor_line_to(CursorLinePosition::Top);
+    assert_eq!(actual.start_line_idx(), 50);
+    assert_eq!(actual.cursor().line_idx(), 50);
+    assert_eq!(actual.cursor().row_idx(), 0);
+
+    // zb
+    actual.scroll_cursor_line_to(CursorLinePosition::Bottom);
+    assert_eq!(actual.start_line_idx(), 31);
+    assert_eq!(actual.cursor().line_idx(), 50);
+    assert_eq!(actual.cursor().row_idx(), 19);
+
+    // Clamps at the beginning of the buffer.
+    move_cursor_to(&mut actual, 0, 3);
+    actual.scroll_cursor_line_to(CursorLinePosition::Center);
+    assert_eq!(actual.start_line_idx(), 0);
+    assert_eq!(actual.cursor().line_idx(), 3);
+    actual.scroll_cursor_line_to(CursorLinePosition::Bottom);
+    assert_eq!(actual.start_line_idx(), 0);
+    assert_eq!(actual.cursor().line_idx(), 3);
+  }
+
+  #[test]
+  fn scroll_cursor_line2() {
+    test_log_init();
+
+    let lines = make_scroll_lines(100);
+    let buffer = make_buffer_from_lines(lines.iter().map(|l| l.as_str()).collect());
+    let size = U16Size::new(20, 20);
+    let options = WindowLocalOptions::builder()
+      .wrap(false)
+      .scroll_off(3)
+      .build();
+    let mut actual = make_viewport_from_size(size, buffer, &options);
+    move_cursor_to(&mut actual, 40, 50);
+
+    // zt
+    actual.scroll_cursor_line_to(CursorLinePosition::Top);
+    assert_eq!(actual.start_line_idx(), 47);
+    assert_eq!(actual.cursor().line_idx(), 50);
+    assert_eq!(actual.cursor().row_idx(), 3);
+
+    // zb
+    actual.scroll_cursor_line_to(CursorLinePosition::Bottom);
+    assert_eq!(actual.start_line_idx(), 34);
+    assert_eq!(actual.cursor().line_idx(), 50);
+    assert_eq!(actual.cursor().row_idx(), 16);
+  }
+
+  #[test]
+  fn scroll_cursor_line3() {
+    test_log_init();
+
+    // Each line occupies 2 rows.
+    let lines: Vec<String> = (0..100)
+      .map(|i| format!("  wrapped line {}\n", i))
+      .collect();
+    let buffer = make_buffer_from_lines(lines.iter().map(|l| l.as_str()).collect());
+    let size = U16Size::new(10, 20);
+    let options = WindowLocalOptions::builder()
+      .wrap(true)
+      .line_break(false)
+      .build();
+    let mut actual = make_viewport_from_size(size, buffer, &options);
+
+    // zz
+    move_cursor_to(&mut actual, 40, 50);
+    actual.scroll_cursor_line_to(CursorLinePosition::Center);
+    assert_eq!(actual.start_line_idx(), 46);
+    assert_eq!(actual.cursor().line_idx(), 50);
+    assert_eq!(actual.cursor().row_idx(), 8);
+
+    // Each line is placed once, i.e. the line 50 and the lines 45-49 above it.
+    let count = layout_char_count();
+    assert_eq!(actual.start_line_for(50, CursorLinePosition::Center), 46);
+    assert_eq!(layout_char_count() - count, 6 * lines[50].chars().count());
+  }
+
+  #[test]
+  fn line_rows1() {
+    test_log_init();
+
+    let lines: Vec<String> = (0..100)
+      .map(|i| format!("  wrapped line {}\n", i))
+      .collect();
+    let buffer = make_buffer_from_lines(lines.iter().map(|l| l.as_str()).collect());
+    let size = U16Size::new(10, 20);
+    let options = WindowLocalOptions::builder()
+      .wrap(true)
+      .line_break(false)
+      .build();
+    let actual = make_viewport_from_size(size, buffer, &options);
+
+    // Only the line itself is placed.
+    let count = layout_char_count();
+    assert_eq!(actual.line_rows(50), 2);
+    assert_eq!(layout_char_count() - count, lines[50].chars().count());
+  }
+
+  #[test]
+  fn fold1() {
+    test_log_init();
+
+    let lines: Vec<String> = (0..10).map(|i| format!("line {}\n", i)).collect();
+    let buffer = make_buffer_from_lines(lines.iter().map(|l| l.as_str()).collect());
+    let size = U16Size::new(20, 5);
+    for (wrap, line_break) in [(false, false), (true, false), (true, true)] {
+      let options = WindowLocalOptions::builder()
+        .wrap(wrap)
+        .line_break(line_break)
+        .build();
+      let mut actual = make_viewport_from_size(size, buffer.clone(), &options);
+      assert_eq!(actual.end_line_idx(), 5);
+
+      // Close the fold over the lines 2-4, the lines below it shift up.
+      actual.folds_mut().create(1..4);
+      actual.sync_from_top_left(0, 0);
+      assert_eq!(actual.start_line_idx(), 0);
+      assert_eq!(actual.end_line_idx(), 7);
+      assert_eq!(actual.total_rows(), 5);
+      assert_eq!(actual.first_row_of_line(1), Some(1));
+      assert_eq!(actual.rows_for_line(1), Some(1));
+      assert_eq!(actual.rows_for_line(2), Some(0));
+      assert_eq!(actual.rows_for_line(3), Some(0));
+      assert_eq!(actual.first_row_of_line(4), Some(2));
+      assert_eq!(actual.first_row_of_line(6), Some(4));
+
+      // The cursor inside the fold is at the summary row.
+      let cursor = actual.cursor_viewport_at(2, 3).unwrap();
+      assert_eq!((cursor.line_idx(), cursor.char_idx()), (1, 0));
+      assert_eq!(cursor.row_idx(), 1);
+
+      // The viewport starts from the fold instead of the hidden line.
+      actual.sync_from_top_left(3, 0);
+      assert_eq!(actual.start_line_idx(), 1);
+      assert_eq!(actual.first_row_of_line(4), Some(1));
+
+      // The lines are displayed again when the fold is opened.
+      assert!(actual.folds_mut().open_at(2));
+      actual.sync_from_top_left(0, 0);
+      assert_eq!(actual.end_line_idx(), 5);
+      assert_eq!(actual.first_row_of_line(2), Some(2));
+    }
+  }
+
+  #[test]
+  fn windex1() {
+    test_log_init();
+
+    let line = format!("{}\n", "0123456789".repeat(1000));
+    let buffer = make_buffer_from_lines(vec![line.as_str(), line.as_str()]);
+    let size = U16Size::new(10, 5);
+    let options = WindowLocalOptions::builder().wrap(false).build();
+    let sync = |start_dcolumn: usize| {
+      let mut actual = make_viewport_from_size(size, buffer.clone(), &options);
+      actual.sync_from_top_left(0, start_dcolumn);
+      actual
+    };
+
+    // The width indexes are built by the first viewport, and reused by the second one.
+    let actual1 = sync(5003);
+    let windex0 = rlock!(buffer).windex(0).unwrap();
+    let windex1 = rlock!(buffer).windex(1).unwrap();
+    let actual2 = sync(5003);
+    assert_eq!(
+      format!("{:?}", actual1.lines()),
+      format!("{:?}", actual2.lines())
+    );
+    assert!(Arc::ptr_eq(&rlock!(buffer).windex(0).unwrap(), &windex0));
+    assert!(Arc::ptr_eq(&rlock!(buffer).windex(1).unwrap(), &windex1));
+
+    // Edit the first line, only its width index is dropped and built again.
+    wlock!(buffer).insert(0, "好").unwrap();
+    assert!(rlock!(buffer).windex(0).is_none());
+    let actual3 = sync(5003);
+    assert!(!Arc::ptr_eq(&rlock!(buffer).windex(0).unwrap(), &windex0));
+    assert!(Arc::ptr_eq(&rlock!(buffer).windex(1).unwrap(), &windex1));
+    let row = actual3.lines().get(&0).unwrap().rows().get(&0).unwrap();
+    assert_eq!(row.start_char_idx(), 5002);
+  }
 }
@@ -0,0 +1,592 @@
+//! The per-line layout engine of the viewport, i.e. place the chars of a buffer line into the
+//! window rows.
+//!
+//! All the `wrap`/`line-break` modes share the same engine, they only differ in the rows limit
+//! (the `wrap=false` line occupies a single row) and the word-break strategy.
+
+use crate::buf::Buffer;
+use crate::cart::U16Rect;
+use crate::ui::widget::window::viewport::sync::show_break_width;
+use crate::ui::widget::window::viewport::RowViewport;
+use crate::ui::widget::window::{LineViewport, ViewportOptions};
+
+use ropey::RopeSlice;
+#[cfg(test)]
+use std::cell::Cell;
+use std::collections::BTreeMap;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// The word-break strategy, i.e. how a line is split into the words. A word that cannot be
+/// placed in the rest of the row starts from the next row, and a word longer than the row is
+/// placed by grapheme clusters.
+pub trait WordBreak {
+  /// Split the text into the words, the words are concatenated back to the text.
+  fn split_words<'a>(&self, text: &'a str) -> Vec<&'a str>;
+}
+
+#[derive(Debug, Copy, Clone, Default)]
+/// Break at the unicode word boundaries, see: <https://www.unicode.org/reports/tr29/>.
+pub struct UnicodeWords;
+
+impl WordBreak for UnicodeWords {
+  fn split_words<'a>(&self, text: &'a str) -> Vec<&'a str> {
+    text.split_word_bounds().collect()
+  }
+}
+
+#[derive(Debug, Clone)]
+/// Break after any of the chars, i.e. the 'breakat' option in Vim.
+/// See: <https://vimhelp.org/options.txt.html#%27breakat%27>.
+pub struct BreakAt {
+  chars: Vec<char>,
+}
+
+impl BreakAt {
+  pub fn new(chars: &str) -> Self {
+    BreakAt {
+      chars: chars.chars().collect(),
+    }
+  }
+}
+
+impl WordBreak for BreakAt {
+  fn split_words<'a>(&self, text: &'a str) -> Vec<&'a str> {
+    let mut words = vec![];
+    let mut start = 0_usize;
+    for (i, c) in text.char_indices() {
+      if self.chars.contains(&c) {
+        let end = i + c.len_utf8();
+        words.push(&text[start..end]);
+        start = end;
+      }
+    }
+    if start < text.len() {
+      words.push(&text[start..]);
+    }
+    words
+  }
+}
+
+#[derive(Debug, Clone, Default)]
+/// The rows of a buffer line placed in the window.
+pub struct LineLayout {
+  rows: Vec<RowViewport>,
+  start_fills: usize,
+  end_fills: usize,
+}
+
+impl LineLayout {
+  /// The rows, starts from the first row of the line.
+  pub fn rows(&self) -> &Vec<RowViewport> {
+    &self.rows
+  }
+
+  /// The filled columns at the beginning of the first row, i.e. the wide char cannot be fully
+  /// placed from the start display column.
+  pub fn start_fills(&self) -> usize {
+    self.start_fills
+  }
+
+  /// The filled columns at the end of the last row, i.e. the wide char cannot be fully placed
+  /// in the rest of the row.
+  pub fn end_fills(&self) -> usize {
+    self.end_fills
+  }
+
+  /// Convert to the line viewport, the first row is placed at the window row `start_row`.
+  pub fn into_line_viewport(self, start_row: u16) -> LineViewport {
+    let rows: BTreeMap<u16, RowViewport> = self
+      .rows
+      .into_iter()
+      .enumerate()
+      .map(|(i, row)| (start_row + i as u16, row))
+      .collect();
+    LineViewport::new(rows, self.start_fills, self.end_fills)
+  }
+}
+
+/// Place the buffer line `line_idx` into at most `max_rows` rows of the window, from the display
+/// column `start_dcolumn`. The line is placed by grapheme clusters if `word_break` is `None`,
+/// otherwise by the words.
+///
+/// The first char placed is the first char starts at or after `start_dcolumn`, i.e. a char ends
+/// at `start_dcolumn` is not visible, and a wide char across `start_dcolumn` is not visible and
+/// its rest cells are filled at the beginning of the first row, see [`LineLayout::start_fills`].
+///
+/// The continuation rows start after the 'showbreak' (only if `wrap=true`).
+pub fn layout_line(
+  options: &ViewportOptions,
+  buffer: &Buffer,
+  line_idx: usize,
+  start_dcolumn: usize,
+  actual_shape: &U16Rect,
+  max_rows: u16,
+  word_break: Option<&dyn WordBreak>,
+) -> LineLayout {
+  let height = actual_shape.height();
+  let width = actual_shape.width();
+  let show_break = if options.wrap {
+    show_break_width(options, width)
+  } else {
+    0_u16
+  };
+  let fit_width = width - show_break;
+
+  let line = match buffer.get_line(line_idx) {
+    Some(line) => line,
+    None => return LineLayout::default(),
+  };
+
+  // Jump to the first char starts at or after `start_dcolumn`, the chars before it are not
+  // visited.
+  let (first_c_idx, first_dcol) = buffer.char_at_dcolumn(line_idx, start_dcolumn);
+  if max_rows == 0 || first_c_idx >= line.len_chars() {
+    return LineLayout::default();
+  }
+
+  let start_fills = std::cmp::min(first_dcol - start_dcolumn, width as usize);
+  let mut placer = Placer {
+    buffer,
+    width,
+    show_break,
+    max_rows,
+    rows: vec![],
+    wcol: start_fills as u16,
+    row_start_wcol: start_fills as u16,
+    dcol: first_dcol,
+    start_dcol: first_dcol,
+    end_dcol: first_dcol,
+    start_c_idx: first_c_idx,
+    end_c_idx: first_c_idx,
+    ch2dcols: BTreeMap::new(),
+    end_fills: 0,
+  };
+
+  match word_break {
+    None => {
+      let len_chars = line.len_chars();
+      for_each_grapheme(buffer, &line, first_c_idx, fit_width, |c_idx, g| {
+        let g_chars = g.chars().count();
+
+        #[cfg(test)]
+        LAYOUT_CHAR_COUNT.with(|count| count.set(count.get() + g_chars));
+
+        placer.push_grapheme(c_idx, g) || placer.finish(c_idx + g_chars == len_chars)
+      });
+    }
+    Some(word_break) => {
+      let truncated_line = buffer.truncated_line(
+        line_idx,
+        first_c_idx,
+        height as usize * width as usize * 2 + height as usize * 2 + 16,
+      );
+      let words = word_break.split_words(&truncated_line);
+      let mut c_idx = first_c_idx;
+      for (i, wd) in words.iter().enumerate() {
+        if placer.push_word(c_idx, wd, fit_width) || placer.finish(i + 1 == words.len()) {
+          break;
+        }
+        c_idx += wd.chars().count();
+      }
+    }
+  }
+
+  LineLayout {
+    rows: placer.rows,
+    start_fills,
+    end_fills: placer.end_fills,
+  }
+}
+
+// The state of placing a line into the rows, the methods return `true` if the rows limit is
+// reached, i.e. stop placing the rest of the line.
+struct Placer<'a> {
+  buffer: &'a Buffer,
+  width: u16,
+  show_break: u16,
+  max_rows: u16,
+
+  rows: Vec<RowViewport>,
+  // The window column of current row, and the window column current row starts from.
+  wcol: u16,
+  row_start_wcol: u16,
+
+  dcol: usize,
+  start_dcol: usize,
+  end_dcol: usize,
+
+  start_c_idx: usize,
+  end_c_idx: usize,
+
+  ch2dcols: BTreeMap<usize, (usize, usize)>,
+  end_fills: usize,
+}
+
+impl Placer<'_> {
+  // Save current row.
+  fn flush(&mut self) {
+    self.rows.push(RowViewport::new(
+      self.start_dcol..self.end_dcol,
+      self.start_c_idx..self.end_c_idx,
+      &self.ch2dcols,
+    ));
+  }
+
+  // Go to next row, which starts after the 'showbreak'.
+  fn next_row(&mut self) -> bool {
+    self.row_start_wcol = self.show_break;
+    self.wcol = self.row_start_wcol;
+    self.start_dcol = self.end_dcol;
+    self.start_c_idx = self.end_c_idx;
+    self.ch2dcols.clear();
+    self.rows.len() >= self.max_rows as usize
+  }
+
+  // Go to next row because the next `g` cannot be placed in current row.
+  fn wrap(&mut self) -> bool {
+    self.flush();
+    let saved_end_fills = (self.width - self.wcol) as usize;
+    if self.next_row() {
+      self.end_fills = saved_end_fills;
+      true
+    } else {
+      false
+    }
+  }
+
+  fn place(&mut self, c_idx: usize, s: &str) {
+    let mut end_c_idx = c_idx;
+    for c in s.chars() {
+      let saved_start_dcol = self.dcol;
+      self.dcol += self.buffer.char_width(c);
+      self
+        .ch2dcols
+        .insert(end_c_idx, (saved_start_dcol, self.dcol));
+      end_c_idx += 1;
+    }
+    self.end_dcol = self.dcol;
+    self.end_c_idx = end_c_idx;
+    // A char wider than the entire row (i.e. a tab or a wide char in a very narrow window) is
+    // truncated at the end of the row.
+    let wcol = self.wcol as usize + self.buffer.str_width(s);
+    self.wcol = std::cmp::min(wcol, self.width as usize) as u16;
+  }
+
+  // Place a grapheme cluster, it goes to next row if it cannot be placed in current row. If it
+  // starts from the beginning of the row, it's placed and truncated, i.e. never save an empty row.
+  fn push_grapheme(&mut self, c_idx: usize, g: &str) -> bool {
+    let g_width = self.buffer.str_width(g);
+    if self.wcol as usize + g_width > self.width as usize
+      && self.wcol > self.row_start_wcol
+      && self.wrap()
+    {
+      return true;
+    }
+    self.place(c_idx, g);
+    false
+  }
+
+  // Place a word. If it cannot be placed in the rest of current row, it starts from next row.
+  // If it's too long to place in an entire row, it's placed by grapheme clusters, i.e. it
+  // fallbacks to the same behavior with `line-break=false`.
+  fn push_word(&mut self, c_idx: usize, wd: &str, fit_width: u16) -> bool {
+    let wd_width = self.buffer.str_width(wd);
+    if self.wcol as usize + wd_width <= self.width as usize {
+      self.place(c_idx, wd);
+      return false;
+    }
+
+    let graphemes = str_graphemes(self.buffer, wd, c_idx, fit_width);
+
+    // If the word starts from the beginning of the row, it doesn't need to start from next row.
+    if self.wcol > self.row_start_wcol {
+      self.flush();
+
+      // The `end_fills` only indicates the cells at the end of the bottom row cannot show the
+      // full char, but the word is not placed in this row at all, thus detect the real end fills
+      // position as if the word is placed by grapheme clusters.
+      let saved_end_fills = {
+        let mut tmp_wcol = self.wcol;
+        for (_, g) in graphemes.iter() {
+          let g_width = self.buffer.str_width(g);
+          if tmp_wcol as usize + g_width > self.width as usize {
+            break;
+          }
+          tmp_wcol += g_width as u16;
+          if tmp_wcol >= self.width {
+            break;
+          }
+        }
+        (self.width - tmp_wcol) as usize
+      };
+
+      if self.next_row() {
+        self.end_fills = saved_end_fills;
+        return true;
+      }
+    }
+
+    // The last cluster is left to [`Placer::finish`], as other words.
+    for (j, (g_c_idx, g)) in graphemes.iter().enumerate() {
+      let g_width = self.buffer.str_width(g);
+      if self.wcol as usize + g_width > self.width as usize
+        && self.wcol > self.row_start_wcol
+        && self.wrap()
+      {
+        return true;
+      }
+      self.place(*g_c_idx, g);
+      if j + 1 < graphemes.len() && self.wcol >= self.width && self.fill_row() {
+        return true;
+      }
+    }
+    false
+  }
+
+  // Current row is fully placed, go to next row.
+  fn fill_row(&mut self) -> bool {
+    self.flush();
+    assert_eq!(self.wcol, self.width);
+    self.next_row()
+  }
+
+  // Finish a grapheme cluster or a word, the `last` indicates it's the end of the line.
+  fn finish(&mut self, last: bool) -> bool {
+    if last {
+      self.flush();
+      return true;
+    }
+    self.wcol >= self.width && self.fill_row()
+  }
+}
+
+// If the grapheme cluster is wider than the row, split it into chars. Otherwise the row cannot
+// place anything and the rendering never goes forward.
+fn fit_grapheme<'a>(buffer: &Buffer, g: &'a str, width: u16) -> impl Iterator<Item = &'a str> {
+  let (whole, chars) = if buffer.str_width(g) > width as usize {
+    (None, Some(g.char_indices()))
+  } else {
+    (Some(g), None)
+  };
+  whole.into_iter().chain(
+    chars
+      .into_iter()
+      .flatten()
+      .map(move |(i, c)| &g[i..i + c.len_utf8()]),
+  )
+}
+
+// Visit the grapheme clusters of the line from the char `start_c_idx`, along with the char index
+// they start from, they're split by [`fit_grapheme`]. The `f` returns `true` to stop visiting.
+//
+// NOTE: The clusters are collected into the same string, i.e. it doesn't allocate for each of
+// them.
+fn for_each_grapheme<F>(buffer: &Buffer, line: &RopeSlice, start_c_idx: usize, width: u16, mut f: F)
+where
+  F: FnMut(usize, &str) -> bool,
+{
+  let mut chars = (start_c_idx..).zip(line.chars_at(start_c_idx)).peekable();
+  let mut cluster = String::new();
+  while let Some((cluster_c_idx, c)) = chars.next() {
+    cluster.clear();
+    cluster.push(c);
+    while let Some((_, next_c)) = chars.peek() {
+      cluster.push(*next_c);
+      if cluster.graphemes(true).nth(1).is_some() {
+        cluster.pop();
+        break;
+      }
+      chars.next();
+    }
+    let mut c_idx = cluster_c_idx;
+    for g in fit_grapheme(buffer, &cluster, width) {
+      if f(c_idx, g) {
+        return;
+      }
+      c_idx += g.chars().count();
+    }
+  }
+}
+
+// The grapheme clusters of the str starts from the char `start_c_idx`, along with the char index
+// they start from, they're split by [`fit_grapheme`].
+fn str_graphemes<'a>(
+  buffer: &Buffer,
+  s: &'a str,
+  start_c_idx: usize,
+  width: u16,
+) -> Vec<(usize, &'a str)> {
+  let mut c_idx = start_c_idx;
+  s.graphemes(true)
+    .flat_map(|g| fit_grapheme(buffer, g, width))
+    .map(|g| {
+      let unit = (c_idx, g);
+      c_idx += g.chars().count();
+      unit
+    })
+    .collect()
+}
+
+#[cfg(test)]
+thread_local! {
+  static LAYOUT_CHAR_COUNT: Cell<usize> = const { Cell::new(0) };
+}
+
+#[cfg(test)]
+/// How many chars are placed by grapheme clusters in current thread.
+pub fn layout_char_count() -> usize {
+  LAYOUT_CHAR_COUNT.with(|count| count.get())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use crate::envar;
+  use crate::rlock;
+  use crate::test::buf::make_buffer_from_lines;
+
+  fn options(wrap: bool, line_break: bool) -> ViewportOptions {
+    ViewportOptions {
+      wrap,
+      line_break,
+      show_break_width: 0,
+      scroll: 0,
+      scroll_off: 0,
+    }
+  }
+
+  // The char index range, display column range of the rows, and the start/end fills.
+  fn layout(
+    lines: Vec<&str>,
+    options: &ViewportOptions,
+    start_dcolumn: usize,
+    size: (u16, u16),
+    word_break: Option<&dyn WordBreak>,
+  ) -> (Vec<(usize, usize, usize, usize)>, usize, usize) {
+    let buffer = make_buffer_from_lines(lines);
+    let buffer = rlock!(buffer);
+    let shape = U16Rect::new((0, 0), size);
+    let layout = layout_line(
+      options,
+      &buffer,
+      0,
+      start_dcolumn,
+      &shape,
+      if options.wrap { size.1 } else { 1 },
+      word_break,
+    );
+    let rows = layout
+      .rows()
+      .iter()
+      .map(|row| {
+        (
+          row.start_char_idx(),
+          row.end_char_idx(),
+          row.start_dcol_idx(),
+          row.end_dcol_idx(),
+        )
+      })
+      .collect();
+    (rows, layout.start_fills(), layout.end_fills())
+  }
+
+  #[test]
+  fn start_dcolumn1() {
+    // All the modes start from the first char starts at or after the start display column.
+    for options in [
+      options(false, false),
+      options(true, false),
+      options(true, true),
+    ] {
+      let word_break: Option<&dyn WordBreak> = if options.line_break {
+        Some(&UnicodeWords)
+      } else {
+        None
+      };
+
+      // The wide char ends at the start display column is not visible.
+      let actual = layout(vec!["好好abc\n"], &options, 2, (10, 1), word_break);
+      assert_eq!(actual, (vec![(1, 6, 2, 7)], 0, 0));
+
+      // The wide char across the start display column is not visible, its cell is filled.
+      let actual = layout(vec!["好好abc\n"], &options, 1, (10, 1), word_break);
+      assert_eq!(actual, (vec![(1, 6, 2, 7)], 1, 0));
+
+      // The line is not that wide.
+      let actual = layout(vec!["好好abc\n"], &options, 8, (10, 1), word_break);
+      assert_eq!(actual, (vec![], 0, 0));
+    }
+  }
+
+  #[test]
+  fn word_break1() {
+    let lines = vec!["aaa-bbb cc\n"];
+    let options = options(true, true);
+
+    let actual = layout(lines.clone(), &options, 0, (5, 3), None);
+    assert_eq!(
+      actual,
+      (vec![(0, 5, 0, 5), (5, 10, 5, 10), (10, 11, 10, 10)], 0, 0)
+    );
+
+    let actual = layout(lines.clone(), &options, 0, (5, 3), Some(&UnicodeWords));
+    assert_eq!(
+      actual,
+      (vec![(0, 4, 0, 4), (4, 8, 4, 8), (8, 11, 8, 10)], 0, 0)
+    );
+
+    let actual = layout(lines.clone(), &options, 0, (5, 3), Some(&BreakAt::new("-")));
+    assert_eq!(
+      actual,
+      (vec![(0, 4, 0, 4), (4, 9, 4, 9), (9, 11, 9, 10)], 0, 0)
+    );
+
+    // The rows limit.
+    let actual = layout(lines, &options, 0, (5, 1), Some(&UnicodeWords));
+    assert_eq!(actual, (vec![(0, 4, 0, 4)], 0, 0));
+  }
+
+  #[test]
+  fn narrow_width1() {
+    // The wide char and the tab are wider than the row, each of them is truncated in a row.
+    for options in [options(true, false), options(true, true)] {
+      let word_break: Option<&dyn WordBreak> = if options.line_break {
+        Some(&UnicodeWords)
+      } else {
+        None
+      };
+      let actual = layout(vec!["a好\tb\n"], &options, 0, (1, 6), word_break);
+      assert_eq!(
+        actual,
+        (
+          vec![
+            (0, 1, 0, 1),
+            (1, 2, 1, 3),
+            (2, 3, 3, 11),
+            (3, 4, 11, 12),
+            (4, 5, 12, 12)
+          ],
+          0,
+          0
+        )
+      );
+    }
+
+    let actual = layout(vec!["好a\n"], &options(false, false), 0, (1, 1), None);
+    assert_eq!(actual, (vec![(0, 1, 0, 2)], 0, 0));
+  }
+
+  #[test]
+  fn split_words1() {
+    assert_eq!(
+      UnicodeWords.split_words("hello, world"),
+      vec!["hello", ",", " ", "world"]
+    );
+    assert_eq!(
+      BreakAt::new(" ,").split_words("hello, world"),
+      vec!["hello,", " ", "world"]
+    );
+    assert_eq!(BreakAt::new("-").split_words("a-b-"), vec!["a-", "b-"]);
+  }
+}
@@ -1,17 +1,17 @@
 //! Internal implementations for Viewport.
 
-use crate::buf::BufferWk;
+use crate::buf::{Buffer, BufferWk};
 use crate::cart::U16Rect;
 use crate::envar;
 use crate::rlock;
+use crate::ui::widget::window::fold::Folds;
+use crate::ui::widget::window::viewport::layout::{layout_line, UnicodeWords, WordBreak};
 use crate::ui::widget::window::viewport::RowViewport;
 use crate::ui::widget::window::{LineViewport, ViewportOptions};
 
-use ropey::RopeSlice;
 use std::collections::BTreeMap;
 use std::ops::Range;
 // use tracing::trace;
-use unicode_segmentation::UnicodeSegmentation;
 
 #[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
 /// Lines index inside [`Viewport`].
@@ -49,9 +49,13 @@ impl ViewportLineRange {
 
 // Given the buffer and window size, collect information from start line and column, i.e. from the
 // top-left corner.
+//
+// Each line is placed by [`layout_line`], the `wrap=false` line occupies a single row, and the
+// `line-break=true` line is broken at the unicode word boundaries.
 pub fn from_top_left(
   options: &ViewportOptions,
   buffer: BufferWk,
+  folds: &Folds,
   actual_shape: &U16Rect,
   start_line: usize,
   start_dcolumn: usize,
@@ -63,1102 +67,159 @@ pub fn from_top_left(
     return (ViewportLineRange::default(), BTreeMap::new());
   }
 
-  match (options.wrap, options.line_break) {
-    (false, _) => {
-      _sync_from_top_left_nowrap(options, buffer, actual_shape, start_line, start_dcolumn)
-    }
-    (true, false) => {
-      _sync_from_top_left_wrap_nolinebreak(options, buffer, actual_shape, start_line, start_dcolumn)
-    }
-    (true, true) => {
-      _sync_from_top_left_wrap_linebreak(options, buffer, actual_shape, start_line, start_dcolumn)
-    }
-  }
-}
+  // The viewport cannot start from a hidden line, i.e. it starts from the closed fold instead.
+  let start_line = match folds.closed_fold_at(start_line) {
+    Some(fold) => fold.start,
+    None => start_line,
+  };
 
-#[allow(dead_code)]
-fn slice2line(s: &RopeSlice) -> String {
-  let mut builder = String::new();
-  for chunk in s.chunks() {
-    builder.push_str(chunk);
-  }
-  builder
-}
-
-#[allow(unused_variables)]
-// Implement [`_sync_from_top_left`] with option `wrap=false`.
-fn _sync_from_top_left_nowrap(
-  _options: &ViewportOptions,
-  buffer: BufferWk,
-  actual_shape: &U16Rect,
-  start_line: usize,
-  start_dcolumn: usize,
-) -> (ViewportLineRange, BTreeMap<usize, LineViewport>) {
-  let height = actual_shape.height();
-  let width = actual_shape.width();
-
-  assert!(height > 0);
-  assert!(width > 0);
-  // trace!(
-  //   "_collect_from_top_left_with_nowrap, actual_shape:{:?}, height/width:{:?}/{:?}",
-  //   actual_shape,
-  //   height,
-  //   width
-  // );
+  let word_break: Option<&dyn WordBreak> = if options.wrap && options.line_break {
+    Some(&UnicodeWords)
+  } else {
+    None
+  };
 
   // Get buffer arc pointer, and lock for read.
   let buffer = buffer.upgrade().unwrap();
   let buffer = rlock!(buffer);
 
-  // trace!(
-  //   "buffer.get_line ({:?}):{:?}",
-  //   start_line,
-  //   match buffer.get_line(start_line) {
-  //     Some(line) => slice2line(&line),
-  //     None => "None".to_string(),
-  //   }
-  // );
+  // The `start_line` is outside of the buffer, i.e. many lines are deleted while the viewport is
+  // scrolled down, it's clamped to show the tail of the buffer.
+  let last_line_idx = buffer.last_line_idx();
+  let start_line = if start_line > last_line_idx {
+    tail_start_line(options, &buffer, folds, actual_shape)
+  } else {
+    start_line
+  };
 
   let mut line_viewports: BTreeMap<usize, LineViewport> = BTreeMap::new();
 
-  match buffer.get_lines_at(start_line) {
-    // The `start_line` is in the buffer.
-    Some(buflines) => {
-      // The first `wrow` in the window maps to the `start_line` in the buffer.
-      let mut wrow = 0;
-      let mut current_line = start_line;
-
-      for (l, line) in buflines.enumerate() {
-        // Current row goes out of viewport.
-        if wrow >= height {
-          break;
-        }
-
-        // trace!(
-        //   "0-l:{:?}, line:'{:?}', current_line:{:?}",
-        //   l,
-        //   slice2line(&line),
-        //   current_line
-        // );
-
-        let mut rows: BTreeMap<u16, RowViewport> = BTreeMap::new();
-        let mut wcol = 0_u16;
-
-        let mut dcol = 0_usize;
-        let mut start_dcol = 0_usize;
-        let mut end_dcol = 0_usize;
-
-        let mut start_c_idx = 0_usize;
-        let mut end_c_idx = 0_usize;
-        let mut start_c_idx_init = false;
-        let mut _end_c_idx_init = false;
-
-        let mut ch2dcols: BTreeMap<usize, (usize, usize)> = BTreeMap::new();
-
-        let mut start_fills = 0_usize;
-        let mut end_fills = 0_usize;
-
-        // Go through each char in the line.
-        for (i, c) in line.chars().enumerate() {
-          let c_width = buffer.char_width(c);
-
-          // Prefix width is still before `start_dcolumn`.
-          if dcol + c_width < start_dcolumn {
-            dcol += c_width;
-            end_dcol = dcol;
-            end_c_idx = i;
-            // trace!(
-            //   "1-wrow/wcol:{}/{}, c:{:?}/{:?}, dcol:{}/{}/{}, c_idx:{}/{}, fills:{}/{}, start_dcolumn:{}",
-            //   wrow, wcol, c, c_width, dcol, start_dcol, end_dcol, start_c_idx, end_c_idx, start_fills, end_fills, start_dcolumn
-            // );
-            continue;
-          }
-
-          if !start_c_idx_init {
-            start_c_idx_init = true;
-            start_dcol = dcol;
-            start_c_idx = i;
-            start_fills = dcol - start_dcolumn;
-            // trace!(
-            //   "2-wrow/wcol:{}/{}, c:{:?}/{:?}, dcol:{}/{}/{}, c_idx:{}/{}, fills:{}/{}, start_dcolumn:{}",
-            //   wrow, wcol, c, c_width, dcol, start_dcol, end_dcol, start_c_idx, end_c_idx, start_fills, end_fills, start_dcolumn
-            // );
-          }
-
-          // Row column with next char will go out of the row.
-          if wcol as usize + c_width > width as usize {
-            end_fills = width as usize - wcol as usize;
-            // trace!(
-            //   "4-wrow/wcol:{}/{}, c:{:?}/{:?}, dcol:{}/{}/{}, c_idx:{}/{}, fills:{}/{}",
-            //   wrow,
-            //   wcol,
-            //   c,
-            //   c_width,
-            //   dcol,
-            //   start_dcol,
-            //   end_dcol,
-            //   start_c_idx,
-            //   end_c_idx,
-            //   start_fills,
-            //   end_fills
-            // );
-            rows.insert(
-              wrow,
-              RowViewport::new(start_dcol..end_dcol, start_c_idx..end_c_idx, &ch2dcols),
-            );
-            break;
-          }
-
-          let saved_start_dcol = dcol;
-          let saved_c_idx = i;
-
-          dcol += c_width;
-          end_dcol = dcol;
-          end_c_idx = i + 1;
-          wcol += c_width as u16;
-
-          ch2dcols.insert(saved_c_idx, (saved_start_dcol, end_dcol));
-
-          // trace!(
-          //   "5-wrow/wcol:{}/{}, c:{:?}/{:?}, dcol:{}/{}/{}, c_idx:{}/{}, fills:{}/{}",
-          //   wrow,
-          //   wcol,
-          //   c,
-          //   c_width,
-          //   dcol,
-          //   start_dcol,
-          //   end_dcol,
-          //   start_c_idx,
-          //   end_c_idx,
-          //   start_fills,
-          //   end_fills
-          // );
-
-          // End of the line.
-          if i + 1 == line.len_chars() {
-            // trace!(
-            //   "6-wrow/wcol:{}/{}, c:{:?}/{:?}, dcol:{}/{}/{}, c_idx:{}/{}, fills:{}/{}",
-            //   wrow,
-            //   wcol,
-            //   c,
-            //   c_width,
-            //   dcol,
-            //   start_dcol,
-            //   end_dcol,
-            //   start_c_idx,
-            //   end_c_idx,
-            //   start_fills,
-            //   end_fills
-            // );
-            rows.insert(
-              wrow,
-              RowViewport::new(start_dcol..end_dcol, start_c_idx..end_c_idx, &ch2dcols),
-            );
-            break;
-          }
-
-          // Row column goes out of the row.
-          if wcol >= width {
-            // trace!(
-            //   "7-wrow/wcol:{}/{}, c:{:?}/{:?}, dcol:{}/{}/{}, c_idx:{}/{}, fills:{}/{}",
-            //   wrow,
-            //   wcol,
-            //   c,
-            //   c_width,
-            //   dcol,
-            //   start_dcol,
-            //   end_dcol,
-            //   start_c_idx,
-            //   end_c_idx,
-            //   start_fills,
-            //   end_fills
-            // );
-            rows.insert(
-              wrow,
-              RowViewport::new(start_dcol..end_dcol, start_c_idx..end_c_idx, &ch2dcols),
-            );
-            break;
-          }
-        }
-
-        line_viewports.insert(
-          current_line,
-          LineViewport::new(rows, start_fills, end_fills),
-        );
-        // trace!(
-        //   "8-current_line:{}, wrow/wcol:{}/{}, dcol:{}/{}/{}, c_idx:{}/{}, fills:{}/{}",
-        //   current_line,
-        //   wrow,
-        //   wcol,
-        //   dcol,
-        //   start_dcol,
-        //   end_dcol,
-        //   start_c_idx,
-        //   end_c_idx,
-        //   start_fills,
-        //   end_fills
-        // );
-        // Go to next row and line
-        current_line += 1;
+  // The first `wrow` in the window maps to the `start_line` in the buffer.
+  let mut wrow = 0_u16;
+  let mut current_line = start_line;
+
+  // Current row goes out of viewport, or current line is the empty line after the trailing line
+  // break, which is not a line.
+  while wrow < height && current_line <= last_line_idx {
+    // The closed fold is displayed as a summary row, the rest lines are hidden.
+    if let Some(fold) = folds.closed_fold_at(current_line) {
+      line_viewports.insert(
+        current_line,
+        folded_line_viewport(&fold, current_line, wrow),
+      );
+      if current_line == fold.start {
         wrow += 1;
       }
-
-      // trace!("9-current_line:{}, row:{}", current_line, wrow,);
-      (
-        ViewportLineRange::new(start_line..current_line),
-        line_viewports,
-      )
-    }
-    None => {
-      // The `start_line` is outside of the buffer.
-      // trace!("10-start_line:{}", start_line);
-      (ViewportLineRange::default(), BTreeMap::new())
+      current_line += 1;
+      continue;
     }
+
+    let max_rows = if options.wrap { height - wrow } else { 1 };
+    let layout = layout_line(
+      options,
+      &buffer,
+      current_line,
+      start_dcolumn,
+      actual_shape,
+      max_rows,
+      word_break,
+    );
+    let rows = std::cmp::max(layout.rows().len() as u16, 1);
+    line_viewports.insert(current_line, layout.into_line_viewport(wrow));
+
+    // Go to next row and line.
+    current_line += 1;
+    wrow += rows;
   }
+
+  (
+    ViewportLineRange::new(start_line..current_line),
+    line_viewports,
+  )
 }
 
-#[allow(unused_variables)]
-// Implement [`_sync_from_top_left`] with option `wrap=true` and `line-break=false`.
-fn _sync_from_top_left_wrap_nolinebreak(
-  _options: &ViewportOptions,
-  buffer: BufferWk,
+// The largest start line that still fills the window with the tail of the buffer, i.e. the rows of
+// the lines are counted backwards from the last line. The last line is always shown, even if it
+// occupies more rows than the window.
+fn tail_start_line(
+  options: &ViewportOptions,
+  buffer: &Buffer,
+  folds: &Folds,
   actual_shape: &U16Rect,
-  start_line: usize,
-  start_dcolumn: usize,
-) -> (ViewportLineRange, BTreeMap<usize, LineViewport>) {
+) -> usize {
   let height = actual_shape.height();
-  let width = actual_shape.width();
-
-  assert!(height > 0);
-  assert!(width > 0);
-  // trace!(
-  //   "_collect_from_top_left_with_wrap_nolinebreak, actual_shape:{:?}, height/width:{:?}/{:?}",
-  //   actual_shape,
-  //   height,
-  //   width
-  // );
-
-  // Get buffer arc pointer, and lock for read.
-  let buffer = buffer.upgrade().unwrap();
-  let buffer = rlock!(buffer);
-
-  // trace!(
-  //   "buffer.get_line ({:?}):'{:?}'",
-  //   start_line,
-  //   match buffer.get_line(start_line) {
-  //     Some(line) => slice2line(&line),
-  //     None => "None".to_string(),
-  //   }
-  // );
-
-  let mut line_viewports: BTreeMap<usize, LineViewport> = BTreeMap::new();
-
-  match buffer.get_lines_at(start_line) {
-    Some(buflines) => {
-      // The `start_line` is inside the buffer.
-
-      // The first `wrow` in the window maps to the `start_line` in the buffer.
-      let mut wrow = 0;
-      let mut current_line = start_line;
-
-      for (l, line) in buflines.enumerate() {
-        // Current row goes out of viewport.
-        if wrow >= height {
-          break;
-        }
-
-        // trace!(
-        //   "0-l:{:?}, line:'{:?}', current_line:{:?}",
-        //   l,
-        //   slice2line(&line),
-        //   current_line
-        // );
-
-        let mut rows: BTreeMap<u16, RowViewport> = BTreeMap::new();
-        let mut wcol = 0_u16;
-
-        let mut dcol = 0_usize;
-        let mut start_dcol = 0_usize;
-        let mut end_dcol = 0_usize;
-
-        let mut start_c_idx = 0_usize;
-        let mut end_c_idx = 0_usize;
-        let mut start_c_idx_init = false;
-        let mut _end_c_idx_init = false;
-
-        let mut ch2dcols: BTreeMap<usize, (usize, usize)> = BTreeMap::new();
-
-        let mut start_fills = 0_usize;
-        let mut end_fills = 0_usize;
-
-        for (i, c) in line.chars().enumerate() {
-          let c_width = buffer.char_width(c);
-
-          // Prefix width is still before `start_dcolumn`.
-          if dcol + c_width < start_dcolumn {
-            dcol += c_width;
-            end_dcol = dcol;
-            end_c_idx = i;
-            // trace!(
-            //   "1-wrow/wcol:{}/{}, c:{}/{:?}, dcol:{}/{}/{}, c_idx:{}/{}, fills:{}/{}, start_dcolumn:{}",
-            //   wrow, wcol, c, c_width, dcol, start_dcol, end_dcol, start_c_idx, end_c_idx, start_fills, end_fills, start_dcolumn
-            // );
-            continue;
-          }
-
-          if !start_c_idx_init {
-            start_c_idx_init = true;
-            start_dcol = dcol;
-            start_c_idx = i;
-            start_fills = dcol - start_dcolumn;
-            // trace!(
-            //   "2-wrow/wcol:{}/{}, c:{}/{:?}, dcol:{}/{}/{}, c_idx:{}/{}, fills:{}/{}",
-            //   wrow,
-            //   wcol,
-            //   c,
-            //   c_width,
-            //   dcol,
-            //   start_dcol,
-            //   end_dcol,
-            //   start_c_idx,
-            //   end_c_idx,
-            //   start_fills,
-            //   end_fills,
-            // );
-          }
-
-          // Column with next char will goes out of the row.
-          if wcol as usize + c_width > width as usize {
-            // trace!(
-            //   "3-wrow/wcol:{}/{}, c:{}/{:?}, dcol:{}/{}/{}, c_idx:{}/{}, fills:{}/{}, width:{}",
-            //   wrow,
-            //   wcol,
-            //   c,
-            //   c_width,
-            //   dcol,
-            //   start_dcol,
-            //   end_dcol,
-            //   start_c_idx,
-            //   end_c_idx,
-            //   start_fills,
-            //   end_fills,
-            //   width
-            // );
-            rows.insert(
-              wrow,
-              RowViewport::new(start_dcol..end_dcol, start_c_idx..end_c_idx, &ch2dcols),
-            );
-            let saved_end_fills = width as usize - wcol as usize;
-            wrow += 1;
-            wcol = 0_u16;
-            start_dcol = end_dcol;
-            start_c_idx = end_c_idx;
-            ch2dcols.clear();
-            if wrow >= height {
-              end_fills = saved_end_fills;
-              // trace!(
-              //   "4-wrow/wcol:{}/{}, c:{}/{:?}, dcol:{}/{}/{}, c_idx:{}/{}, fills:{}/{}, height:{}",
-              //   wrow,
-              //   wcol,
-              //   c,
-              //   c_width,
-              //   dcol,
-              //   start_dcol,
-              //   end_dcol,
-              //   start_c_idx,
-              //   end_c_idx,
-              //   start_fills,
-              //   end_fills,
-              //   height
-              // );
-              break;
-            }
-          }
-
-          let saved_c_idx = i;
-          let saved_start_dcol = dcol;
-
-          dcol += c_width;
-          end_dcol = dcol;
-          end_c_idx = i + 1;
-          wcol += c_width as u16;
-
-          ch2dcols.insert(saved_c_idx, (saved_start_dcol, end_dcol));
-
-          // trace!(
-          //   "5-wrow/wcol:{}/{}, c:{}/{:?}, dcol:{}/{}/{}, c_idx:{}/{}, fills:{}/{}",
-          //   wrow,
-          //   wcol,
-          //   c,
-          //   c_width,
-          //   dcol,
-          //   start_dcol,
-          //   end_dcol,
-          //   start_c_idx,
-          //   end_c_idx,
-          //   start_fills,
-          //   end_fills
-          // );
-
-          // End of the line.
-          if i + 1 == line.len_chars() {
-            // trace!(
-            //   "6-wrow/wcol:{}/{}, c:{}/{:?}, dcol:{}/{}/{}, c_idx:{}/{}, fills:{}/{}",
-            //   wrow,
-            //   wcol,
-            //   c,
-            //   c_width,
-            //   dcol,
-            //   start_dcol,
-            //   end_dcol,
-            //   start_c_idx,
-            //   end_c_idx,
-            //   start_fills,
-            //   end_fills
-            // );
-            rows.insert(
-              wrow,
-              RowViewport::new(start_dcol..end_dcol, start_c_idx..end_c_idx, &ch2dcols),
-            );
-            break;
-          }
-
-          // Column goes out of current row.
-          if wcol >= width {
-            // trace!(
-            //   "7-wrow/wcol:{}/{}, c:{}/{:?}, dcol:{}/{}/{}, c_idx:{}/{}, fills:{}/{}, width:{}",
-            //   wrow,
-            //   wcol,
-            //   c,
-            //   c_width,
-            //   dcol,
-            //   start_dcol,
-            //   end_dcol,
-            //   start_c_idx,
-            //   end_c_idx,
-            //   start_fills,
-            //   end_fills,
-            //   width
-            // );
-            rows.insert(
-              wrow,
-              RowViewport::new(start_dcol..end_dcol, start_c_idx..end_c_idx, &ch2dcols),
-            );
-            assert_eq!(wcol, width);
-            wrow += 1;
-            wcol = 0_u16;
-            start_dcol = end_dcol;
-            start_c_idx = end_c_idx;
-            ch2dcols.clear();
-            if wrow >= height {
-              // trace!(
-              //   "8-wrow/wcol:{}/{}, c:{}/{:?}, dcol:{}/{}/{}, c_idx:{}/{}, fills:{}/{}, height:{}",
-              //   wrow,
-              //   wcol,
-              //   c,
-              //   c_width,
-              //   dcol,
-              //   start_dcol,
-              //   end_dcol,
-              //   start_c_idx,
-              //   end_c_idx,
-              //   start_fills,
-              //   end_fills,
-              //   height
-              // );
-              break;
-            }
-          }
-        }
+  let mut start_line = buffer.last_line_idx() + 1;
+  let mut rows = 0_u16;
+  while start_line > 0 {
+    let rows_above = line_rows(options, buffer, folds, actual_shape, start_line - 1);
+    if rows > 0 && rows + rows_above > height {
+      break;
+    }
+    rows += rows_above;
+    start_line -= 1;
+  }
 
-        line_viewports.insert(
-          current_line,
-          LineViewport::new(rows, start_fills, end_fills),
-        );
-        // trace!(
-        //   "9-current_line:{}, wrow/wcol:{}/{}, dcol:{}/{}/{}, c_idx:{}/{}, fills:{}/{}",
-        //   current_line,
-        //   wrow,
-        //   wcol,
-        //   dcol,
-        //   start_dcol,
-        //   end_dcol,
-        //   start_c_idx,
-        //   end_c_idx,
-        //   start_fills,
-        //   end_fills
-        // );
-        current_line += 1;
-        wrow += 1;
-      }
+  // The viewport cannot start from a hidden line, and its closed fold doesn't fit.
+  match folds.closed_fold_at(start_line) {
+    Some(fold) if start_line != fold.start => fold.end,
+    _ => start_line,
+  }
+}
 
-      // trace!("10-current_line:{}, wrow:{}", current_line, wrow);
-      (
-        ViewportLineRange::new(start_line..current_line),
-        line_viewports,
-      )
-    }
+/// The rows count of the line when it's placed from the top of the window, i.e. at most the height
+/// of the window, and at least 1 row for the empty line. The closed fold occupies a single row,
+/// and the rest lines inside it have no rows.
+///
+/// Only the line itself is placed, i.e. it doesn't sync the whole window.
+pub fn line_rows(
+  options: &ViewportOptions,
+  buffer: &Buffer,
+  folds: &Folds,
+  actual_shape: &U16Rect,
+  line_idx: usize,
+) -> u16 {
+  match folds.closed_fold_at(line_idx) {
+    Some(fold) if line_idx == fold.start => 1,
+    Some(_) => 0,
+    None if !options.wrap => 1,
     None => {
-      // The `start_line` is outside of the buffer.
-      // trace!("11-start_line:{}", start_line);
-      (ViewportLineRange::default(), BTreeMap::new())
+      let word_break: Option<&dyn WordBreak> = if options.line_break {
+        Some(&UnicodeWords)
+      } else {
+        None
+      };
+      let layout = layout_line(
+        options,
+        buffer,
+        line_idx,
+        0,
+        actual_shape,
+        actual_shape.height(),
+        word_break,
+      );
+      std::cmp::max(layout.rows().len() as u16, 1)
     }
   }
 }
 
-fn truncate_line(line: &RopeSlice, start_column: usize, max_bytes: usize) -> String {
-  let mut builder = String::new();
-  builder.reserve(max_bytes);
-  for (i, c) in line.chars().enumerate() {
-    if i < start_column {
-      continue;
-    }
-    if builder.len() > max_bytes {
-      return builder;
-    }
-    builder.push(c);
+// The line inside a closed fold, the first line of the fold occupies a single row that maps to its
+// first char, i.e. the cursor stays at the start of the summary row. The rest lines have no rows.
+fn folded_line_viewport(fold: &Range<usize>, line_idx: usize, wrow: u16) -> LineViewport {
+  let mut rows: BTreeMap<u16, RowViewport> = BTreeMap::new();
+  if line_idx == fold.start {
+    let ch2dcols: BTreeMap<usize, (usize, usize)> = BTreeMap::from([(0, (0, 1))]);
+    rows.insert(wrow, RowViewport::new(0..1, 0..1, &ch2dcols));
   }
-  builder
+  LineViewport::new(rows, 0, 0)
 }
 
-#[allow(unused_variables)]
-// Implement [`_sync_from_top_left`] with option `wrap=true` and `line-break=true`.
-fn _sync_from_top_left_wrap_linebreak(
-  _options: &ViewportOptions,
-  buffer: BufferWk,
-  actual_shape: &U16Rect,
-  start_line: usize,
-  start_dcolumn: usize,
-) -> (ViewportLineRange, BTreeMap<usize, LineViewport>) {
-  let height = actual_shape.height();
-  let width = actual_shape.width();
-
-  // trace!(
-  //   "_collect_from_top_left_with_wrap_linebreak, actual_shape:{:?}, height/width:{:?}/{:?}",
-  //   actual_shape,
-  //   height,
-  //   width
-  // );
-
-  // Get buffer arc pointer, and lock for read.
-  let buffer = buffer.upgrade().unwrap();
-  let buffer = rlock!(buffer);
-
-  // trace!(
-  //   "buffer.get_line ({:?}):'{:?}'",
-  //   start_line,
-  //   match buffer.get_line(start_line) {
-  //     Some(line) => slice2line(&line),
-  //     None => "None".to_string(),
-  //   }
-  // );
-
-  let mut line_viewports: BTreeMap<usize, LineViewport> = BTreeMap::new();
-
-  match buffer.get_lines_at(start_line) {
-    Some(buflines) => {
-      // The `start_line` is inside the buffer.
-
-      // The first `wrow` in the window maps to the `start_line` in the buffer.
-      let mut wrow = 0;
-      let mut current_line = start_line;
-
-      for (l, line) in buflines.enumerate() {
-        // Current row goes out of viewport.
-        if wrow >= height {
-          break;
-        }
-
-        let mut rows: BTreeMap<u16, RowViewport> = BTreeMap::new();
-        let mut wcol = 0_u16;
-
-        let mut bchars = 0_usize;
-        let mut dcol = 0_usize;
-        let mut start_dcol = 0_usize;
-        let mut end_dcol = 0_usize;
-
-        let mut start_c_idx = 0_usize;
-        let mut end_c_idx = 0_usize;
-        let mut start_c_idx_init = false;
-        let mut _end_c_idx_init = false;
-
-        let mut ch2dcols: BTreeMap<usize, (usize, usize)> = BTreeMap::new();
-
-        let mut start_fills = 0_usize;
-        let mut end_fills = 0_usize;
-
-        // Chop the line into maximum chars can hold by current window, thus avoid those super
-        // long lines for iteration performance.
-        // NOTE: Use `height * width * 4` simply for a much bigger size for the total characters in
-        // a viewport.
-        let truncated_line = truncate_line(
-          &line,
-          start_dcolumn,
-          height as usize * width as usize * 2 + height as usize * 2 + 16,
-        );
-        let word_boundaries: Vec<&str> = truncated_line.split_word_bounds().collect();
-        // trace!(
-        //   "0-truncated_line: {:?}, word_boundaries: {:?}, wrow/wcol:{}/{}, dcol:{}/{}/{}, c_idx:{}/{}, fills:{}/{}",
-        //   truncated_line, word_boundaries, wrow, wcol, dcol, start_dcol, end_dcol, start_c_idx, end_c_idx, start_fills, end_fills
-        // );
-
-        for (i, wd) in word_boundaries.iter().enumerate() {
-          let (wd_chars, wd_width) = wd.chars().map(|c| (1_usize, buffer.char_width(c))).fold(
-            (0_usize, 0_usize),
-            |(init_chars, init_width), (count, width)| (init_chars + count, init_width + width),
-          );
-
-          // trace!(
-          //   "1-l:{:?}, line:'{:?}', current_line:{:?}, i:{}, wd:{:?}",
-          //   l,
-          //   slice2line(&line),
-          //   current_line,
-          //   i,
-          //   wd
-          // );
-
-          // Prefix width is still before `start_dcolumn`.
-          if dcol + wd_width < start_dcolumn {
-            dcol += wd_width;
-            bchars += wd_chars;
-            end_dcol = dcol;
-            end_c_idx = bchars;
-            // trace!(
-            //   "2-wrow/wcol:{}/{}, dcol:{}/{}/{}, bchars:{}, c_idx:{}/{}, fills:{}/{}, wd:{}/{}, start_dcolumn:{}",
-            //   wrow,
-            //   wcol,
-            //   dcol,
-            //   start_dcol,
-            //   end_dcol,
-            //   bchars,
-            //   start_c_idx,
-            //   end_c_idx,
-            //   start_fills,
-            //   end_fills,
-            //   wd_chars,
-            //   wd_width,
-            //   start_dcolumn
-            // );
-            continue;
-          }
-
-          if !start_c_idx_init {
-            start_c_idx_init = true;
-            start_dcol = dcol;
-            start_c_idx = bchars;
-            start_fills = dcol - start_dcolumn;
-            // trace!(
-            //   "3-wrow/wcol:{}/{}, dcol:{}/{}/{}, bchars:{}, c_idx:{}/{}, fills:{}/{}, wd:{}/{}",
-            //   wrow,
-            //   wcol,
-            //   dcol,
-            //   start_dcol,
-            //   end_dcol,
-            //   bchars,
-            //   start_c_idx,
-            //   end_c_idx,
-            //   start_fills,
-            //   end_fills,
-            //   wd_chars,
-            //   wd_width
-            // );
-          }
-
-          // Row column with next char will goes out of the row.
-          // i.e. there's not enough space to place this word in current row.
-          // There're two cases:
-          // 1. The word can be placed in next empty row, i.e. the word length is less or equal to
-          //    the row length of the viewport.
-          // 2. The word is too long to place in an entire row, i.e. the word length is greater
-          //    than the row length of the viewport.
-          // Anyway, we simply go to next row and force render all of the word. If the word is too
-          // long to place in an entire row, it fallbacks back to the same behavior with
-          // 'line-break' option is `false`.
-          if wcol as usize + wd_width > width as usize {
-            // trace!(
-            //   "4.1-wrow/wcol:{}/{}, dcol:{}/{}/{}, bchars:{}, c_idx:{}/{}, fills:{}/{}, wd:{}/{}, width:{}",
-            //   wrow,
-            //   wcol,
-            //   dcol,
-            //   start_dcol,
-            //   end_dcol,
-            //   bchars,
-            //   start_c_idx,
-            //   end_c_idx,
-            //   start_fills,
-            //   end_fills,
-            //   wd_chars,
-            //   wd_width,
-            //   width
-            // );
-
-            // If it happens this word starts from the beginning of the row, then we don't need to
-            // start from the next row. Because this is an empty of entire row.
-            // If this word starts in the middle of the row, then we will have to start a new row.
-            if wcol > 0 {
-              rows.insert(
-                wrow,
-                RowViewport::new(start_dcol..end_dcol, start_c_idx..end_c_idx, &ch2dcols),
-              );
-
-              // NOTE: The `end_fills` only indicates the cells at the end of the bottom row in the
-              // viewport cannot show the full unicode character for those ASCII control codes or
-              // other unicodes such as CJK languages.
-              // But for word-wrap rendering, i.e. `line-break` option is `true`, sometimes the whole
-              // word display length is out of the end of the row and it will not be displayed (and
-              // in such case, we don't set `end_fills` for it).
-              // So, here we need to detect the real end fills position for the word.
-
-              let saved_end_fills = {
-                let mut tmp_wcol = wcol;
-                for c in wd.chars() {
-                  let c_width = buffer.char_width(c);
-
-                  // Column with next char will goes out of the row.
-                  if tmp_wcol as usize + c_width > width as usize {
-                    break;
-                  }
-                  tmp_wcol += c_width as u16;
-                  // Column already meets the end of the row.
-                  if tmp_wcol >= width {
-                    break;
-                  }
-                }
-                //   trace!(
-                //   "4.2-wrow/wcol/tmp_wcol:{}/{}/{}, dcol:{}/{}/{}, bchars:{}, c_idx:{}/{}, fills:{}/{}, wd:{}/{}, width:{}",
-                //   wrow,
-                //   wcol,
-                //   tmp_wcol,
-                //   dcol,
-                //   start_dcol,
-                //   end_dcol,
-                //   bchars,
-                //   start_c_idx,
-                //   end_c_idx,
-                //   start_fills,
-                //   end_fills,
-                //   wd_chars,
-                //   wd_width,
-                //   width
-                // );
-                width - tmp_wcol
-              };
-
-              wrow += 1;
-              wcol = 0_u16;
-              start_dcol = end_dcol;
-              start_c_idx = bchars;
-              ch2dcols.clear();
-
-              if wrow >= height {
-                end_fills = saved_end_fills as usize;
-                //   trace!(
-                //   "5-wrow/wcol:{}/{}, dcol:{}/{}/{}, bchars:{}, c_idx:{}/{}, fills:{}/{}, wd:{}/{}, height:{}",
-                //   wrow,
-                //   wcol,
-                //   dcol,
-                //   start_dcol,
-                //   end_dcol,
-                //   bchars,
-                //   start_c_idx,
-                //   end_c_idx,
-                //   start_fills,
-                //   end_fills,
-                //   wd_chars,
-                //   wd_width,
-                //   height
-                // );
-                break;
-              }
-            }
-
-            for (j, c) in wd.chars().enumerate() {
-              let c_width = buffer.char_width(c);
-
-              // Column with next char will goes out of the row.
-              if wcol as usize + c_width > width as usize {
-                // trace!(
-                //   "6-wrow/wcol:{}/{}, dcol:{}/{}/{}, bchars:{}, j/c:{}/{:?}, c_idx:{}/{}, fills:{}/{}, wd:{}/{}, width:{}",
-                //   wrow,
-                //   wcol,
-                //   dcol,
-                //   start_dcol,
-                //   end_dcol,
-                //   bchars,
-                //   j,
-                //   c,
-                //   start_c_idx,
-                //   end_c_idx,
-                //   start_fills,
-                //   end_fills,
-                //   wd_chars,
-                //   wd_width,
-                //   width
-                // );
-                rows.insert(
-                  wrow,
-                  RowViewport::new(start_dcol..end_dcol, start_c_idx..end_c_idx, &ch2dcols),
-                );
-
-                let saved_end_fills = width as usize - wcol as usize;
-                if j > 0 {
-                  wrow += 1;
-                }
-                wcol = 0_u16;
-                start_dcol = end_dcol;
-                start_c_idx = bchars;
-                ch2dcols.clear();
-
-                if wrow >= height {
-                  end_fills = saved_end_fills;
-                  // trace!(
-                  //   "7-wrow/wcol:{}/{}, dcol:{}/{}/{}, bchars:{}, j/c:{}/{:?}, c_idx:{}/{}, fills:{}/{}, wd:{}/{}, height:{}",
-                  //   wrow,
-                  //   wcol,
-                  //   dcol,
-                  //   start_dcol,
-                  //   end_dcol,
-                  //   bchars,
-                  //   j,
-                  //   c,
-                  //   start_c_idx,
-                  //   end_c_idx,
-                  //   start_fills,
-                  //   end_fills,
-                  //   wd_chars,
-                  //   wd_width,
-                  //   height
-                  // );
-                  break;
-                }
-              }
-
-              let saved_c_idx = bchars;
-              let saved_start_dcol = dcol;
-
-              dcol += c_width;
-              bchars += 1;
-              end_dcol = dcol;
-              end_c_idx = bchars;
-              wcol += c_width as u16;
-
-              ch2dcols.insert(saved_c_idx, (saved_start_dcol, end_dcol));
-
-              // trace!(
-              //   "8-wrow/wcol:{}/{}, dcol:{}/{}/{}, bchars:{}, j/c:{}/{:?}, c_idx:{}/{}, fills:{}/{}, wd:{}/{}",
-              //   wrow,
-              //   wcol,
-              //   dcol,
-              //   start_dcol,
-              //   end_dcol,
-              //   bchars,
-              //   j,
-              //   c,
-              //   start_c_idx,
-              //   end_c_idx,
-              //   start_fills,
-              //   end_fills,
-              //   wd_chars,
-              //   wd_width
-              // );
-
-              // Column goes out of current row.
-              if wcol >= width {
-                // trace!(
-                //   "9-wrow/wcol:{}/{}, dcol:{}/{}/{}, bchars:{}, j/c:{}/{:?}, c_idx:{}/{}, fills:{}/{}, wd:{}/{}, width:{}",
-                //   wrow,
-                //   wcol,
-                //   dcol,
-                //   start_dcol,
-                //   end_dcol,
-                //   bchars,
-                //   j,
-                //   c,
-                //   start_c_idx,
-                //   end_c_idx,
-                //   start_fills,
-                //   end_fills,
-                //   wd_chars,
-                //   wd_width,
-                //   width
-                // );
-                rows.insert(
-                  wrow,
-                  RowViewport::new(start_dcol..end_dcol, start_c_idx..end_c_idx, &ch2dcols),
-                );
-                assert_eq!(wcol, width);
-                wrow += 1;
-                wcol = 0_u16;
-                start_dcol = end_dcol;
-                start_c_idx = end_c_idx;
-                ch2dcols.clear();
-
-                if wrow >= height {
-                  // trace!(
-                  //   "10-wrow/wcol:{}/{}, dcol:{}/{}/{}, bchars:{}, j/c:{}/{:?}, c_idx:{}/{}, fills:{}/{}, wd:{}/{}, height:{}",
-                  //   wrow,
-                  //   wcol,
-                  //   dcol,
-                  //   start_dcol,
-                  //   end_dcol,
-                  //   bchars,
-                  //   j,
-                  //   c,
-                  //   start_c_idx,
-                  //   end_c_idx,
-                  //   start_fills,
-                  //   end_fills,
-                  //   wd_chars,
-                  //   wd_width,
-                  //   height
-                  // );
-                  break;
-                }
-              }
-            }
-          } else {
-            // Enough space to place this word in current row
-            let saved_c_idx = bchars;
-            let saved_start_dcol = dcol;
-
-            dcol += wd_width;
-            bchars += wd_chars;
-            end_dcol = dcol;
-            end_c_idx = bchars;
-            wcol += wd_width as u16;
-
-            let mut tmp_start_dcol = saved_start_dcol;
-            for (k, c) in wd.chars().enumerate() {
-              let c_width = buffer.char_width(c);
-              let tmp_end_dcol = tmp_start_dcol + c_width;
-              ch2dcols.insert(saved_c_idx + k, (tmp_start_dcol, tmp_end_dcol));
-              tmp_start_dcol = tmp_end_dcol;
-            }
-          }
-
-          // trace!(
-          //   "9-wrow/wcol:{}/{}, dcol:{}/{}/{}, bchars:{}, c_idx:{}/{}, fills:{}/{}, wd:{}/{}",
-          //   wrow,
-          //   wcol,
-          //   dcol,
-          //   start_dcol,
-          //   end_dcol,
-          //   bchars,
-          //   start_c_idx,
-          //   end_c_idx,
-          //   start_fills,
-          //   end_fills,
-          //   wd_chars,
-          //   wd_width
-          // );
-
-          // End of the line.
-          if i + 1 == word_boundaries.len() {
-            // trace!(
-            //   "10-wrow/wcol:{}/{}, dcol:{}/{}/{}, bchars:{}, c_idx:{}/{}, fills:{}/{}, wd:{}/{}",
-            //   wrow,
-            //   wcol,
-            //   dcol,
-            //   start_dcol,
-            //   end_dcol,
-            //   bchars,
-            //   start_c_idx,
-            //   end_c_idx,
-            //   start_fills,
-            //   end_fills,
-            //   wd_chars,
-            //   wd_width
-            // );
-            rows.insert(
-              wrow,
-              RowViewport::new(start_dcol..end_dcol, start_c_idx..end_c_idx, &ch2dcols),
-            );
-            break;
-          }
-
-          // Column goes out of current row.
-          if wcol >= width {
-            // trace!(
-            //   "11-wrow/wcol:{}/{}, dcol:{}/{}/{}, bchars:{}, c_idx:{}/{}, fills:{}/{}, wd:{}/{}, width:{}",
-            //   wrow,
-            //   wcol,
-            //   dcol,
-            //   start_dcol,
-            //   end_dcol,
-            //   bchars,
-            //   start_c_idx,
-            //   end_c_idx,
-            //   start_fills,
-            //   end_fills,
-            //   wd_chars,
-            //   wd_width,
-            //   width
-            // );
-            rows.insert(
-              wrow,
-              RowViewport::new(start_dcol..end_dcol, start_c_idx..end_c_idx, &ch2dcols),
-            );
-            assert_eq!(wcol, width);
-            wrow += 1;
-            wcol = 0_u16;
-            start_dcol = end_dcol;
-            start_c_idx = end_c_idx;
-            ch2dcols.clear();
-
-            if wrow >= height {
-              // trace!(
-              //   "12-wrow/wcol:{}/{}, dcol:{}/{}/{}, bchars:{}, c_idx:{}/{}, fills:{}/{}, wd:{}/{}, height:{}",
-              //   wrow,
-              //   wcol,
-              //   dcol,
-              //   start_dcol,
-              //   end_dcol,
-              //   bchars,
-              //   start_c_idx,
-              //   end_c_idx,
-              //   start_fills,
-              //   end_fills,
-              //   wd_chars,
-              //   wd_width,
-              //   height
-              // );
-              break;
-            }
-          }
-        }
-
-        line_viewports.insert(
-          current_line,
-          LineViewport::new(rows, start_fills, end_fills),
-        );
-        // trace!(
-        //   "13-wrow/wcol:{}/{}, dcol:{}/{}/{}, bchars:{}, c_idx:{}/{}, fills:{}/{}",
-        //   wrow,
-        //   wcol,
-        //   dcol,
-        //   start_dcol,
-        //   end_dcol,
-        //   bchars,
-        //   start_c_idx,
-        //   end_c_idx,
-        //   start_fills,
-        //   end_fills
-        // );
-        current_line += 1;
-        wrow += 1;
-      }
-
-      // trace!("14-wrow:{}, current_line:{}", wrow, current_line);
-      (
-        ViewportLineRange::new(start_line..current_line),
-        line_viewports,
-      )
-    }
-    None => {
-      // The `start_line` is outside of the buffer.
-      // trace!("15-start_line:{}", start_line);
-      (ViewportLineRange::default(), BTreeMap::new())
-    }
+/// The display width of the 'showbreak' rendered at the start of the continuation rows, it's
+/// ignored if the window is too narrow to put anything after it.
+pub fn show_break_width(options: &ViewportOptions, width: u16) -> u16 {
+  if options.show_break_width < width as usize {
+    options.show_break_width as u16
+  } else {
+    0_u16
   }
 }
 
@@ -1167,7 +228,9 @@ fn _sync_from_top_left_wrap_linebreak(
 mod tests {
   use super::*;
 
+  use crate::buf::truncated::truncate_line_count;
   use crate::test::log::init as test_log_init;
+  use crate::ui::widget::window::viewport::layout::layout_char_count;
   use std::ops::Range;
   use tracing::info;
 
@@ -1182,4 +245,123 @@ mod tests {
     assert!(r1.start == 0);
     assert!(r1.end == 0);
   }
+
+  #[test]
+  fn cached_truncate_line1() {
+    use crate::cart::U16Rect;
+    use crate::test::buf::make_buffer_from_lines;
+    use crate::wlock;
+    use std::sync::Arc;
+
+    test_log_init();
+
+    let buffer = make_buffer_from_lines(vec![
+      "Hello, RSVIM!\n",
+      "This is a quite simple and small test lines.\n",
+      "But still it contains several things we want to test:\n",
+    ]);
+    let options = ViewportOptions {
+      wrap: true,
+      line_break: true,
+      show_break_width: 0,
+      scroll: 0,
+      scroll_off: 0,
+    };
+    let shape = U16Rect::new((0, 0), (10, 10));
+    let folds = Folds::new();
+    let render = || {
+      let (line_range, line_viewports) =
+        from_top_left(&options, Arc::downgrade(&buffer), &folds, &shape, 0, 0);
+      format!("{:?}{:?}", line_range, line_viewports)
+    };
+
+    let count = truncate_line_count();
+    let first = render();
+    assert_eq!(truncate_line_count() - count, 3);
+
+    // The second identical render reuses the truncated lines.
+    let count = truncate_line_count();
+    let second = render();
+    assert_eq!(truncate_line_count() - count, 0);
+    assert_eq!(first, second);
+
+    // The buffer is changed, the truncated lines are materialized again.
+    wlock!(buffer).insert(0, "A").unwrap();
+    let count = truncate_line_count();
+    let third = render();
+    assert_eq!(truncate_line_count() - count, 3);
+    assert_ne!(first, third);
+  }
+
+  #[test]
+  fn nowrap_long_line1() {
+    use crate::cart::U16Rect;
+    use crate::test::buf::make_buffer_from_lines;
+    use std::sync::Arc;
+
+    test_log_init();
+
+    let line1 = format!("{}\n", "0123456789".repeat(5000));
+    let line2 = format!("{}\n", "好".repeat(25000));
+    let buffer = make_buffer_from_lines(vec![line1.as_str(), line2.as_str()]);
+    let options = ViewportOptions {
+      wrap: false,
+      line_break: false,
+      show_break_width: 0,
+      scroll: 0,
+      scroll_off: 0,
+    };
+    let shape = U16Rect::new((0, 0), (10, 2));
+    let folds = Folds::new();
+    let render = |start_dcolumn: usize| {
+      from_top_left(
+        &options,
+        Arc::downgrade(&buffer),
+        &folds,
+        &shape,
+        0,
+        start_dcolumn,
+      )
+      .1
+    };
+    let row = |lines: &BTreeMap<usize, LineViewport>, line_idx: usize| {
+      let line = lines.get(&line_idx).unwrap();
+      let row = line.rows().values().next().unwrap();
+      (
+        row.start_char_idx()..row.end_char_idx(),
+        row.start_dcol_idx()..row.end_dcol_idx(),
+        line.start_filled_columns(),
+        line.end_filled_columns(),
+      )
+    };
+    // The chars visited for a line, i.e. the visible chars.
+    let bound = 2 * (10 + 1);
+
+    // The first render builds the width indexes of the long lines in the buffer.
+    let count = layout_char_count();
+    let lines = render(40003);
+    assert!(layout_char_count() - count <= bound);
+    let windexes = (
+      rlock!(buffer).windex(0).unwrap(),
+      rlock!(buffer).windex(1).unwrap(),
+    );
+    assert_eq!(row(&lines, 0), (40003..40013, 40003..40013, 0, 0));
+    // The wide char at the start display column is not fully visible, its cell is filled.
+    assert_eq!(row(&lines, 1), (20002..20006, 40004..40012, 1, 1));
+
+    // The following renders reuse the width indexes.
+    let count = layout_char_count();
+    let lines = render(40002);
+    assert!(layout_char_count() - count <= bound);
+    assert!(Arc::ptr_eq(&rlock!(buffer).windex(0).unwrap(), &windexes.0));
+    assert!(Arc::ptr_eq(&rlock!(buffer).windex(1).unwrap(), &windexes.1));
+    assert_eq!(row(&lines, 0), (40002..40012, 40002..40012, 0, 0));
+    assert_eq!(row(&lines, 1), (20001..20006, 40002..40012, 0, 0));
+
+    let count = layout_char_count();
+    let lines = render(49995);
+    assert!(layout_char_count() - count <= bound);
+    assert_eq!(row(&lines, 0), (49995..50001, 49995..50000, 0, 0));
+    assert_eq!(row(&lines, 1), (24998..25001, 49996..50000, 1, 0));
+  }
 }
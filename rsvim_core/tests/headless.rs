@@ -0,0 +1,64 @@
+//! Embed the editor with the prelude, i.e. open a file, type the keys, render the frame and check
+//! the screen. It's the canonical embedding example, see `rsvim_core::headless`.
+
+use rsvim_core::prelude::*;
+
+// Dump the rows of the frame, each row is the symbols of its cells.
+fn rows(frame: &Frame) -> Vec<String> {
+  let size = frame.size();
+  (0..size.height())
+    .map(|y| {
+      (0..size.width())
+        .map(|x| frame.get_cell(U16Pos::new(x, y)).symbol().to_string())
+        .collect()
+    })
+    .collect()
+}
+
+fn send_keys(editor: &mut Editor, keys: &str) {
+  for c in keys.chars() {
+    let key = match c {
+      '\x1b' => KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE),
+      c => KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE),
+    };
+    editor.send_key(key);
+  }
+}
+
+#[test]
+fn open_type_render1() {
+  let tmpdir = assert_fs::TempDir::new().unwrap();
+  let path = tmpdir.path().join("hello.txt");
+  std::fs::write(&path, "hello\nworld\n").unwrap();
+
+  let mut editor = Editor::builder().size(16, 4).build().unwrap();
+  let buf_id = editor.open(&path).unwrap();
+  assert_eq!(
+    rows(&editor.render()),
+    vec![
+      "hello           ",
+      "world           ",
+      "~               ",
+      "                "
+    ]
+  );
+
+  // Append to the second line, then go back to normal mode.
+  send_keys(&mut editor, "jA, rsvim\x1b");
+  assert_eq!(
+    rows(&editor.render()),
+    vec![
+      "hello           ",
+      "world, rsvim    ",
+      "~               ",
+      "                "
+    ]
+  );
+  let buffers = editor.buffers();
+  let buffer = buffers.read().get(&buf_id).unwrap().clone();
+  assert_eq!(
+    buffer.read().get_line(1).unwrap().to_string(),
+    "world, rsvim\n"
+  );
+  assert!(buffer.read().is_modified());
+}
@@ -0,0 +1,37 @@
+//! Initialize the logging and adjust its level at runtime. It sets the global subscriber, thus it
+//! runs in its own test process.
+
+use rsvim_core::cli::CliOpt;
+use rsvim_core::log;
+
+use clap::Parser;
+
+#[test]
+fn init_set_level1() {
+  assert!(log::set_level("debug").is_err());
+
+  let tmpdir = assert_fs::TempDir::new().unwrap();
+  let log_file = tmpdir.path().join(log::DEFAULT_LOG_FILE_NAME);
+  let cli_opt = CliOpt::try_parse_from([
+    "rsvim",
+    "--log-level",
+    "info",
+    "--log-file",
+    log_file.to_str().unwrap(),
+  ])
+  .unwrap();
+  log::init(&cli_opt).unwrap();
+
+  tracing::info!("init_set_level1-info-1");
+  tracing::debug!("init_set_level1-debug-1");
+  log::set_level("debug").unwrap();
+  tracing::info!("init_set_level1-info-2");
+  tracing::debug!("init_set_level1-debug-2");
+  assert!(log::set_level("=invalid=").is_err());
+
+  let contents = std::fs::read_to_string(&log_file).unwrap();
+  assert!(contents.contains("init_set_level1-info-1"));
+  assert!(!contents.contains("init_set_level1-debug-1"));
+  assert!(contents.contains("init_set_level1-info-2"));
+  assert!(contents.contains("init_set_level1-debug-2"));
+}